@@ -13,6 +13,7 @@ use thiserror::Error;
 use time::OffsetDateTime;
 use tokio::{fs, io};
 
+use crate::hoard::iter::ScanOptions;
 use crate::hoard::{Direction, Hoard};
 use crate::newtypes::{HoardName, NonEmptyPileName};
 use crate::paths::{HoardPath, RelativePath, SystemPath};
@@ -31,7 +32,7 @@ pub enum Error {
     #[error("an I/O error occurred: {0}")]
     IO(#[from] io::Error),
     /// Unexpected differences in hoard paths. Operation must be forced to continue.
-    #[error("paths used in current hoard operation do not match previous run")]
+    #[error("paths used in current hoard operation do not match previous run -- check the configuration or force the operation with --force")]
     HoardPathsMismatch,
     /// Expected the [`LastPaths`] to have at least one entry in it.
     #[error("LastPaths record has no entries in it!")]
@@ -77,6 +78,7 @@ impl Checker for LastPaths {
         name: &HoardName,
         hoard: &Hoard,
         _direction: Direction,
+        _options: &ScanOptions,
     ) -> Result<Self, Self::Error> {
         Ok(LastPaths({
             let mut map = HashMap::new();
@@ -626,6 +628,8 @@ mod tests {
         let anon_hoard = Hoard::Anonymous(Pile {
             config: PileConfig::default(),
             path: Some(system_path!("/anon/path")),
+            extra_paths: Vec::new(),
+            glob: None,
         });
 
         let named_hoard = Hoard::Named(MultipleEntries {
@@ -633,14 +637,20 @@ mod tests {
                 "first".parse().unwrap() => Pile {
                     config: PileConfig::default(),
                     path: Some(system_path!("/first/path")),
+                    extra_paths: Vec::new(),
+                    glob: None,
                 },
                 "missing".parse().unwrap() => Pile {
                     config: PileConfig::default(),
                     path: None,
+                    extra_paths: Vec::new(),
+                    glob: None,
                 },
                 "second".parse().unwrap() => Pile {
                     config: PileConfig::default(),
-                    path: Some(system_path!("/second/path"))
+                    path: Some(system_path!("/second/path")),
+                    extra_paths: Vec::new(),
+                    glob: None,
                 }
             },
         });
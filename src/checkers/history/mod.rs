@@ -72,6 +72,40 @@ async fn get_history_dirs_not_for_id(id: &Uuid) -> Result<Vec<HoardPath>, io::Er
         })
 }
 
+/// Returns the history directories for all systems, paired with each system's UUID.
+#[tracing::instrument(level = "debug")]
+pub(crate) async fn get_all_history_dirs() -> Result<Vec<(Uuid, HoardPath)>, io::Error> {
+    let root = get_history_root_dir();
+    if !root.exists() {
+        tracing::trace!("history root dir does not exist");
+        return Ok(Vec::new());
+    }
+
+    fs::read_dir(&root)
+        .await
+        .map(ReadDirStream::new)
+        .tap_err(|error| {
+            tracing::error!(%error, "failed to list items in history root directory {}", root.display());
+        })?
+        .try_filter_map(|entry| async move {
+            let path = entry.path();
+            let id = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_str| Uuid::parse_str(file_str).ok());
+            Ok(id.filter(|_| path.is_dir()).map(|id| {
+                let path = HoardPath::try_from(path)
+                    .expect("dir entries based in a HoardPath are always valid HoardPaths");
+                (id, path)
+            }))
+        })
+        .try_collect()
+        .await
+        .tap_err(|error| {
+            tracing::error!(%error, "failed to read metadata for system history directory");
+        })
+}
+
 /// Get this machine's unique UUID, creating if necessary.
 ///
 /// The UUID can be found in a file called "uuid" in the `hoard`
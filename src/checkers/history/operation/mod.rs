@@ -19,21 +19,29 @@ use time::OffsetDateTime;
 use tokio::{fs, io};
 use tokio_stream::wrappers::ReadDirStream;
 
-pub(crate) use util::cleanup_operations;
+pub(crate) use util::{
+    cleanup_operations, export_operations, import_operations, prune_operations,
+    remove_empty_history_dirs, HistoryExport,
+};
 
 use crate::checkers::history::operation::util::TIME_FORMAT;
 use crate::checkers::history::operation::v1::OperationV1;
 use crate::checkers::history::operation::v2::OperationV2;
+use crate::checkers::history::operation::v3::OperationV3;
 use crate::checkers::Checker;
 use crate::checksum::Checksum;
+use crate::hoard::iter::ScanOptions;
+use crate::hoard::pile_config::Permissions;
 use crate::hoard::{Direction, Hoard};
 use crate::hoard_item::{CachedHoardItem, HoardItem};
 use crate::newtypes::{HoardName, PileName};
 use crate::paths::{HoardPath, RelativePath};
 
+mod timestamp;
 pub mod util;
 pub mod v1;
 pub mod v2;
+pub mod v3;
 
 /// Errors that may occur while working with an [`Operation`].
 #[derive(Debug, Error)]
@@ -59,13 +67,42 @@ pub enum Error {
     /// An error occurred in the file iterator.
     #[error("error while iterating files: {0}")]
     Iterator(#[from] crate::hoard::iter::Error),
+    /// An imported operation log has a file name that does not match the log file format.
+    #[error("invalid operation log file name: {0}")]
+    InvalidLogName(String),
     /// Found a mix of empty/anonymous and actual pile names.
     ///
     /// This shouldn't happen in practice, but returning an error is preferred to panicking.
     #[error("found mixed empty/anonymous and non-empty pile names")]
     MixedPileNames,
+    /// The operation log was written in a format newer than this version of Hoard understands.
+    #[error("log written by a newer hoard version (format version {0}), please upgrade")]
+    NewerVersion(u32),
 }
 
+/// The newest operation log format version this version of Hoard can read.
+///
+/// Versions 1 and 2 are told apart by their shape. Version 3 and any later version must record
+/// its number in a top-level `version` field so that older versions of Hoard can recognize it.
+pub const LATEST_LOG_VERSION: u32 = 3;
+
+/// Only the format version of an operation log, if it records one.
+#[derive(Deserialize)]
+struct LogVersion {
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+impl LogVersion {
+    /// Returns the format version of the log if it is newer than [`LATEST_LOG_VERSION`].
+    fn newer(self) -> Option<u32> {
+        self.version.filter(|version| *version > LATEST_LOG_VERSION)
+    }
+}
+
+/// The default number of seconds an operation log may appear to be ahead of the local clock.
+pub const DEFAULT_MAX_CLOCK_SKEW: u64 = 300;
+
 /// Indicates what operation is/was/should be performed on the contained [`HoardItem`]
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[allow(clippy::module_name_repetitions)]
@@ -97,6 +134,17 @@ impl ItemOperation<CachedHoardItem> {
 }
 
 impl<T> ItemOperation<T> {
+    /// Returns a reference to the contained item.
+    pub fn as_inner(&self) -> &T {
+        match self {
+            ItemOperation::Create(item)
+            | ItemOperation::Modify(item)
+            | ItemOperation::Delete(item)
+            | ItemOperation::Nothing(item)
+            | ItemOperation::DoesNotExist(item) => item,
+        }
+    }
+
     /// Converts into the contained item.
     pub fn into_inner(self) -> T {
         match self {
@@ -112,7 +160,7 @@ impl<T> ItemOperation<T> {
 /// Enum representing types of operations
 ///
 /// Does not include no operation.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(clippy::module_name_repetitions)]
 pub enum OperationType {
     /// Created a (system) file.
@@ -134,12 +182,33 @@ pub struct OperationFileInfo {
     checksum: Option<Checksum>,
 }
 
+impl OperationFileInfo {
+    /// The name of the pile this file belongs to.
+    #[must_use]
+    pub fn pile_name(&self) -> &PileName {
+        &self.pile_name
+    }
+
+    /// The path of this file relative to its pile.
+    #[must_use]
+    pub fn relative_path(&self) -> &RelativePath {
+        &self.relative_path
+    }
+
+    /// The checksum of this file, or `None` if the file was deleted.
+    #[must_use]
+    pub fn checksum(&self) -> Option<&Checksum> {
+        self.checksum.as_ref()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(untagged)]
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::large_enum_variant)]
 enum OperationVersion {
     V1(OperationV1),
     V2(OperationV2),
+    V3(OperationV3),
 }
 
 impl<'de> Deserialize<'de> for OperationVersion {
@@ -156,6 +225,27 @@ impl<'de> Deserialize<'de> for OperationVersion {
                 }
             };
 
+        if let Some(version) = <LogVersion as Deserialize>::deserialize(
+            serde::__private::de::ContentRefDeserializer::<D::Error>::new(&content),
+        )
+        .ok()
+        .and_then(LogVersion::newer)
+        {
+            return crate::create_log_error(D::Error::custom(Error::NewerVersion(version)));
+        }
+
+        match Result::map(
+            <OperationV3 as Deserialize>::deserialize(
+                serde::__private::de::ContentRefDeserializer::<D::Error>::new(&content),
+            ),
+            OperationVersion::V3,
+        ) {
+            Ok(ok) => return Ok(ok),
+            Err(err) => {
+                tracing::warn!("operation does not match V3: {}", err);
+            }
+        }
+
         match Result::map(
             <OperationV2 as Deserialize>::deserialize(
                 serde::__private::de::ContentRefDeserializer::<D::Error>::new(&content),
@@ -205,6 +295,14 @@ pub trait OperationImpl {
     /// The checksum associated with the given file, or `None` if the file does not exist or was
     /// deleted.
     fn checksum_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<Checksum>;
+    /// The permissions recorded for the given file, or `None` if none were recorded.
+    fn permissions_for(&self, pile_name: &PileName, rel_path: &RelativePath)
+        -> Option<Permissions>;
+    /// The modification time recorded for the given file, or `None` if none was recorded.
+    fn mtime_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<OffsetDateTime>;
+    /// Whether the given file was moved into the hoard with `--move`, so that it is no longer
+    /// expected to exist on the system.
+    fn is_archived(&self, pile_name: &PileName, rel_path: &RelativePath) -> bool;
     /// An iterator over all files that exist within this operation log, not including any that
     /// were deleted.
     fn all_files_with_checksums<'a>(&'a self) -> Box<dyn Iterator<Item = OperationFileInfo> + 'a>;
@@ -244,6 +342,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(one) => one.direction(),
             OperationVersion::V2(two) => two.direction(),
+            OperationVersion::V3(three) => three.direction(),
         }
     }
 
@@ -256,6 +355,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(one) => one.contains_file(pile_name, rel_path, only_modified),
             OperationVersion::V2(two) => two.contains_file(pile_name, rel_path, only_modified),
+            OperationVersion::V3(three) => three.contains_file(pile_name, rel_path, only_modified),
         }
     }
 
@@ -263,6 +363,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(one) => one.timestamp(),
             OperationVersion::V2(two) => two.timestamp(),
+            OperationVersion::V3(three) => three.timestamp(),
         }
     }
 
@@ -270,6 +371,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(one) => one.hoard_name(),
             OperationVersion::V2(two) => two.hoard_name(),
+            OperationVersion::V3(three) => three.hoard_name(),
         }
     }
 
@@ -277,6 +379,35 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(one) => one.checksum_for(pile_name, rel_path),
             OperationVersion::V2(two) => two.checksum_for(pile_name, rel_path),
+            OperationVersion::V3(three) => three.checksum_for(pile_name, rel_path),
+        }
+    }
+
+    fn permissions_for(
+        &self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+    ) -> Option<Permissions> {
+        match &self {
+            OperationVersion::V1(one) => one.permissions_for(pile_name, rel_path),
+            OperationVersion::V2(two) => two.permissions_for(pile_name, rel_path),
+            OperationVersion::V3(three) => three.permissions_for(pile_name, rel_path),
+        }
+    }
+
+    fn mtime_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<OffsetDateTime> {
+        match &self {
+            OperationVersion::V1(one) => one.mtime_for(pile_name, rel_path),
+            OperationVersion::V2(two) => two.mtime_for(pile_name, rel_path),
+            OperationVersion::V3(three) => three.mtime_for(pile_name, rel_path),
+        }
+    }
+
+    fn is_archived(&self, pile_name: &PileName, rel_path: &RelativePath) -> bool {
+        match &self {
+            OperationVersion::V1(one) => one.is_archived(pile_name, rel_path),
+            OperationVersion::V2(two) => two.is_archived(pile_name, rel_path),
+            OperationVersion::V3(three) => three.is_archived(pile_name, rel_path),
         }
     }
 
@@ -284,6 +415,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(one) => one.all_files_with_checksums(),
             OperationVersion::V2(two) => two.all_files_with_checksums(),
+            OperationVersion::V3(three) => three.all_files_with_checksums(),
         }
     }
 
@@ -295,6 +427,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(v1) => v1.hoard_operations_iter(hoard_root, hoard),
             OperationVersion::V2(v2) => v2.hoard_operations_iter(hoard_root, hoard),
+            OperationVersion::V3(three) => three.hoard_operations_iter(hoard_root, hoard),
         }
     }
 
@@ -306,6 +439,7 @@ impl OperationImpl for OperationVersion {
         match &self {
             OperationVersion::V1(v1) => v1.file_operation(pile_name, rel_path),
             OperationVersion::V2(v2) => v2.file_operation(pile_name, rel_path),
+            OperationVersion::V3(three) => three.file_operation(pile_name, rel_path),
         }
     }
 }
@@ -343,6 +477,22 @@ impl OperationImpl for Operation {
         self.0.checksum_for(pile_name, rel_path)
     }
 
+    fn permissions_for(
+        &self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+    ) -> Option<Permissions> {
+        self.0.permissions_for(pile_name, rel_path)
+    }
+
+    fn mtime_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<OffsetDateTime> {
+        self.0.mtime_for(pile_name, rel_path)
+    }
+
+    fn is_archived(&self, pile_name: &PileName, rel_path: &RelativePath) -> bool {
+        self.0.is_archived(pile_name, rel_path)
+    }
+
     fn all_files_with_checksums<'a>(&'a self) -> Box<dyn Iterator<Item = OperationFileInfo> + 'a> {
         self.0.all_files_with_checksums()
     }
@@ -370,11 +520,31 @@ impl Operation {
         name: &HoardName,
         hoard: &Hoard,
         direction: Direction,
+        options: &ScanOptions,
     ) -> Result<Self, Error> {
-        OperationV2::new(hoards_root, name, hoard, direction)
-            .await
-            .map(OperationVersion::V2)
-            .map(Self)
+        // Boxed to keep the futures of callers shallow enough for the compiler to compute
+        // their layouts.
+        Box::pin(OperationV3::new(
+            hoards_root,
+            name,
+            hoard,
+            direction,
+            options,
+        ))
+        .await
+        .map(OperationVersion::V3)
+        .map(Self)
+    }
+
+    /// Returns how far ahead of the local clock this `Operation` was logged, if that is more than
+    /// `max_skew` seconds.
+    ///
+    /// Such a log was most likely written by a system whose clock is ahead of this one, so its
+    /// timestamp cannot be trusted to decide which system last touched a file.
+    pub(crate) fn clock_skew(&self, max_skew: u64) -> Option<time::Duration> {
+        let max_skew = i64::try_from(max_skew).unwrap_or(i64::MAX);
+        let ahead = self.timestamp() - OffsetDateTime::now_utc();
+        (ahead.whole_seconds() > max_skew).then_some(ahead)
     }
 
     /// Return an error if this `Operation` is not the most recent schema version.
@@ -383,7 +553,7 @@ impl Operation {
     ///
     /// [`Error::UpgradeRequired`] if this `Operation` is not the most recent schema.
     pub fn require_latest_version(&self) -> Result<(), Error> {
-        if let Self(OperationVersion::V2(_)) = self {
+        if let Self(OperationVersion::V3(_)) = self {
             Ok(())
         } else {
             crate::create_log_error(Error::UpgradeRequired)
@@ -408,17 +578,73 @@ impl Operation {
         self.require_latest_version().map(|()| self)
     }
 
+    /// Like [`Operation::into_latest_version`], but only logs a warning for older schema
+    /// versions if `older_format_ok` is `true`.
+    ///
+    /// This should only be used by commands that do not write new operation logs.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::UpgradeRequired`] if this `Operation` is not the most recent schema and
+    /// `older_format_ok` is `false`.
+    pub fn into_readable_version(self, older_format_ok: bool) -> Result<Self, Error> {
+        if older_format_ok && self.require_latest_version().is_err() {
+            tracing::warn!(
+                "operation log format for hoard {} is outdated -- consider running `hoard upgrade`",
+                self.hoard_name()
+            );
+            return Ok(self);
+        }
+
+        self.into_latest_version()
+    }
+
+    /// Reads the operation log at `path`.
+    ///
+    /// Returns `None`, with a warning, if the file is empty or its JSON is cut short or otherwise
+    /// malformed, e.g. because Hoard was stopped while writing it or it is still being synced from
+    /// another system, or if it was written by a newer version of Hoard. Such a log is skipped
+    /// rather than failing the whole command.
     #[tracing::instrument(name = "operation_from_file")]
-    async fn from_file(path: &Path) -> Result<Self, Error> {
+    pub(crate) async fn from_file(path: &Path) -> Result<Option<Self>, Error> {
+        match Self::read_file(path).await {
+            Err(Error::NewerVersion(version)) => {
+                tracing::warn!(
+                    "skipping operation log {}: log written by a newer hoard version (format version {}), please upgrade",
+                    path.display(),
+                    version
+                );
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+
+    /// Like [`Operation::from_file`], but a log written by a newer version of Hoard is returned as
+    /// [`Error::NewerVersion`] instead of being skipped.
+    pub(crate) async fn read_file(path: &Path) -> Result<Option<Self>, Error> {
         tracing::trace!(path=%path.display(), "loading operation log from path");
         let content = fs::read(path).await.tap_err(|error| {
             tracing::error!(%error, "failed to open file at {}", path.display());
         })?;
-        serde_json::from_slice(&content)
-            .map_err(Error::from)
-            .tap_err(|error| {
+        // Check the version first so that a newer log is not mistaken for a malformed one.
+        if let Some(version) = serde_json::from_slice::<LogVersion>(&content)
+            .ok()
+            .and_then(LogVersion::newer)
+        {
+            return Err(Error::NewerVersion(version));
+        }
+        match serde_json::from_slice(&content) {
+            Ok(operation) => Ok(Some(operation)),
+            Err(error) if error.is_eof() || error.is_syntax() => {
+                tracing::warn!(%error, "skipping incomplete operation log {}", path.display());
+                Ok(None)
+            }
+            Err(error) => {
                 tracing::error!(%error, "failed to parse JSON from {}", path.display());
-            })
+                Err(error.into())
+            }
+        }
     }
 
     /// Returns the most recent `Operation`
@@ -461,26 +687,30 @@ impl Operation {
     ) -> Self {
         // Conversion always modifies file_checksums and file_set with the contents of the Operation.
         let latest = match self.0 {
-            OperationVersion::V1(one) => OperationV2::from_v1(file_checksums, file_set, one),
-            OperationVersion::V2(two) => {
-                let mut new_file_set = HashSet::new();
-                for file_info in two.all_files_with_checksums() {
-                    let OperationFileInfo {
-                        pile_name,
-                        relative_path,
-                        checksum,
-                        ..
-                    } = file_info;
-                    let pile_file = (pile_name, relative_path);
-                    new_file_set.insert(pile_file.clone());
-                    file_checksums.insert(pile_file, checksum);
-                }
-                *file_set = new_file_set;
-                two
+            OperationVersion::V1(one) => {
+                return Self(OperationVersion::V3(OperationV3::from(
+                    OperationV2::from_v1(file_checksums, file_set, one),
+                )));
             }
+            OperationVersion::V2(two) => OperationV3::from(two),
+            OperationVersion::V3(three) => three,
         };
 
-        Self(OperationVersion::V2(latest))
+        let mut new_file_set = HashSet::new();
+        for file_info in latest.all_files_with_checksums() {
+            let OperationFileInfo {
+                pile_name,
+                relative_path,
+                checksum,
+                ..
+            } = file_info;
+            let pile_file = (pile_name, relative_path);
+            new_file_set.insert(pile_file.clone());
+            file_checksums.insert(pile_file, checksum);
+        }
+        *file_set = new_file_set;
+
+        Self(OperationVersion::V3(latest))
     }
 
     /// Returns the latest operation for the given hoard from a system history root directory.
@@ -506,7 +736,7 @@ impl Operation {
                 let path = item.path();
                 Ok(util::file_is_log(&path).then_some(path))
             })
-            .and_then(|path| async move { Self::from_file(&path).await })
+            .try_filter_map(|path| async move { Self::from_file(&path).await })
             .try_filter_map(|operation| async {
                 (!backups_only || operation.direction() == Direction::Backup)
                     .then_some(Ok(operation))
@@ -525,6 +755,110 @@ impl Operation {
             .await
     }
 
+    /// Writes this operation to the given file, overwriting any existing content.
+    ///
+    /// # Errors
+    ///
+    /// Any errors that occur while serializing or writing the file.
+    pub(crate) async fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let content = serde_json::to_vec(self).tap_err(|error| {
+            tracing::error!(%error, "failed to serialize operation log as JSON");
+        })?;
+        util::write_log_file(path, &content, true)
+            .await
+            .tap_err(|error| {
+                tracing::error!(%error, "failed to write operation log file to {}", path.display());
+            })?;
+        Ok(())
+    }
+
+    /// Returns the latest operation recorded on this machine for `hoard` and the path of its
+    /// log file.
+    ///
+    /// # Errors
+    ///
+    /// - Any errors that occur while reading from the filesystem
+    /// - Any parsing errors from `serde_json` when parsing the file
+    pub(crate) async fn latest_local_with_path(
+        hoard: &HoardName,
+    ) -> Result<Option<(PathBuf, Self)>, Error> {
+        let uuid = super::get_or_generate_uuid().await?;
+        let dir = super::get_history_dir_for_id(uuid).join(&RelativePath::from(hoard));
+        util::latest_log_in_dir(&dir).await
+    }
+
+    /// Replaces the recorded checksum of a file in this operation.
+    ///
+    /// Returns `false` if the file has no recorded checksum.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::UpgradeRequired`] if this `Operation` is not the most recent schema.
+    pub(crate) fn replace_checksum(
+        &mut self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+        checksum: Checksum,
+    ) -> Result<bool, Error> {
+        match &mut self.0 {
+            OperationVersion::V1(_) | OperationVersion::V2(_) => {
+                crate::create_log_error(Error::UpgradeRequired)
+            }
+            OperationVersion::V3(three) => {
+                Ok(three.replace_checksum(pile_name, rel_path, checksum))
+            }
+        }
+    }
+
+    /// Records that a file was moved into the hoard with `--move`, so that later operations do
+    /// not treat it as deleted from the system.
+    ///
+    /// Returns `false` if the file has no recorded checksum.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::UpgradeRequired`] if this `Operation` is not the most recent schema.
+    pub(crate) fn mark_archived(
+        &mut self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+    ) -> Result<bool, Error> {
+        match &mut self.0 {
+            OperationVersion::V1(_) | OperationVersion::V2(_) => {
+                crate::create_log_error(Error::UpgradeRequired)
+            }
+            OperationVersion::V3(three) => Ok(three.mark_archived(pile_name, rel_path)),
+        }
+    }
+
+    /// Returns all operations for the given hoard from a system history root directory.
+    ///
+    /// # Errors
+    ///
+    /// - Any errors that occur while reading from the filesystem
+    /// - Any parsing errors from `serde_json` when parsing the file
+    #[tracing::instrument(level = "trace")]
+    pub(crate) async fn all_from_history_dir(
+        dir: &HoardPath,
+        hoard: &HoardName,
+    ) -> Result<Vec<Self>, Error> {
+        let root = dir.join(&RelativePath::from(hoard));
+        if !root.exists() {
+            tracing::trace!(dir=?root, "hoard dir does not exist, no logs found");
+            return Ok(Vec::new());
+        }
+
+        ReadDirStream::new(fs::read_dir(&root).await?)
+            .map_err(Error::IO)
+            .try_filter_map(|item| async move {
+                let path = item.path();
+                Ok(util::file_is_log(&path).then_some(path))
+            })
+            .try_filter_map(|path| async move { Self::from_file(&path).await })
+            .try_collect()
+            .await
+    }
+
     /// Returns the latest operation recorded on this machine (by UUID).
     ///
     /// `file`, if provided, must be a path relative to the root of one of the Hoard's Piles.
@@ -544,6 +878,28 @@ impl Operation {
         Self::latest_hoard_operation_from_local_dir(&self_folder, hoard, file, false, false).await
     }
 
+    /// Returns the latest backup operation recorded on any machine, including this one.
+    ///
+    /// # Errors
+    ///
+    /// - Any errors that occur while reading from the filesystem
+    /// - Any parsing errors from `serde_json` when parsing the file
+    #[tracing::instrument(level = "debug")]
+    pub(crate) async fn latest_backup(hoard: &HoardName) -> Result<Option<Self>, Error> {
+        tracing::trace!("finding latest backup Operation file from any machine");
+        let uuid = super::get_or_generate_uuid().await?;
+        let self_folder = super::get_history_dir_for_id(uuid);
+        let local =
+            Self::latest_hoard_operation_from_local_dir(&self_folder, hoard, None, true, false)
+                .await?;
+        let remote = Self::latest_remote_backup(hoard, None, false).await?;
+        let latest = match (local, remote) {
+            (None, latest) | (latest, None) => latest,
+            (Some(local), Some(remote)) => Self::reduce_latest(Some(local), remote).await?,
+        };
+        latest.map(Self::into_latest_version).transpose()
+    }
+
     /// Returns the latest backup operation recorded on any other machine (by UUID).
     ///
     /// `file`, if provided, must be a path relative to the root of one of the Hoard's Piles.
@@ -600,8 +956,9 @@ impl Checker for Operation {
         name: &HoardName,
         hoard: &Hoard,
         direction: Direction,
+        options: &ScanOptions,
     ) -> Result<Self, Error> {
-        Self::new(hoards_root, name, hoard, direction).await
+        Self::new(hoards_root, name, hoard, direction, options).await
     }
 
     #[tracing::instrument]
@@ -652,34 +1009,87 @@ impl Checker for Operation {
     #[tracing::instrument(level = "trace", name = "commit_operation_to_disk")]
     async fn commit_to_disk(self) -> Result<(), Error> {
         let id = super::get_or_generate_uuid().await?;
-        let path = super::get_history_dir_for_id(id)
-            .join(&RelativePath::from(self.hoard_name()))
-            .join(
-                &RelativePath::try_from(PathBuf::from(format!(
-                    "{}.log",
-                    self.timestamp()
-                        .format(&TIME_FORMAT)
-                        .map_err(Error::FormatDatetime)
-                        .tap_err(crate::tap_log_error)?
-                )))
-                .expect("file name is always a valid RelativePath"),
+        let dir = super::get_history_dir_for_id(id).join(&RelativePath::from(self.hoard_name()));
+        let timestamp = self
+            .timestamp()
+            .format(&TIME_FORMAT)
+            .map_err(Error::FormatDatetime)
+            .tap_err(crate::tap_log_error)?;
+        tracing::trace!(dir=%dir.display(), "ensuring parent directories for operation log file");
+        fs::create_dir_all(&dir).await.tap_err(|error| {
+            tracing::error!(
+                %error,
+                "failed to create parent directory {} for operation log",
+                dir.display()
             );
-        tracing::trace!(path=%path.display(), "ensuring parent directories for operation log file");
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).await.tap_err(|error| {
-                tracing::error!(
-                    %error,
-                    "failed to create parent directory {} for operation log",
-                    parent.display()
-                );
-            })?;
-        }
+        })?;
         let content = serde_json::to_vec(&self).tap_err(|error| {
             tracing::error!(%error, "failed to serialize operation log as JSON");
         })?;
-        fs::write(&path, &content).await.tap_err(|error| {
-            tracing::error!(%error, "failed to write operation log file to {}", path.display());
-        })?;
-        Ok(())
+
+        // Never overwrite an existing log: if another operation has the same timestamp, append
+        // a counter to the file name until it is unique.
+        let mut counter = 0;
+        loop {
+            let path = dir.join(
+                &RelativePath::try_from(PathBuf::from(util::log_file_name(&timestamp, counter)))
+                    .expect("file name is always a valid RelativePath"),
+            );
+            match util::write_log_file(path.as_ref(), &content, false).await {
+                Ok(()) => return Ok(()),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    tracing::debug!(
+                        "operation log {} already exists, trying another name",
+                        path.display()
+                    );
+                    counter += 1;
+                }
+                Err(error) => {
+                    tracing::error!(%error, "failed to write operation log file to {}", path.display());
+                    return Err(error.into());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V2_LOG: &str = r#"{
+        "timestamp": "2022-01-01T00:00:00.000000000Z",
+        "direction": "backup",
+        "hoard": "archive",
+        "files": {
+            "Anonymous": { "created": {}, "modified": {}, "deleted": [], "unmodified": {} }
+        }
+    }"#;
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let log = V2_LOG.replacen('{', r#"{ "version": 4, "new_field": [1, 2, 3],"#, 1);
+        let error = serde_json::from_str::<Operation>(&log)
+            .expect_err("a log from a newer version should not be parsed");
+        assert!(
+            error.to_string().contains(
+                "log written by a newer hoard version (format version 4), please upgrade"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_known_versions_are_parsed() {
+        let operation: Operation = serde_json::from_str(V2_LOG).expect("v2 log should parse");
+        assert_eq!(operation.hoard_name().as_ref(), "archive");
+        let log = V2_LOG.replacen('{', r#"{ "version": 2,"#, 1);
+        serde_json::from_str::<Operation>(&log).expect("v2 log with a version should parse");
+
+        let log = V2_LOG.replacen('{', r#"{ "version": 3,"#, 1);
+        let operation: Operation = serde_json::from_str(&log).expect("v3 log should parse");
+        operation
+            .require_latest_version()
+            .expect("v3 should be the latest version");
     }
 }
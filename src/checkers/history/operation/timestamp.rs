@@ -0,0 +1,114 @@
+//! Serialization of operation log timestamps.
+//!
+//! Timestamps are written as RFC 3339 strings in UTC with a fixed number of fractional digits,
+//! e.g. `2024-01-02T03:04:05.123456789Z`, so that they sort the same as text as they do in time,
+//! no matter the offset of the system that wrote them. This is the format of
+//! [version 3](super::v3) logs. Older logs stored timestamps in `time`'s default tuple format,
+//! which is still accepted when reading and is replaced when
+//! [`upgrade_operations`](super::util::upgrade_operations) converts them to version 3.
+
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use time::format_description::well_known::Rfc3339;
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::{OffsetDateTime, UtcOffset};
+
+const FORMAT: &[FormatItem<'_>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:9]Z");
+
+/// A timestamp in either the current or the legacy format.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Rfc3339(String),
+    Legacy(OffsetDateTime),
+}
+
+pub(super) fn serialize<S>(timestamp: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    timestamp
+        .to_offset(UtcOffset::UTC)
+        .format(FORMAT)
+        .map_err(S::Error::custom)?
+        .serialize(serializer)
+}
+
+pub(super) fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timestamp = match Timestamp::deserialize(deserializer)? {
+        Timestamp::Rfc3339(timestamp) => {
+            OffsetDateTime::parse(&timestamp, &Rfc3339).map_err(D::Error::custom)?
+        }
+        Timestamp::Legacy(timestamp) => timestamp,
+    };
+    Ok(timestamp.to_offset(UtcOffset::UTC))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] OffsetDateTime);
+
+    #[test]
+    fn test_serializes_as_utc_rfc3339() {
+        let timestamp = datetime!(2024-01-02 08:04:05.5 +05:00);
+        assert_eq!(
+            serde_json::to_string(&Wrapper(timestamp)).unwrap(),
+            r#""2024-01-02T03:04:05.500000000Z""#
+        );
+    }
+
+    #[test]
+    fn test_different_offsets_sort_chronologically() {
+        // Each is an hour after the previous, but the local times go backwards.
+        let timestamps = [
+            datetime!(2024-01-02 12:00:00 +09:00),
+            datetime!(2024-01-02 04:00:00 UTC),
+            datetime!(2024-01-01 23:00:00 -06:00),
+        ];
+
+        let mut serialized: Vec<String> = timestamps
+            .iter()
+            .map(|timestamp| serde_json::to_string(&Wrapper(*timestamp)).unwrap())
+            .collect();
+        serialized.sort();
+        let deserialized: Vec<OffsetDateTime> = serialized
+            .iter()
+            .map(|timestamp| serde_json::from_str::<Wrapper>(timestamp).unwrap().0)
+            .collect();
+
+        let mut expected = timestamps.to_vec();
+        expected.sort();
+        assert_eq!(deserialized, expected);
+        assert!(deserialized
+            .iter()
+            .all(|timestamp| timestamp.offset() == UtcOffset::UTC));
+    }
+
+    #[test]
+    fn test_reads_legacy_format_as_utc() {
+        let timestamp = datetime!(2024-01-02 08:04:05.5 +05:00);
+        let legacy = serde_json::to_string(&timestamp).unwrap();
+        assert!(legacy.starts_with('['), "legacy format should be a tuple");
+
+        let Wrapper(read) = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(read, timestamp);
+        assert_eq!(read.offset(), UtcOffset::UTC);
+    }
+
+    #[test]
+    fn test_reads_other_offsets() {
+        let Wrapper(read) = serde_json::from_str(r#""2024-01-02T08:04:05+05:00""#).unwrap();
+        assert_eq!(read, datetime!(2024-01-02 03:04:05 UTC));
+        assert_eq!(read.offset(), UtcOffset::UTC);
+    }
+}
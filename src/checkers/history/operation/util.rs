@@ -1,21 +1,26 @@
 //! Helpful functions to use while working with [`Operation`](super::Operation) log files.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 
 use futures::{StreamExt, TryStream, TryStreamExt};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tap::TapFallible;
 use time::format_description::FormatItem;
-use tokio::fs;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tokio::io::AsyncWriteExt;
+use tokio::{fs, io};
 use tokio_stream::wrappers::ReadDirStream;
 use uuid::Uuid;
 
-use crate::checkers::history::get_history_root_dir;
 use crate::checkers::history::operation::OperationImpl;
-use crate::checkers::Checker;
+use crate::checkers::history::{get_history_dir_for_id, get_history_root_dir};
 use crate::hoard::Direction;
+use crate::newtypes::HoardName;
+use crate::paths::RelativePath;
 
 use super::{Error, Operation};
 
@@ -31,12 +36,117 @@ pub static TIME_FORMAT: Lazy<Vec<FormatItem<'static>>> = Lazy::new(|| {
 /// A regular expression that can be used to determine that a file name represents an
 /// [`Operation`](super::Operation) log file.
 ///
+/// If multiple operations share a timestamp, all but the first have a counter appended to the
+/// timestamp, e.g. `{timestamp}-1.log`.
+///
 /// Rather than using this directly, see [`file_is_log`].
 pub(crate) static LOG_FILE_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new("^[0-9]{4}(_[0-9]{2}){2}-([0-9]{2}_){2}([0-9]{2})\\.[0-9]{6}\\.log$")
-        .expect("invalid log file regex")
+    Regex::new(
+        "^(?P<timestamp>[0-9]{4}(_[0-9]{2}){2}-([0-9]{2}_){2}([0-9]{2})\\.[0-9]{6})(-(?P<counter>[0-9]+))?\\.log$",
+    )
+    .expect("invalid log file regex")
 });
 
+/// Returns the file name to use for the `counter`th log file with the given formatted timestamp.
+pub(crate) fn log_file_name(timestamp: &str, counter: u32) -> String {
+    if counter == 0 {
+        format!("{timestamp}.log")
+    } else {
+        format!("{timestamp}-{counter}.log")
+    }
+}
+
+/// Returns a key that sorts log file paths in the order the operations were logged.
+///
+/// Sorting by file name alone would place `{timestamp}-1.log` before `{timestamp}.log`.
+fn log_file_sort_key(path: &Path) -> (String, u32) {
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+    match LOG_FILE_REGEX.captures(name) {
+        None => (name.to_string(), 0),
+        Some(captures) => (
+            captures["timestamp"].to_string(),
+            captures
+                .name("counter")
+                .and_then(|counter| counter.as_str().parse().ok())
+                .unwrap_or(0),
+        ),
+    }
+}
+
+/// Returns the temporary path to write the log file at `path` to before moving it into place.
+///
+/// The name starts with a `.` and does not end in `.log`, so it is never mistaken for a log file.
+fn temp_log_path(path: &Path) -> PathBuf {
+    let name = path.file_name().and_then(OsStr::to_str).unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
+
+/// Makes a rename or new file in `dir` durable.
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir).await?.sync_all().await
+}
+
+/// Directories cannot be opened (and so synced) like files on this platform.
+#[cfg(not(unix))]
+#[allow(clippy::unused_async)]
+async fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Moves the complete file at `temp` to `path`, failing with [`io::ErrorKind::AlreadyExists`]
+/// instead of replacing an existing file.
+async fn move_without_replacing(temp: &Path, path: &Path) -> io::Result<()> {
+    match fs::hard_link(temp, path).await {
+        Ok(()) => fs::remove_file(temp).await,
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => Err(error),
+        // Some filesystems do not support hard links.
+        Err(error) => {
+            tracing::debug!(%error, "failed to link {}, renaming instead", path.display());
+            if fs::try_exists(path).await? {
+                Err(io::ErrorKind::AlreadyExists.into())
+            } else {
+                fs::rename(temp, path).await
+            }
+        }
+    }
+}
+
+/// Writes an operation log file so that `path` only ever contains the complete `content`.
+///
+/// The content is written and synced to a temporary file, which is then moved to `path`, and the
+/// parent directory is synced so the new name survives a crash. If `overwrite` is `false` and
+/// `path` already exists, this fails with [`io::ErrorKind::AlreadyExists`] and leaves it as is.
+pub(super) async fn write_log_file(path: &Path, content: &[u8], overwrite: bool) -> io::Result<()> {
+    let temp = temp_log_path(path);
+    let result = async {
+        let mut file = fs::File::create(&temp).await?;
+        file.write_all(content).await?;
+        file.sync_all().await?;
+        drop(file);
+        if overwrite {
+            fs::rename(&temp, path).await
+        } else {
+            move_without_replacing(&temp, path).await
+        }
+    }
+    .await;
+
+    if let Err(error) = result {
+        if let Err(error) = fs::remove_file(&temp).await {
+            if error.kind() != io::ErrorKind::NotFound {
+                tracing::warn!(%error, "failed to remove temporary file {}", temp.display());
+            }
+        }
+        return Err(error);
+    }
+
+    match path.parent() {
+        Some(dir) => sync_dir(dir).await,
+        None => Ok(()),
+    }
+}
+
 /// Inspects the file name portion of the `path` to determine if it matches the format used
 /// for [`Operation`](super::Operation) log files.
 #[must_use]
@@ -98,44 +208,87 @@ async fn log_files_to_delete_from_dir(
             tracing::error!(%error, "failed to read contents of {}", path.display());
         })?;
 
-    files.sort_unstable();
+    files.sort_by_cached_key(|path| log_file_sort_key(path));
 
-    // The last item is the latest operation for this hoard, so keep it.
-    let recent = files.pop();
+    // The last readable item is the latest operation for this hoard, so keep it.
+    let recent = pop_latest_readable(&mut files).await?;
 
     // Make sure the most recent backup is (also) retained.
     if let Some(recent) = recent {
-        let recent = Operation::from_file(&recent).await?;
-        if recent.direction() == Direction::Restore {
-            tracing::debug!(
-                "most recent log is not a backup, making sure to retain a backup log too"
-            );
-            // Find the index of the latest backup
-            let index = Box::pin(
-                tokio_stream::iter(files.iter().enumerate().rev().map(Ok)).try_filter_map(
-                    |(i, path)| async move {
-                        Operation::from_file(path)
-                            .await
-                            .map(|op| (op.direction() == Direction::Backup).then_some(i))
-                    },
-                ),
-            )
-            .try_next()
-            .await
-            .tap_err(|error| {
-                tracing::error!(%error, "error while finding most recent backup");
-            })?;
-
-            if let Some(index) = index {
-                // Found index of latest backup, remove it from deletion list
-                files.remove(index);
-            }
+        if let Some(index) = backup_to_retain(&recent, &files).await? {
+            // Found index of latest backup, remove it from deletion list
+            files.remove(index);
         }
     } // grcov: ignore
 
     Ok(tokio_stream::iter(files).map(Ok))
 }
 
+/// Removes logs from the end of `files` until one can be read, returning that log's operation.
+///
+/// Incomplete logs after it are left out of `files` so that they are neither deleted nor treated
+/// as the latest operation; they may still be being written or synced.
+///
+/// `files` must be sorted in the order the operations were logged.
+async fn pop_latest_readable(files: &mut Vec<PathBuf>) -> Result<Option<Operation>, Error> {
+    while let Some(path) = files.pop() {
+        if let Some(operation) = Operation::from_file(&path).await? {
+            return Ok(Some(operation));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the path and operation of the latest readable log in `dir`, if any.
+pub(super) async fn latest_log_in_dir(dir: &Path) -> Result<Option<(PathBuf, Operation)>, Error> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .await
+        .map(ReadDirStream::new)?
+        .map_err(Error::IO)
+        .try_filter_map(|entry| async move { Ok(file_is_log(&entry.path()).then(|| entry.path())) })
+        .try_collect()
+        .await?;
+    files.sort_by_cached_key(|path| log_file_sort_key(path));
+
+    while let Some(path) = files.pop() {
+        if let Some(operation) = Operation::from_file(&path).await? {
+            return Ok(Some((path, operation)));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the index in `older` of the latest backup log, if the `recent` log is a restore.
+///
+/// `older` must be sorted in the order the operations were logged.
+async fn backup_to_retain(recent: &Operation, older: &[PathBuf]) -> Result<Option<usize>, Error> {
+    if recent.direction() != Direction::Restore {
+        return Ok(None);
+    }
+
+    tracing::debug!("most recent log is not a backup, making sure to retain a backup log too");
+    // Find the index of the latest backup
+    Box::pin(
+        tokio_stream::iter(older.iter().enumerate().rev().map(Ok)).try_filter_map(
+            |(i, path)| async move {
+                Operation::from_file(path).await.map(|op| {
+                    op.filter(|op| op.direction() == Direction::Backup)
+                        .map(|_| i)
+                })
+            },
+        ),
+    )
+    .try_next()
+    .await
+    .tap_err(|error| {
+        tracing::error!(%error, "error while finding most recent backup");
+    })
+}
+
 // For each system folder, make a list of all log files, excluding 1 or 2 to keep.
 #[tracing::instrument]
 async fn log_files_to_delete(
@@ -164,12 +317,14 @@ async fn log_files_to_delete(
 /// also be retained. If the most recent log file is a *backup*, it will be the only one
 /// retained.
 ///
+/// If `dry_run` is true, the files that would be deleted are logged and counted instead.
+///
 /// # Errors
 ///
 /// - Any I/O error from working with and deleting multiple files
 /// - Any [`Error`]s from parsing files to determine whether or not to keep them
 #[tracing::instrument(level = "trace")]
-pub(crate) async fn cleanup_operations() -> Result<u32, (u32, Error)> {
+pub(crate) async fn cleanup_operations(dry_run: bool) -> Result<u32, (u32, Error)> {
     // Get hoard history root
     // Iterate over every uuid in the directory
     let root = get_history_root_dir();
@@ -193,6 +348,10 @@ pub(crate) async fn cleanup_operations() -> Result<u32, (u32, Error)> {
         .try_flatten()
         // Delete each file.
         .and_then(|path| async move {
+            if dry_run {
+                tracing::info!("would delete {}", path.display());
+                return Ok(());
+            }
             tracing::trace!("deleting {}", path.display());
             fs::remove_file(&path).await.map_err(|error| {
                 tracing::error!(%error, "failed to delete {}", path.display());
@@ -209,8 +368,218 @@ pub(crate) async fn cleanup_operations() -> Result<u32, (u32, Error)> {
         .map(|(count, ())| count)
 }
 
+/// Returns whether `dir` contains nothing, ignoring any entries in `removed`.
+async fn dir_is_empty(dir: &Path, removed: &HashSet<PathBuf>) -> Result<bool, Error> {
+    let mut entries = fs::read_dir(dir).await.map(ReadDirStream::new)?;
+    while let Some(entry) = entries.try_next().await? {
+        if !removed.contains(&entry.path()) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Removes empty system and hoard directories from the history root, hoard directories first.
+///
+/// Only directories with nothing in them are removed, so directories containing any log or other
+/// file, like `last_paths.json`, are always kept. The history root itself is never removed.
+///
+/// If `dry_run` is true, the directories that would be removed are logged and counted instead.
+///
+/// # Errors
+///
+/// Any I/O error from listing or removing directories.
 #[tracing::instrument(level = "trace")]
-async fn all_operations() -> Result<impl TryStream<Ok = Operation, Error = Error>, Error> {
+pub(crate) async fn remove_empty_history_dirs(dry_run: bool) -> Result<u32, Error> {
+    let root = get_history_root_dir();
+    if !root.exists() {
+        tracing::trace!("history root dir does not exist");
+        return Ok(0);
+    }
+
+    let system_dirs: Vec<PathBuf> = fs::read_dir(&root)
+        .await
+        .map(ReadDirStream::new)?
+        .map_err(Error::IO)
+        .try_filter_map(only_valid_uuid_path)
+        .map_ok(|entry| entry.path())
+        .try_collect()
+        .await
+        .tap_err(|error| {
+            tracing::error!(%error, "failed to list system directories in {}", root.display());
+        })?;
+
+    // Tracks what was (or would be) removed, so a dry run can tell that a system directory
+    // would be left empty.
+    let mut removed = HashSet::new();
+    for system_dir in system_dirs {
+        let hoard_dirs: Vec<PathBuf> = fs::read_dir(&system_dir)
+            .await
+            .map(ReadDirStream::new)?
+            .map_err(Error::IO)
+            .try_filter_map(|entry| async move { Ok(entry.path().is_dir().then(|| entry.path())) })
+            .try_collect()
+            .await?;
+
+        for dir in hoard_dirs.into_iter().chain(std::iter::once(system_dir)) {
+            if !dir_is_empty(&dir, &removed).await? {
+                continue;
+            }
+            if dry_run {
+                tracing::info!("would remove empty directory {}", dir.display());
+            } else {
+                tracing::trace!("removing empty directory {}", dir.display());
+                fs::remove_dir(&dir).await.tap_err(|error| {
+                    tracing::error!(%error, "failed to remove {}", dir.display());
+                })?;
+            }
+            removed.insert(dir);
+        }
+    }
+
+    Ok(u32::try_from(removed.len()).unwrap_or(u32::MAX))
+}
+
+/// Returns the time an operation was logged, parsed from the name of its log file.
+fn log_file_timestamp(path: &Path) -> Option<OffsetDateTime> {
+    let name = path.file_name()?.to_str()?;
+    let captures = LOG_FILE_REGEX.captures(name)?;
+    PrimitiveDateTime::parse(&captures["timestamp"], &TIME_FORMAT)
+        .ok()
+        .map(PrimitiveDateTime::assume_utc)
+}
+
+/// The number of log files deleted by [`prune_operations`] and the space they used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PruneSummary {
+    /// The number of log files deleted.
+    pub(crate) deleted: u32,
+    /// The combined size, in bytes, of the deleted log files.
+    pub(crate) bytes_freed: u64,
+}
+
+/// Returns the log files in `dir` that are beyond every given limit, oldest first.
+///
+/// The latest log is always retained, as is the latest backup if the latest log is a restore.
+#[tracing::instrument(level = "trace")]
+async fn log_files_to_prune_from_dir(
+    dir: &Path,
+    cutoff: Option<OffsetDateTime>,
+    keep: Option<usize>,
+) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .await
+        .map(ReadDirStream::new)?
+        .map_err(Error::IO)
+        .try_filter_map(|entry| async move { Ok(file_is_log(&entry.path()).then(|| entry.path())) })
+        .try_collect()
+        .await
+        .tap_err(|error| {
+            tracing::error!(%error, "failed to read contents of {}", dir.display());
+        })?;
+    files.sort_by_cached_key(|path| log_file_sort_key(path));
+
+    let Some(recent) = pop_latest_readable(&mut files).await? else {
+        return Ok(Vec::new());
+    };
+    let backup = backup_to_retain(&recent, &files).await?;
+
+    let to_prune = files
+        .iter()
+        .enumerate()
+        .filter(|(i, path)| {
+            // The `+ 1` counts the latest log, which is no longer in `files`.
+            let beyond_keep = keep.map(|keep| files.len() + 1 - i > keep);
+            // Keep any file whose timestamp cannot be determined.
+            let beyond_age = cutoff
+                .map(|cutoff| log_file_timestamp(path).is_some_and(|timestamp| timestamp < cutoff));
+            Some(*i) != backup
+                && match (beyond_keep, beyond_age) {
+                    (None, None) => false,
+                    (beyond_keep, beyond_age) => {
+                        beyond_keep.unwrap_or(true) && beyond_age.unwrap_or(true)
+                    }
+                }
+        })
+        .map(|(_, path)| path.clone())
+        .collect();
+
+    Ok(to_prune)
+}
+
+/// Deletes operation logs that are beyond the given limits for each (system, hoard) pair.
+///
+/// A log is only deleted if it is older than `cutoff` (when given) *and* not one of the `keep`
+/// most recent logs (when given). Timestamps are parsed from the log file names. As with
+/// [`cleanup_operations`], the latest log and, if that is a restore, the latest backup are
+/// always retained.
+///
+/// # Errors
+///
+/// - Any I/O error from working with and deleting multiple files
+/// - Any [`Error`]s from parsing files to determine whether or not to keep them
+///
+/// The [`PruneSummary`] returned with an error describes the files deleted before it occurred.
+#[tracing::instrument(level = "trace")]
+pub(crate) async fn prune_operations(
+    cutoff: Option<OffsetDateTime>,
+    keep: Option<usize>,
+) -> Result<PruneSummary, (PruneSummary, Error)> {
+    let mut summary = PruneSummary::default();
+    let root = get_history_root_dir();
+    if !root.exists() {
+        tracing::trace!("history root dir does not exist");
+        return Ok(summary);
+    }
+
+    let hoard_dirs: Vec<PathBuf> = fs::read_dir(&root)
+        .await
+        .map(ReadDirStream::new)
+        .map_err(Error::IO)
+        .map_err(|error| (summary, error))?
+        .map_err(Error::IO)
+        .try_filter_map(only_valid_uuid_path)
+        .and_then(|entry| async move {
+            fs::read_dir(entry.path())
+                .await
+                .map(ReadDirStream::new)
+                .map(|stream| stream.map_err(Error::IO))
+                .map_err(Error::IO)
+        })
+        .try_flatten()
+        .try_filter_map(|entry| async move { Ok(entry.path().is_dir().then(|| entry.path())) })
+        .try_collect()
+        .await
+        .tap_err(|error| {
+            tracing::error!(%error, "failed to list hoard history directories in {}", root.display());
+        })
+        .map_err(|error| (summary, error))?;
+
+    for dir in hoard_dirs {
+        let files = log_files_to_prune_from_dir(&dir, cutoff, keep)
+            .await
+            .map_err(|error| (summary, error))?;
+        for path in files {
+            tracing::trace!("deleting {}", path.display());
+            let result = async {
+                let size = fs::metadata(&path).await?.len();
+                fs::remove_file(&path).await?;
+                Ok::<_, std::io::Error>(size)
+            }
+            .await
+            .tap_err(|error| tracing::error!(%error, "failed to delete {}", path.display()));
+            let size = result.map_err(|error| (summary, Error::IO(error)))?;
+            summary.deleted += 1;
+            summary.bytes_freed += size;
+        }
+    }
+
+    Ok(summary)
+}
+
+#[tracing::instrument(level = "trace")]
+async fn all_operations() -> Result<impl TryStream<Ok = (PathBuf, Operation), Error = Error>, Error>
+{
     let history_dir = get_history_root_dir();
     tracing::trace!(?history_dir);
     let iter = fs::read_dir(&history_dir)
@@ -275,18 +644,111 @@ async fn all_operations() -> Result<impl TryStream<Ok = Operation, Error = Error
         .map_ok(|hoard_entry| hoard_entry.path()) // Iterator of PathBuf
         .try_filter_map(|path| async move { Ok(file_is_log(&path).then_some(path)) }) // Only those paths that are log files
         .map_err(Error::IO)
-        .and_then(|path| async move { Operation::from_file(&path).await });
+        .try_filter_map(|path| async move {
+            let operation = Operation::from_file(&path).await?;
+            Ok(operation.map(|operation| (path, operation)))
+        });
 
     Ok(iter)
 }
 
 #[tracing::instrument(level = "trace")]
-async fn sorted_operations() -> Result<Vec<Operation>, Error> {
-    let mut list: Vec<Operation> = all_operations().await?.try_collect().await?;
-    list.sort_unstable_by_key(Operation::timestamp);
+async fn sorted_operations() -> Result<Vec<(PathBuf, Operation)>, Error> {
+    let mut list: Vec<(PathBuf, Operation)> = all_operations().await?.try_collect().await?;
+    list.sort_by_cached_key(|(path, operation)| (operation.timestamp(), log_file_sort_key(path)));
     Ok(list)
 }
 
+/// Operation logs from every system, in a single document.
+///
+/// Logs are keyed by system UUID, hoard name, and log file name, mirroring the layout of the
+/// history directory so that it can be recreated exactly.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct HistoryExport {
+    pub(crate) systems: BTreeMap<Uuid, BTreeMap<HoardName, BTreeMap<String, Operation>>>,
+}
+
+impl HistoryExport {
+    /// Returns the total number of operation logs.
+    pub(crate) fn len(&self) -> usize {
+        self.systems
+            .values()
+            .flat_map(BTreeMap::values)
+            .map(BTreeMap::len)
+            .sum()
+    }
+}
+
+/// Collects the operation logs of all systems into a single [`HistoryExport`].
+///
+/// # Errors
+///
+/// Any I/O errors while reading the history directory, or parsing errors for any log file.
+#[tracing::instrument(level = "trace")]
+pub(crate) async fn export_operations() -> Result<HistoryExport, Error> {
+    let mut export = HistoryExport::default();
+    if !get_history_root_dir().exists() {
+        tracing::trace!("history root dir does not exist");
+        return Ok(export);
+    }
+
+    let operations: Vec<(PathBuf, Operation)> = all_operations().await?.try_collect().await?;
+    for (path, operation) in operations {
+        let system = path
+            .parent()
+            .and_then(Path::parent)
+            .and_then(Path::file_name)
+            .and_then(OsStr::to_str)
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .expect("log files are always in a system history directory named by UUID");
+        let file_name = path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .expect("log file names always match the log file regex")
+            .to_string();
+        export
+            .systems
+            .entry(system)
+            .or_default()
+            .entry(operation.hoard_name().clone())
+            .or_default()
+            .insert(file_name, operation);
+    }
+
+    Ok(export)
+}
+
+/// Writes every operation log in `history` to the history directory, overwriting any logs
+/// with the same system, hoard, and file name.
+///
+/// # Errors
+///
+/// - [`Error::InvalidLogName`] if any log has a file name that is not a valid log file name.
+/// - Any I/O errors while writing the logs.
+#[tracing::instrument(level = "trace", skip(history))]
+pub(crate) async fn import_operations(history: HistoryExport) -> Result<(), Error> {
+    for (system, hoards) in history.systems {
+        for (hoard, operations) in hoards {
+            let dir = get_history_dir_for_id(system).join(&RelativePath::from(&hoard));
+            fs::create_dir_all(&dir).await.tap_err(|error| {
+                tracing::error!(%error, "failed to create history directory {}", dir.display());
+            })?;
+            for (file_name, operation) in operations {
+                if !LOG_FILE_REGEX.is_match(&file_name) {
+                    return crate::create_log_error(Error::InvalidLogName(file_name));
+                }
+                let path = dir.join(
+                    &RelativePath::try_from(PathBuf::from(file_name))
+                        .expect("log file name is always a valid RelativePath"),
+                );
+                operation.write_to_file(&path).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[tracing::instrument(level = "trace")]
 pub(crate) async fn upgrade_operations() -> Result<(), Error> {
     tracing::debug!("upgrading operation files to latest version");
@@ -296,7 +758,7 @@ pub(crate) async fn upgrade_operations() -> Result<(), Error> {
     let all_ops: Vec<_> = sorted_operations().await?;
     tracing::trace!("found operations: {:?}", all_ops);
 
-    for operation in all_ops {
+    for (path, operation) in all_ops {
         if !top_file_checksum_map.contains_key(operation.hoard_name()) {
             top_file_checksum_map.insert(operation.hoard_name().clone(), HashMap::new());
             top_file_set.insert(operation.hoard_name().clone(), HashSet::new());
@@ -310,7 +772,8 @@ pub(crate) async fn upgrade_operations() -> Result<(), Error> {
         tracing::trace!(?operation, "converting operation");
         let operation = operation.convert_to_latest_version(file_checksum_map, file_set);
         tracing::trace!(?operation, "converted operation");
-        operation.commit_to_disk().await?;
+        // Overwrite the original file so that logs sharing a timestamp stay distinct.
+        operation.write_to_file(&path).await?;
     }
 
     Ok(())
@@ -2,6 +2,7 @@
 
 use crate::checkers::history::operation::OperationFileInfo;
 use crate::checksum::{Checksum, MD5};
+use crate::hoard::pile_config::Permissions;
 use crate::hoard::Direction;
 use crate::newtypes::{HoardName, NonEmptyPileName, PileName};
 use crate::paths::RelativePath;
@@ -47,7 +48,7 @@ impl super::OperationImpl for OperationV1 {
             (None, Hoard::Anonymous(pile)) => pile.0.contains_key(rel_path),
             (Some(pile_name), Hoard::Named(piles)) => piles
                 .get(pile_name)
-                .map_or(false, |pile| pile.0.contains_key(rel_path)),
+                .is_some_and(|pile| pile.0.contains_key(rel_path)),
             _ => false,
         }
     }
@@ -71,6 +72,22 @@ impl super::OperationImpl for OperationV1 {
     }
 
     /// Returns, in order: the pile name, the relative path, and the file's checksum.
+    fn permissions_for(
+        &self,
+        _pile_name: &PileName,
+        _rel_path: &RelativePath,
+    ) -> Option<Permissions> {
+        None
+    }
+
+    fn mtime_for(&self, _pile_name: &PileName, _rel_path: &RelativePath) -> Option<OffsetDateTime> {
+        None
+    }
+
+    fn is_archived(&self, _pile_name: &PileName, _rel_path: &RelativePath) -> bool {
+        false
+    }
+
     fn all_files_with_checksums<'s>(&'s self) -> Box<dyn Iterator<Item = OperationFileInfo> + 's> {
         match &self.hoard {
             Hoard::Anonymous(pile) => {
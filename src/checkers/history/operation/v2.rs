@@ -1,18 +1,23 @@
-//! The current version of the operation log format.
+//! The second operation log format, retained for backwards compatibility.
+//!
+//! Its contents are still used by [version 3](super::v3), which only changes how they are
+//! serialized.
 //!
 //! This type should be interacted with using the top-level [`Operation`](super::Operation)
 //! instead of being used directly.
 
 use std::collections::{HashMap, HashSet};
 
+use filetime::FileTime;
 use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
-use tokio::io;
+use tokio::{fs, io};
 
 use crate::checkers::history::operation::{OperationFileInfo, OperationImpl, OperationType};
 use crate::checksum::{Checksum, ChecksumType};
-use crate::hoard::iter::operation_stream;
+use crate::hoard::iter::{operation_stream, ScanOptions};
+use crate::hoard::pile_config::Permissions;
 use crate::hoard::{Direction, Hoard as ConfigHoard};
 use crate::hoard_item::{CachedHoardItem, HoardItem};
 use crate::newtypes::{HoardName, NonEmptyPileName, PileName};
@@ -20,8 +25,6 @@ use crate::paths::{HoardPath, RelativePath};
 
 use super::{Error, ItemOperation};
 
-/// Errors that may occur while working with operation logs.
-
 /// A single operation log.
 ///
 /// This keeps track of the timestamp of the operation (which may include multiple hoards),
@@ -31,13 +34,14 @@ use super::{Error, ItemOperation};
 #[allow(clippy::module_name_repetitions)]
 pub struct OperationV2 {
     /// Timestamp of last operation
-    timestamp: OffsetDateTime,
+    #[serde(deserialize_with = "super::timestamp::deserialize")]
+    pub(super) timestamp: OffsetDateTime,
     /// Which direction this operation went
-    direction: Direction,
+    pub(super) direction: Direction,
     /// The name of the hoard for this `HoardOperation`.
-    hoard: HoardName,
+    pub(super) hoard: HoardName,
     /// Mapping of pile files to checksums
-    files: Hoard,
+    pub(super) files: Hoard,
 }
 
 impl OperationV2 {
@@ -47,12 +51,13 @@ impl OperationV2 {
         name: &HoardName,
         hoard: &ConfigHoard,
         direction: Direction,
+        options: &ScanOptions,
     ) -> Result<Self, Error> {
         Ok(Self {
             timestamp: OffsetDateTime::now_utc(),
             direction,
             hoard: name.clone(),
-            files: Hoard::new(hoards_root, name, hoard, direction).await?,
+            files: Hoard::new(hoards_root, name, hoard, direction, options).await?,
         })
     }
 
@@ -148,6 +153,33 @@ impl OperationV2 {
     }
 }
 
+impl OperationV2 {
+    /// Replaces the recorded checksum of a file, keeping whether it was recorded as created,
+    /// modified, or unmodified.
+    ///
+    /// Returns `false` if the file has no recorded checksum.
+    pub(super) fn replace_checksum(
+        &mut self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+        checksum: Checksum,
+    ) -> bool {
+        self.files
+            .get_pile_mut(pile_name)
+            .is_some_and(|pile| pile.replace_checksum(rel_path, checksum))
+    }
+
+    /// Records that a file was moved into the hoard, so it is no longer expected to exist on the
+    /// system.
+    ///
+    /// Returns `false` if the file has no recorded checksum.
+    pub(super) fn mark_archived(&mut self, pile_name: &PileName, rel_path: &RelativePath) -> bool {
+        self.files
+            .get_pile_mut(pile_name)
+            .is_some_and(|pile| pile.mark_archived(rel_path))
+    }
+}
+
 impl OperationImpl for OperationV2 {
     fn direction(&self) -> Direction {
         self.direction
@@ -161,7 +193,7 @@ impl OperationImpl for OperationV2 {
     ) -> bool {
         self.files
             .get_pile(pile_name)
-            .map_or(false, |pile| pile.contains_file(rel_path, only_modified))
+            .is_some_and(|pile| pile.contains_file(rel_path, only_modified))
     }
 
     fn timestamp(&self) -> OffsetDateTime {
@@ -178,6 +210,28 @@ impl OperationImpl for OperationV2 {
             .and_then(|pile| pile.checksum_for(rel_path))
     }
 
+    fn permissions_for(
+        &self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+    ) -> Option<Permissions> {
+        self.files
+            .get_pile(pile_name)
+            .and_then(|pile| pile.permissions_for(rel_path))
+    }
+
+    fn mtime_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<OffsetDateTime> {
+        self.files
+            .get_pile(pile_name)
+            .and_then(|pile| pile.mtime_for(rel_path))
+    }
+
+    fn is_archived(&self, pile_name: &PileName, rel_path: &RelativePath) -> bool {
+        self.files
+            .get_pile(pile_name)
+            .is_some_and(|pile| pile.archived.contains(rel_path))
+    }
+
     #[tracing::instrument(level = "trace", name = "v2_all_files_with_checksums")]
     fn all_files_with_checksums<'a>(&'a self) -> Box<dyn Iterator<Item = OperationFileInfo> + 'a> {
         match &self.files {
@@ -279,8 +333,8 @@ impl OperationImpl for OperationV2 {
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
-#[allow(variant_size_differences)]
-enum Hoard {
+#[allow(variant_size_differences, clippy::large_enum_variant)]
+pub(super) enum Hoard {
     Anonymous(Pile),
     Named(HashMap<NonEmptyPileName, Pile>),
 }
@@ -333,9 +387,16 @@ impl Hoard {
         hoard_name: &HoardName,
         hoard: &crate::hoard::Hoard,
         direction: Direction,
+        options: &ScanOptions,
     ) -> Result<Self, Error> {
+        // Archived files are not on the system, so their metadata is kept from the last log.
+        let previous = match direction {
+            Direction::Backup => super::Operation::latest_local(hoard_name, None).await?,
+            Direction::Restore => None,
+        };
+        let previous = previous.as_ref();
         let mut inner: HashMap<PileName, Pile> =
-            operation_stream(hoards_root, hoard_name.clone(), hoard, direction)
+            operation_stream(hoards_root, hoard_name.clone(), hoard, direction, options)
                 .await?
                 .map_err(Error::Iterator)
                 .try_fold(HashMap::new(), |mut acc, op| async move {
@@ -352,8 +413,11 @@ impl Hoard {
                                     file.relative_path(),
                                 )?,
                             };
-                            Self::get_or_create_pile(&mut acc, file.pile_name())
-                                .add_created(file.relative_path().clone(), checksum);
+                            let pile = Self::get_or_create_pile(&mut acc, file.pile_name());
+                            pile.add_created(file.relative_path().clone(), checksum);
+                            if direction == Direction::Backup {
+                                pile.add_metadata(&file).await?;
+                            }
                         }
                         ItemOperation::Modify(file) => {
                             let checksum = match direction {
@@ -366,20 +430,38 @@ impl Hoard {
                                     file.relative_path(),
                                 )?,
                             };
-                            Self::get_or_create_pile(&mut acc, file.pile_name())
-                                .add_modified(file.relative_path().clone(), checksum);
+                            let pile = Self::get_or_create_pile(&mut acc, file.pile_name());
+                            pile.add_modified(file.relative_path().clone(), checksum);
+                            if direction == Direction::Backup {
+                                pile.add_metadata(&file).await?;
+                            }
                         }
                         ItemOperation::Delete(file) => {
                             Self::get_or_create_pile(&mut acc, file.pile_name())
                                 .add_deleted(file.relative_path().clone());
                         }
                         ItemOperation::Nothing(file) => {
+                            let typ = Self::checksum_type(hoard, &file);
+                            let pile = Self::get_or_create_pile(&mut acc, file.pile_name());
+                            // Only archived files are unchanged without a system copy.
+                            if let (None, Some(checksum)) =
+                                (file.system_checksum(typ), file.hoard_checksum(typ))
+                            {
+                                pile.add_unmodified(file.relative_path().clone(), checksum);
+                                pile.mark_archived(file.relative_path());
+                                if let Some(previous) = previous {
+                                    pile.copy_metadata(&file, previous);
+                                }
+                                return Ok(acc);
+                            }
                             let checksum = Self::require_checksum(
-                                file.system_checksum(Self::checksum_type(hoard, &file)),
+                                file.system_checksum(typ),
                                 file.relative_path(),
                             )?;
-                            Self::get_or_create_pile(&mut acc, file.pile_name())
-                                .add_unmodified(file.relative_path().clone(), checksum);
+                            pile.add_unmodified(file.relative_path().clone(), checksum);
+                            if direction == Direction::Backup {
+                                pile.add_metadata(&file).await?;
+                            }
                         }
                         ItemOperation::DoesNotExist(_) => {}
                     }
@@ -411,14 +493,31 @@ impl Hoard {
             _ => None,
         }
     }
+
+    fn get_pile_mut(&mut self, name: &PileName) -> Option<&mut Pile> {
+        match (name.as_ref(), self) {
+            (None, Hoard::Anonymous(pile)) => Some(pile),
+            (Some(name), Hoard::Named(piles)) => piles.get_mut(name),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
-struct Pile {
+pub(super) struct Pile {
     created: HashMap<RelativePath, Checksum>,
     modified: HashMap<RelativePath, Checksum>,
     deleted: HashSet<RelativePath>,
     unmodified: HashMap<RelativePath, Checksum>,
+    /// The permissions of each system file at the time of a backup.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    permissions: HashMap<RelativePath, Permissions>,
+    /// The modification time of each system file at the time of a backup.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    mtimes: HashMap<RelativePath, OffsetDateTime>,
+    /// Files that were moved into the hoard with `--move` and are only stored there.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    archived: HashSet<RelativePath>,
 }
 
 impl Pile {
@@ -442,6 +541,67 @@ impl Pile {
         self.unmodified.insert(path, checksum);
     }
 
+    /// Records the permissions and modification time of the system copy of `file`.
+    ///
+    /// Modification times that cannot be represented are not recorded.
+    async fn add_metadata(&mut self, file: &CachedHoardItem) -> Result<(), Error> {
+        let metadata = fs::metadata(file.system_path()).await.map_err(|error| {
+            tracing::error!(
+                %error,
+                "failed to read metadata of {}",
+                file.system_path().display()
+            );
+            Error::IO(error)
+        })?;
+        self.permissions.insert(
+            file.relative_path().clone(),
+            Permissions::from_std(&metadata.permissions()),
+        );
+
+        let mtime = FileTime::from_last_modification_time(&metadata);
+        let nanos =
+            i128::from(mtime.unix_seconds()) * 1_000_000_000 + i128::from(mtime.nanoseconds());
+        match OffsetDateTime::from_unix_timestamp_nanos(nanos) {
+            Ok(mtime) => {
+                self.mtimes.insert(file.relative_path().clone(), mtime);
+            }
+            Err(error) => tracing::warn!(
+                %error,
+                "not recording invalid modification time of {}",
+                file.system_path().display()
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// Records the permissions and modification time that `previous` recorded for `file`.
+    fn copy_metadata(&mut self, file: &CachedHoardItem, previous: &super::Operation) {
+        let (pile_name, rel_path) = (file.pile_name(), file.relative_path());
+        if let Some(permissions) = previous.permissions_for(pile_name, rel_path) {
+            self.permissions.insert(rel_path.clone(), permissions);
+        }
+        if let Some(mtime) = previous.mtime_for(pile_name, rel_path) {
+            self.mtimes.insert(rel_path.clone(), mtime);
+        }
+    }
+
+    fn mark_archived(&mut self, rel_path: &RelativePath) -> bool {
+        let recorded = self.checksum_for(rel_path).is_some();
+        if recorded {
+            self.archived.insert(rel_path.clone());
+        }
+        recorded
+    }
+
+    fn permissions_for(&self, rel_path: &RelativePath) -> Option<Permissions> {
+        self.permissions.get(rel_path).copied()
+    }
+
+    fn mtime_for(&self, rel_path: &RelativePath) -> Option<OffsetDateTime> {
+        self.mtimes.get(rel_path).copied()
+    }
+
     fn contains_file(&self, rel_path: &RelativePath, only_modified: bool) -> bool {
         self.created.contains_key(rel_path)
             || self.modified.contains_key(rel_path)
@@ -457,6 +617,19 @@ impl Pile {
             .cloned()
     }
 
+    fn replace_checksum(&mut self, rel_path: &RelativePath, checksum: Checksum) -> bool {
+        let recorded = [&mut self.created, &mut self.modified, &mut self.unmodified]
+            .into_iter()
+            .find_map(|checksums| checksums.get_mut(rel_path));
+        match recorded {
+            None => false,
+            Some(recorded) => {
+                *recorded = checksum;
+                true
+            }
+        }
+    }
+
     fn all_files_with_checksums(&self) -> impl Iterator<Item = (&RelativePath, Option<Checksum>)> {
         let created = self
             .created
@@ -514,6 +687,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_pile_permissions_are_optional() {
+        let path = RelativePath::try_from(PathBuf::from("script.sh")).unwrap();
+        let checksum = Checksum::MD5(MD5::from_data("testing"));
+        let mut pile = Pile::new();
+        pile.add_created(path.clone(), checksum);
+        let without_perms = serde_json::to_value(&pile).unwrap();
+        assert!(without_perms.get("permissions").is_none());
+        assert_eq!(serde_json::from_value::<Pile>(without_perms).unwrap(), pile);
+
+        pile.permissions
+            .insert(path.clone(), Permissions::Mode(0o755));
+        let with_perms = serde_json::to_value(&pile).unwrap();
+        assert_eq!(with_perms["permissions"]["script.sh"], 0o755);
+        let pile: Pile = serde_json::from_value(with_perms).unwrap();
+        assert_eq!(pile.permissions_for(&path), Some(Permissions::Mode(0o755)));
+    }
+
+    #[test]
+    fn test_pile_mtimes_are_optional() {
+        let path = RelativePath::try_from(PathBuf::from("save.dat")).unwrap();
+        let checksum = Checksum::MD5(MD5::from_data("testing"));
+        let mut pile = Pile::new();
+        pile.add_created(path.clone(), checksum);
+        let without_mtimes = serde_json::to_value(&pile).unwrap();
+        assert!(without_mtimes.get("mtimes").is_none());
+        assert_eq!(
+            serde_json::from_value::<Pile>(without_mtimes).unwrap(),
+            pile
+        );
+
+        let mtime = OffsetDateTime::from_unix_timestamp(1_000_000_000).unwrap();
+        pile.mtimes.insert(path.clone(), mtime);
+        let with_mtimes = serde_json::to_value(&pile).unwrap();
+        let pile: Pile = serde_json::from_value(with_mtimes).unwrap();
+        assert_eq!(pile.mtime_for(&path), Some(mtime));
+    }
+
+    #[test]
+    fn test_pile_archived_is_optional() {
+        let path = RelativePath::try_from(PathBuf::from("old_save")).unwrap();
+        let checksum = Checksum::MD5(MD5::from_data("testing"));
+        let mut pile = Pile::new();
+        assert!(
+            !pile.mark_archived(&path),
+            "unrecorded files cannot be archived"
+        );
+        pile.add_unmodified(path.clone(), checksum);
+        let without_archived = serde_json::to_value(&pile).unwrap();
+        assert!(without_archived.get("archived").is_none());
+        assert_eq!(
+            serde_json::from_value::<Pile>(without_archived).unwrap(),
+            pile
+        );
+
+        assert!(pile.mark_archived(&path));
+        let with_archived = serde_json::to_value(&pile).unwrap();
+        assert_eq!(with_archived["archived"], serde_json::json!(["old_save"]));
+        let pile: Pile = serde_json::from_value(with_archived).unwrap();
+        assert!(pile.archived.contains(&path));
+    }
+
     mod v2_from_v1 {
         use maplit;
         use time::Duration;
@@ -0,0 +1,223 @@
+//! The current version of the operation log format.
+//!
+//! Version 3 records the same information as [version 2](super::v2), but stores the timestamp as
+//! a UTC RFC 3339 string and records its format version in a `version` field, so that older
+//! versions of Hoard skip it instead of failing to read the timestamp.
+//!
+//! This type should be interacted with using the top-level [`Operation`](super::Operation)
+//! instead of being used directly.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use time::OffsetDateTime;
+
+use crate::checkers::history::operation::{OperationFileInfo, OperationImpl, OperationType};
+use crate::checksum::Checksum;
+use crate::hoard::iter::ScanOptions;
+use crate::hoard::pile_config::Permissions;
+use crate::hoard::{Direction, Hoard as ConfigHoard};
+use crate::hoard_item::HoardItem;
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::{HoardPath, RelativePath};
+
+use super::v2::{self, OperationV2};
+use super::{Error, ItemOperation};
+
+/// The format version recorded in every version 3 log.
+const VERSION: u32 = 3;
+
+/// A single operation log.
+///
+/// See [`OperationV2`] for the recorded contents.
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::module_name_repetitions)]
+pub struct OperationV3(OperationV2);
+
+/// The serialized form of [`OperationV3`].
+#[derive(Serialize, Deserialize)]
+struct Repr<T> {
+    version: u32,
+    #[serde(with = "super::timestamp")]
+    timestamp: OffsetDateTime,
+    direction: Direction,
+    hoard: HoardName,
+    files: T,
+}
+
+impl Serialize for OperationV3 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Repr {
+            version: VERSION,
+            timestamp: self.0.timestamp,
+            direction: self.0.direction,
+            hoard: self.0.hoard.clone(),
+            files: &self.0.files,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OperationV3 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = Repr::<v2::Hoard>::deserialize(deserializer)?;
+        if repr.version != VERSION {
+            return Err(D::Error::custom(format!(
+                "expected format version {VERSION}, found {}",
+                repr.version
+            )));
+        }
+        Ok(Self(OperationV2 {
+            timestamp: repr.timestamp,
+            direction: repr.direction,
+            hoard: repr.hoard,
+            files: repr.files,
+        }))
+    }
+}
+
+impl From<OperationV2> for OperationV3 {
+    fn from(operation: OperationV2) -> Self {
+        Self(operation)
+    }
+}
+
+impl OperationV3 {
+    pub(super) async fn new(
+        hoards_root: &HoardPath,
+        name: &HoardName,
+        hoard: &ConfigHoard,
+        direction: Direction,
+        options: &ScanOptions,
+    ) -> Result<Self, Error> {
+        OperationV2::new(hoards_root, name, hoard, direction, options)
+            .await
+            .map(Self)
+    }
+
+    /// Replaces the recorded checksum of a file, keeping whether it was recorded as created,
+    /// modified, or unmodified.
+    ///
+    /// Returns `false` if the file has no recorded checksum.
+    pub(super) fn replace_checksum(
+        &mut self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+        checksum: Checksum,
+    ) -> bool {
+        self.0.replace_checksum(pile_name, rel_path, checksum)
+    }
+
+    /// Records that a file was moved into the hoard, so it is no longer expected to exist on the
+    /// system.
+    ///
+    /// Returns `false` if the file has no recorded checksum.
+    pub(super) fn mark_archived(&mut self, pile_name: &PileName, rel_path: &RelativePath) -> bool {
+        self.0.mark_archived(pile_name, rel_path)
+    }
+}
+
+impl OperationImpl for OperationV3 {
+    fn direction(&self) -> Direction {
+        self.0.direction()
+    }
+
+    fn contains_file(
+        &self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+        only_modified: bool,
+    ) -> bool {
+        self.0.contains_file(pile_name, rel_path, only_modified)
+    }
+
+    fn timestamp(&self) -> OffsetDateTime {
+        self.0.timestamp()
+    }
+
+    fn hoard_name(&self) -> &HoardName {
+        self.0.hoard_name()
+    }
+
+    fn checksum_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<Checksum> {
+        self.0.checksum_for(pile_name, rel_path)
+    }
+
+    fn permissions_for(
+        &self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+    ) -> Option<Permissions> {
+        self.0.permissions_for(pile_name, rel_path)
+    }
+
+    fn mtime_for(&self, pile_name: &PileName, rel_path: &RelativePath) -> Option<OffsetDateTime> {
+        self.0.mtime_for(pile_name, rel_path)
+    }
+
+    fn is_archived(&self, pile_name: &PileName, rel_path: &RelativePath) -> bool {
+        self.0.is_archived(pile_name, rel_path)
+    }
+
+    fn all_files_with_checksums<'a>(&'a self) -> Box<dyn Iterator<Item = OperationFileInfo> + 'a> {
+        self.0.all_files_with_checksums()
+    }
+
+    fn hoard_operations_iter<'a>(
+        &'a self,
+        hoard_root: &HoardPath,
+        hoard: &ConfigHoard,
+    ) -> Result<Box<dyn Iterator<Item = ItemOperation<HoardItem>> + 'a>, Error> {
+        self.0.hoard_operations_iter(hoard_root, hoard)
+    }
+
+    fn file_operation(
+        &self,
+        pile_name: &PileName,
+        rel_path: &RelativePath,
+    ) -> Result<Option<OperationType>, Error> {
+        self.0.file_operation(pile_name, rel_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    const V3_LOG: &str = r#"{
+        "version": 3,
+        "timestamp": "2022-01-01T00:00:00.000000000Z",
+        "direction": "backup",
+        "hoard": "archive",
+        "files": {
+            "Anonymous": { "created": {}, "modified": {}, "deleted": [], "unmodified": {} }
+        }
+    }"#;
+
+    #[test]
+    fn test_round_trip_records_version() {
+        let operation: OperationV3 = serde_json::from_str(V3_LOG).unwrap();
+        assert_eq!(operation.timestamp(), datetime!(2022-01-01 00:00:00 UTC));
+        let value = serde_json::to_value(&operation).unwrap();
+        assert_eq!(value["version"], 3);
+        assert_eq!(value["timestamp"], "2022-01-01T00:00:00.000000000Z");
+        assert_eq!(
+            serde_json::from_value::<OperationV3>(value).unwrap(),
+            operation
+        );
+    }
+
+    #[test]
+    fn test_requires_version() {
+        let unversioned = V3_LOG.replacen(r#""version": 3,"#, "", 1);
+        assert!(serde_json::from_str::<OperationV3>(&unversioned).is_err());
+        let v2 = V3_LOG.replacen(r#""version": 3"#, r#""version": 2"#, 1);
+        assert!(serde_json::from_str::<OperationV3>(&v2).is_err());
+    }
+}
@@ -9,6 +9,7 @@ use thiserror::Error;
 
 use crate::checkers::history::last_paths::{Error as LastPathsError, LastPaths};
 use crate::checkers::history::operation::{Error as OperationError, Operation};
+use crate::hoard::iter::ScanOptions;
 use crate::hoard::{Direction, Hoard};
 use crate::newtypes::HoardName;
 use crate::paths::HoardPath;
@@ -25,6 +26,8 @@ pub trait Checker: Sized + Unpin {
     type Error: std::error::Error;
     /// Returns a new instance of the implementing Checker type.
     ///
+    /// `options` controls how the files of `hoard` are read, if the checker needs to.
+    ///
     /// # Errors
     ///
     /// Any errors that may occur while creating an instance, such as I/O or consistency errors.
@@ -33,6 +36,7 @@ pub trait Checker: Sized + Unpin {
         hoard_name: &HoardName,
         hoard: &Hoard,
         direction: Direction,
+        options: &ScanOptions,
     ) -> Result<Self, Self::Error>;
     /// Returns an error if it is not safe to operate on the given [`Hoard`].
     ///
@@ -75,14 +79,15 @@ impl Checkers {
         hoards_root: &HoardPath,
         hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)>,
         direction: Direction,
+        options: &ScanOptions,
     ) -> Result<Self, Error> {
         let mut last_paths = HashMap::new();
         let mut operations = HashMap::new();
 
         for (name, hoard) in hoards {
             tracing::debug!(%name, ?hoard, "processing hoard");
-            let lp = LastPaths::new(hoards_root, name, hoard, direction).await?;
-            let op = Operation::new(hoards_root, name, hoard, direction).await?;
+            let lp = LastPaths::new(hoards_root, name, hoard, direction, options).await?;
+            let op = Operation::new(hoards_root, name, hoard, direction, options).await?;
             last_paths.insert(name.clone(), lp);
             operations.insert(name.clone(), op);
         }
@@ -123,4 +128,18 @@ impl Checkers {
     pub(crate) fn get_operation_for(&self, hoard_name: &HoardName) -> Option<&Operation> {
         self.operations.get(hoard_name)
     }
+
+    pub(crate) fn get_operation_mut_for(
+        &mut self,
+        hoard_name: &HoardName,
+    ) -> Option<&mut Operation> {
+        self.operations.get_mut(hoard_name)
+    }
+
+    /// Stops tracking `hoard_name`, so that nothing is written for it by
+    /// [`Checkers::commit_to_disk`].
+    pub(crate) fn remove(&mut self, hoard_name: &HoardName) {
+        self.last_paths.remove(hoard_name);
+        self.operations.remove(hoard_name);
+    }
 }
@@ -0,0 +1,370 @@
+//! A portable implementation of the BLAKE3 hash function, following the reference
+//! implementation in the [BLAKE3 specification](https://github.com/BLAKE3-team/BLAKE3-specs).
+//!
+//! Only the default (unkeyed) hash mode with a 32-byte output is supported, which is all
+//! Hoard needs for checksums.
+
+const OUT_LEN: usize = 32;
+const BLOCK_LEN: usize = 64;
+#[allow(clippy::cast_possible_truncation)]
+const BLOCK_LEN_U32: u32 = BLOCK_LEN as u32;
+const CHUNK_LEN: usize = 1024;
+
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+const IV: [u32; 8] = [
+    0x6A09_E667,
+    0xBB67_AE85,
+    0x3C6E_F372,
+    0xA54F_F53A,
+    0x510E_527F,
+    0x9B05_688C,
+    0x1F83_D9AB,
+    0x5BE0_CD19,
+];
+
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// The mixing function, G, which mixes either a column or a diagonal.
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+fn round(state: &mut [u32; 16], m: &[u32; 16]) {
+    // Mix the columns.
+    g(state, 0, 4, 8, 12, m[0], m[1]);
+    g(state, 1, 5, 9, 13, m[2], m[3]);
+    g(state, 2, 6, 10, 14, m[4], m[5]);
+    g(state, 3, 7, 11, 15, m[6], m[7]);
+    // Mix the diagonals.
+    g(state, 0, 5, 10, 15, m[8], m[9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7, 8, 13, m[12], m[13]);
+    g(state, 3, 4, 9, 14, m[14], m[15]);
+}
+
+fn permute(m: &mut [u32; 16]) {
+    let mut permuted = [0; 16];
+    for (dest, src) in permuted.iter_mut().zip(MSG_PERMUTATION) {
+        *dest = m[src];
+    }
+    *m = permuted;
+}
+
+fn compress(
+    chaining_value: &[u32; 8],
+    block_words: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    #[allow(clippy::cast_possible_truncation)]
+    let mut state = [
+        chaining_value[0],
+        chaining_value[1],
+        chaining_value[2],
+        chaining_value[3],
+        chaining_value[4],
+        chaining_value[5],
+        chaining_value[6],
+        chaining_value[7],
+        IV[0],
+        IV[1],
+        IV[2],
+        IV[3],
+        counter as u32,
+        (counter >> 32) as u32,
+        block_len,
+        flags,
+    ];
+    let mut block = *block_words;
+
+    for i in 0..7 {
+        round(&mut state, &block);
+        if i < 6 {
+            permute(&mut block);
+        }
+    }
+
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= chaining_value[i];
+    }
+    state
+}
+
+fn first_8_words(compression_output: [u32; 16]) -> [u32; 8] {
+    let mut words = [0; 8];
+    words.copy_from_slice(&compression_output[0..8]);
+    words
+}
+
+fn words_from_le_bytes(bytes: &[u8], words: &mut [u32]) {
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+}
+
+/// The state needed to produce either a chaining value or the root hash.
+struct Output {
+    input_chaining_value: [u32; 8],
+    block_words: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        first_8_words(compress(
+            &self.input_chaining_value,
+            &self.block_words,
+            self.counter,
+            self.block_len,
+            self.flags,
+        ))
+    }
+
+    fn root_hash(&self) -> [u8; OUT_LEN] {
+        let words = compress(
+            &self.input_chaining_value,
+            &self.block_words,
+            0,
+            self.block_len,
+            self.flags | ROOT,
+        );
+        let mut out = [0; OUT_LEN];
+        for (bytes, word) in out.chunks_exact_mut(4).zip(words) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+}
+
+struct ChunkState {
+    chaining_value: [u32; 8],
+    chunk_counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+}
+
+impl ChunkState {
+    fn new(chunk_counter: u64) -> Self {
+        Self {
+            chaining_value: IV,
+            chunk_counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 {
+            CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    fn block_words(&self) -> [u32; 16] {
+        let mut block_words = [0; 16];
+        words_from_le_bytes(&self.block, &mut block_words);
+        block_words
+    }
+
+    fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // If the block buffer is full, compress it and clear it. More
+            // input is coming, so this compression is not CHUNK_END.
+            if self.block_len == BLOCK_LEN {
+                self.chaining_value = first_8_words(compress(
+                    &self.chaining_value,
+                    &self.block_words(),
+                    self.chunk_counter,
+                    BLOCK_LEN_U32,
+                    self.start_flag(),
+                ));
+                self.blocks_compressed += 1;
+                self.block = [0; BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let want = BLOCK_LEN - self.block_len;
+            let take = want.min(input.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&input[..take]);
+            self.block_len += take;
+            input = &input[take..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        #[allow(clippy::cast_possible_truncation)]
+        Output {
+            input_chaining_value: self.chaining_value,
+            block_words: self.block_words(),
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(left_child_cv: [u32; 8], right_child_cv: [u32; 8]) -> Output {
+    let mut block_words = [0; 16];
+    block_words[..8].copy_from_slice(&left_child_cv);
+    block_words[8..].copy_from_slice(&right_child_cv);
+    Output {
+        input_chaining_value: IV,
+        block_words,
+        counter: 0,
+        block_len: BLOCK_LEN_U32,
+        flags: PARENT,
+    }
+}
+
+/// An incremental BLAKE3 hasher, for hashing input that is not all available at once.
+pub(super) struct Hasher {
+    /// Holds the chaining values of completed subtrees, merged as the tree grows.
+    cv_stack: Vec<[u32; 8]>,
+    chunk_state: ChunkState,
+}
+
+impl Hasher {
+    pub(super) fn new() -> Self {
+        Self {
+            cv_stack: Vec::new(),
+            chunk_state: ChunkState::new(0),
+        }
+    }
+
+    /// Adds `input` to the hashed data.
+    pub(super) fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // If the current chunk is complete, finalize it and reset the chunk state.
+            // More input is coming, so this chunk is not ROOT.
+            if self.chunk_state.len() == CHUNK_LEN {
+                let mut new_cv = self.chunk_state.output().chaining_value();
+                let mut total_chunks = self.chunk_state.chunk_counter + 1;
+                // Merge completed subtrees: one merge for each trailing zero bit
+                // of the total number of chunks so far.
+                while total_chunks & 1 == 0 {
+                    let left = self
+                        .cv_stack
+                        .pop()
+                        .expect("stack should contain a left subtree");
+                    new_cv = parent_output(left, new_cv).chaining_value();
+                    total_chunks >>= 1;
+                }
+                self.cv_stack.push(new_cv);
+                self.chunk_state = ChunkState::new(self.chunk_state.chunk_counter + 1);
+            }
+
+            let want = CHUNK_LEN - self.chunk_state.len();
+            let take = want.min(input.len());
+            self.chunk_state.update(&input[..take]);
+            input = &input[take..];
+        }
+    }
+
+    /// Returns the 32-byte hash of all input given to [`Hasher::update`].
+    pub(super) fn finalize(&self) -> [u8; OUT_LEN] {
+        // Starting with the output of the current chunk, merge every remaining subtree
+        // on the stack to compute the root.
+        let mut output = self.chunk_state.output();
+        for left in self.cv_stack.iter().rev() {
+            output = parent_output(*left, output.chaining_value());
+        }
+        output.root_hash()
+    }
+}
+
+/// Returns the 32-byte BLAKE3 hash of `input`.
+pub(super) fn hash(input: &[u8]) -> [u8; OUT_LEN] {
+    let mut hasher = Hasher::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from the official BLAKE3 repository, where the input is the
+    // repeating byte sequence 0, 1, 2, ..., 250, 0, 1, ... of the given length.
+    const VECTORS: &[(usize, &str)] = &[
+        (
+            0,
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+        ),
+        (
+            1,
+            "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213",
+        ),
+        (
+            1024,
+            "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+        ),
+        (
+            1025,
+            "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+        ),
+        (
+            2048,
+            "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a",
+        ),
+        (
+            2049,
+            "5f4d72f40d7a5f82b15ca2b2e44b1de3c2ef86c426c95c1af0b6879522563030",
+        ),
+    ];
+
+    fn input(len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| u8::try_from(i % 251).expect("value is always less than 251"))
+            .collect()
+    }
+
+    #[test]
+    fn test_official_vectors() {
+        for (len, expected) in VECTORS {
+            assert_eq!(
+                &hex::encode(hash(&input(*len))),
+                expected,
+                "hash of {len} bytes did not match"
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        for (len, expected) in VECTORS {
+            let input = input(*len);
+            // Uneven pieces so updates cross block and chunk boundaries.
+            let mut hasher = Hasher::new();
+            for piece in input.chunks(100) {
+                hasher.update(piece);
+            }
+            assert_eq!(
+                &hex::encode(hasher.finalize()),
+                expected,
+                "incremental hash of {len} bytes did not match"
+            );
+        }
+    }
+}
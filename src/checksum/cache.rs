@@ -0,0 +1,252 @@
+//! A cache of file checksums, used to avoid hashing files that have not changed.
+//!
+//! Each entry records the size and modification time of a file when it was hashed. If both are
+//! the same the next time the file is looked up, the stored checksums are reused instead of
+//! reading the file again. A change that keeps both the size and modification time the same is
+//! not detected, so the cache can be bypassed with `--paranoid`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io};
+
+use super::{Checksum, ChecksumType};
+
+const FILE_NAME: &str = "checksum_cache.json";
+
+/// Files modified this recently are not cached, because a second change within the resolution
+/// of the filesystem's timestamps would not change the modification time.
+const MIN_AGE: Duration = Duration::from_secs(2);
+
+/// The size and modification time of a file, used to tell whether it has changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Stamp {
+    size: u64,
+    modified: SystemTime,
+}
+
+/// The cached checksums of a single file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    #[serde(flatten)]
+    stamp: Stamp,
+    /// Whether line endings were normalized before hashing.
+    normalized: bool,
+    /// Whether the file contained text.
+    pub(crate) is_text: bool,
+    /// The checksums of the file's content.
+    pub(crate) checksums: BTreeMap<ChecksumType, Checksum>,
+}
+
+/// The result of looking up a file in the [`ChecksumCache`].
+#[derive(Debug)]
+pub(crate) enum Lookup {
+    /// The file does not exist.
+    Missing,
+    /// The file has not changed since it was cached.
+    Hit(Entry),
+    /// The file is not cached or has changed since it was cached.
+    ///
+    /// Contains the [`Stamp`] of the file at the time of the lookup, to pass to
+    /// [`ChecksumCache::insert`] once the file has been hashed.
+    Miss(Stamp),
+}
+
+/// A cache of file checksums, keyed by path and stored in the cache directory.
+#[derive(Debug, Default)]
+pub(crate) struct ChecksumCache {
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+}
+
+fn cache_file_path() -> PathBuf {
+    crate::dirs::cache_dir().join(FILE_NAME)
+}
+
+impl ChecksumCache {
+    /// Load the cache from the cache directory.
+    ///
+    /// A missing or unreadable cache file results in an empty cache.
+    ///
+    /// # Errors
+    ///
+    /// Any I/O errors while reading the cache file, other than `NotFound`.
+    #[tracing::instrument(level = "debug")]
+    pub(crate) async fn load() -> io::Result<Self> {
+        let path = cache_file_path();
+        let entries = match fs::read(&path).await {
+            Ok(content) => serde_json::from_slice(&content).unwrap_or_else(|error| {
+                tracing::warn!(
+                    %error,
+                    "ignoring checksum cache at {}: it could not be parsed",
+                    path.display()
+                );
+                HashMap::new()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Self {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Save the cache to the cache directory.
+    ///
+    /// # Errors
+    ///
+    /// Any I/O errors while writing the cache file.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub(crate) async fn save(&self) -> io::Result<()> {
+        let content = serde_json::to_vec(&*self.lock()).map_err(io::Error::other)?;
+        let path = cache_file_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&path, content).await
+    }
+
+    /// Look up the checksums of the file at `path`, hashed with line endings `normalized` or not.
+    ///
+    /// Entries for files that no longer exist are removed.
+    ///
+    /// # Errors
+    ///
+    /// Any I/O errors while reading the file's metadata, other than `NotFound`.
+    pub(crate) async fn get(&self, path: &Path, normalized: bool) -> io::Result<Lookup> {
+        let metadata = match fs::metadata(path).await {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                self.lock().remove(path);
+                return Ok(Lookup::Missing);
+            }
+            Err(error) => return Err(error),
+        };
+
+        let stamp = Stamp {
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        };
+        let lookup = match self.lock().get(path) {
+            Some(entry) if entry.stamp == stamp && entry.normalized == normalized => {
+                tracing::trace!("using cached checksums for {}", path.display());
+                Lookup::Hit(entry.clone())
+            }
+            _ => Lookup::Miss(stamp),
+        };
+        Ok(lookup)
+    }
+
+    /// Record the checksums of the file at `path`, or forget them if `checksums` is `None`.
+    ///
+    /// `stamp` must be taken *before* the file is read, so that a change made while it is being
+    /// read results in a different stamp the next time the file is looked up.
+    pub(crate) fn insert(
+        &self,
+        path: &Path,
+        stamp: Stamp,
+        normalized: bool,
+        is_text: bool,
+        checksums: Option<BTreeMap<ChecksumType, Checksum>>,
+    ) {
+        let is_recent = SystemTime::now()
+            .duration_since(stamp.modified)
+            .map_or(true, |age| age < MIN_AGE);
+
+        match checksums {
+            Some(checksums) if !is_recent => {
+                let entry = Entry {
+                    stamp,
+                    normalized,
+                    is_text,
+                    checksums,
+                };
+                self.lock().insert(path.to_path_buf(), entry);
+            }
+            _ => {
+                self.lock().remove(path);
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, Entry>> {
+        self.entries
+            .lock()
+            .expect("checksum cache lock is poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::SHA256;
+
+    fn checksums(content: &[u8]) -> BTreeMap<ChecksumType, Checksum> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            ChecksumType::SHA256,
+            Checksum::SHA256(SHA256::from_data(content)),
+        );
+        map
+    }
+
+    async fn write_old_file(path: &Path, content: &[u8], modified: SystemTime) {
+        fs::write(path, content).await.unwrap();
+        filetime::set_file_mtime(path, filetime::FileTime::from_system_time(modified)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hit_miss_and_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        let modified = SystemTime::now() - Duration::from_secs(60);
+        let cache = ChecksumCache::default();
+
+        assert!(matches!(
+            cache.get(&path, false).await.unwrap(),
+            Lookup::Missing
+        ));
+
+        write_old_file(&path, b"content", modified).await;
+        let Lookup::Miss(stamp) = cache.get(&path, false).await.unwrap() else {
+            panic!("uncached file should be a cache miss");
+        };
+
+        cache.insert(&path, stamp, false, true, Some(checksums(b"content")));
+        match cache.get(&path, false).await.unwrap() {
+            Lookup::Hit(entry) => assert_eq!(entry.checksums, checksums(b"content")),
+            lookup => panic!("expected a cache hit, got {lookup:?}"),
+        }
+        assert!(matches!(
+            cache.get(&path, true).await.unwrap(),
+            Lookup::Miss(_)
+        ));
+
+        // Changing the size invalidates the entry, even with the same modification time.
+        write_old_file(&path, b"new content", modified).await;
+        assert!(matches!(
+            cache.get(&path, false).await.unwrap(),
+            Lookup::Miss(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recently_modified_files_are_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        fs::write(&path, b"content").await.unwrap();
+
+        let cache = ChecksumCache::default();
+        let Lookup::Miss(stamp) = cache.get(&path, false).await.unwrap() else {
+            panic!("uncached file should be a cache miss");
+        };
+        cache.insert(&path, stamp, false, true, Some(checksums(b"content")));
+        assert!(matches!(
+            cache.get(&path, false).await.unwrap(),
+            Lookup::Miss(_)
+        ));
+    }
+}
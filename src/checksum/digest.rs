@@ -16,6 +16,12 @@ use thiserror::Error;
 pub type MD5 = Digest<md5::Md5>;
 /// Digest definition for SHA256.
 pub type SHA256 = Digest<sha2::Sha256>;
+/// Digest definition for BLAKE3.
+pub type BLAKE3 = Digest<Blake3>;
+
+/// Marker type for the BLAKE3 hash function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blake3;
 
 fn checksum_to_string<H>(data: H) -> String
 where
@@ -30,6 +36,8 @@ mod sealed {
     impl Sealed for md5::Md5 {}
 
     impl Sealed for sha2::Sha256 {}
+
+    impl Sealed for super::Blake3 {}
 }
 
 pub trait Digestable: sealed::Sealed {
@@ -68,6 +76,14 @@ impl Digestable for sha2::Sha256 {
     }
 }
 
+impl Digestable for Blake3 {
+    type OutputSize = digest::typenum::U32;
+    const DIGEST_NAME: &'static str = "BLAKE3";
+    fn digest_to_array<D: AsRef<[u8]>>(data: D) -> GenericArray<u8, Self::OutputSize> {
+        GenericArray::from(super::blake3::hash(data.as_ref()))
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("invalid checksum string: {0}")]
@@ -119,7 +135,7 @@ where
     <<T as Digestable>::OutputSize as Add>::Output: ArrayLength<u8>,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.0.cmp(&other.0))
+        Some(self.cmp(other))
     }
 }
 
@@ -216,6 +232,11 @@ where
     {
         Self(T::digest_to_array(data))
     }
+
+    /// Wrap an already computed digest.
+    pub(super) fn from_array(array: GenericArray<u8, T::OutputSize>) -> Self {
+        Self(array)
+    }
 }
 
 #[cfg(test)]
@@ -310,6 +331,25 @@ mod tests {
         }
     }
 
+    mod blake3 {
+        use super::*;
+
+        const BLAKE3_STR: &str = "61cc98e42ded96807806bf1620e13c4e6a1b85068cad93382a2e3107c269aefe";
+
+        #[test]
+        fn test_blake3_digest_to_string() {
+            let result = Blake3::digest_to_string(DATA);
+            assert_eq!(&result, BLAKE3_STR);
+        }
+
+        #[test]
+        fn test_blake3_digest_from_str() {
+            let expected = Blake3::digest_to_array(DATA);
+            let result = Blake3::digest_from_str(BLAKE3_STR).unwrap();
+            assert_eq!(expected, result);
+        }
+    }
+
     mod digest {
         use ::md5::Md5;
         use serde_test::{assert_tokens, Token};
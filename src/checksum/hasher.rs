@@ -0,0 +1,41 @@
+//! Incremental hashing for every [`ChecksumType`].
+
+use md5::Digest as _;
+
+use super::{blake3, Checksum, ChecksumType, BLAKE3, MD5, SHA256};
+
+/// Computes a [`Checksum`] of data given in pieces, so large files do not need to be read into
+/// memory all at once.
+pub(super) enum Hasher {
+    MD5(md5::Md5),
+    SHA256(sha2::Sha256),
+    BLAKE3(blake3::Hasher),
+}
+
+impl Hasher {
+    pub(super) fn new(typ: ChecksumType) -> Self {
+        match typ {
+            ChecksumType::MD5 => Self::MD5(md5::Md5::new()),
+            ChecksumType::SHA256 => Self::SHA256(sha2::Sha256::new()),
+            ChecksumType::BLAKE3 => Self::BLAKE3(blake3::Hasher::new()),
+        }
+    }
+
+    /// Adds `data` to the hashed content.
+    pub(super) fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::MD5(hasher) => hasher.update(data),
+            Self::SHA256(hasher) => hasher.update(data),
+            Self::BLAKE3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the checksum of all data given to [`Hasher::update`].
+    pub(super) fn finalize(self) -> Checksum {
+        match self {
+            Self::MD5(hasher) => Checksum::MD5(MD5::from_array(hasher.finalize())),
+            Self::SHA256(hasher) => Checksum::SHA256(SHA256::from_array(hasher.finalize())),
+            Self::BLAKE3(hasher) => Checksum::BLAKE3(BLAKE3::from_array(hasher.finalize().into())),
+        }
+    }
+}
@@ -2,24 +2,31 @@
 //! Module for handling checksums.
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::io::{self, Read};
+use std::num::NonZeroUsize;
+use std::path::Path;
+mod blake3;
+pub(crate) mod cache;
 mod digest;
+mod hasher;
 
-pub use self::digest::{MD5, SHA256};
+use self::hasher::Hasher;
+
+pub use self::digest::{BLAKE3, MD5, SHA256};
 
 /// The types of checksums supported by Hoard.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Serialize, Deserialize)]
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Serialize, Deserialize,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum ChecksumType {
     /// MD5 checksum -- provided for backwards compatibility with older versions of Hoard.
     MD5,
     /// SHA256 checksum -- currently the default.
+    #[default]
     SHA256,
-}
-
-impl Default for ChecksumType {
-    fn default() -> Self {
-        Self::SHA256
-    }
+    /// BLAKE3 checksum -- a newer, faster alternative to SHA256.
+    BLAKE3,
 }
 
 /// A file's checksum as a human-readable string.
@@ -27,7 +34,7 @@ impl Default for ChecksumType {
 /// If you have a choice of which variant to construct,
 /// prefer using [`HoardItem::system_checksum`](crate::hoard_item::HoardItem::system_checksum)
 /// or [`HoardItem::hoard_checksum`](crate::hoard_item::HoardItem::system_checksum) with the
-/// return value of [`ChecksumType::default()`]
+/// return value of [`ChecksumType::default()`], or [`Checksum::compute`] for any other file.
 ///
 /// # TODO
 ///
@@ -39,6 +46,8 @@ pub enum Checksum {
     MD5(MD5),
     /// A SHA256 checksum -- currently the default.
     SHA256(SHA256),
+    /// A BLAKE3 checksum.
+    BLAKE3(BLAKE3),
 }
 
 impl Checksum {
@@ -56,8 +65,56 @@ impl Checksum {
         match self {
             Self::MD5(_) => ChecksumType::MD5,
             Self::SHA256(_) => ChecksumType::SHA256,
+            Self::BLAKE3(_) => ChecksumType::BLAKE3,
         }
     }
+
+    /// Computes the [`ChecksumType`] checksum of `data`.
+    ///
+    /// ```
+    /// # use hoard::checksum::{Checksum, ChecksumType};
+    /// let checksum = Checksum::from_data(ChecksumType::MD5, b"testing");
+    /// assert_eq!(checksum.to_string(), "md5(ae2b1fca515949e5d54fb22b8ed95575)");
+    /// ```
+    #[must_use]
+    pub fn from_data(typ: ChecksumType, data: &[u8]) -> Self {
+        let mut hasher = Hasher::new(typ);
+        hasher.update(data);
+        hasher.finalize()
+    }
+
+    /// Computes the [`ChecksumType`] checksum of the file at `path`.
+    ///
+    /// The file is read in fixed-size chunks on a blocking thread, so files of any size can be
+    /// hashed without reading them into memory.
+    ///
+    /// # Errors
+    ///
+    /// Any error opening or reading the file, including if it does not exist or is a directory.
+    #[tracing::instrument(name = "checksum_compute")]
+    pub async fn compute(path: &Path, typ: ChecksumType) -> io::Result<Self> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::compute_blocking(&path, typ))
+            .await
+            .expect("hashing thread should never panic")
+    }
+
+    fn compute_blocking(path: &Path, typ: ChecksumType) -> io::Result<Self> {
+        const CHUNK_SIZE: usize = 64 * 1024;
+
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = Hasher::new(typ);
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => hasher.update(&buffer[..len]),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(hasher.finalize())
+    }
 }
 
 impl fmt::Display for Checksum {
@@ -65,10 +122,62 @@ impl fmt::Display for Checksum {
         match self {
             Self::MD5(md5) => write!(f, "md5({md5})"),
             Self::SHA256(sha256) => write!(f, "sha256({sha256})"),
+            Self::BLAKE3(blake3) => write!(f, "blake3({blake3})"),
+        }
+    }
+}
+
+/// The default number of files a hoard must contain before its files are hashed in parallel.
+pub const DEFAULT_PARALLEL_THRESHOLD: usize = 64;
+
+/// Options for hashing the files of a hoard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashOptions {
+    /// The number of files a hoard must contain before its files are hashed in parallel.
+    ///
+    /// Hashing a small number of files in parallel costs more than it saves.
+    pub parallel_threshold: usize,
+    /// The maximum number of files to hash at once.
+    ///
+    /// If `None`, hashing uses the available parallelism of the system.
+    pub max_jobs: Option<NonZeroUsize>,
+    /// Reuse the cached checksums of files whose size and modification time have not changed
+    /// since they were last hashed.
+    ///
+    /// See [`cache`] for what this can miss.
+    pub use_cache: bool,
+}
+
+impl Default for HashOptions {
+    fn default() -> Self {
+        Self {
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            max_jobs: None,
+            use_cache: false,
         }
     }
 }
 
+impl HashOptions {
+    /// Returns how many files to hash at once when hashing `file_count` files.
+    ///
+    /// This is `1` if `file_count` is below [`parallel_threshold`](Self::parallel_threshold),
+    /// and [`max_jobs`](Self::max_jobs) otherwise.
+    pub(crate) fn concurrency(self, file_count: usize) -> usize {
+        concurrency_for(file_count, self.parallel_threshold, self.max_jobs)
+    }
+}
+
+fn concurrency_for(file_count: usize, threshold: usize, max_jobs: Option<NonZeroUsize>) -> usize {
+    if file_count < threshold {
+        1
+    } else {
+        max_jobs
+            .or_else(|| std::thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -81,6 +190,9 @@ mod tests {
         let md5sum = "ae2b1fca515949e5d54fb22b8ed95575";
         let checksum = Checksum::MD5(md5sum.parse().unwrap());
         assert_eq!(format!("md5({md5sum})"), checksum.to_string());
+        let blake3sum = "61cc98e42ded96807806bf1620e13c4e6a1b85068cad93382a2e3107c269aefe";
+        let checksum = Checksum::BLAKE3(blake3sum.parse().unwrap());
+        assert_eq!(format!("blake3({blake3sum})"), checksum.to_string());
     }
 
     #[test]
@@ -98,5 +210,98 @@ mod tests {
             )
             .typ()
         );
+        assert_eq!(
+            ChecksumType::BLAKE3,
+            Checksum::BLAKE3(
+                "61cc98e42ded96807806bf1620e13c4e6a1b85068cad93382a2e3107c269aefe"
+                    .parse()
+                    .unwrap()
+            )
+            .typ()
+        );
+    }
+
+    const ALL_TYPES: [ChecksumType; 3] = [
+        ChecksumType::MD5,
+        ChecksumType::SHA256,
+        ChecksumType::BLAKE3,
+    ];
+
+    #[test]
+    fn test_from_data_matches_digests() {
+        let data = b"testing";
+        assert_eq!(
+            Checksum::from_data(ChecksumType::MD5, data),
+            Checksum::MD5(MD5::from_data(data))
+        );
+        assert_eq!(
+            Checksum::from_data(ChecksumType::SHA256, data),
+            Checksum::SHA256(SHA256::from_data(data))
+        );
+        assert_eq!(
+            Checksum::from_data(ChecksumType::BLAKE3, data),
+            Checksum::BLAKE3(BLAKE3::from_data(data))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compute_empty_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty");
+        std::fs::write(&path, b"").unwrap();
+        for typ in ALL_TYPES {
+            let checksum = Checksum::compute(&path, typ).await.unwrap();
+            assert_eq!(checksum, Checksum::from_data(typ, b""));
+            assert_eq!(checksum.typ(), typ);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compute_file_larger_than_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("large");
+        // Several chunks, ending partway through one.
+        let data: Vec<u8> = (0..1_000_003_u32)
+            .map(|i| u8::try_from(i % 251).expect("value is always less than 251"))
+            .collect();
+        std::fs::write(&path, &data).unwrap();
+        for typ in ALL_TYPES {
+            assert_eq!(
+                Checksum::compute(&path, typ).await.unwrap(),
+                Checksum::from_data(typ, &data)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compute_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = Checksum::compute(&dir.path().join("missing"), ChecksumType::SHA256)
+            .await
+            .expect_err("hashing a missing file should fail");
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_hash_concurrency_threshold() {
+        assert_eq!(
+            concurrency_for(3, 4, None),
+            1,
+            "small hoards should hash serially"
+        );
+        let parallelism = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        assert_eq!(concurrency_for(4, 4, None), parallelism);
+        assert_eq!(concurrency_for(100, 4, None), parallelism);
+    }
+
+    #[test]
+    fn test_hash_concurrency_max_jobs() {
+        let jobs = NonZeroUsize::new(3);
+        assert_eq!(
+            concurrency_for(3, 4, jobs),
+            1,
+            "small hoards should hash serially"
+        );
+        assert_eq!(concurrency_for(100, 4, jobs), 3);
     }
 }
@@ -235,6 +235,7 @@ mod tests {
     #[serde(transparent)]
     struct Tester(bool);
 
+    #[allow(clippy::infallible_try_from)]
     impl TryFrom<Tester> for bool {
         type Error = Infallible;
 
@@ -1,19 +1,36 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::ffi::OsString;
+use std::future::Future;
+use std::num::NonZeroUsize;
 use std::path::{Component, Path, PathBuf};
 
+use filetime::FileTime;
+use futures::{StreamExt, TryStreamExt};
+use itertools::Itertools;
 use tap::TapFallible;
 //use std::path::{Path, PathBuf};
 use thiserror::Error;
-use tokio::fs;
+use time::OffsetDateTime;
+use tokio::{fs, io};
 
-use crate::checkers::history::operation::ItemOperation;
+use crate::checkers::history::operation::{ItemOperation, Operation};
 use crate::checkers::{history::operation::OperationImpl, Checkers, Error as ConsistencyError};
-use crate::hoard::iter::Error as IterError;
-use crate::hoard::pile_config::Permissions;
-use crate::hoard::{Direction, Hoard};
+use crate::checksum::{Checksum, ChecksumType, HashOptions};
+use crate::hoard::hooks::Hooks;
+use crate::hoard::iter::{DiffSource, Error as IterError, ScanOptions};
+use crate::hoard::pile_config::{LockedFileAction, LockedFilePolicy, Permissions};
+use crate::hoard::skipped::{Skipped, SkippedPaths};
+use crate::hoard::sources::{Error as SourcesError, Sources};
+use crate::hoard::{Direction, Hoard, PileConfig};
 use crate::hoard_item::HoardItem;
-use crate::newtypes::HoardName;
+use crate::newtypes::{HoardName, PileName};
 use crate::paths::{HoardPath, RelativePath, SystemPath};
+use crate::progress::Progress;
+use crate::retry::{retry, RetryPolicy};
+
+use super::status::hoard_diff_source;
+use super::summary::Summary;
 
 /// Errors that may occur while backing up or restoring hoards.
 #[derive(Debug, Error)]
@@ -23,10 +40,142 @@ pub enum Error {
     Consistency(#[from] ConsistencyError),
     /// An I/O error occurred.
     #[error("I/O error: {0}")]
-    IO(#[from] tokio::io::Error),
+    IO(#[from] io::Error),
     /// An error while iterating files to modify.
     #[error("failed to iterate files: {0}")]
     Iterator(#[from] IterError),
+    /// Error while determining which path of a pile a file belongs to.
+    #[error("{0}")]
+    Sources(#[from] SourcesError),
+    /// Safe mode is enabled and the hoard was changed outside of Hoard.
+    #[error(
+        "hoard {0} has unexpected changes -- inspect them with `hoard diff {0}` or pass --force"
+    )]
+    UnexpectedChanges(HoardName),
+    /// The pre hook failed for one or more hoards, which were skipped.
+    #[error("skipped hoard(s) whose pre hook failed: {}", .0.iter().join(", "))]
+    PreHook(Vec<HoardName>),
+    /// Files were selected with `--only`, but none of them exist in the system or the hoards.
+    #[error("no files matched the patterns given with --only")]
+    NothingSelected,
+    /// A restored file does not match the checksum recorded for it.
+    #[error(
+        "restored file {} does not match its recorded checksum (expected {expected}, found {})",
+        path.display(),
+        found.as_ref().map_or_else(|| String::from("no file"), ToString::to_string)
+    )]
+    VerificationFailed {
+        /// The path to the restored file.
+        path: PathBuf,
+        /// The checksum recorded by the operation log.
+        expected: Checksum,
+        /// The checksum of the file after it was restored, if it exists.
+        found: Option<Checksum>,
+    },
+    /// The filesystem at `path` does not have enough free space for the files to be written.
+    #[error(
+        "not enough free space in {}: {required} bytes are needed but only {available} are available -- free up space or pass --force",
+        path.display()
+    )]
+    InsufficientSpace {
+        /// A path on the filesystem that would be written to.
+        path: PathBuf,
+        /// The total size of the files to be written, in bytes.
+        required: u64,
+        /// The free space available on the filesystem, in bytes.
+        available: u64,
+    },
+}
+
+/// Options that modify how files are backed up or restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct Options {
+    /// Skip consistency checks.
+    pub(crate) force: bool,
+    /// Copy extended attributes along with file contents.
+    pub(crate) copy_attributes: bool,
+    /// Remove directories left empty after deleting files.
+    pub(crate) prune_empty_dirs: bool,
+    /// The maximum number of files to restore concurrently.
+    pub(crate) jobs: NonZeroUsize,
+    /// Delete system files once they are safely stored in the hoard after a backup.
+    pub(crate) move_files: bool,
+    /// Warn about backed up files larger than this many bytes.
+    pub(crate) max_file_size_warn: Option<u64>,
+    /// Only restore files that do not exist on the system, skipping all other changes.
+    pub(crate) missing_only: bool,
+    /// Skip backing up hoards without changes, writing no files or operation log for them.
+    pub(crate) if_changed: bool,
+    /// Refuse to touch hoards with unexpected changes unless `force` is also set.
+    pub(crate) safe: bool,
+    /// Do not reapply the file permissions recorded during the last backup when restoring.
+    pub(crate) no_permissions: bool,
+    /// Do not reapply the modification times recorded during the last backup when restoring.
+    pub(crate) no_mtime: bool,
+    /// Do not warn about files in the same pile whose paths only differ by case.
+    pub(crate) case_sensitive: bool,
+    /// Only log what would change, without writing any files or operation logs.
+    pub(crate) dry_run: bool,
+    /// Do not run the pre and post hooks configured for each hoard.
+    pub(crate) no_hooks: bool,
+    /// Do not show a progress bar while applying file operations.
+    pub(crate) quiet: bool,
+    /// Fail if no files were selected, because `--only` was given.
+    pub(crate) require_selection: bool,
+    /// Fail instead of skipping files and directories that cannot be read.
+    pub(crate) strict: bool,
+    /// How to hash the files to back up or restore.
+    pub(crate) hashing: HashOptions,
+    /// How often to retry reading or writing a file that failed with a transient error.
+    pub(crate) retry: RetryPolicy,
+    /// How many seconds an operation log may appear to be ahead of the local clock.
+    pub(crate) max_clock_skew: u64,
+}
+
+impl Options {
+    /// Returns the [`ScanOptions`] for finding the files to operate on.
+    pub(crate) fn scan_options(self) -> ScanOptions {
+        ScanOptions {
+            skipped: SkippedPaths::new(self.strict),
+            hashing: self.hashing,
+            retry: self.retry,
+            max_clock_skew: self.max_clock_skew,
+        }
+    }
+}
+
+/// The file operations of a backup or restore, by hoard.
+///
+/// Every file that was considered is listed, including files that were left unchanged
+/// ([`ItemOperation::Nothing`]). For a dry run, these are the operations that would have been
+/// performed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The operations for each hoard that was backed up or restored.
+    pub hoards: BTreeMap<HoardName, Vec<ItemOperation<HoardItem>>>,
+    /// The files and directories that could not be read and were left out.
+    pub skipped: Vec<Skipped>,
+    /// How many files were changed in each hoard, and how much data was copied.
+    pub summaries: BTreeMap<HoardName, Summary>,
+}
+
+impl Report {
+    /// Returns an iterator over the operations that changed a file, skipping files that were
+    /// left unchanged or do not exist.
+    pub fn changes(&self) -> impl Iterator<Item = (&HoardName, &ItemOperation<HoardItem>)> {
+        self.hoards.iter().flat_map(|(name, operations)| {
+            operations
+                .iter()
+                .filter(|operation| {
+                    !matches!(
+                        operation,
+                        ItemOperation::Nothing(_) | ItemOperation::DoesNotExist(_)
+                    )
+                })
+                .map(move |operation| (name, operation))
+        })
+    }
 }
 
 #[allow(single_use_lifetimes)]
@@ -34,9 +183,10 @@ pub enum Error {
 pub(crate) async fn run_backup<'a>(
     hoards_root: &HoardPath,
     hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)> + Clone,
-    force: bool,
-) -> Result<(), super::Error> {
-    backup_or_restore(hoards_root, Direction::Backup, hoards, force)
+    hooks: &BTreeMap<HoardName, Hooks>,
+    options: Options,
+) -> Result<Report, super::Error> {
+    backup_or_restore(hoards_root, Direction::Backup, hoards, hooks, options)
         .await
         .map_err(super::Error::Backup)
 }
@@ -46,9 +196,10 @@ pub(crate) async fn run_backup<'a>(
 pub(crate) async fn run_restore<'a>(
     hoards_root: &HoardPath,
     hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)> + Clone,
-    force: bool,
-) -> Result<(), super::Error> {
-    backup_or_restore(hoards_root, Direction::Restore, hoards, force)
+    hooks: &BTreeMap<HoardName, Hooks>,
+    options: Options,
+) -> Result<Report, super::Error> {
+    backup_or_restore(hoards_root, Direction::Restore, hoards, hooks, options)
         .await
         .map_err(super::Error::Restore)
 }
@@ -170,9 +321,56 @@ async fn create_all_with_perms(
     Ok(())
 }
 
-#[tracing::instrument(fields(file = ?file.system_path()))]
-async fn copy_file(file: &HoardItem, direction: Direction) -> Result<(), Error> {
-    let (src, dest, dest_root) = match direction {
+/// Copies all extended attributes from `src` to `dest`.
+///
+/// Failures are logged but otherwise ignored, as not all filesystems support extended attributes.
+#[cfg(unix)]
+fn copy_attributes(src: &Path, dest: &Path) {
+    let attrs = match xattr::list(src) {
+        Ok(attrs) => attrs,
+        Err(error) => {
+            tracing::warn!(%error, "failed to list extended attributes of {}", src.display());
+            return;
+        }
+    };
+
+    for attr in attrs {
+        match xattr::get(src, &attr) {
+            Ok(Some(value)) => {
+                tracing::trace!(?attr, "copying extended attribute");
+                if let Err(error) = xattr::set(dest, &attr, &value) {
+                    tracing::warn!(
+                        %error,
+                        ?attr,
+                        "failed to set extended attribute on {}",
+                        dest.display()
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::warn!(
+                    %error,
+                    ?attr,
+                    "failed to read extended attribute of {}",
+                    src.display()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_attributes(src: &Path, _dest: &Path) {
+    tracing::debug!(
+        "copying extended attributes is not supported on this platform, skipping {}",
+        src.display()
+    );
+}
+
+/// Returns the source path, destination path, and destination pile root for copying `file`.
+pub(super) fn copy_paths(file: &HoardItem, direction: Direction) -> (&Path, &Path, &Path) {
+    match direction {
         Direction::Backup => (
             file.system_path().as_ref(),
             file.hoard_path().as_ref(),
@@ -183,7 +381,12 @@ async fn copy_file(file: &HoardItem, direction: Direction) -> Result<(), Error>
             file.system_path().as_ref(),
             file.system_prefix().as_ref(),
         ),
-    };
+    }
+}
+
+/// Creates all missing parent directories of the destination of `file`.
+async fn create_parent_dirs(file: &HoardItem, direction: Direction) -> Result<(), Error> {
+    let (_, dest, dest_root) = copy_paths(file, direction);
     if let Some(parent) = dest.parent() {
         tracing::trace!(?parent, "ensuring parent dirs");
         // Handle cases where pile == file and prefix == dest path
@@ -194,24 +397,175 @@ async fn copy_file(file: &HoardItem, direction: Direction) -> Result<(), Error>
         };
         create_all_with_perms(root, parent, Permissions::folder_default()).await?;
     }
+
+    Ok(())
+}
+
+/// Returns the path of the temporary file used to write `dest` atomically.
+///
+/// The temporary file is in the same directory as `dest` so that it can be renamed into place.
+fn temp_path_for(dest: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(dest.file_name().unwrap_or_default());
+    name.push(format!(".hoard-{}.tmp", std::process::id()));
+    dest.with_file_name(name)
+}
+
+/// Replaces `dest` with the temporary file at `temp`, keeping the permissions of `dest` if it
+/// already exists.
+async fn replace_with_temp(temp: &Path, dest: &Path, policy: RetryPolicy) -> io::Result<()> {
+    if let Ok(metadata) = fs::metadata(dest).await {
+        fs::set_permissions(temp, metadata.permissions()).await?;
+        // Windows refuses to replace a read-only file.
+        #[cfg(windows)]
+        if metadata.permissions().readonly() {
+            let mut perms = metadata.permissions();
+            #[allow(clippy::permissions_set_readonly_false)]
+            perms.set_readonly(false);
+            fs::set_permissions(dest, perms).await?;
+        }
+    }
+
+    // Renaming over an existing file replaces it on all supported platforms.
+    retry(dest, policy, || fs::rename(temp, dest)).await
+}
+
+/// Writes `dest` by calling `write` with a temporary file in the same directory and then renaming
+/// that file into place. If anything fails, `dest` is left untouched and the temporary file is
+/// removed, so `dest` always contains either its old or its new content.
+async fn write_atomically<F, Fut>(dest: &Path, policy: RetryPolicy, write: F) -> Result<(), Error>
+where
+    F: FnOnce(PathBuf) -> Fut,
+    Fut: Future<Output = io::Result<()>>,
+{
+    let temp = temp_path_for(dest);
+    let result = match write(temp.clone()).await {
+        Ok(()) => replace_with_temp(&temp, dest, policy).await,
+        Err(error) => Err(error),
+    };
+
+    if let Err(error) = result {
+        tracing::error!(%error, "failed to write {}", dest.display());
+        if let Err(error) = fs::remove_file(&temp).await {
+            if error.kind() != io::ErrorKind::NotFound {
+                tracing::warn!(%error, "failed to remove temporary file {}", temp.display());
+            }
+        }
+        return Err(error.into());
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(fields(file = ?file.system_path()))]
+pub(super) async fn copy_file(
+    file: &HoardItem,
+    direction: Direction,
+    with_attributes: bool,
+    policy: RetryPolicy,
+) -> Result<(), Error> {
+    let (src, dest, _) = copy_paths(file, direction);
+    create_parent_dirs(file, direction).await?;
     tracing::debug!("copying {} to {}", src.display(), dest.display());
-    fs::copy(src, dest).await.tap_err(|error| {
-        tracing::error!(
-            %error,
-            "failed to copy {} to {}",
-            src.display(),
-            dest.display(),
-        );
-    })?;
+    match direction {
+        Direction::Backup => {
+            retry(src, policy, || fs::copy(src, dest))
+                .await
+                .tap_err(|error| {
+                    tracing::error!(
+                        %error,
+                        "failed to copy {} to {}",
+                        src.display(),
+                        dest.display(),
+                    );
+                })?;
+
+            if with_attributes {
+                copy_attributes(src, dest);
+            }
+        }
+        Direction::Restore => {
+            write_atomically(dest, policy, |temp| async move {
+                retry(src, policy, || fs::copy(src, &temp))
+                    .await
+                    .tap_err(|error| {
+                        tracing::error!(
+                            %error,
+                            "failed to copy {} to {}",
+                            src.display(),
+                            temp.display(),
+                        );
+                    })?;
+
+                if with_attributes {
+                    copy_attributes(src, &temp);
+                }
+
+                Ok(())
+            })
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes each empty parent directory of `path`, stopping at (and never removing) `root`.
+#[tracing::instrument(level = "debug")]
+async fn prune_empty_parents(root: &Path, path: &Path) -> Result<(), Error> {
+    let mut current = path.parent();
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+
+        let is_empty = match fs::read_dir(dir).await {
+            Ok(mut entries) => entries.next_entry().await?.is_none(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => break,
+            Err(error) => {
+                tracing::error!(%error, "failed to read directory {}", dir.display());
+                return Err(error.into());
+            }
+        };
+        if !is_empty {
+            break;
+        }
+
+        tracing::debug!("removing empty directory {}", dir.display());
+        fs::remove_dir(dir).await.tap_err(|error| {
+            tracing::error!(%error, "failed to remove empty directory {}", dir.display());
+        })?;
+        current = dir.parent();
+    }
 
     Ok(())
 }
 
-#[tracing::instrument(skip(hoard))]
-async fn fix_permissions(
+/// Returns the most recent backup of the hoard, which holds the permissions and modification
+/// times to reapply when restoring, or `None` if `skip` is set.
+pub(super) async fn recorded_metadata(
+    hoard_name: &HoardName,
+    skip: bool,
+) -> Result<Option<Operation>, Error> {
+    if skip {
+        return Ok(None);
+    }
+
+    Operation::latest_backup(hoard_name)
+        .await
+        .map_err(|error| Error::Consistency(ConsistencyError::Operation(error)))
+}
+
+/// Sets the permissions of `operation`'s file and its parent directories.
+///
+/// When restoring, a file's permissions are taken from the pile configuration if set, otherwise
+/// from `recorded` if it has permissions for the file, otherwise the default.
+#[tracing::instrument(skip(hoard, recorded))]
+pub(super) async fn fix_permissions(
     hoard: &Hoard,
     operation: &ItemOperation<HoardItem>,
     direction: Direction,
+    recorded: Option<&Operation>,
 ) -> Result<(), Error> {
     // Set permissions if file exists, regardless of if it was modified.
     if let ItemOperation::Create(file)
@@ -229,6 +583,11 @@ async fn fix_permissions(
                 let file_perms = pile
                     .config
                     .file_permissions
+                    .or_else(|| {
+                        recorded.and_then(|operation| {
+                            operation.permissions_for(file.pile_name(), file.relative_path())
+                        })
+                    })
                     .unwrap_or_else(Permissions::file_default);
                 let dir_perms = pile
                     .config
@@ -254,20 +613,685 @@ async fn fix_permissions(
     Ok(())
 }
 
+/// Sets the modification time of the restored system copy of `file` to the one in `recorded`.
+///
+/// Files without a recorded modification time, or with one from before the Unix epoch, keep the
+/// time they were restored at. Failures are logged but otherwise ignored.
+pub(super) fn restore_mtime(file: &HoardItem, recorded: &Operation) {
+    let Some(mtime) = recorded.mtime_for(file.pile_name(), file.relative_path()) else {
+        return;
+    };
+    if mtime < OffsetDateTime::UNIX_EPOCH {
+        tracing::warn!(
+            "not restoring modification time of {}: {} is before the Unix epoch",
+            file.system_path().display(),
+            mtime
+        );
+        return;
+    }
+
+    let mtime = FileTime::from_unix_time(mtime.unix_timestamp(), mtime.nanosecond());
+    tracing::debug!(%mtime, "setting modification time");
+    if let Err(error) = filetime::set_file_mtime(file.system_path(), mtime) {
+        tracing::warn!(
+            %error,
+            "failed to set modification time of {}",
+            file.system_path().display()
+        );
+    }
+}
+
+/// Logs a warning for each file in the same pile as another whose path only differs by case, as
+/// the two overwrite each other on case-insensitive filesystems.
+fn warn_case_collisions(hoard_name: &HoardName, operations: &[ItemOperation<HoardItem>]) {
+    let mut seen: HashMap<(&PileName, String), &HoardItem> = HashMap::new();
+    for operation in operations {
+        let (ItemOperation::Create(file)
+        | ItemOperation::Modify(file)
+        | ItemOperation::Nothing(file)) = operation
+        else {
+            continue;
+        };
+        let folded = file
+            .relative_path()
+            .to_path_buf()
+            .to_string_lossy()
+            .to_lowercase();
+        match seen.entry((file.pile_name(), folded)) {
+            Entry::Occupied(other) => tracing::warn!(
+                "{}: {} and {} only differ by case, so one will overwrite the other on a \
+                 case-insensitive filesystem",
+                hoard_name,
+                other.get().system_path().display(),
+                file.system_path().display()
+            ),
+            Entry::Vacant(entry) => {
+                entry.insert(file);
+            }
+        }
+    }
+}
+
+/// Logs a warning if the system copy of `file` is larger than `max_size` bytes.
+async fn warn_if_too_large(file: &HoardItem, max_size: u64) {
+    match fs::metadata(file.system_path()).await {
+        Ok(metadata) if metadata.len() > max_size => tracing::warn!(
+            "{} is {} bytes, which is larger than the warning threshold of {} bytes",
+            file.system_path().display(),
+            metadata.len(),
+            max_size
+        ),
+        Ok(_) => {}
+        Err(error) => tracing::debug!(
+            %error,
+            "failed to read metadata of {}",
+            file.system_path().display()
+        ),
+    }
+}
+
+/// Copies `file` into the hoard, handling files locked by another process according to `policy`.
+///
+/// Returns whether the file was copied.
+async fn backup_file(
+    file: &HoardItem,
+    with_attributes: bool,
+    policy: LockedFilePolicy,
+    retry_policy: RetryPolicy,
+) -> Result<bool, Error> {
+    let mut attempts = 0;
+    loop {
+        match copy_file(file, Direction::Backup, with_attributes, retry_policy).await {
+            Ok(()) => return Ok(true),
+            Err(Error::IO(error)) => {
+                match policy
+                    .handle_error(file.system_path(), &error, &mut attempts)
+                    .await
+                {
+                    LockedFileAction::Skip => return Ok(false),
+                    LockedFileAction::Retry => {}
+                    LockedFileAction::Fail => return Err(Error::IO(error)),
+                }
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Returns whether `operation` creates a file that does not exist on the system. Used to skip
+/// every other operation when restoring with `missing_only`.
+fn is_missing_locally(operation: &ItemOperation<HoardItem>) -> bool {
+    match operation {
+        ItemOperation::Create(file) if !file.system_path().exists() => true,
+        ItemOperation::Create(file)
+        | ItemOperation::Modify(file)
+        | ItemOperation::Delete(file)
+        | ItemOperation::Nothing(file) => {
+            tracing::debug!(
+                "skipping {}: it already exists locally",
+                file.system_path().display()
+            );
+            false
+        }
+        ItemOperation::DoesNotExist(_) => false,
+    }
+}
+
+/// Performs a single file operation, returning the pile root and path of the file if it was
+/// deleted and empty directories should be pruned.
+async fn apply_operation(
+    hoard: &Hoard,
+    operation: ItemOperation<HoardItem>,
+    direction: Direction,
+    options: Options,
+    recorded: Option<&Operation>,
+    expected: Option<Checksum>,
+) -> Result<Option<(PathBuf, PathBuf)>, Error> {
+    let mut deleted = None;
+    match &operation {
+        ItemOperation::Create(file) | ItemOperation::Modify(file) => {
+            let pile_config = hoard.get_pile(file.pile_name()).map(|pile| &pile.config);
+            let with_attributes = options.copy_attributes
+                || pile_config
+                    .and_then(|config| config.copy_attributes)
+                    .unwrap_or(false);
+            match direction {
+                Direction::Backup => {
+                    if let Some(max_size) = options.max_file_size_warn {
+                        warn_if_too_large(file, max_size).await;
+                    }
+                    let policy = pile_config
+                        .and_then(|config| config.locked_files)
+                        .unwrap_or_default();
+                    if !backup_file(file, with_attributes, policy, options.retry).await? {
+                        return Ok(None);
+                    }
+                }
+                Direction::Restore => {
+                    copy_file(file, direction, with_attributes, options.retry).await?;
+                    if let Some(expected) = expected {
+                        verify_restored_file(file, expected, pile_config).await?;
+                    }
+                }
+            }
+        }
+        ItemOperation::Delete(file) => {
+            let (to_remove, root): (&Path, &Path) = match direction {
+                Direction::Backup => (file.hoard_path().as_ref(), file.hoard_prefix().as_ref()),
+                Direction::Restore => (file.system_path().as_ref(), file.system_prefix().as_ref()),
+            };
+            if to_remove.exists() {
+                tracing::debug!("deleting {}", to_remove.display());
+                fs::remove_file(to_remove).await.tap_err(|error| {
+                    tracing::error!(%error, "failed to delete {}", to_remove.display());
+                })?;
+            }
+            if options.prune_empty_dirs {
+                deleted = Some((root.to_path_buf(), to_remove.to_path_buf()));
+            }
+        }
+        ItemOperation::Nothing(file) => {
+            tracing::debug!("file {} is unchanged", file.system_path().display());
+        }
+        ItemOperation::DoesNotExist(file) => {
+            tracing::trace!("file {} does not exist", file.system_path().display());
+        }
+    }
+
+    let recorded_perms = recorded.filter(|_| !options.no_permissions);
+    fix_permissions(hoard, &operation, direction, recorded_perms).await?;
+    if let (
+        ItemOperation::Create(file) | ItemOperation::Modify(file),
+        Direction::Restore,
+        Some(recorded),
+    ) = (
+        &operation,
+        direction,
+        recorded.filter(|_| !options.no_mtime),
+    ) {
+        restore_mtime(file, recorded);
+    }
+    Ok(deleted)
+}
+
+/// Returns the checksum that `op` records for the file restored by `operation`, if any.
+///
+/// Backed up files and deletions are not verified.
+fn expected_checksum(
+    op: &Operation,
+    operation: &ItemOperation<HoardItem>,
+    direction: Direction,
+) -> Option<Checksum> {
+    match (operation, direction) {
+        (ItemOperation::Create(file) | ItemOperation::Modify(file), Direction::Restore) => {
+            op.checksum_for(file.pile_name(), file.relative_path())
+        }
+        _ => None,
+    }
+}
+
+/// Re-hashes the restored system copy of `file` and checks that it matches `expected`.
+#[tracing::instrument(level = "debug", skip(pile_config))]
+async fn verify_restored_file(
+    file: &HoardItem,
+    expected: Checksum,
+    pile_config: Option<&PileConfig>,
+) -> Result<(), Error> {
+    let found = match pile_config {
+        Some(config) => {
+            file.system_checksum_with_config(expected.typ(), config)
+                .await?
+        }
+        None => file.system_checksum(expected.typ()).await?,
+    };
+
+    if found.as_ref() == Some(&expected) {
+        tracing::trace!(
+            "{} matches its recorded checksum",
+            file.system_path().display()
+        );
+        Ok(())
+    } else {
+        crate::create_log_error(Error::VerificationFailed {
+            path: file.system_path().to_path_buf(),
+            expected,
+            found,
+        })
+    }
+}
+
+/// Returns an error if any of `hoards` has changes both locally and in the hoard.
+async fn check_unexpected_changes<'a>(
+    hoards_root: &HoardPath,
+    hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)>,
+    scan: &ScanOptions,
+) -> Result<(), Error> {
+    for (name, hoard) in hoards {
+        let source = hoard_diff_source(hoards_root, name, hoard, false, scan).await?;
+        if source == Some(DiffSource::Unknown) {
+            return crate::create_log_error(Error::UnexpectedChanges(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// The operations to apply to `hoard`, skipping existing files for `--missing-only` restores.
+///
+/// Files in piles with multiple paths are assigned to the path they belong to.
+fn hoard_operations(
+    op: &Operation,
+    hoard_prefix: &HoardPath,
+    hoard: &Hoard,
+    sources: &Sources,
+    direction: Direction,
+    options: Options,
+) -> Result<Vec<ItemOperation<HoardItem>>, Error> {
+    op.hoard_operations_iter(hoard_prefix, hoard)
+        .map_err(ConsistencyError::Operation)?
+        .map(|operation| sources.assign_operation(operation))
+        .filter_ok(|operation| {
+            direction == Direction::Backup || !options.missing_only || is_missing_locally(operation)
+        })
+        .map(|operation| operation.map_err(Error::from))
+        .collect()
+}
+
+/// The system files that were backed up by `operations`, for removal with `--move`.
+fn files_to_move(operations: &[ItemOperation<HoardItem>]) -> impl Iterator<Item = HoardItem> + '_ {
+    operations.iter().filter_map(|operation| match operation {
+        ItemOperation::Create(file)
+        | ItemOperation::Modify(file)
+        | ItemOperation::Nothing(file) => Some(file.clone()),
+        ItemOperation::Delete(_) | ItemOperation::DoesNotExist(_) => None,
+    })
+}
+
+/// Whether a backup would neither copy nor delete any files.
+fn is_unchanged(operations: &[ItemOperation<HoardItem>]) -> bool {
+    operations.iter().all(|operation| {
+        matches!(
+            operation,
+            ItemOperation::Nothing(_) | ItemOperation::DoesNotExist(_)
+        )
+    })
+}
+
+/// Logs every file or directory that was skipped because it could not be read.
+fn log_skipped(skipped: &[Skipped]) {
+    if skipped.is_empty() {
+        return;
+    }
+
+    let lines = skipped
+        .iter()
+        .map(|skipped| format!("  {}: {}", skipped.path.display(), skipped.reason))
+        .join("\n");
+    tracing::warn!(
+        "skipped {} path(s) that could not be read -- pass --strict to fail instead:\n{}",
+        skipped.len(),
+        lines
+    );
+}
+
+/// Logs what each of `operations` would do to the system files, without changing anything.
+///
+/// Deletions are logged as warnings because they remove local data.
+fn log_dry_run(hoard_name: &HoardName, operations: &[ItemOperation<HoardItem>]) {
+    let mut changes = 0;
+    for operation in operations {
+        match operation {
+            ItemOperation::Create(file) => {
+                tracing::info!(
+                    "{}: would create {}",
+                    hoard_name,
+                    file.system_path().display()
+                );
+            }
+            ItemOperation::Modify(file) => {
+                tracing::info!(
+                    "{}: would modify {}",
+                    hoard_name,
+                    file.system_path().display()
+                );
+            }
+            ItemOperation::Delete(file) => {
+                tracing::warn!(
+                    "{}: would DELETE {}",
+                    hoard_name,
+                    file.system_path().display()
+                );
+            }
+            ItemOperation::Nothing(_) | ItemOperation::DoesNotExist(_) => continue,
+        }
+        changes += 1;
+    }
+
+    if changes == 0 {
+        tracing::info!("{}: no files would change", hoard_name);
+    }
+}
+
+/// Whether the hoard copy of `file` has the same checksum as the system copy, or `None` if there
+/// is no system copy.
+async fn hoard_copy_matches(file: &HoardItem) -> Result<Option<bool>, Error> {
+    let typ = ChecksumType::default();
+    let (system, hoard) = tokio::try_join!(file.system_checksum(typ), file.hoard_checksum(typ))?;
+    Ok(system.map(|system| hoard == Some(system)))
+}
+
+/// Records each of `files` whose hoard copy matches the system copy as archived in the operation
+/// for `hoard_name`, returning the files to delete once that operation is written.
+///
+/// Archived files are only stored in the hoard, so later backups do not treat them as deleted.
+#[tracing::instrument(level = "debug", skip(checkers, files))]
+async fn archive_moved_files(
+    checkers: &mut Checkers,
+    hoard_name: &HoardName,
+    files: Vec<HoardItem>,
+) -> Result<Vec<HoardItem>, Error> {
+    let operation = checkers
+        .get_operation_mut_for(hoard_name)
+        .expect("operation should exist for hoard");
+    let mut archived = Vec::new();
+    for file in files {
+        match hoard_copy_matches(&file).await? {
+            Some(true) => {
+                operation
+                    .mark_archived(file.pile_name(), file.relative_path())
+                    .map_err(ConsistencyError::Operation)?;
+                archived.push(file);
+            }
+            Some(false) => tracing::warn!(
+                "not removing {}: the hoard copy does not match",
+                file.system_path().display()
+            ),
+            None => {}
+        }
+    }
+    Ok(archived)
+}
+
+/// Deletes the system copy of each file, but only if the hoard copy still has a matching
+/// checksum.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn remove_moved_files(files: Vec<HoardItem>) -> Result<(), Error> {
+    for file in files {
+        match hoard_copy_matches(&file).await? {
+            Some(true) => {
+                tracing::debug!("removing moved file {}", file.system_path().display());
+                fs::remove_file(file.system_path()).await.tap_err(|error| {
+                    tracing::error!(
+                        %error,
+                        "failed to remove moved file {}",
+                        file.system_path().display()
+                    );
+                })?;
+            }
+            Some(false) => tracing::warn!(
+                "not removing {}: it changed after it was backed up",
+                file.system_path().display()
+            ),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the parent directories of every file to be copied up front, shallowest first, so
+/// that concurrent copies never race to create the same directory.
+async fn create_all_parent_dirs(
+    operations: &[ItemOperation<HoardItem>],
+    direction: Direction,
+) -> Result<(), Error> {
+    let mut to_copy: Vec<&HoardItem> = operations
+        .iter()
+        .filter_map(|operation| match operation {
+            ItemOperation::Create(file) | ItemOperation::Modify(file) => Some(file),
+            _ => None,
+        })
+        .collect();
+    to_copy.sort_by_key(|file| copy_paths(file, direction).1.components().count());
+    for file in to_copy {
+        create_parent_dirs(file, direction).await?;
+    }
+    Ok(())
+}
+
+/// Runs the pre hook of each hoard, returning the hoards to continue with and the names of the
+/// hoards whose pre hook failed.
+#[allow(single_use_lifetimes)]
+fn run_pre_hooks<'a>(
+    hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)>,
+    hooks: &BTreeMap<HoardName, Hooks>,
+    direction: Direction,
+) -> (Vec<(&'a HoardName, &'a Hoard)>, Vec<HoardName>) {
+    let mut failed = Vec::new();
+    let hoards = hoards
+        .into_iter()
+        .filter(|(name, _)| {
+            let result = hooks
+                .get(*name)
+                .map_or(Ok(()), |hooks| hooks.run_pre(name, direction));
+            if let Err(error) = result {
+                tracing::error!(%error, "pre hook for hoard {} failed, skipping it", name);
+                failed.push((*name).clone());
+                return false;
+            }
+            true
+        })
+        .collect();
+    (hoards, failed)
+}
+
+/// The post hooks of the hoards whose pre hooks have run.
+///
+/// Any post hook that has not run when this is dropped, e.g. because a check failed part way
+/// through the operation, is run then, so that whatever a pre hook stopped is started again.
+struct PostHooks<'a> {
+    hooks: &'a BTreeMap<HoardName, Hooks>,
+    direction: Direction,
+    pending: BTreeSet<&'a HoardName>,
+}
+
+impl<'a> PostHooks<'a> {
+    fn new(
+        hooks: &'a BTreeMap<HoardName, Hooks>,
+        direction: Direction,
+        hoards: &[(&'a HoardName, &'a Hoard)],
+    ) -> Self {
+        Self {
+            hooks,
+            direction,
+            pending: hoards.iter().map(|(name, _)| *name).collect(),
+        }
+    }
+
+    /// Runs the post hook of `name`, unless it has already run.
+    fn run(&mut self, name: &HoardName) {
+        if self.pending.remove(name) {
+            if let Some(hooks) = self.hooks.get(name) {
+                hooks.run_post(name, self.direction);
+            }
+        }
+    }
+}
+
+impl Drop for PostHooks<'_> {
+    fn drop(&mut self) {
+        while let Some(name) = self.pending.first().copied() {
+            self.run(name);
+        }
+    }
+}
+
+/// Returns whether any of the `hoards` have a file to back up or restore.
+fn any_file_selected(
+    hoards_root: &HoardPath,
+    hoards: &[(&HoardName, &Hoard)],
+    checkers: &Checkers,
+) -> Result<bool, Error> {
+    for (name, hoard) in hoards {
+        let hoard_prefix = hoards_root.join(&RelativePath::from(*name));
+        let op = checkers
+            .get_operation_for(name)
+            .expect("operation should exist for hoard");
+        let mut operations = op
+            .hoard_operations_iter(&hoard_prefix, hoard)
+            .map_err(ConsistencyError::Operation)?;
+        if operations.any(|operation| !matches!(operation, ItemOperation::DoesNotExist(_))) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Checks that there is enough free space for all files that the `hoards` will copy, before
+/// any of them are written.
+///
+/// Backups are checked against the filesystem of `hoards_root`, and restores against that of
+/// each pile. The check adds up the size of every created or modified file, so it may be more
+/// strict than necessary when files are overwritten.
+async fn check_free_space(
+    hoards_root: &HoardPath,
+    hoards: &[(&HoardName, &Hoard)],
+    checkers: &Checkers,
+    direction: Direction,
+    options: Options,
+) -> Result<(), Error> {
+    let mut required: BTreeMap<PathBuf, u64> = BTreeMap::new();
+    for (name, hoard) in hoards {
+        let hoard_prefix = hoards_root.join(&RelativePath::from(*name));
+        let op = checkers
+            .get_operation_for(name)
+            .expect("operation should exist for hoard");
+        let sources = Sources::load(name, hoard).await?;
+        for operation in hoard_operations(op, &hoard_prefix, hoard, &sources, direction, options)? {
+            if let ItemOperation::Create(file) | ItemOperation::Modify(file) = &operation {
+                let (src, _, dest_root) = copy_paths(file, direction);
+                let root = match direction {
+                    Direction::Backup => hoards_root.as_ref(),
+                    Direction::Restore => dest_root,
+                };
+                let size = fs::metadata(src).await?.len();
+                *required.entry(root.to_path_buf()).or_default() += size;
+            }
+        }
+    }
+
+    for (path, required) in required {
+        let available = crate::disk_space::available_space(&path)?;
+        tracing::debug!(
+            "{} bytes to write to {}, {} bytes available",
+            required,
+            path.display(),
+            available
+        );
+        if required > available {
+            return crate::create_log_error(Error::InsufficientSpace {
+                path,
+                required,
+                available,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies `operations` to the files of `hoard`, showing progress as they are applied.
+async fn apply_operations(
+    name: &HoardName,
+    hoard: &Hoard,
+    op: &Operation,
+    operations: Vec<ItemOperation<HoardItem>>,
+    direction: Direction,
+    options: Options,
+) -> Result<(), Error> {
+    // Only restores write files concurrently.
+    let jobs = match direction {
+        Direction::Backup => 1,
+        Direction::Restore => options.jobs.get(),
+    };
+    if jobs > 1 {
+        create_all_parent_dirs(&operations, direction).await?;
+    }
+
+    let recorded = match direction {
+        Direction::Backup => None,
+        Direction::Restore => {
+            recorded_metadata(name, options.no_permissions && options.no_mtime).await?
+        }
+    };
+    let recorded = recorded.as_ref();
+    let progress = Progress::new(name.to_string(), operations.len(), options.quiet);
+    let progress = &progress;
+    let deleted: Vec<(PathBuf, PathBuf)> = futures::stream::iter(operations)
+        .map(|operation| {
+            let expected = expected_checksum(op, &operation, direction);
+            async move {
+                progress.start_file(operation.as_inner().system_path().as_ref());
+                let result =
+                    apply_operation(hoard, operation, direction, options, recorded, expected).await;
+                progress.finish_file();
+                result
+            }
+        })
+        .buffer_unordered(jobs)
+        .try_filter_map(|deleted| futures::future::ready(Ok(deleted)))
+        .try_collect()
+        .await?;
+
+    for (root, path) in deleted {
+        prune_empty_parents(&root, &path).await?;
+    }
+
+    Ok(())
+}
+
 #[allow(single_use_lifetimes)]
 async fn backup_or_restore<'a>(
     hoards_root: &HoardPath,
     direction: Direction,
     hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)> + Clone,
-    force: bool,
-) -> Result<(), Error> {
+    hooks: &BTreeMap<HoardName, Hooks>,
+    options: Options,
+) -> Result<Report, Error> {
     tracing::info!("processing files before {}", direction);
-    let mut checkers = Checkers::new(hoards_root, hoards.clone(), direction).await?;
+    // Every stream of this operation records skipped paths here, to be listed once it finishes.
+    let scan = options.scan_options();
+    if options.safe && !options.force {
+        check_unexpected_changes(hoards_root, hoards.clone(), &scan).await?;
+    }
+
+    // Pre hooks run before any files are read, so that e.g. a game can save and exit first.
+    let no_hooks = BTreeMap::new();
+    let hooks = if options.no_hooks || options.dry_run {
+        &no_hooks
+    } else {
+        hooks
+    };
+    let (hoards, failed_hooks) = run_pre_hooks(hoards, hooks, direction);
+    let mut post_hooks = PostHooks::new(hooks, direction, &hoards);
+
+    let mut checkers = Checkers::new(hoards_root, hoards.iter().copied(), direction, &scan).await?;
     tracing::debug!(?checkers, "================");
-    if !force {
+    if !options.force {
         checkers.check().await?;
     }
 
+    if options.require_selection && !any_file_selected(hoards_root, &hoards, &checkers)? {
+        return crate::create_log_error(Error::NothingSelected);
+    }
+
+    if !options.force && !options.dry_run {
+        check_free_space(hoards_root, &hoards, &checkers, direction, options).await?;
+    }
+
+    let mut report = Report::default();
+    let mut moved = Vec::new();
+    let mut unchanged = Vec::new();
     for (name, hoard) in hoards {
         match direction {
             Direction::Backup => tracing::info!(hoard=%name, "backing up"),
@@ -278,45 +1302,181 @@ async fn backup_or_restore<'a>(
         let op = checkers
             .get_operation_for(name)
             .expect("operation should exist for hoard");
-        let iter = op
-            .hoard_operations_iter(&hoard_prefix, hoard)
-            .map_err(ConsistencyError::Operation)?;
-        for operation in iter {
-            match &operation {
-                ItemOperation::Create(file) | ItemOperation::Modify(file) => {
-                    copy_file(file, direction).await?;
-                }
-                ItemOperation::Delete(file) => {
-                    let to_remove = match direction {
-                        Direction::Backup => file.hoard_path().as_ref(),
-                        Direction::Restore => file.system_path().as_ref(),
-                    };
-                    if to_remove.exists() {
-                        tracing::debug!("deleting {}", to_remove.display());
-                        fs::remove_file(to_remove).await.tap_err(|error| {
-                            tracing::error!(%error, "failed to delete {}", to_remove.display());
-                        })?;
-                    }
-                }
-                ItemOperation::Nothing(file) => {
-                    tracing::debug!("file {} is unchanged", file.system_path().display());
-                }
-                ItemOperation::DoesNotExist(file) => {
-                    tracing::trace!("file {} does not exist", file.system_path().display());
-                }
-            }
+        let mut sources = Sources::load(name, hoard).await?;
+        let operations = hoard_operations(op, &hoard_prefix, hoard, &sources, direction, options)?;
+        report.hoards.insert(name.clone(), operations.clone());
+        report.summaries.insert(
+            name.clone(),
+            Summary::of_operations(&operations, direction).await,
+        );
+        if !options.case_sensitive {
+            warn_case_collisions(name, &operations);
+        }
+
+        if direction == Direction::Backup && options.if_changed && is_unchanged(&operations) {
+            tracing::info!("{}: no changes", name);
+            unchanged.push(name);
+            post_hooks.run(name);
+            continue;
+        }
 
-            fix_permissions(hoard, &operation, direction).await?;
+        if options.dry_run {
+            log_dry_run(name, &operations);
+            continue;
         }
+
+        let to_move: Vec<HoardItem> = if direction == Direction::Backup && options.move_files {
+            files_to_move(&operations).collect()
+        } else {
+            Vec::new()
+        };
+
+        apply_operations(name, hoard, op, operations, direction, options).await?;
+        sources.record(name, &report.hoards[name]).await?;
+        moved.extend(archive_moved_files(&mut checkers, name, to_move).await?);
+        post_hooks.run(name);
+    }
+
+    report.skipped = scan.skipped.take();
+    log_skipped(&report.skipped);
+
+    if options.dry_run {
+        tracing::info!("dry run: no files were changed");
+        return Ok(report);
+    }
+
+    for name in unchanged {
+        checkers.remove(name);
     }
 
-    checkers.commit_to_disk().await.map_err(Error::Consistency)
+    checkers
+        .commit_to_disk()
+        .await
+        .map_err(Error::Consistency)?;
+
+    // Only remove files once the operation log recording them has been written.
+    remove_moved_files(moved).await?;
+
+    if failed_hooks.is_empty() {
+        Ok(report)
+    } else {
+        crate::create_log_error(Error::PreHook(failed_hooks))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    mod write_atomically {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_failed_write_keeps_original() {
+            let dir = tempfile::tempdir().unwrap();
+            let dest = dir.path().join("config.toml");
+            fs::write(&dest, "original content").await.unwrap();
+
+            let result = write_atomically(&dest, RetryPolicy::default(), |temp| async move {
+                fs::write(&temp, "partial").await?;
+                Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "simulated failure",
+                ))
+            })
+            .await;
+
+            assert!(result.is_err());
+            assert_eq!(fs::read_to_string(&dest).await.unwrap(), "original content");
+            assert!(
+                !temp_path_for(&dest).exists(),
+                "temporary file should be removed"
+            );
+        }
+
+        #[tokio::test]
+        async fn test_successful_write_replaces_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let dest = dir.path().join("config.toml");
+            fs::write(&dest, "original content").await.unwrap();
+
+            write_atomically(&dest, RetryPolicy::default(), |temp| async move {
+                fs::write(&temp, "new content").await
+            })
+            .await
+            .unwrap();
+
+            assert_eq!(fs::read_to_string(&dest).await.unwrap(), "new content");
+            assert!(!temp_path_for(&dest).exists());
+        }
+    }
+
+    mod verify_restored_file {
+        use crate::newtypes::PileName;
+
+        use super::*;
+
+        fn restored_file(dir: &Path) -> HoardItem {
+            HoardItem::new(
+                PileName::anonymous(),
+                crate::paths::hoards_dir(),
+                SystemPath::try_from(dir.to_path_buf()).unwrap(),
+                RelativePath::try_from(PathBuf::from("file.txt")).unwrap(),
+            )
+        }
+
+        #[tokio::test]
+        async fn test_matching_file_is_ok() {
+            let dir = tempfile::tempdir().unwrap();
+            let file = restored_file(dir.path());
+            fs::write(file.system_path(), "content").await.unwrap();
+            let expected = file.system_checksum(ChecksumType::SHA256).await.unwrap();
+
+            verify_restored_file(&file, expected.unwrap(), None)
+                .await
+                .expect("matching file should pass verification");
+        }
+
+        #[tokio::test]
+        async fn test_mismatched_file_fails() {
+            let dir = tempfile::tempdir().unwrap();
+            let file = restored_file(dir.path());
+            fs::write(file.system_path(), "content").await.unwrap();
+            let expected = file.system_checksum(ChecksumType::SHA256).await.unwrap();
+            fs::write(file.system_path(), "corrupted").await.unwrap();
+
+            let error = verify_restored_file(&file, expected.clone().unwrap(), None)
+                .await
+                .expect_err("mismatched file should fail verification");
+            assert!(matches!(
+                error,
+                Error::VerificationFailed { expected: ref checksum, found: Some(_), .. }
+                    if Some(checksum) == expected.as_ref()
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_applies_pile_config() {
+            let dir = tempfile::tempdir().unwrap();
+            let file = restored_file(dir.path());
+            fs::write(file.system_path(), "line 1\nline 2\n")
+                .await
+                .unwrap();
+            let expected = file.system_checksum(ChecksumType::SHA256).await.unwrap();
+            fs::write(file.system_path(), "line 1\r\nline 2\r\n")
+                .await
+                .unwrap();
+
+            let config = PileConfig {
+                normalize_line_endings: Some(true),
+                ..PileConfig::default()
+            };
+            verify_restored_file(&file, expected.unwrap(), Some(&config))
+                .await
+                .expect("normalized content should pass verification");
+        }
+    }
+
     mod parent_iter {
         use crate::test::path_string;
 
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::io;
+use uuid::Uuid;
+
+use crate::checkers::history::get_all_history_dirs;
+use crate::checkers::history::operation::{
+    Error as OperationError, Operation, OperationImpl, OperationType,
+};
+use crate::hoard::Direction;
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::RelativePath;
+
+/// Errors that may occur while finding the last operation to touch a file.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read the operation logs.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// An I/O error occurred while listing system history directories.
+    #[error("I/O error while reading history: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// The most recent operation, across all systems, that changed a file.
+#[derive(Debug)]
+struct Blame {
+    system: Uuid,
+    timestamp: OffsetDateTime,
+    direction: Direction,
+    pile_name: PileName,
+    operation: OperationType,
+}
+
+impl fmt::Display for Blame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.operation {
+            OperationType::Create => "created",
+            OperationType::Modify => "modified",
+            OperationType::Delete => "deleted",
+        };
+        let timestamp = self
+            .timestamp
+            .format(&Rfc3339)
+            .unwrap_or_else(|_| self.timestamp.to_string());
+        write!(
+            f,
+            "{action} by system {} during {} at {timestamp}",
+            self.system, self.direction
+        )?;
+        if let Some(pile_name) = self.pile_name.as_ref() {
+            write!(f, " (pile {pile_name})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `path` refers to the given file, either relative to its pile or prefixed with
+/// the pile's name.
+pub(super) fn matches_path(
+    pile_name: &PileName,
+    rel_path: &RelativePath,
+    path: &RelativePath,
+) -> bool {
+    rel_path == path
+        || RelativePath::from(pile_name)
+            .to_path_buf()
+            .join(rel_path.to_path_buf())
+            == path.to_path_buf()
+}
+
+/// Returns the operation that most recently created, modified, or deleted the file in the
+/// given operation, if any.
+fn blame_in_operation(
+    system: Uuid,
+    operation: &Operation,
+    path: &RelativePath,
+) -> Result<Option<Blame>, OperationError> {
+    for file in operation.all_files_with_checksums() {
+        if !matches_path(file.pile_name(), file.relative_path(), path) {
+            continue;
+        }
+
+        if let Some(op_type) = operation.file_operation(file.pile_name(), file.relative_path())? {
+            return Ok(Some(Blame {
+                system,
+                timestamp: operation.timestamp(),
+                direction: operation.direction(),
+                pile_name: file.pile_name().clone(),
+                operation: op_type,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+#[tracing::instrument(level = "debug")]
+async fn find_blame(hoard: &HoardName, path: &RelativePath) -> Result<Option<Blame>, Error> {
+    let mut latest: Option<Blame> = None;
+    for (system, dir) in get_all_history_dirs().await? {
+        for operation in Operation::all_from_history_dir(&dir, hoard).await? {
+            let operation = operation.into_latest_version()?;
+            if let Some(blame) = blame_in_operation(system, &operation, path)? {
+                if latest
+                    .as_ref()
+                    .map_or(true, |latest| blame.timestamp > latest.timestamp)
+                {
+                    latest = Some(blame);
+                }
+            }
+        }
+    }
+
+    Ok(latest)
+}
+
+#[tracing::instrument]
+pub(crate) async fn run_blame(hoard: &HoardName, path: &RelativePath) -> Result<(), super::Error> {
+    if let Some(blame) = find_blame(hoard, path).await? {
+        tracing::info!("{}/{}: {}", hoard, path, blame);
+    } else {
+        tracing::info!(
+            "{}/{}: no recorded operations touched this file",
+            hoard,
+            path
+        );
+    }
+
+    Ok(())
+}
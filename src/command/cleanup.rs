@@ -1,15 +1,26 @@
-use crate::checkers::history::operation::cleanup_operations;
+use crate::checkers::history::operation::{cleanup_operations, remove_empty_history_dirs};
 
 #[tracing::instrument]
-pub(crate) async fn run_cleanup() -> Result<(), super::Error> {
-    match cleanup_operations().await {
-        Ok(count) => {
-            tracing::info!("cleaned up {} log files", count);
-            Ok(())
-        }
-        Err((count, error)) => Err(super::Error::Cleanup {
+pub(crate) async fn run_cleanup(dry_run: bool) -> Result<(), super::Error> {
+    let count = cleanup_operations(dry_run)
+        .await
+        .map_err(|(count, error)| super::Error::Cleanup {
             success_count: count,
             error,
-        }),
+        })?;
+    let dirs = remove_empty_history_dirs(dry_run)
+        .await
+        .map_err(|error| super::Error::Cleanup {
+            success_count: count,
+            error,
+        })?;
+
+    if dry_run {
+        tracing::info!("would clean up {} log files", count);
+        tracing::info!("would remove {} empty directories", dirs);
+    } else {
+        tracing::info!("cleaned up {} log files", count);
+        tracing::info!("removed {} empty directories", dirs);
     }
+    Ok(())
 }
@@ -1,78 +1,856 @@
-use std::collections::BTreeSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use futures::TryStreamExt;
+use serde::Serialize;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
-use crate::hoard::iter::{changed_diff_only_stream, HoardFileDiff};
+use crate::checkers::history::get_all_history_dirs;
+use crate::checkers::history::operation::{Error as OperationError, Operation, OperationImpl};
+use crate::checksum::Checksum;
+use crate::diff::{colorize_unified_diff, reverse_unified_diff};
+use crate::hoard::iter::{
+    all_files_stream, changed_diff_only_stream, DiffSource, HoardFileDiff, ScanOptions,
+};
 use crate::hoard::Hoard;
-use crate::newtypes::HoardName;
-use crate::paths::HoardPath;
+use crate::hoard_item::{CachedHoardItem, HoardItem};
+use crate::newtypes::{HoardName, NonEmptyPileName, PileName};
+use crate::paths::{HoardPath, RelativePath};
 
-#[tracing::instrument(skip(hoard))]
-pub(crate) async fn run_diff(
-    hoard: &Hoard,
-    hoard_name: &HoardName,
-    hoards_root: &HoardPath,
-    verbose: bool,
-) -> Result<(), super::Error> {
-    let _span = tracing::trace_span!("run_diff").entered();
-    tracing::trace!("running the diff command");
-    let diffs: BTreeSet<HoardFileDiff> =
-        changed_diff_only_stream(hoards_root, hoard_name.clone(), hoard)
-            .await
-            .map_err(|err| {
-                tracing::error!("failed to create diff stream: {}", err);
-                super::Error::Diff(err)
-            })?
-            .try_collect()
-            .await
-            .map_err(super::Error::Diff)?;
+/// Errors that may occur while diffing against a past operation.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// No operation was recorded for the hoard at or before the requested time.
+    #[error("no operation recorded for hoard {hoard} at or before {timestamp}")]
+    NoOperation {
+        /// The hoard being diffed.
+        hoard: HoardName,
+        /// The requested time, formatted as RFC 3339.
+        timestamp: String,
+    },
+    /// Failed to read the operation logs.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// Failed to list the files in the hoard.
+    #[error("failed to list hoard files: {0}")]
+    Files(#[from] crate::hoard::iter::Error),
+    /// The path to diff is not part of the hoard.
+    #[error(
+        "{path} is not part of hoard {hoard}, which contains: {}",
+        .piles.join(", ")
+    )]
+    NotInHoard {
+        /// The hoard being diffed.
+        hoard: HoardName,
+        /// The path that was given.
+        path: RelativePath,
+        /// The system path of each pile in the hoard, prefixed with the pile name if it has one.
+        piles: Vec<String>,
+    },
+    /// An I/O error occurred while reading files or history directories.
+    #[error("I/O error while diffing: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// Parses an RFC 3339 timestamp given on the command line.
+pub(crate) fn parse_timestamp(s: &str) -> Result<OffsetDateTime, String> {
+    OffsetDateTime::parse(s, &Rfc3339).map_err(|err| {
+        format!("invalid timestamp {s}, expected RFC 3339 (e.g. 2024-01-31T18:00:00Z): {err}")
+    })
+}
+
+pub(super) fn format_timestamp(timestamp: OffsetDateTime) -> String {
+    timestamp
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+/// How to group the output of the diff command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffGrouping {
+    /// Group changed files under a header for the pile that contains them.
+    Pile,
+}
+
+impl FromStr for DiffGrouping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pile" => Ok(Self::Pile),
+            _ => Err(format!(
+                "unsupported diff grouping {s}: only \"pile\" is currently available"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DiffGrouping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pile => write!(f, "pile"),
+        }
+    }
+}
+
+/// The format to print diffs in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// Human-readable lines of text.
+    #[default]
+    Text,
+    /// A JSON array with one object per changed file.
+    Json,
+}
+
+impl FromStr for DiffFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "unsupported diff format {s}: expected \"text\" or \"json\""
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DiffFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// When to color the unified diffs of text files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color output if it is going to a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    /// Always color output.
+    Always,
+    /// Never color output.
+    Never,
+}
+
+impl ColorChoice {
+    /// Returns whether output should be colored with this choice.
+    ///
+    /// Following <https://no-color.org>, a non-empty `NO_COLOR` environment variable disables
+    /// automatic coloring.
+    #[must_use]
+    pub fn should_color(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+                    && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "unsupported color choice {s}: expected \"auto\", \"always\", or \"never\""
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Always => write!(f, "always"),
+            Self::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Options that modify how diffs are displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub(crate) struct Options {
+    /// Print unified diffs for text files.
+    pub(crate) verbose: bool,
+    /// Show changes from the system's perspective instead of the hoard's.
+    pub(crate) reverse: bool,
+    /// How to group changed files, if at all.
+    pub(crate) group_by: Option<DiffGrouping>,
+    /// The format to print diffs in.
+    pub(crate) format: DiffFormat,
+    /// Highlight changed words in unified diffs with ANSI colors.
+    pub(crate) color: bool,
+    /// Show a deleted file and a created file with the same content as a single rename.
+    pub(crate) follow_renames: bool,
+}
+
+/// A change to print: either the diff of a single file or a deleted file and a created file
+/// that were detected as a rename.
+#[derive(Debug, Clone, Copy)]
+enum Change<'a> {
+    File(&'a HoardFileDiff),
+    Renamed {
+        /// The file that was deleted.
+        from: &'a CachedHoardItem,
+        /// The file with the same content that was created.
+        to: &'a CachedHoardItem,
+        diff_source: DiffSource,
+    },
+}
+
+impl<'a> Change<'a> {
+    /// Returns the file that was renamed and its new name, from the perspective set by
+    /// `options`.
+    fn renamed(
+        from: &'a CachedHoardItem,
+        to: &'a CachedHoardItem,
+        options: Options,
+    ) -> (&'a CachedHoardItem, &'a CachedHoardItem) {
+        // When reversed, deleted and created files swap places, so the rename goes backwards.
+        if options.reverse {
+            (to, from)
+        } else {
+            (from, to)
+        }
+    }
+}
+
+/// Returns the checksum of `file` if it exists in only one of the hoard or the system.
+fn sole_checksum(file: &CachedHoardItem) -> Option<Checksum> {
+    match (file.system_blake3(), file.hoard_blake3()) {
+        (Some(checksum), None) | (None, Some(checksum)) => Some(checksum),
+        _ => None,
+    }
+}
+
+/// Pairs each deleted file with a created file that has the same content, pile, and source,
+/// and replaces each pair with a single rename.
+///
+/// Each file is paired at most once. If several created files match a deleted file, the first
+/// one by path is used.
+fn pair_renames(diffs: &BTreeSet<HoardFileDiff>) -> Vec<Change<'_>> {
+    let mut created: HashMap<_, VecDeque<&CachedHoardItem>> = HashMap::new();
     for hoard_diff in diffs {
-        tracing::trace!("printing diff: {:?}", hoard_diff);
-        match hoard_diff {
+        if let HoardFileDiff::Created {
+            file, diff_source, ..
+        } = hoard_diff
+        {
+            if let Some(checksum) = sole_checksum(file) {
+                created
+                    .entry((file.pile_name(), *diff_source, checksum))
+                    .or_default()
+                    .push_back(file);
+            }
+        }
+    }
+
+    let mut renames: HashMap<&CachedHoardItem, &CachedHoardItem> = HashMap::new();
+    for hoard_diff in diffs {
+        if let HoardFileDiff::Deleted { file, diff_source } = hoard_diff {
+            let to = sole_checksum(file).and_then(|checksum| {
+                created
+                    .get_mut(&(file.pile_name(), *diff_source, checksum))?
+                    .pop_front()
+            });
+            if let Some(to) = to {
+                renames.insert(file, to);
+            }
+        }
+    }
+    let paired: HashSet<&CachedHoardItem> = renames.values().copied().collect();
+
+    diffs
+        .iter()
+        .filter_map(|hoard_diff| match hoard_diff {
+            HoardFileDiff::Deleted { file, diff_source } => Some(match renames.get(file) {
+                Some(to) => Change::Renamed {
+                    from: file,
+                    to,
+                    diff_source: *diff_source,
+                },
+                None => Change::File(hoard_diff),
+            }),
+            HoardFileDiff::Created { file, .. } if paired.contains(file) => None,
+            hoard_diff => Some(Change::File(hoard_diff)),
+        })
+        .collect()
+}
+
+/// The kind of change described by a [`JsonFileDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ChangeType {
+    Created,
+    Modified,
+    Deleted,
+    Renamed,
+    Corrupt,
+    Excluded,
+    Ignored,
+}
+
+/// Whether a [`JsonFileDiff`] describes a text or binary file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum ContentKind {
+    Text,
+    Binary,
+}
+
+/// A single changed file, as printed by `hoard diff --format json`.
+#[derive(Debug, Serialize)]
+struct JsonFileDiff {
+    path: PathBuf,
+    pile: Option<NonEmptyPileName>,
+    change_type: ChangeType,
+    diff_source: DiffSource,
+    content_kind: ContentKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unified_diff: Option<String>,
+    /// The previous path of a renamed file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    renamed_from: Option<PathBuf>,
+}
+
+impl JsonFileDiff {
+    /// Converts a [`Change`] to its JSON form, or `None` if it does not describe a change.
+    fn from_change(change: Change<'_>, options: Options) -> Option<Self> {
+        match change {
+            Change::File(hoard_diff) => Self::from_file_diff(hoard_diff, options),
+            Change::Renamed {
+                from,
+                to,
+                diff_source,
+            } => {
+                let (from, to) = Change::renamed(from, to, options);
+                Some(Self {
+                    path: to.system_path().to_path_buf(),
+                    pile: to.pile_name().as_ref().cloned(),
+                    change_type: ChangeType::Renamed,
+                    diff_source,
+                    content_kind: if to.is_text() {
+                        ContentKind::Text
+                    } else {
+                        ContentKind::Binary
+                    },
+                    unified_diff: None,
+                    renamed_from: Some(from.system_path().to_path_buf()),
+                })
+            }
+        }
+    }
+
+    /// Converts a [`HoardFileDiff`] to its JSON form, or `None` if it does not describe a change.
+    fn from_file_diff(hoard_diff: &HoardFileDiff, options: Options) -> Option<Self> {
+        // When reversed, a file created on one side is reported as deleted from the other.
+        let (created, deleted) = if options.reverse {
+            (ChangeType::Deleted, ChangeType::Created)
+        } else {
+            (ChangeType::Created, ChangeType::Deleted)
+        };
+
+        let (file, change_type, diff_source, unified_diff) = match hoard_diff {
             HoardFileDiff::BinaryModified { file, diff_source } => {
-                tracing::info!(
-                    "{}: binary file changed {}",
-                    file.system_path().display(),
-                    diff_source
-                );
+                (file, ChangeType::Modified, *diff_source, None)
             }
             HoardFileDiff::TextModified {
                 file,
                 unified_diff,
                 diff_source,
-            } => {
-                tracing::info!(
-                    "{}: text file changed {}",
-                    file.system_path().display(),
-                    diff_source
-                );
-                if let (true, Some(unified_diff)) = (verbose, unified_diff) {
-                    tracing::info!("{}", unified_diff);
-                }
-            }
+            } => (
+                file,
+                ChangeType::Modified,
+                *diff_source,
+                unified_diff.as_ref(),
+            ),
             HoardFileDiff::Created {
                 file,
-                diff_source,
                 unified_diff,
-            } => {
-                tracing::info!(
-                    "{}: (re)created {}",
-                    file.system_path().display(),
-                    diff_source
-                );
-                if let (true, Some(unified_diff)) = (verbose, unified_diff) {
-                    tracing::info!("{}", unified_diff);
-                }
-            }
-            HoardFileDiff::Deleted { file, diff_source } => {
-                tracing::info!("{}: deleted {}", file.system_path().display(), diff_source);
+                diff_source,
+            } => (file, created, *diff_source, unified_diff.as_ref()),
+            HoardFileDiff::Deleted { file, diff_source } => (file, deleted, *diff_source, None),
+            HoardFileDiff::Corrupt(file) => (file, ChangeType::Corrupt, DiffSource::Unknown, None),
+            HoardFileDiff::Excluded(file) => {
+                (file, ChangeType::Excluded, DiffSource::Unknown, None)
             }
-            HoardFileDiff::Unchanged(file) => {
-                tracing::debug!("{}: unmodified", file.system_path().display());
+            HoardFileDiff::Ignored(file) => (file, ChangeType::Ignored, DiffSource::Unknown, None),
+            HoardFileDiff::Unchanged(_) | HoardFileDiff::Nonexistent(_) => return None,
+        };
+
+        let content_kind = match hoard_diff {
+            HoardFileDiff::BinaryModified { .. } => ContentKind::Binary,
+            HoardFileDiff::TextModified { .. } => ContentKind::Text,
+            _ if file.is_text() => ContentKind::Text,
+            _ => ContentKind::Binary,
+        };
+
+        let unified_diff = unified_diff.map(|unified_diff| {
+            if options.reverse {
+                reverse_unified_diff(unified_diff)
+            } else {
+                unified_diff.clone()
             }
-            HoardFileDiff::Nonexistent(_) => {}
+        });
+
+        Some(Self {
+            path: file.system_path().to_path_buf(),
+            pile: file.pile_name().as_ref().cloned(),
+            change_type,
+            diff_source,
+            content_kind,
+            unified_diff,
+            renamed_from: None,
+        })
+    }
+}
+
+fn write_json(out: &mut impl Write, changes: &[Change<'_>], options: Options) -> io::Result<()> {
+    let diffs: Vec<JsonFileDiff> = changes
+        .iter()
+        .filter_map(|change| JsonFileDiff::from_change(*change, options))
+        .collect();
+    serde_json::to_writer_pretty(&mut *out, &diffs)?;
+    writeln!(out)
+}
+
+fn write_unified_diff(
+    out: &mut impl Write,
+    unified_diff: Option<&String>,
+    options: Options,
+) -> io::Result<()> {
+    if let (true, Some(unified_diff)) = (options.verbose, unified_diff) {
+        let unified_diff = if options.reverse {
+            Cow::Owned(reverse_unified_diff(unified_diff))
+        } else {
+            Cow::Borrowed(unified_diff.as_str())
+        };
+        if options.color {
+            writeln!(out, "{}", colorize_unified_diff(&unified_diff))
+        } else {
+            writeln!(out, "{unified_diff}")
+        }
+    } else {
+        Ok(())
+    }
+}
+
+fn write_file_diff(
+    out: &mut impl Write,
+    hoard_diff: &HoardFileDiff,
+    options: Options,
+) -> io::Result<()> {
+    tracing::trace!("printing diff: {:?}", hoard_diff);
+    // When reversed, a file created on one side is reported as deleted from the other.
+    let (created, deleted) = if options.reverse {
+        ("deleted", "(re)created")
+    } else {
+        ("(re)created", "deleted")
+    };
+
+    match hoard_diff {
+        HoardFileDiff::BinaryModified { file, diff_source } => {
+            writeln!(
+                out,
+                "{}: binary file changed {}",
+                file.system_path().display(),
+                diff_source
+            )?;
+        }
+        HoardFileDiff::TextModified {
+            file,
+            unified_diff,
+            diff_source,
+        } => {
+            writeln!(
+                out,
+                "{}: text file changed {}",
+                file.system_path().display(),
+                diff_source
+            )?;
+            write_unified_diff(out, unified_diff.as_ref(), options)?;
+        }
+        HoardFileDiff::Created {
+            file,
+            diff_source,
+            unified_diff,
+        } => {
+            writeln!(
+                out,
+                "{}: {} {}",
+                file.system_path().display(),
+                created,
+                diff_source
+            )?;
+            write_unified_diff(out, unified_diff.as_ref(), options)?;
+        }
+        HoardFileDiff::Deleted { file, diff_source } => {
+            writeln!(
+                out,
+                "{}: {} {}",
+                file.system_path().display(),
+                deleted,
+                diff_source
+            )?;
+        }
+        HoardFileDiff::Corrupt(file) => {
+            writeln!(
+                out,
+                "{}: hoard file is corrupt -- it does not match the checksum recorded by the last operation, possibly due to an interrupted backup",
+                file.system_path().display()
+            )?;
+        }
+        HoardFileDiff::Excluded(file) => {
+            writeln!(
+                out,
+                "{}: excluded -- file is larger than the pile's max_file_size",
+                file.system_path().display()
+            )?;
         }
+        HoardFileDiff::Ignored(file) => {
+            writeln!(
+                out,
+                "{}: ignored -- file content matches one of the pile's ignore_checksums",
+                file.system_path().display()
+            )?;
+        }
+        HoardFileDiff::Unchanged(file) => {
+            tracing::debug!("{}: unmodified", file.system_path().display());
+        }
+        HoardFileDiff::Nonexistent(_) => {}
     }
 
     Ok(())
 }
+
+fn write_change(out: &mut impl Write, change: Change<'_>, options: Options) -> io::Result<()> {
+    match change {
+        Change::File(hoard_diff) => write_file_diff(out, hoard_diff, options),
+        Change::Renamed {
+            from,
+            to,
+            diff_source,
+        } => {
+            let (from, to) = Change::renamed(from, to, options);
+            writeln!(
+                out,
+                "{} \u{2192} {}: renamed {}",
+                from.system_path().display(),
+                to.system_path().display(),
+                diff_source
+            )
+        }
+    }
+}
+
+/// Returns the pile containing the file, if the change describes a change.
+fn changed_pile<'a>(change: &Change<'a>) -> Option<&'a PileName> {
+    let hoard_diff = match change {
+        Change::File(hoard_diff) => hoard_diff,
+        Change::Renamed { from, .. } => return Some(from.pile_name()),
+    };
+    match hoard_diff {
+        HoardFileDiff::BinaryModified { file, .. }
+        | HoardFileDiff::TextModified { file, .. }
+        | HoardFileDiff::Created { file, .. }
+        | HoardFileDiff::Deleted { file, .. }
+        | HoardFileDiff::Corrupt(file)
+        | HoardFileDiff::Excluded(file)
+        | HoardFileDiff::Ignored(file) => Some(file.pile_name()),
+        HoardFileDiff::Unchanged(_) | HoardFileDiff::Nonexistent(_) => None,
+    }
+}
+
+fn write_grouped_by_pile(
+    out: &mut impl Write,
+    changes: &[Change<'_>],
+    options: Options,
+) -> io::Result<()> {
+    let mut piles: BTreeMap<&PileName, Vec<Change<'_>>> = BTreeMap::new();
+    for change in changes {
+        match changed_pile(change) {
+            Some(pile_name) => piles.entry(pile_name).or_default().push(*change),
+            None => write_change(out, *change, options)?,
+        }
+    }
+
+    for (pile_name, changes) in piles {
+        let count = changes.len();
+        let plural = if count == 1 { "" } else { "s" };
+        if pile_name.is_anonymous() {
+            writeln!(out, "anonymous pile: {count} change{plural}")?;
+        } else {
+            writeln!(out, "pile {pile_name}: {count} change{plural}")?;
+        }
+        for change in changes {
+            write_change(out, change, options)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns a copy of `hoard` that only includes `path`, which is relative to a pile and may be
+/// prefixed with the pile's name.
+///
+/// Fails if `path` does not exist on the system or in the hoard for any pile.
+fn only_path(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    path: &RelativePath,
+) -> Result<Hoard, Error> {
+    let path_buf = path.to_path_buf();
+    let mut files = Vec::new();
+    let mut piles = Vec::new();
+    for (pile_name, hoard_prefix, system_prefix) in
+        hoard.get_paths(hoards_root.join(&RelativePath::from(hoard_name)))
+    {
+        let prefixed = pile_name
+            .as_ref()
+            .and_then(|name| path_buf.strip_prefix::<&str>(name.as_ref()).ok());
+        for rel_path in prefixed.into_iter().chain([path_buf.as_path()]) {
+            let exists = Path::new(&*system_prefix).join(rel_path).exists()
+                || Path::new(&*hoard_prefix).join(rel_path).exists();
+            if exists && !files.iter().any(|(name, _)| name == &pile_name) {
+                files.push((pile_name.clone(), rel_path.to_path_buf()));
+            }
+        }
+        piles.push(match pile_name.as_ref() {
+            None => system_prefix.display().to_string(),
+            Some(name) => format!("{name} ({})", system_prefix.display()),
+        });
+    }
+
+    if files.is_empty() {
+        piles.sort_unstable();
+        return crate::create_log_error(Error::NotInHoard {
+            hoard: hoard_name.clone(),
+            path: path.clone(),
+            piles,
+        });
+    }
+
+    Ok(super::resolve::only_files(hoards_root, hoard, &files))
+}
+
+/// Collects the changed files of `hoard`, or only those under `path` if it is given.
+#[tracing::instrument(skip(hoard))]
+pub(crate) async fn collect_diffs(
+    hoard: &Hoard,
+    hoard_name: &HoardName,
+    hoards_root: &HoardPath,
+    path: Option<&RelativePath>,
+    older_format_ok: bool,
+    scan: &ScanOptions,
+) -> Result<BTreeSet<HoardFileDiff>, super::Error> {
+    let only_path = path
+        .map(|path| only_path(hoards_root, hoard_name, hoard, path))
+        .transpose()?;
+    let hoard = only_path.as_ref().unwrap_or(hoard);
+    let diffs: BTreeSet<HoardFileDiff> = changed_diff_only_stream(
+        hoards_root,
+        hoard_name.clone(),
+        hoard,
+        older_format_ok,
+        scan,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!("failed to create diff stream: {}", err);
+        super::Error::Diff(err)
+    })?
+    .try_collect()
+    .await
+    .map_err(super::Error::Diff)?;
+
+    Ok(diffs)
+}
+
+/// Writes the changed files of `hoard` to `out`, returning whether any were found.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(hoard, out))]
+pub(crate) async fn run_diff(
+    hoard: &Hoard,
+    hoard_name: &HoardName,
+    hoards_root: &HoardPath,
+    path: Option<&RelativePath>,
+    options: Options,
+    older_format_ok: bool,
+    scan: &ScanOptions,
+    out: &mut impl Write,
+) -> Result<bool, super::Error> {
+    let _span = tracing::trace_span!("run_diff").entered();
+    tracing::trace!("running the diff command");
+    let diffs = Box::pin(collect_diffs(
+        hoard,
+        hoard_name,
+        hoards_root,
+        path,
+        older_format_ok,
+        scan,
+    ))
+    .await?;
+    let has_changes = !diffs.is_empty();
+    let changes = if options.follow_renames {
+        pair_renames(&diffs)
+    } else {
+        diffs.iter().map(Change::File).collect()
+    };
+
+    match (options.format, options.group_by) {
+        (DiffFormat::Json, _) => write_json(out, &changes, options)?,
+        (DiffFormat::Text, Some(DiffGrouping::Pile)) => {
+            write_grouped_by_pile(out, &changes, options)?;
+        }
+        (DiffFormat::Text, None) => {
+            for change in &changes {
+                write_change(out, *change, options)?;
+            }
+        }
+    }
+
+    Ok(has_changes)
+}
+
+/// Returns the most recent operation on any system for `hoard_name` that happened at or
+/// before `against`.
+#[tracing::instrument(level = "debug")]
+async fn operation_at_or_before(
+    hoard_name: &HoardName,
+    against: OffsetDateTime,
+    older_format_ok: bool,
+) -> Result<Option<Operation>, Error> {
+    let mut closest: Option<Operation> = None;
+    for (_, dir) in get_all_history_dirs().await? {
+        for operation in Operation::all_from_history_dir(&dir, hoard_name).await? {
+            let operation = operation.into_readable_version(older_format_ok)?;
+            if operation.timestamp() > against {
+                continue;
+            }
+            if closest
+                .as_ref()
+                .map_or(true, |closest| operation.timestamp() > closest.timestamp())
+            {
+                closest = Some(operation);
+            }
+        }
+    }
+
+    Ok(closest)
+}
+
+/// Compares the current system files of a hoard against the checksums recorded by the
+/// operation closest to, but not after, `against`, returning whether any file changed.
+#[tracing::instrument(skip(hoard, out))]
+pub(crate) async fn run_diff_against(
+    hoard: &Hoard,
+    hoard_name: &HoardName,
+    hoards_root: &HoardPath,
+    against: OffsetDateTime,
+    older_format_ok: bool,
+    scan: &ScanOptions,
+    out: &mut impl Write,
+) -> Result<bool, super::Error> {
+    let Some(operation) = operation_at_or_before(hoard_name, against, older_format_ok).await?
+    else {
+        return Err(Error::NoOperation {
+            hoard: hoard_name.clone(),
+            timestamp: format_timestamp(against),
+        }
+        .into());
+    };
+
+    let since = format_timestamp(operation.timestamp());
+    writeln!(
+        out,
+        "{hoard_name}: comparing against {} at {since}",
+        operation.direction()
+    )?;
+
+    let mut recorded: HashMap<(PileName, RelativePath), Checksum> = operation
+        .all_files_with_checksums()
+        .filter_map(|info| {
+            let checksum = info.checksum()?.clone();
+            Some((
+                (info.pile_name().clone(), info.relative_path().clone()),
+                checksum,
+            ))
+        })
+        .collect();
+
+    let mut changes: BTreeMap<String, &str> = BTreeMap::new();
+    // The same file may be found through both the hoard and the system path.
+    let files: BTreeSet<HoardItem> = all_files_stream(hoards_root, hoard_name, hoard, scan)
+        .await
+        .map_err(Error::Files)?
+        .try_collect()
+        .await
+        .map_err(Error::Files)?;
+    for file in files {
+        let old = recorded.remove(&(file.pile_name().clone(), file.relative_path().clone()));
+        let typ = old.as_ref().map_or_else(
+            || {
+                hoard
+                    .get_pile(file.pile_name())
+                    .and_then(|pile| pile.config.checksum_type)
+                    .unwrap_or_default()
+            },
+            Checksum::typ,
+        );
+        let current = file.system_checksum(typ).await.map_err(Error::IO)?;
+        let change = match (old, current) {
+            (Some(old), Some(current)) if old != current => "modified",
+            (None, Some(_)) => "created",
+            (Some(_), None) => "deleted",
+            _ => continue,
+        };
+        changes.insert(file.system_path().display().to_string(), change);
+    }
+
+    // Files recorded by the operation that no longer exist in the hoard or on the system.
+    let piles: HashMap<_, _> = hoard
+        .get_paths(hoards_root.join(&RelativePath::from(hoard_name)))
+        .map(|(pile_name, hoard_prefix, system_prefix)| (pile_name, (hoard_prefix, system_prefix)))
+        .collect();
+    for (pile_name, rel_path) in recorded.into_keys() {
+        let Some((hoard_prefix, system_prefix)) = piles.get(&pile_name) else {
+            continue;
+        };
+        let file = HoardItem::new(
+            pile_name,
+            hoard_prefix.clone(),
+            system_prefix.clone(),
+            rel_path,
+        );
+        if !file.system_path().exists() {
+            changes.insert(file.system_path().display().to_string(), "deleted");
+        }
+    }
+
+    for (path, change) in &changes {
+        writeln!(out, "{path}: {change} since {since}")?;
+    }
+
+    Ok(!changes.is_empty())
+}
@@ -0,0 +1,373 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use futures::TryStreamExt;
+use thiserror::Error;
+use tokio::{fs, io};
+use uuid::Uuid;
+
+use crate::checkers::history::get_all_history_dirs;
+use crate::checkers::history::operation::util::file_is_log;
+use crate::checkers::history::operation::{Error as OperationError, Operation, OperationImpl};
+use crate::checksum::Checksum;
+use crate::hoard::iter::{all_files_stream, Error as IterError, ScanOptions};
+use crate::hoard::Hoard;
+use crate::hoard_item::HoardItem;
+use crate::newtypes::HoardName;
+use crate::paths::HoardPath;
+
+/// The name of the directory in the data directory that unreadable operation logs are moved to.
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// Errors that may occur while checking the data directory.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to list the files stored in a hoard.
+    #[error("failed to list hoard files: {0}")]
+    Iterator(#[from] IterError),
+    /// An I/O error occurred while checking or fixing the data directory.
+    #[error("I/O error while checking the data directory: {0}")]
+    IO(#[from] io::Error),
+    /// Problems were found that were not fixed.
+    #[error("found {0} problem(s) in the data directory")]
+    Problems(usize),
+}
+
+/// A problem found in the data directory.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Problem {
+    /// A directory in the hoards root for a hoard that is not configured.
+    OrphanedHoard(PathBuf),
+    /// An operation log that is incomplete or cannot be parsed.
+    UnreadableLog {
+        /// The system that wrote the log.
+        system: Uuid,
+        /// The name of the directory the log is in, which is the name of its hoard.
+        hoard: String,
+        /// The path to the log.
+        path: PathBuf,
+    },
+    /// An operation log written by a newer version of Hoard.
+    NewerLog {
+        /// The name of the directory the log is in, which is the name of its hoard.
+        hoard: String,
+        /// The path to the log.
+        path: PathBuf,
+        /// The format version of the log.
+        version: u32,
+    },
+    /// A stored file that is not recorded by the latest operation log of its hoard.
+    Unlogged(PathBuf),
+    /// A stored file whose content does not match the checksum recorded by the latest operation
+    /// log of its hoard.
+    ChecksumMismatch {
+        /// The path to the stored file.
+        path: PathBuf,
+        /// The recorded checksum.
+        expected: Checksum,
+        /// The checksum of the stored file.
+        actual: Checksum,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OrphanedHoard(path) => write!(
+                f,
+                "{}: hoard data exists but no hoard with this name is configured",
+                path.display()
+            ),
+            Self::UnreadableLog { path, .. } => {
+                write!(f, "{}: operation log cannot be read", path.display())
+            }
+            Self::NewerLog { path, version, .. } => write!(
+                f,
+                "{}: log written by a newer hoard version (format version {}), please upgrade",
+                path.display(),
+                version
+            ),
+            Self::Unlogged(path) => write!(
+                f,
+                "{}: stored file is not recorded by any operation log",
+                path.display()
+            ),
+            Self::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: checksum mismatch (expected {}, found {})",
+                path.display(),
+                expected,
+                actual
+            ),
+        }
+    }
+}
+
+/// Returns a problem for each directory in `hoards_root` that is not a configured hoard.
+async fn orphaned_hoards(
+    hoards_root: &HoardPath,
+    hoards: &HashMap<HoardName, Hoard>,
+) -> Result<Vec<Problem>, Error> {
+    let mut problems = Vec::new();
+    let mut entries = match fs::read_dir(hoards_root).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(problems),
+        Err(error) => return Err(error.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let configured = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<HoardName>().ok())
+            .is_some_and(|name| hoards.contains_key(&name));
+        if path.is_dir() && !configured {
+            problems.push(Problem::OrphanedHoard(path));
+        }
+    }
+    problems.sort_unstable();
+    Ok(problems)
+}
+
+/// Reads every operation log, returning a problem for each that cannot be read or was written by a
+/// newer version of Hoard, and the latest
+/// readable operation for each hoard directory name.
+async fn read_logs() -> Result<(Vec<Problem>, HashMap<String, Operation>), Error> {
+    let mut problems = Vec::new();
+    let mut latest: HashMap<String, Operation> = HashMap::new();
+    for (system, dir) in get_all_history_dirs().await? {
+        let mut hoard_dirs = fs::read_dir(&dir).await?;
+        while let Some(hoard_dir) = hoard_dirs.next_entry().await? {
+            if !hoard_dir.path().is_dir() {
+                continue;
+            }
+            let hoard = hoard_dir.file_name().to_string_lossy().into_owned();
+            let mut logs = fs::read_dir(hoard_dir.path()).await?;
+            while let Some(log) = logs.next_entry().await? {
+                let path = log.path();
+                if !file_is_log(&path) {
+                    continue;
+                }
+                match Operation::read_file(&path).await {
+                    Ok(Some(operation)) => {
+                        let is_latest = latest
+                            .get(&hoard)
+                            .map_or(true, |other| operation.timestamp() > other.timestamp());
+                        if is_latest {
+                            latest.insert(hoard.clone(), operation);
+                        }
+                    }
+                    Err(OperationError::NewerVersion(version)) => {
+                        problems.push(Problem::NewerLog {
+                            hoard: hoard.clone(),
+                            path,
+                            version,
+                        });
+                    }
+                    Ok(None) | Err(_) => problems.push(Problem::UnreadableLog {
+                        system,
+                        hoard: hoard.clone(),
+                        path,
+                    }),
+                }
+            }
+        }
+    }
+    problems.sort_unstable();
+    Ok((problems, latest))
+}
+
+/// Returns a problem for each file stored in `hoard` that `operation` does not record or
+/// records with a different checksum.
+async fn check_stored_files(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    operation: Option<&Operation>,
+    options: &ScanOptions,
+) -> Result<Vec<Problem>, Error> {
+    // The same file may be found through both the hoard and the system path.
+    let files: BTreeSet<HoardItem> = all_files_stream(hoards_root, hoard_name, hoard, options)
+        .await?
+        .try_filter(|file| futures::future::ready(file.hoard_path().is_file()))
+        .try_collect()
+        .await?;
+
+    let mut problems = Vec::new();
+    for file in files {
+        let path = file.hoard_path().to_path_buf();
+        let Some(expected) =
+            operation.and_then(|op| op.checksum_for(file.pile_name(), file.relative_path()))
+        else {
+            problems.push(Problem::Unlogged(path));
+            continue;
+        };
+        let config = hoard
+            .get_pile(file.pile_name())
+            .map(|pile| pile.config.clone())
+            .unwrap_or_default();
+        match file
+            .hoard_checksum_with_config(expected.typ(), &config)
+            .await?
+        {
+            Some(actual) if actual != expected => problems.push(Problem::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            }),
+            _ => tracing::debug!("{}: checksum matches", path.display()),
+        }
+    }
+    Ok(problems)
+}
+
+/// Asks whether to delete `path`. Reaching the end of input counts as no.
+///
+/// Nothing is deleted without asking if input does not come from a terminal.
+fn confirm_delete(path: &Path) -> io::Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        tracing::warn!(
+            "not deleting {} without confirmation: pass --yes to delete it",
+            path.display()
+        );
+        return Ok(false);
+    }
+
+    let mut stdout = std::io::stdout();
+    loop {
+        write!(stdout, "delete {}? [y/N] ", path.display())?;
+        stdout.flush()?;
+
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            return Ok(false);
+        }
+
+        match input.trim() {
+            "y" | "yes" => return Ok(true),
+            "" | "n" | "no" => return Ok(false),
+            choice => tracing::warn!("invalid choice {}", choice),
+        }
+    }
+}
+
+/// Tries to fix `problem`, returning whether it was fixed.
+///
+/// Unreadable logs are moved to the quarantine directory, keeping the system and hoard
+/// directories they were in. Orphaned hoard data is deleted if `yes` is true or the user agrees
+/// when asked. Other problems, including logs that need a newer version of Hoard, cannot be fixed
+/// automatically.
+async fn fix_problem(problem: &Problem, yes: bool) -> Result<bool, Error> {
+    match problem {
+        Problem::UnreadableLog {
+            system,
+            hoard,
+            path,
+        } => {
+            let dir = crate::dirs::data_dir()
+                .join(QUARANTINE_DIR_NAME)
+                .join(system.to_string())
+                .join(hoard);
+            fs::create_dir_all(&dir).await?;
+            let quarantined = dir.join(path.file_name().unwrap_or_default());
+            fs::rename(path, &quarantined).await?;
+            tracing::info!(
+                "moved unreadable log {} to {}",
+                path.display(),
+                quarantined.display()
+            );
+            Ok(true)
+        }
+        Problem::OrphanedHoard(path) => {
+            if !yes && !confirm_delete(path)? {
+                tracing::info!("keeping {}", path.display());
+                return Ok(false);
+            }
+            fs::remove_dir_all(path).await?;
+            tracing::info!("deleted {}", path.display());
+            Ok(true)
+        }
+        Problem::NewerLog { .. } | Problem::Unlogged(_) | Problem::ChecksumMismatch { .. } => {
+            Ok(false)
+        }
+    }
+}
+
+/// Checks the hoards root and history directories for problems and reports each one.
+///
+/// If `fix` is true, each problem that can be fixed is fixed after being reported. Listing the
+/// files of a hoard requires reading its operation logs, so the stored files of a hoard with
+/// unreadable logs are only checked once those logs are quarantined. Succeeds if no problems
+/// remain.
+#[tracing::instrument(skip(hoards))]
+pub(crate) async fn run_doctor(
+    hoards_root: &HoardPath,
+    hoards: &HashMap<HoardName, Hoard>,
+    fix: bool,
+    yes: bool,
+    options: &ScanOptions,
+) -> Result<(), super::Error> {
+    let mut found = 0;
+    let mut remaining = 0;
+    let mut blocked = HashSet::new();
+
+    let mut problems = orphaned_hoards(hoards_root, hoards).await?;
+    let (unreadable, latest) = read_logs().await?;
+    problems.extend(unreadable);
+    for problem in &problems {
+        tracing::warn!("{}", problem);
+        found += 1;
+        if !fix || !fix_problem(problem, yes).await? {
+            remaining += 1;
+            if let Problem::UnreadableLog { hoard, .. } | Problem::NewerLog { hoard, .. } = problem
+            {
+                blocked.insert(hoard.as_str());
+            }
+        }
+    }
+
+    let mut names: Vec<&HoardName> = hoards.keys().collect();
+    names.sort_unstable();
+    for hoard_name in names {
+        if blocked.contains(hoard_name.as_ref()) {
+            tracing::warn!(
+                "{}: skipping stored file checks until its unreadable operation logs are fixed",
+                hoard_name
+            );
+            continue;
+        }
+        let operation = latest.get(hoard_name.as_ref());
+        for problem in check_stored_files(
+            hoards_root,
+            hoard_name,
+            &hoards[hoard_name],
+            operation,
+            options,
+        )
+        .await?
+        {
+            tracing::warn!("{}", problem);
+            found += 1;
+            remaining += 1;
+        }
+    }
+
+    if found == 0 {
+        tracing::info!("no problems found: the data directory is healthy");
+        Ok(())
+    } else if remaining == 0 {
+        tracing::info!("fixed {} problem(s)", found);
+        Ok(())
+    } else {
+        if !fix {
+            tracing::info!("run `hoard doctor --fix` to fix what can be fixed automatically");
+        }
+        Err(Error::Problems(remaining).into())
+    }
+}
@@ -1,15 +1,20 @@
 use std::{
     io::{stderr, stdin, stdout, IsTerminal},
     path::{Path, PathBuf},
-    process::ExitStatus,
+    process::{Command, ExitStatus},
 };
 
 use tap::TapFallible;
 use thiserror::Error;
 use tokio::{fs, io};
 
+use crate::config::builder::{Builder, Error as BuilderError};
+
 use super::DEFAULT_CONFIG;
 
+/// The environment variable checked for a terminal editor before `$EDITOR`.
+const VISUAL_ENV: &str = "VISUAL";
+
 /// Errors that may occur while running the edit command.
 #[derive(Debug, Error)]
 #[allow(variant_size_differences)]
@@ -26,6 +31,45 @@ pub enum Error {
     /// A directory was provided as the configuration file path.
     #[error("expected a configuration file, found a directory: {0}")]
     IsDirectory(PathBuf),
+    /// The edited configuration file was saved, but could not be loaded.
+    #[error("the configuration was saved, but is not valid: {0}")]
+    Invalid(#[source] Box<BuilderError>),
+}
+
+/// Returns the command that opens `file` for editing.
+///
+/// In a terminal, this prefers `$VISUAL`, then `$EDITOR`, then the system default editor.
+/// Otherwise, the system default graphical editor is used.
+fn editor_command(file: &Path, in_terminal: bool) -> Result<Command, Error> {
+    if !in_terminal {
+        return open_cmd::open(file.to_path_buf()).map_err(|error| {
+            tracing::error!(%error, "failed to generate editor command");
+            Error::Start(error)
+        });
+    }
+
+    match std::env::var_os(VISUAL_ENV).filter(|visual| !visual.is_empty()) {
+        Some(visual) => {
+            tracing::debug!("opening {} with ${}", file.display(), VISUAL_ENV);
+            let mut cmd = Command::new(visual);
+            cmd.arg(file);
+            Ok(cmd)
+        }
+        None => open_cmd::open_editor(file.to_path_buf()).map_err(|error| {
+            tracing::error!(%error, "failed to generate CLI editor command");
+            Error::Start(error)
+        }),
+    }
+}
+
+/// Loads the configuration file at `path` to report any errors right after editing it.
+async fn check_config(path: &Path) -> Result<(), Error> {
+    Builder::from_file(path)
+        .await
+        .and_then(Builder::build)
+        .map(|_| ())
+        .map_err(|error| Error::Invalid(Box::new(error)))
+        .tap_err(crate::tap_log_error)
 }
 
 /// Edit the configuration file at `path`.
@@ -35,10 +79,11 @@ pub enum Error {
 /// 1. Creates a temporary file by either copying the existing file at `path` or, if
 ///    the file does not exist, populating it with the example configuration.
 /// 2. Opens the file...
-///    1. In `$EDITOR` if the variable exists and `hoard` is running in a terminal.
+///    1. In `$VISUAL` or `$EDITOR` if either variable exists and `hoard` is running in a terminal.
 ///    2. Or in the system default graphical editor for the file
 /// 3. If the editor process exits without failure...
 ///    1. The temporary file is copied to the given `path`.
+///    2. The configuration is loaded from `path` to report any errors in it.
 /// 4. The temporary file is deleted.
 ///
 /// # Errors
@@ -68,17 +113,8 @@ pub(crate) async fn run_edit(path: &Path) -> Result<(), super::Error> {
             })?;
     }
 
-    let mut cmd = if stdin().is_terminal() && stderr().is_terminal() && stdout().is_terminal() {
-        open_cmd::open_editor(tmp_file.clone()).map_err(|error| {
-            tracing::error!(%error, "failed to generate CLI editor command");
-            Error::Start(error)
-        })?
-    } else {
-        open_cmd::open(tmp_file.clone()).map_err(|error| {
-            tracing::error!(%error, "failed to generate editor command");
-            Error::Start(error)
-        })?
-    };
+    let in_terminal = stdin().is_terminal() && stderr().is_terminal() && stdout().is_terminal();
+    let mut cmd = editor_command(&tmp_file, in_terminal)?;
 
     let status = cmd.status().map_err(|error| {
         tracing::error!(%error, "failed to run editor command");
@@ -91,6 +127,7 @@ pub(crate) async fn run_edit(path: &Path) -> Result<(), super::Error> {
             tracing::error!(%error, "failed to copy temporary file ({}) to config file location ({})", tmp_file.display(), path.display());
             Error::IO(error)
         })?;
+        check_config(path).await?;
     } else {
         tracing::error!("edit command exited with status {}", status);
         return Err(super::Error::Edit(Error::Exit(status)));
@@ -98,3 +135,32 @@ pub(crate) async fn run_edit(path: &Path) -> Result<(), super::Error> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_config() {
+        let dir = tempfile::tempdir().expect("creating tempdir should succeed");
+        let path = dir.path().join("config.toml");
+
+        fs::write(&path, DEFAULT_CONFIG)
+            .await
+            .expect("writing config should succeed");
+        check_config(&path)
+            .await
+            .expect("sample configuration should be valid");
+
+        fs::write(&path, "[hoards\n")
+            .await
+            .expect("writing config should succeed");
+        let error = check_config(&path)
+            .await
+            .expect_err("malformed configuration should be invalid");
+        assert!(
+            matches!(&error, Error::Invalid(inner) if matches!(**inner, BuilderError::DeserializeTOML { .. })),
+            "unexpected error: {error}"
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use tap::TapFallible;
+use thiserror::Error;
+use tokio::{fs, io};
+
+use crate::checkers::history::operation::{
+    export_operations, import_operations, Error as OperationError, HistoryExport,
+};
+
+/// Errors that may occur while exporting or importing operation logs.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read or write the operation logs.
+    #[error("failed to process operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// An I/O error occurred while reading or writing the export file.
+    #[error("I/O error while accessing export file: {0}")]
+    IO(#[from] io::Error),
+    /// The export file could not be (de)serialized.
+    #[error("failed to (de)serialize history export: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// Subcommands for working with the operation logs of all systems.
+#[derive(Clone, PartialEq, Eq, Debug, Subcommand)]
+pub enum HistoryCommand {
+    /// Export the operation logs of all systems and hoards into a single JSON file.
+    Export {
+        /// The file to write the exported logs to.
+        path: PathBuf,
+    },
+    /// Import operation logs from a file created with `hoard history export`.
+    Import {
+        /// The file to read exported logs from.
+        path: PathBuf,
+    },
+}
+
+async fn export_history(path: &PathBuf) -> Result<(), Error> {
+    let history = export_operations().await?;
+    let content = serde_json::to_vec_pretty(&history).tap_err(|error| {
+        tracing::error!(%error, "failed to serialize history export");
+    })?;
+    fs::write(path, &content).await.tap_err(|error| {
+        tracing::error!(%error, "failed to write history export to {}", path.display());
+    })?;
+    tracing::info!(
+        "exported {} operation logs to {}",
+        history.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+async fn import_history(path: &PathBuf) -> Result<(), Error> {
+    let content = fs::read(path).await.tap_err(|error| {
+        tracing::error!(%error, "failed to read history export from {}", path.display());
+    })?;
+    let history: HistoryExport = serde_json::from_slice(&content).tap_err(|error| {
+        tracing::error!(%error, "failed to parse history export from {}", path.display());
+    })?;
+    let count = history.len();
+    import_operations(history).await?;
+    tracing::info!("imported {} operation logs from {}", count, path.display());
+    Ok(())
+}
+
+#[tracing::instrument]
+pub(crate) async fn run_history(command: &HistoryCommand) -> Result<(), super::Error> {
+    match command {
+        HistoryCommand::Export { path } => export_history(path).await?,
+        HistoryCommand::Import { path } => import_history(path).await?,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use tokio::{fs, io};
+
+use crate::checkers::history::operation::{Error as OperationError, OperationImpl};
+use crate::hoard::Hoard;
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::{HoardPath, RelativePath};
+
+use super::verify::latest_operation;
+
+/// Errors that may occur while gathering information about a hoard.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read the latest operation log.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// An I/O error occurred while reading the hoard directory.
+    #[error("I/O error while reading hoard files: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// The number of files and their total size within a pile or hoard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Usage {
+    files: u64,
+    bytes: u64,
+}
+
+impl Usage {
+    /// Walks `path` in the hoard, counting all files and their sizes.
+    async fn of_path(path: &Path) -> Result<Self, Error> {
+        let mut usage = Self::default();
+        let mut to_visit = vec![path.to_path_buf()];
+        while let Some(path) = to_visit.pop() {
+            let metadata = match fs::metadata(&path).await {
+                Ok(metadata) => metadata,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(error) => {
+                    tracing::error!(%error, "failed to read metadata for {}", path.display());
+                    return Err(error.into());
+                }
+            };
+
+            if metadata.is_dir() {
+                let mut entries = fs::read_dir(&path).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    to_visit.push(entry.path());
+                }
+            } else if metadata.is_file() {
+                usage.files += 1;
+                usage.bytes += metadata.len();
+            }
+        }
+
+        Ok(usage)
+    }
+
+    fn add(&mut self, other: Self) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+    }
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} file(s), {} bytes", self.files, self.bytes)
+    }
+}
+
+#[tracing::instrument(skip(hoard))]
+pub(crate) async fn run_info(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+) -> Result<(), super::Error> {
+    let hoard_prefix = hoards_root.join(&RelativePath::from(hoard_name));
+    let mut piles = BTreeMap::<PileName, Usage>::new();
+    for (pile_name, hoard_path, _) in hoard.get_paths(hoard_prefix) {
+        let usage = Usage::of_path(hoard_path.as_ref()).await?;
+        piles.entry(pile_name).or_default().add(usage);
+    }
+
+    let mut total = Usage::default();
+    for usage in piles.values() {
+        total.add(*usage);
+    }
+
+    let last_operation = match latest_operation(hoard_name)
+        .await
+        .map_err(Error::Operation)?
+    {
+        None => String::from("never"),
+        Some(operation) => {
+            let timestamp = operation.timestamp();
+            let timestamp = timestamp
+                .format(&Rfc3339)
+                .unwrap_or_else(|_| timestamp.to_string());
+            format!("{} at {}", operation.direction(), timestamp)
+        }
+    };
+
+    let mut lines = vec![
+        format!("{hoard_name}: {total}"),
+        format!("last operation: {last_operation}"),
+    ];
+    for (pile_name, usage) in &piles {
+        if pile_name.as_ref().is_some() {
+            lines.push(format!("pile {pile_name}: {usage}"));
+        }
+    }
+    tracing::info!("{}", lines.join("\n"));
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use tokio::io;
+use uuid::Uuid;
+
+use crate::checkers::history::get_all_history_dirs;
+use crate::checkers::history::operation::{
+    Error as OperationError, Operation, OperationImpl, OperationType,
+};
+use crate::hoard::Direction;
+use crate::newtypes::HoardName;
+use crate::paths::RelativePath;
+
+/// Errors that may occur while showing the operation history of a hoard.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read the operation logs.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// An I/O error occurred while listing system history directories.
+    #[error("I/O error while reading history: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// A single logged operation, with the files it changed.
+#[derive(Debug)]
+struct LogEntry {
+    system: Uuid,
+    timestamp: OffsetDateTime,
+    direction: Direction,
+    /// The changed files, sorted by path. Paths in named piles are prefixed with the pile name.
+    changes: Vec<(OperationType, PathBuf)>,
+}
+
+impl LogEntry {
+    fn from_operation(system: Uuid, operation: &Operation) -> Result<Self, OperationError> {
+        let mut changes = Vec::new();
+        for file in operation.all_files_with_checksums() {
+            if let Some(op_type) =
+                operation.file_operation(file.pile_name(), file.relative_path())?
+            {
+                let path = RelativePath::from(file.pile_name())
+                    .to_path_buf()
+                    .join(file.relative_path().to_path_buf());
+                changes.push((op_type, path));
+            }
+        }
+        changes.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        Ok(Self {
+            system,
+            timestamp: operation.timestamp(),
+            direction: operation.direction(),
+            changes,
+        })
+    }
+
+    fn count(&self, op_type: OperationType) -> usize {
+        self.changes
+            .iter()
+            .filter(|(other, _)| *other == op_type)
+            .count()
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = self
+            .timestamp
+            .format(&Rfc3339)
+            .unwrap_or_else(|_| self.timestamp.to_string());
+        let system = self.system.as_simple().to_string();
+        write!(
+            f,
+            "{timestamp} {} {}: {} created, {} modified, {} deleted",
+            &system[..8],
+            self.direction,
+            self.count(OperationType::Create),
+            self.count(OperationType::Modify),
+            self.count(OperationType::Delete),
+        )
+    }
+}
+
+#[tracing::instrument(level = "debug")]
+async fn read_log(hoard: &HoardName) -> Result<Vec<LogEntry>, Error> {
+    let mut entries = Vec::new();
+    for (system, dir) in get_all_history_dirs().await? {
+        for operation in Operation::all_from_history_dir(&dir, hoard).await? {
+            let operation = operation.into_latest_version()?;
+            entries.push(LogEntry::from_operation(system, &operation)?);
+        }
+    }
+
+    entries.sort_by_key(|entry| (entry.timestamp, entry.system));
+    Ok(entries)
+}
+
+#[tracing::instrument]
+pub(crate) async fn run_log(hoard: &HoardName, files: bool) -> Result<(), super::Error> {
+    let entries = read_log(hoard).await?;
+    if entries.is_empty() {
+        tracing::info!("{}: no recorded operations", hoard);
+        return Ok(());
+    }
+
+    for entry in entries {
+        tracing::info!("{}", entry);
+        if files {
+            for (op_type, path) in &entry.changes {
+                let action = match op_type {
+                    OperationType::Create => "created",
+                    OperationType::Modify => "modified",
+                    OperationType::Delete => "deleted",
+                };
+                tracing::info!("    {action:<8} {}", path.display());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,31 +1,83 @@
 //! See [`Command`].
 
 mod backup_restore;
+mod blame;
 mod cleanup;
 mod diff;
+mod doctor;
 mod edit;
+mod history;
+mod info;
 mod init;
 mod list;
+mod log;
+mod pick;
+mod plan;
+mod prune;
+mod resolve;
+mod restore_at;
+mod restore_into;
+mod snapshot;
 mod status;
+mod summary;
 mod upgrade;
+mod validate;
+mod verify;
+mod which;
 
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Args, Parser};
 use thiserror::Error;
+use time::OffsetDateTime;
 
-pub(crate) use backup_restore::{run_backup, run_restore};
+pub(crate) use backup_restore::{run_backup, run_restore, Options as BackupRestoreOptions};
+pub(crate) use blame::run_blame;
 pub(crate) use cleanup::run_cleanup;
-pub(crate) use diff::run_diff;
+pub(crate) use diff::collect_diffs;
+pub(crate) use diff::{run_diff, run_diff_against, Options as DiffOptions};
+pub(crate) use doctor::run_doctor;
 pub(crate) use edit::run_edit;
+pub(crate) use history::run_history;
+pub(crate) use info::run_info;
 pub(crate) use init::run_init;
 pub(crate) use list::run_list;
+pub(crate) use log::run_log;
+pub(crate) use pick::run_pick_restore;
+pub(crate) use plan::run_plan;
+pub(crate) use prune::run_prune;
+pub(crate) use resolve::run_resolve;
+pub(crate) use restore_at::run_restore_at;
+pub(crate) use restore_into::run_restore_into;
+pub(crate) use snapshot::{run_export, run_import};
+pub(crate) use status::hoard_status;
 pub(crate) use status::run_status;
+pub(crate) use summary::write_summary;
 pub(crate) use upgrade::run_upgrade;
+pub(crate) use validate::run_validate;
+pub(crate) use verify::run_verify;
+pub(crate) use which::run_which;
 
-use crate::newtypes::HoardName;
-pub use backup_restore::Error as BackupRestoreError;
+use crate::newtypes::{HoardName, HoardSelector};
+use crate::paths::RelativePath;
+pub use backup_restore::{Error as BackupRestoreError, Report as BackupRestoreReport};
+pub use blame::Error as BlameError;
+pub use diff::{ColorChoice, DiffFormat, DiffGrouping, Error as DiffAgainstError};
+pub use doctor::Error as DoctorError;
 pub use edit::Error as EditError;
+pub use history::{Error as HistoryError, HistoryCommand};
+pub use info::Error as InfoError;
+pub use log::Error as LogError;
+pub use pick::{Error as PickError, FileVersion};
+pub use prune::LogAge;
+pub use resolve::{Error as ResolveError, ResolveStrategy};
+pub use restore_at::Error as RestoreAtError;
+pub use restore_into::Error as RestoreIntoError;
+pub use snapshot::Error as SnapshotError;
+pub use status::HoardStatus;
+pub use summary::Summary;
+pub use validate::{Error as ValidateError, UnresolvedProblems};
+pub use verify::{Error as VerifyError, VerifyFraction};
 
 const DEFAULT_CONFIG: &str = include_str!("../../config.toml.sample");
 
@@ -38,6 +90,9 @@ pub enum Error {
     /// Error occurred while backing up a hoard.
     #[error("failed to back up: {0}")]
     Backup(#[source] BackupRestoreError),
+    /// Error occurred while running the blame command.
+    #[error("error while running hoard blame: {0}")]
+    Blame(#[from] blame::Error),
     /// Error occurred while running [`Checkers`](crate::checkers::Checkers).
     #[error("error while running or saving consistency checks: {0}")]
     Checkers(#[from] crate::checkers::Error),
@@ -53,9 +108,21 @@ pub enum Error {
     /// Error occurred while running the diff command.
     #[error("error while running hoard diff: {0}")]
     Diff(#[source] crate::hoard::iter::Error),
+    /// Error occurred while diffing against a past operation.
+    #[error("error while running hoard diff --against: {0}")]
+    DiffAgainst(#[from] diff::Error),
+    /// Error occurred while checking the data directory for problems.
+    #[error("error while running hoard doctor: {0}")]
+    Doctor(#[from] doctor::Error),
     /// Error occurred while running the edit command.
     #[error("error while running hoard edit: {0}")]
     Edit(#[from] edit::Error),
+    /// Error occurred while exporting or importing operation logs.
+    #[error("error while running hoard history: {0}")]
+    History(#[from] history::Error),
+    /// Error occurred while running the info command.
+    #[error("error while running hoard info: {0}")]
+    Info(#[from] info::Error),
     /// Error occurred while initializing Hoard.
     #[error("failed to create {path}: {error}")]
     Init {
@@ -65,60 +132,348 @@ pub enum Error {
         #[source]
         error: std::io::Error,
     },
+    /// Error occurred while showing the operation history of a hoard.
+    #[error("error while running hoard log: {0}")]
+    Log(#[from] log::Error),
+    /// Error occurred while writing command output.
+    #[error("failed to write command output: {0}")]
+    Output(#[from] std::io::Error),
+    /// Error occurred while restoring individually picked files.
+    #[error("failed to restore picked files: {0}")]
+    Pick(#[from] pick::Error),
+    /// Error occurred while running the plan command.
+    #[error("error while running hoard plan: {0}")]
+    Plan(#[source] crate::hoard::iter::Error),
+    /// An error occurred while running the prune command.
+    #[error("error after pruning {success_count} log files: {error}")]
+    Prune {
+        /// The number of files successfully pruned.
+        success_count: u32,
+        /// The error that occurred.
+        #[source]
+        error: crate::checkers::history::operation::Error,
+    },
+    /// Error occurred while resolving conflicting changes.
+    #[error("error while running hoard resolve: {0}")]
+    Resolve(#[from] resolve::Error),
     /// Error occurred while restoring a hoard.
     #[error("failed to restore: {0}")]
     Restore(#[source] backup_restore::Error),
+    /// Error occurred while restoring hoards as of a point in time.
+    #[error("failed to restore to a point in time: {0}")]
+    RestoreAt(#[from] restore_at::Error),
+    /// Error occurred while restoring hoards into another directory.
+    #[error("failed to restore into another directory: {0}")]
+    RestoreInto(#[from] restore_into::Error),
+    /// Error occurred while exporting or importing a hoard snapshot.
+    #[error("error while exporting or importing a hoard: {0}")]
+    Snapshot(#[from] snapshot::Error),
     /// Error occurred while running the status command.
     #[error("error while running hoard status: {0}")]
     Status(#[source] crate::hoard::iter::Error),
     /// Error occurred while upgrading formats.
     #[error("error while running hoard upgrade: {0}")]
     Upgrade(#[from] upgrade::Error),
+    /// Error occurred while validating the configuration.
+    #[error("error while running hoard validate: {0}")]
+    Validate(#[from] validate::Error),
+    /// Error occurred while verifying hoard files.
+    #[error("error while running hoard verify: {0}")]
+    Verify(#[from] verify::Error),
+}
+
+/// Options for `hoard backup`.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Args)]
+pub struct BackupArgs {
+    /// Delete system files after they are successfully backed up, moving them into the hoard.
+    #[clap(long = "move")]
+    pub move_files: bool,
+    /// Skip hoards with no changes instead of writing a new operation log.
+    #[clap(long)]
+    pub if_changed: bool,
+    /// Which hoards and files to back up.
+    #[clap(flatten)]
+    pub selection: SelectionArgs,
+}
+
+/// Options for `hoard restore`.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Args)]
+pub struct RestoreArgs {
+    /// Only create files that are missing from the system, leaving existing files untouched.
+    #[clap(long)]
+    pub missing_only: bool,
+    /// Which hoards and files to restore.
+    #[clap(flatten)]
+    pub selection: SelectionArgs,
+}
+
+/// Options for choosing which hoards and files `hoard backup` and `hoard restore` act on.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Args)]
+pub struct SelectionArgs {
+    /// Only back up or restore files matching this gitignore-style pattern, relative to the root
+    /// of each pile. May be given multiple times.
+    #[clap(long, value_name = "GLOB")]
+    pub only: Vec<glob::Pattern>,
+    /// Skip files matching this gitignore-style pattern, relative to the root of each pile. May be
+    /// given multiple times.
+    #[clap(long, value_name = "GLOB")]
+    pub ignore: Vec<glob::Pattern>,
+    /// Skip this hoard when acting on all hoards, i.e. when no hoard names are given. May be
+    /// given multiple times.
+    #[clap(long = "exclude-hoard", value_name = "HOARD")]
+    pub exclude_hoards: Vec<HoardName>,
 }
 
 /// The possible subcommands for `hoard`.
-#[derive(Clone, PartialEq, Eq, Debug, Parser)]
+#[derive(Clone, PartialEq, Eq, Debug, Default, Parser)]
 pub enum Command {
-    /// Loads all configuration for validation.
-    /// If the configuration loads and builds, this command succeeds.
+    /// Loads all configuration for validation without touching any files.
+    /// Succeeds if the configuration builds and no problems are found with the configured hoards.
+    #[default]
     Validate,
     /// Cleans up the operation logs for all known systems.
-    Cleanup,
+    Cleanup {
+        /// List the log files that would be deleted without deleting them.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Checks the data directory for problems: hoard data with no configured hoard, operation
+    /// logs that cannot be read, and stored files that are missing from or do not match the
+    /// latest operation log.
+    Doctor {
+        /// Fix the problems that can be fixed: move unreadable logs to the quarantine directory
+        /// and, after asking, delete the data of hoards that are no longer configured.
+        #[clap(long)]
+        fix: bool,
+        /// Delete the data of hoards that are no longer configured without asking.
+        #[clap(long, requires = "fix")]
+        yes: bool,
+    },
+    /// Deletes operation logs beyond the given age and/or count for all known systems.
+    ///
+    /// A log is only deleted if it is beyond every given limit. The most recent log, and the
+    /// most recent backup log, for each system and hoard are always kept.
+    Prune {
+        /// Delete logs older than this age, e.g. `30d`. Units are `s`, `m`, `h`, `d`, and `w`.
+        #[clap(long, required_unless_present = "keep")]
+        older_than: Option<LogAge>,
+        /// Keep this many of the most recent logs for each system and hoard.
+        #[clap(long)]
+        keep: Option<usize>,
+    },
     /// Back up the given hoard(s).
     Backup {
-        /// The name(s) of the hoard(s) to back up. Will back up all hoards if empty.
-        hoards: Vec<HoardName>,
+        /// The name(s) of the hoard(s) to back up. Will back up all hoards if empty. Use
+        /// `hoard:pile` to only back up one pile of a hoard.
+        hoards: Vec<HoardSelector>,
+        /// Print the summary of changed files as JSON instead of text.
+        #[clap(long)]
+        json: bool,
+        /// Options that only apply to backups.
+        #[clap(flatten)]
+        args: BackupArgs,
     },
     /// Restore the files from the given hoard to the filesystem.
     Restore {
-        /// The name(s) of the hoard(s) to restore. Will restore all hoards if empty.
-        hoards: Vec<HoardName>,
+        /// The name(s) of the hoard(s) to restore. Will restore all hoards if empty. Use
+        /// `hoard:pile` to only restore one pile of a hoard.
+        hoards: Vec<HoardSelector>,
+        /// Interactively choose which files to restore from a single hoard.
+        #[clap(long)]
+        pick: bool,
+        /// Restore only this file from a single hoard, without prompting. The path is relative to
+        /// its pile and may be prefixed with the pile name. May be given multiple times.
+        #[clap(long)]
+        pick_file: Vec<RelativePath>,
+        /// Which version of picked files to restore. Only `latest` is currently available.
+        #[clap(long)]
+        version: Option<FileVersion>,
+        /// Print what the restore would create, modify, or delete without changing any files.
+        #[clap(long, conflicts_with_all = ["pick", "pick_file", "version"])]
+        dry_run: bool,
+        /// Restore files to the versions recorded by the operation logs at or before this RFC 3339
+        /// timestamp. Fails without changing anything if a version is no longer in the hoard.
+        #[clap(long, value_parser = diff::parse_timestamp, conflicts_with_all = ["pick", "pick_file", "version"])]
+        at: Option<OffsetDateTime>,
+        /// Print the summary of changed files as JSON instead of text.
+        #[clap(long, conflicts_with_all = ["pick", "pick_file", "version", "at"])]
+        json: bool,
+        /// Restore every file in the hoard under this directory instead of the configured paths,
+        /// keeping their layout. No operation log is written.
+        #[clap(long, value_name = "DIR", conflicts_with_all = ["pick", "pick_file", "version", "at", "dry_run", "json"])]
+        into: Option<PathBuf>,
+        /// Options that only apply to restores.
+        #[clap(flatten)]
+        args: RestoreArgs,
+    },
+    /// Resolve changes made both locally and remotely, or outside of Hoard, one file at a time.
+    ///
+    /// Changes made in only one place are kept. For every other changed file, choose whether to
+    /// keep the local version, the hoard version, or both, or to skip the file. An operation log
+    /// is written for the resolved files.
+    Resolve {
+        /// The name of the hoard to resolve.
+        hoard: HoardName,
+        /// Keep the `local` or `remote` (hoard) version of every conflicting file without
+        /// prompting.
+        #[clap(long)]
+        strategy: Option<ResolveStrategy>,
     },
     /// List configured hoards.
     List,
+    /// Export or import the operation logs of all systems.
+    History {
+        /// What to do with the operation logs.
+        #[clap(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Pack the files stored in a hoard, their checksums, and the hoard's operation logs into a
+    /// single tar archive that can be moved to another system.
+    Export {
+        /// The name of the hoard to export.
+        hoard: HoardName,
+        /// The file to write the snapshot archive to. Must not exist.
+        #[clap(long, value_name = "DIR")]
+        output: PathBuf,
+    },
+    /// Import a snapshot created with `hoard export` into an empty hoard, after verifying that
+    /// every file matches its checksum.
+    Import {
+        /// The snapshot archive to import.
+        path: PathBuf,
+    },
+    /// Show the number of files and total size stored in a hoard, along with the time of the
+    /// most recent operation.
+    Info {
+        /// The name of the hoard to show information about.
+        hoard: HoardName,
+    },
+    /// Show which environments apply to this machine and the paths each pile of a hoard
+    /// resolves to.
+    Which {
+        /// The name of the hoard to show the paths of.
+        hoard: HoardName,
+    },
     /// Open the configuration file in the system default editor.
     Edit,
     /// Initialize a new Hoard setup.
     Init,
+    /// Show the history of backups and restores of a hoard, across all systems.
+    Log {
+        /// The name of the hoard to show the history of.
+        hoard: HoardName,
+        /// Also list the files created, modified, or deleted by each operation.
+        #[clap(long)]
+        files: bool,
+    },
+    /// Show which system and operation most recently created, modified, or deleted a file.
+    Blame {
+        /// The name of the hoard containing the file.
+        hoard: HoardName,
+        /// The path of the file, relative to its pile. For named piles, the path may also be
+        /// prefixed with the pile name.
+        path: RelativePath,
+    },
     /// Show which files differ for a given hoard. Optionally show unified diffs for text files
     /// too.
     Diff {
-        /// The name of the hoard to diff.
-        hoard: HoardName,
+        /// The name of the hoard to diff. Use `hoard:pile` to only diff one pile of a hoard.
+        hoard: HoardSelector,
+        /// Only diff this file, or the files in this directory. The path is relative to its pile
+        /// and may be prefixed with the pile name.
+        path: Option<RelativePath>,
         /// If true, prints unified diffs for text files.
         #[clap(long, short)]
         verbose: bool,
+        /// If true, shows changes from the system's perspective (i.e. what a restore would do)
+        /// instead of the hoard's.
+        #[clap(long)]
+        reverse: bool,
+        /// Group changed files by the given category. Only `pile` is currently available.
+        #[clap(long)]
+        group_by: Option<DiffGrouping>,
+        /// The format to print changes in: `text` (the default) or `json`.
+        #[clap(long, default_value_t)]
+        format: DiffFormat,
+        /// When to highlight changed words in unified diffs: `auto` (the default), `always`, or
+        /// `never`.
+        #[clap(long, default_value_t)]
+        color: ColorChoice,
+        /// Show a file that was deleted and a file with the same content that was created in the
+        /// same pile as a single rename. Off by default, since it is only useful for piles that
+        /// are directories.
+        #[clap(long)]
+        follow_renames: bool,
+        /// Compare system files against the checksums recorded by the most recent operation at
+        /// or before this RFC 3339 timestamp, instead of against the current hoard.
+        #[clap(long, value_parser = diff::parse_timestamp, conflicts_with_all = ["path", "verbose", "reverse", "group_by", "format", "color", "follow_renames"])]
+        against: Option<OffsetDateTime>,
+    },
+    /// Show what a backup and a restore of the given hoard would each do, side by side, without
+    /// changing any files.
+    Plan {
+        /// The name of the hoard to plan for.
+        hoard: HoardName,
     },
     /// Provides a summary of which hoards have changes and if the diffs can be resolved
     /// with a single command.
-    Status,
+    Status {
+        /// Only show the status of this hoard. Shows every hoard if not given.
+        hoard: Option<HoardName>,
+        /// Print the status of each hoard as JSON instead of text.
+        #[clap(long)]
+        json: bool,
+    },
     /// Upgrade internal file formats to the newest format.
-    Upgrade,
+    Upgrade {
+        /// Also re-checksum hoard files whose latest operation log on this system used a
+        /// different checksum type than is now configured for their pile.
+        #[clap(long)]
+        rehash: bool,
+    },
+    /// Verify that the files in the given hoard(s) match the checksums recorded during the
+    /// last operation.
+    Verify {
+        /// The name(s) of the hoard(s) to verify. Will verify all hoards if empty.
+        hoards: Vec<HoardName>,
+        /// Only verify this fraction (between 0 and 1) of the files in each hoard per run.
+        /// Progress is tracked across runs so that every file is eventually verified.
+        #[clap(long)]
+        verify_fraction: Option<VerifyFraction>,
+    },
 }
 
-impl Default for Command {
-    fn default() -> Self {
-        Self::Validate
+impl Command {
+    /// Returns whether this command may change the hoards or operation logs, and so must hold
+    /// the [data directory lock](crate::lock) while it runs.
+    #[must_use]
+    pub fn locks_data_dir(&self) -> bool {
+        match self {
+            Command::Backup { .. }
+            | Command::Prune { .. }
+            | Command::Resolve { .. }
+            | Command::Upgrade { .. }
+            | Command::Import { .. }
+            | Command::History {
+                command: HistoryCommand::Import { .. },
+            } => true,
+            Command::Cleanup { dry_run } | Command::Restore { dry_run, .. } => !dry_run,
+            Command::Doctor { fix, .. } => *fix,
+            Command::Validate
+            | Command::List
+            | Command::History { .. }
+            | Command::Export { .. }
+            | Command::Info { .. }
+            | Command::Which { .. }
+            | Command::Edit
+            | Command::Init
+            | Command::Log { .. }
+            | Command::Blame { .. }
+            | Command::Diff { .. }
+            | Command::Plan { .. }
+            | Command::Status { .. }
+            | Command::Verify { .. } => false,
+        }
     }
 }
 
@@ -131,4 +486,22 @@ mod tests {
         // The default command is validate if one is not given
         assert_eq!(Command::Validate, Command::default());
     }
+
+    #[test]
+    fn only_mutating_commands_lock_data_dir() {
+        assert!(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        }
+        .locks_data_dir());
+        assert!(Command::Cleanup { dry_run: false }.locks_data_dir());
+        assert!(!Command::Cleanup { dry_run: true }.locks_data_dir());
+        assert!(!Command::Status {
+            hoard: None,
+            json: false
+        }
+        .locks_data_dir());
+        assert!(!Command::List.locks_data_dir());
+    }
 }
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use tokio::io;
+
+use crate::checkers::history::operation::{Error as OperationError, ItemOperation, OperationImpl};
+use crate::hoard::{Direction, Hoard};
+use crate::hoard_item::HoardItem;
+use crate::newtypes::HoardName;
+use crate::paths::{HoardPath, RelativePath};
+use crate::retry::RetryPolicy;
+
+use super::backup_restore::{copy_file, fix_permissions, recorded_metadata, Error as RestoreError};
+use super::blame::matches_path;
+use super::verify::latest_operation;
+
+/// Errors that may occur while restoring individually picked files.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Picking files only works with exactly one hoard.
+    #[error("exactly one hoard must be given when picking files, got {0}")]
+    SingleHoard(usize),
+    /// No operations have been recorded for the hoard, so there are no files to pick from.
+    #[error("no operations recorded for hoard {0}, nothing to restore")]
+    NoHistory(HoardName),
+    /// The picked file does not exist in the hoard.
+    #[error("no such file in the hoard: {0}")]
+    NoSuchFile(RelativePath),
+    /// Failed to read the operation logs.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// Failed to restore a picked file.
+    #[error("failed to restore file: {0}")]
+    Restore(#[from] RestoreError),
+    /// An I/O error occurred while prompting for files.
+    #[error("I/O error while picking files: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// Which version of a file to restore.
+///
+/// File contents are only stored for the most recent backup, so only `latest` is available.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileVersion {
+    /// The version currently stored in the hoard.
+    #[default]
+    Latest,
+}
+
+impl FromStr for FileVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(Self::Latest),
+            _ => Err(format!(
+                "unsupported file version {s}: only \"latest\" is currently available"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for FileVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Latest => write!(f, "latest"),
+        }
+    }
+}
+
+/// Returns all files recorded in the latest operation that still exist in the hoard.
+async fn pickable_files(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+) -> Result<(Vec<HoardItem>, String), Error> {
+    let Some(operation) = latest_operation(hoard_name).await? else {
+        return crate::create_log_error(Error::NoHistory(hoard_name.clone()));
+    };
+
+    let piles: HashMap<_, _> = hoard
+        .get_paths(hoards_root.join(&RelativePath::from(hoard_name)))
+        .map(|(pile_name, hoard_prefix, system_prefix)| (pile_name, (hoard_prefix, system_prefix)))
+        .collect();
+
+    let mut files: Vec<HoardItem> = operation
+        .all_files_with_checksums()
+        .filter_map(|info| {
+            let (hoard_prefix, system_prefix) = piles.get(info.pile_name())?;
+            let item = HoardItem::new(
+                info.pile_name().clone(),
+                hoard_prefix.clone(),
+                system_prefix.clone(),
+                info.relative_path().clone(),
+            );
+            item.is_file().then_some(item)
+        })
+        .collect();
+    files.sort_unstable();
+
+    let timestamp = operation.timestamp();
+    let timestamp = timestamp
+        .format(&Rfc3339)
+        .unwrap_or_else(|_| timestamp.to_string());
+    let description = format!("{} at {}", operation.direction(), timestamp);
+
+    Ok((files, description))
+}
+
+/// Returns the path of `file` as shown to the user, prefixed with the pile name, if any.
+pub(super) fn display_path(file: &HoardItem) -> String {
+    let rel_path = file.relative_path().to_path_buf();
+    match file.pile_name().as_ref() {
+        None => rel_path.display().to_string(),
+        Some(pile_name) if rel_path.as_os_str().is_empty() => pile_name.to_string(),
+        Some(pile_name) => format!("{}/{}", pile_name, rel_path.display()),
+    }
+}
+
+/// Lists all pickable files and reads the user's selection from stdin.
+fn prompt_for_files(files: &[HoardItem], description: &str) -> Result<Vec<HoardItem>, Error> {
+    let mut stdout = std::io::stdout();
+    for (index, file) in files.iter().enumerate() {
+        writeln!(
+            stdout,
+            "{:>3}) {} [versions: {} ({})]",
+            index + 1,
+            display_path(file),
+            FileVersion::Latest,
+            description
+        )?;
+    }
+    write!(
+        stdout,
+        "Enter the numbers of the files to restore, separated by spaces: "
+    )?;
+    stdout.flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let picked = input
+        .split_whitespace()
+        .filter_map(|choice| match choice.parse::<usize>() {
+            Ok(number) if (1..=files.len()).contains(&number) => Some(files[number - 1].clone()),
+            _ => {
+                tracing::warn!("ignoring invalid choice {}", choice);
+                None
+            }
+        })
+        .collect();
+
+    Ok(picked)
+}
+
+/// Restores the chosen version of individual files from a single hoard.
+///
+/// If `paths` is empty, the user is prompted to choose files interactively. Picked files are
+/// copied directly from the hoard, so no consistency checks are run and no operation log is
+/// written.
+#[tracing::instrument(skip(hoards))]
+pub(crate) async fn run_pick_restore<'a>(
+    hoards_root: &HoardPath,
+    hoards: Vec<(&'a HoardName, &'a Hoard)>,
+    paths: &[RelativePath],
+    version: FileVersion,
+    copy_attributes: bool,
+    no_permissions: bool,
+    retry: RetryPolicy,
+) -> Result<(), super::Error> {
+    let [(hoard_name, hoard)] = hoards[..] else {
+        return Err(Error::SingleHoard(hoards.len()).into());
+    };
+
+    let (files, description) = pickable_files(hoards_root, hoard_name, hoard).await?;
+    let picked = if paths.is_empty() {
+        prompt_for_files(&files, &description)?
+    } else {
+        paths
+            .iter()
+            .map(|path| {
+                files
+                    .iter()
+                    .find(|file| matches_path(file.pile_name(), file.relative_path(), path))
+                    .cloned()
+                    .ok_or_else(|| Error::NoSuchFile(path.clone()))
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    let recorded = recorded_metadata(hoard_name, no_permissions)
+        .await
+        .map_err(Error::Restore)?;
+    for file in picked {
+        let with_attributes = copy_attributes
+            || hoard
+                .get_pile(file.pile_name())
+                .and_then(|pile| pile.config.copy_attributes)
+                .unwrap_or(false);
+        copy_file(&file, Direction::Restore, with_attributes, retry)
+            .await
+            .map_err(Error::Restore)?;
+        let path = display_path(&file);
+        fix_permissions(
+            hoard,
+            &ItemOperation::Modify(file),
+            Direction::Restore,
+            recorded.as_ref(),
+        )
+        .await
+        .map_err(Error::Restore)?;
+        tracing::info!("{}/{}: restored {} version", hoard_name, path, version);
+    }
+
+    Ok(())
+}
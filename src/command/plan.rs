@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use futures::TryStreamExt;
+
+use crate::checkers::history::operation::ItemOperation;
+use crate::hoard::iter::{operation_stream, ScanOptions};
+use crate::hoard::{Direction, Hoard};
+use crate::hoard_item::CachedHoardItem;
+use crate::newtypes::HoardName;
+use crate::paths::HoardPath;
+
+/// The widest action name, used to align the output columns.
+const COLUMN_WIDTH: usize = 7;
+
+/// Returns a short description of what `operation` would do, or `None` if it does nothing.
+fn action(operation: &ItemOperation<CachedHoardItem>) -> Option<(PathBuf, &'static str)> {
+    let (file, action) = match operation {
+        ItemOperation::Create(file) => (file, "create"),
+        ItemOperation::Modify(file) => (file, "modify"),
+        ItemOperation::Delete(file) => (file, "delete"),
+        ItemOperation::Nothing(_) | ItemOperation::DoesNotExist(_) => return None,
+    };
+    Some((file.system_path().to_path_buf(), action))
+}
+
+/// Returns the action for each file that would change if the hoard were synchronized in the
+/// given direction.
+async fn planned_actions(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    direction: Direction,
+    options: &ScanOptions,
+) -> Result<BTreeMap<PathBuf, &'static str>, super::Error> {
+    operation_stream(hoards_root, hoard_name.clone(), hoard, direction, options)
+        .await
+        .map_err(super::Error::Plan)?
+        .try_filter_map(|operation| futures::future::ok(action(&operation)))
+        .try_collect()
+        .await
+        .map_err(super::Error::Plan)
+}
+
+#[tracing::instrument(skip(hoard))]
+pub(crate) async fn run_plan(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    options: &ScanOptions,
+) -> Result<(), super::Error> {
+    let backup =
+        planned_actions(hoards_root, hoard_name, hoard, Direction::Backup, options).await?;
+    let restore =
+        planned_actions(hoards_root, hoard_name, hoard, Direction::Restore, options).await?;
+
+    let mut files: Vec<&PathBuf> = backup.keys().chain(restore.keys()).collect();
+    files.sort();
+    files.dedup();
+
+    if files.is_empty() {
+        tracing::info!(
+            "{}: up to date -- neither backup nor restore would change anything",
+            hoard_name
+        );
+        return Ok(());
+    }
+
+    tracing::info!(
+        "{:COLUMN_WIDTH$}  {:COLUMN_WIDTH$}  file",
+        "backup",
+        "restore"
+    );
+    for file in files {
+        let backup = backup.get(file).copied().unwrap_or("-");
+        let restore = restore.get(file).copied().unwrap_or("-");
+        tracing::info!(
+            "{:COLUMN_WIDTH$}  {:COLUMN_WIDTH$}  {}",
+            backup,
+            restore,
+            file.display()
+        );
+    }
+
+    Ok(())
+}
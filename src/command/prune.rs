@@ -0,0 +1,95 @@
+use std::fmt;
+use std::str::FromStr;
+
+use time::{Duration, OffsetDateTime};
+
+use crate::checkers::history::operation::{prune_operations, remove_empty_history_dirs};
+
+/// How old an operation log must be before `hoard prune --older-than` deletes it.
+///
+/// Parsed from a whole number followed by a unit: `s` (seconds), `m` (minutes), `h` (hours),
+/// `d` (days), or `w` (weeks), e.g. `30d`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogAge(Duration);
+
+impl FromStr for LogAge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| format!("age {s} is missing a unit (one of s, m, h, d, w)"))?;
+        let (amount, unit) = s.split_at(split);
+        let amount: i64 = amount
+            .parse()
+            .map_err(|err| format!("invalid age {s}: {err}"))?;
+        let duration = match unit {
+            "s" => Duration::seconds(amount),
+            "m" => Duration::minutes(amount),
+            "h" => Duration::hours(amount),
+            "d" => Duration::days(amount),
+            "w" => Duration::weeks(amount),
+            _ => {
+                return Err(format!(
+                    "invalid unit {unit} in age {s}, expected one of s, m, h, d, w"
+                ))
+            }
+        };
+        Ok(Self(duration))
+    }
+}
+
+impl fmt::Display for LogAge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[tracing::instrument]
+pub(crate) async fn run_prune(
+    older_than: Option<LogAge>,
+    keep: Option<usize>,
+) -> Result<(), super::Error> {
+    let cutoff = older_than.map(|age| OffsetDateTime::now_utc() - age.0);
+    let summary = prune_operations(cutoff, keep)
+        .await
+        .map_err(|(summary, error)| super::Error::Prune {
+            success_count: summary.deleted,
+            error,
+        })?;
+    let dirs = remove_empty_history_dirs(false)
+        .await
+        .map_err(|error| super::Error::Prune {
+            success_count: summary.deleted,
+            error,
+        })?;
+
+    tracing::info!(
+        "pruned {} log files, freeing {} bytes",
+        summary.deleted,
+        summary.bytes_freed
+    );
+    tracing::info!("removed {} empty directories", dirs);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_age() {
+        assert_eq!(LogAge::from_str("90s"), Ok(LogAge(Duration::seconds(90))));
+        assert_eq!(LogAge::from_str("15m"), Ok(LogAge(Duration::minutes(15))));
+        assert_eq!(LogAge::from_str("12h"), Ok(LogAge(Duration::hours(12))));
+        assert_eq!(LogAge::from_str("30d"), Ok(LogAge(Duration::days(30))));
+        assert_eq!(LogAge::from_str("2w"), Ok(LogAge(Duration::weeks(2))));
+    }
+
+    #[test]
+    fn test_parse_invalid_log_age() {
+        for age in ["", "30", "d", "-1d", "1.5d", "30y", "30 d"] {
+            assert!(LogAge::from_str(age).is_err(), "{age} should be invalid");
+        }
+    }
+}
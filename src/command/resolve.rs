@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use futures::TryStreamExt;
+use glob::Pattern;
+use tap::TapFallible;
+use thiserror::Error;
+use tokio::{fs, io};
+
+use crate::filters::Selection;
+use crate::hoard::iter::{changed_diff_only_stream, DiffSource, Error as IterError, HoardFileDiff};
+use crate::hoard::Hoard;
+use crate::hoard_item::HoardItem;
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::HoardPath;
+
+use super::backup_restore::Options as BackupOptions;
+use super::pick::display_path;
+
+/// Errors that may occur while resolving conflicting changes.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to determine which files changed.
+    #[error("failed to diff hoard files: {0}")]
+    Iterator(#[from] IterError),
+    /// An I/O error occurred while prompting or moving files.
+    #[error("I/O error while resolving: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// Which version of a conflicting file to keep when resolving without prompting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveStrategy {
+    /// Keep the file on this system.
+    Local,
+    /// Keep the file in the hoard.
+    Remote,
+}
+
+impl FromStr for ResolveStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            _ => Err(format!(
+                "unsupported resolve strategy {s}: expected \"local\" or \"remote\""
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ResolveStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Local => write!(f, "local"),
+            Self::Remote => write!(f, "remote"),
+        }
+    }
+}
+
+/// What to do with a single changed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    KeepLocal,
+    KeepHoard,
+    /// Keep the hoard version in place and the local version next to it, renamed.
+    KeepBoth,
+    Skip,
+}
+
+impl From<ResolveStrategy> for Resolution {
+    fn from(strategy: ResolveStrategy) -> Self {
+        match strategy {
+            ResolveStrategy::Local => Self::KeepLocal,
+            ResolveStrategy::Remote => Self::KeepHoard,
+        }
+    }
+}
+
+/// A changed file, with what is needed to show it to the user.
+struct ChangedFile {
+    file: HoardItem,
+    description: String,
+    unified_diff: Option<String>,
+    is_text: bool,
+}
+
+impl ChangedFile {
+    /// Whether both versions can be kept, which requires both to exist as text files.
+    fn can_keep_both(&self) -> bool {
+        self.is_text && self.file.system_path().is_file() && self.file.hoard_path().is_file()
+    }
+}
+
+/// Returns the resolution of changes that do not conflict, or `None` if `diff` conflicts.
+fn automatic_resolution(diff: &HoardFileDiff) -> Option<Resolution> {
+    match diff {
+        HoardFileDiff::BinaryModified { diff_source, .. }
+        | HoardFileDiff::TextModified { diff_source, .. }
+        | HoardFileDiff::Created { diff_source, .. }
+        | HoardFileDiff::Deleted { diff_source, .. } => match diff_source {
+            DiffSource::Local => Some(Resolution::KeepLocal),
+            DiffSource::Remote => Some(Resolution::KeepHoard),
+            DiffSource::Mixed | DiffSource::Unknown => None,
+        },
+        // Backing up again replaces the partially written hoard file.
+        HoardFileDiff::Corrupt(_) => Some(Resolution::KeepLocal),
+        HoardFileDiff::Unchanged(_)
+        | HoardFileDiff::Nonexistent(_)
+        | HoardFileDiff::Excluded(_)
+        | HoardFileDiff::Ignored(_) => Some(Resolution::Skip),
+    }
+}
+
+fn changed_file(diff: HoardFileDiff) -> ChangedFile {
+    let (file, description, unified_diff, is_text) = match diff {
+        HoardFileDiff::BinaryModified { file, diff_source } => (
+            file,
+            format!("binary file modified {diff_source}"),
+            None,
+            false,
+        ),
+        HoardFileDiff::TextModified {
+            file,
+            unified_diff,
+            diff_source,
+        } => (
+            file,
+            format!("text file modified {diff_source}"),
+            unified_diff,
+            true,
+        ),
+        HoardFileDiff::Created {
+            file,
+            unified_diff,
+            diff_source,
+        } => {
+            let is_text = file.is_text();
+            (
+                file,
+                format!("created {diff_source}"),
+                unified_diff,
+                is_text,
+            )
+        }
+        HoardFileDiff::Deleted { file, diff_source } => {
+            (file, format!("deleted {diff_source}"), None, false)
+        }
+        HoardFileDiff::Corrupt(file)
+        | HoardFileDiff::Unchanged(file)
+        | HoardFileDiff::Nonexistent(file)
+        | HoardFileDiff::Excluded(file)
+        | HoardFileDiff::Ignored(file) => (file, String::new(), None, false),
+    };
+
+    ChangedFile {
+        file: file.into(),
+        description,
+        unified_diff,
+        is_text,
+    }
+}
+
+/// Shows `changed` and reads which version to keep from stdin.
+///
+/// Reaching the end of input skips the file.
+fn prompt_for_resolution(
+    hoard_name: &HoardName,
+    changed: &ChangedFile,
+) -> Result<Resolution, Error> {
+    let mut stdout = std::io::stdout();
+    writeln!(
+        stdout,
+        "{}/{}: {}",
+        hoard_name,
+        display_path(&changed.file),
+        changed.description
+    )?;
+    if let Some(unified_diff) = &changed.unified_diff {
+        writeln!(stdout, "{unified_diff}")?;
+    }
+
+    let can_keep_both = changed.can_keep_both();
+    let choices = if can_keep_both {
+        "keep [l]ocal, keep [h]oard, keep [b]oth, or [s]kip"
+    } else {
+        "keep [l]ocal, keep [h]oard, or [s]kip"
+    };
+
+    loop {
+        write!(stdout, "{choices}? ")?;
+        stdout.flush()?;
+
+        let mut input = String::new();
+        if std::io::stdin().read_line(&mut input)? == 0 {
+            return Ok(Resolution::Skip);
+        }
+
+        match input.trim() {
+            "l" | "local" => return Ok(Resolution::KeepLocal),
+            "h" | "hoard" => return Ok(Resolution::KeepHoard),
+            "b" | "both" if can_keep_both => return Ok(Resolution::KeepBoth),
+            "s" | "skip" => return Ok(Resolution::Skip),
+            choice => tracing::warn!("invalid choice {}", choice),
+        }
+    }
+}
+
+/// Returns the path of a file next to `file` that does not exist yet, relative to its pile, for
+/// keeping the local version of `file`.
+fn renamed_local_path(file: &HoardItem) -> PathBuf {
+    let rel_path = file.relative_path().to_path_buf();
+    let file_name = rel_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let system_prefix: &Path = file.system_prefix().as_ref();
+    let mut renamed = rel_path.with_file_name(format!("{file_name}.local"));
+    let mut count = 1;
+    while system_prefix.join(&renamed).exists() {
+        renamed = rel_path.with_file_name(format!("{file_name}.local.{count}"));
+        count += 1;
+    }
+    renamed
+}
+
+/// Returns a gitignore-style pattern that only matches `rel_path`, relative to its pile.
+pub(super) fn exact_pattern(rel_path: &Path) -> Option<Pattern> {
+    let path = rel_path
+        .components()
+        .map(|component| Pattern::escape(&component.as_os_str().to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join("/");
+    Pattern::new(&format!("/{path}"))
+        .tap_err(|error| tracing::warn!(%error, "failed to create pattern for {}", path))
+        .ok()
+}
+
+/// Returns a copy of `hoard` that only includes `files`, given as pile names and paths relative
+/// to the pile. Directories include everything inside of them, and an empty path includes the
+/// whole pile.
+pub(super) fn only_files(
+    hoards_root: &HoardPath,
+    hoard: &Hoard,
+    files: &[(PileName, PathBuf)],
+) -> Hoard {
+    let mut patterns: HashMap<PileName, Vec<Pattern>> = HashMap::new();
+    for (pile_name, rel_path) in files {
+        if rel_path.as_os_str().is_empty() {
+            patterns.entry(pile_name.clone()).or_default();
+        } else if let Some(pattern) = exact_pattern(rel_path) {
+            patterns.entry(pile_name.clone()).or_default().push(pattern);
+        }
+    }
+
+    let everything = [Pattern::new("*").expect("* should be a valid pattern")];
+    let mut hoard = hoard.clone();
+    let pile_names: Vec<PileName> = hoard
+        .get_paths(hoards_root.clone())
+        .map(|(pile_name, _, _)| pile_name)
+        .collect();
+    for pile_name in pile_names {
+        let selection = match patterns.get(&pile_name) {
+            Some(patterns) => Selection::new(patterns, &[]),
+            None => Selection::new(&[], &everything),
+        };
+        if let Some(pile) = hoard.get_pile_mut(&pile_name) {
+            pile.config.selection = selection;
+        }
+    }
+    hoard
+}
+
+/// Resolves the changes to a single hoard and records the result with operation logs.
+///
+/// Changes from only this system or only another system are kept as they are. For each file
+/// changed in both places or outside of Hoard, the version to keep is taken from `strategy` or,
+/// if `None`, chosen interactively. Skipped files are left unresolved and are not logged.
+#[tracing::instrument(skip(hoard, options))]
+pub(crate) async fn run_resolve(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    strategy: Option<ResolveStrategy>,
+    options: BackupOptions,
+) -> Result<(), super::Error> {
+    let scan = options.scan_options();
+    let diffs: Vec<HoardFileDiff> =
+        changed_diff_only_stream(hoards_root, hoard_name.clone(), hoard, false, &scan)
+            .await
+            .map_err(Error::from)?
+            // Files larger than `max_file_size` or on the checksum blocklist are never backed up
+            // or restored.
+            .try_filter(|diff| {
+                std::future::ready(!matches!(
+                    diff,
+                    HoardFileDiff::Excluded(_) | HoardFileDiff::Ignored(_)
+                ))
+            })
+            .try_collect()
+            .await
+            .map_err(Error::from)?;
+
+    if diffs.is_empty() {
+        tracing::info!("{}: nothing to resolve", hoard_name);
+        return Ok(());
+    }
+
+    let mut keep_local = Vec::new();
+    let mut keep_hoard = Vec::new();
+    for diff in diffs {
+        let automatic = automatic_resolution(&diff);
+        let changed = changed_file(diff);
+        let resolution = match (automatic, strategy) {
+            (Some(resolution), _) => resolution,
+            (None, Some(strategy)) => strategy.into(),
+            (None, None) => prompt_for_resolution(hoard_name, &changed)?,
+        };
+
+        let file = changed.file;
+        let path = display_path(&file);
+        let pile_file = (file.pile_name().clone(), file.relative_path().to_path_buf());
+        match resolution {
+            Resolution::KeepLocal => {
+                tracing::info!("{}/{}: keeping local version", hoard_name, path);
+                keep_local.push(pile_file);
+            }
+            Resolution::KeepHoard => {
+                tracing::info!("{}/{}: keeping hoard version", hoard_name, path);
+                keep_hoard.push(pile_file);
+            }
+            Resolution::KeepBoth => {
+                let renamed = renamed_local_path(&file);
+                let system_prefix: &Path = file.system_prefix().as_ref();
+                fs::copy(file.system_path(), system_prefix.join(&renamed))
+                    .await
+                    .map_err(Error::from)?;
+                tracing::info!(
+                    "{}/{}: keeping hoard version, local version saved as {}",
+                    hoard_name,
+                    path,
+                    renamed.display()
+                );
+                keep_hoard.push(pile_file);
+                keep_local.push((file.pile_name().clone(), renamed));
+            }
+            Resolution::Skip => tracing::info!("{}/{}: skipped", hoard_name, path),
+        }
+    }
+
+    // Restoring and backing up only the resolved files copies the version to keep and records
+    // it in an operation log. Skipped files keep their conflicting state.
+    let options = BackupOptions {
+        force: true,
+        move_files: false,
+        missing_only: false,
+        if_changed: false,
+        safe: false,
+        dry_run: false,
+        no_hooks: true,
+        require_selection: false,
+        ..options
+    };
+    let no_hooks = BTreeMap::new();
+    if !keep_hoard.is_empty() {
+        let hoard = only_files(hoards_root, hoard, &keep_hoard);
+        super::run_restore(hoards_root, [(hoard_name, &hoard)], &no_hooks, options).await?;
+    }
+    if !keep_local.is_empty() {
+        let hoard = only_files(hoards_root, hoard, &keep_local);
+        super::run_backup(hoards_root, [(hoard_name, &hoard)], &no_hooks, options).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!("local".parse(), Ok(ResolveStrategy::Local));
+        assert_eq!("remote".parse(), Ok(ResolveStrategy::Remote));
+        assert!("hoard".parse::<ResolveStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_exact_pattern_escapes_glob_characters() {
+        let pattern = exact_pattern(Path::new("saves/slot[1]*.sav")).unwrap();
+        assert_eq!(pattern.as_str(), "/saves/slot[[]1[]][*].sav");
+
+        let selection = Selection::new(&[pattern], &[]);
+        let keeps = |path: &str| selection.keep(&path.parse().unwrap(), false);
+        assert!(keeps("saves/slot[1]*.sav"));
+        assert!(!keeps("saves/slot1a.sav"));
+    }
+}
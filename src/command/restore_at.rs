@@ -0,0 +1,239 @@
+use std::collections::{BTreeMap, HashMap};
+
+use thiserror::Error;
+use time::OffsetDateTime;
+use tokio::io;
+
+use crate::checkers::history::get_all_history_dirs;
+use crate::checkers::history::operation::{
+    Error as OperationError, ItemOperation, Operation, OperationImpl,
+};
+use crate::checksum::Checksum;
+use crate::hoard::{Direction, Hoard};
+use crate::hoard_item::HoardItem;
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::{HoardPath, RelativePath};
+use crate::retry::RetryPolicy;
+
+use super::backup_restore::{copy_file, fix_permissions, restore_mtime, Error as RestoreError};
+use super::diff::format_timestamp;
+use super::pick::display_path;
+
+/// Errors that may occur while restoring hoards as of a point in time.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// No operation was recorded for the hoard at or before the requested time.
+    #[error("no operation recorded for hoard {hoard} at or before {timestamp}")]
+    NoOperation {
+        /// The hoard being restored.
+        hoard: HoardName,
+        /// The requested time, formatted as RFC 3339.
+        timestamp: String,
+    },
+    /// The hoard no longer contains the version some files had at the requested time.
+    #[error("cannot restore to {timestamp}, the hoard no longer has the version of these files from that time: {}", .files.join(", "))]
+    Unavailable {
+        /// The requested time, formatted as RFC 3339.
+        timestamp: String,
+        /// The files that cannot be restored, each prefixed with its hoard and pile name.
+        files: Vec<String>,
+    },
+    /// Failed to read the operation logs.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// Failed to restore a file.
+    #[error("failed to restore file: {0}")]
+    Restore(#[from] RestoreError),
+    /// An I/O error occurred while reading files or history directories.
+    #[error("I/O error while restoring: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// The checksum of each file in a hoard, or `None` if it was deleted.
+type FileChecksums = BTreeMap<(PileName, RelativePath), Option<Checksum>>;
+
+/// Replays the operation logs of `hoard_name` from all systems, up to and including `at`.
+///
+/// Returns the checksum each file had at that time, along with the last operation replayed.
+#[tracing::instrument(level = "debug")]
+async fn replay_until(
+    hoard_name: &HoardName,
+    at: OffsetDateTime,
+) -> Result<Option<(FileChecksums, Operation)>, Error> {
+    let mut operations = Vec::new();
+    for (_, dir) in get_all_history_dirs().await? {
+        for operation in Operation::all_from_history_dir(&dir, hoard_name).await? {
+            let operation = operation.into_latest_version()?;
+            if operation.timestamp() <= at {
+                operations.push(operation);
+            }
+        }
+    }
+    operations.sort_by_key(Operation::timestamp);
+
+    let mut files = BTreeMap::new();
+    for operation in &operations {
+        for info in operation.all_files_with_checksums() {
+            files.insert(
+                (info.pile_name().clone(), info.relative_path().clone()),
+                info.checksum().cloned(),
+            );
+        }
+    }
+
+    Ok(operations.pop().map(|last| (files, last)))
+}
+
+/// The files to copy to restore a hoard to a point in time.
+struct RestorePlan<'a> {
+    hoard_name: &'a HoardName,
+    hoard: &'a Hoard,
+    /// The last operation at or before the requested time, for file permissions.
+    operation: Operation,
+    files: Vec<HoardItem>,
+}
+
+/// Determines which files of `hoard` need to be copied to restore it as of `at`.
+///
+/// Any file whose content at that time is no longer in the hoard is added to `unavailable`.
+async fn plan_hoard<'a>(
+    hoards_root: &HoardPath,
+    hoard_name: &'a HoardName,
+    hoard: &'a Hoard,
+    at: OffsetDateTime,
+    unavailable: &mut Vec<String>,
+) -> Result<RestorePlan<'a>, Error> {
+    let Some((recorded, operation)) = replay_until(hoard_name, at).await? else {
+        return crate::create_log_error(Error::NoOperation {
+            hoard: hoard_name.clone(),
+            timestamp: format_timestamp(at),
+        });
+    };
+
+    let piles: HashMap<_, _> = hoard
+        .get_paths(hoards_root.join(&RelativePath::from(hoard_name)))
+        .map(|(pile_name, hoard_prefix, system_prefix)| (pile_name, (hoard_prefix, system_prefix)))
+        .collect();
+
+    let mut files = Vec::new();
+    for ((pile_name, rel_path), checksum) in recorded {
+        // Files that did not exist at that time are left alone.
+        let Some(checksum) = checksum else {
+            continue;
+        };
+        let Some((hoard_prefix, system_prefix)) = piles.get(&pile_name) else {
+            tracing::warn!(
+                "{}: skipping {} because its pile is no longer configured",
+                hoard_name,
+                rel_path.to_path_buf().display()
+            );
+            continue;
+        };
+        let file = HoardItem::new(
+            pile_name,
+            hoard_prefix.clone(),
+            system_prefix.clone(),
+            rel_path,
+        );
+
+        if file.hoard_checksum(checksum.typ()).await?.as_ref() != Some(&checksum) {
+            unavailable.push(format!("{hoard_name}/{}", display_path(&file)));
+        } else if file.system_checksum(checksum.typ()).await?.as_ref() == Some(&checksum) {
+            tracing::debug!("{}/{}: already up to date", hoard_name, display_path(&file));
+        } else {
+            files.push(file);
+        }
+    }
+
+    Ok(RestorePlan {
+        hoard_name,
+        hoard,
+        operation,
+        files,
+    })
+}
+
+/// Restores each of `hoards` to the state it was in at `at`, according to the operation logs.
+///
+/// Only the latest content of each file is kept in the hoard, so this can only restore files
+/// that have not changed since. If any file cannot be restored, nothing is changed and all such
+/// files are reported. Like picked files, no consistency checks are run and no operation log is
+/// written.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(skip(hoards))]
+pub(crate) async fn run_restore_at<'a>(
+    hoards_root: &HoardPath,
+    hoards: Vec<(&'a HoardName, &'a Hoard)>,
+    at: OffsetDateTime,
+    dry_run: bool,
+    copy_attributes: bool,
+    no_permissions: bool,
+    no_mtime: bool,
+    retry: RetryPolicy,
+) -> Result<(), super::Error> {
+    let mut unavailable = Vec::new();
+    let mut plans = Vec::new();
+    for (hoard_name, hoard) in hoards {
+        plans.push(plan_hoard(hoards_root, hoard_name, hoard, at, &mut unavailable).await?);
+    }
+
+    if !unavailable.is_empty() {
+        for file in &unavailable {
+            tracing::error!(
+                "{}: version from {} is no longer in the hoard",
+                file,
+                format_timestamp(at)
+            );
+        }
+        return Err(Error::Unavailable {
+            timestamp: format_timestamp(at),
+            files: unavailable,
+        }
+        .into());
+    }
+
+    for plan in plans {
+        let since = format_timestamp(plan.operation.timestamp());
+        let recorded = (!no_permissions).then_some(&plan.operation);
+        for file in plan.files {
+            let path = display_path(&file);
+            if dry_run {
+                tracing::info!(
+                    "{}/{}: would restore version from {}",
+                    plan.hoard_name,
+                    path,
+                    since
+                );
+                continue;
+            }
+            let with_attributes = copy_attributes
+                || plan
+                    .hoard
+                    .get_pile(file.pile_name())
+                    .and_then(|pile| pile.config.copy_attributes)
+                    .unwrap_or(false);
+            copy_file(&file, Direction::Restore, with_attributes, retry)
+                .await
+                .map_err(Error::Restore)?;
+            if !no_mtime {
+                restore_mtime(&file, &plan.operation);
+            }
+            fix_permissions(
+                plan.hoard,
+                &ItemOperation::Modify(file),
+                Direction::Restore,
+                recorded,
+            )
+            .await
+            .map_err(Error::Restore)?;
+            tracing::info!(
+                "{}/{}: restored version from {}",
+                plan.hoard_name,
+                path,
+                since
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+
+use futures::TryStreamExt;
+use thiserror::Error;
+
+use crate::checkers::history::operation::ItemOperation;
+use crate::hoard::iter::{all_files_stream, Error as IterError, ScanOptions};
+use crate::hoard::{Direction, Hoard};
+use crate::hoard_item::HoardItem;
+use crate::newtypes::HoardName;
+use crate::paths::{Error as PathError, HoardPath, SystemPath};
+
+use super::backup_restore::{
+    copy_file, fix_permissions, recorded_metadata, restore_mtime, Error as RestoreError,
+};
+use super::pick::display_path;
+
+/// Errors that may occur while restoring hoards into another directory.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A file cannot be restored under the given directory, e.g. because it is in the hoards root.
+    #[error("cannot restore into {}: {error}", path.display())]
+    InvalidTarget {
+        /// The path the file would be restored to.
+        path: PathBuf,
+        /// Why the path is invalid.
+        #[source]
+        error: PathError,
+    },
+    /// Failed to list the files in a hoard.
+    #[error("failed to list hoard files: {0}")]
+    Iterator(#[from] IterError),
+    /// Failed to restore a file.
+    #[error("failed to restore file: {0}")]
+    Restore(#[from] RestoreError),
+}
+
+/// Returns `path` as if the root of the filesystem were `into`.
+///
+/// On Windows, the drive letter is kept as a directory, so `C:\Users` becomes `{into}\C\Users`.
+fn rebase(into: &Path, path: &Path) -> PathBuf {
+    path.components()
+        .fold(into.to_path_buf(), |rebased, component| match component {
+            Component::Prefix(prefix) => {
+                let drive = prefix.as_os_str().to_string_lossy();
+                rebased.join(drive.trim_end_matches(':').trim_start_matches(r"\\?\"))
+            }
+            Component::RootDir | Component::CurDir => rebased,
+            component => rebased.join(component),
+        })
+}
+
+/// Returns a copy of `file` whose system path is under `into` instead of the configured path.
+fn redirect(file: &HoardItem, into: &Path) -> Result<HoardItem, Error> {
+    let system_prefix = rebase(into, file.system_prefix().as_ref());
+    let system_prefix =
+        SystemPath::try_from(system_prefix.clone()).map_err(|error| Error::InvalidTarget {
+            path: system_prefix,
+            error,
+        })?;
+    Ok(HoardItem::new(
+        file.pile_name().clone(),
+        file.hoard_prefix().clone(),
+        system_prefix,
+        file.relative_path().clone(),
+    ))
+}
+
+/// Copies every file stored in each of `hoards` to `into`, keeping the layout of the configured
+/// system paths under it.
+///
+/// Files at the configured system paths are never written, so this is safe to run before a risky
+/// restore. Since the system is left as it was, no consistency checks are run and no operation
+/// log is written.
+#[tracing::instrument(skip(hoards))]
+pub(crate) async fn run_restore_into<'a>(
+    hoards_root: &HoardPath,
+    hoards: Vec<(&'a HoardName, &'a Hoard)>,
+    into: &Path,
+    copy_attributes: bool,
+    no_permissions: bool,
+    no_mtime: bool,
+    options: &ScanOptions,
+) -> Result<(), super::Error> {
+    for (hoard_name, hoard) in hoards {
+        let files: BTreeSet<HoardItem> = all_files_stream(hoards_root, hoard_name, hoard, options)
+            .await
+            .map_err(Error::Iterator)?
+            .try_filter(|file| futures::future::ready(file.hoard_path().is_file()))
+            .try_collect()
+            .await
+            .map_err(Error::Iterator)?;
+        let recorded = recorded_metadata(hoard_name, no_permissions && no_mtime)
+            .await
+            .map_err(Error::Restore)?;
+
+        for file in files {
+            let file = redirect(&file, into)?;
+            let with_attributes = copy_attributes
+                || hoard
+                    .get_pile(file.pile_name())
+                    .and_then(|pile| pile.config.copy_attributes)
+                    .unwrap_or(false);
+            copy_file(&file, Direction::Restore, with_attributes, options.retry)
+                .await
+                .map_err(Error::Restore)?;
+            if let (false, Some(recorded)) = (no_mtime, &recorded) {
+                restore_mtime(&file, recorded);
+            }
+            fix_permissions(
+                hoard,
+                &ItemOperation::Modify(file.clone()),
+                Direction::Restore,
+                recorded.as_ref().filter(|_| !no_permissions),
+            )
+            .await
+            .map_err(Error::Restore)?;
+            tracing::info!(
+                "{}/{}: restored to {}",
+                hoard_name,
+                display_path(&file),
+                file.system_path().display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_rebase_unix() {
+        assert_eq!(
+            rebase(Path::new("/tmp/inspect"), Path::new("/home/user/.config")),
+            PathBuf::from("/tmp/inspect/home/user/.config")
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_rebase_windows() {
+        assert_eq!(
+            rebase(
+                Path::new(r"D:\inspect"),
+                Path::new(r"C:\Users\user\AppData")
+            ),
+            PathBuf::from(r"D:\inspect\C\Users\user\AppData")
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Exporting a hoard as a single archive file and importing it again.
+
+mod tar;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tap::TapFallible;
+use thiserror::Error;
+use tokio::{fs, io};
+
+use crate::checkers::history::operation::{
+    export_operations, import_operations, Error as OperationError, HistoryExport,
+};
+use crate::checksum::Checksum;
+use crate::hoard::Hoard;
+use crate::newtypes::HoardName;
+use crate::paths::{HoardPath, RelativePath};
+
+use super::verify::{latest_operation, recorded_files};
+
+/// The name of the manifest file in a snapshot archive.
+const MANIFEST_FILE: &str = "manifest.json";
+/// The name of the directory holding the hoard files in a snapshot archive.
+const FILES_DIR: &str = "files";
+
+/// Errors that may occur while exporting or importing a hoard snapshot.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read or write the operation logs.
+    #[error("failed to process operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// An I/O error occurred while copying files or accessing the manifest.
+    #[error("I/O error while accessing snapshot: {0}")]
+    IO(#[from] io::Error),
+    /// The manifest could not be (de)serialized.
+    #[error("failed to (de)serialize snapshot manifest: {0}")]
+    Serde(#[from] serde_json::Error),
+    /// The hoard has no operation logs, so which files it holds is unknown.
+    #[error("{0} has no operation logs, so there is nothing to export")]
+    NothingToExport(HoardName),
+    /// The hoard to import into already contains files.
+    #[error("{} already exists and is not empty", .0.display())]
+    NotEmpty(PathBuf),
+    /// The file to export to already exists.
+    #[error("{} already exists", .0.display())]
+    Exists(PathBuf),
+    /// A file path cannot be stored in a snapshot archive.
+    #[error("{} cannot be stored in a snapshot: path is not valid UTF-8", .0.display())]
+    InvalidPath(PathBuf),
+    /// One or more files did not match their recorded checksums.
+    #[error("{0} file(s) failed verification")]
+    Failed(usize),
+}
+
+/// Describes the contents of a snapshot archive.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    /// The hoard that was exported.
+    hoard: HoardName,
+    /// The checksum of each exported file, keyed by its path relative to the hoard directory.
+    files: BTreeMap<RelativePath, Checksum>,
+    /// The operation logs of the hoard from every system.
+    history: HistoryExport,
+}
+
+/// Returns whether `path` does not exist or is an empty directory.
+async fn is_empty(path: &Path) -> io::Result<bool> {
+    match fs::metadata(path).await {
+        Ok(metadata) if metadata.is_dir() => {
+            Ok(fs::read_dir(path).await?.next_entry().await?.is_none())
+        }
+        Ok(_) => Ok(false),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(true),
+        Err(error) => Err(error),
+    }
+}
+
+/// Returns the path of the file at `relative` in the unpacked snapshot directory `dir`.
+fn snapshot_path(dir: &Path, relative: &RelativePath) -> PathBuf {
+    let files_dir = dir.join(FILES_DIR);
+    match relative.as_path() {
+        None => files_dir,
+        Some(relative) => files_dir.join(relative),
+    }
+}
+
+/// Copies `src` to `dest`, creating the parent directories of `dest` as needed.
+async fn copy_file(src: &Path, dest: &Path) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).await.tap_err(|error| {
+            tracing::error!(%error, "failed to create directory {}", parent.display());
+        })?;
+    }
+    fs::copy(src, dest).await.tap_err(|error| {
+        tracing::error!(
+            %error,
+            "failed to copy {} to {}",
+            src.display(),
+            dest.display()
+        );
+    })?;
+    Ok(())
+}
+
+/// Returns the path of the file at `relative` in a snapshot archive.
+fn archive_path(relative: &RelativePath) -> Result<String, Error> {
+    let mut path = String::from(FILES_DIR);
+    for component in relative.as_path().into_iter().flat_map(Path::components) {
+        let component = component.as_os_str().to_str().ok_or_else(|| {
+            Error::InvalidPath(relative.as_path().unwrap_or(Path::new("")).to_path_buf())
+        })?;
+        path.push('/');
+        path.push_str(component);
+    }
+    Ok(path)
+}
+
+/// Returns the permissions to store in a snapshot archive for a file with `metadata`.
+#[cfg(unix)]
+fn archive_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode()
+}
+
+/// Returns the permissions to store in a snapshot archive for a file with `metadata`.
+#[cfg(not(unix))]
+fn archive_mode(_metadata: &std::fs::Metadata) -> u32 {
+    0o644
+}
+
+/// Writes the snapshot archive for `manifest` to `output`, reading each file from `sources`.
+async fn write_archive(
+    output: &Path,
+    manifest: &Manifest,
+    sources: &BTreeMap<RelativePath, PathBuf>,
+) -> Result<(), Error> {
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output)
+        .await?;
+    let mut archive = tar::Writer::new(io::BufWriter::new(file));
+
+    let content = serde_json::to_vec_pretty(manifest)?;
+    archive
+        .append(
+            MANIFEST_FILE,
+            0o644,
+            content.len() as u64,
+            content.as_slice(),
+        )
+        .await?;
+    for (relative, source) in sources {
+        let file = fs::File::open(source).await?;
+        let metadata = file.metadata().await?;
+        archive
+            .append(
+                &archive_path(relative)?,
+                archive_mode(&metadata),
+                metadata.len(),
+                file,
+            )
+            .await?;
+    }
+
+    archive.finish().await?.into_inner().sync_all().await?;
+    Ok(())
+}
+
+async fn export_hoard(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    output: &Path,
+) -> Result<(), Error> {
+    if fs::symlink_metadata(output).await.is_ok() {
+        return crate::create_log_error(Error::Exists(output.to_path_buf()));
+    }
+
+    let Some(operation) = latest_operation(hoard_name).await? else {
+        return crate::create_log_error(Error::NothingToExport(hoard_name.clone()));
+    };
+
+    let hoard_dir = hoards_root.join(&RelativePath::from(hoard_name));
+    let mut files = BTreeMap::new();
+    let mut sources = BTreeMap::new();
+    let mut failed = 0;
+    for (path, (item, expected)) in recorded_files(hoards_root, hoard_name, hoard, &operation) {
+        let config = hoard
+            .get_pile(item.pile_name())
+            .map(|pile| pile.config.clone())
+            .unwrap_or_default();
+        let actual = item
+            .hoard_checksum_with_config(expected.typ(), &config)
+            .await?;
+        if actual.as_ref() != Some(&expected) {
+            tracing::error!(
+                "{}: file is missing from the hoard or does not match its recorded checksum",
+                path.display()
+            );
+            failed += 1;
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(&hoard_dir)
+            .map(Path::to_path_buf)
+            .ok()
+            .and_then(|relative| RelativePath::try_from(relative).ok())
+            .expect("hoard files are always in the hoard directory");
+        // Checksum the whole file, so that import can tell if it changed since.
+        let checksum = Checksum::compute(&path, expected.typ()).await?;
+        files.insert(relative.clone(), checksum);
+        sources.insert(relative, path);
+    }
+
+    if failed > 0 {
+        return Err(Error::Failed(failed));
+    }
+
+    let mut history = export_operations().await?;
+    for hoards in history.systems.values_mut() {
+        hoards.retain(|name, _| name == hoard_name);
+    }
+    history.systems.retain(|_, hoards| !hoards.is_empty());
+
+    let manifest = Manifest {
+        hoard: hoard_name.clone(),
+        files,
+        history,
+    };
+    if let Err(error) = write_archive(output, &manifest, &sources).await {
+        tracing::error!(%error, "failed to write {}", output.display());
+        if let Err(error) = fs::remove_file(output).await {
+            if error.kind() != io::ErrorKind::NotFound {
+                tracing::warn!(%error, "failed to remove incomplete archive {}", output.display());
+            }
+        }
+        return Err(error);
+    }
+    tracing::info!(
+        "exported {} file(s) from {} to {}",
+        manifest.files.len(),
+        hoard_name,
+        output.display()
+    );
+
+    Ok(())
+}
+
+async fn import_hoard(hoards_root: &HoardPath, archive: &Path) -> Result<(), Error> {
+    let unpacked = tempfile::tempdir()?;
+    let input = unpacked.path();
+    let file = fs::File::open(archive).await.tap_err(|error| {
+        tracing::error!(%error, "failed to open {}", archive.display());
+    })?;
+    tar::unpack(io::BufReader::new(file), input)
+        .await
+        .tap_err(|error| {
+            tracing::error!(%error, "failed to unpack {}", archive.display());
+        })?;
+
+    let manifest_path = input.join(MANIFEST_FILE);
+    let content = fs::read(&manifest_path).await.tap_err(|error| {
+        tracing::error!(%error, "failed to read {}", manifest_path.display());
+    })?;
+    let manifest: Manifest = serde_json::from_slice(&content).tap_err(|error| {
+        tracing::error!(%error, "failed to parse {}", manifest_path.display());
+    })?;
+
+    let hoard_dir = hoards_root.join(&RelativePath::from(&manifest.hoard));
+    if !is_empty(hoard_dir.as_ref()).await? {
+        return crate::create_log_error(Error::NotEmpty(hoard_dir.to_path_buf()));
+    }
+
+    // Verify every file before changing anything.
+    let mut failed = 0;
+    for (relative, expected) in &manifest.files {
+        let path = snapshot_path(input, relative);
+        match Checksum::compute(&path, expected.typ()).await {
+            Ok(actual) if &actual == expected => {
+                tracing::debug!("{}: checksum matches", path.display());
+            }
+            Ok(actual) => {
+                tracing::error!(
+                    "{}: checksum mismatch (expected {}, found {})",
+                    path.display(),
+                    expected,
+                    actual
+                );
+                failed += 1;
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                tracing::error!("{}: file is missing from the snapshot", path.display());
+                failed += 1;
+            }
+            Err(error) => return Err(Error::IO(error)),
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::Failed(failed));
+    }
+
+    for relative in manifest.files.keys() {
+        copy_file(
+            &snapshot_path(input, relative),
+            hoard_dir.join(relative).as_ref(),
+        )
+        .await?;
+    }
+
+    let logs = manifest.history.len();
+    import_operations(manifest.history).await?;
+    tracing::info!(
+        "imported {} file(s) and {} operation log(s) into {} from {}",
+        manifest.files.len(),
+        logs,
+        manifest.hoard,
+        archive.display()
+    );
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(hoard))]
+pub(crate) async fn run_export(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    output: &Path,
+) -> Result<(), super::Error> {
+    export_hoard(hoards_root, hoard_name, hoard, output).await?;
+    Ok(())
+}
+
+#[tracing::instrument]
+pub(crate) async fn run_import(hoards_root: &HoardPath, input: &Path) -> Result<(), super::Error> {
+    import_hoard(hoards_root, input).await?;
+    Ok(())
+}
@@ -0,0 +1,355 @@
+//! A minimal reader and writer for uncompressed POSIX ustar archives.
+//!
+//! Only regular files and directories are supported, which is all that a snapshot contains.
+
+use std::path::{Component, Path, PathBuf};
+
+use tokio::fs;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The size of a header and the unit that file contents are padded to.
+const BLOCK_SIZE: usize = 512;
+/// The largest file size that fits in the 11 octal digits of the size field.
+const MAX_SIZE: u64 = 0o777_7777_7777;
+/// The magic and version fields of a ustar header.
+const MAGIC: &[u8; 8] = b"ustar\x0000";
+
+/// The byte range of each header field that Hoard reads or writes.
+mod field {
+    use std::ops::Range;
+
+    pub(super) const NAME: Range<usize> = 0..100;
+    pub(super) const MODE: Range<usize> = 100..108;
+    pub(super) const UID: Range<usize> = 108..116;
+    pub(super) const GID: Range<usize> = 116..124;
+    pub(super) const SIZE: Range<usize> = 124..136;
+    pub(super) const MTIME: Range<usize> = 136..148;
+    pub(super) const CHECKSUM: Range<usize> = 148..156;
+    pub(super) const TYPE: usize = 156;
+    pub(super) const MAGIC: Range<usize> = 257..265;
+    pub(super) const PREFIX: Range<usize> = 345..500;
+}
+
+/// The type flag of a regular file. Old archives may use a NUL byte instead.
+const REGULAR_FILE: u8 = b'0';
+/// The type flag of a directory.
+const DIRECTORY: u8 = b'5';
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Returns how many bytes of padding follow `size` bytes of file contents.
+fn padding(size: u64) -> usize {
+    let block = BLOCK_SIZE as u64;
+    usize::try_from((block - size % block) % block).expect("padding is less than one block")
+}
+
+/// Writes `value` as zero-padded octal digits followed by a NUL byte.
+fn write_octal(field: &mut [u8], value: u64) -> io::Result<()> {
+    let digits = field.len() - 1;
+    let octal = format!("{value:0digits$o}");
+    if octal.len() > digits {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{value} is too large for a tar header"),
+        ));
+    }
+    field[..digits].copy_from_slice(octal.as_bytes());
+    field[digits] = 0;
+    Ok(())
+}
+
+fn read_octal(field: &[u8]) -> io::Result<u64> {
+    let text = std::str::from_utf8(field)
+        .map_err(|_| invalid_data("tar header contains a non-numeric field"))?
+        .trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8)
+        .map_err(|_| invalid_data("tar header contains a non-numeric field"))
+}
+
+/// Returns the sum of the header bytes, counting the checksum field as spaces.
+fn checksum(header: &[u8; BLOCK_SIZE]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            if field::CHECKSUM.contains(&i) {
+                u64::from(b' ')
+            } else {
+                u64::from(*byte)
+            }
+        })
+        .sum()
+}
+
+/// Splits `path` into the prefix and name fields of a header.
+fn split_path(path: &str) -> io::Result<(&str, &str)> {
+    let name_len = field::NAME.len();
+    if path.len() <= name_len {
+        return Ok(("", path));
+    }
+
+    path.match_indices('/')
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .find(|(prefix, name)| {
+            prefix.len() <= field::PREFIX.len() && !name.is_empty() && name.len() <= name_len
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{path} is too long to store in a tar archive"),
+            )
+        })
+}
+
+fn file_header(path: &str, mode: u32, size: u64) -> io::Result<[u8; BLOCK_SIZE]> {
+    let (prefix, name) = split_path(path)?;
+    let mut header = [0; BLOCK_SIZE];
+    header[field::NAME][..name.len()].copy_from_slice(name.as_bytes());
+    header[field::PREFIX][..prefix.len()].copy_from_slice(prefix.as_bytes());
+    write_octal(&mut header[field::MODE], u64::from(mode & 0o777))?;
+    write_octal(&mut header[field::UID], 0)?;
+    write_octal(&mut header[field::GID], 0)?;
+    write_octal(&mut header[field::SIZE], size)?;
+    write_octal(&mut header[field::MTIME], 0)?;
+    header[field::TYPE] = REGULAR_FILE;
+    header[field::MAGIC].copy_from_slice(MAGIC);
+    let sum = checksum(&header);
+    // Six octal digits, a NUL byte, and a space, as written by most implementations.
+    header[field::CHECKSUM].copy_from_slice(format!("{sum:06o}\0 ").as_bytes());
+    Ok(header)
+}
+
+/// Writes regular files into a ustar archive.
+pub(super) struct Writer<W> {
+    inner: W,
+}
+
+impl<W: AsyncWrite + Unpin> Writer<W> {
+    pub(super) fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Appends a regular file at `path`, using `/` as the separator, with the given permission
+    /// `mode` and the `size` bytes read from `content`.
+    ///
+    /// # Errors
+    ///
+    /// If the path or size do not fit in a ustar header, if `content` has fewer than `size`
+    /// bytes, or if writing fails.
+    pub(super) async fn append(
+        &mut self,
+        path: &str,
+        mode: u32,
+        size: u64,
+        content: impl AsyncRead + Unpin,
+    ) -> io::Result<()> {
+        if size > MAX_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{path} is too large to store in a tar archive"),
+            ));
+        }
+        self.inner
+            .write_all(&file_header(path, mode, size)?)
+            .await?;
+        let copied = io::copy(&mut content.take(size), &mut self.inner).await?;
+        if copied != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{path} changed size while it was being archived"),
+            ));
+        }
+        self.inner
+            .write_all(&[0; BLOCK_SIZE][..padding(size)])
+            .await
+    }
+
+    /// Writes the end-of-archive marker and flushes the underlying writer.
+    pub(super) async fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[0; 2 * BLOCK_SIZE]).await?;
+        self.inner.flush().await?;
+        Ok(self.inner)
+    }
+}
+
+/// Returns the path stored in `header`, if it is a relative path that stays inside the archive.
+fn entry_path(header: &[u8; BLOCK_SIZE]) -> io::Result<PathBuf> {
+    let text = |range| {
+        let bytes: &[u8] = &header[range];
+        let len = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(bytes.len());
+        std::str::from_utf8(&bytes[..len])
+            .map_err(|_| invalid_data("tar archive contains a path that is not valid UTF-8"))
+    };
+    let (prefix, name) = (text(field::PREFIX)?, text(field::NAME)?);
+    let path = if prefix.is_empty() {
+        PathBuf::from(name)
+    } else {
+        PathBuf::from(format!("{prefix}/{name}"))
+    };
+
+    let is_safe = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_safe || path.components().next().is_none() {
+        return Err(invalid_data(format!(
+            "tar archive contains an invalid path: {}",
+            path.display()
+        )));
+    }
+    Ok(path)
+}
+
+/// Extracts the ustar archive read from `reader` into the directory `dest`.
+///
+/// # Errors
+///
+/// If the archive is truncated or corrupted, contains entries other than regular files and
+/// directories or paths outside of `dest`, or if writing to `dest` fails.
+pub(super) async fn unpack(mut reader: impl AsyncRead + Unpin, dest: &Path) -> io::Result<()> {
+    loop {
+        let mut header = [0; BLOCK_SIZE];
+        reader.read_exact(&mut header).await.map_err(|error| {
+            if error.kind() == io::ErrorKind::UnexpectedEof {
+                invalid_data("tar archive is truncated")
+            } else {
+                error
+            }
+        })?;
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(());
+        }
+
+        if read_octal(&header[field::CHECKSUM])? != checksum(&header) {
+            return Err(invalid_data("tar archive has a corrupted header"));
+        }
+        if !header[field::MAGIC].starts_with(b"ustar") {
+            return Err(invalid_data("archive is not in the ustar format"));
+        }
+
+        let path = dest.join(entry_path(&header)?);
+        let size = read_octal(&header[field::SIZE])?;
+        match header[field::TYPE] {
+            REGULAR_FILE | 0 => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).await?;
+                }
+                let mut file = fs::File::create(&path).await?;
+                let copied = io::copy(&mut (&mut reader).take(size), &mut file).await?;
+                if copied != size {
+                    return Err(invalid_data("tar archive is truncated"));
+                }
+                file.flush().await?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let mode = u32::try_from(read_octal(&header[field::MODE])? & 0o777)
+                        .expect("permission bits fit in a u32");
+                    fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).await?;
+                }
+                let mut pad = [0; BLOCK_SIZE];
+                reader.read_exact(&mut pad[..padding(size)]).await?;
+            }
+            DIRECTORY => fs::create_dir_all(&path).await?,
+            other => {
+                return Err(invalid_data(format!(
+                    "tar archive contains an unsupported entry type {:?} at {}",
+                    char::from(other),
+                    path.display()
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new());
+        for (path, content) in files {
+            writer
+                .append(path, 0o644, content.len() as u64, *content)
+                .await
+                .unwrap();
+        }
+        writer.finish().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let long_path = format!("{}/{}", "dir".repeat(40), "file".repeat(20));
+        let files: [(&str, &[u8]); 3] = [
+            ("manifest.json", b"{}"),
+            ("files/nested/block", &[7; BLOCK_SIZE]),
+            (&long_path, b"long"),
+        ];
+        let archive = archive(&files).await;
+        assert_eq!(archive.len() % BLOCK_SIZE, 0);
+
+        let dir = tempfile::tempdir().unwrap();
+        unpack(archive.as_slice(), dir.path()).await.unwrap();
+        for (path, content) in files {
+            assert_eq!(fs::read(dir.path().join(path)).await.unwrap(), content);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_path_too_long_is_error() {
+        let mut writer = Writer::new(Vec::new());
+        let error = writer
+            .append(&"a".repeat(300), 0o644, 0, b"".as_slice())
+            .await
+            .expect_err("path should be too long");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_short_content_is_error() {
+        let mut writer = Writer::new(Vec::new());
+        let error = writer
+            .append("file", 0o644, 10, b"short".as_slice())
+            .await
+            .expect_err("content is shorter than the given size");
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_or_truncated_archive_is_error() {
+        let archive = archive(&[("file", b"content")]).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut corrupted = archive.clone();
+        corrupted[0] = b'x';
+        let error = unpack(corrupted.as_slice(), dir.path()).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = unpack(&archive[..BLOCK_SIZE + 3], dir.path())
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_path_outside_archive_is_error() {
+        let mut archive = archive(&[("xx/file", b"content")]).await;
+        archive[..2].copy_from_slice(b"..");
+        let sum = checksum(archive[..BLOCK_SIZE].try_into().unwrap());
+        archive[field::CHECKSUM].copy_from_slice(format!("{sum:06o}\0 ").as_bytes());
+
+        let dir = tempfile::tempdir().unwrap();
+        let error = unpack(archive.as_slice(), &dir.path().join("dest"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!dir.path().join("file").exists());
+    }
+}
@@ -1,76 +1,188 @@
+use std::io::Write;
+
 use futures::TryStreamExt;
+use serde::Serialize;
 
-use crate::hoard::iter::{diff_stream, DiffSource, HoardFileDiff};
+use crate::hoard::iter::{diff_stream, DiffSource, Error as IterError, HoardFileDiff, ScanOptions};
 use crate::hoard::Hoard;
 use crate::newtypes::HoardName;
 use crate::paths::HoardPath;
 
-#[tracing::instrument(skip(hoards))]
+/// Determines where the changes to a hoard, if any, came from.
+///
+/// Returns `None` if the hoard is up to date.
+#[tracing::instrument(skip(hoard))]
+pub(super) async fn hoard_diff_source(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    older_format_ok: bool,
+    options: &ScanOptions,
+) -> Result<Option<DiffSource>, IterError> {
+    diff_stream(
+        hoards_root,
+        hoard_name.clone(),
+        hoard,
+        older_format_ok,
+        options,
+    )
+    .await?
+    .try_filter_map(|hoard_diff| async move {
+        #[allow(clippy::match_same_arms)]
+        let source = match hoard_diff {
+            HoardFileDiff::BinaryModified { diff_source, .. } => Some(diff_source),
+            HoardFileDiff::TextModified { diff_source, .. } => Some(diff_source),
+            HoardFileDiff::Created { diff_source, .. } => Some(diff_source),
+            HoardFileDiff::Deleted { diff_source, .. } => Some(diff_source),
+            HoardFileDiff::Corrupt(_) => Some(DiffSource::Unknown),
+            HoardFileDiff::Unchanged(_)
+            | HoardFileDiff::Nonexistent(_)
+            | HoardFileDiff::Excluded(_)
+            | HoardFileDiff::Ignored(_) => None,
+        };
+
+        Ok(source)
+    })
+    .try_fold(None, |acc, source| async move {
+        match acc {
+            None => Ok(Some(source)),
+            Some(acc) => {
+                let new_source = if acc == DiffSource::Unknown || source == DiffSource::Unknown {
+                    DiffSource::Unknown
+                } else if acc == source {
+                    acc
+                } else {
+                    DiffSource::Mixed
+                };
+
+                Ok(Some(new_source))
+            }
+        }
+    })
+    .await
+}
+
+/// The state of a single hoard, as reported by `hoard status`.
+///
+/// With `--json`, each variant is printed as its `snake_case` name. These names are stable and
+/// will not change between releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoardStatus {
+    /// The hoard and system files match the last operation. Printed as `up_to_date`.
+    UpToDate,
+    /// Only this system has changes, which can be synced with `hoard backup`.
+    /// Printed as `modified_locally`.
+    ModifiedLocally,
+    /// Only another system has changes, which can be synced with `hoard restore`.
+    /// Printed as `modified_remotely`.
+    ModifiedRemotely,
+    /// Both this system and another system have changes. Printed as `mixed`.
+    Mixed,
+    /// The hoard was changed outside of Hoard. Printed as `unexpected`.
+    Unexpected,
+}
+
+impl From<Option<DiffSource>> for HoardStatus {
+    fn from(source: Option<DiffSource>) -> Self {
+        match source {
+            None => Self::UpToDate,
+            Some(DiffSource::Local) => Self::ModifiedLocally,
+            Some(DiffSource::Remote) => Self::ModifiedRemotely,
+            Some(DiffSource::Mixed) => Self::Mixed,
+            Some(DiffSource::Unknown) => Self::Unexpected,
+        }
+    }
+}
+
+impl HoardStatus {
+    /// Returns the command that resolves or helps inspect the changes to `hoard_name`, if any.
+    #[must_use]
+    pub fn remediation(self, hoard_name: &HoardName) -> Option<String> {
+        match self {
+            Self::UpToDate => None,
+            Self::ModifiedLocally => Some(format!("hoard backup {hoard_name}")),
+            Self::ModifiedRemotely => Some(format!("hoard restore {hoard_name}")),
+            Self::Mixed | Self::Unexpected => Some(format!("hoard diff {hoard_name}")),
+        }
+    }
+}
+
+/// Determines the [`HoardStatus`] of a single hoard.
+pub(crate) async fn hoard_status(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    older_format_ok: bool,
+    options: &ScanOptions,
+) -> Result<HoardStatus, super::Error> {
+    hoard_diff_source(hoards_root, hoard_name, hoard, older_format_ok, options)
+        .await
+        .map(HoardStatus::from)
+        .map_err(super::Error::Status)
+}
+
+/// A single hoard's status, as printed by `hoard status --json`.
+#[derive(Debug, Serialize)]
+struct JsonStatus<'a> {
+    hoard: &'a HoardName,
+    status: HoardStatus,
+    remediation: Option<String>,
+}
+
+/// Writes the status of each hoard to `out`, returning whether any hoard is not up to date.
+#[tracing::instrument(skip(hoards, out))]
 pub(crate) async fn run_status<'a>(
     hoards_root: &HoardPath,
     hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)>,
-) -> Result<(), super::Error> {
+    older_format_ok: bool,
+    options: &ScanOptions,
+    json: bool,
+    out: &mut impl Write,
+) -> Result<bool, super::Error> {
+    let mut statuses = Vec::new();
+    let mut has_changes = false;
     for (hoard_name, hoard) in hoards {
         let _span = tracing::error_span!("run_status", hoard=%hoard_name).entered();
-        let source = diff_stream(hoards_root, hoard_name.clone(), hoard)
-            .await
-            .map_err(super::Error::Status)?
-            .map_err(super::Error::Status)
-            .try_filter_map(|hoard_diff| async move {
-                #[allow(clippy::match_same_arms)]
-                let source = match hoard_diff {
-                    HoardFileDiff::BinaryModified { diff_source, .. } => Some(diff_source),
-                    HoardFileDiff::TextModified { diff_source, .. } => Some(diff_source),
-                    HoardFileDiff::Created { diff_source, .. } => Some(diff_source),
-                    HoardFileDiff::Deleted { diff_source, .. } => Some(diff_source),
-                    HoardFileDiff::Unchanged(_) | HoardFileDiff::Nonexistent(_) => None,
-                };
+        let status = hoard_status(hoards_root, hoard_name, hoard, older_format_ok, options).await?;
+        has_changes |= status != HoardStatus::UpToDate;
 
-                Ok(source)
-            })
-            .try_fold(None, |acc, source| async move {
-                match acc {
-                    None => Ok(Some(source)),
-                    Some(acc) => {
-                        let new_source =
-                            if acc == DiffSource::Unknown || source == DiffSource::Unknown {
-                                DiffSource::Unknown
-                            } else if acc == source {
-                                acc
-                            } else {
-                                DiffSource::Mixed
-                            };
-
-                        Ok(Some(new_source))
-                    }
-                }
-            })
-            .await?;
+        if json {
+            statuses.push(JsonStatus {
+                hoard: hoard_name,
+                status,
+                remediation: status.remediation(hoard_name),
+            });
+            continue;
+        }
 
-        match source {
-            None => tracing::info!("{}: up to date", hoard_name),
-            Some(source) => {
-                match source {
-                    DiffSource::Local => tracing::info!(
-                        "{}: modified {} -- sync with `hoard backup {}`",
-                        hoard_name, source, hoard_name
-                    ),
-                    DiffSource::Remote => tracing::info!(
-                        "{}: modified {} -- sync with `hoard restore {}`",
-                        hoard_name, source, hoard_name
-                    ),
-                    DiffSource::Mixed => tracing::info!(
-                        "{0}: mixed changes -- manual intervention recommended (see `hoard diff {0}`)",
-                        hoard_name
-                    ),
-                    DiffSource::Unknown => tracing::info!(
-                        "{0}: unexpected changes -- manual intervention recommended (see `hoard diff {0}`)",
-                        hoard_name
-                    ),
-                }
-            }
+        match status {
+            HoardStatus::UpToDate => writeln!(out, "{hoard_name}: up to date")?,
+            HoardStatus::ModifiedLocally => writeln!(
+                out,
+                "{hoard_name}: modified {} -- sync with `hoard backup {hoard_name}`",
+                DiffSource::Local
+            )?,
+            HoardStatus::ModifiedRemotely => writeln!(
+                out,
+                "{hoard_name}: modified {} -- sync with `hoard restore {hoard_name}`",
+                DiffSource::Remote
+            )?,
+            HoardStatus::Mixed => writeln!(
+                out,
+                "{hoard_name}: mixed changes -- manual intervention recommended (see `hoard diff {hoard_name}`)"
+            )?,
+            HoardStatus::Unexpected => writeln!(
+                out,
+                "{hoard_name}: unexpected changes -- manual intervention recommended (see `hoard diff {hoard_name}`)"
+            )?,
         }
     }
 
-    Ok(())
+    if json {
+        serde_json::to_writer_pretty(&mut *out, &statuses).map_err(std::io::Error::from)?;
+        writeln!(out)?;
+    }
+
+    Ok(has_changes)
 }
@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::Serialize;
+use tokio::fs;
+
+use crate::checkers::history::operation::ItemOperation;
+use crate::hoard::Direction;
+use crate::hoard_item::HoardItem;
+use crate::newtypes::HoardName;
+
+use super::backup_restore::copy_paths;
+
+/// How many files a backup or restore created, modified, and deleted, and the total size of the
+/// files it copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// The number of files created.
+    pub created: usize,
+    /// The number of files modified.
+    pub modified: usize,
+    /// The number of files deleted.
+    pub deleted: usize,
+    /// The total size in bytes of the created and modified files.
+    pub bytes: u64,
+}
+
+impl Summary {
+    /// Counts the files changed by `operations`, adding up the size of each file to be copied.
+    ///
+    /// Sizes are read from the side files are copied from, so this must be called before any
+    /// files are moved.
+    pub(crate) async fn of_operations(
+        operations: &[ItemOperation<HoardItem>],
+        direction: Direction,
+    ) -> Self {
+        let mut summary = Self::default();
+        for operation in operations {
+            match operation {
+                ItemOperation::Create(file) | ItemOperation::Modify(file) => {
+                    if matches!(operation, ItemOperation::Create(_)) {
+                        summary.created += 1;
+                    } else {
+                        summary.modified += 1;
+                    }
+                    let (src, _, _) = copy_paths(file, direction);
+                    match fs::metadata(src).await {
+                        Ok(metadata) => summary.bytes += metadata.len(),
+                        Err(error) => {
+                            tracing::debug!(%error, "failed to read size of {}", src.display());
+                        }
+                    }
+                }
+                ItemOperation::Delete(_) => summary.deleted += 1,
+                ItemOperation::Nothing(_) | ItemOperation::DoesNotExist(_) => {}
+            }
+        }
+        summary
+    }
+
+    fn add(&mut self, other: Self) {
+        self.created += other.created;
+        self.modified += other.modified;
+        self.deleted += other.deleted;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Formats `bytes` with the largest binary unit that keeps the number at least 1.
+#[allow(clippy::cast_precision_loss)]
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{size:.1} {unit}")
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} created, {} modified, {} deleted ({})",
+            self.created,
+            self.modified,
+            self.deleted,
+            format_size(self.bytes)
+        )
+    }
+}
+
+/// The summary of each hoard, as printed with `--json`.
+#[derive(Debug, Serialize)]
+struct JsonSummary<'a> {
+    hoards: &'a BTreeMap<HoardName, Summary>,
+    total: Summary,
+}
+
+/// Writes one line per hoard in `summaries` to `out`, followed by the total if there is more than
+/// one hoard, or all of them as a single JSON object if `json` is set.
+pub(crate) fn write_summary(
+    summaries: &BTreeMap<HoardName, Summary>,
+    json: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut total = Summary::default();
+    for summary in summaries.values() {
+        total.add(*summary);
+    }
+
+    if json {
+        serde_json::to_writer_pretty(
+            &mut *out,
+            &JsonSummary {
+                hoards: summaries,
+                total,
+            },
+        )?;
+        return writeln!(out);
+    }
+
+    for (name, summary) in summaries {
+        writeln!(out, "{name}: {summary}")?;
+    }
+    if summaries.len() > 1 {
+        writeln!(out, "total: {total}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(created: usize, modified: usize, deleted: usize, bytes: u64) -> Summary {
+        Summary {
+            created,
+            modified,
+            deleted,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(2_202_010), "2.1 MiB");
+        assert_eq!(format_size(3 << 30), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_single_hoard_has_no_total() {
+        let summaries = BTreeMap::from([("mygame".parse().unwrap(), summary(3, 1, 0, 2_202_010))]);
+        let mut out = Vec::new();
+        write_summary(&summaries, false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "mygame: 3 created, 1 modified, 0 deleted (2.1 MiB)\n"
+        );
+    }
+
+    #[test]
+    fn test_multiple_hoards_have_total() {
+        let summaries = BTreeMap::from([
+            ("first".parse().unwrap(), summary(1, 0, 2, 100)),
+            ("second".parse().unwrap(), summary(2, 1, 0, 200)),
+        ]);
+        let mut out = Vec::new();
+        write_summary(&summaries, false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "first: 1 created, 0 modified, 2 deleted (100 B)\n\
+             second: 2 created, 1 modified, 0 deleted (200 B)\n\
+             total: 3 created, 1 modified, 2 deleted (300 B)\n"
+        );
+    }
+
+    #[test]
+    fn test_json_summary() {
+        let summaries = BTreeMap::from([("first".parse().unwrap(), summary(1, 0, 2, 100))]);
+        let mut out = Vec::new();
+        write_summary(&summaries, true, &mut out).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "hoards": {
+                    "first": { "created": 1, "modified": 0, "deleted": 2, "bytes": 100 },
+                },
+                "total": { "created": 1, "modified": 0, "deleted": 2, "bytes": 100 },
+            })
+        );
+    }
+}
@@ -1,21 +1,128 @@
+use std::collections::HashMap;
+
 use thiserror::Error;
 
 use crate::checkers::history::operation::util::upgrade_operations;
-use crate::checkers::history::operation::Error as OperationError;
+use crate::checkers::history::operation::{Error as OperationError, Operation, OperationImpl};
+use crate::hoard::Hoard;
+use crate::hoard_item::HoardItem;
+use crate::newtypes::HoardName;
+use crate::paths::{HoardPath, RelativePath};
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("failed to upgrade operation logs: {0}")]
     Operations(OperationError),
+    #[error("failed to rehash files of hoard {0}: {1}")]
+    Rehash(HoardName, OperationError),
 }
 
-#[tracing::instrument]
-pub(crate) async fn run_upgrade() -> Result<(), super::Error> {
+/// Re-checksums the files recorded by the latest operation log of `hoard_name` on this system
+/// whose checksums use a different type than is configured for their pile, then rewrites the log.
+///
+/// A checksum is only replaced if the hoard file exists and still matches it, so that files
+/// changed outside of Hoard keep showing up as changed. Returns the number of replaced checksums.
+async fn rehash_hoard(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+) -> Result<usize, OperationError> {
+    let Some((path, mut operation)) = Operation::latest_local_with_path(hoard_name).await? else {
+        return Ok(0);
+    };
+    operation.require_latest_version()?;
+
+    let prefixes: HashMap<_, _> = hoard
+        .get_paths(hoards_root.join(&RelativePath::from(hoard_name)))
+        .map(|(pile_name, hoard_prefix, system_prefix)| (pile_name, (hoard_prefix, system_prefix)))
+        .collect();
+    let files: Vec<_> = operation.all_files_with_checksums().collect();
+
+    let mut rehashed = 0;
+    for file in files {
+        let (Some(old), Some(pile), Some((hoard_prefix, system_prefix))) = (
+            file.checksum(),
+            hoard.get_pile(file.pile_name()),
+            prefixes.get(file.pile_name()),
+        ) else {
+            continue;
+        };
+        let new_type = pile.config.checksum_type.unwrap_or_default();
+        if old.typ() == new_type {
+            continue;
+        }
+
+        let item = HoardItem::new(
+            file.pile_name().clone(),
+            hoard_prefix.clone(),
+            system_prefix.clone(),
+            file.relative_path().clone(),
+        );
+        match item
+            .hoard_checksum_with_config(old.typ(), &pile.config)
+            .await?
+        {
+            None => {
+                tracing::warn!(
+                    "not rehashing {}: the file is missing from the hoard",
+                    item.hoard_path().display()
+                );
+                continue;
+            }
+            Some(current) if &current != old => {
+                tracing::warn!(
+                    "not rehashing {}: the file does not match its recorded checksum",
+                    item.hoard_path().display()
+                );
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        if let Some(checksum) = item
+            .hoard_checksum_with_config(new_type, &pile.config)
+            .await?
+        {
+            tracing::debug!(file=%item.hoard_path().display(), %old, new=%checksum, "rehashed");
+            if operation.replace_checksum(file.pile_name(), file.relative_path(), checksum)? {
+                rehashed += 1;
+            }
+        }
+    }
+
+    if rehashed > 0 {
+        operation.write_to_file(&path).await?;
+    }
+
+    Ok(rehashed)
+}
+
+#[tracing::instrument(skip(hoards))]
+pub(crate) async fn run_upgrade<'a>(
+    hoards_root: &HoardPath,
+    hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)>,
+    rehash: bool,
+) -> Result<(), super::Error> {
     tracing::info!("Upgrading operation logs to the latest format...");
     upgrade_operations()
         .await
         .map_err(Error::Operations)
         .map_err(super::Error::Upgrade)?;
     tracing::info!("Successfully upgraded all operation logs");
+
+    if rehash {
+        let mut hoards: Vec<_> = hoards.into_iter().collect();
+        hoards.sort_unstable_by_key(|(name, _)| *name);
+        for (hoard_name, hoard) in hoards {
+            let rehashed = rehash_hoard(hoards_root, hoard_name, hoard)
+                .await
+                .map_err(|error| Error::Rehash(hoard_name.clone(), error))
+                .map_err(super::Error::Upgrade)?;
+            if rehashed > 0 {
+                tracing::info!("{}: rehashed {} files", hoard_name, rehashed);
+            }
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::hoard::Hoard;
+use crate::newtypes::{EnvironmentName, HoardName, NonEmptyPileName};
+use crate::paths::SystemPath;
+
+/// Errors that may occur while validating the configuration.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// One or more problems were found with the configuration.
+    #[error("found {0} problem(s) with the configuration")]
+    Invalid(usize),
+}
+
+/// Problems that can only be detected before the configuration is resolved for the current
+/// environment.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnresolvedProblems {
+    /// Hoards that do not define a path for any environment condition.
+    pub pathless_hoards: Vec<HoardName>,
+    /// Environments named in an exclusivity list that are not defined.
+    pub unknown_exclusive_envs: Vec<EnvironmentName>,
+}
+
+fn duplicate_piles(hoard_name: &HoardName, hoard: &Hoard) -> Vec<String> {
+    let Hoard::Named(named) = hoard else {
+        return Vec::new();
+    };
+
+    let mut by_path: BTreeMap<&SystemPath, Vec<&NonEmptyPileName>> = BTreeMap::new();
+    for (pile_name, pile) in &named.piles {
+        if let Some(path) = &pile.path {
+            by_path.entry(path).or_default().push(pile_name);
+        }
+    }
+
+    by_path
+        .into_iter()
+        .filter(|(_, piles)| piles.len() > 1)
+        .map(|(path, mut piles)| {
+            piles.sort_unstable();
+            let piles: Vec<_> = piles.iter().map(ToString::to_string).collect();
+            format!(
+                "hoard \"{hoard_name}\": piles {} all map to {}",
+                piles.join(", "),
+                path.display()
+            )
+        })
+        .collect()
+}
+
+fn warn_outside_home(hoard_name: &HoardName, hoard: &Hoard, home: &Path) {
+    let piles: Vec<(Option<&NonEmptyPileName>, &SystemPath)> = match hoard {
+        Hoard::Anonymous(pile) => pile.path.iter().map(|path| (None, path)).collect(),
+        Hoard::Named(named) => named
+            .piles
+            .iter()
+            .filter_map(|(name, pile)| pile.path.as_ref().map(|path| (Some(name), path)))
+            .collect(),
+    };
+
+    for (pile_name, path) in piles {
+        if path.starts_with(home) {
+            continue;
+        }
+        match pile_name {
+            None => tracing::warn!(
+                "hoard \"{hoard_name}\": {} is outside of the home directory",
+                path.display()
+            ),
+            Some(pile_name) => tracing::warn!(
+                "hoard \"{hoard_name}\", pile \"{pile_name}\": {} is outside of the home directory",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Check the configuration for problems without touching any files.
+///
+/// Each problem is logged as an error, while hoard paths outside of the home directory are
+/// logged as warnings.
+#[tracing::instrument(skip_all)]
+pub(crate) fn run_validate(
+    hoards: &HashMap<HoardName, Hoard>,
+    unresolved: &UnresolvedProblems,
+) -> Result<(), super::Error> {
+    let mut problems = Vec::new();
+
+    for env in &unresolved.unknown_exclusive_envs {
+        problems.push(format!(
+            "exclusivity list contains environment \"{env}\", which is not defined"
+        ));
+    }
+
+    for hoard_name in &unresolved.pathless_hoards {
+        problems.push(format!(
+            "hoard \"{hoard_name}\" does not define a path for any environment"
+        ));
+    }
+
+    let home = crate::dirs::try_home_dir().ok();
+    let mut hoard_names: Vec<_> = hoards.keys().collect();
+    hoard_names.sort_unstable();
+    for hoard_name in hoard_names {
+        let hoard = &hoards[hoard_name];
+        problems.extend(duplicate_piles(hoard_name, hoard));
+        if let Some(home) = &home {
+            warn_outside_home(hoard_name, hoard, home);
+        }
+    }
+
+    if problems.is_empty() {
+        tracing::info!("configuration is valid");
+        Ok(())
+    } else {
+        for problem in &problems {
+            tracing::error!("{}", problem);
+        }
+        Err(super::Error::Validate(Error::Invalid(problems.len())))
+    }
+}
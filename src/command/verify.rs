@@ -0,0 +1,353 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{fs, io};
+
+use crate::checkers::history::operation::{Error as OperationError, Operation, OperationImpl};
+use crate::checksum::Checksum;
+use crate::hoard::Hoard;
+use crate::hoard_item::HoardItem;
+use crate::newtypes::HoardName;
+use crate::paths::{HoardPath, RelativePath};
+
+/// Errors that may occur while verifying hoard files.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Failed to read the operation logs that hold the expected checksums.
+    #[error("failed to read operation logs: {0}")]
+    Operation(#[from] OperationError),
+    /// An I/O error occurred while reading files or verification progress.
+    #[error("I/O error while verifying files: {0}")]
+    IO(#[from] io::Error),
+    /// Failed to (de)serialize the verification progress.
+    #[error("failed to (de)serialize verification progress: {0}")]
+    Serde(#[from] serde_json::Error),
+    /// One or more files did not match their recorded checksums.
+    #[error("{0} file(s) failed verification")]
+    Failed(usize),
+}
+
+/// The fraction of a hoard's files to verify in a single run.
+///
+/// Must be greater than `0` and no greater than `1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerifyFraction(f64);
+
+// The contained value is guaranteed to never be NaN.
+impl Eq for VerifyFraction {}
+
+impl FromStr for VerifyFraction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value: f64 = s
+            .parse()
+            .map_err(|err| format!("invalid fraction {s}: {err}"))?;
+        if value > 0.0 && value <= 1.0 {
+            Ok(Self(value))
+        } else {
+            Err(format!(
+                "fraction must be greater than 0 and at most 1, got {value}"
+            ))
+        }
+    }
+}
+
+impl fmt::Display for VerifyFraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl VerifyFraction {
+    /// How many of `total` files should be verified in a single run. Always at least 1.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn batch_size(self, total: usize) -> usize {
+        ((total as f64 * self.0).ceil() as usize).max(1)
+    }
+}
+
+/// Tracks which files have been verified during the current verification cycle.
+///
+/// Once every file has been verified, the next batch starts a new cycle.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct VerifyProgress {
+    verified: BTreeSet<PathBuf>,
+}
+
+impl VerifyProgress {
+    fn progress_file(hoard_name: &HoardName) -> PathBuf {
+        crate::dirs::cache_dir()
+            .join("verify")
+            .join(format!("{hoard_name}.json"))
+    }
+
+    #[tracing::instrument(level = "debug")]
+    async fn load(hoard_name: &HoardName) -> Result<Self, Error> {
+        let path = Self::progress_file(hoard_name);
+        match fs::read(&path).await {
+            Ok(content) => serde_json::from_slice(&content).map_err(Error::Serde),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                tracing::debug!("no verification progress found, starting a new cycle");
+                Ok(Self::default())
+            }
+            Err(err) => crate::create_log_error_msg(
+                &format!("failed to read {}", path.display()),
+                Error::IO(err),
+            ),
+        }
+    }
+
+    #[tracing::instrument(level = "debug")]
+    async fn save(&self, hoard_name: &HoardName) -> Result<(), Error> {
+        let path = Self::progress_file(hoard_name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let content = serde_json::to_vec(self)?;
+        fs::write(&path, content).await.map_err(Error::IO)
+    }
+
+    /// Selects the next batch of `files` to verify.
+    ///
+    /// Files that have already been verified this cycle are skipped. If all `files` have
+    /// been verified, a new cycle is started. Selected files are not recorded until they
+    /// are passed to [`VerifyProgress::mark_verified`].
+    fn next_batch(&mut self, files: &BTreeSet<PathBuf>, fraction: VerifyFraction) -> Vec<PathBuf> {
+        self.verified.retain(|file| files.contains(file));
+        if self.verified.len() == files.len() {
+            tracing::debug!("all files verified, starting a new cycle");
+            self.verified.clear();
+        }
+
+        let batch: Vec<PathBuf> = files
+            .difference(&self.verified)
+            .take(fraction.batch_size(files.len()))
+            .cloned()
+            .collect();
+        batch
+    }
+
+    /// Records `files` as verified for the current cycle.
+    fn mark_verified(&mut self, files: impl IntoIterator<Item = PathBuf>) {
+        self.verified.extend(files);
+    }
+}
+
+/// Returns the most recent operation for the hoard from any system.
+pub(super) async fn latest_operation(
+    hoard_name: &HoardName,
+) -> Result<Option<Operation>, OperationError> {
+    let local = Operation::latest_local(hoard_name, None).await?;
+    let remote = Operation::latest_remote_backup(hoard_name, None, false).await?;
+    Ok(match (local, remote) {
+        (Some(local), Some(remote)) => {
+            if remote.timestamp() > local.timestamp() {
+                Some(remote)
+            } else {
+                Some(local)
+            }
+        }
+        (local, remote) => local.or(remote),
+    })
+}
+
+/// Returns every file recorded with a checksum by `operation`, keyed by its path in the hoard.
+pub(super) fn recorded_files(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    operation: &Operation,
+) -> BTreeMap<PathBuf, (HoardItem, Checksum)> {
+    let piles: HashMap<_, _> = hoard
+        .get_paths(hoards_root.join(&RelativePath::from(hoard_name)))
+        .map(|(pile_name, hoard_prefix, system_prefix)| (pile_name, (hoard_prefix, system_prefix)))
+        .collect();
+
+    operation
+        .all_files_with_checksums()
+        .filter_map(|info| {
+            let checksum = info.checksum()?.clone();
+            let (hoard_prefix, system_prefix) = piles.get(info.pile_name())?;
+            let item = HoardItem::new(
+                info.pile_name().clone(),
+                hoard_prefix.clone(),
+                system_prefix.clone(),
+                info.relative_path().clone(),
+            );
+            Some((item.hoard_path().to_path_buf(), (item, checksum)))
+        })
+        .collect()
+}
+
+#[tracing::instrument(skip(hoard))]
+async fn verify_hoard(
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+    fraction: Option<VerifyFraction>,
+) -> Result<usize, Error> {
+    let Some(operation) = latest_operation(hoard_name).await? else {
+        tracing::info!("{}: no operations recorded, nothing to verify", hoard_name);
+        return Ok(0);
+    };
+
+    let files = recorded_files(hoards_root, hoard_name, hoard, &operation);
+    let (progress, to_verify): (_, Vec<PathBuf>) = match fraction {
+        None => (None, files.keys().cloned().collect()),
+        Some(fraction) => {
+            let mut progress = VerifyProgress::load(hoard_name).await?;
+            let all_files = files.keys().cloned().collect();
+            let batch = progress.next_batch(&all_files, fraction);
+            (Some(progress), batch)
+        }
+    };
+
+    let mut failed = 0;
+    let mut passed = Vec::with_capacity(to_verify.len());
+    for path in &to_verify {
+        let (item, expected) = &files[path];
+        let config = hoard
+            .get_pile(item.pile_name())
+            .map(|pile| pile.config.clone())
+            .unwrap_or_default();
+        match item
+            .hoard_checksum_with_config(expected.typ(), &config)
+            .await?
+        {
+            None => {
+                tracing::error!("{}: file is missing from the hoard", path.display());
+                failed += 1;
+            }
+            Some(actual) if &actual != expected => {
+                tracing::error!(
+                    "{}: checksum mismatch (expected {}, found {})",
+                    path.display(),
+                    expected,
+                    actual
+                );
+                failed += 1;
+            }
+            Some(_) => {
+                tracing::debug!("{}: checksum matches", path.display());
+                passed.push(path.clone());
+            }
+        }
+    }
+
+    if let Some(mut progress) = progress {
+        progress.mark_verified(passed);
+        progress.save(hoard_name).await?;
+    }
+
+    tracing::info!(
+        "{}: verified {} of {} files",
+        hoard_name,
+        to_verify.len(),
+        files.len()
+    );
+
+    Ok(failed)
+}
+
+#[tracing::instrument(skip(hoards))]
+pub(crate) async fn run_verify<'a>(
+    hoards_root: &HoardPath,
+    hoards: impl IntoIterator<Item = (&'a HoardName, &'a Hoard)>,
+    fraction: Option<VerifyFraction>,
+) -> Result<(), super::Error> {
+    let mut failed = 0;
+    for (hoard_name, hoard) in hoards {
+        failed += verify_hoard(hoards_root, hoard_name, hoard, fraction).await?;
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(super::Error::Verify(Error::Failed(failed)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(s: &str) -> VerifyFraction {
+        s.parse().expect("fraction should be valid")
+    }
+
+    #[test]
+    fn test_fraction_must_be_in_range() {
+        assert!("0".parse::<VerifyFraction>().is_err());
+        assert!("-0.5".parse::<VerifyFraction>().is_err());
+        assert!("1.5".parse::<VerifyFraction>().is_err());
+        assert!("NaN".parse::<VerifyFraction>().is_err());
+        assert!("not a number".parse::<VerifyFraction>().is_err());
+        assert_eq!(fraction("1").batch_size(10), 10);
+        assert_eq!(fraction("0.25").batch_size(10), 3);
+        assert_eq!(fraction("0.01").batch_size(10), 1);
+    }
+
+    #[test]
+    fn test_every_file_verified_once_per_cycle() {
+        let files: BTreeSet<PathBuf> = (0..10).map(|i| PathBuf::from(format!("file{i}"))).collect();
+        let fraction = fraction("0.3");
+        let mut progress = VerifyProgress::default();
+
+        for _ in 0..3 {
+            let mut seen = Vec::new();
+            // 3 + 3 + 3 + 1 files
+            for _ in 0..4 {
+                let batch = progress.next_batch(&files, fraction);
+                assert!(!batch.is_empty());
+                progress.mark_verified(batch.iter().cloned());
+                seen.extend(batch);
+            }
+            seen.sort();
+            let expected: Vec<PathBuf> = files.iter().cloned().collect();
+            assert_eq!(
+                seen, expected,
+                "each file should be verified exactly once per cycle"
+            );
+        }
+    }
+
+    #[test]
+    fn test_removed_files_are_forgotten() {
+        let mut files: BTreeSet<PathBuf> =
+            (0..4).map(|i| PathBuf::from(format!("file{i}"))).collect();
+        let fraction = fraction("0.5");
+        let mut progress = VerifyProgress::default();
+
+        let first = progress.next_batch(&files, fraction);
+        progress.mark_verified(first.iter().cloned());
+        for file in &first {
+            files.remove(file);
+        }
+        let second = progress.next_batch(&files, fraction);
+        assert_eq!(second.len(), 1);
+        assert!(progress.verified.iter().all(|file| files.contains(file)));
+    }
+
+    #[test]
+    fn test_failed_files_are_verified_again() {
+        let files: BTreeSet<PathBuf> = (0..4).map(|i| PathBuf::from(format!("file{i}"))).collect();
+        let fraction = fraction("0.5");
+        let mut progress = VerifyProgress::default();
+
+        let first = progress.next_batch(&files, fraction);
+        // Only the first file in the batch passed verification.
+        progress.mark_verified(first.iter().take(1).cloned());
+        let second = progress.next_batch(&files, fraction);
+        assert!(second.contains(&first[1]));
+        assert!(!second.contains(&first[0]));
+    }
+}
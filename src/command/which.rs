@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use crate::config::builder::environment::Condition;
+use crate::hoard::{Hoard, Pile};
+use crate::newtypes::{EnvironmentName, HoardName, PileName};
+use crate::paths::{HoardPath, RelativePath};
+
+/// Formats whether `name` applies, followed by the result of each of its conditions.
+fn environment_lines(name: &EnvironmentName, conditions: &[Condition]) -> Vec<String> {
+    let applies = conditions.iter().all(|cond| cond.matched);
+    let mut lines = vec![format!("  {name}: {applies}")];
+    lines.extend(
+        conditions
+            .iter()
+            .map(|cond| format!("    {}: {}", cond.condition, cond.matched)),
+    );
+    lines
+}
+
+/// Formats the system path(s) chosen for a pile and where they are stored in the hoard.
+fn pile_lines(pile_name: &PileName, pile: &Pile, hoard_path: &HoardPath) -> Vec<String> {
+    let label = if pile_name.is_anonymous() {
+        String::from("  (anonymous pile)")
+    } else {
+        format!("  pile {pile_name}")
+    };
+
+    if pile.path.is_none() {
+        return vec![format!("{label}: no path for the current environments")];
+    }
+
+    let mut lines = vec![format!("{label}: {}", hoard_path.display())];
+    lines.extend(
+        pile.source_paths()
+            .map(|path| format!("    {}", path.display())),
+    );
+    if let Some(glob) = &pile.glob {
+        lines.push(format!("    matching {}", glob.as_str()));
+    }
+    lines
+}
+
+#[tracing::instrument(skip(environments, hoard))]
+pub(crate) fn run_which(
+    environments: &BTreeMap<EnvironmentName, Vec<Condition>>,
+    hoards_root: &HoardPath,
+    hoard_name: &HoardName,
+    hoard: &Hoard,
+) {
+    let mut lines = vec![String::from("environments:")];
+    for (name, conditions) in environments {
+        lines.extend(environment_lines(name, conditions));
+    }
+
+    lines.push(format!("hoard {hoard_name}:"));
+    let hoard_prefix = hoards_root.join(&RelativePath::from(hoard_name));
+    match hoard {
+        Hoard::Anonymous(pile) => {
+            lines.extend(pile_lines(&PileName::anonymous(), pile, &hoard_prefix));
+        }
+        Hoard::Named(named) => {
+            let piles: BTreeMap<_, _> = named.piles.iter().collect();
+            for (name, pile) in piles {
+                let hoard_path = hoard_prefix.join(&RelativePath::from(name));
+                let pile_name = PileName::from(name.clone());
+                lines.extend(pile_lines(&pile_name, pile, &hoard_path));
+            }
+        }
+    }
+
+    tracing::info!("{}", lines.join("\n"));
+}
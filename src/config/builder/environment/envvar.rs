@@ -1,35 +1,108 @@
 //! See [`EnvVariable`].
 
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::convert::{Infallible, TryInto};
+use std::convert::TryInto;
 use std::fmt;
 use std::fmt::Formatter;
+use thiserror::Error;
+
+/// Errors that may occur while checking an [`EnvVariable`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// More than one of `expected`, `contains`, and `matches` was set.
+    #[error("only one of `expected`, `contains`, or `matches` may be set for ${{{0}}}")]
+    MultipleConditions(String),
+    /// The `matches` pattern is not a valid regular expression.
+    #[error("invalid regular expression for ${{{var}}}: {error}")]
+    InvalidRegex {
+        /// The variable the pattern is for.
+        var: String,
+        /// The error from parsing the pattern.
+        #[source]
+        error: regex::Error,
+    },
+}
 
 /// A conditional structure that checks if the given environment variable exists and optionally if
-/// it is set to a specific value.
+/// its value equals, contains, or matches a given string.
+///
+/// At most one of `expected`, `contains`, and `matches` may be set. If the variable is not set,
+/// or its value is not valid Unicode when checking `contains` or `matches`, the condition is false.
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
 pub struct EnvVariable {
     /// The variable to check.
     pub var: String,
     /// The expected value to check against. If `None`, this matches any value.
     pub expected: Option<String>,
+    /// A substring that the value must contain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contains: Option<String>,
+    /// A regular expression that the value must match. The pattern is anchored, so it must match
+    /// the entire value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matches: Option<String>,
+}
+
+impl EnvVariable {
+    /// Checks that at most one kind of value check is set and that any `matches` pattern is valid.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::MultipleConditions`] if more than one value check is set.
+    /// - [`Error::InvalidRegex`] if `matches` is not a valid regular expression.
+    pub fn validate(&self) -> Result<(), Error> {
+        let checks = [
+            self.expected.is_some(),
+            self.contains.is_some(),
+            self.matches.is_some(),
+        ];
+        if checks.into_iter().filter(|is_set| *is_set).count() > 1 {
+            return crate::create_log_error(Error::MultipleConditions(self.var.clone()));
+        }
+        self.regex().map(|_| ())
+    }
+
+    fn regex(&self) -> Result<Option<Regex>, Error> {
+        self.matches
+            .as_ref()
+            .map(|pattern| {
+                Regex::new(&format!("^(?:{pattern})$")).map_err(|error| Error::InvalidRegex {
+                    var: self.var.clone(),
+                    error,
+                })
+            })
+            .transpose()
+    }
 }
 
 impl TryInto<bool> for EnvVariable {
-    type Error = Infallible;
+    type Error = Error;
 
     fn try_into(self) -> Result<bool, Self::Error> {
-        let EnvVariable { var, expected } = self;
+        self.validate()?;
+        let regex = self.regex()?;
+        let EnvVariable {
+            var,
+            expected,
+            contains,
+            ..
+        } = self;
         tracing::trace!(%var, "checking if environment variable exists");
-        let result = match std::env::var_os(&var) {
-            None => false,
-            Some(val) => match expected {
-                None => true,
-                Some(expected) => {
-                    tracing::trace!(%var, %expected, "checking if variable matches expected value");
-                    val == expected.as_str()
-                }
-            },
+        let Some(val) = std::env::var_os(&var) else {
+            return Ok(false);
+        };
+        let result = if let Some(expected) = expected {
+            tracing::trace!(%var, %expected, "checking if variable matches expected value");
+            val == expected.as_str()
+        } else if let Some(contains) = contains {
+            tracing::trace!(%var, %contains, "checking if variable contains value");
+            val.to_str().is_some_and(|val| val.contains(&contains))
+        } else if let Some(regex) = regex {
+            tracing::trace!(%var, %regex, "checking if variable matches pattern");
+            val.to_str().is_some_and(|val| regex.is_match(val))
+        } else {
+            true
         };
         Ok(result)
     }
@@ -38,9 +111,15 @@ impl TryInto<bool> for EnvVariable {
 // For use in displaying in boolean strings
 impl fmt::Display for EnvVariable {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match &self.expected {
-            None => write!(f, "ENV ${{{}}} IS SET", self.var),
-            Some(expected) => write!(f, "ENV ${{{}}} == \"{}\"", self.var, expected),
+        match (&self.expected, &self.contains, &self.matches) {
+            (Some(expected), _, _) => write!(f, "ENV ${{{}}} == \"{}\"", self.var, expected),
+            (None, Some(contains), _) => {
+                write!(f, "ENV ${{{}}} CONTAINS \"{}\"", self.var, contains)
+            }
+            (None, None, Some(matches)) => {
+                write!(f, "ENV ${{{}}} MATCHES \"{}\"", self.var, matches)
+            }
+            (None, None, None) => write!(f, "ENV ${{{}}} IS SET", self.var),
         }
     }
 }
@@ -54,6 +133,8 @@ mod tests {
         let env = EnvVariable {
             var: "TESTING_VAR".to_string(),
             expected: None,
+            contains: None,
+            matches: None,
         };
         assert_eq!("ENV ${TESTING_VAR} IS SET", env.to_string());
     }
@@ -63,6 +144,8 @@ mod tests {
         let env = EnvVariable {
             var: "TESTING_VAR".to_string(),
             expected: Some("testing value".to_string()),
+            contains: None,
+            matches: None,
         };
         assert_eq!("ENV ${TESTING_VAR} == \"testing value\"", env.to_string());
     }
@@ -74,6 +157,8 @@ mod tests {
         let is_set: bool = EnvVariable {
             var,
             expected: None,
+            contains: None,
+            matches: None,
         }
         .try_into()
         .expect("failed to check environment variable");
@@ -88,6 +173,8 @@ mod tests {
         let is_set: bool = EnvVariable {
             var,
             expected: Some(value),
+            contains: None,
+            matches: None,
         }
         .try_into()
         .expect("failed to check environment variable");
@@ -104,6 +191,8 @@ mod tests {
         let is_set: bool = EnvVariable {
             var,
             expected: None,
+            contains: None,
+            matches: None,
         }
         .try_into()
         .expect("failed to check environment variable");
@@ -117,9 +206,94 @@ mod tests {
         let is_set: bool = EnvVariable {
             var,
             expected: Some(String::from("wrong value")),
+            contains: None,
+            matches: None,
         }
         .try_into()
         .expect("failed to check environment variable");
         assert!(!is_set);
     }
+
+    fn check(var: &str, contains: Option<&str>, matches: Option<&str>) -> bool {
+        EnvVariable {
+            var: var.to_string(),
+            expected: None,
+            contains: contains.map(str::to_string),
+            matches: matches.map(str::to_string),
+        }
+        .try_into()
+        .expect("failed to check environment variable")
+    }
+
+    #[test]
+    fn test_display_env_contains_and_matches() {
+        let env = EnvVariable {
+            var: "TESTING_VAR".to_string(),
+            expected: None,
+            contains: Some("work".to_string()),
+            matches: None,
+        };
+        assert_eq!("ENV ${TESTING_VAR} CONTAINS \"work\"", env.to_string());
+        let env = EnvVariable {
+            contains: None,
+            matches: Some("wor.".to_string()),
+            ..env
+        };
+        assert_eq!("ENV ${TESTING_VAR} MATCHES \"wor.\"", env.to_string());
+    }
+
+    #[test]
+    fn test_env_variable_contains() {
+        let var = "HOARD_ENV_CONTAINS";
+        std::env::set_var(var, "home-work-laptop");
+        assert!(check(var, Some("work"), None));
+        assert!(!check(var, Some("play"), None));
+    }
+
+    #[test]
+    fn test_env_variable_matches_is_anchored() {
+        let var = "HOARD_ENV_MATCHES";
+        std::env::set_var(var, "work-laptop");
+        assert!(check(var, None, Some("work-.*")));
+        assert!(check(var, None, Some("work|work-laptop")));
+        assert!(!check(var, None, Some("work")));
+        assert!(!check(var, None, Some("laptop")));
+    }
+
+    #[test]
+    fn test_unset_env_variable_does_not_contain_or_match() {
+        let var = "HOARD_ENV_UNSET_VALUE_CHECK";
+        assert!(
+            std::env::var_os(var).is_none(),
+            "env var {var} should not be set"
+        );
+        assert!(!check(var, Some(""), None));
+        assert!(!check(var, None, Some(".*")));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_error() {
+        let err = EnvVariable {
+            var: "HOARD_ENV_BAD_REGEX".to_string(),
+            expected: None,
+            contains: None,
+            matches: Some("(unclosed".to_string()),
+        }
+        .validate()
+        .expect_err("invalid regex should fail validation");
+        assert!(matches!(err, Error::InvalidRegex { .. }), "{err}");
+    }
+
+    #[test]
+    fn test_multiple_value_checks_is_error() {
+        let err = EnvVariable {
+            var: "HOARD_ENV_MULTIPLE".to_string(),
+            expected: Some("work".to_string()),
+            contains: Some("work".to_string()),
+            matches: None,
+        }
+        .validate()
+        .expect_err("setting both expected and contains should fail validation");
+        assert!(matches!(err, Error::MultipleConditions(_)), "{err}");
+    }
 }
@@ -75,7 +75,7 @@ impl TryFrom<PathBuf> for Executable {
 
     #[tracing::instrument(name = "new_executable")]
     fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
-        let is_lone_file_name = value.parent().map_or(false, |s| s.as_os_str().is_empty());
+        let is_lone_file_name = value.parent().is_some_and(|s| s.as_os_str().is_empty());
         if value.is_absolute() || is_lone_file_name {
             Ok(Self(value))
         } else {
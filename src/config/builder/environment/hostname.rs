@@ -1,41 +1,150 @@
 //! See [`Hostname`].
 
+use glob::Pattern;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::convert::TryInto;
+use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Formatter;
-use tap::TapFallible;
+use thiserror::Error;
+
+/// Errors that may occur while checking a [`Hostname`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The `glob` pattern is not a valid glob.
+    #[error("invalid hostname glob {pattern:?}: {error}")]
+    InvalidGlob {
+        /// The invalid pattern.
+        pattern: String,
+        /// The error from parsing the pattern.
+        #[source]
+        error: glob::PatternError,
+    },
+    /// The `matches` pattern is not a valid regular expression.
+    #[error("invalid hostname regular expression {pattern:?}: {error}")]
+    InvalidRegex {
+        /// The invalid pattern.
+        pattern: String,
+        /// The error from parsing the pattern.
+        #[source]
+        error: regex::Error,
+    },
+}
 
-/// A conditional structure that compares the system's hostname to the given string.
+/// A conditional structure that compares the system's hostname to the given string or pattern.
+///
+/// If the system hostname cannot be determined, a warning is logged and the condition is false.
+///
+/// # Example (TOML)
+///
+/// ```toml
+/// hostname = [
+///     # Exactly this hostname.
+///     "thinkpad",
+///     # Any hostname matching this glob.
+///     { glob = "work-laptop-*" },
+///     # Any hostname matching this regular expression.
+///     { matches = "desktop-[0-9]+" },
+/// ]
+/// ```
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
-#[serde(transparent)]
-pub struct Hostname(pub String);
+#[serde(untagged, deny_unknown_fields)]
+pub enum Hostname {
+    /// The hostname must equal this string exactly.
+    Exact(String),
+    /// The hostname must match this glob pattern, e.g. `work-*`.
+    Glob {
+        /// The glob pattern.
+        glob: String,
+    },
+    /// The hostname must match this regular expression. The pattern is anchored, so it must match
+    /// the entire hostname.
+    Matches {
+        /// The regular expression.
+        matches: String,
+    },
+}
 
-impl TryInto<bool> for Hostname {
-    type Error = super::Error;
+impl Hostname {
+    /// Checks that any `glob` or `matches` pattern is valid.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::InvalidGlob`] if `glob` is not a valid glob pattern.
+    /// - [`Error::InvalidRegex`] if `matches` is not a valid regular expression.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Self::Exact(_) => Ok(()),
+            Self::Glob { glob } => Self::glob(glob).map(|_| ()),
+            Self::Matches { matches } => Self::regex(matches).map(|_| ()),
+        }
+    }
 
-    fn try_into(self) -> Result<bool, super::Error> {
-        let Hostname(expected) = self;
-        let host = hostname::get()
-            .map_err(super::Error::Hostname)
-            .tap_err(crate::tap_log_error)?;
+    fn glob(pattern: &str) -> Result<Pattern, Error> {
+        Pattern::new(pattern).map_err(|error| Error::InvalidGlob {
+            pattern: pattern.to_string(),
+            error,
+        })
+    }
+
+    fn regex(pattern: &str) -> Result<Regex, Error> {
+        Regex::new(&format!("^(?:{pattern})$")).map_err(|error| Error::InvalidRegex {
+            pattern: pattern.to_string(),
+            error,
+        })
+    }
+
+    /// Returns whether `host` satisfies this condition.
+    ///
+    /// Patterns never match a hostname that is not valid Unicode.
+    fn is_match(&self, host: &OsStr) -> Result<bool, Error> {
+        Ok(match self {
+            Self::Exact(expected) => host == expected.as_str(),
+            Self::Glob { glob } => {
+                let pattern = Self::glob(glob)?;
+                host.to_str().is_some_and(|host| pattern.matches(host))
+            }
+            Self::Matches { matches } => {
+                let regex = Self::regex(matches)?;
+                host.to_str().is_some_and(|host| regex.is_match(host))
+            }
+        })
+    }
+}
+
+impl TryInto<bool> for Hostname {
+    type Error = Error;
+
+    fn try_into(self) -> Result<bool, Error> {
+        self.validate()?;
+        let host = match hostname::get() {
+            Ok(host) => host,
+            Err(error) => {
+                tracing::warn!(%error, "failed to detect hostname, treating \"{}\" as false", self);
+                return Ok(false);
+            }
+        };
 
         // grcov: ignore-start
         tracing::trace!(
             hostname = host.to_string_lossy().as_ref(),
-            %expected,
+            expected = %self,
             "checking if system hostname matches expected",
         );
         // grcov: ignore-end
 
-        Ok(host == expected.as_str())
+        self.is_match(&host)
     }
 }
 
 impl fmt::Display for Hostname {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let Hostname(hostname) = self;
-        write!(f, "HOSTNAME == {hostname}")
+        match self {
+            Self::Exact(hostname) => write!(f, "HOSTNAME == {hostname}"),
+            Self::Glob { glob } => write!(f, "HOSTNAME LIKE \"{glob}\""),
+            Self::Matches { matches } => write!(f, "HOSTNAME MATCHES \"{matches}\""),
+        }
     }
 }
 
@@ -51,7 +160,7 @@ mod tests {
             .expect("failed to convert to str")
             .to_owned();
 
-        let hostname_test = Hostname(host);
+        let hostname_test = Hostname::Exact(host);
 
         let has_hostname: bool = hostname_test.try_into().expect("checking hostname failed");
 
@@ -67,10 +176,69 @@ mod tests {
             .to_owned();
         hostname.push_str("-invalid");
 
-        let hostname_test = Hostname(hostname);
+        let hostname_test = Hostname::Exact(hostname);
 
         let has_hostname: bool = hostname_test.try_into().expect("checking hostname failed");
 
         assert!(!has_hostname);
     }
+
+    #[test]
+    fn test_glob_hostname() {
+        let glob = Hostname::Glob {
+            glob: String::from("thinkpad-*"),
+        };
+        assert!(glob.is_match(OsStr::new("thinkpad-x1")).unwrap());
+        assert!(!glob.is_match(OsStr::new("desktop")).unwrap());
+        assert!(!glob.is_match(OsStr::new("my-thinkpad-x1")).unwrap());
+    }
+
+    #[test]
+    fn test_regex_hostname_is_anchored() {
+        let regex = Hostname::Matches {
+            matches: String::from("desktop-[0-9]+"),
+        };
+        assert!(regex.is_match(OsStr::new("desktop-42")).unwrap());
+        assert!(!regex.is_match(OsStr::new("desktop-42.local")).unwrap());
+        assert!(!regex.is_match(OsStr::new("old-desktop-42")).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        let glob = Hostname::Glob {
+            glob: String::from("[thinkpad"),
+        };
+        assert!(matches!(glob.validate(), Err(Error::InvalidGlob { .. })));
+        let regex = Hostname::Matches {
+            matches: String::from("(desktop"),
+        };
+        assert!(matches!(regex.validate(), Err(Error::InvalidRegex { .. })));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Debug, Deserialize)]
+        struct Wrapper {
+            hostname: Vec<Hostname>,
+        }
+
+        let Wrapper { hostname } =
+            toml::from_str(r#"hostname = ["exact", { glob = "work-*" }, { matches = "home-.+" }]"#)
+                .unwrap();
+        assert_eq!(
+            hostname,
+            vec![
+                Hostname::Exact(String::from("exact")),
+                Hostname::Glob {
+                    glob: String::from("work-*")
+                },
+                Hostname::Matches {
+                    matches: String::from("home-.+")
+                },
+            ]
+        );
+
+        toml::from_str::<Wrapper>(r#"hostname = [{ glob = "a", matches = "b" }]"#)
+            .expect_err("setting both glob and matches should fail");
+    }
 }
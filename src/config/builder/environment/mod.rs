@@ -6,7 +6,7 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::combinator::Combinator;
+use crate::combinator::{Combinator, Inner};
 
 pub use self::envvar::EnvVariable;
 pub use self::exe::ExeExists;
@@ -24,9 +24,12 @@ pub mod path;
 #[derive(Debug, Error)]
 #[allow(variant_size_differences)]
 pub enum Error {
-    /// An error that occurred while determining the system hostname.
-    #[error("failed to detect hostname: {0}")]
-    Hostname(#[from] tokio::io::Error),
+    /// A hostname condition is invalid.
+    #[error(transparent)]
+    Hostname(#[from] hostname::Error),
+    /// An environment variable condition is invalid.
+    #[error(transparent)]
+    EnvVariable(#[from] envvar::Error),
     /// An error that occurred while checking if a program exists in `$PATH`.
     #[error("failed to detect if exe exists in path: {0}")]
     ExeExists(#[from] <ExeExists as TryInto<bool>>::Error),
@@ -55,7 +58,7 @@ impl From<Infallible> for Error {
 /// ```toml
 /// [envs.first_env]
 ///     # [Hostname][`Hostname`] must match one of the items in the list.
-///     hostname = ["localhost", "localhost.localdomain", "first.env"]
+///     hostname = ["localhost", "localhost.localdomain", { glob = "first-*" }]
 ///     # The [operating system][`OperatingSystem`] must match one of the items in the list.
 ///     os = ["linux", "macos", "freebsd"]
 ///     # Either `vim`, `nvim`, or both `vi` and `nano` must exist on the system.
@@ -150,15 +153,78 @@ impl TryInto<bool> for Environment {
     }
 }
 
+/// The result of evaluating one kind of condition in an [`Environment`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    /// The field the condition was set on, e.g. `hostname` or `exe_exists`.
+    pub kind: &'static str,
+    /// The condition, as displayed for an [`Environment`].
+    pub condition: String,
+    /// Whether the condition is true for the current system.
+    pub matched: bool,
+}
+
+fn evaluate<T, E>(
+    kind: &'static str,
+    combinator: Option<&Combinator<T>>,
+) -> Result<Option<Condition>, Error>
+where
+    T: TryInto<bool, Error = E> + Clone + fmt::Display,
+    E: std::error::Error,
+    Error: From<E>,
+{
+    combinator
+        .map(|comb| {
+            Ok(Condition {
+                kind,
+                condition: comb.to_string(),
+                matched: bool::try_from(comb.clone())?,
+            })
+        })
+        .transpose()
+}
+
 impl Environment {
+    /// Evaluates each condition set on this `Environment` separately.
+    ///
+    /// The environment applies if every returned [`Condition`] matched.
+    ///
+    /// # Errors
+    ///
+    /// Any error that occurs while evaluating a condition.
+    pub fn evaluate_conditions(&self) -> Result<Vec<Condition>, Error> {
+        let Environment {
+            hostname,
+            os,
+            env,
+            exe_exists,
+            path_exists,
+        } = self;
+
+        Ok([
+            evaluate("hostname", hostname.as_ref())?,
+            evaluate("os", os.as_ref())?,
+            evaluate("env", env.as_ref())?,
+            evaluate("exe_exists", exe_exists.as_ref())?,
+            evaluate("path_exists", path_exists.as_ref())?,
+        ]
+        .into_iter()
+        .flatten()
+        .collect())
+    }
+
     /// Checks that there are no invalid or impossible conditions set.
     ///
     /// # Errors
     ///
-    /// [`Error::InvalidCondition`]
+    /// - [`Error::InvalidCondition`]
+    /// - [`Error::Hostname`] if a [`Hostname`] pattern is invalid.
+    /// - [`Error::EnvVariable`] if an [`EnvVariable`] condition is invalid.
     #[tracing::instrument(name = "validate_environment")]
     pub fn validate(&self) -> Result<(), Error> {
-        let Environment { hostname, os, .. } = self;
+        let Environment {
+            hostname, os, env, ..
+        } = self;
         if let Some(comb) = hostname {
             if comb.is_only_and() || comb.is_complex() {
                 return crate::create_log_error(Error::InvalidCondition {
@@ -166,6 +232,13 @@ impl Environment {
                     message: String::from("machines cannot have multiple hostnames at once!"),
                 });
             }
+
+            for inner in &comb.0 {
+                match inner {
+                    Inner::Single(host) => host.validate()?,
+                    Inner::Multiple(hosts) => hosts.iter().try_for_each(Hostname::validate)?,
+                }
+            }
         }
 
         if let Some(comb) = os {
@@ -179,14 +252,21 @@ impl Environment {
             }
         }
 
+        if let Some(Combinator(list)) = env {
+            for inner in list {
+                match inner {
+                    Inner::Single(var) => var.validate()?,
+                    Inner::Multiple(vars) => vars.iter().try_for_each(EnvVariable::validate)?,
+                }
+            }
+        }
+
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::combinator::Inner;
-
     use super::*;
 
     mod display {
@@ -203,11 +283,13 @@ mod tests {
 
         #[test]
         fn test_display_with_all() {
-            let hostname = Hostname("hostname.one".into());
+            let hostname = Hostname::Exact("hostname.one".into());
             let os = OperatingSystem("linux".into());
             let env_var = EnvVariable {
                 var: "TEST_VARIABLE".to_string(),
                 expected: None,
+                contains: None,
+                matches: None,
             };
             let exe_exists = ExeExists(PathBuf::from("test").try_into().unwrap());
             let path_exists = PathExists(Some(
@@ -238,14 +320,54 @@ mod tests {
         }
     }
 
+    mod evaluate_conditions {
+        use super::*;
+
+        #[test]
+        fn test_each_condition_is_evaluated() {
+            let env = Environment {
+                os: Some(Combinator(vec![Inner::Single(OperatingSystem(
+                    std::env::consts::OS.to_string(),
+                ))])),
+                hostname: Some(Combinator(vec![Inner::Single(Hostname::Exact(
+                    "no-such-host.invalid".to_string(),
+                ))])),
+                ..Environment::default()
+            };
+
+            let conditions = env.evaluate_conditions().unwrap();
+            assert_eq!(
+                conditions,
+                vec![
+                    Condition {
+                        kind: "hostname",
+                        condition: String::from("HOSTNAME == no-such-host.invalid"),
+                        matched: false,
+                    },
+                    Condition {
+                        kind: "os",
+                        condition: format!("OPERATING SYSTEM == {}", std::env::consts::OS),
+                        matched: true,
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn test_no_conditions() {
+            let conditions = Environment::default().evaluate_conditions().unwrap();
+            assert!(conditions.is_empty());
+        }
+    }
+
     mod validate_hostname {
         use super::*;
 
         #[test]
         fn test_env_condition_invalid_hostname_only_and() {
             let combinator = Combinator(vec![Inner::Multiple(vec![
-                Hostname("hostname.one".to_string()),
-                Hostname("hostname.two".to_string()),
+                Hostname::Exact("hostname.one".to_string()),
+                Hostname::Exact("hostname.two".to_string()),
             ])]);
 
             let condition = Environment {
@@ -266,10 +388,10 @@ mod tests {
         #[test]
         fn test_env_condition_invalid_hostname_complex() {
             let combinator = Combinator(vec![
-                Inner::Single(Hostname("hostname.single".to_string())),
+                Inner::Single(Hostname::Exact("hostname.single".to_string())),
                 Inner::Multiple(vec![
-                    Hostname("hostname.one".to_string()),
-                    Hostname("hostname.two".to_string()),
+                    Hostname::Exact("hostname.one".to_string()),
+                    Hostname::Exact("hostname.two".to_string()),
                 ]),
             ]);
 
@@ -290,8 +412,8 @@ mod tests {
         #[test]
         fn test_env_condition_valid_hostname() {
             let combinator = Combinator(vec![
-                Inner::Single(Hostname("hostname.one".to_string())),
-                Inner::Single(Hostname("hostname.two".to_string())),
+                Inner::Single(Hostname::Exact("hostname.one".to_string())),
+                Inner::Single(Hostname::Exact("hostname.two".to_string())),
             ]);
 
             let condition = Environment {
@@ -303,6 +425,26 @@ mod tests {
                 .validate()
                 .expect("expecting one of two hostnames should succeed");
         }
+
+        #[test]
+        fn test_env_condition_invalid_hostname_pattern() {
+            let combinator = Combinator(vec![Inner::Single(Hostname::Matches {
+                matches: String::from("(unclosed"),
+            })]);
+
+            let condition = Environment {
+                hostname: Some(combinator),
+                ..Environment::default()
+            };
+
+            let err = condition
+                .validate()
+                .expect_err("an invalid hostname pattern should fail");
+            match err {
+                Error::Hostname(hostname::Error::InvalidRegex { .. }) => {}
+                err => panic!("unexpected error: {err}"),
+            }
+        }
     }
 
     mod validate_operating_system {
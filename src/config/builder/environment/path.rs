@@ -1,6 +1,6 @@
 //! See [`PathExists`].
 
-use crate::env_vars::PathWithEnv;
+use crate::env_vars::{Error as EnvError, PathWithEnv};
 use crate::paths::SystemPath;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use std::convert::{Infallible, TryInto};
@@ -28,8 +28,12 @@ impl<'de> de::Visitor<'de> for PathExistsVisitor {
         E: de::Error,
     {
         tracing::trace!("parsing path_exists item {}", s);
-        let inner = PathWithEnv::from(s).process().ok();
-        Ok(PathExists(inner))
+        match PathWithEnv::from(s).process() {
+            Ok(path) => Ok(PathExists(Some(path))),
+            // A variable marked as required should not quietly make the condition false.
+            Err(error @ EnvError::Required { .. }) => Err(E::custom(error)),
+            Err(_) => Ok(PathExists(None)),
+        }
     }
 }
 
@@ -158,4 +162,22 @@ mod tests {
         let path = PathExists(Some(system_path!("/test/path/hoard-test/leaf")));
         assert_de_tokens(&path, &[Token::Str(path_with_env)]);
     }
+
+    #[test]
+    fn test_missing_env_is_none() {
+        std::env::remove_var("HOARD_TEST_MISSING_ENV");
+        assert_de_tokens(
+            &PathExists(None),
+            &[Token::Str("/test/path/${HOARD_TEST_MISSING_ENV}/leaf")],
+        );
+    }
+
+    #[test]
+    fn test_missing_required_env_is_error() {
+        std::env::remove_var("HOARD_TEST_REQUIRED_ENV");
+        assert_de_tokens_error::<PathExists>(
+            &[Token::Str("/test/path/${HOARD_TEST_REQUIRED_ENV:?}/leaf")],
+            "required environment variable is not set: HOARD_TEST_REQUIRED_ENV",
+        );
+    }
 }
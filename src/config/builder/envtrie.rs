@@ -8,11 +8,11 @@ use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashSet};
 
 use petgraph::algo::toposort;
-use petgraph::graph::DiGraph;
+use petgraph::graph::{DiGraph, NodeIndex};
 use tap::TapFallible;
 use thiserror::Error;
 
-use crate::env_vars::PathWithEnv;
+use crate::config::builder::hoard::PilePaths;
 use crate::newtypes::{EnvironmentName, EnvironmentString};
 
 /// Errors that may occur while building or evaluating an [`EnvTrie`].
@@ -24,7 +24,7 @@ pub enum Error {
     /// One [`Pile`](super::hoard::Pile) has the same combination of environments defined
     /// multiple times.
     #[error("The same condition is defined twice with paths {0} and {1}")]
-    DoubleDefine(PathWithEnv, PathWithEnv),
+    DoubleDefine(PilePaths, PilePaths),
     /// No environment exists with the given name, but a [`Pile`](super::hoard::Pile) thinks
     /// one does.
     #[error("\"{0}\" is not an environment that exists")]
@@ -47,7 +47,7 @@ pub enum Error {
 struct Node {
     score: usize,
     tree: Option<BTreeMap<EnvironmentName, Node>>,
-    value: Option<PathWithEnv>,
+    value: Option<PilePaths>,
     name: EnvironmentName,
 }
 
@@ -81,7 +81,7 @@ fn merge_two_trees(
 #[allow(single_use_lifetimes)]
 struct Evaluation<'a> {
     name: EnvironmentString,
-    path: Option<&'a PathWithEnv>,
+    path: Option<&'a PilePaths>,
     scores: Vec<usize>,
 }
 
@@ -166,7 +166,10 @@ impl Node {
     }
 
     #[tracing::instrument(level = "trace")]
-    fn get_evaluation(&self, envs: &BTreeMap<EnvironmentName, bool>) -> Result<Evaluation, Error> {
+    fn get_evaluation(
+        &self,
+        envs: &BTreeMap<EnvironmentName, bool>,
+    ) -> Result<Evaluation<'_>, Error> {
         // Default evaluation if subtree does not exist
         let mut eval = Evaluation {
             name: EnvironmentString::from(self.name.clone()),
@@ -247,7 +250,7 @@ impl Node {
     fn get_highest_path(
         &self,
         envs: &BTreeMap<EnvironmentName, bool>,
-    ) -> Result<Option<&PathWithEnv>, Error> {
+    ) -> Result<Option<&PilePaths>, Error> {
         tracing::trace!("evaluating envtrie for best matching path");
         let Evaluation { path, .. } = self.get_evaluation(envs).tap_err(crate::tap_log_error)?;
         Ok(path)
@@ -272,12 +275,16 @@ fn get_weighted_map(
     // Check for cycles, then discard graph
     tracing::trace!("checking for cycles");
     let mut score_dag = DiGraph::<EnvironmentName, ()>::new();
+    // Each environment must have a single node, or cycles across lists are never found
+    let mut indices: BTreeMap<&EnvironmentName, NodeIndex> = BTreeMap::new();
     for list in exclusive_list {
         let mut prev_idx = None;
 
         for name in list.iter().rev() {
             // Add node to graph
-            let idx = score_dag.add_node(name.clone());
+            let idx = *indices
+                .entry(name)
+                .or_insert_with(|| score_dag.add_node(name.clone()));
 
             // If not first node, create edge
             if let Some(prev) = prev_idx {
@@ -317,6 +324,16 @@ fn get_weighted_map(
     Ok(weighted_map)
 }
 
+/// Checks that no two exclusivity lists disagree on which environment is preferred, like
+/// `["foo", "bar"]` and `["bar", "foo"]`, and that no list names an environment twice.
+///
+/// # Errors
+///
+/// [`Error::WeightCycle`] with one of the environments whose preference is contradictory.
+pub fn check_exclusivity(exclusive_list: &[Vec<EnvironmentName>]) -> Result<(), Error> {
+    get_weighted_map(exclusive_list).map(|_| ())
+}
+
 fn merge_maps(
     mut map1: BTreeMap<EnvironmentName, HashSet<EnvironmentName>>,
     map2: BTreeMap<EnvironmentName, HashSet<EnvironmentName>>,
@@ -362,7 +379,7 @@ impl EnvTrie {
     /// Any [`enum@Error`] relating to parsing or validating environment condition strings.
     #[tracing::instrument(level = "trace", name = "new_envtrie")]
     pub fn new(
-        envs: &BTreeMap<EnvironmentString, PathWithEnv>,
+        envs: &BTreeMap<EnvironmentString, PilePaths>,
         exclusive_list: &[Vec<EnvironmentName>],
     ) -> Result<Self, Error> {
         tracing::trace!("creating a new envtrie");
@@ -458,7 +475,7 @@ impl EnvTrie {
     pub fn get_path(
         &self,
         environments: &BTreeMap<EnvironmentName, bool>,
-    ) -> Result<Option<&PathWithEnv>, Error> {
+    ) -> Result<Option<&PilePaths>, Error> {
         tracing::trace!(
             trie = ?self,
             ?environments,
@@ -564,7 +581,7 @@ mod tests {
         (name: $name:ident, environments: $envs:expr, exclusivity: $excl:expr, expected: $result:expr) => {
             #[test]
             fn $name() {
-                let environments: BTreeMap<EnvironmentString, PathWithEnv> = $envs;
+                let environments: BTreeMap<EnvironmentString, PilePaths> = $envs;
                 let exclusivity: Vec<Vec<EnvironmentName>> = $excl;
 
                 let res: Result<EnvTrie, Error> = EnvTrie::new(&environments, &exclusivity);
@@ -707,4 +724,37 @@ mod tests {
         exclusivity: vec![vec![LABEL_A_1.parse().unwrap(), LABEL_A_2.parse().unwrap()]],
         expected: Err(Error::CombinedMutuallyExclusive(format!("{LABEL_A_1}|{LABEL_A_2}").parse().unwrap()))
     }
+
+    #[test]
+    fn test_contradicting_exclusivity_is_invalid() {
+        let first: EnvironmentName = LABEL_A_1.parse().unwrap();
+        let second: EnvironmentName = LABEL_A_2.parse().unwrap();
+        let exclusivity = vec![
+            vec![first.clone(), second.clone()],
+            vec![second.clone(), first.clone()],
+        ];
+        match check_exclusivity(&exclusivity) {
+            Err(Error::WeightCycle(env)) => assert!(env == first || env == second),
+            result => panic!("expected a weight cycle, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_repeated_exclusive_env_is_invalid() {
+        let env: EnvironmentName = LABEL_A_1.parse().unwrap();
+        assert_eq!(
+            check_exclusivity(&[vec![env.clone(), env.clone()]]),
+            Err(Error::WeightCycle(env))
+        );
+    }
+
+    #[test]
+    fn test_overlapping_exclusivity_is_valid() {
+        let exclusivity = vec![
+            vec![LABEL_A_1.parse().unwrap(), LABEL_A_2.parse().unwrap()],
+            vec![LABEL_A_2.parse().unwrap(), LABEL_A_3.parse().unwrap()],
+            vec![LABEL_A_1.parse().unwrap(), LABEL_A_3.parse().unwrap()],
+        ];
+        check_exclusivity(&exclusivity).expect("lists agree on the preferred environments");
+    }
 }
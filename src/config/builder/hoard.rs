@@ -9,14 +9,20 @@
 //! used.
 
 use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::config::builder::envtrie::{EnvTrie, Error as TrieError};
 use crate::env_vars::{Error as EnvError, PathWithEnv};
+use crate::filters::ignore::IgnoreFilter;
+use crate::hoard::hooks::Hooks;
+use crate::hoard::pile_glob::{Error as GlobError, PileGlob};
 use crate::hoard::PileConfig;
 use crate::newtypes::{EnvironmentName, EnvironmentString, NonEmptyPileName};
+use crate::paths::SystemPath;
 
 type ConfigMultiple = crate::config::hoard::MultipleEntries;
 type ConfigSingle = crate::config::hoard::Pile;
@@ -32,6 +38,102 @@ pub enum Error {
     /// Error while expanding environment variables in a path.
     #[error("error while expanding environment variables in path: {0}")]
     ExpandEnv(#[from] EnvError),
+    /// Hooks were set on a named pile instead of on its hoard.
+    #[error("hooks must be set on the hoard, not on pile {0}")]
+    PileHooks(NonEmptyPileName),
+    /// Error while parsing a pile path as a glob.
+    #[error("error while parsing glob in pile path: {0}")]
+    Glob(#[from] GlobError),
+    /// A pile was given an empty list of paths.
+    #[error("a pile cannot have an empty list of paths")]
+    NoPaths,
+    /// A glob pile was given multiple paths.
+    #[error("a glob pile can only have a single path")]
+    GlobWithMultiplePaths,
+    /// One path of a pile with multiple paths is the same as or contains another.
+    #[error("pile paths {} and {} overlap", .0.display(), .1.display())]
+    OverlappingPaths(PathBuf, PathBuf),
+    /// One of the pile's ignore patterns is not a valid gitignore pattern.
+    #[error("invalid ignore pattern: {0}")]
+    Ignore(#[from] ignore::Error),
+    /// The pile sets a setting that Hoard does not support.
+    #[error("the `{0}` setting is not supported: hoard stores files uncompressed, remove it from the configuration")]
+    Unsupported(&'static str),
+}
+
+/// The path or paths of a [`Pile`] for a single environment condition.
+///
+/// Multiple paths are merged into the same hoard subtree, with the first path being the
+/// primary one. See [`SourceConflict`](crate::hoard::pile_config::SourceConflict) for what
+/// happens when a file exists under more than one of them.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PilePaths {
+    /// A single path.
+    Single(PathWithEnv),
+    /// Multiple paths merged into one pile.
+    Multiple(Vec<PathWithEnv>),
+}
+
+impl From<PathWithEnv> for PilePaths {
+    fn from(path: PathWithEnv) -> Self {
+        Self::Single(path)
+    }
+}
+
+impl From<&str> for PilePaths {
+    fn from(path: &str) -> Self {
+        Self::Single(path.into())
+    }
+}
+
+impl fmt::Display for PilePaths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Single(path) => path.fmt(f),
+            Self::Multiple(paths) => {
+                write!(f, "[")?;
+                for (i, path) in paths.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    path.fmt(f)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl PilePaths {
+    /// Expand environment variables in every path, returning the primary path first.
+    fn process(self) -> Result<Vec<SystemPath>, Error> {
+        let paths = match self {
+            Self::Single(path) => vec![path],
+            Self::Multiple(paths) if paths.is_empty() => {
+                return crate::create_log_error(Error::NoPaths)
+            }
+            Self::Multiple(paths) => paths,
+        };
+
+        let paths = paths
+            .into_iter()
+            .map(PathWithEnv::process)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (i, first) in paths.iter().enumerate() {
+            for second in &paths[i + 1..] {
+                if first.starts_with(second) || second.starts_with(first) {
+                    return crate::create_log_error(Error::OverlappingPaths(
+                        first.to_path_buf(),
+                        second.to_path_buf(),
+                    ));
+                }
+            }
+        }
+
+        Ok(paths)
+    }
 }
 
 /// A single pile in the hoard.
@@ -42,11 +144,22 @@ pub struct Pile {
     /// Will be merged with higher-level configuration. If no configuration is specified
     /// (i.e., merging results in `None`), a default configuration will be used.
     pub config: Option<PileConfig>,
-    /// Mapping of environment strings to a string path that may contain environment variables.
+    /// Commands to run before and after backing up or restoring an anonymous hoard.
+    ///
+    /// Not allowed on the piles of a named hoard; use [`MultipleEntries::hooks`] instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Hooks>,
+    /// Whether the path is a glob pattern that may match multiple files or directories.
+    ///
+    /// See [`PileGlob`] for how matched paths are stored in the hoard.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub glob: bool,
+    /// Mapping of environment strings to one or more string paths that may contain environment
+    /// variables.
     ///
     /// See [`PathWithEnv`] for more on path format.
     #[serde(flatten)]
-    pub items: BTreeMap<EnvironmentString, PathWithEnv>,
+    pub items: BTreeMap<EnvironmentString, PilePaths>,
 }
 
 impl Pile {
@@ -56,17 +169,47 @@ impl Pile {
         envs: &BTreeMap<EnvironmentName, bool>,
         exclusivity: &[Vec<EnvironmentName>],
     ) -> Result<ConfigSingle, Error> {
-        let Pile { config, items } = self;
+        let Pile {
+            config,
+            items,
+            glob,
+            ..
+        } = self;
         let trie = EnvTrie::new(&items, exclusivity)?;
-        let path = trie
+        let mut paths = trie
             .get_path(envs)?
             .cloned()
-            .map(PathWithEnv::process)
-            .transpose()?;
+            .map(PilePaths::process)
+            .transpose()?
+            .unwrap_or_default()
+            .into_iter();
+        let path = paths.next();
+        let extra_paths: Vec<_> = paths.collect();
+
+        if glob && !extra_paths.is_empty() {
+            return crate::create_log_error(Error::GlobWithMultiplePaths);
+        }
+
+        let (path, glob) = match path {
+            Some(path) if glob => match PileGlob::split(&path)? {
+                Some((root, glob)) => (Some(root), Some(glob)),
+                None => (Some(path), None),
+            },
+            path => (path, None),
+        };
+
+        let mut config = config.unwrap_or_default();
+        if let Some(setting) = config.unsupported_setting() {
+            return crate::create_log_error(Error::Unsupported(setting));
+        }
+        config.apply_env_ignore(envs);
+        IgnoreFilter::build_matcher(&config.ignore)?;
 
         Ok(ConfigSingle {
-            config: config.unwrap_or_default(),
+            config,
             path,
+            extra_paths,
+            glob,
         })
     }
 
@@ -82,6 +225,9 @@ pub struct MultipleEntries {
     ///
     /// If `None`, a default configuration will be used during processing.
     pub config: Option<PileConfig>,
+    /// Commands to run before and after backing up or restoring this hoard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<Hooks>,
     /// A mapping of pile name to not-yet-processed [`Pile`]s.
     #[serde(flatten)]
     pub items: BTreeMap<NonEmptyPileName, Pile>,
@@ -94,13 +240,16 @@ impl MultipleEntries {
         envs: &BTreeMap<EnvironmentName, bool>,
         exclusivity: &[Vec<EnvironmentName>],
     ) -> Result<ConfigMultiple, super::Error> {
-        let MultipleEntries { config, items } = self;
+        let MultipleEntries { config, items, .. } = self;
         let items = items
             .into_iter()
             .map(|(pile, mut entry)| {
                 tracing::debug!(%pile, "processing pile");
+                if entry.hooks.is_some() {
+                    return Err(Error::PileHooks(pile).into());
+                }
                 entry.layer_config(config.as_ref());
-                let entry = entry.process_with(envs, exclusivity).map_err(Error::from)?;
+                let entry = entry.process_with(envs, exclusivity)?;
                 Ok((pile, entry))
             })
             .collect::<Result<_, super::Error>>()?;
@@ -162,6 +311,22 @@ impl Hoard {
             Hoard::Multiple(multi) => multi.layer_config(config),
         }
     }
+
+    /// The hooks configured for this `Hoard`, if any.
+    pub(crate) fn hooks(&self) -> Option<&Hooks> {
+        match self {
+            Hoard::Single(pile) => pile.hooks.as_ref(),
+            Hoard::Multiple(multi) => multi.hooks.as_ref(),
+        }
+    }
+
+    /// Whether any [`Pile`] in this `Hoard` defines a path for at least one environment condition.
+    pub(crate) fn has_any_path(&self) -> bool {
+        match self {
+            Hoard::Single(pile) => !pile.items.is_empty(),
+            Hoard::Multiple(multi) => multi.items.values().any(|pile| !pile.items.is_empty()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -179,6 +344,7 @@ mod tests {
 
         use crate::hoard::Pile as RealPile;
         use crate::paths::SystemPath;
+        use crate::test::system_path;
 
         use super::*;
 
@@ -186,6 +352,8 @@ mod tests {
         fn env_vars_are_expanded() {
             let pile = Pile {
                 config: None,
+                hooks: None,
+                glob: false,
                 #[cfg(unix)]
                 items: btreemap! {
                     "foo".parse().unwrap() => "${HOME}/something".into()
@@ -205,6 +373,8 @@ mod tests {
                 path: Some(
                     SystemPath::try_from(PathBuf::from(format!("{home}/something"))).unwrap(),
                 ),
+                extra_paths: Vec::new(),
+                glob: None,
             };
 
             let envs = btreemap! { "foo".parse().unwrap() =>  true };
@@ -214,6 +384,211 @@ mod tests {
 
             assert_eq!(result, expected);
         }
+
+        #[test]
+        fn glob_path_is_split() {
+            let hoard: Hoard = toml::from_str(
+                r#"
+                [saves]
+                    "foo" = "/game/profiles/*/save.dat"
+                    glob = true
+                [literal]
+                    "foo" = "/game/profiles/*/save.dat"
+                "#,
+            )
+            .expect("hoard should deserialize");
+
+            let envs = btreemap! { "foo".parse().unwrap() => true };
+            let ConfigHoard::Named(named) = hoard
+                .process_with(&envs, &[])
+                .expect("hoard should process without issues")
+            else {
+                panic!("expected a hoard with named piles");
+            };
+
+            let saves = &named.piles[&"saves".parse().unwrap()];
+            assert_eq!(saves.path, Some(system_path!("/game/profiles")));
+            assert_eq!(
+                saves.glob.as_ref().map(PileGlob::as_str),
+                Some("*/save.dat")
+            );
+
+            let literal = &named.piles[&"literal".parse().unwrap()];
+            assert_eq!(
+                literal.path,
+                Some(system_path!("/game/profiles/*/save.dat"))
+            );
+            assert_eq!(literal.glob, None);
+        }
+
+        #[test]
+        fn multiple_paths_are_processed_in_order() {
+            let hoard: Hoard = toml::from_str(
+                r#"
+                [saves]
+                    "foo" = ["/game/saves", "/game/profiles"]
+                "#,
+            )
+            .expect("hoard should deserialize");
+
+            let envs = btreemap! { "foo".parse().unwrap() => true };
+            let ConfigHoard::Named(named) = hoard
+                .process_with(&envs, &[])
+                .expect("hoard should process without issues")
+            else {
+                panic!("expected a hoard with named piles");
+            };
+
+            let saves = &named.piles[&"saves".parse().unwrap()];
+            assert_eq!(saves.path, Some(system_path!("/game/saves")));
+            assert_eq!(saves.extra_paths, vec![system_path!("/game/profiles")]);
+        }
+
+        fn process_error(pile: &str) -> Error {
+            let hoard: Hoard = toml::from_str(pile).expect("hoard should deserialize");
+            let envs = btreemap! { "foo".parse().unwrap() => true };
+            match hoard.process_with(&envs, &[]) {
+                Err(crate::config::builder::Error::ProcessHoard(error)) => error,
+                result => panic!("expected a pile error, got {result:?}"),
+            }
+        }
+
+        #[test]
+        fn invalid_multiple_paths_are_error() {
+            assert!(matches!(process_error(r#""foo" = []"#), Error::NoPaths));
+            assert!(matches!(
+                process_error(r#""foo" = ["/game/saves", "/game/saves/slot1"]"#),
+                Error::OverlappingPaths(..)
+            ));
+            assert!(matches!(
+                process_error(r#""foo" = ["/game/saves", "/game/saves"]"#),
+                Error::OverlappingPaths(..)
+            ));
+            assert!(matches!(
+                process_error(
+                    r#"
+                    "foo" = ["/game/*/saves", "/other/*/saves"]
+                    glob = true
+                    "#
+                ),
+                Error::GlobWithMultiplePaths
+            ));
+        }
+
+        #[test]
+        fn invalid_ignore_pattern_is_error() {
+            assert!(matches!(
+                process_error(
+                    r#"
+                    "foo" = "/game/saves"
+                    config = { ignore = ["{unclosed"] }
+                    "#
+                ),
+                Error::Ignore(_)
+            ));
+        }
+
+        #[test]
+        fn compression_is_error() {
+            for pile in [
+                r#"
+                "foo" = "/game/saves"
+                config = { compression = "zstd" }
+                "#,
+                r#"
+                config = { compression_level = 15 }
+                [saves]
+                "foo" = "/game/saves"
+                "#,
+            ] {
+                let hoard: Hoard = toml::from_str(pile).expect("hoard should deserialize");
+                let envs = btreemap! { "foo".parse().unwrap() => true };
+                let error = hoard
+                    .process_with(&envs, &[])
+                    .expect_err("compression should be rejected");
+                assert!(
+                    error.to_string().contains("setting is not supported"),
+                    "unexpected error: {error}"
+                );
+            }
+        }
+
+        fn processed_ignore(hoard: &Hoard, envs: &BTreeMap<EnvironmentName, bool>) -> Vec<String> {
+            let ConfigHoard::Named(named) = hoard
+                .clone()
+                .process_with(envs, &[])
+                .expect("hoard should process without issues")
+            else {
+                panic!("expected a hoard with named piles");
+            };
+            let config = &named.piles[&"saves".parse().unwrap()].config;
+            assert!(config.env_ignore.is_empty());
+            config.ignore.iter().map(ToString::to_string).collect()
+        }
+
+        #[test]
+        fn env_ignore_only_applies_to_active_env() {
+            let hoard: Hoard = toml::from_str(
+                r#"
+                config = { env_ignore = { windows = ["Thumbs.db"] } }
+                [saves]
+                    "windows" = "/windows/saves"
+                    "macos" = "/saves"
+                    config = { ignore = ["*.tmp"], env_ignore = { windows = ["desktop.ini"], macos = [".DS_Store"] } }
+                "#,
+            )
+            .expect("hoard should deserialize");
+
+            let windows = btreemap! {
+                "windows".parse().unwrap() => true,
+                "macos".parse().unwrap() => false,
+            };
+            assert_eq!(
+                processed_ignore(&hoard, &windows),
+                vec!["*.tmp", "Thumbs.db", "desktop.ini"]
+            );
+
+            let macos = btreemap! {
+                "windows".parse().unwrap() => false,
+                "macos".parse().unwrap() => true,
+            };
+            assert_eq!(processed_ignore(&hoard, &macos), vec!["*.tmp", ".DS_Store"]);
+        }
+
+        #[test]
+        fn hooks_on_named_pile_are_error() {
+            let hoard: Hoard = toml::from_str(
+                r#"
+                hooks = { pre = "true" }
+                [first]
+                    "foo" = "/some/path"
+                [second]
+                    "foo" = "/other/path"
+                    hooks = { post = "true" }
+                "#,
+            )
+            .expect("hoard should deserialize");
+            assert_eq!(
+                hoard.hooks(),
+                Some(&Hooks {
+                    pre: Some("true".to_string()),
+                    post: None,
+                })
+            );
+
+            let envs = btreemap! { "foo".parse().unwrap() => true };
+            let error = hoard
+                .process_with(&envs, &[])
+                .expect_err("hooks on a named pile should fail");
+            assert!(
+                matches!(
+                    &error,
+                    crate::config::builder::Error::ProcessHoard(Error::PileHooks(pile))
+                        if pile.as_ref() == "second"
+                ),
+                "unexpected error: {error:?}"
+            );
+        }
     }
 
     mod serde {
@@ -226,6 +601,8 @@ mod tests {
         fn single_entry_no_config() {
             let hoard = Hoard::Single(Pile {
                 config: None,
+                hooks: None,
+                glob: false,
                 items: btreemap! {
                     "bar_env|foo_env".parse().unwrap() => "/some/path".into()
                 },
@@ -244,6 +621,61 @@ mod tests {
             );
         }
 
+        #[test]
+        fn single_entry_multiple_paths() {
+            let hoard = Hoard::Single(Pile {
+                config: None,
+                hooks: None,
+                glob: false,
+                items: btreemap! {
+                    "foo_env".parse().unwrap() => PilePaths::Multiple(vec![
+                        "/some/path".into(),
+                        "/other/path".into(),
+                    ])
+                },
+            });
+
+            assert_tokens(
+                &hoard,
+                &[
+                    Token::Map { len: None },
+                    Token::Str("config"),
+                    Token::None,
+                    Token::Str("foo_env"),
+                    Token::Seq { len: Some(2) },
+                    Token::Str("/some/path"),
+                    Token::Str("/other/path"),
+                    Token::SeqEnd,
+                    Token::MapEnd,
+                ],
+            );
+        }
+
+        #[test]
+        fn single_entry_with_hooks() {
+            let hoard: Hoard = toml::from_str(
+                r#"
+                "foo" = "/some/path"
+                hooks = { pre = "pkill game", post = "game &" }
+                "#,
+            )
+            .expect("hoard should deserialize");
+            assert_eq!(
+                hoard,
+                Hoard::Single(Pile {
+                    config: None,
+                    hooks: Some(Hooks {
+                        pre: Some("pkill game".to_string()),
+                        post: Some("game &".to_string()),
+                    }),
+                    glob: false,
+                    items: btreemap! {
+                        "foo".parse().unwrap() => "/some/path".into()
+                    },
+                })
+            );
+        }
+
         #[test]
         fn single_entry_with_config() {
             let hoard = Hoard::Single(Pile {
@@ -253,6 +685,8 @@ mod tests {
                     })),
                     ..PileConfig::default()
                 }),
+                hooks: None,
+                glob: false,
                 items: btreemap! {
                     "bar_env|foo_env".parse().unwrap() => "/some/path".into()
                 },
@@ -266,7 +700,7 @@ mod tests {
                     Token::Some,
                     Token::Struct {
                         name: "Config",
-                        len: 5,
+                        len: 12,
                     },
                     Token::Str("hash_algorithm"),
                     Token::None,
@@ -284,10 +718,25 @@ mod tests {
                     Token::Str("ignore"),
                     Token::Seq { len: Some(0) },
                     Token::SeqEnd,
+                    Token::Str("checksum_ignore"),
+                    Token::Seq { len: Some(0) },
+                    Token::SeqEnd,
                     Token::Str("file_permissions"),
                     Token::None,
                     Token::Str("folder_permissions"),
                     Token::None,
+                    Token::Str("copy_attributes"),
+                    Token::None,
+                    Token::Str("normalize_line_endings"),
+                    Token::None,
+                    Token::Str("respect_gitignore"),
+                    Token::None,
+                    Token::Str("locked_files"),
+                    Token::None,
+                    Token::Str("source_conflict"),
+                    Token::None,
+                    Token::Str("max_file_size"),
+                    Token::None,
                     Token::StructEnd,
                     Token::Str("bar_env|foo_env"),
                     Token::Str("/some/path"),
@@ -300,9 +749,12 @@ mod tests {
         fn multiple_entry_no_config() {
             let hoard = Hoard::Multiple(MultipleEntries {
                 config: None,
+                hooks: None,
                 items: btreemap! {
                     "item1".parse().unwrap() => Pile {
                         config: None,
+                        hooks: None,
+                        glob: false,
                         items: btreemap! {
                             "bar_env|foo_env".parse().unwrap() => "/some/path".into()
                         }
@@ -337,9 +789,12 @@ mod tests {
                     ))),
                     ..PileConfig::default()
                 }),
+                hooks: None,
                 items: btreemap! {
                     "item1".parse().unwrap() => Pile {
                         config: None,
+                        hooks: None,
+                        glob: false,
                         items: btreemap! {
                             "bar_env|foo_env".parse().unwrap() => "/some/path".into()
                         }
@@ -355,7 +810,7 @@ mod tests {
                     Token::Some,
                     Token::Struct {
                         name: "Config",
-                        len: 5,
+                        len: 12,
                     },
                     Token::Str("hash_algorithm"),
                     Token::None,
@@ -370,10 +825,25 @@ mod tests {
                     Token::Str("ignore"),
                     Token::Seq { len: Some(0) },
                     Token::SeqEnd,
+                    Token::Str("checksum_ignore"),
+                    Token::Seq { len: Some(0) },
+                    Token::SeqEnd,
                     Token::Str("file_permissions"),
                     Token::None,
                     Token::Str("folder_permissions"),
                     Token::None,
+                    Token::Str("copy_attributes"),
+                    Token::None,
+                    Token::Str("normalize_line_endings"),
+                    Token::None,
+                    Token::Str("respect_gitignore"),
+                    Token::None,
+                    Token::Str("locked_files"),
+                    Token::None,
+                    Token::Str("source_conflict"),
+                    Token::None,
+                    Token::Str("max_file_size"),
+                    Token::None,
                     Token::StructEnd,
                     Token::Str("item1"),
                     Token::Map { len: None },
@@ -393,7 +863,7 @@ mod tests {
                 &[
                     Token::Struct {
                         name: "Config",
-                        len: 5,
+                        len: 12,
                     },
                     Token::Str("hash_algorithm"),
                     Token::None,
@@ -429,7 +899,7 @@ mod tests {
                 &[
                     Token::Struct {
                         name: "Config",
-                        len: 5,
+                        len: 12,
                     },
                     Token::Str("hash_algorithm"),
                     Token::None,
@@ -440,10 +910,25 @@ mod tests {
                     Token::Str("**/valid*"),
                     Token::Str("*/also_valid/**"),
                     Token::SeqEnd,
+                    Token::Str("checksum_ignore"),
+                    Token::Seq { len: Some(0) },
+                    Token::SeqEnd,
                     Token::Str("file_permissions"),
                     Token::None,
                     Token::Str("folder_permissions"),
                     Token::None,
+                    Token::Str("copy_attributes"),
+                    Token::None,
+                    Token::Str("normalize_line_endings"),
+                    Token::None,
+                    Token::Str("respect_gitignore"),
+                    Token::None,
+                    Token::Str("locked_files"),
+                    Token::None,
+                    Token::Str("source_conflict"),
+                    Token::None,
+                    Token::Str("max_file_size"),
+                    Token::None,
                     Token::StructEnd,
                 ],
             );
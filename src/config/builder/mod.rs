@@ -1,7 +1,7 @@
 //! The [`Builder`] struct serves as an intermediate step between raw configuration and the
 //! [`Config`] type that is used by `hoard`.
 use std::collections::BTreeMap;
-use std::convert::TryInto;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 
 use clap::Parser;
@@ -9,11 +9,13 @@ use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::{fs, io};
+use tracing::Level;
 
-use environment::Environment;
+use environment::{Condition, Environment};
 
-use crate::command::Command;
+use crate::command::{self, Command};
 use crate::config::builder::var_defaults::{EnvVarDefaults, EnvVarDefaultsError};
+use crate::hoard::hooks::Hooks;
 use crate::hoard::PileConfig;
 use crate::newtypes::{EnvironmentName, HoardName};
 use crate::CONFIG_FILE_STEM;
@@ -28,18 +30,47 @@ pub mod hoard;
 pub mod var_defaults;
 
 const DEFAULT_CONFIG_EXT: &str = "toml";
+/// The directory, next to the default configuration file, of fragments merged over it.
+const CONFIG_FRAGMENTS_DIR: &str = "config.d";
 /// The items are listed in descending order of precedence
-const SUPPORTED_CONFIG_EXTS: [&str; 3] = ["toml", "yaml", "yml"];
+/// Supported configuration file extensions, in order of precedence.
+const SUPPORTED_CONFIG_EXTS: &[&str] = &[
+    "toml",
+    #[cfg(feature = "yaml")]
+    "yaml",
+    #[cfg(feature = "yaml")]
+    "yml",
+    #[cfg(feature = "json")]
+    "json",
+];
 
 /// Errors that can happen when using a [`Builder`].
 #[derive(Debug, Error)]
 pub enum Error {
     /// Error while parsing a TOML configuration file.
-    #[error("failed to parse TOML configuration file: {0}")]
-    DeserializeTOML(toml::de::Error),
+    #[error(
+        "failed to parse TOML configuration file {}{}: {}",
+        path.display(),
+        location.map_or_else(String::new, |(line, column)| format!(" at line {line}, column {column}")),
+        error.message()
+    )]
+    DeserializeTOML {
+        /// The configuration file that failed to parse.
+        path: PathBuf,
+        /// The line and column (both starting at 1) of the problem, if known.
+        location: Option<(usize, usize)>,
+        /// The underlying parsing error.
+        #[source]
+        error: Box<toml::de::Error>,
+    },
     /// Error while parsing a YAML configuration file.
+    #[cfg(feature = "yaml")]
     #[error("failed to parse YAML configuration file: {0}")]
     DeserializeYAML(serde_yaml::Error),
+    /// Error while parsing a JSON configuration file.
+    #[cfg(feature = "json")]
+    #[error("failed to parse JSON configuration file: {0}")]
+    DeserializeJSON(serde_json::Error),
     /// Error while reading from a configuration file.
     #[error("failed to read configuration file: {0}")]
     ReadConfig(io::Error),
@@ -49,9 +80,13 @@ pub enum Error {
     /// Error while determining which paths to use for configured hoards.
     #[error("failed to process hoard configuration: {0}")]
     ProcessHoard(#[from] hoard::Error),
+    /// The exclusivity lists contradict each other.
+    #[error("invalid exclusivity lists: {0}")]
+    Exclusivity(#[source] envtrie::Error),
     /// The given file has no or invalid file extension
     #[error(
-        "configuration file does not have file extension \".toml\", \".yaml\", or \".yml\": {0}"
+        "configuration file does not have a supported file extension ({}): {0}",
+        SUPPORTED_CONFIG_EXTS.join(", ")
     )]
     InvalidExtension(PathBuf),
     /// Failed to set one or more environment variable default.
@@ -59,11 +94,26 @@ pub enum Error {
     EnvVarDefaults(#[from] EnvVarDefaultsError),
 }
 
+/// Returns the line and column (both starting at 1) where a TOML parsing error occurred.
+fn toml_error_location(content: &str, error: &toml::de::Error) -> Option<(usize, usize)> {
+    let offset = error.span()?.start;
+    let before = content.get(..offset)?;
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |index| &before[index + 1..])
+        .chars()
+        .count()
+        + 1;
+    Some((line, column))
+}
+
 /// Intermediate data structure to build a [`Config`].
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, Parser)]
 #[clap(author, version, about, long_about = None, rename_all = "kebab")]
 #[serde(rename_all = "snake_case")]
 #[serde(deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Builder {
     #[clap(skip)]
     #[serde(rename = "envs")]
@@ -84,10 +134,98 @@ pub struct Builder {
     #[serde(skip)]
     #[clap(subcommand)]
     command: Option<Command>,
-    /// Force commands to run (skips consistency checks).
+    /// DANGEROUS: skip the consistency checks, possibly overwriting remote or local changes.
+    ///
+    /// Without this, `backup` and `restore` refuse to run when a check finds that they would
+    /// overwrite changes made on another system or not yet backed up on this one. Only use this
+    /// after inspecting those changes with `hoard diff` and deciding to discard them.
     #[serde(skip)]
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     force: bool,
+    /// Copy extended file attributes (e.g. xattrs) during backup and restore.
+    #[serde(skip)]
+    #[clap(long)]
+    copy_attributes: bool,
+    /// Remove directories left empty after deleting files during backup and restore.
+    #[serde(skip)]
+    #[clap(long)]
+    prune_empty_dirs: bool,
+    /// Only warn about outdated operation log formats in read-only commands like status and diff.
+    #[serde(default)]
+    #[clap(long)]
+    older_format_ok: bool,
+    /// Do not warn about files in the same pile whose paths only differ by case.
+    #[serde(default)]
+    #[clap(long)]
+    case_sensitive: bool,
+    /// Reuse checksums of files whose size and modification time have not changed since they
+    /// were last hashed.
+    #[serde(default)]
+    #[clap(long)]
+    checksum_cache: bool,
+    /// Hash every file in full, even if `checksum_cache` is enabled.
+    #[serde(skip)]
+    #[clap(long)]
+    paranoid: bool,
+    /// Fail if a file or directory cannot be read, instead of skipping it with a warning.
+    #[serde(skip)]
+    #[clap(long)]
+    strict: bool,
+    /// Do not lock the data directory while changing it, even though another hoard process may
+    /// be running.
+    #[serde(default)]
+    #[clap(long)]
+    no_lock: bool,
+    /// Maximum number of files to hash concurrently, and to write concurrently during restore.
+    #[serde(skip)]
+    #[clap(long, global = true)]
+    jobs: Option<NonZeroUsize>,
+    /// Warn when backing up any file larger than this many bytes.
+    #[serde(skip)]
+    #[clap(long, value_name = "BYTES")]
+    max_file_size_warn: Option<u64>,
+    /// Hash files in parallel once a hoard contains at least this many files.
+    #[serde(skip)]
+    #[clap(long, value_name = "FILES")]
+    hash_parallel_threshold: Option<usize>,
+    /// Treat remote changes as unexpected if they were logged more than this many seconds ahead
+    /// of the local clock.
+    #[serde(skip)]
+    #[clap(long, value_name = "SECONDS")]
+    max_clock_skew: Option<u64>,
+    /// Retry reading or writing a file this many times if it fails with an error that may be
+    /// temporary, like those seen on network filesystems.
+    #[serde(skip)]
+    #[clap(long, value_name = "N")]
+    retries: Option<u32>,
+    /// Wait this many milliseconds before the first retry. Each later retry waits twice as long.
+    #[serde(skip)]
+    #[clap(long, value_name = "MILLISECONDS")]
+    retry_delay: Option<u64>,
+    /// Refuse to back up or restore hoards with unexpected changes unless --force is given.
+    #[serde(skip)]
+    #[clap(long)]
+    safe: bool,
+    /// Do not restore the file permissions recorded during the last backup.
+    #[serde(skip)]
+    #[clap(long)]
+    no_permissions: bool,
+    /// Do not restore the file modification times recorded during the last backup.
+    #[serde(skip)]
+    #[clap(long)]
+    no_mtime: bool,
+    /// Do not run the pre and post hooks configured for hoards.
+    #[serde(skip)]
+    #[clap(long)]
+    no_hooks: bool,
+    /// Do not show a progress bar while backing up or restoring files, and only log errors.
+    #[serde(skip)]
+    #[clap(short, long)]
+    quiet: bool,
+    /// Log more details. Pass twice (`-vv`) to log everything.
+    #[serde(skip)]
+    #[clap(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
     #[clap(skip)]
     hoards: Option<BTreeMap<HoardName, Hoard>>,
     #[clap(skip)]
@@ -109,6 +247,15 @@ impl Builder {
         crate::dirs::config_dir().join(format!("{CONFIG_FILE_STEM}.{DEFAULT_CONFIG_EXT}"))
     }
 
+    /// Returns whether a configuration file with any supported extension exists in the default
+    /// location.
+    fn default_file_exists() -> bool {
+        let dir = crate::dirs::config_dir();
+        SUPPORTED_CONFIG_EXTS
+            .iter()
+            .any(|ext| dir.join(format!("{CONFIG_FILE_STEM}.{ext}")).is_file())
+    }
+
     /// Create a new `Builder`.
     ///
     /// If [`build`](Builder::build) is immediately called on this, the returned
@@ -126,11 +273,34 @@ impl Builder {
             environments: None,
             exclusivity: None,
             force: false,
+            copy_attributes: false,
+            prune_empty_dirs: false,
+            older_format_ok: false,
+            case_sensitive: false,
+            checksum_cache: false,
+            paranoid: false,
+            strict: false,
+            no_lock: false,
+            jobs: None,
+            max_file_size_warn: None,
+            hash_parallel_threshold: None,
+            max_clock_skew: None,
+            retries: None,
+            retry_delay: None,
+            safe: false,
+            no_permissions: false,
+            no_mtime: false,
+            no_hooks: false,
+            quiet: false,
+            verbose: 0,
             global_config: None,
         }
     }
 
-    /// Create a new [`Builder`] pre-populated with the contents of the given TOML file.
+    /// Create a new [`Builder`] pre-populated with the contents of the given configuration file.
+    ///
+    /// The format is determined by the file extension: `.toml`, `.yaml`/`.yml` (with the `yaml`
+    /// feature), or `.json` (with the `json` feature).
     ///
     /// # Errors
     ///
@@ -145,16 +315,25 @@ impl Builder {
         match path.extension().and_then(std::ffi::OsStr::to_str) {
             None => crate::create_log_error(Error::InvalidExtension(path.to_owned())),
             Some(ext) => match ext {
-                "toml" | "TOML" => toml::from_str(&s).map_err(crate::map_log_error_msg(
-                    &format!("failed to parse TOML from {}", path.display()),
-                    Error::DeserializeTOML,
-                )),
+                "toml" | "TOML" => toml::from_str(&s).map_err(crate::map_log_error(|error| {
+                    Error::DeserializeTOML {
+                        path: path.to_owned(),
+                        location: toml_error_location(&s, &error),
+                        error: Box::new(error),
+                    }
+                })),
+                #[cfg(feature = "yaml")]
                 "yaml" | "yml" | "YAML" | "YML" => {
                     serde_yaml::from_str(&s).map_err(crate::map_log_error_msg(
                         &format!("failed to parse YAML from {}", path.display()),
                         Error::DeserializeYAML,
                     ))
                 }
+                #[cfg(feature = "json")]
+                "json" | "JSON" => serde_json::from_str(&s).map_err(crate::map_log_error_msg(
+                    &format!("failed to parse JSON from {}", path.display()),
+                    Error::DeserializeJSON,
+                )),
                 _ => crate::create_log_error(Error::InvalidExtension(path.to_owned())),
             },
         }
@@ -162,7 +341,7 @@ impl Builder {
 
     /// Reads configuration from the default configuration file.
     ///
-    /// Prefers a TOML file, if found, falling back to YAML if present.
+    /// Prefers a TOML file, if found, falling back to YAML and then JSON if present.
     ///
     /// # Errors
     ///
@@ -175,11 +354,12 @@ impl Builder {
             let error = Error::ReadConfig(io::Error::new(
                 io::ErrorKind::NotFound,
                 format!(
-                    "could not find any of {name}.toml, {name}.yaml, or {name}.yml in {dir}",
-                    name = path
-                        .file_stem()
-                        .expect("default config should always have a file name")
-                        .to_string_lossy(),
+                    "could not find any of {names} in {dir}",
+                    names = SUPPORTED_CONFIG_EXTS
+                        .iter()
+                        .map(|ext| format!("{CONFIG_FILE_STEM}.{ext}"))
+                        .collect::<Vec<_>>()
+                        .join(", "),
                     dir = path
                         .parent()
                         .expect("default config should always have a parent")
@@ -223,12 +403,121 @@ impl Builder {
         .ok_or_else(error_closure)
     }
 
+    /// Merges every `*.toml` file in `dir` over this builder, in filename order.
+    ///
+    /// Hoards and environments defined in more than one place are replaced by the later
+    /// definition, logging a warning that names both sources. Exclusivity lists are combined, and
+    /// all other settings are [layered](Builder::layer). `source` is the file this builder was
+    /// read from, if any, and is only used in warnings.
+    ///
+    /// # Errors
+    ///
+    /// Any error from reading `dir` or from reading and parsing a fragment with
+    /// [`Builder::from_file`].
+    #[tracing::instrument(level = "debug", name = "config_builder_merge_fragments", skip(self))]
+    pub async fn merge_fragments(
+        mut self,
+        source: Option<&Path>,
+        dir: &Path,
+    ) -> Result<Self, Error> {
+        let mut fragments = Vec::new();
+        let mut entries = fs::read_dir(dir)
+            .await
+            .map_err(crate::map_log_error(Error::ReadConfig))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(crate::map_log_error(Error::ReadConfig))?
+        {
+            let path = entry.path();
+            if path.extension().and_then(std::ffi::OsStr::to_str) == Some(DEFAULT_CONFIG_EXT) {
+                fragments.push(path);
+            }
+        }
+        fragments.sort_unstable();
+
+        let base = source.map_or_else(
+            || String::from("the configuration file"),
+            |path| path.display().to_string(),
+        );
+        let mut origins: BTreeMap<String, String> = BTreeMap::new();
+        for name in self.hoards.iter().flat_map(BTreeMap::keys) {
+            origins.insert(format!("hoard \"{name}\""), base.clone());
+        }
+        for name in self.environments.iter().flat_map(BTreeMap::keys) {
+            origins.insert(format!("environment \"{name}\""), base.clone());
+        }
+
+        for path in fragments {
+            tracing::debug!("merging configuration fragment {}", path.display());
+            let mut fragment = Self::from_file(&path).await?;
+            let origin = path.display().to_string();
+            let mut record = |key: String| {
+                if let Some(previous) = origins.insert(key.clone(), origin.clone()) {
+                    tracing::warn!("{} in {} overrides the one in {}", key, origin, previous);
+                }
+            };
+
+            for (name, hoard) in fragment.hoards.take().into_iter().flatten() {
+                record(format!("hoard \"{name}\""));
+                self.hoards
+                    .get_or_insert_with(BTreeMap::new)
+                    .insert(name, hoard);
+            }
+            for (name, env) in fragment.environments.take().into_iter().flatten() {
+                record(format!("environment \"{name}\""));
+                self.environments
+                    .get_or_insert_with(BTreeMap::new)
+                    .insert(name, env);
+            }
+            if let Some(global_config) = fragment.global_config.take() {
+                record(String::from("global pile config"));
+                self.global_config = Some(global_config);
+            }
+            if let Some(exclusivity) = fragment.exclusivity.take() {
+                self.exclusivity
+                    .get_or_insert_with(Vec::new)
+                    .extend(exclusivity);
+            }
+
+            self = self.layer(fragment);
+        }
+
+        Ok(self)
+    }
+
+    /// Reads configuration from the default configuration file, then merges the fragments in the
+    /// `config.d` directory next to it over that, if the directory exists.
+    ///
+    /// If the directory exists, the default configuration file is optional.
+    ///
+    /// # Errors
+    ///
+    /// See [`Builder::from_default_file`] and [`Builder::merge_fragments`].
+    async fn from_default_location() -> Result<Self, Error> {
+        let fragments_dir = crate::dirs::config_dir().join(CONFIG_FRAGMENTS_DIR);
+        if !fragments_dir.is_dir() {
+            return Self::from_default_file().await;
+        }
+
+        let from_file = if Self::default_file_exists() {
+            Self::from_default_file().await?
+        } else {
+            Self::new()
+        };
+        from_file.merge_fragments(None, &fragments_dir).await
+    }
+
     /// Helper method to process command-line arguments and the config file specified on CLI
     /// (or the default).
     ///
+    /// If no configuration file is given on the command line, fragments in a `config.d`
+    /// directory next to the default configuration file are
+    /// [merged](Builder::merge_fragments) over it.
+    ///
     /// # Errors
     ///
-    /// See [`Builder::from_file`]
+    /// See [`Builder::from_file`] and [`Builder::merge_fragments`]
     #[tracing::instrument(level = "debug", name = "config_builder_from_args_then_file")]
     pub async fn from_args_then_file() -> Result<Self, Error> {
         tracing::debug!("loading configuration from cli arguments");
@@ -245,7 +534,7 @@ impl Builder {
 
                 Self::from_file(config_file).await?
             }
-            None => Self::from_default_file().await?,
+            None => Self::from_default_location().await?,
         };
 
         tracing::debug!("merging configuration file and cli arguments");
@@ -275,6 +564,28 @@ impl Builder {
         self.var_defaults.merge_with(other.var_defaults);
 
         self.force = self.force || other.force;
+        self.copy_attributes = self.copy_attributes || other.copy_attributes;
+        self.prune_empty_dirs = self.prune_empty_dirs || other.prune_empty_dirs;
+        self.older_format_ok = self.older_format_ok || other.older_format_ok;
+        self.case_sensitive = self.case_sensitive || other.case_sensitive;
+        self.checksum_cache = self.checksum_cache || other.checksum_cache;
+        self.paranoid = self.paranoid || other.paranoid;
+        self.strict = self.strict || other.strict;
+        self.no_lock = self.no_lock || other.no_lock;
+        self.jobs = other.jobs.or(self.jobs);
+        self.max_file_size_warn = other.max_file_size_warn.or(self.max_file_size_warn);
+        self.safe = self.safe || other.safe;
+        self.no_permissions = self.no_permissions || other.no_permissions;
+        self.no_mtime = self.no_mtime || other.no_mtime;
+        self.no_hooks = self.no_hooks || other.no_hooks;
+        self.quiet = self.quiet || other.quiet;
+        self.verbose = self.verbose.max(other.verbose);
+        self.hash_parallel_threshold = other
+            .hash_parallel_threshold
+            .or(self.hash_parallel_threshold);
+        self.max_clock_skew = other.max_clock_skew.or(self.max_clock_skew);
+        self.retries = other.retries.or(self.retries);
+        self.retry_delay = other.retry_delay.or(self.retry_delay);
 
         self
     }
@@ -350,13 +661,13 @@ impl Builder {
     }
 
     /// Evaluates the stored environment definitions and returns a mapping of
-    /// environment name to (boolean) whether that environment applies.
+    /// environment name to the result of each of its conditions.
     ///
     /// # Errors
     ///
     /// Any error that occurs while evaluating the environments.
     #[tracing::instrument(level = "trace", skip_all)]
-    fn evaluated_environments(&self) -> Result<BTreeMap<EnvironmentName, bool>, Error> {
+    fn evaluated_environments(&self) -> Result<BTreeMap<EnvironmentName, Vec<Condition>>, Error> {
         if let Some(envs) = &self.environments {
             for (key, env) in envs {
                 tracing::trace!(%key, %env);
@@ -369,7 +680,7 @@ impl Builder {
                 || Ok(BTreeMap::new()),
                 |map| {
                     map.iter()
-                        .map(|(key, env)| Ok((key.clone(), env.clone().try_into()?)))
+                        .map(|(key, env)| Ok((key.clone(), env.evaluate_conditions()?)))
                         .collect()
                 },
             )
@@ -385,16 +696,73 @@ impl Builder {
     pub fn build(mut self) -> Result<Config, Error> {
         tracing::debug!("building configuration from builder");
         tracing::trace!(builder=?self);
-        let environments = self.evaluated_environments()?;
+        let conditions = self.evaluated_environments()?;
+        tracing::debug!(?conditions);
+        let environments: BTreeMap<EnvironmentName, bool> = conditions
+            .iter()
+            .map(|(name, conds)| (name.clone(), conds.iter().all(|cond| cond.matched)))
+            .collect();
         tracing::debug!(?environments);
         let exclusivity = self.exclusivity.unwrap_or_default();
         tracing::debug!(?exclusivity);
+        envtrie::check_exclusivity(&exclusivity).map_err(Error::Exclusivity)?;
         let config_file = self.config_file.unwrap_or_else(Self::default_config_file);
         tracing::debug!(?config_file);
         let command = self.command.unwrap_or_default();
         tracing::debug!(?command);
         let force = self.force;
         tracing::debug!(?force);
+        let copy_attributes = self.copy_attributes;
+        tracing::debug!(?copy_attributes);
+        let prune_empty_dirs = self.prune_empty_dirs;
+        tracing::debug!(?prune_empty_dirs);
+        let older_format_ok = self.older_format_ok;
+        tracing::debug!(?older_format_ok);
+        let case_sensitive = self.case_sensitive;
+        tracing::debug!(?case_sensitive);
+        let checksum_cache = self.checksum_cache;
+        tracing::debug!(?checksum_cache);
+        let paranoid = self.paranoid;
+        tracing::debug!(?paranoid);
+        let strict = self.strict;
+        tracing::debug!(?strict);
+        let no_lock = self.no_lock;
+        tracing::debug!(?no_lock);
+        let jobs = self.jobs;
+        tracing::debug!(?jobs);
+        let max_file_size_warn = self.max_file_size_warn;
+        tracing::debug!(?max_file_size_warn);
+        let hash_parallel_threshold = self
+            .hash_parallel_threshold
+            .unwrap_or(crate::checksum::DEFAULT_PARALLEL_THRESHOLD);
+        tracing::debug!(?hash_parallel_threshold);
+        let max_clock_skew = self
+            .max_clock_skew
+            .unwrap_or(crate::checkers::history::operation::DEFAULT_MAX_CLOCK_SKEW);
+        tracing::debug!(?max_clock_skew);
+        let retries = self.retries.unwrap_or(crate::retry::DEFAULT_RETRIES);
+        tracing::debug!(?retries);
+        let retry_delay = self
+            .retry_delay
+            .unwrap_or(crate::retry::DEFAULT_RETRY_DELAY);
+        tracing::debug!(?retry_delay);
+        let safe = self.safe;
+        tracing::debug!(?safe);
+        let no_permissions = self.no_permissions;
+        tracing::debug!(?no_permissions);
+        let no_mtime = self.no_mtime;
+        tracing::debug!(?no_mtime);
+        let no_hooks = self.no_hooks;
+        tracing::debug!(?no_hooks);
+        let quiet = self.quiet;
+        tracing::debug!(?quiet);
+        let log_level = match (quiet, self.verbose) {
+            (true, _) => Some(Level::ERROR),
+            (false, 0) => None,
+            (false, 1) => Some(Level::DEBUG),
+            (false, _) => Some(Level::TRACE),
+        };
+        tracing::debug!(?log_level);
 
         self.var_defaults.apply()?;
 
@@ -413,6 +781,33 @@ impl Builder {
             }
         }
 
+        let unresolved = command::UnresolvedProblems {
+            pathless_hoards: self
+                .hoards
+                .iter()
+                .flatten()
+                .filter(|(_, hoard)| !hoard.has_any_path())
+                .map(|(name, _)| name.clone())
+                .collect(),
+            unknown_exclusive_envs: exclusivity
+                .iter()
+                .flatten()
+                .filter(|env| !environments.contains_key(*env))
+                .cloned()
+                .collect::<std::collections::BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        };
+        tracing::debug!(?unresolved);
+
+        let hooks: BTreeMap<HoardName, Hooks> = self
+            .hoards
+            .iter()
+            .flatten()
+            .filter_map(|(name, hoard)| hoard.hooks().map(|hooks| (name.clone(), hooks.clone())))
+            .collect();
+        tracing::debug!(?hooks);
+
         tracing::debug!("processing hoards...");
         let hoards = self
             .hoards
@@ -432,6 +827,34 @@ impl Builder {
             config_file,
             hoards,
             force,
+            copy_attributes,
+            prune_empty_dirs,
+            older_format_ok,
+            case_sensitive,
+            checksum_cache,
+            paranoid,
+            strict,
+            no_lock,
+            jobs,
+            move_files: false,
+            max_file_size_warn,
+            missing_only: false,
+            if_changed: false,
+            hash_parallel_threshold,
+            max_clock_skew,
+            retries,
+            retry_delay,
+            selection: crate::filters::Selection::default(),
+            exclude_hoards: Vec::new(),
+            safe,
+            no_permissions,
+            no_mtime,
+            no_hooks,
+            quiet,
+            log_level,
+            hooks,
+            unresolved,
+            environments: conditions,
         })
     }
 }
@@ -456,6 +879,26 @@ mod tests {
                 exclusivity: None,
                 hoards: None,
                 force: false,
+                copy_attributes: false,
+                prune_empty_dirs: false,
+                older_format_ok: false,
+                case_sensitive: false,
+                checksum_cache: false,
+                paranoid: false,
+                strict: false,
+                no_lock: false,
+                jobs: None,
+                max_file_size_warn: None,
+                hash_parallel_threshold: None,
+                max_clock_skew: None,
+                retries: None,
+                retry_delay: None,
+                safe: false,
+                no_permissions: false,
+                no_mtime: false,
+                no_hooks: false,
+                quiet: false,
+                verbose: 0,
                 global_config: None,
                 var_defaults: EnvVarDefaults::default(),
             }
@@ -468,11 +911,39 @@ mod tests {
                 config_file: Some(PathBuf::from("/testing/config.toml")),
                 command: Some(Command::Restore {
                     hoards: vec!["test".parse().unwrap()],
+                    pick: false,
+                    pick_file: Vec::new(),
+                    version: None,
+                    dry_run: false,
+                    at: None,
+                    json: false,
+                    into: None,
+                    args: command::RestoreArgs::default(),
                 }),
                 environments: None,
                 exclusivity: None,
                 hoards: None,
                 force: false,
+                copy_attributes: false,
+                prune_empty_dirs: false,
+                older_format_ok: false,
+                case_sensitive: false,
+                checksum_cache: false,
+                paranoid: false,
+                strict: false,
+                no_lock: false,
+                jobs: None,
+                max_file_size_warn: None,
+                hash_parallel_threshold: None,
+                max_clock_skew: None,
+                retries: None,
+                retry_delay: None,
+                safe: false,
+                no_permissions: false,
+                no_mtime: false,
+                no_hooks: false,
+                quiet: false,
+                verbose: 0,
                 global_config: None,
                 var_defaults: {
                     let mut defaults = EnvVarDefaults::default();
@@ -498,6 +969,26 @@ mod tests {
                 hoards: None,
                 exclusivity: None,
                 force: false,
+                copy_attributes: false,
+                prune_empty_dirs: false,
+                older_format_ok: false,
+                case_sensitive: false,
+                checksum_cache: false,
+                paranoid: false,
+                strict: false,
+                no_lock: false,
+                jobs: None,
+                max_file_size_warn: None,
+                hash_parallel_threshold: None,
+                max_clock_skew: None,
+                retries: None,
+                retry_delay: None,
+                safe: false,
+                no_permissions: false,
+                no_mtime: false,
+                no_hooks: false,
+                quiet: false,
+                verbose: 0,
                 global_config: None,
                 var_defaults: EnvVarDefaults::default(),
             };
@@ -611,5 +1102,254 @@ mod tests {
             builder.build().unwrap();
             assert_eq!(std::env::var(DEFAULT_VAR).unwrap(), DEFAULT_VAR_VALUE);
         }
+
+        #[test]
+        fn verbosity_flags_set_log_level() {
+            let log_level = |args: &[&str]| {
+                Builder::try_parse_from(args)
+                    .expect("failed to parse arguments")
+                    .build()
+                    .expect("failed to build config")
+                    .log_level
+            };
+
+            assert_eq!(None, log_level(&["hoard", "validate"]));
+            assert_eq!(Some(Level::ERROR), log_level(&["hoard", "-q", "validate"]));
+            assert_eq!(Some(Level::DEBUG), log_level(&["hoard", "-v", "validate"]));
+            assert_eq!(Some(Level::TRACE), log_level(&["hoard", "-vv", "validate"]));
+            assert_eq!(
+                Some(Level::TRACE),
+                log_level(&["hoard", "-vvv", "validate"])
+            );
+            assert!(Builder::try_parse_from(["hoard", "-q", "-v", "validate"]).is_err());
+        }
+
+        #[test]
+        fn force_flag_is_accepted_before_and_after_subcommand() {
+            let force = |args: &[&str]| {
+                Builder::try_parse_from(args)
+                    .expect("failed to parse arguments")
+                    .build()
+                    .expect("failed to build config")
+                    .force
+            };
+
+            assert!(!force(&["hoard", "backup"]));
+            assert!(force(&["hoard", "--force", "backup"]));
+            assert!(force(&["hoard", "backup", "--force"]));
+            assert!(force(&["hoard", "restore", "-f", "some_hoard"]));
+        }
+
+        #[test]
+        fn backup_flags_are_only_accepted_by_backup() {
+            let builder = Builder::try_parse_from([
+                "hoard",
+                "backup",
+                "--move",
+                "--if-changed",
+                "some_hoard",
+            ])
+            .expect("failed to parse arguments");
+            assert!(matches!(
+                builder.command,
+                Some(Command::Backup {
+                    args: command::BackupArgs {
+                        move_files: true,
+                        if_changed: true,
+                        ..
+                    },
+                    ..
+                })
+            ));
+
+            for args in [
+                ["hoard", "restore", "--move"],
+                ["hoard", "list", "--move"],
+                ["hoard", "restore", "--if-changed"],
+                ["hoard", "status", "--if-changed"],
+            ] {
+                assert!(
+                    Builder::try_parse_from(args).is_err(),
+                    "{args:?} should be rejected"
+                );
+            }
+        }
+
+        #[test]
+        fn restore_flags_are_only_accepted_by_restore() {
+            let builder = Builder::try_parse_from(["hoard", "restore", "--missing-only"])
+                .expect("failed to parse arguments");
+            assert!(matches!(
+                builder.command,
+                Some(Command::Restore {
+                    args: command::RestoreArgs {
+                        missing_only: true,
+                        ..
+                    },
+                    ..
+                })
+            ));
+
+            for args in [
+                ["hoard", "backup", "--missing-only"],
+                ["hoard", "list", "--missing-only"],
+                ["hoard", "status", "--missing-only"],
+                ["hoard", "diff", "--missing-only"],
+            ] {
+                assert!(
+                    Builder::try_parse_from(args).is_err(),
+                    "{args:?} should be rejected"
+                );
+            }
+        }
+
+        #[test]
+        fn selection_flags_are_only_accepted_by_backup_and_restore() {
+            let selection = command::SelectionArgs {
+                only: vec![glob::Pattern::new("saves/*.sav").unwrap()],
+                ignore: vec![glob::Pattern::new("*.bak").unwrap()],
+                exclude_hoards: vec!["other_hoard".parse::<HoardName>().unwrap()],
+            };
+            for subcommand in ["backup", "restore"] {
+                let builder = Builder::try_parse_from([
+                    "hoard",
+                    subcommand,
+                    "some_hoard",
+                    "--only",
+                    "saves/*.sav",
+                    "--ignore",
+                    "*.bak",
+                    "--jobs",
+                    "2",
+                    "--exclude-hoard",
+                    "other_hoard",
+                ])
+                .expect("failed to parse arguments");
+                assert_eq!(builder.jobs, NonZeroUsize::new(2));
+                match builder.command {
+                    Some(Command::Backup { args, .. }) => assert_eq!(args.selection, selection),
+                    Some(Command::Restore { args, .. }) => assert_eq!(args.selection, selection),
+                    command => panic!("unexpected command {command:?}"),
+                }
+            }
+
+            for args in [
+                ["hoard", "list", "--only", "*.x"],
+                ["hoard", "status", "--ignore", "*.x"],
+                ["hoard", "diff", "--exclude-hoard", "some_hoard"],
+            ] {
+                assert!(
+                    Builder::try_parse_from(args).is_err(),
+                    "{args:?} should be rejected"
+                );
+            }
+        }
+
+        #[test]
+        fn contradicting_exclusivity_fails_to_build() {
+            let unix: EnvironmentName = "unix".parse().unwrap();
+            let windows: EnvironmentName = "windows".parse().unwrap();
+            let mut builder = Builder::new();
+            builder.exclusivity = Some(vec![
+                vec![unix.clone(), windows.clone()],
+                vec![windows, unix],
+            ]);
+            let error = builder
+                .build()
+                .expect_err("contradicting lists should fail");
+            assert!(
+                matches!(error, Error::Exclusivity(envtrie::Error::WeightCycle(_))),
+                "unexpected error: {error:?}"
+            );
+        }
+    }
+
+    mod fragments {
+        use super::*;
+
+        const BASE: &str = r#"
+            [hoards]
+            [hoards.base]
+                "first" = "/base/file"
+        "#;
+
+        const FIRST: &str = r#"
+            exclusivity = [["first", "second"]]
+
+            [envs]
+            [envs.first]
+                os = ["linux"]
+
+            [hoards]
+            [hoards.game]
+                "first" = "/first/game"
+            [hoards.other]
+                "first" = "/first/other"
+        "#;
+
+        const SECOND: &str = r#"
+            exclusivity = [["third", "fourth"]]
+
+            [envs]
+            [envs.first]
+                os = ["windows"]
+
+            [hoards]
+            [hoards.game]
+                "first" = "/second/game"
+        "#;
+
+        async fn merged() -> Builder {
+            let dir = tempfile::tempdir().expect("failed to create temporary directory");
+            // Written out of order to check that fragments are sorted by file name.
+            fs::write(dir.path().join("20-second.toml"), SECOND)
+                .await
+                .unwrap();
+            fs::write(dir.path().join("10-first.toml"), FIRST)
+                .await
+                .unwrap();
+            fs::write(dir.path().join("README.md"), "not a fragment")
+                .await
+                .unwrap();
+
+            let base: Builder = toml::from_str(BASE).unwrap();
+            base.merge_fragments(Some(Path::new("config.toml")), dir.path())
+                .await
+                .expect("fragments should merge")
+        }
+
+        #[tokio::test]
+        async fn later_fragments_override_earlier() {
+            let builder = merged().await;
+            let second: Builder = toml::from_str(SECOND).unwrap();
+            let hoards = builder.hoards.expect("hoards should be merged");
+
+            assert_eq!(
+                hoards.keys().map(AsRef::as_ref).collect::<Vec<&str>>(),
+                ["base", "game", "other"]
+            );
+            let game: HoardName = "game".parse().unwrap();
+            assert_eq!(hoards.get(&game), second.hoards.unwrap().get(&game));
+            assert_eq!(
+                builder
+                    .exclusivity
+                    .expect("exclusivity should be merged")
+                    .len(),
+                2,
+                "exclusivity lists should be combined"
+            );
+        }
+
+        #[tokio::test]
+        async fn later_environments_override_earlier() {
+            let builder = merged().await;
+            let second: Builder = toml::from_str(SECOND).unwrap();
+            let first_env: EnvironmentName = "first".parse().unwrap();
+
+            assert_eq!(
+                builder.environments.unwrap().get(&first_env),
+                second.environments.unwrap().get(&first_env)
+            );
+        }
     }
 }
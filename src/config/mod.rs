@@ -1,18 +1,38 @@
 //! See [`Config`].
 
-use std::collections::HashMap;
-use std::path::PathBuf;
+use itertools::Itertools;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tap::TapFallible;
 
 use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::Level;
 
-use crate::command::{self, Command};
+use crate::checksum::HashOptions;
+use crate::command::{
+    self, BackupRestoreReport, ColorChoice, Command, DiffFormat, DiffGrouping, HoardStatus,
+};
+use crate::filters::Selection;
+use crate::hoard::hooks::Hooks;
+use crate::hoard::iter::{HoardFileDiff, ScanOptions};
+use crate::hoard::skipped::SkippedPaths;
 use crate::hoard::{self, Hoard};
-use crate::newtypes::HoardName;
+use crate::newtypes::{EnvironmentName, HoardName, HoardSelector, NonEmptyPileName};
+use crate::paths::RelativePath;
+use crate::retry::RetryPolicy;
+use crate::ExitCode;
+
+use self::builder::environment::Condition;
 
 pub use self::builder::Builder;
+pub use self::resolved::{ResolvedConfig, ResolvedHoard, ResolvedPile};
 
 pub mod builder;
+pub mod resolved;
 
 /// Errors that can occur while working with a [`Config`].
 #[derive(Debug, Error)]
@@ -26,12 +46,54 @@ pub enum Error {
     /// The requested hoard does not exist.
     #[error("no such hoard is configured: {0}")]
     NoSuchHoard(HoardName),
+    /// The requested pile does not exist in the selected hoard.
+    #[error("hoard {hoard} has no pile named {pile} (available piles: {})", format_piles(.piles))]
+    NoSuchPile {
+        /// The selected hoard.
+        hoard: HoardName,
+        /// The requested pile.
+        pile: NonEmptyPileName,
+        /// The piles that the hoard does have.
+        piles: Vec<NonEmptyPileName>,
+    },
+    /// A single pile was selected together with an option that only works on whole hoards.
+    #[error("cannot select pile {selector} together with {option}")]
+    PileNotSupported {
+        /// The `hoard:pile` selector.
+        selector: HoardSelector,
+        /// The command line option that was given with it.
+        option: &'static str,
+    },
+    /// Another process holds the data directory lock, or it could not be taken.
+    #[error(transparent)]
+    Lock(#[from] crate::lock::Error),
+}
+
+impl Error {
+    /// Returns the [`ExitCode`] that `hoard` exits with because of this error.
+    #[must_use]
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Self::Builder(_) | Self::Command(command::Error::Validate(_)) => ExitCode::ConfigError,
+            _ => ExitCode::Error,
+        }
+    }
+}
+
+/// Formats the pile names for [`Error::NoSuchPile`].
+fn format_piles(piles: &[NonEmptyPileName]) -> String {
+    if piles.is_empty() {
+        String::from("none")
+    } else {
+        piles.iter().join(", ")
+    }
 }
 
 /// A (processed) configuration.
 ///
 /// To create a configuration, use [`Builder`] instead.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Config {
     /// The command to run.
     pub command: Command,
@@ -41,6 +103,65 @@ pub struct Config {
     pub hoards: HashMap<HoardName, Hoard>,
     /// Whether to force the operation to continue despite possible inconsistencies.
     pub force: bool,
+    /// Whether to copy extended file attributes during backup and restore.
+    pub copy_attributes: bool,
+    /// Whether to remove directories left empty after deleting files during backup and restore.
+    pub prune_empty_dirs: bool,
+    /// Whether read-only commands should only warn about operation logs in an older format.
+    pub older_format_ok: bool,
+    /// Whether to skip warning about files in the same pile whose paths only differ by case.
+    pub case_sensitive: bool,
+    /// Whether to reuse the checksums of files whose size and modification time are unchanged.
+    pub checksum_cache: bool,
+    /// Whether to hash every file in full, even if `checksum_cache` is enabled.
+    pub paranoid: bool,
+    /// Whether to fail if a file or directory cannot be read, instead of skipping it.
+    pub strict: bool,
+    /// Whether to skip locking the data directory while running commands that change it.
+    pub no_lock: bool,
+    /// The maximum number of files to hash or restore concurrently.
+    ///
+    /// If `None`, files are hashed using all available CPUs and restored one at a time.
+    pub jobs: Option<NonZeroUsize>,
+    /// Whether to delete system files after they are successfully backed up.
+    pub move_files: bool,
+    /// Warn about files larger than this many bytes during backup.
+    pub max_file_size_warn: Option<u64>,
+    /// Whether restores should only create files that are missing from the system.
+    pub missing_only: bool,
+    /// Whether backups should skip hoards that have no changes.
+    pub if_changed: bool,
+    /// The number of files a hoard must contain before its files are hashed in parallel.
+    pub hash_parallel_threshold: usize,
+    /// How many seconds a remote operation log may be ahead of the local clock before its
+    /// changes are treated as unexpected.
+    pub max_clock_skew: u64,
+    /// How many times to retry reading or writing a file that failed with a transient error.
+    pub retries: u32,
+    /// How many milliseconds to wait before the first retry of a file operation.
+    pub retry_delay: u64,
+    /// The files given with `--only` and `--ignore` to back up or restore.
+    pub selection: Selection,
+    /// The hoards given with `--exclude-hoard` to skip when acting on all hoards.
+    pub exclude_hoards: Vec<HoardName>,
+    /// Whether backup and restore should refuse to run on hoards with unexpected changes.
+    pub safe: bool,
+    /// Whether restore should skip reapplying the file permissions recorded during backup.
+    pub no_permissions: bool,
+    /// Whether restore should skip reapplying the file modification times recorded during backup.
+    pub no_mtime: bool,
+    /// Whether backup and restore should skip the hooks configured for each hoard.
+    pub no_hooks: bool,
+    /// Whether backup and restore should not show a progress bar.
+    pub quiet: bool,
+    /// The logging level chosen with `--quiet` or `--verbose`, if any.
+    pub log_level: Option<Level>,
+    /// The hooks configured for each hoard, if any.
+    pub hooks: BTreeMap<HoardName, Hooks>,
+    /// Problems found while building, reported by [`Command::Validate`].
+    pub unresolved: command::UnresolvedProblems,
+    /// Each configured environment and the result of each of its conditions on this machine.
+    pub environments: BTreeMap<EnvironmentName, Vec<Condition>>,
 }
 
 impl Default for Config {
@@ -82,30 +203,185 @@ impl Config {
         Ok(config)
     }
 
+    /// Load the configuration file at `path` and resolve it for the current environment.
+    ///
+    /// Unlike [`Config::load`], this does not read any CLI arguments. The returned
+    /// [`ResolvedConfig`] exposes each hoard's piles with their system and hoard paths.
+    ///
+    /// # Errors
+    ///
+    /// Any errors from reading, parsing, or building the configuration, wrapped in
+    /// [`Error::Builder`].
+    #[tracing::instrument(level = "debug", name = "load_resolved_config")]
+    pub async fn load_resolved(path: &Path) -> Result<ResolvedConfig, Error> {
+        let config = Builder::from_file(path)
+            .await?
+            .set_config_file(path.to_path_buf())
+            .build()?;
+        Ok(ResolvedConfig::new(&config, &crate::paths::hoards_dir()))
+    }
+
     /// The path to the configured configuration file.
     #[must_use]
     pub fn get_config_file_path(&self) -> PathBuf {
         self.config_file.clone()
     }
 
+    fn backup_restore_options(&self) -> command::BackupRestoreOptions {
+        command::BackupRestoreOptions {
+            force: self.force,
+            copy_attributes: self.copy_attributes,
+            prune_empty_dirs: self.prune_empty_dirs,
+            jobs: self.jobs.unwrap_or(NonZeroUsize::MIN),
+            move_files: self.move_files,
+            max_file_size_warn: self.max_file_size_warn,
+            missing_only: self.missing_only,
+            if_changed: self.if_changed,
+            safe: self.safe,
+            no_permissions: self.no_permissions,
+            no_mtime: self.no_mtime,
+            case_sensitive: self.case_sensitive,
+            no_hooks: self.no_hooks,
+            quiet: self.quiet,
+            dry_run: false,
+            require_selection: self.selection.has_only(),
+            strict: self.strict,
+            hashing: self.hash_options(),
+            retry: self.retry_policy(),
+            max_clock_skew: self.max_clock_skew,
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            retries: self.retries,
+            delay: Duration::from_millis(self.retry_delay),
+        }
+    }
+
+    fn hash_options(&self) -> HashOptions {
+        HashOptions {
+            parallel_threshold: self.hash_parallel_threshold,
+            max_jobs: self.jobs,
+            use_cache: self.checksum_cache && !self.paranoid,
+        }
+    }
+
+    fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            skipped: SkippedPaths::new(self.strict),
+            hashing: self.hash_options(),
+            retry: self.retry_policy(),
+            max_clock_skew: self.max_clock_skew,
+        }
+    }
+
+    /// Returns a copy of this [`Config`] with the options given to `hoard backup` applied.
+    fn with_backup_args(&self, args: &command::BackupArgs) -> Self {
+        Self {
+            move_files: self.move_files || args.move_files,
+            if_changed: self.if_changed || args.if_changed,
+            ..self.with_selection_args(&args.selection)
+        }
+    }
+
+    /// Returns a copy of this [`Config`] with the options given to `hoard restore` applied.
+    fn with_restore_args(&self, args: &command::RestoreArgs) -> Self {
+        Self {
+            missing_only: self.missing_only || args.missing_only,
+            ..self.with_selection_args(&args.selection)
+        }
+    }
+
+    /// Returns a copy of this [`Config`] with the hoards and files chosen by `args` selected.
+    fn with_selection_args(&self, args: &command::SelectionArgs) -> Self {
+        let selection = if args.only.is_empty() && args.ignore.is_empty() {
+            self.selection.clone()
+        } else {
+            Selection::new(&args.only, &args.ignore)
+        };
+        let mut exclude_hoards = self.exclude_hoards.clone();
+        exclude_hoards.extend(args.exclude_hoards.iter().cloned());
+        Self {
+            selection,
+            exclude_hoards,
+            ..self.clone()
+        }
+    }
+
+    /// Returns copies of `hoards` that only include the files selected with `--only` and
+    /// `--ignore`.
+    fn select_files<'a>(
+        &self,
+        hoards: HashMap<&'a HoardName, &'a Hoard>,
+    ) -> HashMap<&'a HoardName, Hoard> {
+        hoards
+            .into_iter()
+            .map(|(name, hoard)| (name, hoard.with_selection(&self.selection)))
+            .collect()
+    }
+
     #[tracing::instrument(level = "debug", name = "config_get_hoard", skip(self))]
     fn get_hoards<'a>(
         &'a self,
-        hoards: &'a [HoardName],
+        hoards: &[HoardName],
     ) -> Result<HashMap<&'a HoardName, &'a Hoard>, Error> {
         if hoards.is_empty() {
             tracing::debug!("no hoard names provided, acting on all of them.");
-            Ok(self.hoards.iter().collect())
+            for name in &self.exclude_hoards {
+                if !self.hoards.contains_key(name) {
+                    tracing::warn!("cannot exclude hoard {}: no such hoard is configured", name);
+                }
+            }
+            Ok(self
+                .hoards
+                .iter()
+                .filter(|(name, _)| !self.exclude_hoards.contains(name))
+                .collect())
         } else {
+            if !self.exclude_hoards.is_empty() {
+                tracing::warn!("ignoring --exclude-hoard because hoard names were given");
+            }
             tracing::debug!("using hoard names provided on cli");
             tracing::debug!(?hoards);
             hoards
                 .iter()
-                .map(|key| self.get_hoard(key).map(|hoard| (key, hoard)))
+                .map(|key| {
+                    self.hoards
+                        .get_key_value(key)
+                        .ok_or_else(|| Error::NoSuchHoard(key.clone()))
+                        .tap_err(crate::tap_log_error)
+                })
                 .collect()
         }
     }
 
+    /// Returns copies of the hoards named by `selectors` that only include the files selected with
+    /// `--only` and `--ignore` and, for `hoard:pile` selectors, the selected piles.
+    fn get_selected_hoards<'a>(
+        &'a self,
+        selectors: &[HoardSelector],
+    ) -> Result<HashMap<&'a HoardName, Hoard>, Error> {
+        let names: Vec<HoardName> = selectors
+            .iter()
+            .map(|selector| selector.hoard().clone())
+            .unique()
+            .collect();
+        let mut hoards = self.select_files(self.get_hoards(&names)?);
+        select_piles(&mut hoards, selectors)?;
+        Ok(hoards)
+    }
+
+    /// Returns a copy of the hoard named by `selector`, only including the selected pile, if any.
+    fn get_selected_hoard(&self, selector: &HoardSelector) -> Result<Hoard, Error> {
+        let hoard = self.get_hoard(selector.hoard())?.clone();
+        let mut hoards = HashMap::from([(selector.hoard(), hoard)]);
+        select_piles(&mut hoards, std::slice::from_ref(selector))?;
+        Ok(hoards
+            .remove(selector.hoard())
+            .expect("the selected hoard should still be present"))
+    }
+
     #[tracing::instrument(name = "config_get_hoard", skip(self))]
     fn get_hoard<'a>(&'a self, name: &'_ HoardName) -> Result<&'a Hoard, Error> {
         self.hoards
@@ -114,25 +390,299 @@ impl Config {
             .tap_err(crate::tap_log_error)
     }
 
+    /// Run the status command, writing its output to `out` instead of logging it.
+    ///
+    /// If `hoard` is given, only the status of that hoard is written. If `json` is true, the
+    /// output is a JSON array with one object per hoard. Returns whether any hoard is not up to
+    /// date.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::NoSuchHoard`] if `hoard` is not configured, or any other [`enum@Error`] that
+    /// might happen while determining the status of each hoard, or while writing to `out`.
+    pub async fn write_status(
+        &self,
+        hoard: Option<&HoardName>,
+        json: bool,
+        out: &mut impl Write,
+    ) -> Result<bool, Error> {
+        let hoards = match hoard {
+            Some(name) => vec![(name, self.get_hoard(name)?)],
+            None => self.hoards.iter().collect(),
+        };
+        // Boxed to keep the futures of callers shallow enough for the compiler to compute
+        // their layouts.
+        Box::pin(command::run_status(
+            &crate::paths::hoards_dir(),
+            hoards,
+            self.older_format_ok,
+            &self.scan_options(),
+            json,
+            out,
+        ))
+        .await
+        .map_err(Error::from)
+    }
+
+    /// Run the diff command for `hoard`, writing its output to `out` instead of logging it.
+    ///
+    /// If `hoard` selects a single pile, only that pile is diffed. If `path` is given, only that
+    /// file or directory of the hoard is diffed. If `follow_renames` is true, a deleted file and a
+    /// created file with the same content are shown as a single rename. Returns whether any
+    /// changed files were found.
+    ///
+    /// # Errors
+    ///
+    /// Any [`enum@Error`] that might happen while diffing the hoard, or while writing to `out`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write_diff(
+        &self,
+        hoard: &HoardSelector,
+        path: Option<&RelativePath>,
+        verbose: bool,
+        reverse: bool,
+        group_by: Option<DiffGrouping>,
+        format: DiffFormat,
+        color: ColorChoice,
+        follow_renames: bool,
+        out: &mut impl Write,
+    ) -> Result<bool, Error> {
+        let options = command::DiffOptions {
+            verbose,
+            reverse,
+            group_by,
+            format,
+            color: color.should_color(),
+            follow_renames,
+        };
+        command::run_diff(
+            &self.get_selected_hoard(hoard)?,
+            hoard.hoard(),
+            &crate::paths::hoards_dir(),
+            path,
+            options,
+            self.older_format_ok,
+            &self.scan_options(),
+            out,
+        )
+        .await
+        .map_err(Error::from)
+    }
+
+    /// Compare the system files of `hoard` against the checksums recorded by the most recent
+    /// operation at or before `against`, writing the result to `out`. Returns whether any file
+    /// changed.
+    ///
+    /// # Errors
+    ///
+    /// Any [`enum@Error`] that might happen while reading the operation logs or diffing the
+    /// hoard, or while writing to `out`.
+    pub async fn write_diff_against(
+        &self,
+        hoard: &HoardName,
+        against: OffsetDateTime,
+        out: &mut impl Write,
+    ) -> Result<bool, Error> {
+        command::run_diff_against(
+            self.get_hoard(hoard)?,
+            hoard,
+            &crate::paths::hoards_dir(),
+            against,
+            self.older_format_ok,
+            &self.scan_options(),
+            out,
+        )
+        .await
+        .map_err(Error::from)
+    }
+
+    /// Back up `hoards`, returning the operation performed on each file.
+    ///
+    /// If `hoards` is empty, all hoards are backed up except those in
+    /// [`exclude_hoards`](Self::exclude_hoards). Selectors of the form `hoard:pile` only act on
+    /// that pile of the hoard. This behaves like `hoard backup`, using the options stored in this
+    /// [`Config`], including holding the [data directory lock](crate::lock) while it runs.
+    ///
+    /// # Errors
+    ///
+    /// Any [`enum@Error`] that might happen while locking the data directory or backing up the
+    /// hoards.
+    pub async fn backup(&self, hoards: &[HoardSelector]) -> Result<BackupRestoreReport, Error> {
+        let _lock = self.lock_data_dir()?;
+        self.backup_files(hoards).await
+    }
+
+    async fn backup_files(&self, hoards: &[HoardSelector]) -> Result<BackupRestoreReport, Error> {
+        let hoards = self.get_selected_hoards(hoards)?;
+        // Boxed for the same reason as in `write_status`.
+        Box::pin(command::run_backup(
+            &crate::paths::hoards_dir(),
+            hoards.iter().map(|(name, hoard)| (*name, hoard)),
+            &self.hooks,
+            self.backup_restore_options(),
+        ))
+        .await
+        .map_err(Error::from)
+    }
+
+    /// Restore `hoards`, returning the operation performed on each file.
+    ///
+    /// If `hoards` is empty, all hoards are restored except those in
+    /// [`exclude_hoards`](Self::exclude_hoards). Selectors of the form `hoard:pile` only act on
+    /// that pile of the hoard. This behaves like `hoard restore`, using the options stored in this
+    /// [`Config`], including holding the [data directory lock](crate::lock) while it runs.
+    ///
+    /// # Errors
+    ///
+    /// Any [`enum@Error`] that might happen while locking the data directory or restoring the
+    /// hoards.
+    pub async fn restore(&self, hoards: &[HoardSelector]) -> Result<BackupRestoreReport, Error> {
+        let _lock = self.lock_data_dir()?;
+        self.restore_files(hoards, false).await
+    }
+
+    /// Locks the data directory unless locking was disabled with `--no-lock`.
+    fn lock_data_dir(&self) -> Result<Option<crate::lock::DataDirLock>, Error> {
+        if self.no_lock {
+            return Ok(None);
+        }
+        crate::lock::DataDirLock::acquire(&crate::dirs::data_dir())
+            .map(Some)
+            .map_err(Error::from)
+    }
+
+    async fn restore_files(
+        &self,
+        hoards: &[HoardSelector],
+        dry_run: bool,
+    ) -> Result<BackupRestoreReport, Error> {
+        let options = command::BackupRestoreOptions {
+            dry_run,
+            ..self.backup_restore_options()
+        };
+        let hoards = self.get_selected_hoards(hoards)?;
+        Box::pin(command::run_restore(
+            &crate::paths::hoards_dir(),
+            hoards.iter().map(|(name, hoard)| (*name, hoard)),
+            &self.hooks,
+            options,
+        ))
+        .await
+        .map_err(Error::from)
+    }
+
+    /// Returns the [`HoardStatus`] of every configured hoard, like `hoard status`.
+    ///
+    /// # Errors
+    ///
+    /// Any [`enum@Error`] that might happen while determining the status of each hoard.
+    pub async fn status(&self) -> Result<BTreeMap<HoardName, HoardStatus>, Error> {
+        let hoards_root = crate::paths::hoards_dir();
+        let scan = self.scan_options();
+        let mut statuses = BTreeMap::new();
+        for (name, hoard) in &self.hoards {
+            let status =
+                command::hoard_status(&hoards_root, name, hoard, self.older_format_ok, &scan)
+                    .await?;
+            statuses.insert(name.clone(), status);
+        }
+        Ok(statuses)
+    }
+
+    /// Returns the files of `hoard` that differ between the system and the hoard, like
+    /// `hoard diff`.
+    ///
+    /// If `hoard` selects a single pile, only that pile is diffed. If `path` is given, only that
+    /// file or directory of the hoard is diffed.
+    ///
+    /// # Errors
+    ///
+    /// Any [`enum@Error`] that might happen while diffing the hoard.
+    pub async fn diff(
+        &self,
+        hoard: &HoardSelector,
+        path: Option<&RelativePath>,
+    ) -> Result<Vec<HoardFileDiff>, Error> {
+        command::collect_diffs(
+            &self.get_selected_hoard(hoard)?,
+            hoard.hoard(),
+            &crate::paths::hoards_dir(),
+            path,
+            self.older_format_ok,
+            &self.scan_options(),
+        )
+        .await
+        .map(|diffs| diffs.into_iter().collect())
+        .map_err(Error::from)
+    }
+
     /// Run the stored [`Command`] using this [`Config`].
     ///
+    /// Returns [`ExitCode::ChangesDetected`] if `hoard status` or `hoard diff` found changes,
+    /// and [`ExitCode::Success`] otherwise.
+    ///
     /// # Errors
     ///
     /// Any [`enum@Error`] that might happen while running the command.
     #[tracing::instrument(name = "run_command", skip(self))]
-    pub async fn run(&self) -> Result<(), Error> {
+    pub async fn run(&self) -> Result<ExitCode, Error> {
         tracing::trace!(command = ?self.command, "running command");
+        // Held until the command finishes, so that no other hoard process changes the same files.
+        let _lock = if self.command.locks_data_dir() {
+            self.lock_data_dir()?
+        } else {
+            None
+        };
+        let mut has_changes = false;
         match &self.command {
-            Command::Status => {
-                let iter = self.hoards.iter();
-                command::run_status(&crate::paths::hoards_dir(), iter).await?;
+            Command::Status { hoard, json } => {
+                let mut output = Vec::new();
+                has_changes = self
+                    .write_status(hoard.as_ref(), *json, &mut output)
+                    .await?;
+                log_output(&output);
             }
-            Command::Diff { hoard, verbose } => {
-                command::run_diff(
-                    self.get_hoard(hoard)?,
-                    hoard,
+            Command::Diff {
+                hoard,
+                path,
+                verbose,
+                reverse,
+                group_by,
+                format,
+                color,
+                follow_renames,
+                against,
+            } => {
+                let mut output = Vec::new();
+                has_changes = match against {
+                    Some(against) => {
+                        let hoard = whole_hoards(std::slice::from_ref(hoard), "--against")?;
+                        self.write_diff_against(&hoard[0], *against, &mut output)
+                            .await?
+                    }
+                    None => {
+                        self.write_diff(
+                            hoard,
+                            path.as_ref(),
+                            *verbose,
+                            *reverse,
+                            *group_by,
+                            *format,
+                            *color,
+                            *follow_renames,
+                            &mut output,
+                        )
+                        .await?
+                    }
+                };
+                log_output(&output);
+            }
+            Command::Plan { hoard } => {
+                command::run_plan(
                     &crate::paths::hoards_dir(),
-                    *verbose,
+                    hoard,
+                    self.get_hoard(hoard)?,
+                    &self.scan_options(),
                 )
                 .await?;
             }
@@ -143,31 +693,234 @@ impl Config {
                 command::run_init(self).await?;
             }
             Command::Validate => {
-                tracing::info!("configuration is valid");
+                command::run_validate(&self.hoards, &self.unresolved)?;
             }
             Command::List => {
                 command::run_list(self.hoards.keys());
             }
-            Command::Cleanup => {
-                command::run_cleanup().await?;
+            Command::History { command } => {
+                command::run_history(command).await?;
             }
-            Command::Backup { hoards } => {
+            Command::Export { hoard, output } => {
+                command::run_export(
+                    &crate::paths::hoards_dir(),
+                    hoard,
+                    self.get_hoard(hoard)?,
+                    output,
+                )
+                .await?;
+            }
+            Command::Import { path } => {
+                command::run_import(&crate::paths::hoards_dir(), path).await?;
+            }
+            Command::Info { hoard } => {
+                command::run_info(&crate::paths::hoards_dir(), hoard, self.get_hoard(hoard)?)
+                    .await?;
+            }
+            Command::Which { hoard } => {
+                command::run_which(
+                    &self.environments,
+                    &crate::paths::hoards_dir(),
+                    hoard,
+                    self.get_hoard(hoard)?,
+                );
+            }
+            Command::Cleanup { dry_run } => {
+                command::run_cleanup(*dry_run).await?;
+            }
+            Command::Doctor { fix, yes } => {
+                command::run_doctor(
+                    &crate::paths::hoards_dir(),
+                    &self.hoards,
+                    *fix,
+                    *yes,
+                    &self.scan_options(),
+                )
+                .await?;
+            }
+            Command::Prune { older_than, keep } => {
+                command::run_prune(*older_than, *keep).await?;
+            }
+            Command::Backup { hoards, json, args } => {
+                // The data directory is already locked, so `backup` would fail to lock it again.
+                let report = self.with_backup_args(args).backup_files(hoards).await?;
+                log_summary(&report, *json);
+            }
+            Command::Restore {
+                hoards,
+                pick,
+                pick_file,
+                version,
+                dry_run,
+                at,
+                json,
+                into,
+                args,
+            } => {
+                let config = self.with_restore_args(args);
                 let data_dir = crate::paths::hoards_dir();
-                let hoards = self.get_hoards(hoards)?;
-                command::run_backup(&data_dir, hoards, self.force).await?;
+                if let Some(into) = into {
+                    let hoards = whole_hoards(hoards, "--into")?;
+                    let mut hoards: Vec<_> = config.get_hoards(&hoards)?.into_iter().collect();
+                    hoards.sort_unstable_by_key(|(name, _)| *name);
+                    let into = if into.is_absolute() {
+                        into.clone()
+                    } else {
+                        std::env::current_dir()
+                            .map_err(command::Error::Output)?
+                            .join(into)
+                    };
+                    command::run_restore_into(
+                        &data_dir,
+                        hoards,
+                        &into,
+                        config.copy_attributes,
+                        config.no_permissions,
+                        config.no_mtime,
+                        &config.scan_options(),
+                    )
+                    .await?;
+                } else if let Some(at) = at {
+                    let hoards = whole_hoards(hoards, "--at")?;
+                    let mut hoards: Vec<_> = config.get_hoards(&hoards)?.into_iter().collect();
+                    hoards.sort_unstable_by_key(|(name, _)| *name);
+                    command::run_restore_at(
+                        &data_dir,
+                        hoards,
+                        *at,
+                        *dry_run,
+                        config.copy_attributes,
+                        config.no_permissions,
+                        config.no_mtime,
+                        config.retry_policy(),
+                    )
+                    .await?;
+                } else if *pick || !pick_file.is_empty() || version.is_some() {
+                    let hoards = whole_hoards(hoards, "--pick, --pick-file, or --version")?;
+                    command::run_pick_restore(
+                        &data_dir,
+                        config.get_hoards(&hoards)?.into_iter().collect(),
+                        pick_file,
+                        version.unwrap_or_default(),
+                        config.copy_attributes,
+                        config.no_permissions,
+                        config.retry_policy(),
+                    )
+                    .await?;
+                } else {
+                    let report = config.restore_files(hoards, *dry_run).await?;
+                    log_summary(&report, *json);
+                }
             }
-            Command::Restore { hoards } => {
+            Command::Resolve { hoard, strategy } => {
+                command::run_resolve(
+                    &crate::paths::hoards_dir(),
+                    hoard,
+                    self.get_hoard(hoard)?,
+                    *strategy,
+                    self.backup_restore_options(),
+                )
+                .await?;
+            }
+            Command::Log { hoard, files } => {
+                command::run_log(hoard, *files).await?;
+            }
+            Command::Blame { hoard, path } => {
+                command::run_blame(hoard, path).await?;
+            }
+            Command::Upgrade { rehash } => {
+                command::run_upgrade(&crate::paths::hoards_dir(), &self.hoards, *rehash).await?;
+            }
+            Command::Verify {
+                hoards,
+                verify_fraction,
+            } => {
                 let data_dir = crate::paths::hoards_dir();
                 let hoards = self.get_hoards(hoards)?;
-                command::run_restore(&data_dir, hoards, self.force).await?;
-            }
-            Command::Upgrade => {
-                command::run_upgrade().await?;
+                command::run_verify(&data_dir, hoards, *verify_fraction).await?;
             }
         }
 
-        Ok(())
+        if has_changes {
+            Ok(ExitCode::ChangesDetected)
+        } else {
+            Ok(ExitCode::Success)
+        }
+    }
+}
+
+/// Limits each hoard in `hoards` to the piles selected with `hoard:pile` in `selectors`.
+///
+/// Hoards that are also selected without a pile are left whole.
+fn select_piles(
+    hoards: &mut HashMap<&HoardName, Hoard>,
+    selectors: &[HoardSelector],
+) -> Result<(), Error> {
+    let mut piles: HashMap<&HoardName, Vec<NonEmptyPileName>> = HashMap::new();
+    for selector in selectors {
+        let Some(pile) = selector.pile() else {
+            continue;
+        };
+        let hoard = &hoards[selector.hoard()];
+        if hoard.get_pile(&pile.clone().into()).is_none() {
+            return crate::create_log_error(Error::NoSuchPile {
+                hoard: selector.hoard().clone(),
+                pile: pile.clone(),
+                piles: hoard.pile_names(),
+            });
+        }
+        piles
+            .entry(selector.hoard())
+            .or_default()
+            .push(pile.clone());
+    }
+
+    for (name, piles) in piles {
+        let whole = selectors
+            .iter()
+            .any(|selector| selector.hoard() == name && selector.pile().is_none());
+        if let (false, Some(hoard)) = (whole, hoards.get_mut(name)) {
+            tracing::debug!(%name, ?piles, "only using selected piles");
+            *hoard = hoard.with_only_piles(&piles);
+        }
     }
+
+    Ok(())
+}
+
+/// Returns the hoard names in `selectors`, failing if any selects a single pile, as `option`
+/// only works on whole hoards.
+fn whole_hoards(
+    selectors: &[HoardSelector],
+    option: &'static str,
+) -> Result<Vec<HoardName>, Error> {
+    selectors
+        .iter()
+        .map(|selector| match selector.pile() {
+            None => Ok(selector.hoard().clone()),
+            Some(_) => crate::create_log_error(Error::PileNotSupported {
+                selector: selector.clone(),
+                option,
+            }),
+        })
+        .collect()
+}
+
+/// Logs output that a command wrote to a buffer, as the CLI reports all results through logging.
+fn log_output(output: &[u8]) {
+    let output = String::from_utf8_lossy(output);
+    let output = output.trim_end();
+    if !output.is_empty() {
+        tracing::info!("{}", output);
+    }
+}
+
+/// Logs the summary of changed files for each hoard in `report`.
+fn log_summary(report: &BackupRestoreReport, json: bool) {
+    let mut output = Vec::new();
+    command::write_summary(&report.summaries, json, &mut output)
+        .expect("writing to a buffer should not fail");
+    log_output(&output);
 }
 
 #[cfg(test)]
@@ -0,0 +1,130 @@
+//! A read-only, fully-resolved view of a [`Config`](super::Config).
+//!
+//! This is intended for tools that embed Hoard and want to inspect which paths a configuration
+//! resolves to on the current system without running a command.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::hoard::{Hoard, PileConfig};
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::{HoardPath, RelativePath, SystemPath};
+
+use super::Config;
+
+/// A single pile with its paths resolved for the current environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedPile {
+    hoard_path: HoardPath,
+    system_path: SystemPath,
+    config: PileConfig,
+}
+
+impl ResolvedPile {
+    /// The root of this pile inside of the hoards directory.
+    #[must_use]
+    pub fn hoard_path(&self) -> &HoardPath {
+        &self.hoard_path
+    }
+
+    /// The root of this pile on the system.
+    #[must_use]
+    pub fn system_path(&self) -> &SystemPath {
+        &self.system_path
+    }
+
+    /// The pile's configuration, after layering global and hoard configuration.
+    #[must_use]
+    pub fn config(&self) -> &PileConfig {
+        &self.config
+    }
+}
+
+/// A hoard with all of its piles resolved for the current environment.
+///
+/// Piles that have no path for the current environment are not included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedHoard {
+    piles: BTreeMap<PileName, ResolvedPile>,
+}
+
+impl ResolvedHoard {
+    fn new(hoard: &Hoard, hoard_root: HoardPath) -> Self {
+        let piles = hoard
+            .get_paths(hoard_root)
+            .map(|(pile_name, hoard_path, system_path)| {
+                let config = hoard
+                    .get_pile(&pile_name)
+                    .map(|pile| pile.config.clone())
+                    .unwrap_or_default();
+                let pile = ResolvedPile {
+                    hoard_path,
+                    system_path,
+                    config,
+                };
+                (pile_name, pile)
+            })
+            .collect();
+
+        Self { piles }
+    }
+
+    /// All resolved piles in this hoard.
+    ///
+    /// Anonymous hoards have a single pile named [`PileName::anonymous`].
+    #[must_use]
+    pub fn piles(&self) -> &BTreeMap<PileName, ResolvedPile> {
+        &self.piles
+    }
+
+    /// Returns the resolved pile with the given name, if any.
+    #[must_use]
+    pub fn pile(&self, name: &PileName) -> Option<&ResolvedPile> {
+        self.piles.get(name)
+    }
+}
+
+/// A configuration with environments, exclusivity, and path expansion applied.
+///
+/// Create one with [`Config::load_resolved`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedConfig {
+    config_file: PathBuf,
+    hoards: BTreeMap<HoardName, ResolvedHoard>,
+}
+
+impl ResolvedConfig {
+    pub(super) fn new(config: &Config, hoards_root: &HoardPath) -> Self {
+        let hoards = config
+            .hoards
+            .iter()
+            .map(|(name, hoard)| {
+                let prefix = hoards_root.join(&RelativePath::from(name));
+                (name.clone(), ResolvedHoard::new(hoard, prefix))
+            })
+            .collect();
+
+        Self {
+            config_file: config.config_file.clone(),
+            hoards,
+        }
+    }
+
+    /// The path of the configuration file that was loaded.
+    #[must_use]
+    pub fn config_file(&self) -> &Path {
+        &self.config_file
+    }
+
+    /// All configured hoards.
+    #[must_use]
+    pub fn hoards(&self) -> &BTreeMap<HoardName, ResolvedHoard> {
+        &self.hoards
+    }
+
+    /// Returns the resolved hoard with the given name, if any.
+    #[must_use]
+    pub fn hoard(&self, name: &HoardName) -> Option<&ResolvedHoard> {
+        self.hoards.get(name)
+    }
+}
@@ -40,6 +40,17 @@ impl FileContent {
         }
     }
 
+    /// Converts CRLF (`\r\n`) line endings in text content to LF (`\n`).
+    ///
+    /// Binary and missing content is returned unchanged.
+    #[must_use]
+    pub fn normalize_line_endings(self) -> Self {
+        match self {
+            Self::Text(s) if s.contains("\r\n") => Self::Text(s.replace("\r\n", "\n")),
+            content => content,
+        }
+    }
+
     #[tracing::instrument(level = "debug")]
     pub fn as_bytes(&self) -> Option<&[u8]> {
         match self {
@@ -88,6 +99,192 @@ pub(crate) fn str_diff(
     }
 }
 
+/// Swaps the sides of a unified diff created by [`str_diff`], so that it describes the changes
+/// from the system file to the hoard file instead.
+pub(crate) fn reverse_unified_diff(unified_diff: &str) -> String {
+    /// Writes out a block of changes, now-removed lines first.
+    fn flush(output: &mut String, removed: &mut Vec<String>, added: &mut Vec<String>) {
+        for line in added.drain(..).chain(removed.drain(..)) {
+            output.push_str(&line);
+        }
+    }
+
+    let mut output = String::with_capacity(unified_diff.len());
+    let mut old_header = None;
+    let mut in_hunk = false;
+    // Lines that were removed in the original diff and are now added, and vice versa.
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let mut last_was_removed = false;
+
+    for line in unified_diff.split_inclusive('\n') {
+        if !in_hunk && line.starts_with("--- ") {
+            old_header = Some(&line[4..]);
+        } else if !in_hunk && line.starts_with("+++ ") {
+            output.push_str("--- ");
+            output.push_str(&line[4..]);
+            output.push_str("+++ ");
+            output.push_str(old_header.take().unwrap_or("\n"));
+        } else if let Some(rest) = line.strip_prefix("@@ ") {
+            in_hunk = true;
+            flush(&mut output, &mut removed, &mut added);
+            match rest.split_once(" @@") {
+                Some((ranges, tail)) => match ranges.split_once(' ') {
+                    Some((old, new)) => {
+                        let old = old.trim_start_matches('-');
+                        let new = new.trim_start_matches('+');
+                        for part in ["@@ -", new, " +", old, " @@", tail] {
+                            output.push_str(part);
+                        }
+                    }
+                    None => output.push_str(line),
+                },
+                None => output.push_str(line),
+            }
+        } else if let Some(rest) = line.strip_prefix('-') {
+            removed.push(format!("+{rest}"));
+            last_was_removed = true;
+        } else if let Some(rest) = line.strip_prefix('+') {
+            added.push(format!("-{rest}"));
+            last_was_removed = false;
+        } else if line.starts_with('\\') {
+            // "No newline at end of file" markers belong to the preceding line.
+            let block = if last_was_removed {
+                &mut removed
+            } else {
+                &mut added
+            };
+            match block.last_mut() {
+                Some(prev) => prev.push_str(line),
+                None => output.push_str(line),
+            }
+        } else {
+            flush(&mut output, &mut removed, &mut added);
+            output.push_str(line);
+        }
+    }
+
+    flush(&mut output, &mut removed, &mut added);
+    output
+}
+
+/// ANSI escape codes used by [`colorize_unified_diff`].
+mod ansi {
+    pub(super) const RESET: &str = "\x1b[0m";
+    pub(super) const BOLD: &str = "\x1b[1m";
+    pub(super) const CYAN: &str = "\x1b[36m";
+    pub(super) const RED: &str = "\x1b[31m";
+    pub(super) const GREEN: &str = "\x1b[32m";
+    /// Highlights changed words within a removed line.
+    pub(super) const RED_HIGHLIGHT: &str = "\x1b[1;97;41m";
+    /// Highlights changed words within an added line.
+    pub(super) const GREEN_HIGHLIGHT: &str = "\x1b[1;97;42m";
+}
+
+/// Adds ANSI colors to a unified diff created by [`str_diff`] or [`reverse_unified_diff`].
+///
+/// Removed lines are red and added lines are green. When a block of removed lines is directly
+/// followed by a block of added lines, the lines are paired up in order and only the words
+/// that changed between each pair are highlighted.
+pub(crate) fn colorize_unified_diff(unified_diff: &str) -> String {
+    /// Writes out a block of changes, highlighting the changed words of paired lines.
+    fn flush(output: &mut String, removed: &mut Vec<String>, added: &mut Vec<String>) {
+        for (i, line) in removed.iter().enumerate() {
+            match added.get(i) {
+                Some(other) => push_word_diff(output, line, other, ChangeTag::Delete),
+                None => push_line(output, ansi::RED, line),
+            }
+        }
+        for (i, line) in added.iter().enumerate() {
+            match removed.get(i) {
+                Some(other) => push_word_diff(output, other, line, ChangeTag::Insert),
+                None => push_line(output, ansi::GREEN, line),
+            }
+        }
+        removed.clear();
+        added.clear();
+    }
+
+    let mut output = String::with_capacity(unified_diff.len() * 2);
+    let mut in_hunk = false;
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let mut last_was_removed = false;
+    let mut lines = unified_diff.split_inclusive('\n').peekable();
+
+    while let Some(line) = lines.next() {
+        // "No newline at end of file" markers belong to the preceding line, so keep them together.
+        let mut line = line.to_string();
+        if let Some(marker) = lines.next_if(|next| next.starts_with('\\')) {
+            line.push_str(marker);
+        }
+
+        if !in_hunk && (line.starts_with("--- ") || line.starts_with("+++ ")) {
+            push_line(&mut output, ansi::BOLD, &line);
+        } else if line.starts_with("@@ ") {
+            in_hunk = true;
+            flush(&mut output, &mut removed, &mut added);
+            push_line(&mut output, ansi::CYAN, &line);
+        } else if line.starts_with('-') {
+            if !last_was_removed && !added.is_empty() {
+                flush(&mut output, &mut removed, &mut added);
+            }
+            removed.push(line);
+            last_was_removed = true;
+        } else if line.starts_with('+') {
+            added.push(line);
+            last_was_removed = false;
+        } else {
+            flush(&mut output, &mut removed, &mut added);
+            output.push_str(&line);
+        }
+    }
+
+    flush(&mut output, &mut removed, &mut added);
+    output
+}
+
+/// Splits a diff line into its content and the line ending, along with any "no newline" marker.
+fn split_line_end(line: &str) -> (&str, &str) {
+    let end = line.find('\n').unwrap_or(line.len());
+    line.split_at(end)
+}
+
+/// Writes `line` in `color`, resetting the color before the line ending.
+fn push_line(output: &mut String, color: &str, line: &str) {
+    let (content, end) = split_line_end(line);
+    for part in [color, content, ansi::RESET, end] {
+        output.push_str(part);
+    }
+}
+
+/// Writes the `side` of a pair of changed lines, highlighting the words that differ from the
+/// other side.
+fn push_word_diff(output: &mut String, removed: &str, added: &str, side: ChangeTag) {
+    let (old, _) = split_line_end(&removed[1..]);
+    let (new, _) = split_line_end(&added[1..]);
+    let (prefix, line, color, highlight) = match side {
+        ChangeTag::Insert => ("+", added, ansi::GREEN, ansi::GREEN_HIGHLIGHT),
+        _ => ("-", removed, ansi::RED, ansi::RED_HIGHLIGHT),
+    };
+    let (_, end) = split_line_end(line);
+
+    output.push_str(color);
+    output.push_str(prefix);
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let tag = change.tag();
+        if tag == ChangeTag::Equal {
+            output.push_str(change.value());
+        } else if tag == side {
+            for part in [highlight, change.value(), ansi::RESET, color] {
+                output.push_str(part);
+            }
+        }
+    }
+    output.push_str(ansi::RESET);
+    output.push_str(end);
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -113,5 +310,128 @@ mod test {
         fn test_missing_into_bytes() {
             assert_eq!(FileContent::Missing.as_bytes(), None);
         }
+
+        #[test]
+        fn test_normalize_line_endings() {
+            let s = FileContent::Text(String::from("line 1\r\nline 2\n"));
+            assert_eq!(
+                s.normalize_line_endings(),
+                FileContent::Text(String::from("line 1\nline 2\n"))
+            );
+
+            let bytes = vec![b'a', b'\r', b'\n', 0u8];
+            let b = FileContent::Binary(bytes.clone());
+            assert_eq!(b.normalize_line_endings(), FileContent::Binary(bytes));
+        }
+    }
+
+    mod reverse {
+        use super::*;
+
+        #[test]
+        fn test_reverse_unified_diff() {
+            let forward = concat!(
+                "--- /hoard/file\n",
+                "+++ /system/file\n",
+                "@@ -1,4 +1,5 @@\n",
+                " one\n",
+                "-two\n",
+                "+2\n",
+                " three\n",
+                "-four\n",
+                "\\ No newline at end of file\n",
+                "+four\n",
+                "+five\n",
+                "\\ No newline at end of file\n",
+            );
+            let reversed = concat!(
+                "--- /system/file\n",
+                "+++ /hoard/file\n",
+                "@@ -1,5 +1,4 @@\n",
+                " one\n",
+                "-2\n",
+                "+two\n",
+                " three\n",
+                "-four\n",
+                "-five\n",
+                "\\ No newline at end of file\n",
+                "+four\n",
+                "\\ No newline at end of file\n",
+            );
+
+            assert_eq!(reverse_unified_diff(forward), reversed);
+            assert_eq!(reverse_unified_diff(reversed), forward);
+        }
+    }
+
+    mod colorize {
+        use super::*;
+
+        /// Removes all ANSI escape codes from `s`.
+        fn strip_ansi(s: &str) -> String {
+            let mut output = String::new();
+            let mut in_escape = false;
+            for c in s.chars() {
+                match (in_escape, c) {
+                    (false, '\x1b') => in_escape = true,
+                    (false, c) => output.push(c),
+                    (true, 'm') => in_escape = false,
+                    (true, _) => {}
+                }
+            }
+            output
+        }
+
+        const DIFF: &str = concat!(
+            "--- /hoard/file\n",
+            "+++ /system/file\n",
+            "@@ -1,4 +1,4 @@\n",
+            " one\n",
+            "-the quick fox\n",
+            "+the slow fox\n",
+            " three\n",
+            "-removed\n",
+            "\\ No newline at end of file\n",
+            "+added\n",
+            "+extra\n",
+            "\\ No newline at end of file\n",
+        );
+
+        #[test]
+        fn test_colorize_only_adds_escape_codes() {
+            assert_eq!(strip_ansi(&colorize_unified_diff(DIFF)), DIFF);
+        }
+
+        #[test]
+        fn test_colorize_highlights_changed_words() {
+            let colored = colorize_unified_diff(DIFF);
+            assert!(colored.contains(&format!(
+                "{}-the {}quick{}{} fox{}\n",
+                ansi::RED,
+                ansi::RED_HIGHLIGHT,
+                ansi::RESET,
+                ansi::RED,
+                ansi::RESET
+            )));
+            assert!(colored.contains(&format!(
+                "{}+the {}slow{}{} fox{}\n",
+                ansi::GREEN,
+                ansi::GREEN_HIGHLIGHT,
+                ansi::RESET,
+                ansi::GREEN,
+                ansi::RESET
+            )));
+        }
+
+        #[test]
+        fn test_colorize_unpaired_lines() {
+            let colored = colorize_unified_diff(DIFF);
+            assert!(colored.contains(&format!("{}\n one\n", ansi::RESET)));
+            assert!(colored.contains(&format!(
+                "{}+extra{}\n\\ No newline at end of file\n",
+                ansi::GREEN,
+                ansi::RESET
+            )));
+        }
     }
 }
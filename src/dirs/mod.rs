@@ -1,7 +1,11 @@
 //! Functions to determine special folders for Hoard to work with on different platforms.
+//!
+//! Following the XDG Base Directory specification, `XDG_*` variables that are empty or contain
+//! a relative path are treated as unset.
 use std::path::{Path, PathBuf};
 
 use once_cell::sync::Lazy;
+use thiserror::Error;
 #[cfg(windows)]
 pub use windows::Win32::UI::Shell::{FOLDERID_Profile, FOLDERID_RoamingAppData};
 
@@ -27,13 +31,47 @@ pub const PROJECT: &str = "hoard";
 pub const DATA_DIR_ENV: &str = "HOARD_DATA_DIR";
 /// The environment variable that takes precendence over config dir detection.
 pub const CONFIG_DIR_ENV: &str = "HOARD_CONFIG_DIR";
+/// The environment variable that takes precendence over cache dir detection.
+pub const CACHE_DIR_ENV: &str = "HOARD_CACHE_DIR";
+
+/// Errors that may occur while determining special folders.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Neither the platform's lookup nor the fallback environment variable provided a path.
+    #[error("could not determine {folder}: {reason}. Set {env_var} to a valid path and try again")]
+    NotFound {
+        /// Description of the folder that was being looked up.
+        folder: &'static str,
+        /// The environment variable that can be set to provide the path.
+        env_var: &'static str,
+        /// Why the folder could not be determined.
+        reason: String,
+    },
+    /// An environment variable that overrides one of Hoard's directories contains a relative
+    /// path whose parent directory does not exist.
+    #[error("invalid path in {env_var} ({}): {reason}", path.display())]
+    InvalidOverride {
+        /// The environment variable containing the path.
+        env_var: &'static str,
+        /// The path given in the environment variable.
+        path: PathBuf,
+        /// Why the path cannot be used.
+        reason: String,
+    },
+    /// The given string cannot be used as a macOS bundle identifier.
+    #[error("invalid bundle identifier {0:?}: must be non-empty and not contain path separators")]
+    InvalidBundleId(String),
+}
 
 static EMPTY_SPAN: Lazy<tracing::Span> = Lazy::new(|| tracing::trace_span!("get_dir_path"));
 
 #[inline]
 #[tracing::instrument(level = "trace")]
 fn path_from_env(var: &str) -> Option<PathBuf> {
-    match std::env::var_os(var).map(PathBuf::from) {
+    match std::env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+    {
         None => {
             tracing::trace!("could not find path in env var {}", var);
             None
@@ -45,17 +83,99 @@ fn path_from_env(var: &str) -> Option<PathBuf> {
     }
 }
 
+/// Makes `path`, the value of the override variable `var`, into an absolute path.
+///
+/// A leading `~` is replaced with the user's home directory, and relative paths are resolved
+/// against the current directory. Absolute paths are returned untouched.
+fn resolve_override(var: &'static str, path: PathBuf) -> Result<PathBuf, Error> {
+    let path = match path.strip_prefix("~") {
+        Ok(rest) => try_home_dir()?.join(rest),
+        Err(_) => path,
+    };
+    if path.is_absolute() {
+        return Ok(path);
+    }
+
+    let invalid = |reason: String| Error::InvalidOverride {
+        env_var: var,
+        path: path.clone(),
+        reason,
+    };
+    let resolved = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            parent.canonicalize().map(|parent| parent.join(name))
+        }
+        _ => path.canonicalize(),
+    }
+    .map_err(|error| invalid(format!("parent directory cannot be resolved: {error}")))?;
+
+    tracing::trace!(
+        "resolved {} = {} to {}",
+        var,
+        path.display(),
+        resolved.display()
+    );
+    Ok(resolved)
+}
+
+/// Returns the absolute path in the override variable `var`, if it is set.
+fn override_from_env(var: &'static str) -> Result<Option<PathBuf>, Error> {
+    path_from_env(var)
+        .map(|path| resolve_override(var, path))
+        .transpose()
+}
+
+/// Makes the paths in [`CONFIG_DIR_ENV`], [`DATA_DIR_ENV`], and [`CACHE_DIR_ENV`] absolute.
+///
+/// This should be called at startup so that relative paths are resolved against the directory
+/// Hoard was started in, even if the current directory changes later.
+///
+/// # Errors
+///
+/// [`Error::InvalidOverride`] if a relative path has a parent directory that does not exist, or
+/// [`Error::NotFound`] if a path starts with `~` and the home directory cannot be determined.
+#[tracing::instrument(level = "trace")]
+pub fn resolve_dir_overrides() -> Result<(), Error> {
+    for var in [CONFIG_DIR_ENV, DATA_DIR_ENV, CACHE_DIR_ENV] {
+        if let Some(path) = override_from_env(var)? {
+            std::env::set_var(var, path);
+        }
+    }
+    Ok(())
+}
+
 /// Returns the current user's home directory.
 ///
 /// - Windows: The "known folder" `FOLDERID_Profile`, fallback to `%USERPROFILE%`.
-/// - macOS/Linux/BSD: The value of `$HOME`.
-#[must_use]
+/// - macOS/Linux/BSD/illumos: The value of `$HOME`.
+///
+/// # Errors
+///
+/// [`Error::NotFound`] if the home directory cannot be determined.
 #[inline]
-pub fn home_dir() -> PathBuf {
+pub fn try_home_dir() -> Result<PathBuf, Error> {
     let _span = tracing::trace_span!(parent: &*EMPTY_SPAN, "home_dir").entered();
     sys::home_dir()
 }
 
+/// Returns the current user's home directory.
+///
+/// See [`try_home_dir`] for a non-panicking version.
+///
+/// # Panics
+///
+/// If the home directory cannot be determined.
+#[must_use]
+#[inline]
+pub fn home_dir() -> PathBuf {
+    try_home_dir().unwrap_or_else(|error| panic!("{error}"))
+}
+
 /// Returns Hoard's configuration directory for the current user.
 ///
 /// Returns the contents of `HOARD_CONFIG_DIR`, if set, otherwise:
@@ -64,12 +184,31 @@ pub fn home_dir() -> PathBuf {
 ///   `FOLDERID_RoamingAppData` or the value of `%APPDATA%`.
 /// - macOS: `${XDG_CONFIG_HOME}/hoard`, if `XDG_CONFIG_HOME` is set, otherwise
 ///   `$HOME/Library/Application Support/com.shadow53.hoard`.
-/// - Linux/BSD: `${XFG_CONFIG_HOME}/hoard`, if `XDG_CONFIG_HOME` is set, otherwise `$HOME/.config/hoard`.
+/// - Linux/BSD/illumos: `${XDG_CONFIG_HOME}/hoard`, if `XDG_CONFIG_HOME` is set, otherwise
+///   `$HOME/.config/hoard`.
+///
+/// # Errors
+///
+/// - [`Error::NotFound`] if the directory is not set explicitly and cannot be determined.
+/// - [`Error::InvalidOverride`] if the directory is set explicitly to a relative path whose
+///   parent does not exist.
+#[inline]
+pub fn try_config_dir() -> Result<PathBuf, Error> {
+    let _span = tracing::trace_span!(parent: &*EMPTY_SPAN, "config_dir").entered();
+    override_from_env(CONFIG_DIR_ENV)?.map_or_else(sys::config_dir, Ok)
+}
+
+/// Returns Hoard's configuration directory for the current user.
+///
+/// See [`try_config_dir`] for details and a non-panicking version.
+///
+/// # Panics
+///
+/// If the directory cannot be determined.
 #[must_use]
 #[inline]
 pub fn config_dir() -> PathBuf {
-    let _span = tracing::trace_span!(parent: &*EMPTY_SPAN, "config_dir").entered();
-    path_from_env(CONFIG_DIR_ENV).unwrap_or_else(sys::config_dir)
+    try_config_dir().unwrap_or_else(|error| panic!("{error}"))
 }
 
 /// Returns Hoard's data directory for the current user.
@@ -78,12 +217,108 @@ pub fn config_dir() -> PathBuf {
 ///   `FOLDERID_RoamingAppData` or the value of `%APPDATA%`.
 /// - macOS: `${XDG_DATA_HOME}/hoard`, if `XDG_DATA_HOME` is set, otherwise
 ///   `$HOME/Library/Application Support/com.shadow53.hoard`.
-/// - Linux/BSD: `${XFG_DATA_HOME}/hoard`, if `XDG_DATA_HOME` is set, otherwise `$HOME/.local/share/hoard`.
+/// - Linux/BSD/illumos: `${XDG_DATA_HOME}/hoard`, if `XDG_DATA_HOME` is set, otherwise
+///   `$HOME/.local/share/hoard`.
+///
+/// # Errors
+///
+/// - [`Error::NotFound`] if the directory is not set explicitly and cannot be determined.
+/// - [`Error::InvalidOverride`] if the directory is set explicitly to a relative path whose
+///   parent does not exist.
+#[inline]
+pub fn try_data_dir() -> Result<PathBuf, Error> {
+    let _span = tracing::trace_span!(parent: &*EMPTY_SPAN, "data_dir").entered();
+    override_from_env(DATA_DIR_ENV)?.map_or_else(sys::data_dir, Ok)
+}
+
+/// Returns Hoard's data directory for the current user.
+///
+/// See [`try_data_dir`] for details and a non-panicking version.
+///
+/// # Panics
+///
+/// If the directory cannot be determined.
 #[must_use]
 #[inline]
 pub fn data_dir() -> PathBuf {
-    let _span = tracing::trace_span!(parent: &*EMPTY_SPAN, "data_dir").entered();
-    path_from_env(DATA_DIR_ENV).unwrap_or_else(sys::data_dir)
+    try_data_dir().unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Returns Hoard's cache directory for the current user.
+///
+/// Files in this directory are not required for Hoard to work correctly and may be deleted
+/// at any time.
+///
+/// Returns the contents of `HOARD_CACHE_DIR`, if set, otherwise:
+///
+/// - Windows: `{localappdata}/shadow53/hoard/cache` where `{localappdata}` is the "known folder"
+///   `FOLDERID_LocalAppData` or the value of `%LOCALAPPDATA%`.
+/// - macOS: `${XDG_CACHE_HOME}/hoard`, if `XDG_CACHE_HOME` is set, otherwise
+///   `$HOME/Library/Caches/com.shadow53.hoard`.
+/// - Linux/BSD/illumos: `${XDG_CACHE_HOME}/hoard`, if `XDG_CACHE_HOME` is set, otherwise
+///   `$HOME/.cache/hoard`.
+///
+/// # Errors
+///
+/// - [`Error::NotFound`] if the directory is not set explicitly and cannot be determined.
+/// - [`Error::InvalidOverride`] if the directory is set explicitly to a relative path whose
+///   parent does not exist.
+#[inline]
+pub fn try_cache_dir() -> Result<PathBuf, Error> {
+    let _span = tracing::trace_span!(parent: &*EMPTY_SPAN, "cache_dir").entered();
+    override_from_env(CACHE_DIR_ENV)?.map_or_else(sys::cache_dir, Ok)
+}
+
+/// Returns Hoard's cache directory for the current user.
+///
+/// See [`try_cache_dir`] for details and a non-panicking version.
+///
+/// # Panics
+///
+/// If the directory cannot be determined.
+#[must_use]
+#[inline]
+pub fn cache_dir() -> PathBuf {
+    try_cache_dir().unwrap_or_else(|error| panic!("{error}"))
+}
+
+/// Returns the `Data` directory of the macOS sandbox container for the app with the given
+/// bundle identifier, e.g. `com.example.game`.
+///
+/// Sandboxed apps, including those from the Mac App Store, keep their files in
+/// `$HOME/Library/Containers/{bundle_id}/Data` instead of directly under `$HOME`. The returned
+/// path may not exist yet if the app has not been run.
+///
+/// Hoard paths can refer to this directory with a leading `~container:{bundle_id}`.
+///
+/// # Errors
+///
+/// - [`Error::InvalidBundleId`] if `bundle_id` is empty, is `.` or `..`, or contains a path
+///   separator.
+/// - [`Error::NotFound`] if the home directory cannot be determined.
+#[tracing::instrument(level = "trace")]
+pub fn try_mac_container_dir(bundle_id: &str) -> Result<PathBuf, Error> {
+    if bundle_id.is_empty()
+        || bundle_id == "."
+        || bundle_id == ".."
+        || bundle_id.contains(['/', '\\'])
+    {
+        return Err(Error::InvalidBundleId(bundle_id.to_string()));
+    }
+
+    let path = try_home_dir()?
+        .join("Library")
+        .join("Containers")
+        .join(bundle_id)
+        .join("Data");
+    if !path.is_dir() {
+        tracing::trace!(
+            "container for {} does not exist yet at {}",
+            bundle_id,
+            path.display()
+        );
+    }
+    Ok(path)
 }
 
 /// Set the environment variable that overrides Hoard's config directory.
@@ -102,6 +337,14 @@ pub fn set_data_dir(path: &Path) {
     std::env::set_var(DATA_DIR_ENV, path);
 }
 
+/// Set the environment variable that overrides Hoard's cache directory.
+///
+/// See [`CACHE_DIR_ENV`].
+#[tracing::instrument(level = "trace")]
+pub fn set_cache_dir(path: &Path) {
+    std::env::set_var(CACHE_DIR_ENV, path);
+}
+
 #[cfg(test)]
 mod tests {
     use std::env;
@@ -129,4 +372,79 @@ mod tests {
         assert_eq!(new_path.as_os_str(), env::var_os(DATA_DIR_ENV).unwrap());
         assert_eq!(new_path, data_dir());
     }
+
+    #[test]
+    fn test_env_cache_dir() {
+        env::remove_var(CACHE_DIR_ENV);
+        let original = cache_dir();
+        let new_path = PathBuf::from("/env/cache/dir");
+        assert_ne!(original, new_path);
+        set_cache_dir(&new_path);
+        assert_eq!(new_path.as_os_str(), env::var_os(CACHE_DIR_ENV).unwrap());
+        assert_eq!(new_path, cache_dir());
+    }
+
+    #[test]
+    fn test_override_absolute_path_is_untouched() {
+        let path = PathBuf::from("/env/data/dir/../dir");
+        assert_eq!(path, resolve_override(DATA_DIR_ENV, path.clone()).unwrap());
+    }
+
+    #[test]
+    fn test_override_relative_path_is_absolute() {
+        let cwd = env::current_dir().unwrap().canonicalize().unwrap();
+        assert_eq!(
+            cwd.join("data"),
+            resolve_override(DATA_DIR_ENV, PathBuf::from("./data")).unwrap()
+        );
+        assert_eq!(
+            cwd.join("config"),
+            resolve_override(CONFIG_DIR_ENV, PathBuf::from("config")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_override_tilde_is_home_dir() {
+        assert_eq!(
+            home_dir().join("data"),
+            resolve_override(DATA_DIR_ENV, PathBuf::from("~/data")).unwrap()
+        );
+        assert_eq!(
+            home_dir(),
+            resolve_override(DATA_DIR_ENV, PathBuf::from("~")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_override_relative_path_without_parent() {
+        let path = PathBuf::from("does/not/exist/data");
+        assert!(matches!(
+            resolve_override(DATA_DIR_ENV, path.clone()),
+            Err(Error::InvalidOverride { env_var, path: error_path, .. })
+                if env_var == DATA_DIR_ENV && error_path == path
+        ));
+    }
+
+    #[test]
+    fn test_mac_container_dir() {
+        let expected = home_dir()
+            .join("Library")
+            .join("Containers")
+            .join("com.example.game")
+            .join("Data");
+        assert_eq!(expected, try_mac_container_dir("com.example.game").unwrap());
+    }
+
+    #[test]
+    fn test_mac_container_dir_invalid_bundle_id() {
+        for bundle_id in ["", ".", "..", "com.example/game", "com.example\\game"] {
+            assert!(
+                matches!(
+                    try_mac_container_dir(bundle_id),
+                    Err(Error::InvalidBundleId(id)) if id == bundle_id
+                ),
+                "{bundle_id:?} should be rejected"
+            );
+        }
+    }
 }
@@ -1,65 +1,211 @@
 use std::path::PathBuf;
 
-use super::{path_from_env, PROJECT};
+use super::{path_from_env, Error, PROJECT};
 #[cfg(target_os = "macos")]
 use super::{COMPANY, TLD};
 
+/// Returns Hoard's folder inside the XDG base directory in `var`, if set.
+///
+/// Per the XDG Base Directory specification, empty and relative paths are treated as if the
+/// variable were not set.
 #[tracing::instrument(level = "trace")]
+fn xdg_dir(var: &str) -> Option<PathBuf> {
+    let path = path_from_env(var)?;
+    if path.is_absolute() {
+        Some(path.join(PROJECT))
+    } else {
+        tracing::warn!(
+            "ignoring {}={}: XDG base directories must be absolute paths",
+            var,
+            path.display()
+        );
+        None
+    }
+}
+
 fn xdg_config_dir() -> Option<PathBuf> {
-    path_from_env("XDG_CONFIG_HOME").map(|path| path.join(PROJECT))
+    xdg_dir("XDG_CONFIG_HOME")
 }
 
-#[tracing::instrument(level = "trace")]
 fn xdg_data_dir() -> Option<PathBuf> {
-    path_from_env("XDG_DATA_HOME").map(|path| path.join(PROJECT))
+    xdg_dir("XDG_DATA_HOME")
+}
+
+fn xdg_cache_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CACHE_HOME")
 }
 
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn home_dir() -> PathBuf {
-    path_from_env("HOME").expect("could not determine user home directory")
+pub(super) fn home_dir() -> Result<PathBuf, Error> {
+    path_from_env("HOME").ok_or_else(|| Error::NotFound {
+        folder: "the user home directory",
+        env_var: "HOME",
+        reason: String::from("$HOME is not set"),
+    })
 }
 
 #[cfg(target_os = "macos")]
 #[tracing::instrument(level = "trace")]
-fn mac_config_dir() -> PathBuf {
+fn mac_config_dir() -> Result<PathBuf, Error> {
     tracing::trace!("using macos-specific config/data directory");
-    home_dir()
-        .join("Library")
-        .join("Application Support")
-        .join(format!("{}.{}.{}", TLD, COMPANY, PROJECT))
+    home_dir().map(|home| {
+        home.join("Library")
+            .join("Application Support")
+            .join(format!("{}.{}.{}", TLD, COMPANY, PROJECT))
+    })
 }
 
 #[cfg(target_os = "macos")]
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn config_dir() -> PathBuf {
-    xdg_config_dir().unwrap_or_else(mac_config_dir)
+pub(super) fn config_dir() -> Result<PathBuf, Error> {
+    xdg_config_dir().map_or_else(mac_config_dir, Ok)
 }
 
 #[cfg(target_os = "macos")]
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn data_dir() -> PathBuf {
-    xdg_data_dir().unwrap_or_else(mac_config_dir)
+pub(super) fn data_dir() -> Result<PathBuf, Error> {
+    xdg_data_dir().map_or_else(mac_config_dir, Ok)
 }
 
 #[cfg(not(target_os = "macos"))]
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn config_dir() -> PathBuf {
-    xdg_config_dir().unwrap_or_else(|| {
-        tracing::trace!("using fallback config directory");
-        home_dir().join(".config").join(PROJECT)
-    })
+pub(super) fn config_dir() -> Result<PathBuf, Error> {
+    xdg_config_dir().map_or_else(
+        || {
+            tracing::trace!("using fallback config directory");
+            home_dir().map(|home| home.join(".config").join(PROJECT))
+        },
+        Ok,
+    )
 }
 
 #[cfg(not(target_os = "macos"))]
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn data_dir() -> PathBuf {
-    xdg_data_dir().unwrap_or_else(|| {
-        tracing::trace!("using fallback data directory");
-        home_dir().join(".local").join("share").join(PROJECT)
-    })
+pub(super) fn data_dir() -> Result<PathBuf, Error> {
+    xdg_data_dir().map_or_else(
+        || {
+            tracing::trace!("using fallback data directory");
+            home_dir().map(|home| home.join(".local").join("share").join(PROJECT))
+        },
+        Ok,
+    )
+}
+
+#[cfg(target_os = "macos")]
+#[tracing::instrument(level = "trace")]
+pub(super) fn cache_dir() -> Result<PathBuf, Error> {
+    xdg_cache_dir().map_or_else(
+        || {
+            tracing::trace!("using macos-specific cache directory");
+            home_dir().map(|home| {
+                home.join("Library")
+                    .join("Caches")
+                    .join(format!("{}.{}.{}", TLD, COMPANY, PROJECT))
+            })
+        },
+        Ok,
+    )
+}
+
+#[cfg(not(target_os = "macos"))]
+#[tracing::instrument(level = "trace")]
+pub(super) fn cache_dir() -> Result<PathBuf, Error> {
+    xdg_cache_dir().map_or_else(
+        || {
+            tracing::trace!("using fallback cache directory");
+            home_dir().map(|home| home.join(".cache").join(PROJECT))
+        },
+        Ok,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::ffi::OsString;
+
+    use super::*;
+
+    /// Runs `f` with `var` set to `value`, or unset if `None`, restoring the original value after.
+    fn with_var<T>(var: &str, value: Option<&str>, f: impl FnOnce() -> T) -> T {
+        let original: Option<OsString> = env::var_os(var);
+        match value {
+            Some(value) => env::set_var(var, value),
+            None => env::remove_var(var),
+        }
+        let result = f();
+        match original {
+            Some(original) => env::set_var(var, original),
+            None => env::remove_var(var),
+        }
+        result
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn fallback_config_dir() -> PathBuf {
+        home_dir().unwrap().join(".config").join(PROJECT)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn fallback_config_dir() -> PathBuf {
+        mac_config_dir().unwrap()
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn fallback_data_dir() -> PathBuf {
+        home_dir()
+            .unwrap()
+            .join(".local")
+            .join("share")
+            .join(PROJECT)
+    }
+
+    #[cfg(target_os = "macos")]
+    fn fallback_data_dir() -> PathBuf {
+        mac_config_dir().unwrap()
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn xdg_config_home_is_used_when_set() {
+        let path = with_var("XDG_CONFIG_HOME", Some("/xdg/config"), config_dir).unwrap();
+        assert_eq!(path, PathBuf::from("/xdg/config").join(PROJECT));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn unset_xdg_config_home_falls_back() {
+        let path = with_var("XDG_CONFIG_HOME", None, config_dir).unwrap();
+        assert_eq!(path, fallback_config_dir());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn empty_xdg_config_home_is_treated_as_unset() {
+        let path = with_var("XDG_CONFIG_HOME", Some(""), config_dir).unwrap();
+        assert_eq!(path, fallback_config_dir());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn relative_xdg_config_home_is_treated_as_unset() {
+        let path = with_var("XDG_CONFIG_HOME", Some("relative/config"), config_dir).unwrap();
+        assert_eq!(path, fallback_config_dir());
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn xdg_data_home_is_used_when_set() {
+        let path = with_var("XDG_DATA_HOME", Some("/xdg/data"), data_dir).unwrap();
+        assert_eq!(path, PathBuf::from("/xdg/data").join(PROJECT));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn empty_xdg_data_home_is_treated_as_unset() {
+        let unset = with_var("XDG_DATA_HOME", None, data_dir).unwrap();
+        let empty = with_var("XDG_DATA_HOME", Some(""), data_dir).unwrap();
+        assert_eq!(unset, fallback_data_dir());
+        assert_eq!(empty, unset);
+    }
 }
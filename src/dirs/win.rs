@@ -4,10 +4,10 @@ use std::path::{Path, PathBuf};
 
 use windows::core::{Result as WinResult, GUID, PCWSTR, PWSTR};
 use windows::Win32::Foundation::HANDLE;
-use windows::Win32::UI::Shell::{FOLDERID_Profile, FOLDERID_RoamingAppData};
+use windows::Win32::UI::Shell::{FOLDERID_LocalAppData, FOLDERID_Profile, FOLDERID_RoamingAppData};
 use windows::Win32::UI::Shell::{SHGetKnownFolderPath, SHSetKnownFolderPath, KF_FLAG_CREATE};
 
-use super::{path_from_env, COMPANY, PROJECT};
+use super::{path_from_env, Error, COMPANY, PROJECT};
 
 #[allow(unsafe_code)]
 fn pwstr_len(pwstr: PWSTR) -> usize {
@@ -70,36 +70,80 @@ macro_rules! get_and_log_known_folder {
     }};
 }
 
-#[must_use]
+/// Returns the path from a known folder lookup, falling back to the given environment variable.
+///
+/// Empty paths from either source are treated as missing.
+fn known_folder_or_env(
+    folder: &'static str,
+    known: WinResult<PathBuf>,
+    env_var: &'static str,
+) -> Result<PathBuf, Error> {
+    let reason = match known {
+        Ok(path) if !path.as_os_str().is_empty() => return Ok(path),
+        Ok(_) => String::from("known folder lookup returned an empty path"),
+        Err(error) => format!("known folder lookup failed ({error})"),
+    };
+
+    tracing::debug!(%reason, "falling back to %{}%", env_var);
+    path_from_env(env_var).ok_or_else(|| Error::NotFound {
+        folder,
+        env_var,
+        reason: format!("{reason} and %{env_var}% is not set"),
+    })
+}
+
 #[tracing::instrument(level = "trace")]
-pub(super) fn home_dir() -> PathBuf {
-    get_and_log_known_folder!(FOLDERID_Profile)
-        .ok()
-        .or_else(|| path_from_env("USERPROFILE"))
-        .expect("could not determine user home directory")
+pub(super) fn home_dir() -> Result<PathBuf, Error> {
+    known_folder_or_env(
+        "the user home directory",
+        get_and_log_known_folder!(FOLDERID_Profile),
+        "USERPROFILE",
+    )
 }
 
 #[inline]
 #[tracing::instrument(level = "trace")]
-fn appdata() -> PathBuf {
-    get_and_log_known_folder!(FOLDERID_RoamingAppData)
-        .ok()
-        .or_else(|| path_from_env("APPDATA"))
-        .unwrap_or_else(|| home_dir().join("AppData").join("Roaming"))
-        .join(COMPANY)
-        .join(PROJECT)
+fn appdata() -> Result<PathBuf, Error> {
+    known_folder_or_env(
+        "the roaming AppData directory",
+        get_and_log_known_folder!(FOLDERID_RoamingAppData),
+        "APPDATA",
+    )
+    .or_else(|error| {
+        tracing::debug!(%error, "falling back to the user home directory");
+        home_dir().map(|home| home.join("AppData").join("Roaming"))
+    })
+    .map(|path| path.join(COMPANY).join(PROJECT))
+}
+
+#[inline]
+#[tracing::instrument(level = "trace")]
+fn local_appdata() -> Result<PathBuf, Error> {
+    known_folder_or_env(
+        "the local AppData directory",
+        get_and_log_known_folder!(FOLDERID_LocalAppData),
+        "LOCALAPPDATA",
+    )
+    .or_else(|error| {
+        tracing::debug!(%error, "falling back to the user home directory");
+        home_dir().map(|home| home.join("AppData").join("Local"))
+    })
+    .map(|path| path.join(COMPANY).join(PROJECT))
+}
+
+#[tracing::instrument(level = "trace")]
+pub(super) fn config_dir() -> Result<PathBuf, Error> {
+    appdata().map(|path| path.join("config"))
 }
 
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn config_dir() -> PathBuf {
-    appdata().join("config")
+pub(super) fn data_dir() -> Result<PathBuf, Error> {
+    appdata().map(|path| path.join("data"))
 }
 
-#[must_use]
 #[tracing::instrument(level = "trace")]
-pub(super) fn data_dir() -> PathBuf {
-    appdata().join("data")
+pub(super) fn cache_dir() -> Result<PathBuf, Error> {
+    local_appdata().map(|path| path.join("cache"))
 }
 
 #[cfg(test)]
@@ -112,4 +156,46 @@ mod tests {
         let env_home = std::env::var_os("USERPROFILE").map(PathBuf::from).unwrap();
         assert_eq!(known_home, env_home);
     }
+
+    fn failed_lookup() -> WinResult<PathBuf> {
+        Err(windows::core::Error::from(
+            windows::Win32::Foundation::E_FAIL,
+        ))
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn known_folder_falls_back_to_env() {
+        let expected = PathBuf::from("C:\\fallback\\home");
+        std::env::set_var("HOARD_TEST_FALLBACK", &expected);
+        let path = known_folder_or_env("test folder", failed_lookup(), "HOARD_TEST_FALLBACK")
+            .expect("env var fallback should be used");
+        std::env::remove_var("HOARD_TEST_FALLBACK");
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn missing_known_folder_and_env_is_error() {
+        std::env::remove_var("HOARD_TEST_FALLBACK");
+        let error = known_folder_or_env("test folder", failed_lookup(), "HOARD_TEST_FALLBACK")
+            .expect_err("lookup should fail without known folder or env var");
+        assert!(matches!(
+            error,
+            Error::NotFound {
+                env_var: "HOARD_TEST_FALLBACK",
+                ..
+            }
+        ));
+        assert!(error.to_string().contains("HOARD_TEST_FALLBACK"));
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn empty_known_folder_and_env_is_error() {
+        std::env::set_var("HOARD_TEST_FALLBACK", "");
+        let result = known_folder_or_env("test folder", Ok(PathBuf::new()), "HOARD_TEST_FALLBACK");
+        std::env::remove_var("HOARD_TEST_FALLBACK");
+        result.expect_err("empty paths should not be returned");
+    }
 }
@@ -0,0 +1,48 @@
+//! Query how much free space is available on a filesystem.
+
+use std::io;
+use std::path::Path;
+
+#[cfg(unix)]
+use unix as sys;
+#[cfg(windows)]
+use win as sys;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod win;
+
+/// Returns the number of bytes available to the current user on the filesystem containing `path`.
+///
+/// If `path` does not exist yet, the nearest existing ancestor is used instead, as that is where
+/// it would be created.
+///
+/// # Errors
+///
+/// Any I/O error from querying the filesystem.
+#[tracing::instrument(level = "debug")]
+pub(crate) fn available_space(path: &Path) -> io::Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(path);
+    sys::available_space(existing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_path_uses_existing_ancestor() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let missing = dir.path().join("does").join("not").join("exist");
+        let available = available_space(dir.path()).expect("failed to query existing path");
+        assert!(available > 0, "temporary directory should have free space");
+        assert!(
+            available_space(&missing).is_ok(),
+            "querying a path that does not exist yet should succeed"
+        );
+    }
+}
@@ -0,0 +1,11 @@
+use std::io;
+use std::path::Path;
+
+use nix::sys::statvfs::statvfs;
+
+// The field types of `statvfs` differ between platforms.
+#[allow(clippy::useless_conversion)]
+pub(super) fn available_space(path: &Path) -> io::Result<u64> {
+    let stat = statvfs(path)?;
+    Ok(u64::from(stat.blocks_available()) * u64::from(stat.fragment_size()))
+}
@@ -0,0 +1,21 @@
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+
+use windows::core::PCWSTR;
+use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+#[allow(unsafe_code)]
+pub(super) fn available_space(path: &Path) -> io::Result<u64> {
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut available = 0;
+    unsafe {
+        GetDiskFreeSpaceExW(
+            PCWSTR(path.as_ptr()),
+            Some(std::ptr::addr_of_mut!(available)),
+            None,
+            None,
+        )?;
+    }
+    Ok(available)
+}
@@ -1,10 +1,11 @@
 //! Expand environment variables inside of a path.
 
+use crate::dirs::Error as DirsError;
 use crate::paths::{Error as PathError, SystemPath};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{is_separator, PathBuf};
 use std::{env, fmt};
 
 // Following the example of `std::env::set_var`, the only things disallowed are
@@ -29,8 +30,23 @@ pub enum Error {
         /// The variable that caused the error.
         var: String,
     },
+    /// A variable written as `${VAR:?}` was not set or was empty.
+    Required {
+        /// The variable that was not set.
+        var: String,
+        /// The message given after `:?`, if any.
+        message: Option<String>,
+    },
     /// The error returned while creating a [`SystemPath`] using [`PathWithEnv`].
     Path(PathError),
+    /// A path started with `~` but the home directory could not be determined.
+    HomeDir(DirsError),
+    /// A path started with `~user`, but `user` does not exist or their home directory could not
+    /// be determined.
+    UnknownUser(String),
+    /// A path started with `~container:bundle_id`, but the container directory could not be
+    /// determined.
+    Container(DirsError),
 }
 
 impl fmt::Display for Error {
@@ -47,7 +63,22 @@ impl fmt::Display for Error {
                 ..
             } => error.fmt(f),
             // grcov: ignore-end
+            Self::Required { var, message } => {
+                write!(f, "required environment variable is not set: {var}")?;
+                match message {
+                    Some(message) => write!(f, " ({message})"),
+                    None => Ok(()),
+                }
+            }
             Self::Path(error) => write!(f, "{error}"),
+            Self::HomeDir(error) => write!(f, "failed to expand ~: {error}"),
+            Self::UnknownUser(user) => {
+                write!(
+                    f,
+                    "failed to expand ~{user}: no home directory found for {user}"
+                )
+            }
+            Self::Container(error) => write!(f, "failed to expand sandbox container path: {error}"),
         }
     }
 }
@@ -56,12 +87,54 @@ impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self {
             Error::Env { error, .. } => Some(error),
+            Error::Required { .. } | Error::UnknownUser(_) => None,
             Error::Path(error) => Some(error),
+            Error::HomeDir(error) | Error::Container(error) => Some(error),
         }
     }
 }
 
+/// Returns the value of `var`, treating an empty value the same as an unset variable.
+///
+/// Returns `Ok(None)` if the variable is unset or empty.
+fn non_empty_var(var: &str) -> Result<Option<String>, Error> {
+    match env::var(var) {
+        Ok(value) if !value.is_empty() => Ok(Some(value)),
+        Ok(_) | Err(env::VarError::NotPresent) => Ok(None),
+        Err(error) => Err(Error::Env {
+            error,
+            var: var.to_string(),
+        }),
+    }
+}
+
+/// Returns the value to substitute for the contents of a `${...}` expression.
+///
+/// Like a POSIX shell, `VAR:-default` expands to `default` and `VAR:?` is an error if `VAR`
+/// is unset or empty.
+fn expand_var(expr: &str) -> Result<String, Error> {
+    if let Some((var, default)) = expr.split_once(":-") {
+        return Ok(non_empty_var(var)?.unwrap_or_else(|| default.to_string()));
+    }
+
+    if let Some((var, message)) = expr.split_once(":?") {
+        return non_empty_var(var)?.ok_or_else(|| Error::Required {
+            var: var.to_string(),
+            message: (!message.is_empty()).then(|| message.to_string()),
+        });
+    }
+
+    env::var(expr).map_err(|error| Error::Env {
+        error,
+        var: expr.to_string(),
+    })
+}
+
 /// A [`String`] that may contain one or more environment variables to be expanded.
+///
+/// Variables are written as `${VAR}`. `${VAR:-default}` expands to `default` if `VAR` is unset
+/// or empty, and `${VAR:?}` (optionally followed by a message) returns [`Error::Required`]
+/// instead of [`Error::Env`].
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[repr(transparent)]
 #[serde(transparent)]
@@ -105,10 +178,7 @@ impl StringWithEnv {
             // (a) The context is not terribly important for the error
             // (b) This is used when parsing the configuration file, so there is no
             //     simple way to only parse the paths that apply to this system.
-            let value = env::var(var).map_err(|error| Error::Env {
-                error,
-                var: var.to_string(),
-            })?;
+            let value = expand_var(var)?;
 
             old_start = start;
             start += mat.start() + value.len();
@@ -160,20 +230,64 @@ impl fmt::Display for PathWithEnv {
     }
 }
 
+/// What follows `~` in a path that starts with a macOS sandbox container directory.
+const CONTAINER_PREFIX: &str = "container:";
+
+/// Returns the home directory of `user`, or of the current user if `user` is empty.
+fn user_home_dir(user: &str) -> Result<PathBuf, Error> {
+    if user.is_empty() {
+        return crate::dirs::try_home_dir().map_err(Error::HomeDir);
+    }
+
+    #[cfg(unix)]
+    if let Ok(Some(found)) = nix::unistd::User::from_name(user) {
+        return Ok(found.dir);
+    }
+
+    Err(Error::UnknownUser(user.to_string()))
+}
+
+/// Splits a leading `~`, `~user`, or `~container:bundle_id` off of `path`, returning the matching
+/// directory and the rest of the path.
+///
+/// Like in a POSIX shell, only a `~` at the very start of the path, followed by the end of the
+/// path or a separator, is expanded. `~container:bundle_id` expands to the `Data` directory of the
+/// macOS sandbox container for `bundle_id`, see [`crate::dirs::try_mac_container_dir`]. A `:`
+/// cannot appear in a user name, so this never conflicts with `~user`.
+fn expand_tilde(path: &str) -> Result<Option<(PathBuf, &str)>, Error> {
+    let Some(rest) = path.strip_prefix('~') else {
+        return Ok(None);
+    };
+
+    let (user, rest) = rest.split_at(rest.find(is_separator).unwrap_or(rest.len()));
+    let rest = rest.trim_start_matches(is_separator);
+    if let Some(bundle_id) = user.strip_prefix(CONTAINER_PREFIX) {
+        tracing::trace!(bundle_id, "expanding sandbox container directory in path");
+        let container = crate::dirs::try_mac_container_dir(bundle_id).map_err(Error::Container)?;
+        return Ok(Some((container, rest)));
+    }
+
+    tracing::trace!(user, "expanding home directory in path");
+    let home = user_home_dir(user)?;
+    Ok(Some((home, rest)))
+}
+
 impl PathWithEnv {
-    /// Replace any environment variables with their associated values and attempt to convert
-    /// into a [`SystemPath`].
+    /// Replace a leading `~`, `~user`, or `~container:bundle_id` with the matching directory and
+    /// any environment variables with their associated values, then attempt to convert into a [`SystemPath`].
     ///
     /// # Errors
     ///
     /// See [`Error`]
     #[tracing::instrument(level = "debug", name = "process_path_with_env")]
     pub fn process(self) -> Result<SystemPath, Error> {
-        let new_path = StringWithEnv(self.0).process()?;
+        let new_path = match expand_tilde(&self.0)? {
+            Some((home, rest)) => home.join(StringWithEnv::from(rest).process()?),
+            None => PathBuf::from(StringWithEnv(self.0).process()?),
+        };
 
         // Splitting into components and collecting will collapse multiple separators.
-        SystemPath::try_from(PathBuf::from(new_path).components().collect::<PathBuf>())
-            .map_err(Error::Path)
+        SystemPath::try_from(new_path.components().collect::<PathBuf>()).map_err(Error::Path)
     }
 }
 
@@ -333,6 +447,176 @@ mod tests {
         expected: "/test/${WRAPPING_VARIABLE}/test"
     }
 
+    #[test]
+    fn test_default_used_when_var_unset() {
+        env::remove_var("HOARD_TEST_UNSET_DEFAULT");
+        let result = StringWithEnv::from("${HOARD_TEST_UNSET_DEFAULT:-/fallback/path}/file")
+            .process()
+            .unwrap();
+        assert_eq!(result, "/fallback/path/file");
+    }
+
+    #[test]
+    fn test_default_used_when_var_empty() {
+        env::set_var("HOARD_TEST_EMPTY_DEFAULT", "");
+        let result = StringWithEnv::from("${HOARD_TEST_EMPTY_DEFAULT:-fallback}")
+            .process()
+            .unwrap();
+        assert_eq!(result, "fallback");
+    }
+
+    #[test]
+    fn test_default_ignored_when_var_set() {
+        env::set_var("HOARD_TEST_SET_DEFAULT", "/set/path");
+        let result = StringWithEnv::from("${HOARD_TEST_SET_DEFAULT:-/fallback/path}/file")
+            .process()
+            .unwrap();
+        assert_eq!(result, "/set/path/file");
+    }
+
+    #[test]
+    fn test_required_var_unset_is_error() {
+        env::remove_var("HOARD_TEST_REQUIRED");
+        let error = StringWithEnv::from("/path/${HOARD_TEST_REQUIRED:?}")
+            .process()
+            .expect_err("missing required variable should fail");
+        assert!(
+            matches!(&error, Error::Required { var, message: None } if var == "HOARD_TEST_REQUIRED")
+        );
+        assert_eq!(
+            error.to_string(),
+            "required environment variable is not set: HOARD_TEST_REQUIRED"
+        );
+
+        let error = StringWithEnv::from("${HOARD_TEST_REQUIRED:?set it first}")
+            .process()
+            .expect_err("missing required variable should fail");
+        assert_eq!(
+            error.to_string(),
+            "required environment variable is not set: HOARD_TEST_REQUIRED (set it first)"
+        );
+    }
+
+    #[test]
+    fn test_required_var_set() {
+        env::set_var("HOARD_TEST_REQUIRED_SET", "value");
+        let result = StringWithEnv::from("/${HOARD_TEST_REQUIRED_SET:?}/file")
+            .process()
+            .unwrap();
+        assert_eq!(result, "/value/file");
+    }
+
+    fn home_path(path: &str) -> SystemPath {
+        let path = crate::dirs::home_dir().join(path);
+        SystemPath::try_from(path.components().collect::<PathBuf>()).unwrap()
+    }
+
+    #[test]
+    fn test_tilde_alone_is_home() {
+        let result = PathWithEnv::from("~").process().unwrap();
+        assert_eq!(result, home_path(""));
+    }
+
+    #[test]
+    fn test_leading_tilde_is_home() {
+        let result = PathWithEnv::from("~/foo/bar").process().unwrap();
+        assert_eq!(result, home_path("foo/bar"));
+        let result = PathWithEnv::from("~//foo").process().unwrap();
+        assert_eq!(result, home_path("foo"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_tilde_same_as_home_var() {
+        let tilde = PathWithEnv::from("~/foo").process().unwrap();
+        let var = PathWithEnv::from("${HOME}/foo").process().unwrap();
+        assert_eq!(tilde, var);
+    }
+
+    #[test]
+    fn test_tilde_with_env_var() {
+        env::set_var("HOARD_TEST_TILDE_DIR", "saves");
+        let result = PathWithEnv::from("~/${HOARD_TEST_TILDE_DIR}/file")
+            .process()
+            .unwrap();
+        assert_eq!(result, home_path("saves/file"));
+    }
+
+    #[test]
+    fn test_tilde_from_env_var_is_literal() {
+        env::set_var("HOARD_TEST_TILDE_VALUE", "~");
+        let result = PathWithEnv::from("${HOARD_TEST_TILDE_VALUE}/file")
+            .process()
+            .expect_err("a relative path should not be a valid system path");
+        assert!(matches!(result, Error::Path(_)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_tilde_in_middle_is_literal() {
+        let result = PathWithEnv::from("/path/~/file").process().unwrap();
+        assert_eq!(
+            result,
+            SystemPath::try_from(PathBuf::from("/path/~/file")).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_tilde_user_is_their_home() {
+        let user = nix::unistd::User::from_uid(nix::unistd::getuid())
+            .unwrap()
+            .expect("current user should exist");
+        let result = PathWithEnv::from(format!("~{}/foo", user.name))
+            .process()
+            .unwrap();
+        assert_eq!(result, SystemPath::try_from(user.dir.join("foo")).unwrap());
+    }
+
+    #[test]
+    fn test_tilde_unknown_user_is_error() {
+        let error = PathWithEnv::from("~hoard-no-such-user/foo")
+            .process()
+            .expect_err("unknown user should fail");
+        assert!(matches!(&error, Error::UnknownUser(user) if user == "hoard-no-such-user"));
+        assert_eq!(
+            error.to_string(),
+            "failed to expand ~hoard-no-such-user: no home directory found for hoard-no-such-user"
+        );
+    }
+
+    #[test]
+    fn test_tilde_container_is_container_data_dir() {
+        let result = PathWithEnv::from("~container:com.example.game/Library/saves")
+            .process()
+            .unwrap();
+        let expected = crate::dirs::try_mac_container_dir("com.example.game")
+            .unwrap()
+            .join("Library")
+            .join("saves");
+        assert_eq!(result, SystemPath::try_from(expected).unwrap());
+        assert_eq!(
+            PathWithEnv::from("~container:com.example.game")
+                .process()
+                .unwrap(),
+            home_path("Library/Containers/com.example.game/Data")
+        );
+    }
+
+    #[test]
+    fn test_tilde_container_invalid_bundle_id_is_error() {
+        let error = PathWithEnv::from("~container:/saves")
+            .process()
+            .expect_err("empty bundle identifier should fail");
+        assert!(matches!(
+            &error,
+            Error::Container(DirsError::InvalidBundleId(id)) if id.is_empty()
+        ));
+        assert!(error
+            .to_string()
+            .starts_with("failed to expand sandbox container path: invalid bundle identifier"));
+    }
+
     #[test]
     fn test_error_traits() {
         let env_error = env::var("DOESNOTEXIST").expect_err("variable should not exist");
@@ -0,0 +1,43 @@
+//! The exit codes used by the `hoard` binary.
+//!
+//! These let scripts tell outcomes apart without parsing the output, e.g.
+//! `hoard diff my-hoard; echo $?`.
+
+/// The exit code of a `hoard` invocation.
+///
+/// [`ChangesDetected`](ExitCode::ChangesDetected) is only used by commands where finding
+/// changes is an expected outcome, namely `hoard status` and `hoard diff`. It does not mean
+/// that the command failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ExitCode {
+    /// The command succeeded. For `hoard status` and `hoard diff`, no changes were found.
+    Success = 0,
+    /// The command failed for any reason not covered by another code.
+    Error = 1,
+    /// The configuration could not be loaded, or `hoard validate` found problems with it.
+    ConfigError = 2,
+    /// `hoard status` or `hoard diff` succeeded and found changes.
+    ChangesDetected = 3,
+}
+
+impl ExitCode {
+    /// Returns the numeric code to exit the process with.
+    #[must_use]
+    pub fn code(self) -> i32 {
+        i32::from(self as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_stable() {
+        assert_eq!(ExitCode::Success.code(), 0);
+        assert_eq!(ExitCode::Error.code(), 1);
+        assert_eq!(ExitCode::ConfigError.code(), 2);
+        assert_eq!(ExitCode::ChangesDetected.code(), 3);
+    }
+}
@@ -0,0 +1,104 @@
+/// Provides a [`Filter`] based on `.gitignore` files found in the pile.
+///
+/// To use this filter, set `respect_gitignore` under `config`. For example:
+///
+/// ```ignore
+/// [config]
+///     respect_gitignore = true
+/// ```
+///
+/// As with git, each `.gitignore` applies to the directory containing it, and patterns in more
+/// deeply nested files take precedence over those closer to the pile root.
+use std::path::Path;
+
+use ignore::gitignore::GitignoreBuilder;
+use ignore::Match;
+
+use crate::hoard::PileConfig;
+use crate::paths::{RelativePath, SystemPath};
+
+use super::Filter;
+
+const GITIGNORE_FILE: &str = ".gitignore";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub(crate) struct GitignoreFilter {
+    enabled: bool,
+}
+
+impl GitignoreFilter {
+    /// Checks `path` against the `.gitignore` in `dir`, if one exists.
+    fn matches_in_dir(dir: &Path, path: &Path, is_dir: bool) -> Match<()> {
+        let gitignore_path = dir.join(GITIGNORE_FILE);
+        if !gitignore_path.is_file() {
+            return Match::None;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        if let Some(error) = builder.add(&gitignore_path) {
+            tracing::warn!(%error, "failed to parse {}", gitignore_path.display());
+        }
+        match builder.build() {
+            Ok(gitignore) => match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::None => Match::None,
+                Match::Ignore(_) => Match::Ignore(()),
+                Match::Whitelist(_) => Match::Whitelist(()),
+            },
+            Err(error) => {
+                tracing::warn!(%error, "failed to parse {}", gitignore_path.display());
+                Match::None
+            }
+        }
+    }
+}
+
+impl Filter for GitignoreFilter {
+    fn new(pile_config: &PileConfig) -> Self {
+        GitignoreFilter {
+            enabled: pile_config.respect_gitignore.unwrap_or(false),
+        }
+    }
+
+    #[tracing::instrument(name = "run_gitignore_filter", skip(self))]
+    fn keep(&self, prefix: &SystemPath, rel_path: &RelativePath) -> bool {
+        if !self.enabled {
+            return true;
+        }
+
+        let path = prefix.join(rel_path);
+        let is_dir = path.is_dir();
+        // Check the deepest `.gitignore` first, since it takes precedence.
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(prefix) {
+                break;
+            }
+
+            match Self::matches_in_dir(dir, &path, is_dir) {
+                Match::None => {}
+                Match::Ignore(()) => {
+                    tracing::trace!(
+                        ?rel_path,
+                        "ignored by {}",
+                        dir.join(GITIGNORE_FILE).display()
+                    );
+                    return false;
+                }
+                Match::Whitelist(()) => return true,
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_by_default() {
+        let filter = GitignoreFilter::new(&PileConfig::default());
+        assert_eq!(filter, GitignoreFilter::default());
+        assert!(!filter.enabled);
+    }
+}
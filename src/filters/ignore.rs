@@ -1,44 +1,104 @@
-/// Provides a [`Filter`] based on glob ignore patterns.
+/// Provides a [`Filter`] based on gitignore-style ignore patterns.
 ///
-/// To use this filter, add an list of glob patterns to `ignore` under `config`. For example:
+/// To use this filter, add an list of patterns to `ignore` under `config`. For example:
 ///
 /// ```ignore
 /// [config]
-///     ignore = ["some*glob"]
+///     ignore = ["some*glob", "/anchored", "only_dirs/", "**/nested", "!re-included"]
 /// ```
 ///
+/// Patterns follow the same rules as a `.gitignore` file at the root of the pile, including
+/// `!` negation, where the last matching pattern wins. Note that this means a pattern without a
+/// `/`, like `.hidden`, matches at any depth; prefix it with `/` to only match at the pile root.
+///
 /// This can be put under global, hoard, or pile scope.
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
 use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 
 use crate::hoard::PileConfig;
 use crate::paths::{RelativePath, SystemPath};
 
 use super::Filter;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Debug, Clone)]
 pub(crate) struct IgnoreFilter {
     globs: Vec<Pattern>,
+    matcher: Gitignore,
+}
+
+impl IgnoreFilter {
+    /// Builds a matcher that applies `globs` relative to the pile root.
+    ///
+    /// # Errors
+    ///
+    /// Any error from parsing one of `globs` as a gitignore pattern.
+    pub(crate) fn build_matcher(globs: &[Pattern]) -> Result<Gitignore, ignore::Error> {
+        let mut builder = GitignoreBuilder::new("");
+        for glob in globs {
+            builder.add_line(None, glob.as_str())?;
+        }
+        builder.build()
+    }
+}
+
+impl Default for IgnoreFilter {
+    fn default() -> Self {
+        Self {
+            globs: Vec::new(),
+            matcher: Gitignore::empty(),
+        }
+    }
+}
+
+// The matcher is derived entirely from the globs, so only compare those.
+impl PartialEq for IgnoreFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.globs == other.globs
+    }
+}
+
+impl Eq for IgnoreFilter {}
+
+impl PartialOrd for IgnoreFilter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IgnoreFilter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.globs.cmp(&other.globs)
+    }
+}
+
+impl Hash for IgnoreFilter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.globs.hash(state);
+    }
 }
 
 impl Filter for IgnoreFilter {
     fn new(pile_config: &PileConfig) -> Self {
         IgnoreFilter {
+            matcher: Self::build_matcher(&pile_config.ignore)
+                .expect("ignore patterns are checked when the config is built"),
             globs: pile_config.ignore.clone(),
         }
     }
 
-    #[tracing::instrument(name = "run_ignore_filter", skip(self, _prefix))]
-    fn keep(&self, _prefix: &SystemPath, rel_path: &RelativePath) -> bool {
-        self.globs.iter().all(|glob| {
-            let matches = glob.matches_path(&rel_path.to_path_buf());
-            tracing::trace!(
-                "{:?} {} glob {:?}",
-                rel_path,
-                if matches { "matches" } else { "does not match" },
-                glob
-            );
-            !matches
-        })
+    #[tracing::instrument(name = "run_ignore_filter", skip(self))]
+    fn keep(&self, prefix: &SystemPath, rel_path: &RelativePath) -> bool {
+        // The pile root itself is never ignored.
+        let Some(path) = rel_path.as_path() else {
+            return true;
+        };
+        let is_dir = prefix.join(rel_path).is_dir();
+        let matched = self.matcher.matched_path_or_any_parents(path, is_dir);
+        tracing::trace!("{:?} matched ignore pattern: {:?}", rel_path, matched);
+        !matched.is_ignore()
     }
 }
 
@@ -66,4 +126,71 @@ mod tests {
         assert_eq!(filter, filter.clone());
         assert_ne!(filter, other);
     }
+
+    fn ignore_filter(patterns: &[&str]) -> IgnoreFilter {
+        IgnoreFilter::new(&PileConfig {
+            ignore: patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern).unwrap())
+                .collect(),
+            ..PileConfig::default()
+        })
+    }
+
+    fn keeps(filter: &IgnoreFilter, path: &str) -> bool {
+        let prefix =
+            SystemPath::try_from(std::env::temp_dir().join("hoard-ignore-filter-test")).unwrap();
+        let rel_path = RelativePath::try_from(std::path::PathBuf::from(path)).unwrap();
+        filter.keep(&prefix, &rel_path)
+    }
+
+    #[test]
+    fn test_unanchored_matches_any_depth() {
+        let filter = ignore_filter(&["*.log"]);
+        assert!(!keeps(&filter, "top.log"));
+        assert!(!keeps(&filter, "nested/dir/deep.log"));
+        assert!(keeps(&filter, "nested/dir/deep.txt"));
+    }
+
+    #[test]
+    fn test_leading_slash_anchors_to_pile_root() {
+        let filter = ignore_filter(&["/build"]);
+        assert!(!keeps(&filter, "build"));
+        assert!(!keeps(&filter, "build/output.bin"));
+        assert!(keeps(&filter, "src/build"));
+    }
+
+    #[test]
+    fn test_double_star_matches_nested_dirs() {
+        let filter = ignore_filter(&["saves/**/*.bak"]);
+        assert!(!keeps(&filter, "saves/slot.bak"));
+        assert!(!keeps(&filter, "saves/a/b/slot.bak"));
+        assert!(keeps(&filter, "other/slot.bak"));
+    }
+
+    #[test]
+    fn test_last_match_wins() {
+        let filter = ignore_filter(&["*.cfg", "!important.cfg"]);
+        assert!(!keeps(&filter, "other.cfg"));
+        assert!(keeps(&filter, "important.cfg"));
+        assert!(keeps(&filter, "nested/important.cfg"));
+
+        let filter = ignore_filter(&["!important.cfg", "*.cfg"]);
+        assert!(!keeps(&filter, "important.cfg"));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_error() {
+        let patterns = [Pattern::new("{unclosed").unwrap()];
+        IgnoreFilter::build_matcher(&patterns).expect_err("unclosed alternation should fail");
+    }
+
+    #[test]
+    fn test_pile_root_is_kept() {
+        let filter = ignore_filter(&["*"]);
+        assert!(filter.keep(
+            &SystemPath::try_from(std::env::temp_dir()).unwrap(),
+            &RelativePath::none()
+        ));
+    }
 }
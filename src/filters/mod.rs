@@ -3,7 +3,11 @@
 use crate::hoard::PileConfig;
 use crate::paths::{RelativePath, SystemPath};
 
+pub(crate) mod gitignore;
 pub(crate) mod ignore;
+pub mod selection;
+
+pub use selection::Selection;
 
 /// The [`Filter`] trait provides a common interface for all filters.
 pub trait Filter: Sized {
@@ -21,18 +25,44 @@ pub trait Filter: Sized {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Filters {
     ignore: ignore::IgnoreFilter,
+    gitignore: gitignore::GitignoreFilter,
+    selection: Selection,
+}
+
+impl Filters {
+    /// Creates filters that only apply the [`Selection`] in `pile_config`.
+    ///
+    /// This is used for files found in operation logs, which are not subject to the configured
+    /// filters.
+    pub(crate) fn selection_only(pile_config: &PileConfig) -> Self {
+        Self {
+            selection: pile_config.selection.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Whether the item at `path` is part of the [`Selection`] for this run.
+    pub(crate) fn selects(&self, path: &RelativePath, is_dir: bool) -> bool {
+        self.selection.keep(path, is_dir)
+    }
 }
 
 impl Filter for Filters {
     #[tracing::instrument]
     fn new(pile_config: &PileConfig) -> Self {
         let ignore = ignore::IgnoreFilter::new(pile_config);
-        Self { ignore }
+        let gitignore = gitignore::GitignoreFilter::new(pile_config);
+        let selection = pile_config.selection.clone();
+        Self {
+            ignore,
+            gitignore,
+            selection,
+        }
     }
 
     #[tracing::instrument(name = "run_filters")]
     fn keep(&self, prefix: &SystemPath, path: &RelativePath) -> bool {
-        self.ignore.keep(prefix, path)
+        self.ignore.keep(prefix, path) && self.gitignore.keep(prefix, path)
     }
 }
 
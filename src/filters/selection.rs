@@ -0,0 +1,192 @@
+//! Provides a [`Selection`] of files given on the command line with `--only` and `--ignore`.
+//!
+//! Unlike the other filters, a [`Selection`] is never read from the configuration file. It only
+//! applies to a single invocation of `hoard backup` or `hoard restore`, and it also applies to
+//! files that are only known from previous operation logs.
+//!
+//! Patterns use the same gitignore-style syntax as the `ignore` pile configuration and are
+//! relative to the root of each pile.
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::paths::RelativePath;
+
+/// Files selected for a single backup or restore.
+///
+/// The same selection is set on every pile, so the patterns are shared between clones.
+#[derive(Debug, Clone, Default)]
+pub struct Selection(Arc<Patterns>);
+
+#[derive(Debug)]
+struct Patterns {
+    only: Vec<Pattern>,
+    ignore: Vec<Pattern>,
+    only_matcher: Gitignore,
+    ignore_matcher: Gitignore,
+}
+
+impl Default for Patterns {
+    fn default() -> Self {
+        Self {
+            only: Vec::new(),
+            ignore: Vec::new(),
+            only_matcher: Gitignore::empty(),
+            ignore_matcher: Gitignore::empty(),
+        }
+    }
+}
+
+impl Selection {
+    /// Create a new [`Selection`] of files that match any of the `only` patterns, if any are
+    /// given, and do not match any of the `ignore` patterns.
+    #[must_use]
+    pub fn new(only: &[Pattern], ignore: &[Pattern]) -> Self {
+        Self(Arc::new(Patterns {
+            only: only.to_vec(),
+            ignore: ignore.to_vec(),
+            only_matcher: Self::build_matcher(only),
+            ignore_matcher: Self::build_matcher(ignore),
+        }))
+    }
+
+    fn build_matcher(globs: &[Pattern]) -> Gitignore {
+        let mut builder = GitignoreBuilder::new("");
+        for glob in globs {
+            if let Err(error) = builder.add_line(None, glob.as_str()) {
+                tracing::warn!(%error, "failed to parse pattern {}", glob.as_str());
+            }
+        }
+        builder.build().unwrap_or_else(|error| {
+            tracing::warn!(%error, "failed to build patterns");
+            Gitignore::empty()
+        })
+    }
+
+    /// Returns whether this selection includes every file.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.only.is_empty() && self.0.ignore.is_empty()
+    }
+
+    /// Returns whether any `only` patterns were given.
+    #[must_use]
+    pub fn has_only(&self) -> bool {
+        !self.0.only.is_empty()
+    }
+
+    /// Whether the item at `rel_path` is selected.
+    ///
+    /// Directories that do not match any `only` pattern are still selected so that the files
+    /// inside of them can be checked.
+    pub(crate) fn keep(&self, rel_path: &RelativePath, is_dir: bool) -> bool {
+        // The pile root itself is always selected.
+        let Some(path) = rel_path.as_path() else {
+            return true;
+        };
+
+        let patterns = &self.0;
+        if patterns
+            .ignore_matcher
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+        {
+            return false;
+        }
+
+        patterns.only.is_empty()
+            || is_dir
+            || patterns
+                .only_matcher
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+    }
+}
+
+// The matchers are derived entirely from the globs, so only compare those.
+impl PartialEq for Selection {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.only == other.0.only && self.0.ignore == other.0.ignore
+    }
+}
+
+impl Eq for Selection {}
+
+impl PartialOrd for Selection {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Selection {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.0.only, &self.0.ignore).cmp(&(&other.0.only, &other.0.ignore))
+    }
+}
+
+impl Hash for Selection {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.only.hash(state);
+        self.0.ignore.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<Pattern> {
+        patterns
+            .iter()
+            .map(|pattern| Pattern::new(pattern).unwrap())
+            .collect()
+    }
+
+    fn keeps(selection: &Selection, path: &str, is_dir: bool) -> bool {
+        selection.keep(&path.parse().unwrap(), is_dir)
+    }
+
+    #[test]
+    fn test_empty_selection_keeps_everything() {
+        let selection = Selection::default();
+        assert!(selection.is_empty());
+        assert!(keeps(&selection, "some/file", false));
+        assert!(keeps(&selection, "some/dir", true));
+    }
+
+    #[test]
+    fn test_only() {
+        let selection = Selection::new(&patterns(&["saves/slot1.sav"]), &[]);
+        assert!(selection.has_only());
+        assert!(keeps(&selection, "saves/slot1.sav", false));
+        assert!(!keeps(&selection, "saves/slot2.sav", false));
+        assert!(!keeps(&selection, "settings.ini", false));
+        // Directories are searched for matching files.
+        assert!(keeps(&selection, "other", true));
+    }
+
+    #[test]
+    fn test_only_includes_files_in_matching_dirs() {
+        let selection = Selection::new(&patterns(&["saves/"]), &[]);
+        assert!(keeps(&selection, "saves/slot1.sav", false));
+        assert!(!keeps(&selection, "settings.ini", false));
+    }
+
+    #[test]
+    fn test_ignore_wins_over_only() {
+        let selection = Selection::new(&patterns(&["*.sav"]), &patterns(&["slot2.sav"]));
+        assert!(keeps(&selection, "slot1.sav", false));
+        assert!(!keeps(&selection, "slot2.sav", false));
+    }
+
+    #[test]
+    fn test_ignore_dirs() {
+        let selection = Selection::new(&[], &patterns(&["backups/"]));
+        assert!(!keeps(&selection, "backups", true));
+        assert!(!keeps(&selection, "backups/slot1.sav", false));
+        assert!(keeps(&selection, "slot1.sav", false));
+    }
+}
@@ -0,0 +1,152 @@
+//! Commands to run before and after a hoard is backed up or restored.
+//!
+//! Hooks are configured per hoard, e.g. `hooks = { pre = "steam -shutdown" }`. Each hook is run
+//! through the system shell with `HOARD_NAME` and `HOARD_DIRECTION` (`backup` or `restore`) set
+//! in its environment.
+
+use std::io;
+use std::process::{Command, ExitStatus};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::hoard::Direction;
+use crate::newtypes::HoardName;
+
+/// Errors that may occur while running a hook.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The hook command could not be started.
+    #[error("failed to run hook `{command}`: {error}")]
+    Spawn {
+        /// The hook command.
+        command: String,
+        /// Why the command could not be started.
+        #[source]
+        error: io::Error,
+    },
+    /// The hook command exited with a failure status.
+    #[error("hook `{command}` exited with failure status: {status}")]
+    Exit {
+        /// The hook command.
+        command: String,
+        /// The status the command exited with.
+        status: ExitStatus,
+    },
+}
+
+/// Commands to run around the backup or restore of a single hoard.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Run before any files in the hoard are read or changed. If it fails, the hoard is skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre: Option<String>,
+    /// Run after the files in the hoard are changed. Failures are only logged as warnings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<String>,
+}
+
+impl Hooks {
+    /// Run the `pre` hook, if any.
+    ///
+    /// # Errors
+    ///
+    /// Any [`Error`] from running the hook.
+    pub(crate) fn run_pre(&self, hoard: &HoardName, direction: Direction) -> Result<(), Error> {
+        self.pre
+            .as_deref()
+            .map_or(Ok(()), |command| run_hook(command, hoard, direction))
+    }
+
+    /// Run the `post` hook, if any, logging a warning if it fails.
+    pub(crate) fn run_post(&self, hoard: &HoardName, direction: Direction) {
+        if let Some(command) = self.post.as_deref() {
+            if let Err(error) = run_hook(command, hoard, direction) {
+                tracing::warn!(%error, "post hook for hoard {} failed", hoard);
+            }
+        }
+    }
+}
+
+#[tracing::instrument]
+fn run_hook(command: &str, hoard: &HoardName, direction: Direction) -> Result<(), Error> {
+    tracing::info!(%hoard, "running hook: {}", command);
+
+    #[cfg(windows)]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(windows))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    let status = shell
+        .arg(command)
+        .env("HOARD_NAME", hoard.as_ref())
+        .env("HOARD_DIRECTION", direction.to_string())
+        .status()
+        .map_err(|error| Error::Spawn {
+            command: command.to_string(),
+            error,
+        })?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Exit {
+            command: command.to_string(),
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hoard() -> HoardName {
+        "test_hoard".parse().unwrap()
+    }
+
+    #[test]
+    fn test_no_hooks_succeed() {
+        let hooks = Hooks::default();
+        hooks
+            .run_pre(&hoard(), Direction::Backup)
+            .expect("missing pre hook should succeed");
+        hooks.run_post(&hoard(), Direction::Backup);
+    }
+
+    #[test]
+    fn test_failing_pre_hook_is_error() {
+        let hooks = Hooks {
+            pre: Some("exit 3".to_string()),
+            post: None,
+        };
+        let error = hooks
+            .run_pre(&hoard(), Direction::Restore)
+            .expect_err("failing pre hook should return an error");
+        assert!(matches!(error, Error::Exit { status, .. } if status.code() == Some(3)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_environment() {
+        let hooks = Hooks {
+            pre: Some(
+                r#"test "$HOARD_NAME" = test_hoard && test "$HOARD_DIRECTION" = backup"#
+                    .to_string(),
+            ),
+            post: None,
+        };
+        hooks
+            .run_pre(&hoard(), Direction::Backup)
+            .expect("hook should see the hoard name and direction");
+    }
+}
@@ -1,5 +1,5 @@
-use std::collections::BTreeSet;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 
 use futures::stream::Peekable;
@@ -9,7 +9,9 @@ use tokio_stream::wrappers::ReadDirStream;
 
 use crate::checkers::history::operation::{ItemOperation, Operation, OperationImpl};
 use crate::filters::{Filter, Filters};
-use crate::hoard::Hoard;
+use crate::hoard::skipped::SkippedPaths;
+use crate::hoard::sources::Sources;
+use crate::hoard::{Hoard, Pile, PileGlob};
 use crate::hoard_item::HoardItem;
 use crate::newtypes::{HoardName, PileName};
 use crate::paths::{HoardPath, RelativePath, SystemPath};
@@ -18,6 +20,7 @@ use crate::paths::{HoardPath, RelativePath, SystemPath};
 pub(crate) struct RootPathItem {
     hoard_file: HoardItem,
     filters: Filters,
+    glob: Option<PileGlob>,
 }
 
 impl RootPathItem {
@@ -27,6 +30,12 @@ impl RootPathItem {
                 self.hoard_file.system_prefix(),
                 self.hoard_file.relative_path(),
             )
+            && self
+                .filters
+                .selects(self.hoard_file.relative_path(), self.is_dir())
+            && self.glob.as_ref().map_or(true, |glob| {
+                glob.keep(self.hoard_file.relative_path(), self.is_dir())
+            })
     }
 
     fn is_file(&self) -> bool {
@@ -40,12 +49,46 @@ impl RootPathItem {
     fn exists(&self) -> bool {
         self.hoard_file.is_file() || self.hoard_file.is_dir()
     }
+
+    /// Returns the system or hoard path of this item if it is neither a file nor a directory,
+    /// like a named pipe, socket, or device file.
+    ///
+    /// Reading from these may block forever, so they are skipped instead.
+    fn special_path(&self) -> Option<&Path> {
+        let paths: [&Path; 2] = [
+            self.hoard_file.system_path().as_ref(),
+            self.hoard_file.hoard_path(),
+        ];
+        paths.into_iter().find(|path| {
+            std::fs::metadata(path).is_ok_and(|meta| !meta.is_file() && !meta.is_dir())
+        })
+    }
+
+    /// Returns whether this item should be skipped because it is a special file, logging a
+    /// warning if so.
+    fn is_special(&self) -> bool {
+        match self.special_path() {
+            None => false,
+            Some(path) => {
+                tracing::warn!(
+                    "skipping {}: it is not a regular file or directory (e.g. a named pipe, socket, or device)",
+                    path.display()
+                );
+                true
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct AllFilesIter {
     seen_paths: BTreeSet<SystemPath>,
+    /// The canonical path of each existing pile root, keyed by the configured root.
+    canonical_roots: BTreeMap<SystemPath, PathBuf>,
+    /// Which path each file belongs to, for piles with multiple paths.
+    sources: Sources,
     root_paths: Vec<RootPathItem>,
+    skipped: SkippedPaths,
     system_entries: Option<Peekable<ReadDirStream>>,
     hoard_entries: Option<Peekable<ReadDirStream>>,
     current_root: Option<RootPathItem>,
@@ -57,47 +100,37 @@ impl AllFilesIter {
         hoard: &Hoard,
         hoard_name_root: &HoardPath,
     ) -> Result<Vec<RootPathItem>, super::Error> {
-        match hoard {
-            Hoard::Anonymous(pile) => {
-                let path = pile.path.clone();
-                let filters = Filters::new(&pile.config);
-                match path {
-                    None => Ok(Vec::default()),
-                    Some(system_prefix) => std::iter::once(Ok(RootPathItem {
-                        hoard_file: HoardItem::new(
-                            PileName::anonymous(),
-                            hoard_name_root.clone(),
-                            system_prefix,
-                            RelativePath::none(),
-                        ),
-                        filters,
-                    }))
-                    .collect(),
-                }
-            }
+        let piles: Vec<(PileName, HoardPath, &Pile)> = match hoard {
+            Hoard::Anonymous(pile) => vec![(PileName::anonymous(), hoard_name_root.clone(), pile)],
             Hoard::Named(piles) => piles
                 .piles
                 .iter()
-                .filter_map(|(name, pile)| {
-                    let filters = Filters::new(&pile.config);
-                    let name_path = RelativePath::from(name);
-                    pile.path.as_ref().map(|path| {
-                        let hoard_prefix = hoard_name_root.join(&name_path);
-                        let system_prefix = path.clone();
-
-                        Ok(RootPathItem {
-                            hoard_file: HoardItem::new(
-                                name.clone().into(),
-                                hoard_prefix,
-                                system_prefix,
-                                RelativePath::none(),
-                            ),
-                            filters,
-                        })
-                    })
+                .map(|(name, pile)| {
+                    let hoard_prefix = hoard_name_root.join(&RelativePath::from(name));
+                    (name.clone().into(), hoard_prefix, pile)
                 })
                 .collect(),
-        }
+        };
+
+        // Every path of a pile with multiple paths shares the same hoard prefix.
+        let items = piles
+            .into_iter()
+            .flat_map(|(pile_name, hoard_prefix, pile)| {
+                let filters = Filters::new(&pile.config);
+                pile.source_paths().map(move |system_prefix| RootPathItem {
+                    hoard_file: HoardItem::new(
+                        pile_name.clone(),
+                        hoard_prefix.clone(),
+                        system_prefix.clone(),
+                        RelativePath::none(),
+                    ),
+                    filters: filters.clone(),
+                    glob: pile.glob.clone(),
+                })
+            })
+            .collect();
+
+        Ok(items)
     }
 
     #[tracing::instrument]
@@ -147,9 +180,18 @@ impl AllFilesIter {
 
         let list = from_logs
             .into_iter()
-            .map(|item| RootPathItem {
-                hoard_file: item.into_inner(),
-                filters: Filters::default(),
+            .map(|item| {
+                let hoard_file = item.into_inner();
+                // Only the selection for this run applies to files that were already logged.
+                let filters = hoard
+                    .get_pile(hoard_file.pile_name())
+                    .map(|pile| Filters::selection_only(&pile.config))
+                    .unwrap_or_default();
+                RootPathItem {
+                    hoard_file,
+                    filters,
+                    glob: None,
+                }
             })
             .collect::<Vec<_>>();
 
@@ -161,6 +203,7 @@ impl AllFilesIter {
         hoards_root: &HoardPath,
         hoard_name: &HoardName,
         hoard: &Hoard,
+        options: &super::ScanOptions,
     ) -> Result<Self, super::Error> {
         let hoard_name_root = hoards_root.join(&RelativePath::from(hoard_name));
         let mut root_paths = Self::paths_from_hoard(hoard, &hoard_name_root)?;
@@ -171,9 +214,44 @@ impl AllFilesIter {
         root_paths.dedup();
         tracing::trace!(?root_paths);
 
+        // Resolve symlinks in each pile root once, so every path found under that root can be
+        // checked against it. Roots that do not exist have nothing to escape from.
+        let mut canonical_roots = BTreeMap::new();
+        let mut skipped_roots = BTreeSet::new();
+        for item in &root_paths {
+            let system_prefix = item.hoard_file.system_prefix();
+            if canonical_roots.contains_key(system_prefix) || skipped_roots.contains(system_prefix)
+            {
+                continue;
+            }
+            match fs::canonicalize(system_prefix).await {
+                Ok(canonical) => {
+                    canonical_roots.insert(system_prefix.clone(), canonical);
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                // Nothing under an unreadable root can be told apart from a deleted file.
+                Err(err) if options.skipped.skip(system_prefix, &err) => {
+                    skipped_roots.insert(system_prefix.clone());
+                }
+                Err(err) => {
+                    return crate::create_log_error_msg(
+                        &format!("failed to resolve pile root {}", system_prefix.display()),
+                        err,
+                    )
+                    .map_err(super::Error::IO);
+                }
+            }
+        }
+
+        root_paths.retain(|item| !skipped_roots.contains(item.hoard_file.system_prefix()));
+        let sources = Sources::load(hoard_name, hoard).await?;
+
         Ok(Self {
             seen_paths: BTreeSet::new(),
+            canonical_roots,
+            sources,
             root_paths,
+            skipped: options.skipped.clone(),
             system_entries: None,
             hoard_entries: None,
             current_root: None,
@@ -198,6 +276,60 @@ impl AllFilesIter {
         false
     }
 
+    /// Returns whether the system path of `item` resolves to a location inside of its pile root,
+    /// following any symlinks on the way.
+    ///
+    /// Paths that do not exist on the system are always considered inside of the root.
+    async fn is_within_root(&self, item: &HoardItem) -> io::Result<bool> {
+        let Some(root) = self.canonical_roots.get(item.system_prefix()) else {
+            return Ok(true);
+        };
+
+        match fs::canonicalize(item.system_path()).await {
+            Ok(canonical) if canonical.starts_with(root) => Ok(true),
+            Ok(canonical) => {
+                tracing::warn!(
+                    "skipping {}: it resolves to {}, which is outside of {}",
+                    item.system_path().display(),
+                    canonical.display(),
+                    item.system_prefix().display(),
+                );
+                Ok(false)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(err) if self.skipped.skip(item.system_path(), &err) => Ok(false),
+            Err(err) => crate::create_log_error_msg(
+                &format!("failed to resolve {}", item.system_path().display()),
+                err,
+            ),
+        }
+    }
+
+    /// Returns whether both the system and hoard paths of `item` can be inspected, skipping
+    /// `item` if permission to do so is denied.
+    ///
+    /// Otherwise, such a file would look like it does not exist and be deleted from the other
+    /// side.
+    fn is_accessible(&self, item: &HoardItem) -> io::Result<bool> {
+        let paths: [&Path; 2] = [item.system_path().as_ref(), item.hoard_path().as_ref()];
+        for path in paths {
+            match std::fs::metadata(path) {
+                Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                    if !self.skipped.skip(path, &err) {
+                        return crate::create_log_error_msg(
+                            &format!("failed to read metadata of {}", path.display()),
+                            err,
+                        );
+                    }
+                    return Ok(false);
+                }
+                Ok(_) | Err(_) => {}
+            }
+        }
+
+        Ok(true)
+    }
+
     fn has_seen_path(&mut self, path: &SystemPath) -> bool {
         if self.seen_paths.contains(path) {
             true
@@ -240,38 +372,42 @@ impl AllFilesIter {
 
     #[tracing::instrument]
     async fn get_next_relative_path(&mut self) -> io::Result<Option<RelativePath>> {
-        match self.get_next_entry_with_prefix().await {
-            None => Ok(None),
-            Some((Ok(entry), prefix)) => {
-                let rel_path = RelativePath::try_from(
-                    entry
-                        .path()
-                        .strip_prefix(prefix)
-                        .expect("prefix should always match path")
-                        .to_path_buf(),
-                )
-                .expect("path created with strip_prefix should always be valid RelativePath");
-                Ok(Some(rel_path))
-            }
-            Some((Err(error), prefix)) => {
-                let rel_path = self
-                    .current_root
-                    .as_ref()
-                    .unwrap()
-                    .hoard_file
-                    .relative_path()
-                    .to_path_buf();
-                let path = prefix.join(rel_path);
-                crate::create_log_error_msg(
-                    &format!("could not process entry in {}", path.display(),),
-                    error,
-                )
+        loop {
+            match self.get_next_entry_with_prefix().await {
+                None => return Ok(None),
+                Some((Ok(entry), prefix)) => {
+                    let rel_path = RelativePath::try_from(
+                        entry
+                            .path()
+                            .strip_prefix(prefix)
+                            .expect("prefix should always match path")
+                            .to_path_buf(),
+                    )
+                    .expect("path created with strip_prefix should always be valid RelativePath");
+                    return Ok(Some(rel_path));
+                }
+                Some((Err(error), prefix)) => {
+                    let rel_path = self
+                        .current_root
+                        .as_ref()
+                        .unwrap()
+                        .hoard_file
+                        .relative_path()
+                        .to_path_buf();
+                    let path = prefix.join(rel_path);
+                    if !self.skipped.skip(&path, &error) {
+                        return crate::create_log_error_msg(
+                            &format!("could not process entry in {}", path.display()),
+                            error,
+                        );
+                    }
+                }
             }
         }
     }
 
     #[tracing::instrument]
-    async fn process_dir_entry(&mut self) -> Result<Option<HoardItem>, io::Error> {
+    async fn process_dir_entry(&mut self) -> Result<Option<HoardItem>, super::Error> {
         let current_root = self
             .current_root
             .as_ref()
@@ -281,6 +417,7 @@ impl AllFilesIter {
         let system_prefix = current_root.hoard_file.system_prefix().clone();
         let hoard_prefix = current_root.hoard_file.hoard_prefix().clone();
         let filters = current_root.filters.clone();
+        let glob = current_root.glob.clone();
 
         loop {
             match self.get_next_relative_path().await? {
@@ -292,17 +429,36 @@ impl AllFilesIter {
                         system_prefix.clone(),
                         relative_path,
                     );
+                    if !self.is_accessible(&hoard_item)? {
+                        continue;
+                    }
+                    // Directories are walked under every path, but files only under their own.
+                    let hoard_item = if hoard_item.is_dir() {
+                        hoard_item
+                    } else {
+                        self.sources.assign(hoard_item)?
+                    };
 
                     if hoard_item.is_file() && self.has_seen_path(hoard_item.system_path()) {
                         tracing::trace!(item=?hoard_item, "ignoring");
                         continue;
                     }
 
+                    // Directories are checked when they are taken from `root_paths`.
+                    if hoard_item.is_file() && !self.is_within_root(&hoard_item).await? {
+                        continue;
+                    }
+
                     let new_item = RootPathItem {
                         hoard_file: hoard_item,
                         filters: filters.clone(),
+                        glob: glob.clone(),
                     };
 
+                    if new_item.is_special() {
+                        continue;
+                    }
+
                     if new_item.keep() {
                         if new_item.is_dir() {
                             self.root_paths.push(new_item);
@@ -320,57 +476,72 @@ impl AllFilesIter {
 
     #[allow(clippy::option_option)]
     #[tracing::instrument]
-    async fn ensure_dir_entries(&mut self) -> Option<Option<io::Result<HoardItem>>> {
+    async fn ensure_dir_entries(&mut self) -> Option<Option<Result<HoardItem, super::Error>>> {
         // Attempt to create direntry iterator.
         // If a path to a file is encountered, return that.
         // Otherwise, continue until existing directory is found.
         while !self.has_dir_entries().await {
             match self.root_paths.pop() {
                 None => return Some(None),
-                Some(item) => {
+                Some(mut item) => {
+                    match self.is_accessible(&item.hoard_file) {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(err) => return Some(Some(Err(err.into()))),
+                    }
+                    if !item.is_dir() {
+                        match self.sources.assign(item.hoard_file) {
+                            Ok(file) => item.hoard_file = file,
+                            Err(err) => return Some(Some(Err(err.into()))),
+                        }
+                    }
+                    match self.is_within_root(&item.hoard_file).await {
+                        Ok(true) => {}
+                        Ok(false) => continue,
+                        Err(err) => return Some(Some(Err(err.into()))),
+                    }
+                    if item.is_special() {
+                        continue;
+                    }
                     if item.keep() {
                         if item.is_dir() {
-                            let hoard_path = item.hoard_file.hoard_path();
-                            let system_path = item.hoard_file.system_path();
-                            match fs::read_dir(system_path).await {
-                                Ok(iter) => {
-                                    self.system_entries = Some(ReadDirStream::new(iter).peekable());
-                                }
-                                Err(err) => {
-                                    if err.kind() == io::ErrorKind::NotFound {
-                                        self.system_entries = None;
-                                    } else {
-                                        return Some(Some(crate::create_log_error_msg(
-                                            &format!(
-                                                "failed to read directory {}",
-                                                system_path.display(),
-                                            ),
-                                            err,
-                                        )));
+                            let system_path = item.hoard_file.system_path().as_ref();
+                            let hoard_path = item.hoard_file.hoard_path().as_ref();
+                            let (system_entries, hoard_entries) =
+                                match (read_dir(system_path).await, read_dir(hoard_path).await) {
+                                    (Ok(system_entries), Ok(hoard_entries)) => {
+                                        (system_entries, hoard_entries)
                                     }
-                                }
-                            }
-                            match fs::read_dir(hoard_path).await {
-                                Ok(iter) => {
-                                    self.hoard_entries = Some(ReadDirStream::new(iter).peekable());
-                                }
-                                Err(err) => {
-                                    if err.kind() == io::ErrorKind::NotFound {
-                                        self.hoard_entries = None;
-                                    } else {
-                                        return Some(Some(crate::create_log_error_msg(
-                                            &format!(
-                                                "failed to read directory {}",
-                                                hoard_path.display(),
-                                            ),
-                                            err,
-                                        )));
+                                    // Skip the whole directory, so that files on one side are
+                                    // not mistaken for having been deleted from the other.
+                                    (Err((path, err)), _) | (_, Err((path, err)))
+                                        if self.skipped.skip(path, &err) =>
+                                    {
+                                        continue;
                                     }
-                                }
-                            }
+                                    (Err((path, err)), _) | (_, Err((path, err))) => {
+                                        return Some(Some(
+                                            crate::create_log_error_msg(
+                                                &format!(
+                                                    "failed to read directory {}",
+                                                    path.display(),
+                                                ),
+                                                err,
+                                            )
+                                            .map_err(Into::into),
+                                        ));
+                                    }
+                                };
+                            self.system_entries = system_entries;
+                            self.hoard_entries = hoard_entries;
                             self.current_root = Some(item);
                         } else {
-                            return Some(Some(Ok(item.hoard_file)));
+                            let file = item.hoard_file;
+                            // The same file may be logged and also found under another path.
+                            if self.has_seen_path(file.system_path()) {
+                                continue;
+                            }
+                            return Some(Some(Ok(file)));
                         }
                     }
                 }
@@ -381,7 +552,7 @@ impl AllFilesIter {
     }
 
     #[tracing::instrument]
-    async fn next_item(&mut self) -> Option<io::Result<HoardItem>> {
+    async fn next_item(&mut self) -> Option<Result<HoardItem, super::Error>> {
         loop {
             if let Some(return_value) = self.ensure_dir_entries().await {
                 match return_value.as_ref() {
@@ -402,8 +573,20 @@ impl AllFilesIter {
     }
 }
 
+/// Starts reading the directory at `path`, returning `None` if it does not exist.
+async fn read_dir(path: &Path) -> Result<Option<Peekable<ReadDirStream>>, (&Path, io::Error)> {
+    match fs::read_dir(path).await {
+        Ok(iter) => Ok(Some(ReadDirStream::new(iter).peekable())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err((path, err)),
+    }
+}
+
 /// A [`TryStream`] of all managed files under the given [`Hoard`].
 ///
+/// Files and directories that cannot be read are skipped and recorded in `options`, unless it is
+/// strict.
+///
 /// # Errors
 ///
 /// Any errors that may occur while building the stream. See [`Error`](enum@super::Error) for more.
@@ -413,8 +596,9 @@ pub async fn all_files_stream(
     hoards_root: &HoardPath,
     hoard_name: &HoardName,
     hoard: &Hoard,
+    options: &super::ScanOptions,
 ) -> Result<impl TryStream<Ok = HoardItem, Error = super::Error>, super::Error> {
-    let mut all_files = AllFilesIter::new(hoards_root, hoard_name, hoard).await?;
+    let mut all_files = AllFilesIter::new(hoards_root, hoard_name, hoard, options).await?;
     let stream = async_stream::try_stream! {
         while let Some(item) = all_files.next_item().await {
             yield item?;
@@ -1,29 +1,37 @@
 #![allow(unused)]
 
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::Permissions;
+use std::path::Path;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use futures::{TryStream, TryStreamExt};
+use serde::Serialize;
 use tokio::io;
 use tokio_stream::{Iter, Stream, StreamExt};
 use tracing::trace_span;
 
 use crate::checkers::history::operation::{Operation, OperationImpl, OperationType};
-use crate::checksum::Checksum;
+use crate::checksum::cache::ChecksumCache;
+use crate::checksum::{Checksum, MD5};
 use crate::diff::Diff;
 use crate::hoard::iter::Error;
-use crate::hoard::Hoard;
+use crate::hoard::pile_config::{FileSize, LockedFileAction};
+use crate::hoard::{Hoard, PileConfig};
 use crate::hoard_item::{CachedHoardItem, HoardItem};
-use crate::newtypes::HoardName;
+use crate::newtypes::{HoardName, PileName};
 use crate::paths::HoardPath;
+use crate::retry::retry;
 
 use super::all_files::all_files_stream;
 
 /// Indicates where a given change originated from.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DiffSource {
     /// The local machine.
     Local,
@@ -91,10 +99,17 @@ pub enum HoardFileDiff {
         /// The source of the change.
         diff_source: DiffSource,
     },
+    /// The hoard file does not match the checksum recorded by the latest operation and is a
+    /// truncated copy of the system file, likely left behind by an interrupted backup.
+    Corrupt(CachedHoardItem),
     /// A file is unchanged.
     Unchanged(CachedHoardItem),
     /// A file or path is directly listed in the configuration but does not exist anywhere.
     Nonexistent(CachedHoardItem),
+    /// A file is larger than the pile's `max_file_size` and was skipped without being read.
+    Excluded(CachedHoardItem),
+    /// A file's content matches one of the pile's `ignore_checksums` and was skipped.
+    Ignored(CachedHoardItem),
 }
 
 impl fmt::Display for HoardFileDiff {
@@ -132,6 +147,9 @@ impl fmt::Display for HoardFileDiff {
                 diff_source,
                 file.system_path().display()
             ),
+            HoardFileDiff::Corrupt(file) => {
+                write!(f, "Corrupt {{ file: {} }}", file.system_path().display())
+            }
             HoardFileDiff::Unchanged(file) => {
                 write!(f, "Unchanged {{ file: {} }}", file.system_path().display())
             }
@@ -140,6 +158,72 @@ impl fmt::Display for HoardFileDiff {
                 "Nonexistent {{ file: {} }}",
                 file.system_path().display()
             ),
+            HoardFileDiff::Excluded(file) => {
+                write!(f, "Excluded {{ file: {} }}", file.system_path().display())
+            }
+            HoardFileDiff::Ignored(file) => {
+                write!(f, "Ignored {{ file: {} }}", file.system_path().display())
+            }
+        }
+    }
+}
+
+impl HoardFileDiff {
+    /// Treats any content changes as no change, as long as the file exists in both the hoard
+    /// and on the system.
+    fn without_content_changes(self) -> Self {
+        match self {
+            HoardFileDiff::BinaryModified { file, .. }
+            | HoardFileDiff::TextModified { file, .. }
+            | HoardFileDiff::Created { file, .. }
+                if file.hoard_path().is_file() && file.system_path().is_file() =>
+            {
+                tracing::debug!(
+                    "ignoring content changes to {}",
+                    file.system_path().display()
+                );
+                HoardFileDiff::Unchanged(file)
+            }
+            diff => diff,
+        }
+    }
+
+    /// Marks changes attributed to a remote system as having an unknown source.
+    fn with_unknown_remote_source(self) -> Self {
+        match self {
+            HoardFileDiff::BinaryModified {
+                file,
+                diff_source: DiffSource::Remote,
+            } => HoardFileDiff::BinaryModified {
+                file,
+                diff_source: DiffSource::Unknown,
+            },
+            HoardFileDiff::TextModified {
+                file,
+                unified_diff,
+                diff_source: DiffSource::Remote,
+            } => HoardFileDiff::TextModified {
+                file,
+                unified_diff,
+                diff_source: DiffSource::Unknown,
+            },
+            HoardFileDiff::Created {
+                file,
+                unified_diff,
+                diff_source: DiffSource::Remote,
+            } => HoardFileDiff::Created {
+                file,
+                unified_diff,
+                diff_source: DiffSource::Unknown,
+            },
+            HoardFileDiff::Deleted {
+                file,
+                diff_source: DiffSource::Remote,
+            } => HoardFileDiff::Deleted {
+                file,
+                diff_source: DiffSource::Unknown,
+            },
+            diff => diff,
         }
     }
 }
@@ -155,11 +239,19 @@ struct ProcessedFile {
     expected_system_checksum: Option<Checksum>,
     latest_local_log: Option<Operation>,
     latest_remote_log: Option<Operation>,
+    remote_log_is_skewed: bool,
+    /// Whether this system moved the file into the hoard with `--move`.
+    archived: bool,
 }
 
 impl ProcessedFile {
     #[tracing::instrument(name = "process_file", fields(file = %file.system_path().display()))]
-    async fn process(hoard_name: &HoardName, file: CachedHoardItem) -> Result<Self, Error> {
+    async fn process(
+        hoard_name: &HoardName,
+        file: CachedHoardItem,
+        older_format_ok: bool,
+        max_clock_skew: u64,
+    ) -> Result<Self, Error> {
         let _span = tracing::trace_span!("processing_file", hoard=%hoard_name, ?file).entered();
         let diff = file.diff().cloned();
 
@@ -167,7 +259,7 @@ impl ProcessedFile {
             Operation::latest_local(hoard_name, Some((file.pile_name(), file.relative_path())))
                 .await
                 .map_err(Box::new)?
-                .map(Operation::into_latest_version)
+                .map(|op| op.into_readable_version(older_format_ok))
                 .transpose()
                 .map_err(Box::new)?;
         let latest_remote_log = Operation::latest_remote_backup(
@@ -177,10 +269,25 @@ impl ProcessedFile {
         )
         .await
         .map_err(Box::new)?
-        .map(Operation::into_latest_version)
+        .map(|op| op.into_readable_version(older_format_ok))
         .transpose()
         .map_err(Box::new)?;
 
+        let remote_log_is_skewed = match latest_remote_log
+            .as_ref()
+            .and_then(|log| log.clock_skew(max_clock_skew))
+        {
+            Some(ahead) => {
+                tracing::warn!(
+                    "latest remote change to {} was logged {} seconds in the future -- system clocks may be out of sync",
+                    file.system_path().display(),
+                    ahead.whole_seconds()
+                );
+                true
+            }
+            None => false,
+        };
+
         let (latest_op, local_log_is_latest) =
             match (latest_local_log.as_ref(), latest_remote_log.as_ref()) {
                 (None, None) => (None, false),
@@ -218,6 +325,9 @@ impl ProcessedFile {
         };
 
         let system_checksum = file.system_checksum(system_checksum_type);
+        let archived = latest_local_log
+            .as_ref()
+            .is_some_and(|op| op.is_archived(file.pile_name(), file.relative_path()));
 
         Ok(Self {
             file,
@@ -229,6 +339,8 @@ impl ProcessedFile {
             expected_system_checksum,
             latest_local_log,
             latest_remote_log,
+            remote_log_is_skewed,
+            archived,
         })
     }
 
@@ -240,12 +352,22 @@ impl ProcessedFile {
         let remote_op_type = self.remote_op_type();
         let unexpected_op_type = self.unexpected_hoard_op();
         let has_logs = self.latest_remote_log.is_some() || self.latest_local_log.is_some();
+        let remote_log_is_skewed = self.remote_log_is_skewed;
+        let archived = self.archived;
 
         let file = self.file.clone();
         drop(span);
 
         let _span = tracing::debug_span!("calculate_diff", %has_logs, ?local_op_type, ?remote_op_type, diff=?self.diff, file=?file.system_path()).entered();
 
+        if matches!(unexpected_op_type, Some(OperationType::Modify)) && file.hoard_is_truncated() {
+            tracing::debug!(
+                "hoard file {} does not match its recorded checksum and appears to be partially written",
+                file.hoard_path().display()
+            );
+            return HoardFileDiff::Corrupt(file);
+        }
+
         #[allow(clippy::match_same_arms)]
         let diff = match (
             has_logs,
@@ -312,6 +434,10 @@ impl ProcessedFile {
                     HoardFileDiff::Nonexistent(file)
                 }
             }
+            // Only the hoard has a copy of a file moved into it with `--move`.
+            (true, None, None, None, Some(Diff::SystemNotExists)) if archived => {
+                HoardFileDiff::Unchanged(file)
+            }
             (_, None, None, None, Some(_)) => {
                 unreachable!("cannot have a diff if there are no changes");
             }
@@ -648,6 +774,14 @@ impl ProcessedFile {
                 unreachable!("should have detected unexpected hoard file creation");
             }
         };
+
+        // A remote change is only known to be the latest because of its timestamp, which cannot
+        // be trusted if the remote clock is ahead of this one.
+        let diff = if remote_log_is_skewed {
+            diff.with_unknown_remote_source()
+        } else {
+            diff
+        };
         tracing::debug!(%diff, "found diff");
         diff
     }
@@ -668,6 +802,8 @@ impl ProcessedFile {
             self.system_checksum.as_ref(),
         ) {
             (None, None) => None,
+            // An archived file is not expected to be on the system.
+            (Some(_), None) if self.archived => None,
             (None, Some(_)) => Some(OperationType::Create),
             (Some(_), None) => Some(OperationType::Delete),
             (Some(expected), Some(current)) => {
@@ -695,8 +831,188 @@ impl ProcessedFile {
     }
 }
 
+/// Returns the size of the larger of the system and hoard files if it is greater than `max_size`.
+///
+/// Only file metadata is read, so oversized files are never hashed.
+async fn oversized_file_size(file: &HoardItem, max_size: FileSize) -> Result<Option<u64>, Error> {
+    let mut largest = 0;
+    let paths: [&Path; 2] = [file.system_path().as_ref(), file.hoard_path().as_ref()];
+    for path in paths {
+        match tokio::fs::metadata(path).await {
+            Ok(metadata) if metadata.is_file() => largest = largest.max(metadata.len()),
+            Ok(_) => {}
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(Error::IO(error)),
+        }
+    }
+    Ok((largest > max_size.as_bytes()).then_some(largest))
+}
+
+/// Returns whether the system or hoard copy of `file` has one of the MD5 checksums in
+/// `blocklist`.
+///
+/// Nothing is read if `blocklist` is empty. Copies that cannot be read are treated as not
+/// matching, leaving the error to be reported when the file is hashed as usual.
+async fn has_ignored_checksum(file: &HoardItem, blocklist: &[MD5]) -> bool {
+    if blocklist.is_empty() {
+        return false;
+    }
+
+    for checksum in [file.system_md5().await, file.hoard_md5().await] {
+        match checksum {
+            Ok(Some(Checksum::MD5(md5))) if blocklist.contains(&md5) => return true,
+            Ok(_) => {}
+            Err(error) => tracing::debug!(
+                %error,
+                "failed to hash {} to check ignore_checksums",
+                file.system_path().display()
+            ),
+        }
+    }
+    false
+}
+
+/// Like [`diff_stream`], but does not apply the pile's `checksum_ignore` configuration.
+///
+/// This is used to determine which operations to perform, so content changes to
+/// checksum-ignored files are still synchronized.
+#[tracing::instrument]
+pub(super) async fn raw_diff_stream(
+    hoards_root: &HoardPath,
+    hoard_name: HoardName,
+    hoard: &Hoard,
+    older_format_ok: bool,
+    options: &super::ScanOptions,
+) -> Result<impl TryStream<Ok = HoardFileDiff, Error = Error>, Error> {
+    tracing::trace!("creating new diff stream");
+    let configs: HashMap<PileName, PileConfig> = hoard
+        .get_paths(hoards_root.clone())
+        .filter_map(|(pile_name, _, _)| {
+            let config = hoard.get_pile(&pile_name)?.config.clone();
+            Some((pile_name, config))
+        })
+        .collect();
+    let files: Vec<HoardItem> = all_files_stream(hoards_root, &hoard_name, hoard, options)
+        .await?
+        .try_collect()
+        .await?;
+    let concurrency = options.hashing.concurrency(files.len());
+    tracing::debug!(
+        "hashing {} files with up to {} at a time",
+        files.len(),
+        concurrency
+    );
+    let cache = if options.hashing.use_cache {
+        Some(Arc::new(ChecksumCache::load().await?))
+    } else {
+        None
+    };
+    let saved_cache = cache.clone();
+    let skipped = options.skipped.clone();
+    let retry_policy = options.retry;
+    let max_clock_skew = options.max_clock_skew;
+    let stream = futures::StreamExt::buffered(
+        futures::stream::iter(files).map(move |file| {
+            let config = configs.get(file.pile_name()).cloned().unwrap_or_default();
+            let hoard_name = hoard_name.clone();
+            let cache = cache.clone();
+            let skipped = skipped.clone();
+            async move {
+                if let Some(max_size) = config.max_file_size {
+                    if let Some(size) = oversized_file_size(&file, max_size).await? {
+                        tracing::warn!(
+                            "skipping {}: it is {} bytes, which is larger than the max_file_size of {}",
+                            file.system_path().display(),
+                            size,
+                            max_size
+                        );
+                        return Ok(Some(HoardFileDiff::Excluded(
+                            CachedHoardItem::without_content(file),
+                        )));
+                    }
+                }
+
+                if has_ignored_checksum(&file, &config.ignore_checksums).await {
+                    tracing::info!(
+                        "ignoring {}: its content matches one of the pile's ignore_checksums",
+                        file.system_path().display()
+                    );
+                    return Ok(Some(HoardFileDiff::Ignored(
+                        CachedHoardItem::without_content(file),
+                    )));
+                }
+
+                let policy = config.locked_files.unwrap_or_default();
+                let mut attempts = 0;
+                let file = loop {
+                    let (file, config, cache) = (&file, &config, &cache);
+                    let result = retry(file.system_path(), retry_policy, || async move {
+                        match cache {
+                            Some(cache) => {
+                                CachedHoardItem::try_from_hoard_item_with_cache(
+                                    file.clone(),
+                                    config,
+                                    cache,
+                                )
+                                .await
+                            }
+                            None => {
+                                CachedHoardItem::try_from_hoard_item_with_config(
+                                    file.clone(),
+                                    config,
+                                )
+                                .await
+                            }
+                        }
+                    })
+                    .await;
+                    match result {
+                        Ok(file) => break file,
+                        Err(error) => {
+                            match policy
+                                .handle_error(file.system_path(), &error, &mut attempts)
+                                .await
+                            {
+                                LockedFileAction::Skip => return Ok(None),
+                                LockedFileAction::Retry => {}
+                                LockedFileAction::Fail => {
+                                    if skipped.skip(file.system_path(), &error) {
+                                        return Ok(None);
+                                    }
+                                    return Err(Error::IO(error));
+                                }
+                            }
+                        }
+                    }
+                };
+                let _span = trace_span!("diff_iterator_next", ?file);
+                let processed: ProcessedFile =
+                    ProcessedFile::process(&hoard_name, file, older_format_ok, max_clock_skew).await?;
+                Ok(Some(processed.get_hoard_diff()))
+            }
+        }),
+        concurrency,
+    )
+    // Once every file has been processed, save the checksums that were cached along the way.
+    .chain(futures::stream::once(async move {
+        if let Some(cache) = saved_cache {
+            cache.save().await?;
+        }
+        Ok(None)
+    }))
+    .try_filter_map(futures::future::ok);
+
+    Ok(stream)
+}
+
 /// A [`TryStream`] returning a [`HoardFileDiff`] for every Hoard-managed file in the given hoard.
 ///
+/// Files matching a pile's `checksum_ignore` patterns are reported as
+/// [`HoardFileDiff::Unchanged`] if they exist in both the hoard and on the system.
+///
+/// If `older_format_ok` is `true`, operation logs in an older format are used with a warning
+/// instead of returning an error.
+///
 /// # Errors
 ///
 /// Any errors that may occur while creating the stream.
@@ -705,18 +1021,42 @@ pub async fn diff_stream(
     hoards_root: &HoardPath,
     hoard_name: HoardName,
     hoard: &Hoard,
+    older_format_ok: bool,
+    options: &super::ScanOptions,
 ) -> Result<impl TryStream<Ok = HoardFileDiff, Error = Error>, Error> {
-    tracing::trace!("creating new diff stream");
-    let stream = all_files_stream(hoards_root, &hoard_name, hoard)
+    let checksum_ignore: HashMap<PileName, Vec<glob::Pattern>> = hoard
+        .get_paths(hoards_root.clone())
+        .filter_map(|(pile_name, _, _)| {
+            let patterns = hoard.get_pile(&pile_name)?.config.checksum_ignore.clone();
+            (!patterns.is_empty()).then_some((pile_name, patterns))
+        })
+        .collect();
+
+    let stream = raw_diff_stream(hoards_root, hoard_name, hoard, older_format_ok, options)
         .await?
-        .map_ok(move |file| (file, hoard_name.clone()))
-        .and_then(|(file, hoard_name)| async move {
-            let file = CachedHoardItem::try_from_hoard_item(file)
-                .await
-                .map_err(Error::IO)?;
-            let _span = trace_span!("diff_iterator_next", ?file);
-            let processed: ProcessedFile = ProcessedFile::process(&hoard_name, file).await?;
-            Ok(processed.get_hoard_diff())
+        .map_ok(move |diff| {
+            let file = match &diff {
+                HoardFileDiff::BinaryModified { file, .. }
+                | HoardFileDiff::TextModified { file, .. }
+                | HoardFileDiff::Created { file, .. }
+                | HoardFileDiff::Deleted { file, .. }
+                | HoardFileDiff::Corrupt(file)
+                | HoardFileDiff::Unchanged(file)
+                | HoardFileDiff::Nonexistent(file)
+                | HoardFileDiff::Excluded(file)
+                | HoardFileDiff::Ignored(file) => file,
+            };
+            let is_ignored = checksum_ignore
+                .get(file.pile_name())
+                .is_some_and(|patterns| {
+                    let rel_path = file.relative_path().to_path_buf();
+                    patterns.iter().any(|glob| glob.matches_path(&rel_path))
+                });
+            if is_ignored {
+                diff.without_content_changes()
+            } else {
+                diff
+            }
         });
 
     Ok(stream)
@@ -732,8 +1072,10 @@ pub async fn changed_diff_only_stream(
     hoards_root: &HoardPath,
     hoard_name: HoardName,
     hoard: &Hoard,
+    older_format_ok: bool,
+    options: &super::ScanOptions,
 ) -> Result<impl Stream<Item = Result<HoardFileDiff, Error>>, Error> {
-    let stream = diff_stream(hoards_root, hoard_name, hoard).await?;
+    let stream = diff_stream(hoards_root, hoard_name, hoard, older_format_ok, options).await?;
     let stream = stream.try_filter_map(|item| async move {
         match item {
             HoardFileDiff::Unchanged(_) | HoardFileDiff::Nonexistent(_) => Ok(None),
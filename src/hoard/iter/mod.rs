@@ -6,12 +6,44 @@ pub use all_files::all_files_stream;
 pub use diff_files::{changed_diff_only_stream, diff_stream, DiffSource, HoardFileDiff};
 pub use operation::operation_stream;
 
-use crate::checkers::history::operation::Error as OperationError;
+use crate::checkers::history::operation::{Error as OperationError, DEFAULT_MAX_CLOCK_SKEW};
+use crate::checksum::HashOptions;
+use crate::hoard::skipped::SkippedPaths;
+use crate::hoard::sources::Error as SourcesError;
+use crate::paths::HoardPath;
+use crate::retry::RetryPolicy;
 
 mod all_files;
 mod diff_files;
 mod operation;
 
+/// Options for finding and reading the files of a hoard.
+///
+/// Clones share the same [`SkippedPaths`].
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Where to record files and directories that could not be read.
+    pub skipped: SkippedPaths,
+    /// How to hash the files that were found.
+    pub hashing: HashOptions,
+    /// How often to retry reading a file that failed with a transient error.
+    pub retry: RetryPolicy,
+    /// How many seconds an operation log from another system may appear to be ahead of the local
+    /// clock before the clocks of the two systems are assumed to be out of sync.
+    pub max_clock_skew: u64,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            skipped: SkippedPaths::default(),
+            hashing: HashOptions::default(),
+            retry: RetryPolicy::default(),
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+        }
+    }
+}
+
 /// Errors that may occur while using a stream.
 #[derive(Debug, Error)]
 #[allow(variant_size_differences)]
@@ -19,7 +51,15 @@ pub enum Error {
     /// Some I/O error occurred.
     #[error("I/O error occurred: {0}")]
     IO(#[from] tokio::io::Error),
+    /// A hoard file does not match its recorded checksum and appears to be partially written.
+    #[error(
+        "hoard file {0} is corrupt: it does not match the checksum recorded by the last operation"
+    )]
+    Corrupt(HoardPath),
     /// Error occurred while loading operation logs.
     #[error("failed to check hoard operations: {0}")]
     Operation(#[from] Box<OperationError>),
+    /// Error while determining which path of a pile a file belongs to.
+    #[error("{0}")]
+    Sources(#[from] SourcesError),
 }
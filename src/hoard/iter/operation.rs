@@ -1,5 +1,6 @@
-use super::diff_stream;
+use super::diff_files::raw_diff_stream;
 use crate::checkers::history::operation::ItemOperation;
+use crate::diff::Diff;
 use crate::hoard::iter::{DiffSource, HoardFileDiff};
 use crate::hoard::{Direction, Hoard};
 use crate::hoard_item::CachedHoardItem;
@@ -19,9 +20,10 @@ pub async fn operation_stream(
     hoard_name: HoardName,
     hoard: &Hoard,
     direction: Direction,
+    options: &super::ScanOptions,
 ) -> Result<impl TryStream<Ok = ItemOperation<CachedHoardItem>, Error = super::Error>, super::Error>
 {
-    diff_stream(hoards_root, hoard_name, hoard)
+    raw_diff_stream(hoards_root, hoard_name, hoard, false, options)
         .await
         .map(move |stream| {
             stream.and_then(move |diff| async move {
@@ -54,8 +56,27 @@ pub async fn operation_stream(
                         (Direction::Backup, DiffSource::Remote | DiffSource::Unknown)
                         | (Direction::Restore, DiffSource::Local) => ItemOperation::Create(file),
                     },
+                    // Backing up again replaces the corrupt file, but restoring it would
+                    // overwrite the system file with partial content.
+                    HoardFileDiff::Corrupt(file) => match direction {
+                        Direction::Backup => ItemOperation::Modify(file),
+                        Direction::Restore => {
+                            return Err(super::Error::Corrupt(file.hoard_path().clone()));
+                        }
+                    },
+                    // Restoring brings back files that were moved into the hoard.
+                    HoardFileDiff::Unchanged(file)
+                        if direction == Direction::Restore
+                            && matches!(file.diff(), Some(Diff::SystemNotExists)) =>
+                    {
+                        ItemOperation::Create(file)
+                    }
                     HoardFileDiff::Unchanged(file) => ItemOperation::Nothing(file),
                     HoardFileDiff::Nonexistent(file) => ItemOperation::DoesNotExist(file),
+                    // Files that are too large are left alone and kept out of the logs.
+                    HoardFileDiff::Excluded(file) => ItemOperation::DoesNotExist(file),
+                    // As are files whose content is on the pile's blocklist.
+                    HoardFileDiff::Ignored(file) => ItemOperation::DoesNotExist(file),
                 };
                 Ok(op)
             })
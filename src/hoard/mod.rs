@@ -6,17 +6,25 @@ use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
 
+use glob::Pattern;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::io;
 
 pub use pile_config::Config as PileConfig;
+pub use pile_glob::PileGlob;
 
+use crate::filters::Selection;
 use crate::newtypes::{NonEmptyPileName, PileName};
 use crate::paths::{HoardPath, RelativePath, SystemPath};
 
+pub mod hooks;
 pub mod iter;
 pub mod pile_config;
+pub mod pile_glob;
+pub mod skipped;
+pub mod sources;
 
 /// Errors that can happen while backing up or restoring a hoard.
 #[derive(Debug, Error)]
@@ -90,6 +98,19 @@ pub struct Pile {
     /// The path is optional because it will almost always be set by processing a configuration
     /// file and it is possible that none of the environment combinations match.
     pub path: Option<SystemPath>,
+    /// Additional paths whose files are merged into the same hoard subtree as `path`.
+    ///
+    /// This is only non-empty if `path` is set.
+    pub extra_paths: Vec<SystemPath>,
+    /// The glob pattern that files must match, relative to `path`, if the pile path is a glob.
+    pub glob: Option<PileGlob>,
+}
+
+impl Pile {
+    /// Returns every source path of this pile, starting with the primary `path`.
+    pub(crate) fn source_paths(&self) -> impl Iterator<Item = &SystemPath> {
+        self.path.iter().chain(&self.extra_paths)
+    }
 }
 
 /// A collection of multiple related [`Pile`]s.
@@ -101,7 +122,7 @@ pub struct MultipleEntries {
 
 /// A configured hoard. May contain one or more [`Pile`]s.
 #[derive(Clone, Debug, PartialEq, Eq)]
-#[allow(variant_size_differences)]
+#[allow(variant_size_differences, clippy::large_enum_variant)]
 pub enum Hoard {
     /// A single anonymous [`Pile`].
     Anonymous(Pile),
@@ -113,6 +134,7 @@ impl Hoard {
     /// Returns an iterator over all piles with associated paths.
     ///
     /// The [`HoardPath`] and [`SystemPath`] represent the relevant prefix/root path for the given pile.
+    /// For piles with multiple paths, only the primary path is returned.
     #[must_use]
     #[tracing::instrument(name = "get_hoard_paths")]
     pub fn get_paths(
@@ -137,6 +159,45 @@ impl Hoard {
         }
     }
 
+    /// Returns a copy of this hoard that only includes the files in `selection`.
+    #[must_use]
+    pub fn with_selection(&self, selection: &Selection) -> Self {
+        let mut hoard = self.clone();
+        match &mut hoard {
+            Hoard::Anonymous(pile) => pile.config.selection = selection.clone(),
+            Hoard::Named(named) => {
+                for pile in named.piles.values_mut() {
+                    pile.config.selection = selection.clone();
+                }
+            }
+        }
+        hoard
+    }
+
+    /// Returns a copy of this hoard that excludes every file outside of `piles`.
+    #[must_use]
+    pub(crate) fn with_only_piles(&self, piles: &[NonEmptyPileName]) -> Self {
+        let mut hoard = self.clone();
+        if let Hoard::Named(named) = &mut hoard {
+            let everything = [Pattern::new("*").expect("* should be a valid pattern")];
+            for (name, pile) in &mut named.piles {
+                if !piles.contains(name) {
+                    pile.config.selection = Selection::new(&[], &everything);
+                }
+            }
+        }
+        hoard
+    }
+
+    /// Returns the names of all named piles in this hoard, sorted.
+    #[must_use]
+    pub fn pile_names(&self) -> Vec<NonEmptyPileName> {
+        match self {
+            Hoard::Anonymous(_) => Vec::new(),
+            Hoard::Named(named) => named.piles.keys().cloned().sorted().collect(),
+        }
+    }
+
     /// Returns the pile with the given [`PileName`], if exists.
     #[must_use]
     pub fn get_pile(&self, name: &PileName) -> Option<&Pile> {
@@ -146,4 +207,13 @@ impl Hoard {
             _ => None,
         }
     }
+
+    /// Returns a mutable reference to the pile with the given [`PileName`], if exists.
+    pub(crate) fn get_pile_mut(&mut self, name: &PileName) -> Option<&mut Pile> {
+        match (name.as_ref(), self) {
+            (None, Self::Anonymous(pile)) => Some(pile),
+            (Some(name), Self::Named(map)) => map.piles.get_mut(name),
+            _ => None,
+        }
+    }
 }
@@ -1,16 +1,20 @@
 //! Helper types representing a pile's configuration.
 
+use std::collections::{BTreeMap, HashSet};
 use std::fs::Permissions as StdPermissions;
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
+use std::time::Duration;
 
 use serde::de::Error as _;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tap::TapFallible;
 use tokio::{fs, io};
 
-use crate::checksum::ChecksumType;
+use crate::checksum::{ChecksumType, MD5};
+use crate::filters::Selection;
+use crate::newtypes::EnvironmentName;
 
 /// Configuration for symmetric (password) encryption. (Not yet implemented)
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -41,6 +45,214 @@ pub enum Encryption {
     Asymmetric(AsymmetricEncryption),
 }
 
+/// A size in bytes, like the `max_file_size` of a pile.
+///
+/// Sizes can be given as a number of bytes or as a string with a unit, like `"50MiB"` or
+/// `"1.5 GB"`. Decimal units (`KB`, `MB`, ...) are multiples of 1000 and binary units (`KiB`,
+/// `MiB`, ...) are multiples of 1024.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "RawFileSize", into = "u64")]
+pub struct FileSize(u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawFileSize {
+    Bytes(u64),
+    Human(String),
+}
+
+impl FileSize {
+    const UNITS: [(&'static str, u64); 9] = [
+        ("b", 1),
+        ("kb", 1000),
+        ("kib", 1 << 10),
+        ("mb", 1000 * 1000),
+        ("mib", 1 << 20),
+        ("gb", 1000 * 1000 * 1000),
+        ("gib", 1 << 30),
+        ("tb", 1000 * 1000 * 1000 * 1000),
+        ("tib", 1 << 40),
+    ];
+
+    /// Create a new [`FileSize`] of `bytes` bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: u64) -> Self {
+        Self(bytes)
+    }
+
+    /// The size in bytes.
+    #[must_use]
+    pub const fn as_bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::str::FromStr for FileSize {
+    type Err = String;
+
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let unit = unit.trim().to_ascii_lowercase();
+
+        let multiplier = if unit.is_empty() {
+            1
+        } else {
+            Self::UNITS
+                .iter()
+                .find_map(|(name, multiplier)| (*name == unit).then_some(*multiplier))
+                .ok_or_else(|| format!("unknown file size unit in \"{s}\""))?
+        };
+
+        if let Ok(bytes) = number.parse::<u64>() {
+            return bytes
+                .checked_mul(multiplier)
+                .map(Self)
+                .ok_or_else(|| format!("file size \"{s}\" is too large"));
+        }
+
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("invalid file size \"{s}\""))?;
+        let bytes = (number * multiplier as f64).round();
+        if bytes >= u64::MAX as f64 {
+            return Err(format!("file size \"{s}\" is too large"));
+        }
+        Ok(Self(bytes as u64))
+    }
+}
+
+impl TryFrom<RawFileSize> for FileSize {
+    type Error = String;
+
+    fn try_from(raw: RawFileSize) -> Result<Self, Self::Error> {
+        match raw {
+            RawFileSize::Bytes(bytes) => Ok(Self(bytes)),
+            RawFileSize::Human(size) => size.parse(),
+        }
+    }
+}
+
+impl From<FileSize> for u64 {
+    fn from(size: FileSize) -> Self {
+        size.0
+    }
+}
+
+impl std::fmt::Display for FileSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} bytes", self.0)
+    }
+}
+
+/// How to handle files that are locked by another process during backup.
+///
+/// Locked files are only detected on Windows, where copying a file that another program has open
+/// fails with a sharing or lock violation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockedFilePolicy {
+    /// Skip the file with a warning. It will be backed up during a later backup.
+    Skip,
+    /// Wait and try again a few times before failing.
+    Retry,
+    /// Fail the backup.
+    #[default]
+    Error,
+}
+
+/// What to do when a file exists under more than one path of a pile with multiple paths.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceConflict {
+    /// Fail with an error naming the file and the paths it exists under.
+    #[default]
+    Error,
+    /// Use the file under the path listed first and ignore the others.
+    FirstWins,
+}
+
+/// What to do after failing to access a file with a given [`LockedFilePolicy`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum LockedFileAction {
+    /// Skip the file.
+    Skip,
+    /// Try to access the file again.
+    Retry,
+    /// Return the error.
+    Fail,
+}
+
+impl LockedFilePolicy {
+    /// How many times to retry accessing a locked file with [`LockedFilePolicy::Retry`].
+    const RETRIES: u32 = 3;
+    /// How long to wait between attempts to access a locked file.
+    const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+    /// Decides what to do after accessing `path` failed with `error`, waiting first if the
+    /// access should be retried.
+    ///
+    /// `attempts` counts the retries made so far and is incremented on each retry.
+    pub(crate) async fn handle_error(
+        self,
+        path: &Path,
+        error: &io::Error,
+        attempts: &mut u32,
+    ) -> LockedFileAction {
+        if !is_locked_error(error) {
+            return LockedFileAction::Fail;
+        }
+
+        match self {
+            Self::Skip => {
+                tracing::warn!(
+                    "skipping {}: it is locked by another process",
+                    path.display()
+                );
+                LockedFileAction::Skip
+            }
+            Self::Retry if *attempts < Self::RETRIES => {
+                *attempts += 1;
+                tracing::warn!(
+                    "{} is locked by another process, retrying ({}/{})",
+                    path.display(),
+                    attempts,
+                    Self::RETRIES
+                );
+                // Sleep on a blocking thread so other files can be processed in the meantime.
+                tokio::task::spawn_blocking(|| std::thread::sleep(Self::RETRY_DELAY))
+                    .await
+                    .expect("sleeping thread should never panic");
+                LockedFileAction::Retry
+            }
+            Self::Retry | Self::Error => LockedFileAction::Fail,
+        }
+    }
+}
+
+/// Returns whether `error` indicates that another process has the file open or locked.
+#[cfg(windows)]
+fn is_locked_error(error: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    matches!(error.raw_os_error(), Some(32 | 33))
+}
+
+/// Returns whether `error` indicates that another process has the file open or locked.
+///
+/// Other platforms only support advisory locks, which do not prevent reading a file.
+#[cfg(not(windows))]
+fn is_locked_error(_error: &io::Error) -> bool {
+    false
+}
+
 /// Configurable permissions for files and folders.
 ///
 /// Can be declared as a unix `chmod(1)` style mode or as a set of boolean flags.
@@ -157,6 +369,21 @@ impl Permissions {
         }
     }
 
+    /// Returns the [`Permissions`] equivalent to the given filesystem permissions.
+    ///
+    /// On Windows, only whether the target is read-only is preserved.
+    #[must_use]
+    pub fn from_std(perms: &StdPermissions) -> Self {
+        #[cfg(unix)]
+        return Self::Mode(perms.mode() & 0o7777);
+        #[cfg(not(unix))]
+        if perms.readonly() {
+            Self::Mode(Self::OWNER_READ)
+        } else {
+            Self::file_default()
+        }
+    }
+
     /// Modifies the provided permissions to set them equal to this [`Permissions`].
     #[must_use]
     pub fn set_permissions(self, mut perms: StdPermissions) -> StdPermissions {
@@ -217,6 +444,78 @@ where
     value.serialize(serializer)
 }
 
+#[allow(single_use_lifetimes)]
+fn deserialize_env_glob<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<EnvironmentName, Vec<glob::Pattern>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<EnvironmentName, Vec<String>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(env, patterns)| {
+            patterns
+                .iter()
+                .map(String::as_str)
+                .map(glob::Pattern::new)
+                .collect::<Result<_, _>>()
+                .map(|patterns| (env, patterns))
+        })
+        .collect::<Result<_, _>>()
+        .map_err(D::Error::custom)
+}
+
+fn serialize_env_glob<S>(
+    value: &BTreeMap<EnvironmentName, Vec<glob::Pattern>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let value = value
+        .iter()
+        .map(|(env, patterns)| {
+            let patterns = patterns
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<String>>();
+            (env, patterns)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    value.serialize(serializer)
+}
+
+/// Appends the `specific` ignore patterns to the `general` ones.
+///
+/// Order matters because the last matching pattern wins, so the more general patterns go first
+/// and only the last copy of a duplicate is kept.
+fn merge_ignore(general: &[glob::Pattern], specific: Vec<glob::Pattern>) -> Vec<glob::Pattern> {
+    let mut ignore = general.to_vec();
+    ignore.extend(specific);
+    let mut seen = HashSet::new();
+    ignore.reverse();
+    ignore.retain(|pattern| seen.insert(pattern.clone()));
+    ignore.reverse();
+    ignore
+}
+
+/// A setting that Hoard does not support.
+///
+/// Any value is accepted while parsing, so that processing the configuration can report which
+/// setting is unsupported instead of failing with a generic parse error.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Unsupported;
+
+impl<'de> Deserialize<'de> for Unsupported {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde::de::IgnoredAny::deserialize(deserializer).map(|_| Self)
+    }
+}
+
 /// Hoard/Pile configuration.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
@@ -227,13 +526,44 @@ pub struct Config {
     /// The [`Encryption`] configuration for a pile.
     #[serde(default, rename = "encrypt")]
     pub encryption: Option<Encryption>,
-    /// A list of glob patterns matching files to ignore.
+    /// A list of gitignore-style patterns matching files to ignore.
+    ///
+    /// When configs are layered, patterns from the more specific config come last, so they
+    /// take precedence.
     #[serde(
         default,
         deserialize_with = "deserialize_glob",
         serialize_with = "serialize_glob"
     )]
     pub ignore: Vec<glob::Pattern>,
+    /// Additional `ignore` patterns that only apply when the named environment is active.
+    ///
+    /// These are merged into `ignore` when the pile is processed, after the patterns that apply
+    /// everywhere.
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "deserialize_env_glob",
+        serialize_with = "serialize_env_glob"
+    )]
+    pub env_ignore: BTreeMap<EnvironmentName, Vec<glob::Pattern>>,
+    /// A list of glob patterns matching files whose content changes should be ignored.
+    ///
+    /// Matching files are still backed up and restored, but are reported as unchanged
+    /// by `hoard status` and `hoard diff` if they exist in both the hoard and the system.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_glob",
+        serialize_with = "serialize_glob"
+    )]
+    pub checksum_ignore: Vec<glob::Pattern>,
+    /// MD5 checksums of file contents that should never be backed up or restored.
+    ///
+    /// Unlike `ignore`, this matches on content instead of path, so every file in the pile must be
+    /// read and hashed before it can be filtered. Files are only hashed for this if the list is
+    /// not empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_checksums: Vec<MD5>,
     /// The [`Permissions`] to set on restored files.
     ///
     /// See [`Permissions::file_default`] for the default value.
@@ -244,6 +574,44 @@ pub struct Config {
     /// See [`Permissions::folder_default`] for the default value.
     #[serde(default)]
     pub folder_permissions: Option<Permissions>,
+    /// Whether to copy extended attributes (e.g. xattrs) along with file contents.
+    ///
+    /// This is only supported on some platforms and is ignored elsewhere.
+    #[serde(default)]
+    pub copy_attributes: Option<bool>,
+    /// Whether to treat CRLF (`\r\n`) line endings in text files as LF (`\n`) when
+    /// computing checksums and diffs.
+    ///
+    /// Files are still stored with their original line endings.
+    #[serde(default)]
+    pub normalize_line_endings: Option<bool>,
+    /// Whether to also ignore files matched by `.gitignore` files found in the pile.
+    #[serde(default)]
+    pub respect_gitignore: Option<bool>,
+    /// How to handle files that are locked by another process during backup.
+    ///
+    /// See [`LockedFilePolicy`] for the default value.
+    #[serde(default)]
+    pub locked_files: Option<LockedFilePolicy>,
+    /// How to handle a file that exists under more than one path of a pile with multiple paths.
+    ///
+    /// See [`SourceConflict`] for the default value.
+    #[serde(default)]
+    pub source_conflict: Option<SourceConflict>,
+    /// Files larger than this [`FileSize`] are skipped instead of being backed up or restored.
+    #[serde(default)]
+    pub max_file_size: Option<FileSize>,
+    /// Compression of stored files is not supported, so setting this is an error.
+    #[serde(default, skip_serializing)]
+    pub compression: Option<Unsupported>,
+    /// Compression of stored files is not supported, so setting this is an error.
+    #[serde(default, skip_serializing)]
+    pub compression_level: Option<Unsupported>,
+    /// Files selected on the command line with `--only` and `--ignore`.
+    ///
+    /// This cannot be set in the configuration file and is only set on processed piles.
+    #[serde(skip)]
+    pub selection: Selection,
 }
 
 impl Config {
@@ -259,11 +627,55 @@ impl Config {
 
         self.file_permissions = self.file_permissions.or(other.file_permissions);
         self.folder_permissions = self.folder_permissions.or(other.folder_permissions);
+        self.copy_attributes = self.copy_attributes.or(other.copy_attributes);
+        self.normalize_line_endings = self.normalize_line_endings.or(other.normalize_line_endings);
+        self.respect_gitignore = self.respect_gitignore.or(other.respect_gitignore);
+        self.locked_files = self.locked_files.or(other.locked_files);
+        self.source_conflict = self.source_conflict.or(other.source_conflict);
+        self.max_file_size = self.max_file_size.or(other.max_file_size);
+        self.compression = self.compression.or(other.compression);
+        self.compression_level = self.compression_level.or(other.compression_level);
+        // `selection` is only set on processed piles, after all layering is done.
+
+        // Merge ignore lists, including those for each environment.
+        self.ignore = merge_ignore(&other.ignore, std::mem::take(&mut self.ignore));
+        for (env, general) in &other.env_ignore {
+            let specific = self.env_ignore.remove(env).unwrap_or_default();
+            self.env_ignore
+                .insert(env.clone(), merge_ignore(general, specific));
+        }
+
+        // Merge checksum ignore lists.
+        self.checksum_ignore.extend(other.checksum_ignore.clone());
+        self.checksum_ignore.sort_unstable();
+        self.checksum_ignore.dedup();
+
+        // Merge checksum blocklists.
+        self.ignore_checksums.extend(other.ignore_checksums.clone());
+        self.ignore_checksums.sort_unstable();
+        self.ignore_checksums.dedup();
+    }
 
-        // Merge ignore lists.
-        self.ignore.extend(other.ignore.clone());
-        self.ignore.sort_unstable();
-        self.ignore.dedup();
+    /// Returns the name of the first unsupported setting that is set, if any.
+    pub(crate) fn unsupported_setting(&self) -> Option<&'static str> {
+        if self.compression.is_some() {
+            Some("compression")
+        } else if self.compression_level.is_some() {
+            Some("compression_level")
+        } else {
+            None
+        }
+    }
+
+    /// Merges the `env_ignore` patterns of every environment that is active in `envs` into
+    /// `ignore`, leaving `env_ignore` empty.
+    pub(crate) fn apply_env_ignore(&mut self, envs: &BTreeMap<EnvironmentName, bool>) {
+        for (env, patterns) in std::mem::take(&mut self.env_ignore) {
+            if envs.get(&env).copied().unwrap_or(false) {
+                tracing::trace!(%env, ?patterns, "applying ignore patterns for environment");
+                self.ignore = merge_ignore(&self.ignore, patterns);
+            }
+        }
     }
 
     /// Layer the `general` config with the `specific` one, modifying the `specific` one in place.
@@ -281,7 +693,7 @@ impl Config {
 
 #[cfg(test)]
 mod tests {
-    use crate::checksum::ChecksumType;
+    use crate::checksum::{ChecksumType, MD5};
     use crate::hoard::pile_config::Permissions;
 
     use super::*;
@@ -302,8 +714,20 @@ mod tests {
                 "password".into(),
             ))),
             ignore: vec![glob::Pattern::new("ignore me").unwrap()],
+            env_ignore: BTreeMap::new(),
+            checksum_ignore: vec![glob::Pattern::new("*.lock").unwrap()],
+            ignore_checksums: vec!["d41d8cd98f00b204e9800998ecf8427e".parse::<MD5>().unwrap()],
             file_permissions: Some(Permissions::Mode(0o666)),
             folder_permissions: Some(Permissions::Mode(0o777)),
+            copy_attributes: Some(true),
+            normalize_line_endings: Some(true),
+            respect_gitignore: Some(true),
+            locked_files: Some(LockedFilePolicy::Skip),
+            source_conflict: None,
+            max_file_size: None,
+            compression: None,
+            compression_level: None,
+            selection: Selection::default(),
         });
         let old_specific = specific.clone();
         let general = None;
@@ -320,14 +744,27 @@ mod tests {
                 "password".into(),
             ))),
             ignore: vec![glob::Pattern::new("ignore me").unwrap()],
+            env_ignore: BTreeMap::new(),
+            checksum_ignore: vec![glob::Pattern::new("*.lock").unwrap()],
+            ignore_checksums: vec!["d41d8cd98f00b204e9800998ecf8427e".parse::<MD5>().unwrap()],
             file_permissions: Some(Permissions::Mode(0o666)),
             folder_permissions: Some(Permissions::Mode(0o777)),
+            copy_attributes: Some(true),
+            normalize_line_endings: Some(true),
+            respect_gitignore: Some(true),
+            locked_files: Some(LockedFilePolicy::Skip),
+            source_conflict: None,
+            max_file_size: None,
+            compression: None,
+            compression_level: None,
+            selection: Selection::default(),
         });
         Config::layer_options(&mut specific, general.as_ref());
         assert_eq!(specific, general);
     }
 
     #[test]
+    #[allow(clippy::too_many_lines)]
     fn test_layer_configs_both_some() {
         let mut specific = Some(Config {
             checksum_type: Some(ChecksumType::default()),
@@ -338,8 +775,20 @@ mod tests {
                 glob::Pattern::new("ignore me").unwrap(),
                 glob::Pattern::new("duplicate").unwrap(),
             ],
+            env_ignore: BTreeMap::new(),
+            checksum_ignore: vec![glob::Pattern::new("*.lock").unwrap()],
+            ignore_checksums: vec!["d41d8cd98f00b204e9800998ecf8427e".parse::<MD5>().unwrap()],
             file_permissions: Some(Permissions::Mode(0o644)),
             folder_permissions: Some(Permissions::Mode(0o777)),
+            copy_attributes: Some(true),
+            normalize_line_endings: Some(true),
+            respect_gitignore: Some(true),
+            locked_files: Some(LockedFilePolicy::Skip),
+            source_conflict: None,
+            max_file_size: None,
+            compression: None,
+            compression_level: None,
+            selection: Selection::default(),
         });
         let old_specific = specific.clone();
         let general = Some(Config {
@@ -351,8 +800,26 @@ mod tests {
                 glob::Pattern::new("me too").unwrap(),
                 glob::Pattern::new("duplicate").unwrap(),
             ],
+            env_ignore: BTreeMap::new(),
+            checksum_ignore: vec![
+                glob::Pattern::new("*.lock").unwrap(),
+                glob::Pattern::new("*.tmp").unwrap(),
+            ],
+            ignore_checksums: vec![
+                "2829cce0d3a32f7afcf10cd0cc31f21b".parse::<MD5>().unwrap(),
+                "d41d8cd98f00b204e9800998ecf8427e".parse::<MD5>().unwrap(),
+            ],
             file_permissions: Some(Permissions::Mode(0o666)),
             folder_permissions: Some(Permissions::Mode(0o755)),
+            copy_attributes: Some(true),
+            normalize_line_endings: Some(true),
+            respect_gitignore: Some(true),
+            locked_files: Some(LockedFilePolicy::Retry),
+            source_conflict: Some(SourceConflict::FirstWins),
+            max_file_size: None,
+            compression: None,
+            compression_level: None,
+            selection: Selection::default(),
         });
         Config::layer_options(&mut specific, general.as_ref());
         assert!(specific.is_some());
@@ -363,11 +830,27 @@ mod tests {
         assert_eq!(
             specific.as_ref().unwrap().ignore,
             vec![
-                glob::Pattern::new("duplicate").unwrap(),
-                glob::Pattern::new("ignore me").unwrap(),
                 glob::Pattern::new("me too").unwrap(),
+                glob::Pattern::new("ignore me").unwrap(),
+                glob::Pattern::new("duplicate").unwrap(),
             ]
         );
+        assert_eq!(
+            specific.as_ref().unwrap().checksum_ignore,
+            vec![
+                glob::Pattern::new("*.lock").unwrap(),
+                glob::Pattern::new("*.tmp").unwrap(),
+            ]
+        );
+        let mut ignore_checksums = vec![
+            "d41d8cd98f00b204e9800998ecf8427e".parse::<MD5>().unwrap(),
+            "2829cce0d3a32f7afcf10cd0cc31f21b".parse::<MD5>().unwrap(),
+        ];
+        ignore_checksums.sort_unstable();
+        assert_eq!(
+            specific.as_ref().unwrap().ignore_checksums,
+            ignore_checksums
+        );
         assert_eq!(
             specific
                 .as_ref()
@@ -386,6 +869,40 @@ mod tests {
                 .as_mode(),
             0o777
         );
+        assert_eq!(
+            specific.as_ref().unwrap().locked_files,
+            Some(LockedFilePolicy::Skip)
+        );
+        assert_eq!(
+            specific.as_ref().unwrap().source_conflict,
+            Some(SourceConflict::FirstWins)
+        );
+    }
+
+    #[test]
+    fn test_parse_file_size() {
+        let parse = |s: &str| s.parse::<FileSize>().map(FileSize::as_bytes);
+        assert_eq!(parse("1024"), Ok(1024));
+        assert_eq!(parse("10B"), Ok(10));
+        assert_eq!(parse("2KB"), Ok(2000));
+        assert_eq!(parse("2KiB"), Ok(2048));
+        assert_eq!(parse("50MiB"), Ok(50 * 1024 * 1024));
+        assert_eq!(parse("50 mb"), Ok(50_000_000));
+        assert_eq!(parse("1.5GiB"), Ok(1_610_612_736));
+        assert_eq!(parse("1TB"), Ok(1_000_000_000_000));
+        assert!(parse("").is_err());
+        assert!(parse("MiB").is_err());
+        assert!(parse("5 parsecs").is_err());
+        assert!(parse("99999999999999TiB").is_err());
+
+        let config: Config =
+            toml::from_str("max_file_size = \"50MiB\"").expect("file size string should parse");
+        assert_eq!(config.max_file_size, Some(FileSize(50 * 1024 * 1024)));
+        let config: Config =
+            toml::from_str("max_file_size = 100").expect("file size number should parse");
+        assert_eq!(config.max_file_size, Some(FileSize(100)));
+        toml::from_str::<Config>("max_file_size = \"lots\"")
+            .expect_err("invalid file size should fail");
     }
 
     mod permissions {
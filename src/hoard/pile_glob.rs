@@ -0,0 +1,183 @@
+//! Glob patterns in pile paths.
+//!
+//! A pile with `glob = true` has its path split at the first component that contains a glob
+//! character (`*`, `?`, or `[`). The path before that component becomes the root of the pile, and
+//! the rest is matched against paths relative to that root. For example, the pile path
+//! `~/game/profiles/*/save.dat` is rooted at `~/game/profiles`, and the file
+//! `~/game/profiles/alice/save.dat` is stored in the hoard as `alice/save.dat`.
+
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern, PatternError};
+use thiserror::Error;
+
+use crate::paths::{Error as PathError, RelativePath, SystemPath};
+
+const GLOB_CHARS: [char; 3] = ['*', '?', '['];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Errors that may occur while parsing a glob pile path.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The part of the path after the root directory is not a valid glob pattern.
+    #[error("invalid glob pattern: {0}")]
+    Pattern(#[from] PatternError),
+    /// The directory before the first glob component is not a valid pile path.
+    #[error("invalid root directory for glob: {0}")]
+    Root(#[from] PathError),
+}
+
+/// The glob pattern of a pile, relative to the root directory of the pile.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PileGlob {
+    pattern: Pattern,
+    components: Vec<Pattern>,
+}
+
+impl PileGlob {
+    /// Splits `path` into the directory before its first glob component and a [`PileGlob`]
+    /// matching the rest of the path.
+    ///
+    /// Returns `None` if `path` does not contain any glob characters.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Pattern`] if the rest of the path is not a valid glob pattern.
+    /// - [`Error::Root`] if the directory before the glob is not a valid pile path, e.g. because
+    ///   it is Hoard's own data directory.
+    pub fn split(path: &SystemPath) -> Result<Option<(SystemPath, Self)>, Error> {
+        let mut root = PathBuf::new();
+        let mut rest: Vec<String> = Vec::new();
+        for component in path.components() {
+            let component_str = component.as_os_str().to_string_lossy();
+            if rest.is_empty() && !component_str.contains(GLOB_CHARS) {
+                root.push(component);
+            } else {
+                rest.push(component_str.into_owned());
+            }
+        }
+
+        if rest.is_empty() {
+            return Ok(None);
+        }
+
+        let components = rest
+            .iter()
+            .map(|component| Pattern::new(component))
+            .collect::<Result<_, _>>()?;
+        let glob = Self {
+            pattern: Pattern::new(&rest.join("/"))?,
+            components,
+        };
+
+        Ok(Some((SystemPath::try_from(root)?, glob)))
+    }
+
+    /// Returns the pattern, relative to the root of the pile.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    /// Returns whether the item at `rel_path` should be included in the pile.
+    ///
+    /// Items are included if they match the pattern or are inside of a directory that does.
+    /// Directories are also included if they may contain a match, so they can be searched.
+    pub(crate) fn keep(&self, rel_path: &RelativePath, is_dir: bool) -> bool {
+        let Some(path) = rel_path.as_path() else {
+            return true;
+        };
+
+        let matched = path
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.pattern.matches_path_with(ancestor, MATCH_OPTIONS));
+
+        matched || (is_dir && self.may_contain_match(path))
+    }
+
+    fn may_contain_match(&self, dir: &Path) -> bool {
+        for (i, component) in dir.components().enumerate() {
+            match self.components.get(i) {
+                None => return false,
+                Some(pattern) if pattern.as_str() == "**" => return true,
+                Some(pattern) => {
+                    if !pattern
+                        .matches_with(&component.as_os_str().to_string_lossy(), MATCH_OPTIONS)
+                    {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> PathBuf {
+        crate::dirs::home_dir()
+    }
+
+    fn split(path: &str) -> Option<(SystemPath, PileGlob)> {
+        let path = SystemPath::try_from(home().join(path)).unwrap();
+        PileGlob::split(&path).unwrap()
+    }
+
+    fn keeps(glob: &PileGlob, path: &str, is_dir: bool) -> bool {
+        glob.keep(&path.parse().unwrap(), is_dir)
+    }
+
+    #[test]
+    fn test_split_without_glob() {
+        assert!(split("game/profiles/save.dat").is_none());
+    }
+
+    #[test]
+    fn test_split_at_first_glob_component() {
+        let (root, glob) = split("game/profiles/*/saves/*.dat").unwrap();
+        assert_eq!(root.to_path_buf(), home().join("game").join("profiles"));
+        assert_eq!(glob.as_str(), "*/saves/*.dat");
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let path = SystemPath::try_from(home().join("game").join("[profiles")).unwrap();
+        assert!(matches!(PileGlob::split(&path), Err(Error::Pattern(_))));
+    }
+
+    #[test]
+    fn test_keep_matching_files() {
+        let (_, glob) = split("game/profiles/*/save.dat").unwrap();
+        assert!(keeps(&glob, "", true));
+        assert!(keeps(&glob, "alice", true));
+        assert!(keeps(&glob, "alice/save.dat", false));
+        assert!(!keeps(&glob, "alice/other.dat", false));
+        assert!(!keeps(&glob, "alice/nested", true));
+        assert!(!keeps(&glob, "readme.txt", false));
+    }
+
+    #[test]
+    fn test_keep_files_in_matching_dirs() {
+        let (_, glob) = split("game/profiles/*").unwrap();
+        assert!(keeps(&glob, "alice", true));
+        assert!(keeps(&glob, "alice/nested/save.dat", false));
+    }
+
+    #[test]
+    fn test_keep_double_star() {
+        let (_, glob) = split("game/**/*.sav").unwrap();
+        assert!(keeps(&glob, "a/b/c", true));
+        assert!(keeps(&glob, "a/b/c/slot1.sav", false));
+        assert!(!keeps(&glob, "a/b/c/slot1.txt", false));
+    }
+}
@@ -0,0 +1,112 @@
+//! Files and directories that could not be read while looking for hoard files.
+//!
+//! By default, a file or directory that cannot be read, e.g. because of missing permissions, is
+//! skipped with a warning instead of failing the whole command, so that everything else can still
+//! be backed up or restored. Each skipped path is recorded in a [`SkippedPaths`] so that
+//! `hoard backup` and `hoard restore` can list them once they finish. With `--strict`, such errors
+//! fail the command instead.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tokio::io;
+
+/// A file or directory that was skipped because it could not be read.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Skipped {
+    /// The path that could not be read.
+    pub path: PathBuf,
+    /// Why the path could not be read.
+    pub reason: String,
+}
+
+/// Records the files and directories skipped while looking for hoard files.
+///
+/// Clones share the same record, so that paths skipped by any stream of a command can be taken
+/// once the command finishes.
+#[derive(Debug, Clone, Default)]
+pub struct SkippedPaths {
+    /// Whether unreadable files should fail the command instead of being skipped.
+    strict: bool,
+    skipped: Arc<Mutex<Vec<Skipped>>>,
+}
+
+impl SkippedPaths {
+    /// Create a new, empty record. If `strict`, nothing is ever skipped.
+    #[must_use]
+    pub fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            skipped: Arc::default(),
+        }
+    }
+
+    /// Records `path` as skipped because of `error`, unless strict mode is enabled.
+    ///
+    /// Returns whether `path` was skipped. If not, the caller should return `error`.
+    pub(crate) fn skip(&self, path: &Path, error: &io::Error) -> bool {
+        if self.strict {
+            return false;
+        }
+
+        tracing::warn!(%error, "skipping {}: it could not be read", path.display());
+        self.skipped
+            .lock()
+            .expect("skipped paths lock should not be poisoned")
+            .push(Skipped {
+                path: path.to_path_buf(),
+                reason: error.to_string(),
+            });
+        true
+    }
+
+    /// Returns every path skipped since the last call, sorted and without duplicates.
+    ///
+    /// # Panics
+    ///
+    /// If another thread panicked while recording a skipped path.
+    #[must_use]
+    pub fn take(&self) -> Vec<Skipped> {
+        let mut skipped = std::mem::take(
+            &mut *self
+                .skipped
+                .lock()
+                .expect("skipped paths lock should not be poisoned"),
+        );
+        skipped.sort_unstable();
+        skipped.dedup_by(|a, b| a.path == b.path);
+        skipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skipped_paths_are_taken_once() {
+        let error = io::Error::new(io::ErrorKind::PermissionDenied, "permission denied");
+        let first = Path::new("/skipped/test/first");
+        let second = Path::new("/skipped/test/second");
+        let skipped = SkippedPaths::default();
+        assert!(skipped.skip(second, &error));
+        assert!(skipped.clone().skip(first, &error));
+        assert!(skipped.skip(second, &error));
+
+        let paths: Vec<PathBuf> = skipped
+            .take()
+            .into_iter()
+            .map(|skipped| skipped.path)
+            .collect();
+        assert_eq!(paths, vec![first.to_path_buf(), second.to_path_buf()]);
+        assert!(skipped.take().is_empty());
+    }
+
+    #[test]
+    fn test_strict_skips_nothing() {
+        let error = io::Error::new(io::ErrorKind::PermissionDenied, "permission denied");
+        let skipped = SkippedPaths::new(true);
+        assert!(!skipped.skip(Path::new("/skipped/test/strict"), &error));
+        assert!(skipped.take().is_empty());
+    }
+}
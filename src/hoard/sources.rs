@@ -0,0 +1,315 @@
+//! Determine which path of a pile with multiple paths each file belongs to.
+//!
+//! The files under every path of such a pile are stored in the same hoard subtree. A file that
+//! exists under exactly one of the paths belongs to that path. A file that exists under more
+//! than one is a conflict, resolved using the pile's [`SourceConflict`] policy. A file that
+//! exists under none of them, like when restoring onto a new system, belongs to the path it was
+//! recorded with during the last backup or restore, or the first path if it was never recorded.
+//!
+//! Paths are recorded by their position in the pile's list of paths, in the data directory, so
+//! that the records are synchronized along with the hoards.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{fs, io};
+
+use crate::checkers::history::operation::ItemOperation;
+use crate::hoard::pile_config::SourceConflict;
+use crate::hoard::{Hoard, Pile};
+use crate::hoard_item::HoardItem;
+use crate::newtypes::{HoardName, PileName};
+use crate::paths::{RelativePath, SystemPath};
+
+const SOURCES_DIR_NAME: &str = "sources";
+
+/// Errors that may occur while determining which path a file belongs to.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A file exists under more than one path of a pile whose [`SourceConflict`] policy is
+    /// [`SourceConflict::Error`].
+    #[error(
+        "{path} exists under more than one path of its pile ({}) -- remove all but one or set source_conflict = \"first_wins\"",
+        .sources.iter().map(|source| source.display()).join(", ")
+    )]
+    Conflict {
+        /// The path of the file, relative to each pile path.
+        path: RelativePath,
+        /// The pile paths the file exists under.
+        sources: Vec<PathBuf>,
+    },
+    /// An I/O error occurred while reading or writing the recorded paths.
+    #[error("failed to access recorded pile paths: {0}")]
+    IO(#[from] io::Error),
+    /// The recorded paths could not be parsed.
+    #[error("failed to parse recorded pile paths: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// The path recorded for a single file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Record {
+    pile: PileName,
+    path: RelativePath,
+    /// The position of the path in the pile's list of paths.
+    source: usize,
+}
+
+/// The paths of a single pile with multiple paths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PileSources {
+    paths: Vec<SystemPath>,
+    conflict: SourceConflict,
+    recorded: BTreeMap<RelativePath, usize>,
+}
+
+impl PileSources {
+    /// Returns the path that the file at `rel_path` belongs to.
+    fn owner(&self, rel_path: &RelativePath) -> Result<&SystemPath, Error> {
+        let existing: Vec<&SystemPath> = self
+            .paths
+            .iter()
+            .filter(|path| path.join(rel_path).is_file())
+            .collect();
+
+        match (existing.as_slice(), self.conflict) {
+            ([], _) => Ok(self
+                .recorded
+                .get(rel_path)
+                .and_then(|index| self.paths.get(*index))
+                .unwrap_or(&self.paths[0])),
+            ([only], _) | ([only, ..], SourceConflict::FirstWins) => Ok(only),
+            (sources, SourceConflict::Error) => crate::create_log_error(Error::Conflict {
+                path: rel_path.clone(),
+                sources: sources.iter().map(|path| path.to_path_buf()).collect(),
+            }),
+        }
+    }
+}
+
+/// The paths of every pile with multiple paths in a single hoard.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Sources(HashMap<PileName, PileSources>);
+
+fn sources_file_path(hoard_name: &HoardName) -> PathBuf {
+    crate::dirs::data_dir()
+        .join(SOURCES_DIR_NAME)
+        .join(format!("{hoard_name}.json"))
+}
+
+impl Sources {
+    /// Load the paths recorded for `hoard`.
+    ///
+    /// Nothing is read if the hoard has no piles with multiple paths.
+    ///
+    /// # Errors
+    ///
+    /// Any errors while reading or parsing the recorded paths.
+    #[tracing::instrument(level = "debug", skip(hoard))]
+    pub(crate) async fn load(hoard_name: &HoardName, hoard: &Hoard) -> Result<Self, Error> {
+        let all_piles: Vec<(PileName, &Pile)> = match hoard {
+            Hoard::Anonymous(pile) => vec![(PileName::anonymous(), pile)],
+            Hoard::Named(named) => named
+                .piles
+                .iter()
+                .map(|(name, pile)| (name.clone().into(), pile))
+                .collect(),
+        };
+        let mut piles: HashMap<PileName, PileSources> = all_piles
+            .into_iter()
+            .filter(|(_, pile)| !pile.extra_paths.is_empty())
+            .map(|(name, pile)| {
+                let sources = PileSources {
+                    paths: pile.source_paths().cloned().collect(),
+                    conflict: pile.config.source_conflict.unwrap_or_default(),
+                    recorded: BTreeMap::new(),
+                };
+                (name, sources)
+            })
+            .collect();
+
+        if piles.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let records: Vec<Record> = match fs::read(sources_file_path(hoard_name)).await {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        for record in records {
+            if let Some(pile) = piles.get_mut(&record.pile) {
+                pile.recorded.insert(record.path, record.source);
+            }
+        }
+
+        Ok(Self(piles))
+    }
+
+    /// Returns `item` moved to the path of its pile that it belongs to.
+    ///
+    /// Items in piles with a single path are returned unchanged.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Conflict`] if the file exists under multiple paths of its pile and the pile's
+    /// policy is [`SourceConflict::Error`].
+    pub(crate) fn assign(&self, item: HoardItem) -> Result<HoardItem, Error> {
+        let Some(pile) = self.0.get(item.pile_name()) else {
+            return Ok(item);
+        };
+
+        let owner = pile.owner(item.relative_path())?;
+        if owner == item.system_prefix() {
+            return Ok(item);
+        }
+
+        Ok(HoardItem::new(
+            item.pile_name().clone(),
+            item.hoard_prefix().clone(),
+            owner.clone(),
+            item.relative_path().clone(),
+        ))
+    }
+
+    /// Like [`Sources::assign`], but for the item of an [`ItemOperation`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Sources::assign`].
+    pub(crate) fn assign_operation(
+        &self,
+        operation: ItemOperation<HoardItem>,
+    ) -> Result<ItemOperation<HoardItem>, Error> {
+        let assigned = match operation {
+            ItemOperation::Create(item) => ItemOperation::Create(self.assign(item)?),
+            ItemOperation::Modify(item) => ItemOperation::Modify(self.assign(item)?),
+            ItemOperation::Delete(item) => ItemOperation::Delete(self.assign(item)?),
+            ItemOperation::Nothing(item) => ItemOperation::Nothing(self.assign(item)?),
+            ItemOperation::DoesNotExist(item) => ItemOperation::DoesNotExist(self.assign(item)?),
+        };
+        Ok(assigned)
+    }
+
+    /// Record the path that each file in `operations` belongs to, forgetting deleted files.
+    ///
+    /// # Errors
+    ///
+    /// Any I/O errors while writing the recorded paths.
+    #[tracing::instrument(level = "debug", skip(operations))]
+    pub(crate) async fn record(
+        &mut self,
+        hoard_name: &HoardName,
+        operations: &[ItemOperation<HoardItem>],
+    ) -> Result<(), Error> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+
+        for operation in operations {
+            let item = operation.as_inner();
+            let Some(pile) = self.0.get_mut(item.pile_name()) else {
+                continue;
+            };
+            match operation {
+                ItemOperation::Delete(_) | ItemOperation::DoesNotExist(_) => {
+                    pile.recorded.remove(item.relative_path());
+                }
+                ItemOperation::Create(_) | ItemOperation::Modify(_) | ItemOperation::Nothing(_) => {
+                    if let Some(index) = pile
+                        .paths
+                        .iter()
+                        .position(|path| path == item.system_prefix())
+                    {
+                        pile.recorded.insert(item.relative_path().clone(), index);
+                    }
+                }
+            }
+        }
+
+        let records: Vec<Record> = self
+            .0
+            .iter()
+            .sorted_unstable_by_key(|(pile, _)| *pile)
+            .flat_map(|(pile, sources)| {
+                sources.recorded.iter().map(|(path, source)| Record {
+                    pile: pile.clone(),
+                    path: path.clone(),
+                    source: *source,
+                })
+            })
+            .collect();
+
+        let path = sources_file_path(hoard_name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&path, serde_json::to_vec_pretty(&records)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(dir: &std::path::Path, conflict: SourceConflict) -> PileSources {
+        PileSources {
+            paths: vec![
+                SystemPath::try_from(dir.join("first")).unwrap(),
+                SystemPath::try_from(dir.join("second")).unwrap(),
+            ],
+            conflict,
+            recorded: BTreeMap::new(),
+        }
+    }
+
+    fn write(path: PathBuf) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "content").unwrap();
+    }
+
+    #[test]
+    fn test_owner_is_path_file_exists_under() {
+        let dir = tempfile::tempdir().unwrap();
+        let sources = sources(dir.path(), SourceConflict::Error);
+        let rel_path: RelativePath = "save.dat".parse().unwrap();
+        write(dir.path().join("second/save.dat"));
+
+        assert_eq!(sources.owner(&rel_path).unwrap(), &sources.paths[1]);
+    }
+
+    #[test]
+    fn test_owner_of_missing_file_is_recorded_or_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sources = sources(dir.path(), SourceConflict::Error);
+        let rel_path: RelativePath = "save.dat".parse().unwrap();
+
+        assert_eq!(sources.owner(&rel_path).unwrap(), &sources.paths[0]);
+        sources.recorded.insert(rel_path.clone(), 1);
+        assert_eq!(sources.owner(&rel_path).unwrap(), &sources.paths[1]);
+    }
+
+    #[test]
+    fn test_conflict_is_error_or_first_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let rel_path: RelativePath = "save.dat".parse().unwrap();
+        write(dir.path().join("first/save.dat"));
+        write(dir.path().join("second/save.dat"));
+
+        let error = sources(dir.path(), SourceConflict::Error)
+            .owner(&rel_path)
+            .expect_err("a file under both paths should conflict");
+        assert!(
+            matches!(&error, Error::Conflict { path, sources } if path == &rel_path && sources.len() == 2),
+            "unexpected error: {error:?}"
+        );
+
+        let sources = sources(dir.path(), SourceConflict::FirstWins);
+        assert_eq!(sources.owner(&rel_path).unwrap(), &sources.paths[0]);
+    }
+}
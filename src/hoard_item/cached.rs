@@ -3,8 +3,10 @@ use std::collections::BTreeMap;
 use tokio::io;
 use tokio::try_join;
 
-use crate::checksum::{Checksum, ChecksumType, MD5, SHA256};
+use crate::checksum::cache::{ChecksumCache, Entry, Lookup};
+use crate::checksum::{Checksum, ChecksumType};
 use crate::diff::{str_diff, Diff, FileContent};
+use crate::hoard::PileConfig;
 use crate::newtypes::PileName;
 use crate::paths::{HoardPath, RelativePath, SystemPath};
 
@@ -32,6 +34,7 @@ pub struct CachedHoardItem {
     is_dir: bool,
     is_text: bool,
     exists: bool,
+    hoard_is_truncated: bool,
 }
 
 impl From<CachedHoardItem> for HoardItem {
@@ -67,29 +70,166 @@ impl CachedHoardItem {
     /// Any I/O errors while reading the associated files, etc.
     #[tracing::instrument]
     pub async fn try_from_hoard_item(inner: HoardItem) -> io::Result<Self> {
-        let (is_file, is_dir) = {
-            let system_exists = inner.system_path().exists();
-            let hoard_exists = inner.hoard_path().exists();
+        Self::try_from_hoard_item_with_config(inner, &PileConfig::default()).await
+    }
 
-            let is_file = (inner.system_path().is_file() || !system_exists)
-                && (inner.hoard_path().is_file() || !hoard_exists)
-                && (system_exists || hoard_exists);
+    /// Like [`CachedHoardItem::try_from_hoard_item`], but applies the given pile configuration
+    /// when reading file content.
+    ///
+    /// Currently, this only applies [`PileConfig::normalize_line_endings`].
+    ///
+    /// # Errors
+    ///
+    /// Any I/O errors while reading the associated files, etc.
+    #[tracing::instrument(skip(config))]
+    pub async fn try_from_hoard_item_with_config(
+        inner: HoardItem,
+        config: &PileConfig,
+    ) -> io::Result<Self> {
+        let (is_file, is_dir) = Self::file_types(&inner);
+        let contents = if is_file {
+            Some(Self::read_contents(&inner, config).await?)
+        } else {
+            None
+        };
+        Ok(Self::from_contents(inner, is_file, is_dir, contents))
+    }
 
-            let is_dir = (inner.system_path().is_dir() || !system_exists)
-                && (inner.hoard_path().is_dir() || !hoard_exists)
-                && (system_exists || hoard_exists);
+    /// Like [`CachedHoardItem::try_from_hoard_item_with_config`], but reuses checksums from
+    /// `cache` for files that have not changed since they were last hashed, and records the
+    /// checksums of files that have.
+    ///
+    /// File content is only read if the cached checksums are not enough to tell whether the
+    /// hoard and system files differ.
+    ///
+    /// # Errors
+    ///
+    /// Any I/O errors while reading the associated files, etc.
+    #[tracing::instrument(skip(config, cache))]
+    pub(crate) async fn try_from_hoard_item_with_cache(
+        inner: HoardItem,
+        config: &PileConfig,
+        cache: &ChecksumCache,
+    ) -> io::Result<Self> {
+        let (is_file, is_dir) = Self::file_types(&inner);
+        if !is_file {
+            return Ok(Self::from_contents(inner, is_file, is_dir, None));
+        }
 
-            (is_file, is_dir)
-        };
+        let normalized = config.normalize_line_endings.unwrap_or(false);
+        let system = cache.get(inner.system_path(), normalized).await?;
+        let hoard = cache.get(inner.hoard_path(), normalized).await?;
+        match (&system, &hoard) {
+            (Lookup::Hit(system), Lookup::Hit(hoard)) if system.checksums == hoard.checksums => {
+                return Ok(Self::from_cache_entries(inner, Some(system), Some(hoard)));
+            }
+            (Lookup::Hit(system), Lookup::Missing) => {
+                return Ok(Self::from_cache_entries(inner, Some(system), None));
+            }
+            (Lookup::Missing, Lookup::Hit(hoard)) => {
+                return Ok(Self::from_cache_entries(inner, None, Some(hoard)));
+            }
+            _ => {}
+        }
 
-        let (system_content, hoard_content) = if is_file {
-            let system_content = inner.system_content();
-            let hoard_content = inner.hoard_content();
-            let (system_content, hoard_content) = try_join!(system_content, hoard_content)?;
-            (Some(system_content), Some(hoard_content))
-        } else {
-            (None, None)
+        let (system_content, hoard_content) = Self::read_contents(&inner, config).await?;
+        let system_is_text = matches!(system_content, FileContent::Text(_));
+        let hoard_is_text = matches!(hoard_content, FileContent::Text(_));
+        let item = Self::from_contents(
+            inner,
+            is_file,
+            is_dir,
+            Some((system_content, hoard_content)),
+        );
+
+        if let Lookup::Miss(stamp) = system {
+            cache.insert(
+                item.system_path(),
+                stamp,
+                normalized,
+                system_is_text,
+                item.system_checksums.clone(),
+            );
+        }
+        if let Lookup::Miss(stamp) = hoard {
+            cache.insert(
+                item.hoard_path(),
+                stamp,
+                normalized,
+                hoard_is_text,
+                item.hoard_checksums.clone(),
+            );
+        }
+
+        Ok(item)
+    }
+
+    /// Returns whether `inner` is a file and whether it is a directory.
+    fn file_types(inner: &HoardItem) -> (bool, bool) {
+        let system_exists = inner.system_path().exists();
+        let hoard_exists = inner.hoard_path().exists();
+
+        let is_file = (inner.system_path().is_file() || !system_exists)
+            && (inner.hoard_path().is_file() || !hoard_exists)
+            && (system_exists || hoard_exists);
+
+        let is_dir = (inner.system_path().is_dir() || !system_exists)
+            && (inner.hoard_path().is_dir() || !hoard_exists)
+            && (system_exists || hoard_exists);
+
+        (is_file, is_dir)
+    }
+
+    /// Reads the content of the system and hoard files, in that order.
+    async fn read_contents(
+        inner: &HoardItem,
+        config: &PileConfig,
+    ) -> io::Result<(FileContent, FileContent)> {
+        let system_content = inner.system_content();
+        let hoard_content = inner.hoard_content();
+        let (mut system_content, mut hoard_content) = try_join!(system_content, hoard_content)?;
+        if config.normalize_line_endings.unwrap_or(false) {
+            system_content = system_content.normalize_line_endings();
+            hoard_content = hoard_content.normalize_line_endings();
+        }
+        Ok((system_content, hoard_content))
+    }
+
+    /// Creates an item for a file from the cached checksums of its system and hoard versions,
+    /// or `None` for a version that does not exist.
+    ///
+    /// The versions must either have the same checksums or only one of them may exist, because
+    /// a diff of their content cannot be created without reading them.
+    fn from_cache_entries(inner: HoardItem, system: Option<&Entry>, hoard: Option<&Entry>) -> Self {
+        let diff = match (system, hoard) {
+            (Some(_), None) => Some(Diff::HoardNotExists),
+            (None, Some(_)) => Some(Diff::SystemNotExists),
+            _ => None,
         };
+        let is_text = system.into_iter().chain(hoard).all(|entry| entry.is_text);
+
+        Self {
+            inner,
+            hoard_checksums: hoard.map(|entry| entry.checksums.clone()),
+            system_checksums: system.map(|entry| entry.checksums.clone()),
+            diff,
+            is_file: true,
+            is_dir: false,
+            is_text,
+            exists: true,
+            hoard_is_truncated: false,
+        }
+    }
+
+    /// Creates an item from the content of its system and hoard files, which is `None` if the
+    /// item is not a file.
+    fn from_contents(
+        inner: HoardItem,
+        is_file: bool,
+        is_dir: bool,
+        contents: Option<(FileContent, FileContent)>,
+    ) -> Self {
+        let (system_content, hoard_content) = contents.unzip();
 
         let system_checksums = system_content.as_ref().and_then(Self::checksums);
         let hoard_checksums = hoard_content.as_ref().and_then(Self::checksums);
@@ -118,6 +258,18 @@ impl CachedHoardItem {
             None
         };
 
+        let hoard_is_truncated = match (&system_content, &hoard_content) {
+            (Some(system_content), Some(hoard_content)) => {
+                match (Self::bytes(system_content), Self::bytes(hoard_content)) {
+                    (Some(system), Some(hoard)) => {
+                        hoard.len() < system.len() && system.starts_with(hoard)
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+
         let is_text = is_file
             && matches!(
                 (system_content, hoard_content),
@@ -129,7 +281,7 @@ impl CachedHoardItem {
 
         let exists = inner.hoard_path().exists() || inner.system_path().exists();
 
-        Ok(Self {
+        Self {
             inner,
             hoard_checksums,
             system_checksums,
@@ -138,7 +290,31 @@ impl CachedHoardItem {
             is_dir,
             is_text,
             exists,
-        })
+            hoard_is_truncated,
+        }
+    }
+
+    /// Create a cached version of the given [`HoardItem`] without reading any file content.
+    ///
+    /// The result has no checksums or diff, so it is only useful for files that are skipped.
+    pub(crate) fn without_content(inner: HoardItem) -> Self {
+        let system_exists = inner.system_path().exists();
+        let hoard_exists = inner.hoard_path().exists();
+        let is_file = (inner.system_path().is_file() || !system_exists)
+            && (inner.hoard_path().is_file() || !hoard_exists)
+            && (system_exists || hoard_exists);
+
+        Self {
+            inner,
+            hoard_checksums: None,
+            system_checksums: None,
+            diff: None,
+            is_file,
+            is_dir: false,
+            is_text: false,
+            exists: system_exists || hoard_exists,
+            hoard_is_truncated: false,
+        }
     }
 
     /// Returns the name of the pile this item belongs to, if any.
@@ -220,44 +396,45 @@ impl CachedHoardItem {
         !self.is_text
     }
 
+    /// Returns whether the hoard file is a truncated copy of the system file.
+    ///
+    /// This is `true` if both files exist and the hoard file's content is a strict prefix of the
+    /// system file's, as is left behind by an interrupted copy.
+    #[must_use]
+    pub fn hoard_is_truncated(&self) -> bool {
+        self.hoard_is_truncated
+    }
+
     /// Returns the precomputed diff between the hoard and system files of this [`CachedHoardItem`].
     #[must_use]
     pub fn diff(&self) -> Option<&Diff> {
         self.diff.as_ref()
     }
 
-    #[tracing::instrument(name = "calculate_calculate_cached_hoard_item_checksums")]
-
-    fn checksums(content: &FileContent) -> Option<BTreeMap<ChecksumType, Checksum>> {
+    fn bytes(content: &FileContent) -> Option<&[u8]> {
         match content {
+            FileContent::Text(text) => Some(text.as_bytes()),
+            FileContent::Binary(bytes) => Some(bytes),
             FileContent::Missing => None,
-            FileContent::Text(s) => {
-                let mut map = BTreeMap::new();
-                map.insert(
-                    ChecksumType::MD5,
-                    Checksum::MD5(MD5::from_data(s.as_bytes())),
-                );
-                map.insert(
-                    ChecksumType::SHA256,
-                    Checksum::SHA256(SHA256::from_data(s.as_bytes())),
-                );
-                Some(map)
-            }
-            FileContent::Binary(data) => {
-                let mut map = BTreeMap::new();
-                map.insert(
-                    ChecksumType::MD5,
-                    Checksum::MD5(MD5::from_data(data.as_slice())),
-                );
-                map.insert(
-                    ChecksumType::SHA256,
-                    Checksum::SHA256(SHA256::from_data(data.as_slice())),
-                );
-                Some(map)
-            }
         }
     }
 
+    #[tracing::instrument(name = "calculate_calculate_cached_hoard_item_checksums")]
+
+    fn checksums(content: &FileContent) -> Option<BTreeMap<ChecksumType, Checksum>> {
+        let data = content.as_bytes()?;
+        Some(
+            [
+                ChecksumType::MD5,
+                ChecksumType::SHA256,
+                ChecksumType::BLAKE3,
+            ]
+            .into_iter()
+            .map(|typ| (typ, Checksum::from_data(typ, data)))
+            .collect(),
+        )
+    }
+
     /// Returns the requested [`ChecksumType`] for the Hoard version of the file.
     ///
     /// # Errors
@@ -272,6 +449,7 @@ impl CachedHoardItem {
         match typ {
             ChecksumType::MD5 => self.hoard_md5(),
             ChecksumType::SHA256 => self.hoard_sha256(),
+            ChecksumType::BLAKE3 => self.hoard_blake3(),
         }
     }
 
@@ -301,6 +479,19 @@ impl CachedHoardItem {
             .and_then(|map| map.get(&ChecksumType::SHA256).cloned())
     }
 
+    /// Returns the BLAKE3 checksum for the Hoard version of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Ok(None)` if the file does not exist, and errors for all other
+    /// error cases for [`std::fs::read`], including if `hoard_path` is a directory.
+    #[must_use]
+    pub fn hoard_blake3(&self) -> Option<Checksum> {
+        self.hoard_checksums
+            .as_ref()
+            .and_then(|map| map.get(&ChecksumType::BLAKE3).cloned())
+    }
+
     /// Returns the requested [`ChecksumType`] for the system version of the file.
     ///
     /// # Errors
@@ -315,6 +506,7 @@ impl CachedHoardItem {
         match typ {
             ChecksumType::MD5 => self.system_md5(),
             ChecksumType::SHA256 => self.system_sha256(),
+            ChecksumType::BLAKE3 => self.system_blake3(),
         }
     }
 
@@ -343,4 +535,17 @@ impl CachedHoardItem {
             .as_ref()
             .and_then(|map| map.get(&ChecksumType::SHA256).cloned())
     }
+
+    /// Returns the BLAKE3 checksum for the system version of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Ok(None)` if the file does not exist, and errors for all other
+    /// error cases for [`std::fs::read`], including if `system_path` is a directory.
+    #[must_use]
+    pub fn system_blake3(&self) -> Option<Checksum> {
+        self.system_checksums
+            .as_ref()
+            .and_then(|map| map.get(&ChecksumType::BLAKE3).cloned())
+    }
 }
@@ -3,8 +3,9 @@ use tap::TapFallible;
 
 use tokio::io;
 
-use crate::checksum::{Checksum, ChecksumType, MD5, SHA256};
+use crate::checksum::{Checksum, ChecksumType};
 use crate::diff::FileContent;
+use crate::hoard::PileConfig;
 use crate::newtypes::PileName;
 use crate::paths::{HoardPath, RelativePath, SystemPath};
 
@@ -136,12 +137,15 @@ impl HoardItem {
             )))
     }
 
-    async fn raw_content(path: &Path) -> io::Result<Option<Vec<u8>>> {
-        Ok(match Self::content(path).await? {
-            FileContent::Missing => None,
-            FileContent::Binary(data) => Some(data),
-            FileContent::Text(s) => Some(s.into_bytes()),
-        })
+    async fn checksum(path: &Path, typ: ChecksumType) -> io::Result<Option<Checksum>> {
+        match Checksum::compute(path, typ).await {
+            Ok(checksum) => Ok(Some(checksum)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error).tap_err(crate::tap_log_error_msg(&format!(
+                "failed to compute checksum of {}",
+                path.display()
+            ))),
+        }
     }
 
     /// Returns the content, as bytes, of the system version of the file.
@@ -180,9 +184,32 @@ impl HoardItem {
         match typ {
             ChecksumType::MD5 => self.hoard_md5().await,
             ChecksumType::SHA256 => self.hoard_sha256().await,
+            ChecksumType::BLAKE3 => self.hoard_blake3().await,
         }
     }
 
+    /// Like [`HoardItem::hoard_checksum`], but applies the given pile configuration when
+    /// reading the file, e.g. [`PileConfig::normalize_line_endings`].
+    ///
+    /// # Errors
+    ///
+    /// See [`HoardItem::hoard_checksum`].
+    #[tracing::instrument(name = "hoard_item_hoard_checksum_with_config", skip(config))]
+    pub async fn hoard_checksum_with_config(
+        &self,
+        typ: ChecksumType,
+        config: &PileConfig,
+    ) -> io::Result<Option<Checksum>> {
+        if !config.normalize_line_endings.unwrap_or(false) {
+            return self.hoard_checksum(typ).await;
+        }
+
+        let content = self.hoard_content().await?.normalize_line_endings();
+        Ok(content
+            .as_bytes()
+            .map(|content| Checksum::from_data(typ, content)))
+    }
+
     /// Returns the MD5 checksum for the Hoard version of the file.
     ///
     /// # Errors
@@ -191,9 +218,7 @@ impl HoardItem {
     /// error cases for [`std::fs::read`], including if `hoard_path` is a directory.
     #[tracing::instrument(name = "hoard_item_hoard_md5")]
     pub async fn hoard_md5(&self) -> io::Result<Option<Checksum>> {
-        Self::raw_content(self.hoard_path())
-            .await
-            .map(|content| content.as_deref().map(Self::md5))
+        Self::checksum(self.hoard_path(), ChecksumType::MD5).await
     }
 
     /// Returns the SHA256 checksum for the Hoard version of the file.
@@ -204,9 +229,18 @@ impl HoardItem {
     /// error cases for [`std::fs::read`], including if `hoard_path` is a directory.
     #[tracing::instrument(name = "hoard_item_hoard_sha256")]
     pub async fn hoard_sha256(&self) -> io::Result<Option<Checksum>> {
-        Self::raw_content(self.hoard_path())
-            .await
-            .map(|content| content.as_deref().map(Self::sha256))
+        Self::checksum(self.hoard_path(), ChecksumType::SHA256).await
+    }
+
+    /// Returns the BLAKE3 checksum for the Hoard version of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Ok(None)` if the file does not exist, and errors for all other
+    /// error cases for [`std::fs::read`], including if `hoard_path` is a directory.
+    #[tracing::instrument(name = "hoard_item_hoard_blake3")]
+    pub async fn hoard_blake3(&self) -> io::Result<Option<Checksum>> {
+        Self::checksum(self.hoard_path(), ChecksumType::BLAKE3).await
     }
 
     /// Returns the requested [`ChecksumType`] for the system version of the file.
@@ -223,9 +257,32 @@ impl HoardItem {
         match typ {
             ChecksumType::MD5 => self.system_md5().await,
             ChecksumType::SHA256 => self.system_sha256().await,
+            ChecksumType::BLAKE3 => self.system_blake3().await,
         }
     }
 
+    /// Like [`HoardItem::system_checksum`], but applies the given pile configuration when
+    /// reading the file, e.g. [`PileConfig::normalize_line_endings`].
+    ///
+    /// # Errors
+    ///
+    /// See [`HoardItem::system_checksum`].
+    #[tracing::instrument(name = "hoard_item_system_checksum_with_config", skip(config))]
+    pub async fn system_checksum_with_config(
+        &self,
+        typ: ChecksumType,
+        config: &PileConfig,
+    ) -> io::Result<Option<Checksum>> {
+        if !config.normalize_line_endings.unwrap_or(false) {
+            return self.system_checksum(typ).await;
+        }
+
+        let content = self.system_content().await?.normalize_line_endings();
+        Ok(content
+            .as_bytes()
+            .map(|content| Checksum::from_data(typ, content)))
+    }
+
     /// Returns the MD5 checksum for the system version of the file.
     ///
     /// # Errors
@@ -234,9 +291,7 @@ impl HoardItem {
     /// error cases for [`std::fs::read`], including if `system_path` is a directory.
     #[tracing::instrument(name = "hoard_item_system_md5")]
     pub async fn system_md5(&self) -> io::Result<Option<Checksum>> {
-        Self::raw_content(self.system_path())
-            .await
-            .map(|content| content.as_deref().map(Self::md5))
+        Self::checksum(self.system_path(), ChecksumType::MD5).await
     }
 
     /// Returns the SHA256 checksum for the system version of the file.
@@ -247,17 +302,18 @@ impl HoardItem {
     /// error cases for [`std::fs::read`], including if `system_path` is a directory.
     #[tracing::instrument(name = "hoard_item_system_sha256")]
     pub async fn system_sha256(&self) -> io::Result<Option<Checksum>> {
-        Self::raw_content(self.system_path())
-            .await
-            .map(|content| content.as_deref().map(Self::sha256))
-    }
-
-    fn md5(content: &[u8]) -> Checksum {
-        Checksum::MD5(MD5::from_data(content))
+        Self::checksum(self.system_path(), ChecksumType::SHA256).await
     }
 
-    fn sha256(content: &[u8]) -> Checksum {
-        Checksum::SHA256(SHA256::from_data(content))
+    /// Returns the BLAKE3 checksum for the system version of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Ok(None)` if the file does not exist, and errors for all other
+    /// error cases for [`std::fs::read`], including if `system_path` is a directory.
+    #[tracing::instrument(name = "hoard_item_system_blake3")]
+    pub async fn system_blake3(&self) -> io::Result<Option<Checksum>> {
+        Self::checksum(self.system_path(), ChecksumType::BLAKE3).await
     }
 }
 
@@ -266,6 +322,7 @@ mod tests {
     use std::fs;
     use std::path::PathBuf;
 
+    use crate::checksum::{MD5, SHA256};
     use crate::test::Tester;
 
     use super::*;
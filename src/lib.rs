@@ -15,6 +15,25 @@
 //!   [`Hoard`](crate::config::builder::hoard::Hoard).
 //! - [`Environment`](crate::config::builder::environment::Environment): A combination of conditions
 //!   that can be used to determine where to find files in a [`Pile`](crate::config::builder::hoard::Pile).
+//!
+//! # Library Usage
+//!
+//! Besides running a [`Command`](crate::command::Command) with [`Config::run`], a [`Config`] can
+//! back up, restore, diff, and check the status of hoards directly, returning structured results
+//! instead of printing them:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use hoard::config::Builder;
+//!
+//! let config = Builder::from_default_file().await?.build()?;
+//! let report = config.backup(&[]).await?;
+//! for (hoard, operation) in report.changes() {
+//!     println!("{hoard}: {operation:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
 
 #![deny(clippy::all)]
 #![deny(clippy::correctness)]
@@ -62,6 +81,7 @@ while_true
 )]
 
 pub use config::Config;
+pub use exit_code::ExitCode;
 
 pub mod checkers;
 pub mod checksum;
@@ -70,13 +90,18 @@ pub mod command;
 pub mod config;
 pub(crate) mod diff;
 pub mod dirs;
+pub(crate) mod disk_space;
 pub mod env_vars;
+pub mod exit_code;
 pub mod filters;
 pub mod hoard;
 pub mod hoard_item;
+pub mod lock;
 pub mod logging;
 pub mod newtypes;
 pub mod paths;
+pub mod progress;
+pub mod retry;
 pub mod test;
 
 /// The default file stem of the configuration file (i.e. without file extension).
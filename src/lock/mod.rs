@@ -0,0 +1,93 @@
+//! An advisory lock on the data directory, so that only one `hoard` process changes it at a time.
+//!
+//! Commands that change the hoards or operation logs hold the lock for as long as they run. The
+//! lock is released when the [`DataDirLock`] is dropped, including while unwinding from a panic,
+//! and by the operating system if the process exits in any other way.
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use tokio::io;
+
+#[cfg(unix)]
+use unix as sys;
+#[cfg(windows)]
+use win as sys;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod win;
+
+/// The name of the lock file in the data directory.
+pub const LOCK_FILE_NAME: &str = "hoard.lock";
+
+/// Errors that may occur while locking the data directory.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Another process holds the lock.
+    #[error(
+        "another hoard process is running -- wait for it to finish or, if none is, pass --no-lock (lock file: {})",
+        .0.display()
+    )]
+    Locked(PathBuf),
+    /// The lock file could not be created or locked.
+    #[error("failed to lock {}: {error}", path.display())]
+    IO {
+        /// The path to the lock file.
+        path: PathBuf,
+        /// The error that occurred.
+        #[source]
+        error: io::Error,
+    },
+}
+
+/// A held lock on the data directory, released when dropped.
+#[derive(Debug)]
+pub struct DataDirLock {
+    _lock: sys::Lock,
+}
+
+impl DataDirLock {
+    /// Locks `data_dir`, failing immediately if another process already holds the lock.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::Locked`] if another process holds the lock.
+    /// - [`Error::IO`] if the lock file could not be created or locked.
+    #[tracing::instrument(level = "debug")]
+    pub fn acquire(data_dir: &Path) -> Result<Self, Error> {
+        let path = data_dir.join(LOCK_FILE_NAME);
+        let io_error = |error| Error::IO {
+            path: path.clone(),
+            error,
+        };
+        std::fs::create_dir_all(data_dir)
+            .map_err(io_error)
+            .or_else(crate::create_log_error)?;
+        match sys::try_lock(&path) {
+            Ok(Some(lock)) => {
+                tracing::debug!("locked {}", path.display());
+                Ok(Self { _lock: lock })
+            }
+            Ok(None) => crate::create_log_error(Error::Locked(path)),
+            Err(error) => crate::create_log_error(io_error(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let dir = tempfile::tempdir().expect("failed to create temporary directory");
+        let lock = DataDirLock::acquire(dir.path()).expect("first lock should succeed");
+        let error = DataDirLock::acquire(dir.path()).expect_err("second lock should fail");
+        assert!(matches!(error, Error::Locked(path) if path == dir.path().join(LOCK_FILE_NAME)));
+
+        drop(lock);
+        DataDirLock::acquire(dir.path()).expect("lock should succeed once released");
+    }
+}
@@ -0,0 +1,26 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::windows::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// The error returned when opening a file that another process has open without sharing it.
+const ERROR_SHARING_VIOLATION: i32 = 32;
+
+pub(super) type Lock = File;
+
+/// Opens the file at `path` without sharing it with any other handle, creating it if necessary.
+///
+/// Returns `None` if another handle already has the file open.
+pub(super) fn try_lock(path: &Path) -> io::Result<Option<Lock>> {
+    match OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .share_mode(0)
+        .open(path)
+    {
+        Ok(file) => Ok(Some(file)),
+        Err(error) if error.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
@@ -3,6 +3,7 @@
 //! [`Formatter`] does the heavy lifting of formatting the output.
 //!
 //! [`get_subscriber`] returns a default [`SubscriberBuilder`] that can be used without any changes.
+//! [`get_subscriber_with_level`] does the same, but lets the CLI verbosity flags set the level.
 
 use std::collections::BTreeMap;
 use std::fmt;
@@ -164,9 +165,33 @@ where
 
 /// Returns a [`SubscriberBuilder`] with the default settings for Hoard.
 pub fn get_subscriber() -> SubscriberBuilder<Formatter, Formatter, EnvFilter> {
-    let max_level = {
-        let env_str = std::env::var(LOG_ENV).unwrap_or_else(|_| String::new());
+    get_subscriber_with_level(None)
+}
 
+/// Returns a [`SubscriberBuilder`] like [`get_subscriber`], but logging at `level` if given.
+///
+/// The given level replaces any bare level set in `HOARD_LOG`, but directives for specific
+/// targets (e.g. `hoard::checkers=trace`) are still applied on top of it.
+pub fn get_subscriber_with_level(
+    level: Option<Level>,
+) -> SubscriberBuilder<Formatter, Formatter, EnvFilter> {
+    let env_str = std::env::var(LOG_ENV).unwrap_or_else(|_| String::new());
+
+    let env_filter = match level {
+        None => EnvFilter::try_from_env(LOG_ENV).ok(),
+        Some(level) => {
+            // Drop any bare levels so they do not conflict with the given one
+            let directives = env_str
+                .split(',')
+                .map(str::trim)
+                .filter(|directive| Level::from_str(directive).is_err())
+                .collect::<Vec<_>>()
+                .join(",");
+            Some(EnvFilter::new(directives).add_directive(level.into()))
+        }
+    };
+
+    let max_level = level.unwrap_or_else(|| {
         // Get the last item that is only a level
         let level_opt = env_str
             .split(',')
@@ -182,10 +207,10 @@ pub fn get_subscriber() -> SubscriberBuilder<Formatter, Formatter, EnvFilter> {
                 Level::INFO
             }
         })
-    };
+    });
 
-    let env_filter = EnvFilter::try_from_env(LOG_ENV)
-        .unwrap_or_else(|_| EnvFilter::default().add_directive(max_level.into()));
+    let env_filter =
+        env_filter.unwrap_or_else(|| EnvFilter::default().add_directive(max_level.into()));
 
     FmtSubscriber::builder()
         .with_env_filter(env_filter)
@@ -1,14 +1,14 @@
-use hoard::Config;
+use hoard::{Config, ExitCode};
 use tracing_subscriber::util::SubscriberInitExt;
 mod logging;
 
-fn error_and_exit<E: std::error::Error>(err: E) -> ! {
+fn error_and_exit<E: std::error::Error>(err: E, code: ExitCode) -> ! {
     // Ignore error if default subscriber already exists
     // This just helps ensure that logging happens and is
     // consistent.
     let _guard = logging::get_subscriber().set_default();
     tracing::error!("{}", err);
-    std::process::exit(1);
+    std::process::exit(code.code());
 }
 
 #[tokio::main]
@@ -16,16 +16,37 @@ async fn main() {
     // Set up default logging
     // There is no obvious way to set up a default logging level in case the env
     // isn't set, so use this match thing instead.
-    let _guard = logging::get_subscriber().set_default();
+    let _guard = logging::get_subscriber()
+        .with_writer(hoard::progress::LogWriter::default)
+        .set_default();
+
+    if let Err(err) = hoard::dirs::resolve_dir_overrides() {
+        error_and_exit(err, ExitCode::Error);
+    }
 
     // Get configuration
     let config = match Config::load().await {
         Ok(config) => config,
-        Err(err) => error_and_exit(err),
+        Err(err) => {
+            let code = err.exit_code();
+            error_and_exit(err, code)
+        }
     };
 
+    // Replace the default logging if `--quiet` or `--verbose` were given
+    let _level_guard = config.log_level.map(|level| {
+        logging::get_subscriber_with_level(Some(level))
+            .with_writer(hoard::progress::LogWriter::default)
+            .set_default()
+    });
+
     // Run command with config
-    if let Err(err) = config.run().await {
-        error_and_exit(err);
+    match config.run().await {
+        Ok(ExitCode::Success) => {}
+        Ok(code) => std::process::exit(code.code()),
+        Err(err) => {
+            let code = err.exit_code();
+            error_and_exit(err, code);
+        }
     }
 }
@@ -56,7 +56,10 @@ mod tests {
             ("", Err(Error::DisallowedName(String::new()))),
             (
                 "invalid name",
-                Err(Error::DisallowedCharacters(String::from("invalid name"))),
+                Err(Error::DisallowedCharacters(
+                    String::from("invalid name"),
+                    ' ',
+                )),
             ),
             ("valid", Ok(EnvironmentName(String::from("valid")))),
         ];
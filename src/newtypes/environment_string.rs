@@ -209,7 +209,7 @@ mod tests {
                 (Some(s), Error::EmptyName) => {
                     panic!("expected Error::InvalidName(\"{s}\"), got {error:?}")
                 }
-                (Some(s1), Error::DisallowedName(s2) | Error::DisallowedCharacters(s2)) => {
+                (Some(s1), Error::DisallowedName(s2) | Error::DisallowedCharacters(s2, _)) => {
                     assert_eq!(s1, s2, "expected invalid name to be \"{s1}\", got \"{s2}\"");
                 }
             }
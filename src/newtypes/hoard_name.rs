@@ -66,9 +66,34 @@ mod tests {
             ),
             (
                 String::from("bad name"),
-                Err(Error::DisallowedCharacters(String::from("bad name"))),
+                Err(Error::DisallowedCharacters(String::from("bad name"), ' ')),
+            ),
+            (
+                String::from("../evil"),
+                Err(Error::DisallowedCharacters(String::from("../evil"), '/')),
+            ),
+            (
+                String::from("a/b"),
+                Err(Error::DisallowedCharacters(String::from("a/b"), '/')),
+            ),
+            (
+                String::from("a\\b"),
+                Err(Error::DisallowedCharacters(String::from("a\\b"), '\\')),
+            ),
+            (
+                String::from("game:saves"),
+                Err(Error::DisallowedCharacters(String::from("game:saves"), ':')),
+            ),
+            (
+                String::from(".."),
+                Err(Error::DisallowedName(String::from(".."))),
+            ),
+            (
+                String::from("."),
+                Err(Error::DisallowedName(String::from("."))),
             ),
             (String::from("valid"), Ok(HoardName(String::from("valid")))),
+            (String::from("v1.2"), Ok(HoardName(String::from("v1.2")))),
         ];
 
         for (s, expected) in inputs {
@@ -0,0 +1,104 @@
+use std::{fmt, str::FromStr};
+
+use super::{Error, HoardName, NonEmptyPileName};
+
+/// A hoard given on the command line, optionally limited to one of its named piles by writing
+/// the selector as `hoard:pile`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HoardSelector {
+    hoard: HoardName,
+    pile: Option<NonEmptyPileName>,
+}
+
+impl HoardSelector {
+    /// The name of the selected hoard.
+    #[must_use]
+    pub fn hoard(&self) -> &HoardName {
+        &self.hoard
+    }
+
+    /// The name of the selected pile, or `None` if the whole hoard is selected.
+    #[must_use]
+    pub fn pile(&self) -> Option<&NonEmptyPileName> {
+        self.pile.as_ref()
+    }
+}
+
+impl FromStr for HoardSelector {
+    type Err = Error;
+
+    #[tracing::instrument(level = "trace", name = "parse_hoard_selector")]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hoard, pile) = match s.split_once(':') {
+            Some((hoard, pile)) => (hoard, Some(pile.parse()?)),
+            None => (s, None),
+        };
+
+        Ok(Self {
+            hoard: hoard.parse()?,
+            pile,
+        })
+    }
+}
+
+impl From<HoardName> for HoardSelector {
+    fn from(hoard: HoardName) -> Self {
+        Self { hoard, pile: None }
+    }
+}
+
+impl fmt::Display for HoardSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.pile {
+            None => write!(f, "{}", self.hoard),
+            Some(pile) => write!(f, "{}:{}", self.hoard, pile),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let hoard: HoardName = "game".parse().unwrap();
+        let pile: NonEmptyPileName = "saves".parse().unwrap();
+        let inputs = [
+            (
+                "game",
+                Ok(HoardSelector {
+                    hoard: hoard.clone(),
+                    pile: None,
+                }),
+            ),
+            (
+                "game:saves",
+                Ok(HoardSelector {
+                    hoard,
+                    pile: Some(pile),
+                }),
+            ),
+            ("game:", Err(Error::DisallowedName(String::new()))),
+            (":saves", Err(Error::DisallowedName(String::new()))),
+            (
+                "game:saves:extra",
+                Err(Error::DisallowedCharacters(
+                    String::from("saves:extra"),
+                    ':',
+                )),
+            ),
+        ];
+
+        for (s, expected) in inputs {
+            assert_eq!(s.parse::<HoardSelector>(), expected, "parsing {s}");
+        }
+    }
+
+    #[test]
+    fn test_to_string() {
+        for s in ["game", "game:saves"] {
+            assert_eq!(s, s.parse::<HoardSelector>().unwrap().to_string());
+        }
+    }
+}
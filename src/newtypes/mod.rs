@@ -1,28 +1,34 @@
 //! Newtypes used to enforce invariants throughout this library.
 //!
-//! - Names (`*Name`) must contain only alphanumeric characters, dash (`-`), or underscore (`_`).
+//! - Names (`*Name`) must contain only alphanumeric characters, dash (`-`), underscore (`_`), or
+//!   period (`.`). In particular, path separators and `:` (which separates a hoard from a pile in a
+//!   [`HoardSelector`]) are not allowed, and neither are `.` and `..`, so that a name can never
+//!   point outside of the directory it is used in.
 //! - [`EnvironmentString`] has its own requirements.
+//! - [`HoardSelector`] is a [`HoardName`], optionally followed by `:` and a [`NonEmptyPileName`].
 
 use thiserror::Error;
 
 pub use environment_name::EnvironmentName;
 pub use environment_string::EnvironmentString;
 pub use hoard_name::HoardName;
+pub use hoard_selector::HoardSelector;
 pub use non_empty_pile_name::NonEmptyPileName;
 pub use pile_name::PileName;
 
 mod environment_name;
 mod environment_string;
 mod hoard_name;
+mod hoard_selector;
 mod non_empty_pile_name;
 mod pile_name;
 
 /// Errors that may occur while creating an instance of one of this newtypes.
 #[derive(Debug, Error, PartialEq, Eq)]
 pub enum Error {
-    /// The given string contains disallowed characters.
-    #[error("invalid name \"{0}\": must contain only alphanumeric characters, '-', '_', or '.'")]
-    DisallowedCharacters(String),
+    /// The given string contains a disallowed character, the first of which is included.
+    #[error("invalid name \"{0}\": character {1:?} is not allowed (must contain only alphanumeric characters, '-', '_', or '.')")]
+    DisallowedCharacters(String, char),
     /// The given string is a disallowed name.
     #[error("name \"{0}\" is not allowed")]
     DisallowedName(String),
@@ -31,15 +37,15 @@ pub enum Error {
     EmptyName,
 }
 
-const DISALLOWED_NAMES: [&str; 2] = ["", "config"];
+const DISALLOWED_NAMES: [&str; 4] = ["", ".", "..", "config"];
 
 #[tracing::instrument(level = "trace")]
 fn validate_name(name: String) -> Result<String, Error> {
-    if !name
+    if let Some(c) = name
         .chars()
-        .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+        .find(|&c| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
     {
-        return crate::create_log_error(Error::DisallowedCharacters(name));
+        return crate::create_log_error(Error::DisallowedCharacters(name, c));
     }
 
     if DISALLOWED_NAMES
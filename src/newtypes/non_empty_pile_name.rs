@@ -91,6 +91,18 @@ mod tests {
                 String::from("config"),
                 Err(Error::DisallowedName(String::from("config"))),
             ),
+            (
+                String::from("../evil"),
+                Err(Error::DisallowedCharacters(String::from("../evil"), '/')),
+            ),
+            (
+                String::from("a/b"),
+                Err(Error::DisallowedCharacters(String::from("a/b"), '/')),
+            ),
+            (
+                String::from(".."),
+                Err(Error::DisallowedName(String::from(".."))),
+            ),
         ];
 
         for (s, expected) in inputs {
@@ -54,6 +54,13 @@ impl<'de> de::Visitor<'de> for PileNameVisitor {
     {
         Ok(PileName(None))
     }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visit_none()
+    }
 }
 
 impl<'de> Deserialize<'de> for PileName {
@@ -149,7 +156,10 @@ mod tests {
             ("name", Ok(PileName(Some("name".parse().unwrap())))),
             (
                 "invalid name",
-                Err(Error::DisallowedCharacters(String::from("invalid name"))),
+                Err(Error::DisallowedCharacters(
+                    String::from("invalid name"),
+                    ' ',
+                )),
             ),
         ];
 
@@ -165,8 +175,8 @@ mod tests {
                         panic!("expected {err1:?}, got {err2:?}");
                     }
                     (
-                        Error::DisallowedName(invalid1) | Error::DisallowedCharacters(invalid1),
-                        Error::DisallowedName(invalid2) | Error::DisallowedCharacters(invalid2),
+                        Error::DisallowedName(invalid1) | Error::DisallowedCharacters(invalid1, _),
+                        Error::DisallowedName(invalid2) | Error::DisallowedCharacters(invalid2, _),
                     ) => {
                         assert_eq!(
                             invalid1, invalid2,
@@ -196,6 +206,13 @@ mod tests {
         assert_tokens(&name, &[Token::None]);
     }
 
+    #[test]
+    fn test_serde_unit() {
+        assert_de_tokens(&PileName::anonymous(), &[Token::Unit]);
+        let name: PileName = serde_json::from_str("null").unwrap();
+        assert_eq!(name, PileName::anonymous());
+    }
+
     #[test]
     fn test_serde_empty_str() {
         serde_test::assert_de_tokens_error::<PileName>(
@@ -52,7 +52,7 @@ pub fn normalize_path(path: &Path) -> PathBuf {
             }
             Component::CurDir => {}
             Component::ParentDir => {
-                if matches!(ret.components().last(), Some(Component::Normal(_))) {
+                if matches!(ret.components().next_back(), Some(Component::Normal(_))) {
                     ret.pop();
                 } else {
                     ret.push(Component::ParentDir);
@@ -290,6 +290,14 @@ impl TryFrom<PathBuf> for RelativePath {
     }
 }
 
+impl FromStr for RelativePath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(PathBuf::from(s))
+    }
+}
+
 impl TryFrom<Option<PathBuf>> for RelativePath {
     type Error = Error;
 
@@ -0,0 +1,118 @@
+//! Progress bars for backing up and restoring hoards with many files.
+//!
+//! Progress is drawn on stderr, and only if it is a terminal. Log output should be written
+//! through [`LogWriter`] so that log lines are not drawn over by the progress bar.
+
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// The progress bar currently being drawn, if any.
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+const TEMPLATE: &str = "{prefix}: [{bar:30}] {pos}/{len} {wide_msg}";
+
+/// Runs `f` with the active progress bar, if any, cleared from the terminal.
+fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    let active = ACTIVE
+        .lock()
+        .map(|active| active.clone())
+        .unwrap_or_default();
+    match active {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
+
+/// Writes log output to stdout, hiding the active progress bar while doing so.
+///
+/// Use [`LogWriter::default`] as the writer of a [`tracing_subscriber`] subscriber.
+#[derive(Debug, Default)]
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        suspend(|| io::stdout().write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        suspend(|| io::stdout().write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Shows how many of the files in a hoard have been processed, and which file is being
+/// processed.
+///
+/// The progress bar is removed when this is dropped.
+#[derive(Debug)]
+pub(crate) struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    /// Starts showing progress for `total` files, labeled with `prefix`.
+    ///
+    /// Nothing is drawn if `quiet` is true or stderr is not a terminal.
+    pub(crate) fn new(prefix: String, total: usize, quiet: bool) -> Self {
+        if quiet || !io::stderr().is_terminal() {
+            return Self {
+                bar: ProgressBar::hidden(),
+            };
+        }
+
+        let style = ProgressStyle::with_template(TEMPLATE)
+            .expect("progress template should be valid")
+            .progress_chars("=> ");
+        let bar = ProgressBar::new(total as u64)
+            .with_style(style)
+            .with_prefix(prefix);
+        if let Ok(mut active) = ACTIVE.lock() {
+            active.replace(bar.clone());
+        }
+        Self { bar }
+    }
+
+    /// Shows `path` as the file currently being processed.
+    pub(crate) fn start_file(&self, path: &Path) {
+        self.bar.set_message(path.display().to_string());
+    }
+
+    /// Marks one more file as processed.
+    pub(crate) fn finish_file(&self) {
+        self.bar.inc(1);
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.bar.is_hidden() {
+            return;
+        }
+        self.bar.finish_and_clear();
+        if let Ok(mut active) = ACTIVE.lock() {
+            active.take();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_progress_is_hidden() {
+        let progress = Progress::new(String::from("hoard"), 2, true);
+        assert!(progress.bar.is_hidden());
+        progress.start_file(Path::new("file"));
+        progress.finish_file();
+        assert_eq!(progress.bar.position(), 1);
+        assert!(ACTIVE.lock().unwrap().is_none());
+        assert_eq!(suspend(|| 5), 5);
+    }
+}
@@ -0,0 +1,221 @@
+//! Retrying file operations that fail with transient errors.
+//!
+//! Network filesystems like NFS and SMB can fail reads and writes with errors like `EIO` or a
+//! timeout that go away if the operation is tried again. Operations wrapped with [`retry`] are
+//! attempted again, waiting twice as long before each new attempt, up to the number of times set
+//! in the given [`RetryPolicy`]. Errors that will not go away on their own, like a missing file or
+//! missing permissions, are returned immediately.
+
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+use tokio::io;
+
+/// The default number of times to retry an operation that failed with a transient error.
+pub const DEFAULT_RETRIES: u32 = 0;
+
+/// The default number of milliseconds to wait before the first retry.
+pub const DEFAULT_RETRY_DELAY: u64 = 100;
+
+/// The longest time to wait between two attempts, no matter how many retries were made.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How often to retry a file operation that failed with a transient error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times to retry the operation.
+    pub retries: u32,
+    /// How long to wait before the first retry. Each later retry waits twice as long as the one
+    /// before.
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_RETRIES,
+            delay: Duration::from_millis(DEFAULT_RETRY_DELAY),
+        }
+    }
+}
+
+/// Returns whether `error` may go away if the operation is tried again.
+fn is_transient(error: &io::Error) -> bool {
+    if matches!(
+        error.kind(),
+        io::ErrorKind::Interrupted | io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+    ) {
+        return true;
+    }
+
+    error.raw_os_error().is_some_and(is_transient_os_error)
+}
+
+#[cfg(unix)]
+fn is_transient_os_error(code: i32) -> bool {
+    use nix::errno::Errno;
+
+    matches!(
+        Errno::from_raw(code),
+        Errno::EIO | Errno::ETIMEDOUT | Errno::ESTALE | Errno::ECONNRESET | Errno::ENETRESET
+    )
+}
+
+#[cfg(windows)]
+fn is_transient_os_error(code: i32) -> bool {
+    // ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, and ERROR_SEM_TIMEOUT
+    matches!(code, 59 | 64 | 121)
+}
+
+/// Returns how long to wait before retry number `attempt`, starting at 1.
+fn delay_for(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2_u32.saturating_pow(attempt - 1))
+        .min(MAX_DELAY)
+}
+
+/// Runs `operation` on `path`, running it again after a delay if it fails with a transient error,
+/// as often as `policy` allows.
+///
+/// # Errors
+///
+/// The last error returned by `operation`, if it never succeeded.
+pub(crate) async fn retry<T, F, Fut>(
+    path: &Path,
+    policy: RetryPolicy,
+    operation: F,
+) -> io::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = io::Result<T>>,
+{
+    retry_with(path, policy.retries, policy.delay, operation).await
+}
+
+async fn retry_with<T, F, Fut>(
+    path: &Path,
+    retries: u32,
+    base_delay: Duration,
+    mut operation: F,
+) -> io::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = io::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(error) if attempt < retries && is_transient(&error) => {
+                attempt += 1;
+                let delay = delay_for(base_delay, attempt);
+                tracing::warn!(
+                    %error,
+                    "accessing {} failed, retrying in {:?} ({}/{})",
+                    path.display(),
+                    delay,
+                    attempt,
+                    retries
+                );
+                // Sleep on a blocking thread so other files can be processed in the meantime.
+                tokio::task::spawn_blocking(move || std::thread::sleep(delay))
+                    .await
+                    .expect("sleeping thread should never panic");
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Returns a fake file operation that fails with `error` the first `failures` times it is
+    /// run, and a counter of the times it was run.
+    fn inject_errors(
+        failures: usize,
+        error: fn() -> io::Error,
+    ) -> (
+        impl FnMut() -> std::future::Ready<io::Result<&'static str>>,
+        Arc<AtomicUsize>,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let operation = move || {
+            let call = counter.fetch_add(1, Ordering::Relaxed);
+            std::future::ready(if call < failures {
+                Err(error())
+            } else {
+                Ok("done")
+            })
+        };
+        (operation, calls)
+    }
+
+    #[cfg(unix)]
+    fn transient() -> io::Error {
+        io::Error::from_raw_os_error(nix::errno::Errno::EIO as i32)
+    }
+
+    #[cfg(windows)]
+    fn transient() -> io::Error {
+        io::Error::from_raw_os_error(64)
+    }
+
+    fn not_found() -> io::Error {
+        io::Error::from(io::ErrorKind::NotFound)
+    }
+
+    fn permission_denied() -> io::Error {
+        io::Error::from(io::ErrorKind::PermissionDenied)
+    }
+
+    #[tokio::test]
+    async fn test_transient_error_is_retried_until_success() {
+        let (operation, calls) = inject_errors(2, transient);
+        let result = retry_with(Path::new("file"), 3, Duration::ZERO, operation).await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_transient_error_fails_after_retries() {
+        let (operation, calls) = inject_errors(5, transient);
+        let error = retry_with(Path::new("file"), 2, Duration::ZERO, operation)
+            .await
+            .expect_err("operation should fail once retries run out");
+        assert_eq!(error.raw_os_error(), transient().raw_os_error());
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_errors_are_not_retried() {
+        for error in [not_found, permission_denied] {
+            let (operation, calls) = inject_errors(1, error);
+            let result = retry_with(Path::new("file"), 3, Duration::ZERO, operation).await;
+            assert_eq!(result.unwrap_err().kind(), error().kind());
+            assert_eq!(calls.load(Ordering::Relaxed), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_retries_by_default() {
+        let (operation, calls) = inject_errors(1, transient);
+        retry(Path::new("file"), RetryPolicy::default(), operation)
+            .await
+            .expect_err("operation should not be retried");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_max() {
+        let base = Duration::from_millis(100);
+        assert_eq!(delay_for(base, 1), Duration::from_millis(100));
+        assert_eq!(delay_for(base, 2), Duration::from_millis(200));
+        assert_eq!(delay_for(base, 4), Duration::from_millis(800));
+        assert_eq!(delay_for(base, 40), MAX_DELAY);
+    }
+}
@@ -44,15 +44,19 @@ mod macros {
 
 pub(crate) use macros::{path_string, relative_path, system_path};
 
+/// Errors that may occur while setting up a [`Tester`].
 #[derive(Debug, Error)]
 #[allow(variant_size_differences)]
 pub enum Error {
+    /// An I/O error occurred.
     #[error("I/O error: {0}")]
     IO(#[from] io::Error),
+    /// A UUID could not be parsed.
     #[error("failed to parse UUID: {0}")]
     Uuid(#[from] uuid::Error),
 }
 
+/// Test helper that sets up temporary config and data directories.
 #[derive(Debug)]
 pub struct Tester {
     config_dir: TempDir,
@@ -0,0 +1,98 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "archive";
+
+const IF_CHANGED_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.archive]
+    "unix"    = "${HOME}/archive"
+    "windows" = "${HOARD_TMP}/archive"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs {
+            if_changed: true,
+            ..BackupArgs::default()
+        },
+    }
+}
+
+async fn count_logs(tester: &Tester) -> usize {
+    let dir = tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().as_hyphenated().to_string())
+        .join(HOARD);
+    let mut entries = fs::read_dir(&dir)
+        .await
+        .expect("failed to read history dir");
+    let mut count = 0;
+    while entries.next_entry().await.unwrap().is_some() {
+        count += 1;
+    }
+    count
+}
+
+#[tokio::test]
+async fn test_backup_if_changed() {
+    let tester = Tester::new(IF_CHANGED_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    let file = system_dir.join("save");
+    fs::write(&file, "level 1")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+    assert_eq!(count_logs(&tester).await, 1);
+
+    tester.clear_output();
+    tester.expect_command(backup()).await;
+    assert_eq!(
+        count_logs(&tester).await,
+        1,
+        "unchanged backup should not write an operation log"
+    );
+    tester.assert_has_output("no changes");
+
+    fs::write(&file, "level 2")
+        .await
+        .expect("failed to modify system file");
+    tester.clear_output();
+    tester.expect_command(backup()).await;
+    assert_eq!(
+        count_logs(&tester).await,
+        2,
+        "changed backup should write an operation log"
+    );
+    tester.assert_not_has_output("no changes");
+    let hoard_content =
+        fs::read_to_string(tester.data_dir().join("hoards").join(HOARD).join("save"))
+            .await
+            .expect("failed to read hoard file");
+    assert_eq!(hoard_content, "level 2");
+}
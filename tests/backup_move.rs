@@ -0,0 +1,134 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "archive";
+
+const MOVE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.archive]
+    "unix"    = "${HOME}/archive"
+    "windows" = "${HOARD_TMP}/archive"
+"#;
+
+const FILES: [(&str, &str); 2] = [("old_save", "level 1"), ("nested/old_photo", "pixels")];
+
+fn backup(move_files: bool) -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs {
+            move_files,
+            ..BackupArgs::default()
+        },
+    }
+}
+
+/// Moves [`FILES`] into the hoard, leaving the system directory empty.
+async fn move_files(tester: &Tester) {
+    let system_dir = tester.home_dir().join(HOARD);
+    for (path, content) in FILES {
+        let path = system_dir.join(path);
+        fs::create_dir_all(path.parent().unwrap())
+            .await
+            .expect("failed to create system dir");
+        fs::write(&path, content)
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester.expect_command(backup(true)).await;
+}
+
+async fn assert_moved(tester: &Tester) {
+    let system_dir = tester.home_dir().join(HOARD);
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    for (path, content) in FILES {
+        assert!(
+            !system_dir.join(path).exists(),
+            "{path} should have been removed from the system"
+        );
+        let hoard_content = fs::read_to_string(hoard_dir.join(path))
+            .await
+            .expect("failed to read hoard file");
+        assert_eq!(hoard_content, content);
+    }
+}
+
+#[tokio::test]
+async fn test_backup_move() {
+    let tester = Tester::new(MOVE_TOML).await;
+    move_files(&tester).await;
+    assert_moved(&tester).await;
+}
+
+#[tokio::test]
+async fn test_backup_after_move_keeps_hoard_files() {
+    let tester = Tester::new(MOVE_TOML).await;
+    move_files(&tester).await;
+
+    // Moved files are missing from the system on purpose, so they are not local deletions.
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(&format!("{HOARD}: up to date"));
+
+    tester.expect_command(backup(false)).await;
+    tester.expect_command(backup(false)).await;
+    assert_moved(&tester).await;
+
+    // A new file is backed up as usual alongside the archived ones.
+    let new_file = tester.home_dir().join(HOARD).join("new_save");
+    fs::write(&new_file, "level 2").await.unwrap();
+    tester.expect_command(backup(false)).await;
+    assert_moved(&tester).await;
+    assert!(tester
+        .data_dir()
+        .join("hoards")
+        .join(HOARD)
+        .join("new_save")
+        .exists());
+
+    // Restoring brings the archived files back.
+    tester
+        .expect_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+    let system_dir = tester.home_dir().join(HOARD);
+    for (path, content) in FILES {
+        let restored = fs::read_to_string(system_dir.join(path))
+            .await
+            .expect("archived file should be restored");
+        assert_eq!(restored, content);
+    }
+}
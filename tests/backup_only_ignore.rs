@@ -0,0 +1,159 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use glob::Pattern;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use hoard::filters::Selection;
+use tokio::fs;
+
+const HOARD: &str = "game";
+
+const SELECTION_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.game]
+    "unix"    = "${HOME}/game"
+    "windows" = "${HOARD_TMP}/game"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn restore() -> Command {
+    Command::Restore {
+        hoards: vec![HOARD.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+fn patterns(patterns: &[&str]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern).unwrap())
+        .collect()
+}
+
+fn select(tester: &mut Tester, only: &[&str], ignore: &[&str]) {
+    tester.mut_config().selection = Selection::new(&patterns(only), &patterns(ignore));
+}
+
+fn game_dir(tester: &Tester) -> PathBuf {
+    tester.home_dir().join("game")
+}
+
+fn hoard_dir(tester: &Tester) -> PathBuf {
+    tester.data_dir().join("hoards").join(HOARD)
+}
+
+async fn setup_game(tester: &Tester) {
+    let saves = game_dir(tester).join("saves");
+    fs::create_dir_all(&saves).await.unwrap();
+    fs::write(saves.join("slot1.sav"), "slot 1").await.unwrap();
+    fs::write(saves.join("slot2.sav"), "slot 2").await.unwrap();
+    fs::write(game_dir(tester).join("settings.ini"), "settings")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_backup_only() {
+    let mut tester = Tester::new(SELECTION_TOML).await;
+    setup_game(&tester).await;
+    select(&mut tester, &["saves/"], &[]);
+    tester.expect_command(backup()).await;
+
+    assert!(hoard_dir(&tester).join("saves").join("slot1.sav").exists());
+    assert!(hoard_dir(&tester).join("saves").join("slot2.sav").exists());
+    assert!(!hoard_dir(&tester).join("settings.ini").exists());
+
+    // A full backup afterwards picks up the rest of the files.
+    select(&mut tester, &[], &[]);
+    tester.expect_command(backup()).await;
+    assert!(hoard_dir(&tester).join("settings.ini").exists());
+}
+
+#[tokio::test]
+async fn test_ignore_wins_over_only() {
+    let mut tester = Tester::new(SELECTION_TOML).await;
+    setup_game(&tester).await;
+    select(&mut tester, &["*.sav"], &["slot2.sav"]);
+    tester.expect_command(backup()).await;
+
+    assert!(hoard_dir(&tester).join("saves").join("slot1.sav").exists());
+    assert!(!hoard_dir(&tester).join("saves").join("slot2.sav").exists());
+    assert!(!hoard_dir(&tester).join("settings.ini").exists());
+}
+
+#[tokio::test]
+async fn test_restore_only() {
+    let mut tester = Tester::new(SELECTION_TOML).await;
+    setup_game(&tester).await;
+    tester.expect_command(backup()).await;
+
+    fs::remove_dir_all(game_dir(&tester)).await.unwrap();
+    select(&mut tester, &["saves/slot1.sav"], &[]);
+    tester.expect_command(restore()).await;
+
+    let saves = game_dir(&tester).join("saves");
+    assert_eq!(
+        fs::read_to_string(saves.join("slot1.sav")).await.unwrap(),
+        "slot 1"
+    );
+    assert!(!saves.join("slot2.sav").exists());
+    assert!(!game_dir(&tester).join("settings.ini").exists());
+}
+
+#[tokio::test]
+async fn test_ignored_deletions_are_kept() {
+    let mut tester = Tester::new(SELECTION_TOML).await;
+    setup_game(&tester).await;
+    tester.expect_command(backup()).await;
+
+    // Deleting an ignored file from the system does not delete it from the hoard.
+    fs::remove_file(game_dir(&tester).join("settings.ini"))
+        .await
+        .unwrap();
+    select(&mut tester, &[], &["settings.ini"]);
+    tester.expect_command(backup()).await;
+    assert!(hoard_dir(&tester).join("settings.ini").exists());
+}
+
+#[tokio::test]
+async fn test_only_matching_nothing_fails() {
+    let mut tester = Tester::new(SELECTION_TOML).await;
+    setup_game(&tester).await;
+    select(&mut tester, &["missing.sav"], &[]);
+    let error = tester
+        .run_command(backup())
+        .await
+        .expect_err("backup should fail when nothing is selected");
+    assert!(error.to_string().contains("no files matched"));
+    assert!(!hoard_dir(&tester).exists());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const CONFIG: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.first]
+    "unix"    = "${HOME}/first"
+    "windows" = "${HOARD_TMP}/first"
+[hoards.second]
+    "unix"    = "${HOME}/second"
+    "windows" = "${HOARD_TMP}/second"
+"#;
+
+#[tokio::test]
+async fn test_backup_prints_summary() {
+    let tester = Tester::new(CONFIG).await;
+    let first = tester.home_dir().join("first");
+    let second = tester.home_dir().join("second");
+    fs::create_dir_all(&first).await.unwrap();
+    fs::create_dir_all(&second).await.unwrap();
+    fs::write(first.join("a.txt"), "aaaa").await.unwrap();
+    fs::write(first.join("b.txt"), "bb").await.unwrap();
+    fs::write(second.join("c.txt"), "c").await.unwrap();
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("first backup should succeed");
+    tester.assert_has_output("first: 2 created, 0 modified, 0 deleted (6 B)");
+    tester.assert_has_output("second: 1 created, 0 modified, 0 deleted (1 B)");
+    tester.assert_has_output("total: 3 created, 0 modified, 0 deleted (7 B)");
+
+    tester.clear_output();
+    fs::write(first.join("a.txt"), "changed").await.unwrap();
+    fs::remove_file(first.join("b.txt")).await.unwrap();
+    tester
+        .run_command(Command::Backup {
+            hoards: vec!["first".parse().unwrap()],
+            json: true,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("second backup should succeed");
+    tester.assert_has_output(r#""created": 0,"#);
+    tester.assert_has_output(r#""modified": 1,"#);
+    tester.assert_has_output(r#""deleted": 1,"#);
+    tester.assert_has_output(r#""bytes": 7"#);
+    tester.assert_not_has_output("second");
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const CASE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+const WARNING: &str = "only differ by case";
+
+#[tokio::test]
+async fn test_case_collisions() {
+    let mut tester = Tester::new(CASE_TOML).await;
+    let saves = tester.home_dir().join("saves");
+    fs::create_dir_all(&saves)
+        .await
+        .expect("failed to create system dir");
+    fs::write(saves.join("save.dat"), "lower")
+        .await
+        .expect("failed to write system file");
+    fs::write(saves.join("Save.dat"), "upper")
+        .await
+        .expect("failed to write system file");
+    // Files that only collide on a case-insensitive filesystem cannot be tested there.
+    if fs::read_to_string(saves.join("save.dat")).await.unwrap() != "lower" {
+        return;
+    }
+
+    let backup = Command::Backup {
+        hoards: vec!["saves".parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    };
+    tester.use_local_uuid().await;
+    tester.mut_config().case_sensitive = true;
+    tester.expect_command(backup.clone()).await;
+    tester.assert_not_has_output(WARNING);
+
+    tester.mut_config().case_sensitive = false;
+    tester.expect_command(backup).await;
+    tester.assert_has_output(WARNING);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use hoard::ExitCode;
+use tokio::fs;
+
+const HOARD: &str = "cached";
+
+const CACHE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+checksum_cache = true
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.cached]
+    "unix"    = "${HOME}/cached.txt"
+    "windows" = "${HOARD_TMP}/cached.txt"
+"#;
+
+/// Write `content` to `path` and set its modification time to `modified`.
+///
+/// Recently modified files are never cached, so tests use a time in the past.
+async fn write_with_mtime(path: &Path, content: &str, modified: SystemTime) {
+    fs::write(path, content)
+        .await
+        .expect("failed to write file");
+    filetime::set_file_mtime(path, filetime::FileTime::from_system_time(modified))
+        .expect("failed to set modification time");
+}
+
+async fn status(tester: &Tester) -> ExitCode {
+    tester
+        .run_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await
+        .expect("status should succeed")
+}
+
+#[tokio::test]
+async fn test_checksum_cache_and_paranoid_mode() {
+    let mut tester = Tester::new(CACHE_TOML).await;
+    let modified = SystemTime::now() - Duration::from_secs(60);
+    let system_file = tester.home_dir().join("cached.txt");
+    let hoard_file = tester.data_dir().join("hoards").join(HOARD);
+    write_with_mtime(&system_file, "old content\n", modified).await;
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed");
+    write_with_mtime(&hoard_file, "old content\n", modified).await;
+
+    assert_eq!(status(&tester).await, ExitCode::Success);
+    assert!(
+        hoard::dirs::cache_dir()
+            .join("checksum_cache.json")
+            .is_file(),
+        "checksums should be cached in the cache directory"
+    );
+
+    // A change in size is detected even though the modification time is the same.
+    write_with_mtime(&system_file, "new, longer content\n", modified).await;
+    assert_eq!(status(&tester).await, ExitCode::ChangesDetected);
+
+    // A change that keeps both the size and modification time is not detected with the cache...
+    write_with_mtime(&system_file, "old content\n", modified).await;
+    assert_eq!(status(&tester).await, ExitCode::Success);
+    write_with_mtime(&system_file, "new content\n", modified).await;
+    assert_eq!(status(&tester).await, ExitCode::Success);
+
+    // ...but is detected when every file is hashed in full.
+    tester.mut_config().paranoid = true;
+    assert_eq!(status(&tester).await, ExitCode::ChangesDetected);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const CHECKSUM_IGNORE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+[hoards.saves.config]
+    checksum_ignore = ["*.lock"]
+"#;
+
+#[tokio::test]
+async fn test_checksum_ignored_file_is_up_to_date() {
+    let tester = Tester::new(CHECKSUM_IGNORE_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let lock_file = system_dir.join("game.lock");
+    let save_file = system_dir.join("save.dat");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&lock_file, "locked at 1")
+        .await
+        .expect("failed to write lock file");
+    fs::write(&save_file, "level 1")
+        .await
+        .expect("failed to write save file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::write(&lock_file, "locked at 2")
+        .await
+        .expect("failed to modify lock file");
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    assert!(
+        tester.output().contains(&format!("{HOARD}: up to date")),
+        "checksum-ignored changes should not affect status"
+    );
+
+    // Checksum-ignored files are still backed up.
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    let hoard_lock = tester
+        .data_dir()
+        .join("hoards")
+        .join(HOARD)
+        .join("game.lock");
+    let content = fs::read_to_string(&hoard_lock)
+        .await
+        .expect("failed to read hoard lock file");
+    assert_eq!(content, "locked at 2");
+
+    fs::write(&save_file, "level 2")
+        .await
+        .expect("failed to modify save file");
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    assert!(
+        tester
+            .output()
+            .contains(&format!("{HOARD}: modified locally")),
+        "other changes should still affect status"
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use serde_json::Value;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime};
+use tokio::fs;
+
+const HOARD: &str = "skewed";
+
+const SKEW_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.skewed]
+    "unix"    = "${HOME}/skewed.txt"
+    "windows" = "${HOARD_TMP}/skewed.txt"
+"#;
+
+fn restore() -> Command {
+    Command::Restore {
+        hoards: vec![HOARD.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+/// Moves the timestamp of the latest remote operation log a day into the future.
+async fn skew_latest_remote_log(tester: &Tester) {
+    let dir = tester
+        .data_dir()
+        .join("history")
+        .join(tester.remote_uuid().as_hyphenated().to_string())
+        .join(HOARD);
+    let mut entries = fs::read_dir(&dir).await.unwrap();
+    let mut logs = Vec::new();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        logs.push(entry.path());
+    }
+    logs.sort_unstable();
+    let latest = logs.last().expect("remote system should have logs");
+
+    let mut log: Value = serde_json::from_slice(&fs::read(latest).await.unwrap()).unwrap();
+    let timestamp = OffsetDateTime::parse(log["timestamp"].as_str().unwrap(), &Rfc3339).unwrap();
+    log["timestamp"] = Value::from((timestamp + Duration::days(1)).format(&Rfc3339).unwrap());
+    fs::write(latest, serde_json::to_vec(&log).unwrap())
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_remote_log_from_future_is_unexpected() {
+    let mut tester = Tester::new(SKEW_TOML).await;
+    let path = tester.home_dir().join("skewed.txt");
+    fs::write(&path, "original").await.unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    tester.use_remote_uuid().await;
+    tester.expect_command(restore()).await;
+    fs::write(&path, "changed remotely").await.unwrap();
+    tester.expect_command(backup()).await;
+    fs::write(&path, "original").await.unwrap();
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("skewed: modified remotely");
+
+    skew_latest_remote_log(&tester).await;
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("skewed: unexpected changes");
+    tester.assert_has_output("system clocks may be out of sync");
+
+    tester.mut_config().max_clock_skew = 2 * 24 * 60 * 60;
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("skewed: modified remotely");
+    tester.assert_not_has_output("system clocks may be out of sync");
+}
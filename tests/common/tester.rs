@@ -13,6 +13,7 @@ use hoard::dirs::{COMPANY, PROJECT, TLD};
 use hoard::{
     command::Command,
     config::{Builder, Config, Error},
+    ExitCode,
 };
 
 pub struct Tester {
@@ -176,7 +177,7 @@ impl Tester {
         &self.data_dir
     }
 
-    async fn inner_run_command(&self, command: Command, force: bool) -> Result<(), Error> {
+    async fn inner_run_command(&self, command: Command, force: bool) -> Result<ExitCode, Error> {
         let config = Config {
             command,
             force,
@@ -188,12 +189,12 @@ impl Tester {
     }
 
     #[inline]
-    pub async fn run_command(&self, command: Command) -> Result<(), Error> {
+    pub async fn run_command(&self, command: Command) -> Result<ExitCode, Error> {
         self.inner_run_command(command, false).await
     }
 
     #[inline]
-    pub async fn force_command(&self, command: Command) -> Result<(), Error> {
+    pub async fn force_command(&self, command: Command) -> Result<ExitCode, Error> {
         self.inner_run_command(command, true).await
     }
 
@@ -236,7 +237,7 @@ impl Tester {
         output_list.join("\n")
     }
 
-    async fn handle_command_result(&self, command: Command, result: Result<(), Error>) {
+    async fn handle_command_result(&self, command: Command, result: Result<ExitCode, Error>) {
         if let Err(error) = result {
             let debug_output = Self::extra_logging_output(self).await;
             panic!("command {command:?} failed: {error:?}\n{debug_output}");
@@ -0,0 +1,57 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::config::Builder;
+use tokio::fs;
+
+#[tokio::test]
+async fn test_json_support() {
+    let tester = Tester::new(common::base::BASE_CONFIG).await;
+    let path = tester.config_dir().join("config.json");
+
+    let builder: Builder = toml::from_str(common::base::BASE_CONFIG).expect("failed to parse TOML");
+    let content = serde_json::to_string_pretty(&builder).expect("failed to serialize to JSON");
+    fs::write(&path, &content)
+        .await
+        .expect("failed to write to JSON config file");
+
+    let config = Builder::from_file(&path)
+        .await
+        .expect("failed to parse JSON config")
+        .build()
+        .expect("failed to build config");
+
+    assert_eq!(&config, tester.config());
+}
+
+#[tokio::test]
+async fn test_all_formats_parse_identically() {
+    let tester = Tester::new(common::base::BASE_CONFIG).await;
+    let builder: Builder = toml::from_str(common::base::BASE_CONFIG).expect("failed to parse TOML");
+
+    let files = [
+        (
+            "config.toml",
+            toml::to_string_pretty(&builder).expect("failed to serialize to TOML"),
+        ),
+        (
+            "config.yaml",
+            serde_yaml::to_string(&builder).expect("failed to serialize to YAML"),
+        ),
+        (
+            "config.json",
+            serde_json::to_string_pretty(&builder).expect("failed to serialize to JSON"),
+        ),
+    ];
+
+    for (name, content) in files {
+        let path = tester.config_dir().join(name);
+        fs::write(&path, &content)
+            .await
+            .expect("failed to write config file");
+        let parsed = Builder::from_file(&path)
+            .await
+            .unwrap_or_else(|err| panic!("failed to parse {name}: {err}"));
+        assert_eq!(parsed, builder, "{name} should parse identically");
+    }
+}
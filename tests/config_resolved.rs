@@ -0,0 +1,98 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::config::Config;
+use hoard::newtypes::PileName;
+use tokio::fs;
+
+const DIFF_TOML: &str = r#"
+exclusivity = [
+    ["first", "second"],
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOMEPATH"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+
+[hoards]
+[hoards.anon_txt]
+    "unix"    = "${HOME}/anon.txt"
+    "windows" = "${HOARD_TMP}/anon.txt"
+
+[hoards.anon_bin]
+    "unix"    = "${HOME}/anon.bin"
+    "windows" = "${HOARD_TMP}/anon.bin"
+
+[hoards.named]
+[hoards.named.text]
+    "unix"    = "${HOME}/named.txt"
+    "windows" = "${HOARD_TMP}/named.txt"
+[hoards.named.binary]
+    "unix"    = "${HOME}/named.bin"
+    "windows" = "${HOARD_TMP}/named.bin"
+
+[hoards.anon_dir]
+    config = { ignore = ["*ignore*"] }
+    "unix"    = "${HOME}/testdir"
+    "windows" = "${HOARD_TMP}/testdir"
+"#;
+
+#[tokio::test]
+async fn test_load_resolved_config() {
+    let tester = Tester::new(DIFF_TOML).await;
+    let config_file = tester.config_dir().join("config.toml");
+    fs::create_dir_all(tester.config_dir())
+        .await
+        .expect("failed to create config dir");
+    fs::write(&config_file, DIFF_TOML)
+        .await
+        .expect("failed to write config file");
+
+    let resolved = Config::load_resolved(&config_file)
+        .await
+        .expect("failed to load resolved config");
+    assert_eq!(resolved.config_file(), config_file);
+    assert_eq!(resolved.hoards().len(), 4);
+
+    let hoards_dir = tester.data_dir().join("hoards");
+    for (hoard, file) in [
+        ("anon_txt", "anon.txt"),
+        ("anon_bin", "anon.bin"),
+        ("anon_dir", "testdir"),
+    ] {
+        let resolved_hoard = resolved
+            .hoard(&hoard.parse().unwrap())
+            .unwrap_or_else(|| panic!("missing hoard {hoard}"));
+        assert_eq!(resolved_hoard.piles().len(), 1);
+        let pile = resolved_hoard
+            .pile(&PileName::anonymous())
+            .expect("anonymous hoard should have an anonymous pile");
+        assert_eq!(pile.system_path().as_ref(), tester.home_dir().join(file));
+        assert_eq!(pile.hoard_path().as_ref(), hoards_dir.join(hoard));
+    }
+
+    let anon_dir = resolved.hoard(&"anon_dir".parse().unwrap()).unwrap();
+    let anon_dir_pile = anon_dir.pile(&PileName::anonymous()).unwrap();
+    assert_eq!(anon_dir_pile.config().ignore.len(), 1);
+
+    let named = resolved.hoard(&"named".parse().unwrap()).unwrap();
+    assert_eq!(named.piles().len(), 2);
+    for (pile_name, file) in [("text", "named.txt"), ("binary", "named.bin")] {
+        let pile = named
+            .pile(&pile_name.parse().unwrap())
+            .unwrap_or_else(|| panic!("missing pile {pile_name}"));
+        assert_eq!(pile.system_path().as_ref(), tester.home_dir().join(file));
+        assert_eq!(
+            pile.hoard_path().as_ref(),
+            hoards_dir.join("named").join(pile_name)
+        );
+    }
+}
@@ -0,0 +1,78 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "attrs";
+const ATTR_NAME: &str = "user.hoard.test";
+const ATTR_VALUE: &[u8] = b"preserved";
+
+const COPY_ATTRIBUTES_TOML: &str = r#"
+[envs]
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.attrs]
+    "unix" = "${HOME}/attrs.dat"
+"#;
+
+#[tokio::test]
+async fn test_copy_attributes_preserves_xattrs() {
+    let mut tester = Tester::new(COPY_ATTRIBUTES_TOML).await;
+    tester.mut_config().copy_attributes = true;
+
+    let system_file = tester.home_dir().join("attrs.dat");
+    fs::write(&system_file, "save data")
+        .await
+        .expect("failed to write system file");
+    if let Err(error) = xattr::set(&system_file, ATTR_NAME, ATTR_VALUE) {
+        // Not all filesystems support user extended attributes.
+        eprintln!("skipping test, could not set xattr: {error}");
+        return;
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    let hoard_file = tester.data_dir().join("hoards").join(HOARD);
+    assert_eq!(
+        xattr::get(&hoard_file, ATTR_NAME).expect("failed to read hoard file xattr"),
+        Some(ATTR_VALUE.to_vec()),
+        "backup should preserve extended attributes"
+    );
+
+    fs::remove_file(&system_file)
+        .await
+        .expect("failed to remove system file");
+    tester
+        .expect_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+
+    assert_eq!(
+        xattr::get(&system_file, ATTR_NAME).expect("failed to read system file xattr"),
+        Some(ATTR_VALUE.to_vec()),
+        "restore should preserve extended attributes"
+    );
+}
@@ -0,0 +1,87 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::fs::OpenOptions;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use hoard::lock::LOCK_FILE_NAME;
+use nix::fcntl::{Flock, FlockArg};
+
+const CONFIG: &str = r#"
+[envs]
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.locked]
+    "unix" = "${HOME}/locked.txt"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec!["locked".parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_mutating_commands_fail_while_locked() {
+    let mut tester = Tester::new(CONFIG).await;
+    tokio::fs::write(tester.home_dir().join("locked.txt"), "content")
+        .await
+        .unwrap();
+    tester.use_local_uuid().await;
+
+    // Hold the lock the same way another hoard process would.
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(tester.data_dir().join(LOCK_FILE_NAME))
+        .unwrap();
+    let lock = Flock::lock(file, FlockArg::LockExclusiveNonblock)
+        .map_err(|(_, errno)| errno)
+        .unwrap();
+
+    let error = tester
+        .run_command(backup())
+        .await
+        .expect_err("backup should fail while another process holds the lock");
+    assert!(
+        error
+            .to_string()
+            .contains("another hoard process is running"),
+        "unexpected error: {error}"
+    );
+    assert!(!tester.data_dir().join("hoards").join("locked").exists());
+
+    tester
+        .run_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await
+        .expect("read-only commands should not take the lock");
+
+    tester.mut_config().no_lock = true;
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should ignore the lock with no_lock");
+    tester.mut_config().no_lock = false;
+
+    drop(lock);
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should succeed once the lock is released");
+    tester
+        .run_command(backup())
+        .await
+        .expect("the lock should be released after each command");
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use tokio::fs;
+
+const HOARD: &str = "sink";
+
+const SINK_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.sink]
+    "unix"    = "${HOME}/sink.txt"
+    "windows" = "${HOARD_TMP}/sink.txt"
+"#;
+
+#[tokio::test]
+async fn test_diff_and_status_write_to_buffer() {
+    let tester = Tester::new(SINK_TOML).await;
+    let system_file = tester.home_dir().join("sink.txt");
+    fs::write(&system_file, "first line\n")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::write(&system_file, "first line\nsecond line\n")
+        .await
+        .expect("failed to modify system file");
+    tester.clear_output();
+
+    let mut diff_output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            None,
+            true,
+            false,
+            None,
+            DiffFormat::Text,
+            ColorChoice::Never,
+            false,
+            &mut diff_output,
+        )
+        .await
+        .expect("diff should succeed");
+    let diff_output = String::from_utf8(diff_output).expect("diff output should be UTF-8");
+    assert!(
+        diff_output.contains(&format!(
+            "{}: text file changed locally",
+            system_file.display()
+        )),
+        "unexpected diff output: {diff_output}"
+    );
+    assert!(
+        diff_output.contains("+second line"),
+        "unexpected diff output: {diff_output}"
+    );
+
+    let mut status_output = Vec::new();
+    tester
+        .config()
+        .write_status(None, false, &mut status_output)
+        .await
+        .expect("status should succeed");
+    let status_output = String::from_utf8(status_output).expect("status output should be UTF-8");
+    assert_eq!(
+        status_output,
+        format!("{HOARD}: modified locally -- sync with `hoard backup {HOARD}`\n")
+    );
+
+    assert!(
+        tester.output().is_empty(),
+        "writing to a buffer should not log the output: {}",
+        tester.output()
+    );
+}
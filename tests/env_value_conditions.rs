@@ -0,0 +1,67 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::newtypes::PileName;
+
+const PROFILE_VAR: &str = "HOARD_TEST_PROFILE";
+
+const ENV_VALUE_TOML: &str = r#"
+exclusivity = [
+    ["work", "home"],
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+[envs.work]
+    env = [{ var = "HOARD_TEST_PROFILE", matches = "work(-.+)?" }]
+[envs.home]
+    env = [{ var = "HOARD_TEST_PROFILE", contains = "laptop" }]
+
+[hoards]
+[hoards.saves]
+    "unix|work"    = "${HOME}/work"
+    "unix|home"    = "${HOME}/home"
+    "windows|work" = "${HOARD_TMP}/work"
+    "windows|home" = "${HOARD_TMP}/home"
+"#;
+
+fn resolved_dir(tester: &Tester) -> Option<String> {
+    tester
+        .config()
+        .hoards
+        .get(&"saves".parse().unwrap())
+        .expect("hoard should exist")
+        .get_pile(&PileName::anonymous())
+        .and_then(|pile| pile.path.as_ref())
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+}
+
+#[tokio::test]
+async fn test_env_value_conditions() {
+    // Both envs match, so exclusivity decides.
+    std::env::set_var(PROFILE_VAR, "work-laptop");
+    let mut tester = Tester::new(ENV_VALUE_TOML).await;
+    assert_eq!(resolved_dir(&tester).as_deref(), Some("work"));
+
+    // `matches` is anchored, so only the `contains` env matches.
+    std::env::set_var(PROFILE_VAR, "home-laptop");
+    tester.reset_config(ENV_VALUE_TOML);
+    assert_eq!(resolved_dir(&tester).as_deref(), Some("home"));
+
+    std::env::set_var(PROFILE_VAR, "work");
+    tester.reset_config(ENV_VALUE_TOML);
+    assert_eq!(resolved_dir(&tester).as_deref(), Some("work"));
+
+    // Neither env matches an unset variable.
+    std::env::remove_var(PROFILE_VAR);
+    tester.reset_config(ENV_VALUE_TOML);
+    assert_eq!(resolved_dir(&tester), None);
+}
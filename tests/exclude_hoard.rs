@@ -0,0 +1,75 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const EXCLUDE_HOARD_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+[hoards.noisy]
+    "unix"    = "${HOME}/noisy"
+    "windows" = "${HOARD_TMP}/noisy"
+"#;
+
+#[tokio::test]
+async fn test_exclude_hoard() {
+    let mut tester = Tester::new(EXCLUDE_HOARD_TOML).await;
+    for hoard in ["saves", "noisy"] {
+        let dir = tester.home_dir().join(hoard);
+        fs::create_dir_all(&dir)
+            .await
+            .expect("failed to create system dir");
+        fs::write(dir.join("file"), hoard)
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester.mut_config().exclude_hoards = vec!["noisy".parse().unwrap(), "nosiy".parse().unwrap()];
+    tester
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    tester.assert_has_output("cannot exclude hoard nosiy: no such hoard is configured");
+
+    let hoards_dir = tester.data_dir().join("hoards");
+    assert!(
+        hoards_dir.join("saves").join("file").exists(),
+        "hoards that are not excluded should be backed up"
+    );
+    assert!(
+        !hoards_dir.join("noisy").exists(),
+        "excluded hoards should not be backed up"
+    );
+
+    // Naming hoards explicitly takes precedence over exclusions.
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec!["noisy".parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    tester.assert_has_output("ignoring --exclude-hoard");
+    assert!(hoards_dir.join("noisy").join("file").exists());
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use hoard::config::{self, Builder};
+use hoard::ExitCode;
+use tokio::fs;
+
+const HOARD: &str = "codes";
+
+const CODES_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.codes]
+    "unix"    = "${HOME}/codes.txt"
+    "windows" = "${HOARD_TMP}/codes.txt"
+"#;
+
+fn diff() -> Command {
+    Command::Diff {
+        hoard: HOARD.parse().unwrap(),
+        path: None,
+        verbose: false,
+        reverse: false,
+        group_by: None,
+        format: DiffFormat::Text,
+        color: ColorChoice::Never,
+        follow_renames: false,
+        against: None,
+    }
+}
+
+#[tokio::test]
+async fn test_status_and_diff_report_changes_with_exit_code() {
+    let tester = Tester::new(CODES_TOML).await;
+    let system_file = tester.home_dir().join("codes.txt");
+    fs::write(&system_file, "first line\n")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    let code = tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed");
+    assert_eq!(code, ExitCode::Success, "backup never reports changes");
+
+    for command in [
+        Command::Status {
+            hoard: None,
+            json: false,
+        },
+        diff(),
+    ] {
+        let code = tester
+            .run_command(command.clone())
+            .await
+            .expect("command should succeed");
+        assert_eq!(code, ExitCode::Success, "{command:?} found changes");
+    }
+
+    fs::write(&system_file, "first line\nsecond line\n")
+        .await
+        .expect("failed to modify system file");
+
+    for command in [
+        Command::Status {
+            hoard: None,
+            json: false,
+        },
+        diff(),
+    ] {
+        let code = tester
+            .run_command(command.clone())
+            .await
+            .expect("command should succeed");
+        assert_eq!(
+            code,
+            ExitCode::ChangesDetected,
+            "{command:?} missed changes"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_config_errors_use_config_exit_code() {
+    let tester = Tester::new(CODES_TOML).await;
+    let error = tester
+        .run_command(Command::Info {
+            hoard: "missing".parse().unwrap(),
+        })
+        .await
+        .expect_err("unknown hoard should fail");
+    assert_eq!(error.exit_code(), ExitCode::Error);
+
+    let error = Builder::from_file(&tester.config_dir().join("missing.toml"))
+        .await
+        .expect_err("missing config file should fail");
+    assert_eq!(
+        config::Error::Builder(error).exit_code(),
+        ExitCode::ConfigError
+    );
+}
@@ -4,7 +4,7 @@ use tokio::{fs, io::AsyncWriteExt};
 
 use crate::common::base::DefaultConfigTester;
 use common::tester::Tester;
-use hoard::command::Command;
+use hoard::command::{BackupArgs, Command};
 use hoard::config::builder::{Builder, Error as BuilderError};
 
 #[tokio::test]
@@ -22,7 +22,11 @@ async fn test_invalid_uuid() {
     }
 
     tester
-        .expect_command(Command::Backup { hoards: Vec::new() })
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
         .await;
 
     let content = fs::read_to_string(&uuid_path)
@@ -73,9 +77,35 @@ async fn test_missing_config_dir() {
         .await
         .expect("failed to delete config dir");
     tester
-        .run_command(Command::Backup { hoards: Vec::new() })
+        .run_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
         .await
         .expect("running backup without config dir should not fail");
     tester.assert_not_has_output("error while saving uuid to file");
     tester.assert_not_has_output("No such file or directory");
 }
+
+#[tokio::test]
+async fn test_malformed_toml_reports_location() {
+    let tester = Tester::new(common::base::BASE_CONFIG).await;
+    let path = tester.config_dir().join("malformed.toml");
+    let content = "[hoards]\n[hoards.saves]\n    \"unix\" = \"${HOME}/saves\n";
+    fs::write(&path, content)
+        .await
+        .expect("failed to write malformed config");
+
+    let error = Builder::from_file(&path)
+        .await
+        .expect_err("malformed config file should fail");
+    assert!(
+        matches!(&error, BuilderError::DeserializeTOML { path: bad_path, location: Some((3, _)), .. } if &path == bad_path),
+        "unexpected error: {error:?}"
+    );
+
+    let message = error.to_string();
+    assert!(message.contains(&path.display().to_string()), "{message}");
+    assert!(message.contains("at line 3, column "), "{message}");
+}
@@ -107,7 +107,7 @@ pub async fn set_default_gui_editor(editor: Editor) -> EditorGuard {
         guard.script_path().display()
     );
 
-    for file_type in ["toml", "yaml", "yml"] {
+    for file_type in ["toml", "yaml", "yml", "json"] {
         set_default_editor(file_type, &command);
     }
 
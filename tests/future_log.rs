@@ -0,0 +1,96 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "archive";
+
+const FUTURE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.archive]
+    "unix"    = "${HOME}/archive.txt"
+    "windows" = "${HOARD_TMP}/archive.txt"
+"#;
+
+/// A log in a format this version of Hoard does not know, with a field it has never seen.
+const FUTURE_LOG: &str = r#"{
+    "version": 4,
+    "timestamp": "2999-01-01T00:00:00.000000000Z",
+    "direction": "backup",
+    "hoard": "archive",
+    "files": { "Anonymous": { "created": {}, "modified": {}, "deleted": [], "unmodified": {} } },
+    "signature": "not understood by this version"
+}"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_future_log_is_skipped() {
+    let tester = Tester::new(FUTURE_TOML).await;
+    let file = tester.home_dir().join("archive.txt");
+    fs::write(&file, "first").await.unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    let future = tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().as_hyphenated().to_string())
+        .join(HOARD)
+        .join("2999_01_01-00_00_00.000000.log");
+    fs::write(&future, FUTURE_LOG).await.unwrap();
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(
+        "log written by a newer hoard version (format version 4), please upgrade",
+    );
+
+    fs::write(&file, "second").await.unwrap();
+    tester.expect_command(backup()).await;
+    assert!(future.exists(), "logs from newer versions should be kept");
+
+    tester.clear_output();
+    let error = tester
+        .run_command(Command::Doctor {
+            fix: true,
+            yes: true,
+        })
+        .await
+        .expect_err("a log from a newer version cannot be fixed");
+    assert!(error.to_string().contains("found 1 problem(s)"), "{error}");
+    tester.assert_has_output(&format!(
+        "{}: log written by a newer hoard version (format version 4), please upgrade",
+        future.display()
+    ));
+    assert!(
+        future.exists(),
+        "logs from newer versions should not be quarantined"
+    );
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::num::NonZeroUsize;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "hashed";
+
+const HASH_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.hashed]
+    "unix"    = "${HOME}/hashed"
+    "windows" = "${HOARD_TMP}/hashed"
+"#;
+
+#[tokio::test]
+async fn test_hash_jobs() {
+    let mut tester = Tester::with_log_level(HASH_TOML, tracing::Level::DEBUG).await;
+    tester.mut_config().hash_parallel_threshold = 4;
+    tester.mut_config().jobs = NonZeroUsize::new(2);
+    let system_dir = tester.home_dir().join(HOARD);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    for i in 0..6 {
+        fs::write(system_dir.join(format!("file{i}")), format!("content {i}"))
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    assert!(
+        tester
+            .output()
+            .contains("hashing 6 files with up to 2 at a time"),
+        "hashing should be limited by --jobs: {}",
+        tester.output()
+    );
+
+    for i in 0..6 {
+        let content = fs::read_to_string(
+            tester
+                .data_dir()
+                .join("hoards")
+                .join(HOARD)
+                .join(format!("file{i}")),
+        )
+        .await
+        .expect("failed to read hoard file");
+        assert_eq!(content, format!("content {i}"));
+    }
+
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(&format!("{HOARD}: up to date\n"));
+}
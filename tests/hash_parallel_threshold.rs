@@ -0,0 +1,83 @@
+mod common;
+
+use std::num::NonZeroUsize;
+
+use common::tester::Tester;
+use hoard::command::Command;
+use tokio::fs;
+
+const HOARD: &str = "hashed";
+
+const HASH_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.hashed]
+    "unix"    = "${HOME}/hashed"
+    "windows" = "${HOARD_TMP}/hashed"
+"#;
+
+#[tokio::test]
+async fn test_hash_parallel_threshold() {
+    let mut tester = Tester::with_log_level(HASH_TOML, tracing::Level::DEBUG).await;
+    tester.mut_config().hash_parallel_threshold = 4;
+    let system_dir = tester.home_dir().join(HOARD);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    for i in 0..2 {
+        fs::write(system_dir.join(format!("file{i}")), format!("content {i}"))
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    assert!(
+        tester
+            .output()
+            .contains("hashing 2 files with up to 1 at a time"),
+        "small hoards should hash serially: {}",
+        tester.output()
+    );
+
+    for i in 2..6 {
+        fs::write(system_dir.join(format!("file{i}")), format!("content {i}"))
+            .await
+            .expect("failed to write system file");
+    }
+
+    let parallelism = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    assert!(
+        tester.output().contains(&format!(
+            "hashing 6 files with up to {parallelism} at a time"
+        )),
+        "large hoards should hash in parallel: {}",
+        tester.output()
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, HistoryCommand};
+use tokio::fs;
+
+const HOARD: &str = "exported";
+
+const EXPORT_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.exported]
+    "unix"    = "${HOME}/exported.txt"
+    "windows" = "${HOARD_TMP}/exported.txt"
+"#;
+
+async fn status(tester: &Tester) -> String {
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_status(None, false, &mut output)
+        .await
+        .expect("status should succeed");
+    String::from_utf8(output).expect("status output should be UTF-8")
+}
+
+#[tokio::test]
+async fn test_history_export_import_round_trip() {
+    let tester = Tester::new(EXPORT_TOML).await;
+    let system_file = tester.home_dir().join("exported.txt");
+    let export_file = tester.home_dir().join("history.json");
+    let history_dir = tester.data_dir().join("history");
+    fs::write(&system_file, "first version\n")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed");
+    fs::write(&system_file, "second version\n")
+        .await
+        .expect("failed to modify system file");
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("second backup should succeed");
+    assert_eq!(status(&tester).await, format!("{HOARD}: up to date\n"));
+
+    tester
+        .run_command(Command::History {
+            command: HistoryCommand::Export {
+                path: export_file.clone(),
+            },
+        })
+        .await
+        .expect("export should succeed");
+    let exported = fs::read_to_string(&export_file)
+        .await
+        .expect("failed to read export file");
+    assert!(exported.contains(HOARD), "export should contain the hoard");
+
+    fs::remove_dir_all(&history_dir)
+        .await
+        .expect("failed to remove history dir");
+
+    tester
+        .run_command(Command::History {
+            command: HistoryCommand::Import {
+                path: export_file.clone(),
+            },
+        })
+        .await
+        .expect("import should succeed");
+    assert_eq!(status(&tester).await, format!("{HOARD}: up to date\n"));
+
+    // Exporting the imported logs yields the same document.
+    let reexport_file = tester.home_dir().join("history-again.json");
+    tester
+        .run_command(Command::History {
+            command: HistoryCommand::Import {
+                path: export_file.clone(),
+            },
+        })
+        .await
+        .expect("importing twice should succeed");
+    tester
+        .run_command(Command::History {
+            command: HistoryCommand::Export {
+                path: reexport_file.clone(),
+            },
+        })
+        .await
+        .expect("second export should succeed");
+    let reexported = fs::read_to_string(&reexport_file)
+        .await
+        .expect("failed to read second export file");
+    assert_eq!(exported, reexported);
+}
+
+#[tokio::test]
+async fn test_history_import_rejects_invalid_file() {
+    let tester = Tester::new(EXPORT_TOML).await;
+    let export_file = tester.home_dir().join("history.json");
+    fs::write(&export_file, "not json")
+        .await
+        .expect("failed to write export file");
+
+    let result = tester
+        .run_command(Command::History {
+            command: HistoryCommand::Import { path: export_file },
+        })
+        .await;
+    assert!(result.is_err(), "importing invalid JSON should fail");
+}
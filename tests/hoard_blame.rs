@@ -0,0 +1,98 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "blame_dir";
+const FILE: &str = "save.dat";
+
+const BLAME_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.blame_dir]
+    "unix"    = "${HOME}/blame_dir"
+    "windows" = "${HOARD_TMP}/blame_dir"
+"#;
+
+fn blame_command() -> Command {
+    Command::Blame {
+        hoard: HOARD.parse().unwrap(),
+        path: FILE.parse().unwrap(),
+    }
+}
+
+fn backup_command() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_hoard_blame() {
+    let tester = Tester::new(BLAME_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let file = system_dir.join(FILE);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+
+    tester.use_local_uuid().await;
+    tester.expect_command(blame_command()).await;
+    assert!(tester
+        .output()
+        .contains("no recorded operations touched this file"));
+
+    fs::write(&file, "local content")
+        .await
+        .expect("failed to write file");
+    tester.expect_command(backup_command()).await;
+    tester.expect_command(blame_command()).await;
+    assert!(tester.output().contains(&format!(
+        "created by system {} during backup",
+        tester.local_uuid()
+    )));
+
+    // Simulate the other system restoring, changing, then backing up the file.
+    tester.use_remote_uuid().await;
+    fs::remove_file(&file).await.expect("failed to remove file");
+    tester
+        .expect_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+    fs::write(&file, "remote content")
+        .await
+        .expect("failed to modify file");
+    tester.expect_command(backup_command()).await;
+
+    tester.use_local_uuid().await;
+    tester.expect_command(blame_command()).await;
+    assert!(tester.output().contains(&format!(
+        "modified by system {} during backup",
+        tester.remote_uuid()
+    )));
+}
@@ -4,7 +4,7 @@ use common::base::DefaultConfigTester;
 use common::base::{HOARD_ANON_DIR, HOARD_ANON_FILE, HOARD_NAMED};
 use common::UuidLocation;
 use futures::{StreamExt, TryStreamExt};
-use hoard::command::Command;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
 use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
@@ -118,6 +118,8 @@ async fn run_operation(
             tester
                 .expect_command(Command::Backup {
                     hoards: vec![hoard.parse().unwrap()],
+                    json: false,
+                    args: BackupArgs::default(),
                 })
                 .await
         }
@@ -125,6 +127,14 @@ async fn run_operation(
             tester
                 .expect_command(Command::Restore {
                     hoards: vec![hoard.parse().unwrap()],
+                    pick: false,
+                    pick_file: Vec::new(),
+                    version: None,
+                    dry_run: false,
+                    at: None,
+                    json: false,
+                    into: None,
+                    args: RestoreArgs::default(),
                 })
                 .await
         }
@@ -188,7 +198,21 @@ async fn test_operation_cleanup() {
             .collect()
             .await;
 
-    tester.expect_command(Command::Cleanup).await;
+    let local_named = data_dir.join("history").join(&local_uuid).join(HOARD_NAMED);
+    let mut before = files_in_dir(&local_named).await;
+    before.sort_unstable();
+    tester
+        .expect_command(Command::Cleanup { dry_run: true })
+        .await;
+    let mut after = files_in_dir(&local_named).await;
+    after.sort_unstable();
+    assert_eq!(before, after, "dry run should not delete any files");
+    tester.assert_has_output(&format!("would delete {}\n", before[0].display()));
+    tester.assert_has_output("would clean up ");
+
+    tester
+        .expect_command(Command::Cleanup { dry_run: false })
+        .await;
 
     for (location, retained) in RETAINED.iter() {
         for hoard in retained.keys() {
@@ -219,6 +243,8 @@ async fn test_operation_cleanup() {
     tester
         .run_command(Command::Backup {
             hoards: vec![HOARD_NAMED.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await
         .expect_err("backing up named hoard should fail");
@@ -226,11 +252,62 @@ async fn test_operation_cleanup() {
     tester
         .expect_command(Command::Backup {
             hoards: vec![HOARD_ANON_DIR.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     tester
         .expect_command(Command::Backup {
             hoards: vec![HOARD_ANON_FILE.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 }
+
+#[tokio::test]
+async fn test_cleanup_removes_empty_history_dirs() {
+    let mut tester = DefaultConfigTester::new().await;
+    tester.use_first_env();
+    tester.setup_files().await;
+    run_operation(&tester, UuidLocation::Local, Direction::Backup, HOARD_NAMED).await;
+
+    let history = tester.data_dir().join("history");
+    let local_dir = history.join(tester.local_uuid().as_hyphenated().to_string());
+    let populated_hoard = local_dir.join(HOARD_NAMED);
+    let empty_hoard = local_dir.join("removed_hoard");
+    // A system with nothing but empty hoard directories is removed entirely.
+    let empty_system = history.join(tester.remote_uuid().as_hyphenated().to_string());
+    let nested_empty_hoard = empty_system.join(HOARD_ANON_DIR);
+    // A system directory with other files in it is kept.
+    let other_system = history.join(uuid::Uuid::new_v4().as_hyphenated().to_string());
+    let other_file = other_system.join("last_paths.json");
+    for dir in [&empty_hoard, &nested_empty_hoard, &other_system] {
+        fs::create_dir_all(dir).await.unwrap();
+    }
+    fs::write(&other_file, "{}").await.unwrap();
+
+    tester
+        .expect_command(Command::Cleanup { dry_run: true })
+        .await;
+    tester.assert_has_output("would remove 3 empty directories");
+    assert!(
+        empty_hoard.exists(),
+        "dry run should not remove directories"
+    );
+    assert!(nested_empty_hoard.exists());
+
+    tester
+        .expect_command(Command::Cleanup { dry_run: false })
+        .await;
+    tester.assert_has_output("removed 3 empty directories");
+    assert!(!empty_hoard.exists());
+    assert!(!nested_empty_hoard.exists());
+    assert!(!empty_system.exists());
+    assert!(local_dir.exists());
+    assert!(
+        !files_in_dir(&populated_hoard).await.is_empty(),
+        "retained logs should not be removed"
+    );
+    assert!(other_file.exists());
+}
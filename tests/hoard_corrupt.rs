@@ -0,0 +1,112 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "corrupt";
+
+const CORRUPT_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.corrupt]
+    "unix"    = "${HOME}/corrupt.txt"
+    "windows" = "${HOARD_TMP}/corrupt.txt"
+"#;
+
+#[tokio::test]
+async fn test_truncated_hoard_file_is_corrupt() {
+    let tester = Tester::new(CORRUPT_TOML).await;
+    let system_file = tester.home_dir().join("corrupt.txt");
+    let hoard_file = tester.data_dir().join("hoards").join(HOARD);
+    fs::write(&system_file, "first line\nsecond line\n")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    // Simulate a backup that was interrupted partway through copying the file.
+    fs::write(&hoard_file, "first line\nsec")
+        .await
+        .expect("failed to truncate hoard file");
+
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            None,
+            true,
+            false,
+            None,
+            DiffFormat::Text,
+            ColorChoice::Never,
+            false,
+            &mut output,
+        )
+        .await
+        .expect("diff should succeed");
+    let output = String::from_utf8(output).expect("diff output should be UTF-8");
+    assert_eq!(
+        output,
+        format!(
+            "{}: hoard file is corrupt -- it does not match the checksum recorded by the last operation, possibly due to an interrupted backup\n",
+            system_file.display()
+        )
+    );
+
+    assert!(
+        tester
+            .force_command(Command::Restore {
+                hoards: vec![HOARD.parse().unwrap()],
+                pick: false,
+                pick_file: Vec::new(),
+                version: None,
+                dry_run: false,
+                at: None,
+                json: false,
+                into: None,
+                args: RestoreArgs::default(),
+            })
+            .await
+            .is_err(),
+        "restoring a corrupt hoard file should fail"
+    );
+    let content = fs::read_to_string(&system_file)
+        .await
+        .expect("failed to read system file");
+    assert_eq!(content, "first line\nsecond line\n");
+
+    // Backing up again replaces the corrupt file.
+    tester
+        .expect_forced_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    let content = fs::read_to_string(&hoard_file)
+        .await
+        .expect("failed to read hoard file");
+    assert_eq!(content, "first line\nsecond line\n");
+}
@@ -1,7 +1,7 @@
 mod common;
 
 use common::tester::Tester;
-use hoard::command::Command;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat, RestoreArgs};
 use hoard::newtypes::HoardName;
 use std::collections::BTreeMap;
 use std::io::ErrorKind;
@@ -176,8 +176,15 @@ async fn assert_diff_contains(
     tester.use_local_uuid().await;
     tester
         .expect_command(Command::Diff {
-            hoard: hoard.clone(),
+            hoard: hoard.clone().into(),
+            path: None,
             verbose: is_verbose,
+            reverse: false,
+            group_by: None,
+            format: DiffFormat::Text,
+            color: ColorChoice::Never,
+            follow_renames: false,
+            against: None,
         })
         .await;
     if invert {
@@ -205,8 +212,8 @@ fn get_full_diff(
         Some(Content((system_content, _))) => system_content,
     };
 
-    if file.is_text && file.hoard_path.is_some() && hoard_content != system_content {
-        format!(
+    match &file.hoard_path {
+        Some(hoard_path) if file.is_text && hoard_content != system_content => format!(
             r#"--- {}
 +++ {}
 @@ -1 +1 @@
@@ -216,13 +223,12 @@ fn get_full_diff(
 \ No newline at end of file
 
 "#,
-            file.hoard_path.as_ref().unwrap().display(),
+            hoard_path.display(),
             file.path.display(),
             hoard_content,
             system_content
-        )
-    } else {
-        String::new()
+        ),
+        _ => String::new(),
     }
 }
 
@@ -312,7 +318,7 @@ macro_rules! test_diff_inner {
         setup: {backup; $($ops:tt)*}
     ) => {
         $hoard_content = $system_content.clone();
-        $tester.expect_command(Command::Backup { hoards: vec![$hoard_name.clone()] }).await;
+        $tester.expect_command(Command::Backup { hoards: vec![$hoard_name.clone().into()], json: false, args: BackupArgs::default() }).await;
         if let Some(hoard_path) = $file.hoard_path.as_deref() {
             assert_content(hoard_path, $hoard_content.clone(), $file.is_text).await;
         }
@@ -328,7 +334,7 @@ macro_rules! test_diff_inner {
         setup: {restore; $($ops:tt)*}
     ) => {
         $system_content = $hoard_content.clone();
-        $tester.expect_command(Command::Restore { hoards: vec![$hoard_name.clone()] }).await;
+        $tester.expect_command(Command::Restore { hoards: vec![$hoard_name.clone().into()], pick: false, pick_file: Vec::new(), version: None, dry_run: false, at: None, json: false, into: None, args: RestoreArgs::default() }).await;
         if $file.hoard_path.is_some() {
             assert_content(&$file.path, $system_content.clone(), $file.is_text).await;
         }
@@ -0,0 +1,124 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use time::{Duration, OffsetDateTime};
+use tokio::fs;
+
+const HOARD: &str = "against";
+
+const AGAINST_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.against]
+    "unix"    = "${HOME}/against"
+    "windows" = "${HOARD_TMP}/against"
+"#;
+
+async fn diff_against(tester: &Tester, against: OffsetDateTime) -> String {
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff_against(&HOARD.parse().unwrap(), against, &mut output)
+        .await
+        .expect("diff should succeed");
+    String::from_utf8(output).expect("diff output should be UTF-8")
+}
+
+#[tokio::test]
+async fn test_diff_against_earlier_backup() {
+    let tester = Tester::new(AGAINST_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let save_file = system_dir.join("save.dat");
+    let extra_file = system_dir.join("extra.dat");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&save_file, "level 1")
+        .await
+        .expect("failed to write save file");
+
+    let before_first = OffsetDateTime::now_utc() - Duration::hours(1);
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("first backup should succeed");
+    let after_first = OffsetDateTime::now_utc();
+
+    fs::write(&save_file, "level 2")
+        .await
+        .expect("failed to modify save file");
+    fs::write(&extra_file, "bonus")
+        .await
+        .expect("failed to write extra file");
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("second backup should succeed");
+
+    // Against the latest backup, nothing has changed.
+    let output = diff_against(&tester, OffsetDateTime::now_utc()).await;
+    assert_eq!(output.lines().count(), 1, "unexpected changes: {output}");
+    assert!(output.starts_with(&format!("{HOARD}: comparing against backup at ")));
+
+    // Against the first backup, the later changes are shown.
+    let output = diff_against(&tester, after_first).await;
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 3, "unexpected output: {output}");
+    assert!(
+        lines[1].starts_with(&format!("{}: created since ", extra_file.display())),
+        "unexpected output: {output}"
+    );
+    assert!(
+        lines[2].starts_with(&format!("{}: modified since ", save_file.display())),
+        "unexpected output: {output}"
+    );
+
+    // Deleted files are reported as well.
+    fs::remove_file(&save_file)
+        .await
+        .expect("failed to remove save file");
+    let output = diff_against(&tester, after_first).await;
+    assert!(
+        output.contains(&format!("{}: deleted since ", save_file.display())),
+        "unexpected output: {output}"
+    );
+
+    // There is nothing to compare against before the first backup.
+    let result = tester
+        .run_command(Command::Diff {
+            hoard: HOARD.parse().unwrap(),
+            path: None,
+            verbose: false,
+            reverse: false,
+            group_by: None,
+            format: DiffFormat::Text,
+            color: ColorChoice::Never,
+            follow_renames: false,
+            against: Some(before_first),
+        })
+        .await;
+    assert!(result.is_err(), "diffing before any backup should fail");
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use tokio::fs;
+
+const HOARD: &str = "color";
+
+const COLOR_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.color]
+    "unix"    = "${HOME}/color.txt"
+    "windows" = "${HOARD_TMP}/color.txt"
+"#;
+
+async fn diff_with_color(tester: &Tester, color: ColorChoice) -> String {
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            None,
+            true,
+            false,
+            None,
+            DiffFormat::Text,
+            color,
+            false,
+            &mut output,
+        )
+        .await
+        .expect("diff should succeed");
+    String::from_utf8(output).expect("diff output should be UTF-8")
+}
+
+#[tokio::test]
+async fn test_diff_color() {
+    let tester = Tester::new(COLOR_TOML).await;
+    let system_file = tester.home_dir().join("color.txt");
+    fs::write(&system_file, "the quick fox\n")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::write(&system_file, "the slow fox\n")
+        .await
+        .expect("failed to modify system file");
+
+    let plain = diff_with_color(&tester, ColorChoice::Never).await;
+    assert!(!plain.contains('\x1b'), "unexpected colors: {plain:?}");
+    assert!(plain.contains("\n-the quick fox\n+the slow fox\n"));
+
+    let colored = diff_with_color(&tester, ColorChoice::Always).await;
+    assert!(
+        colored.contains("\x1b[1;97;41mquick\x1b[0m"),
+        "removed word should be highlighted: {colored:?}"
+    );
+    assert!(
+        colored.contains("\x1b[1;97;42mslow\x1b[0m"),
+        "added word should be highlighted: {colored:?}"
+    );
+    assert!(
+        !colored.contains("\x1b[1;97;42mthe"),
+        "unchanged words should not be highlighted: {colored:?}"
+    );
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat, DiffGrouping};
+use tokio::fs;
+
+const HOARD: &str = "grouped";
+
+const GROUP_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.grouped.configs]
+    "unix"    = "${HOME}/grouped/configs"
+    "windows" = "${HOARD_TMP}/grouped/configs"
+[hoards.grouped.saves]
+    "unix"    = "${HOME}/grouped/saves"
+    "windows" = "${HOARD_TMP}/grouped/saves"
+"#;
+
+#[tokio::test]
+async fn test_diff_grouped_by_pile() {
+    let tester = Tester::new(GROUP_TOML).await;
+    let configs = tester.home_dir().join(HOARD).join("configs");
+    let saves = tester.home_dir().join(HOARD).join("saves");
+    let config_file = configs.join("settings.ini");
+    let save_files = [saves.join("slot1.sav"), saves.join("slot2.sav")];
+    fs::create_dir_all(&configs)
+        .await
+        .expect("failed to create configs dir");
+    fs::create_dir_all(&saves)
+        .await
+        .expect("failed to create saves dir");
+    for file in save_files.iter().chain(std::iter::once(&config_file)) {
+        fs::write(file, "original")
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    for file in save_files.iter().chain(std::iter::once(&config_file)) {
+        fs::write(file, "changed")
+            .await
+            .expect("failed to modify system file");
+    }
+
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            None,
+            false,
+            false,
+            Some(DiffGrouping::Pile),
+            DiffFormat::Text,
+            ColorChoice::Never,
+            false,
+            &mut output,
+        )
+        .await
+        .expect("diff should succeed");
+    let output = String::from_utf8(output).expect("diff output should be UTF-8");
+
+    let expected = format!(
+        "pile configs: 1 change\n{}: text file changed locally\npile saves: 2 changes\n{}: text file changed locally\n{}: text file changed locally\n",
+        config_file.display(),
+        save_files[0].display(),
+        save_files[1].display(),
+    );
+    assert_eq!(output, expected);
+}
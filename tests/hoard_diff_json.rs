@@ -0,0 +1,122 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use serde_json::{json, Value};
+use tokio::fs;
+
+const HOARD: &str = "json";
+
+const JSON_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.json.text]
+    "unix"    = "${HOME}/json/text"
+    "windows" = "${HOARD_TMP}/json/text"
+[hoards.json.binary]
+    "unix"    = "${HOME}/json/binary"
+    "windows" = "${HOARD_TMP}/json/binary"
+"#;
+
+#[tokio::test]
+async fn test_diff_json_format() {
+    let tester = Tester::new(JSON_TOML).await;
+    let text_dir = tester.home_dir().join(HOARD).join("text");
+    let binary_dir = tester.home_dir().join(HOARD).join("binary");
+    let modified = text_dir.join("modified.txt");
+    let created = text_dir.join("created.txt");
+    let deleted = binary_dir.join("deleted.bin");
+    fs::create_dir_all(&text_dir)
+        .await
+        .expect("failed to create text dir");
+    fs::create_dir_all(&binary_dir)
+        .await
+        .expect("failed to create binary dir");
+    fs::write(&modified, "first line\n")
+        .await
+        .expect("failed to write text file");
+    fs::write(&deleted, [0xFD, 0xFF, 0xFE])
+        .await
+        .expect("failed to write binary file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::write(&modified, "first line\nsecond line\n")
+        .await
+        .expect("failed to modify text file");
+    fs::write(&created, "new file\n")
+        .await
+        .expect("failed to create text file");
+    fs::remove_file(&deleted)
+        .await
+        .expect("failed to delete binary file");
+
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            None,
+            false,
+            false,
+            None,
+            DiffFormat::Json,
+            ColorChoice::Never,
+            false,
+            &mut output,
+        )
+        .await
+        .expect("diff should succeed");
+    let diffs: Vec<Value> = serde_json::from_slice(&output).expect("diff output should be JSON");
+    let find = |path: &std::path::Path| {
+        diffs
+            .iter()
+            .find(|diff| diff["path"] == json!(path))
+            .unwrap_or_else(|| panic!("missing diff for {}: {diffs:?}", path.display()))
+    };
+    assert_eq!(diffs.len(), 3, "unexpected diffs: {diffs:?}");
+
+    let diff = find(&modified);
+    assert_eq!(diff["pile"], "text");
+    assert_eq!(diff["change_type"], "modified");
+    assert_eq!(diff["diff_source"], "local");
+    assert_eq!(diff["content_kind"], "text");
+    assert!(
+        diff["unified_diff"]
+            .as_str()
+            .is_some_and(|unified_diff| unified_diff.contains("+second line")),
+        "modified text file should include a unified diff: {diff}"
+    );
+
+    let diff = find(&created);
+    assert_eq!(diff["change_type"], "created");
+    assert_eq!(diff["diff_source"], "local");
+    assert_eq!(diff["content_kind"], "text");
+
+    let diff = find(&deleted);
+    assert_eq!(diff["pile"], "binary");
+    assert_eq!(diff["change_type"], "deleted");
+    assert_eq!(diff["diff_source"], "local");
+    assert_eq!(diff["content_kind"], "binary");
+    assert!(diff.get("unified_diff").is_none());
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use hoard::paths::RelativePath;
+use tokio::fs;
+
+const HOARD: &str = "game";
+
+const DIFF_PATH_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.game]
+[hoards.game.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+[hoards.game.configs]
+    "unix"    = "${HOME}/configs"
+    "windows" = "${HOARD_TMP}/configs"
+"#;
+
+async fn diff_path(tester: &Tester, path: &str) -> Result<String, hoard::config::Error> {
+    let path: RelativePath = path.parse().expect("path should be relative");
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            Some(&path),
+            false,
+            false,
+            None,
+            DiffFormat::Text,
+            ColorChoice::Never,
+            false,
+            &mut output,
+        )
+        .await?;
+    Ok(String::from_utf8(output).expect("diff output should be UTF-8"))
+}
+
+#[tokio::test]
+async fn test_diff_single_path() {
+    let tester = Tester::new(DIFF_PATH_TOML).await;
+    let saves = tester.home_dir().join("saves");
+    let configs = tester.home_dir().join("configs");
+    for dir in [&saves, &configs] {
+        fs::create_dir_all(dir)
+            .await
+            .expect("failed to create system dir");
+        fs::write(dir.join("one"), "one\n")
+            .await
+            .expect("failed to write system file");
+        fs::write(dir.join("two"), "two\n")
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    for dir in [&saves, &configs] {
+        for file in ["one", "two"] {
+            fs::write(dir.join(file), "changed\n")
+                .await
+                .expect("failed to modify system file");
+        }
+    }
+
+    let output = diff_path(&tester, "saves/one")
+        .await
+        .expect("diff should succeed");
+    assert!(
+        output.contains(&saves.join("one").display().to_string()),
+        "unexpected diff output: {output}"
+    );
+    assert!(
+        !output.contains(&saves.join("two").display().to_string()),
+        "other files should not be diffed: {output}"
+    );
+    assert!(
+        !output.contains(&configs.display().to_string()),
+        "other piles should not be diffed: {output}"
+    );
+
+    // Paths may also be given relative to the pile without its name.
+    let output = diff_path(&tester, "two")
+        .await
+        .expect("diff should succeed");
+    assert!(
+        output.contains(&saves.join("two").display().to_string())
+            && output.contains(&configs.join("two").display().to_string()),
+        "unexpected diff output: {output}"
+    );
+    assert!(
+        !output.contains(&saves.join("one").display().to_string()),
+        "other files should not be diffed: {output}"
+    );
+
+    // Pile names select every file in the pile.
+    let output = diff_path(&tester, "configs")
+        .await
+        .expect("diff should succeed");
+    assert!(
+        output.contains(&configs.join("one").display().to_string())
+            && output.contains(&configs.join("two").display().to_string()),
+        "unexpected diff output: {output}"
+    );
+    assert!(
+        !output.contains(&saves.display().to_string()),
+        "other piles should not be diffed: {output}"
+    );
+
+    let err = diff_path(&tester, "missing")
+        .await
+        .expect_err("diffing a path outside of the hoard should fail");
+    let message = err.to_string();
+    assert!(
+        message.contains("missing is not part of hoard game")
+            && message.contains(&saves.display().to_string())
+            && message.contains(&configs.display().to_string()),
+        "unexpected error: {message}"
+    );
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use serde_json::{json, Value};
+use tokio::fs;
+
+const HOARD: &str = "renames";
+
+const RENAMES_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.renames]
+    "unix"    = "${HOME}/renames"
+    "windows" = "${HOARD_TMP}/renames"
+"#;
+
+struct Files {
+    old: PathBuf,
+    new: PathBuf,
+    deleted: PathBuf,
+    created: PathBuf,
+}
+
+/// Backs up two files, then renames one, deletes the other, and creates an unrelated file.
+async fn setup(tester: &Tester) -> Files {
+    let dir = tester.home_dir().join(HOARD);
+    let files = Files {
+        old: dir.join("old.sav"),
+        new: dir.join("new.sav"),
+        deleted: dir.join("deleted.sav"),
+        created: dir.join("created.sav"),
+    };
+    fs::create_dir_all(&dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&files.old, "renamed content")
+        .await
+        .expect("failed to write system file");
+    fs::write(&files.deleted, "deleted content")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::rename(&files.old, &files.new)
+        .await
+        .expect("failed to rename system file");
+    fs::remove_file(&files.deleted)
+        .await
+        .expect("failed to delete system file");
+    fs::write(&files.created, "created content")
+        .await
+        .expect("failed to create system file");
+    files
+}
+
+async fn diff(tester: &Tester, reverse: bool, format: DiffFormat, follow_renames: bool) -> String {
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &HOARD.parse().unwrap(),
+            None,
+            false,
+            reverse,
+            None,
+            format,
+            ColorChoice::Never,
+            follow_renames,
+            &mut output,
+        )
+        .await
+        .expect("diff should succeed");
+    String::from_utf8(output).expect("diff output should be UTF-8")
+}
+
+#[tokio::test]
+async fn test_renames_not_followed_by_default() {
+    let tester = Tester::new(RENAMES_TOML).await;
+    let files = setup(&tester).await;
+
+    let output = diff(&tester, false, DiffFormat::Text, false).await;
+    assert!(
+        output.contains(&format!("{}: deleted locally", files.old.display())),
+        "{output}"
+    );
+    assert!(
+        output.contains(&format!("{}: (re)created locally", files.new.display())),
+        "{output}"
+    );
+    assert!(!output.contains("renamed"), "{output}");
+}
+
+#[tokio::test]
+async fn test_follow_renames_pairs_equal_content() {
+    let tester = Tester::new(RENAMES_TOML).await;
+    let files = setup(&tester).await;
+
+    let output = diff(&tester, false, DiffFormat::Text, true).await;
+    let expected = format!(
+        "{}: (re)created locally\n{}: deleted locally\n{} \u{2192} {}: renamed locally\n",
+        files.created.display(),
+        files.deleted.display(),
+        files.old.display(),
+        files.new.display(),
+    );
+    assert_eq!(output, expected);
+
+    let output = diff(&tester, true, DiffFormat::Text, true).await;
+    assert!(
+        output.contains(&format!(
+            "{} \u{2192} {}: renamed locally",
+            files.new.display(),
+            files.old.display()
+        )),
+        "reversed renames should go the other way: {output}"
+    );
+}
+
+#[tokio::test]
+async fn test_follow_renames_json() {
+    let tester = Tester::new(RENAMES_TOML).await;
+    let files = setup(&tester).await;
+
+    let output = diff(&tester, false, DiffFormat::Json, true).await;
+    let diffs: Vec<Value> = serde_json::from_str(&output).expect("diff output should be JSON");
+    assert_eq!(diffs.len(), 3, "unexpected diffs: {diffs:?}");
+    let renamed = diffs
+        .iter()
+        .find(|diff| diff["change_type"] == "renamed")
+        .unwrap_or_else(|| panic!("missing rename: {diffs:?}"));
+    assert_eq!(renamed["path"], json!(files.new));
+    assert_eq!(renamed["renamed_from"], json!(files.old));
+    assert_eq!(renamed["diff_source"], "local");
+    assert!(diffs
+        .iter()
+        .filter(|diff| diff["change_type"] != "renamed")
+        .all(|diff| diff.get("renamed_from").is_none()));
+}
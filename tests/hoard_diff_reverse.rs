@@ -0,0 +1,95 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use tokio::fs;
+
+const HOARD: &str = "reverse";
+
+const REVERSE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.reverse]
+    "unix"    = "${HOME}/reverse"
+    "windows" = "${HOARD_TMP}/reverse"
+"#;
+
+fn diff_command(reverse: bool) -> Command {
+    Command::Diff {
+        hoard: HOARD.parse().unwrap(),
+        path: None,
+        verbose: true,
+        reverse,
+        group_by: None,
+        format: DiffFormat::Text,
+        color: ColorChoice::Never,
+        follow_renames: false,
+        against: None,
+    }
+}
+
+#[tokio::test]
+async fn test_hoard_diff_reverse() {
+    let tester = Tester::new(REVERSE_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    let changed = system_dir.join("changed");
+    let created = system_dir.join("created");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&changed, "hoard content\n")
+        .await
+        .expect("failed to write file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::write(&changed, "system content\n")
+        .await
+        .expect("failed to modify file");
+    fs::write(&created, "new file\n")
+        .await
+        .expect("failed to create file");
+
+    let hoard_path = hoard_dir.join("changed");
+    let forward_diff = format!(
+        "--- {}\n+++ {}\n@@ -1 +1 @@\n-hoard content\n+system content\n",
+        hoard_path.display(),
+        changed.display()
+    );
+    let reverse_diff = format!(
+        "--- {}\n+++ {}\n@@ -1 +1 @@\n-system content\n+hoard content\n",
+        changed.display(),
+        hoard_path.display()
+    );
+
+    tester.expect_command(diff_command(false)).await;
+    tester.assert_has_output(&forward_diff);
+    tester.assert_has_output(&format!("{}: (re)created locally", created.display()));
+
+    tester.clear_output();
+    tester.expect_command(diff_command(true)).await;
+    tester.assert_has_output(&reverse_diff);
+    tester.assert_not_has_output(&forward_diff);
+    tester.assert_has_output(&format!("{}: deleted locally", created.display()));
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "doctor";
+
+const DOCTOR_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.doctor]
+    "unix"    = "${HOME}/doctor"
+    "windows" = "${HOARD_TMP}/doctor"
+"#;
+
+fn doctor(fix: bool) -> Command {
+    Command::Doctor { fix, yes: fix }
+}
+
+fn hoard_dir(tester: &Tester) -> PathBuf {
+    tester.data_dir().join("hoards").join(HOARD)
+}
+
+async fn backup(tester: &Tester) {
+    let system_dir = tester.home_dir().join(HOARD);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    for name in ["save1.dat", "save2.dat"] {
+        fs::write(system_dir.join(name), name)
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+}
+
+#[tokio::test]
+async fn test_doctor_healthy_data_dir() {
+    let tester = Tester::new(DOCTOR_TOML).await;
+    backup(&tester).await;
+
+    tester.clear_output();
+    tester.expect_command(doctor(false)).await;
+    tester.assert_has_output("no problems found: the data directory is healthy");
+}
+
+#[tokio::test]
+async fn test_doctor_reports_and_fixes_problems() {
+    let tester = Tester::new(DOCTOR_TOML).await;
+    backup(&tester).await;
+
+    let orphan = tester.data_dir().join("hoards").join("removed_hoard");
+    fs::create_dir_all(&orphan)
+        .await
+        .expect("failed to create orphaned hoard dir");
+    fs::write(orphan.join("old.dat"), "old")
+        .await
+        .expect("failed to write orphaned file");
+
+    let log_dir = tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().to_string())
+        .join(HOARD);
+    let bad_log = log_dir.join("2000_01_01-00_00_00.000000.log");
+    fs::write(&bad_log, r#"{"not": "an operation"}"#)
+        .await
+        .expect("failed to write unreadable log");
+
+    let unlogged = hoard_dir(&tester).join("unlogged.dat");
+    fs::write(&unlogged, "never backed up")
+        .await
+        .expect("failed to write unlogged file");
+    let corrupt = hoard_dir(&tester).join("save1.dat");
+    fs::write(&corrupt, "corrupted")
+        .await
+        .expect("failed to corrupt stored file");
+
+    tester.clear_output();
+    let error = tester
+        .run_command(doctor(false))
+        .await
+        .expect_err("doctor should fail when problems are found");
+    assert!(error.to_string().contains("found 2 problem(s)"), "{error}");
+    tester.assert_has_output(&format!(
+        "{}: hoard data exists but no hoard with this name is configured",
+        orphan.display()
+    ));
+    tester.assert_has_output(&format!(
+        "{}: operation log cannot be read",
+        bad_log.display()
+    ));
+    tester.assert_has_output(&format!(
+        "{HOARD}: skipping stored file checks until its unreadable operation logs are fixed"
+    ));
+    assert!(orphan.exists() && bad_log.exists());
+
+    tester.clear_output();
+    let error = tester
+        .run_command(doctor(true))
+        .await
+        .expect_err("stored file problems cannot be fixed automatically");
+    assert!(error.to_string().contains("found 2 problem(s)"), "{error}");
+    tester.assert_has_output(&format!(
+        "{}: stored file is not recorded by any operation log",
+        unlogged.display()
+    ));
+    tester.assert_has_output(&format!("{}: checksum mismatch", corrupt.display()));
+    assert!(!orphan.exists(), "orphaned hoard data should be deleted");
+    assert!(!bad_log.exists(), "unreadable log should be moved");
+    let quarantined = tester
+        .data_dir()
+        .join("quarantine")
+        .join(tester.local_uuid().to_string())
+        .join(HOARD)
+        .join("2000_01_01-00_00_00.000000.log");
+    assert!(quarantined.exists(), "unreadable log should be quarantined");
+
+    // Backing up again replaces the corrupt file and removes the one missing from the system.
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    assert!(!unlogged.exists());
+    tester.clear_output();
+    tester.expect_command(doctor(false)).await;
+    tester.assert_has_output("no problems found: the data directory is healthy");
+}
+
+#[tokio::test]
+async fn test_doctor_keeps_orphaned_data_without_confirmation() {
+    let tester = Tester::new(DOCTOR_TOML).await;
+    backup(&tester).await;
+
+    let orphan = tester.data_dir().join("hoards").join("removed_hoard");
+    fs::create_dir_all(&orphan)
+        .await
+        .expect("failed to create orphaned hoard dir");
+
+    // Tests do not run in a terminal, so nothing is deleted without --yes.
+    tester
+        .run_command(Command::Doctor {
+            fix: true,
+            yes: false,
+        })
+        .await
+        .expect_err("orphaned data that is kept is still a problem");
+    tester.assert_has_output("pass --yes to delete it");
+    assert!(orphan.exists());
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "exported_dir";
+
+const EXPORT_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.exported_dir]
+    "unix"    = "${HOME}/exported_dir"
+    "windows" = "${HOARD_TMP}/exported_dir"
+"#;
+
+const FILES: [(&str, &str); 2] = [
+    ("first", "first file\n"),
+    ("nested/second", "second file\n"),
+];
+
+async fn backup_files(tester: &Tester) {
+    let system_dir = tester.home_dir().join(HOARD);
+    for (name, content) in FILES {
+        let path = system_dir.join(name);
+        fs::create_dir_all(path.parent().unwrap())
+            .await
+            .expect("failed to create system dir");
+        fs::write(&path, content)
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+}
+
+fn export(tester: &Tester) -> Command {
+    Command::Export {
+        hoard: HOARD.parse().unwrap(),
+        output: tester.home_dir().join("snapshot.tar"),
+    }
+}
+
+fn import(tester: &Tester) -> Command {
+    Command::Import {
+        path: tester.home_dir().join("snapshot.tar"),
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// Removes the hoard files and operation logs, as if importing on a new system.
+async fn clear_data_dir(tester: &Tester) {
+    for dir in ["hoards", "history"] {
+        fs::remove_dir_all(tester.data_dir().join(dir))
+            .await
+            .expect("failed to remove data directory contents");
+    }
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    let tester = Tester::new(EXPORT_TOML).await;
+    backup_files(&tester).await;
+    tester.expect_command(export(&tester)).await;
+    assert!(tester.output().contains("exported 2 file(s)"));
+
+    let archive = fs::read(tester.home_dir().join("snapshot.tar"))
+        .await
+        .expect("failed to read exported archive");
+    assert_eq!(archive.len() % 512, 0, "archive should be whole tar blocks");
+    assert_eq!(
+        &archive[257..262],
+        b"ustar",
+        "archive should be a ustar archive"
+    );
+    for (name, content) in FILES {
+        let name = format!("files/{name}");
+        assert!(
+            contains(&archive, name.as_bytes()) && contains(&archive, content.as_bytes()),
+            "archive should contain {name}"
+        );
+    }
+
+    clear_data_dir(&tester).await;
+    fs::remove_dir_all(tester.home_dir().join(HOARD))
+        .await
+        .expect("failed to remove system files");
+    tester.use_remote_uuid().await;
+    tester.expect_command(import(&tester)).await;
+
+    tester
+        .expect_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+    for (name, content) in FILES {
+        let restored = fs::read_to_string(tester.home_dir().join(HOARD).join(name))
+            .await
+            .expect("failed to read restored file");
+        assert_eq!(restored, content);
+    }
+}
+
+#[tokio::test]
+async fn test_import_rejects_modified_snapshot() {
+    let tester = Tester::new(EXPORT_TOML).await;
+    backup_files(&tester).await;
+    tester.expect_command(export(&tester)).await;
+    let path = tester.home_dir().join("snapshot.tar");
+    let mut archive = fs::read(&path)
+        .await
+        .expect("failed to read exported archive");
+    // Same length, so that only the file contents change.
+    let start = archive
+        .windows(FILES[0].1.len())
+        .position(|window| window == FILES[0].1.as_bytes())
+        .expect("archive should contain the first file");
+    archive[start..start + 8].copy_from_slice(b"tampered");
+    fs::write(&path, archive)
+        .await
+        .expect("failed to modify exported archive");
+
+    clear_data_dir(&tester).await;
+    let result = tester.run_command(import(&tester)).await;
+    assert!(result.is_err(), "importing a modified snapshot should fail");
+    assert!(
+        !tester.data_dir().join("hoards").join(HOARD).exists(),
+        "no files should be imported if any fail verification"
+    );
+    assert!(
+        !tester.data_dir().join("history").exists(),
+        "no logs should be imported if any file fails verification"
+    );
+}
+
+#[tokio::test]
+async fn test_export_and_import_refuse_non_empty_destination() {
+    let tester = Tester::new(EXPORT_TOML).await;
+    backup_files(&tester).await;
+    tester.expect_command(export(&tester)).await;
+
+    let result = tester.run_command(export(&tester)).await;
+    assert!(
+        result.is_err(),
+        "exporting over an existing file should fail"
+    );
+
+    let result = tester.run_command(import(&tester)).await;
+    assert!(
+        result.is_err(),
+        "importing into a non-empty hoard should fail"
+    );
+}
@@ -0,0 +1,167 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, BackupRestoreError, Command, Error as CommandError, RestoreArgs};
+use hoard::config::Error as ConfigError;
+use tokio::fs;
+
+const HOOKS_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.hooked]
+    "unix"    = "${HOME}/hooked"
+    "windows" = "${HOARD_TMP}/hooked"
+    hooks = { pre = "echo \"pre $HOARD_NAME $HOARD_DIRECTION\" >> \"$HOME/hooks.log\"", post = "echo \"post $HOARD_NAME $HOARD_DIRECTION\" >> \"$HOME/hooks.log\"" }
+[hoards.failing]
+    hooks = { pre = "exit 1", post = "echo \"post failing\" >> \"$HOME/hooks.log\"" }
+[hoards.failing.saves]
+    "unix"    = "${HOME}/failing"
+    "windows" = "${HOARD_TMP}/failing"
+"#;
+
+fn backup(hoard: &str) -> Command {
+    Command::Backup {
+        hoards: vec![hoard.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn restore(hoard: &str) -> Command {
+    Command::Restore {
+        hoards: vec![hoard.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_hooks_run_around_operation() {
+    let tester = Tester::new(HOOKS_TOML).await;
+    let file = tester.home_dir().join("hooked");
+    fs::write(&file, "content").await.unwrap();
+
+    tester.expect_command(backup("hooked")).await;
+    tester.expect_command(restore("hooked")).await;
+
+    let log = fs::read_to_string(tester.home_dir().join("hooks.log"))
+        .await
+        .unwrap();
+    assert_eq!(
+        log,
+        "pre hooked backup\npost hooked backup\npre hooked restore\npost hooked restore\n"
+    );
+}
+
+#[tokio::test]
+async fn test_failing_pre_hook_skips_hoard() {
+    let tester = Tester::new(HOOKS_TOML).await;
+    fs::write(tester.home_dir().join("hooked"), "content")
+        .await
+        .unwrap();
+    fs::create_dir_all(tester.home_dir().join("failing"))
+        .await
+        .unwrap();
+    fs::write(tester.home_dir().join("failing").join("file"), "content")
+        .await
+        .unwrap();
+
+    let error = tester
+        .run_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect_err("backup should fail when a pre hook fails");
+    assert!(
+        matches!(
+            &error,
+            ConfigError::Command(CommandError::Backup(BackupRestoreError::PreHook(hoards)))
+                if hoards == &vec!["failing".parse().unwrap()]
+        ),
+        "unexpected error: {error:?}"
+    );
+
+    // The other hoard is still backed up, but the failing one is not touched.
+    assert!(tester.data_dir().join("hoards").join("hooked").exists());
+    assert!(!tester.data_dir().join("hoards").join("failing").exists());
+    let log = fs::read_to_string(tester.home_dir().join("hooks.log"))
+        .await
+        .unwrap();
+    assert!(!log.contains("post failing"), "post hook should not run");
+}
+
+#[tokio::test]
+async fn test_no_hooks() {
+    let mut tester = Tester::new(HOOKS_TOML).await;
+    tester.mut_config().no_hooks = true;
+    fs::create_dir_all(tester.home_dir().join("failing"))
+        .await
+        .unwrap();
+    fs::write(tester.home_dir().join("failing").join("file"), "content")
+        .await
+        .unwrap();
+
+    tester.expect_command(backup("failing")).await;
+    assert!(tester.data_dir().join("hoards").join("failing").exists());
+    assert!(!tester.home_dir().join("hooks.log").exists());
+}
+
+#[tokio::test]
+async fn test_post_hook_runs_when_check_fails() {
+    let tester = Tester::new(HOOKS_TOML).await;
+    let file = tester.home_dir().join("hooked");
+    fs::write(&file, "first").await.unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup("hooked")).await;
+
+    tester.use_remote_uuid().await;
+    tester.expect_command(restore("hooked")).await;
+    fs::write(&file, "remote").await.unwrap();
+    tester.expect_command(backup("hooked")).await;
+
+    tester.use_local_uuid().await;
+    fs::write(&file, "local").await.unwrap();
+    fs::remove_file(tester.home_dir().join("hooks.log"))
+        .await
+        .unwrap();
+    let error = tester
+        .run_command(backup("hooked"))
+        .await
+        .expect_err("backup should fail when there are unapplied remote changes");
+    assert!(
+        matches!(
+            &error,
+            ConfigError::Command(CommandError::Backup(BackupRestoreError::Consistency(_)))
+        ),
+        "unexpected error: {error:?}"
+    );
+
+    // The post hook still runs, so that whatever the pre hook stopped is started again.
+    let log = fs::read_to_string(tester.home_dir().join("hooks.log"))
+        .await
+        .unwrap();
+    assert_eq!(log, "pre hooked backup\npost hooked backup\n");
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "info";
+
+const INFO_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.info.saves]
+    "unix"    = "${HOME}/info/saves"
+    "windows" = "${HOARD_TMP}/info/saves"
+[hoards.info.settings]
+    "unix"    = "${HOME}/info/settings.ini"
+    "windows" = "${HOARD_TMP}/info/settings.ini"
+"#;
+
+/// Parses the `N file(s), M bytes` summary from a line of output.
+fn parse_usage(line: &str) -> (u64, u64) {
+    let (files, rest) = line
+        .split_once(" file(s), ")
+        .expect("line should contain a file count");
+    let files = files.rsplit(' ').next().unwrap().parse().unwrap();
+    let bytes = rest.trim_end_matches(" bytes").parse().unwrap();
+    (files, bytes)
+}
+
+#[tokio::test]
+async fn test_hoard_info() {
+    let tester = Tester::new(INFO_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let saves_dir = system_dir.join("saves").join("slot1");
+    fs::create_dir_all(&saves_dir)
+        .await
+        .expect("failed to create saves dir");
+    for (name, content) in [("a.sav", "a".repeat(100)), ("b.sav", "b".repeat(200))] {
+        fs::write(saves_dir.join(name), content)
+            .await
+            .expect("failed to write save file");
+    }
+    fs::write(system_dir.join("settings.ini"), "volume=11\n")
+        .await
+        .expect("failed to write settings file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Info {
+            hoard: HOARD.parse().unwrap(),
+        })
+        .await;
+    tester.assert_has_output("info: 0 file(s), 0 bytes");
+    tester.assert_has_output("last operation: never");
+
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Info {
+            hoard: HOARD.parse().unwrap(),
+        })
+        .await;
+    let output = tester.output();
+    let total = output
+        .lines()
+        .find(|line| line.contains("info: "))
+        .expect("output should contain a total");
+    let (files, bytes) = parse_usage(total);
+    assert_eq!(files, 3);
+    assert!(
+        (310..=400).contains(&bytes),
+        "expected total size near 310 bytes, got {bytes}"
+    );
+
+    tester.assert_has_output("last operation: backup at ");
+    tester.assert_has_output("pile saves: 2 file(s), 300 bytes");
+    tester.assert_has_output("pile settings: 1 file(s), 10 bytes");
+}
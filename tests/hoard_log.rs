@@ -0,0 +1,91 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const LOG_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves.first]
+    "unix"    = "${HOME}/first"
+    "windows" = "${HOARD_TMP}/first"
+[hoards.saves.second]
+    "unix"    = "${HOME}/second"
+    "windows" = "${HOARD_TMP}/second"
+"#;
+
+fn log(files: bool) -> Command {
+    Command::Log {
+        hoard: "saves".parse().unwrap(),
+        files,
+    }
+}
+
+#[tokio::test]
+async fn test_hoard_log() {
+    let tester = Tester::new(LOG_TOML).await;
+    tester.expect_command(log(false)).await;
+    tester.assert_has_output("saves: no recorded operations");
+
+    let first = tester.home_dir().join("first");
+    let second = tester.home_dir().join("second");
+    fs::create_dir_all(&first).await.unwrap();
+    fs::create_dir_all(&second).await.unwrap();
+    fs::write(first.join("a.sav"), "a").await.unwrap();
+    fs::write(first.join("b.sav"), "b").await.unwrap();
+    fs::write(second.join("c.sav"), "c").await.unwrap();
+
+    tester.use_local_uuid().await;
+    let backup = Command::Backup {
+        hoards: vec!["saves".parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    };
+    tester.expect_command(backup.clone()).await;
+
+    fs::write(first.join("a.sav"), "changed").await.unwrap();
+    fs::remove_file(first.join("b.sav")).await.unwrap();
+    fs::write(second.join("d.sav"), "d").await.unwrap();
+    tester.expect_command(backup).await;
+
+    let system = tester.local_uuid().as_simple().to_string();
+    let system = &system[..8];
+
+    tester.clear_output();
+    tester.expect_command(log(false)).await;
+    let output = tester.output();
+    let first_op = output
+        .find(&format!(
+            "{system} backup: 3 created, 0 modified, 0 deleted"
+        ))
+        .expect("log should contain the first backup");
+    let second_op = output
+        .find(&format!(
+            "{system} backup: 1 created, 1 modified, 1 deleted"
+        ))
+        .expect("log should contain the second backup");
+    assert!(first_op < second_op, "operations should be sorted by time");
+    tester.assert_not_has_output("a.sav");
+
+    tester.clear_output();
+    tester.expect_command(log(true)).await;
+    let sep = std::path::MAIN_SEPARATOR;
+    tester.assert_has_output(&format!("    created  second{sep}c.sav\n"));
+    tester.assert_has_output(&format!("    modified first{sep}a.sav\n"));
+    tester.assert_has_output(&format!("    deleted  first{sep}b.sav\n"));
+    tester.assert_has_output(&format!("    created  second{sep}d.sav\n"));
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat, RestoreArgs};
+use hoard::newtypes::HoardSelector;
+use time::OffsetDateTime;
+use tokio::fs;
+
+const PILE_SELECTOR_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.game]
+[hoards.game.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+[hoards.game.configs]
+    "unix"    = "${HOME}/configs"
+    "windows" = "${HOARD_TMP}/configs"
+"#;
+
+fn selector(s: &str) -> HoardSelector {
+    s.parse().expect("selector should be valid")
+}
+
+fn restore(hoards: Vec<HoardSelector>) -> Command {
+    Command::Restore {
+        hoards,
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_pile_selector() {
+    let tester = Tester::new(PILE_SELECTOR_TOML).await;
+    let saves = tester.home_dir().join("saves");
+    let configs = tester.home_dir().join("configs");
+    for (dir, content) in [(&saves, "saves\n"), (&configs, "configs\n")] {
+        fs::create_dir_all(dir)
+            .await
+            .expect("failed to create system dir");
+        fs::write(dir.join("file"), content)
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![selector("game:saves")],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    let hoard_dir = tester.data_dir().join("hoards").join("game");
+    assert!(hoard_dir.join("saves").join("file").exists());
+    assert!(
+        !hoard_dir.join("configs").join("file").exists(),
+        "other piles should not be backed up"
+    );
+
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![selector("game")],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    assert!(hoard_dir.join("configs").join("file").exists());
+
+    for dir in [&saves, &configs] {
+        fs::write(dir.join("file"), "changed\n")
+            .await
+            .expect("failed to modify system file");
+    }
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_diff(
+            &selector("game:saves"),
+            None,
+            false,
+            false,
+            None,
+            DiffFormat::Text,
+            ColorChoice::Never,
+            false,
+            &mut output,
+        )
+        .await
+        .expect("diff should succeed");
+    let output = String::from_utf8(output).expect("diff output should be UTF-8");
+    assert!(
+        output.contains(&saves.join("file").display().to_string()),
+        "unexpected diff output: {output}"
+    );
+    assert!(
+        !output.contains(&configs.display().to_string()),
+        "other piles should not be diffed: {output}"
+    );
+
+    for dir in [&saves, &configs] {
+        fs::remove_file(dir.join("file"))
+            .await
+            .expect("failed to remove system file");
+    }
+    tester
+        .expect_command(restore(vec![selector("game:configs")]))
+        .await;
+    assert_eq!(
+        fs::read_to_string(configs.join("file"))
+            .await
+            .expect("selected pile should be restored"),
+        "configs\n"
+    );
+    assert!(
+        !saves.join("file").exists(),
+        "other piles should not be restored"
+    );
+
+    let err = tester
+        .run_command(Command::Backup {
+            hoards: vec![selector("game:missing")],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect_err("selecting a missing pile should fail");
+    let message = err.to_string();
+    assert!(
+        message.contains("hoard game has no pile named missing")
+            && message.contains("available piles: configs, saves"),
+        "unexpected error: {message}"
+    );
+
+    let err = tester
+        .run_command(Command::Restore {
+            hoards: vec![selector("game:saves")],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: Some(OffsetDateTime::now_utc()),
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await
+        .expect_err("selecting a pile with --at should fail");
+    assert!(
+        err.to_string()
+            .contains("cannot select pile game:saves together with --at"),
+        "unexpected error: {err}"
+    );
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "planned";
+
+const PLAN_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.planned]
+    "unix"    = "${HOME}/planned"
+    "windows" = "${HOARD_TMP}/planned"
+"#;
+
+#[tokio::test]
+async fn test_plan_shows_both_directions() {
+    let tester = Tester::new(PLAN_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let modified = system_dir.join("modified.txt");
+    let deleted = system_dir.join("deleted.txt");
+    let created = system_dir.join("created.txt");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    for file in [&modified, &deleted] {
+        fs::write(file, "original")
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    tester.clear_output();
+    tester
+        .expect_command(Command::Plan {
+            hoard: HOARD.parse().unwrap(),
+        })
+        .await;
+    assert!(
+        tester
+            .output()
+            .contains("planned: up to date -- neither backup nor restore would change anything"),
+        "unexpected output: {}",
+        tester.output()
+    );
+
+    fs::write(&modified, "changed")
+        .await
+        .expect("failed to modify system file");
+    fs::remove_file(&deleted)
+        .await
+        .expect("failed to delete system file");
+    fs::write(&created, "new")
+        .await
+        .expect("failed to create system file");
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Plan {
+            hoard: HOARD.parse().unwrap(),
+        })
+        .await;
+    let expected = format!(
+        "backup   restore  file\ncreate   delete   {}\ndelete   create   {}\nmodify   modify   {}\n",
+        created.display(),
+        deleted.display(),
+        modified.display(),
+    );
+    assert_eq!(tester.output(), expected);
+
+    // Planning does not change any files.
+    assert_eq!(
+        fs::read_to_string(&modified)
+            .await
+            .expect("failed to read system file"),
+        "changed"
+    );
+    assert!(!deleted.exists(), "plan should not restore files");
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const PRUNE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+const OLD_LOG: &str = "2000_01_01-00_00_00.000000.log";
+
+fn prune(older_than: Option<&str>, keep: Option<usize>) -> Command {
+    Command::Prune {
+        older_than: older_than.map(|age| age.parse().unwrap()),
+        keep,
+    }
+}
+
+fn log_dir(tester: &Tester) -> PathBuf {
+    tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().as_hyphenated().to_string())
+        .join(HOARD)
+}
+
+async fn log_files(tester: &Tester) -> Vec<String> {
+    let mut entries = fs::read_dir(log_dir(tester)).await.unwrap();
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        files.push(entry.file_name().to_string_lossy().into_owned());
+    }
+    files.sort_unstable();
+    files
+}
+
+async fn back_up_times(tester: &Tester, count: usize) {
+    let file = tester.home_dir().join(HOARD).join("file.sav");
+    fs::create_dir_all(file.parent().unwrap()).await.unwrap();
+    for i in 0..count {
+        fs::write(&file, format!("version {i}")).await.unwrap();
+        tester
+            .expect_command(Command::Backup {
+                hoards: vec![HOARD.parse().unwrap()],
+                json: false,
+                args: BackupArgs::default(),
+            })
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn test_prune_keep() {
+    let tester = Tester::new(PRUNE_TOML).await;
+    tester.use_local_uuid().await;
+    back_up_times(&tester, 4).await;
+    let before = log_files(&tester).await;
+    assert_eq!(before.len(), 4);
+
+    tester.expect_command(prune(None, Some(2))).await;
+    assert_eq!(log_files(&tester).await, before[2..]);
+    tester.assert_has_output("pruned 2 log files, freeing ");
+
+    // The latest log is always kept.
+    tester.expect_command(prune(None, Some(0))).await;
+    assert_eq!(log_files(&tester).await, before[3..]);
+}
+
+#[tokio::test]
+async fn test_prune_older_than() {
+    let tester = Tester::new(PRUNE_TOML).await;
+    tester.use_local_uuid().await;
+    back_up_times(&tester, 3).await;
+    let recent = log_files(&tester).await;
+
+    // Timestamps come from the file name, so this copy looks like it was logged long ago.
+    let dir = log_dir(&tester);
+    fs::copy(dir.join(&recent[0]), dir.join(OLD_LOG))
+        .await
+        .unwrap();
+    let size = fs::metadata(dir.join(OLD_LOG)).await.unwrap().len();
+
+    tester.expect_command(prune(Some("30d"), None)).await;
+    assert_eq!(log_files(&tester).await, recent);
+    tester.assert_has_output(&format!("pruned 1 log files, freeing {size} bytes"));
+
+    tester.clear_output();
+    tester.expect_command(prune(Some("30d"), None)).await;
+    tester.assert_has_output("pruned 0 log files, freeing 0 bytes");
+}
+
+#[tokio::test]
+async fn test_prune_both_limits_must_be_exceeded() {
+    let tester = Tester::new(PRUNE_TOML).await;
+    tester.use_local_uuid().await;
+    back_up_times(&tester, 3).await;
+    let recent = log_files(&tester).await;
+    let dir = log_dir(&tester);
+    fs::copy(dir.join(&recent[0]), dir.join(OLD_LOG))
+        .await
+        .unwrap();
+
+    // Recent logs are not old enough, and the old log is one of the 4 most recent.
+    tester.expect_command(prune(Some("30d"), Some(4))).await;
+    assert_eq!(log_files(&tester).await.len(), 4);
+
+    tester.expect_command(prune(Some("30d"), Some(1))).await;
+    assert_eq!(log_files(&tester).await, recent);
+}
+
+#[tokio::test]
+async fn test_prune_keeps_latest_backup() {
+    let tester = Tester::new(PRUNE_TOML).await;
+    tester.use_local_uuid().await;
+    back_up_times(&tester, 2).await;
+    tester
+        .expect_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+    let before = log_files(&tester).await;
+    assert_eq!(before.len(), 3);
+
+    tester.expect_command(prune(None, Some(1))).await;
+    assert_eq!(log_files(&tester).await, before[1..]);
+}
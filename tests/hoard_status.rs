@@ -1,7 +1,9 @@
 mod common;
 
 use common::tester::Tester;
-use hoard::command::Command;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use hoard::ExitCode;
+use serde_json::{json, Value};
 use tokio::fs;
 
 const DEFAULT_CONTENT: &str = "default text";
@@ -56,6 +58,8 @@ async fn setup_no_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_NO_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 }
@@ -69,12 +73,22 @@ async fn setup_local_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_LOCAL_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     tester.use_local_uuid().await;
     tester
         .expect_forced_command(Command::Restore {
             hoards: vec![HOARD_LOCAL_CHANGES.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
         })
         .await;
     fs::write(&path, CHANGED_CONTENT)
@@ -91,12 +105,22 @@ async fn setup_remote_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_REMOTE_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     tester.use_remote_uuid().await;
     tester
         .expect_forced_command(Command::Restore {
             hoards: vec![HOARD_REMOTE_CHANGES.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
         })
         .await;
     fs::write(&path, CHANGED_CONTENT)
@@ -105,6 +129,8 @@ async fn setup_remote_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_REMOTE_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     fs::write(&path, DEFAULT_CONTENT)
@@ -121,6 +147,8 @@ async fn setup_mixed_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_MIXED_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     tester.use_remote_uuid().await;
@@ -130,6 +158,8 @@ async fn setup_mixed_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_MIXED_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     tester.use_local_uuid().await;
@@ -148,6 +178,8 @@ async fn setup_unexpected_changes(tester: &Tester) {
     tester
         .expect_forced_command(Command::Backup {
             hoards: vec![HOARD_UNEXPECTED_CHANGES.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
     fs::write(&hoard_path, CHANGED_CONTENT)
@@ -165,7 +197,12 @@ async fn test_hoard_status() {
     setup_unexpected_changes(&tester).await;
 
     tester.use_local_uuid().await;
-    tester.expect_command(Command::Status).await;
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
 
     tester.assert_has_output("no_changes: up to date\n");
     tester.assert_has_output(
@@ -176,4 +213,105 @@ async fn test_hoard_status() {
     );
     tester.assert_has_output("mixed_changes: mixed changes -- manual intervention recommended (see `hoard diff mixed_changes`)\n");
     tester.assert_has_output("unexpected_changes: unexpected changes -- manual intervention recommended (see `hoard diff unexpected_changes`)\n");
+
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_status(None, true, &mut output)
+        .await
+        .expect("status should succeed");
+    let statuses: Value = serde_json::from_slice(&output).expect("status output should be JSON");
+    let statuses = statuses
+        .as_array()
+        .expect("status output should be an array");
+    for (hoard, status, remediation) in [
+        (HOARD_NO_CHANGES, "up_to_date", None),
+        (
+            HOARD_LOCAL_CHANGES,
+            "modified_locally",
+            Some("hoard backup local_changes"),
+        ),
+        (
+            HOARD_REMOTE_CHANGES,
+            "modified_remotely",
+            Some("hoard restore remote_changes"),
+        ),
+        (
+            HOARD_MIXED_CHANGES,
+            "mixed",
+            Some("hoard diff mixed_changes"),
+        ),
+        (
+            HOARD_UNEXPECTED_CHANGES,
+            "unexpected",
+            Some("hoard diff unexpected_changes"),
+        ),
+    ] {
+        let expected = json!({
+            "hoard": hoard,
+            "status": status,
+            "remediation": remediation,
+        });
+        assert!(
+            statuses.contains(&expected),
+            "missing {expected} in {statuses:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_single_hoard_status() {
+    let tester = Tester::new(STATUS_TOML).await;
+    setup_no_changes(&tester).await;
+    setup_local_changes(&tester).await;
+
+    tester.use_local_uuid().await;
+    tester.clear_output();
+    let code = tester
+        .run_command(Command::Status {
+            hoard: Some(HOARD_NO_CHANGES.parse().unwrap()),
+            json: false,
+        })
+        .await
+        .expect("status should succeed");
+    assert_eq!(code, ExitCode::Success, "other hoards should be ignored");
+    tester.assert_has_output("no_changes: up to date");
+    tester.assert_not_has_output(HOARD_LOCAL_CHANGES);
+
+    let code = tester
+        .run_command(Command::Status {
+            hoard: Some(HOARD_LOCAL_CHANGES.parse().unwrap()),
+            json: false,
+        })
+        .await
+        .expect("status should succeed");
+    assert_eq!(code, ExitCode::ChangesDetected);
+
+    let mut output = Vec::new();
+    tester
+        .config()
+        .write_status(
+            Some(&HOARD_LOCAL_CHANGES.parse().unwrap()),
+            true,
+            &mut output,
+        )
+        .await
+        .expect("status should succeed");
+    let statuses: Value = serde_json::from_slice(&output).expect("status output should be JSON");
+    assert_eq!(
+        statuses,
+        json!([{
+            "hoard": HOARD_LOCAL_CHANGES,
+            "status": "modified_locally",
+            "remediation": "hoard backup local_changes",
+        }])
+    );
+
+    tester
+        .run_command(Command::Status {
+            hoard: Some("missing".parse().unwrap()),
+            json: false,
+        })
+        .await
+        .expect_err("status of a missing hoard should fail");
 }
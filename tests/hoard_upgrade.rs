@@ -4,6 +4,7 @@ use common::tester::Tester;
 use futures::TryStreamExt;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use time::format_description::well_known::Rfc3339;
 use time::Duration;
 use tokio::fs;
 use tokio_stream::wrappers::ReadDirStream;
@@ -235,7 +236,9 @@ async fn test_hoard_upgrade() {
     write_to_files(&tester, &v1_anon_dir).await;
     write_to_files(&tester, &v1_named).await;
 
-    tester.expect_command(Command::Upgrade).await;
+    tester
+        .expect_command(Command::Upgrade { rehash: false })
+        .await;
 
     let converted_anon_file = read_from_files(&tester, "anon_file").await;
     let converted_anon_dir = read_from_files(&tester, "anon_dir").await;
@@ -245,3 +248,67 @@ async fn test_hoard_upgrade() {
     assert_eq!(v2_anon_dir, converted_anon_dir);
     assert_eq!(v2_named, converted_named);
 }
+
+#[tokio::test]
+async fn test_hoard_upgrade_rewrites_legacy_timestamps() {
+    let tester = Tester::new("").await;
+    tester.use_local_uuid().await;
+
+    let v1_anon_file = anon_file_operations();
+    write_to_files(&tester, &v1_anon_file).await;
+    tester
+        .expect_command(Command::Upgrade { rehash: false })
+        .await;
+
+    // Rewrite each log as a version 2 log, with its timestamp in the legacy tuple format at a
+    // non-UTC offset.
+    let offset = time::UtcOffset::from_hms(5, 30, 0).unwrap();
+    let dir = tester
+        .data_dir()
+        .join("history")
+        .join(tester.get_uuid().await.unwrap())
+        .join("anon_file");
+    let mut paths = Vec::new();
+    let mut entries = fs::read_dir(&dir).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        if entry.file_name() == "last_paths.json" {
+            continue;
+        }
+        let mut value: serde_json::Value =
+            serde_json::from_slice(&fs::read(entry.path()).await.unwrap()).unwrap();
+        let timestamp =
+            time::OffsetDateTime::parse(value["timestamp"].as_str().unwrap(), &Rfc3339).unwrap();
+        value["timestamp"] = serde_json::to_value(timestamp.to_offset(offset)).unwrap();
+        assert!(value["timestamp"].is_array());
+        assert_eq!(
+            value.as_object_mut().unwrap().remove("version"),
+            Some(3.into())
+        );
+        fs::write(entry.path(), serde_json::to_vec(&value).unwrap())
+            .await
+            .unwrap();
+        paths.push(entry.path());
+    }
+    assert_eq!(paths.len(), v1_anon_file.len());
+
+    let legacy = read_from_files(&tester, "anon_file").await;
+    assert_eq!(legacy, convert_vec(&v1_anon_file));
+
+    tester
+        .expect_command(Command::Upgrade { rehash: false })
+        .await;
+
+    for path in paths {
+        let value: serde_json::Value =
+            serde_json::from_slice(&fs::read(&path).await.unwrap()).unwrap();
+        let timestamp = value["timestamp"]
+            .as_str()
+            .expect("timestamp should be rewritten as a string");
+        assert!(timestamp.ends_with('Z'), "{timestamp} should be in UTC");
+        assert_eq!(value["version"], 3, "log should be upgraded to version 3");
+    }
+    assert_eq!(
+        read_from_files(&tester, "anon_file").await,
+        convert_vec(&v1_anon_file)
+    );
+}
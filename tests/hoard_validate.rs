@@ -0,0 +1,104 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::Command;
+use hoard::config::Error as ConfigError;
+use tokio::fs;
+
+const BASE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+"#;
+
+const VALID_TOML: &str = r#"
+[hoards]
+[hoards.saves.first]
+    "unix"    = "${HOME}/first"
+    "windows" = "${HOARD_TMP}/first"
+[hoards.saves.second]
+    "unix"    = "${HOME}/second"
+    "windows" = "${HOARD_TMP}/second"
+[hoards.outside]
+    "unix"    = "/tmp/hoard_validate_outside"
+    "windows" = "C:\\hoard_validate_outside"
+"#;
+
+const INVALID_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"],
+    ["unix", "undefined_env"],
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.empty]
+[hoards.duplicates.first]
+    "unix"    = "${HOME}/same"
+    "windows" = "${HOARD_TMP}/same"
+[hoards.duplicates.second]
+    "unix"    = "${HOME}/same"
+    "windows" = "${HOARD_TMP}/same"
+"#;
+
+#[tokio::test]
+async fn test_validate_valid_config() {
+    let tester = Tester::new(&format!("{BASE_TOML}{VALID_TOML}")).await;
+    fs::remove_dir_all(tester.data_dir())
+        .await
+        .expect("failed to remove data dir");
+
+    tester.expect_command(Command::Validate).await;
+
+    tester.assert_has_output("configuration is valid");
+    tester.assert_has_output("hoard \"outside\": ");
+    tester.assert_has_output("is outside of the home directory");
+    assert!(
+        !tester.data_dir().exists(),
+        "validate should not create the data directory"
+    );
+}
+
+#[tokio::test]
+async fn test_validate_reports_problems() {
+    let tester = Tester::new(INVALID_TOML).await;
+
+    let error = tester
+        .run_command(Command::Validate)
+        .await
+        .expect_err("validating an invalid config should fail");
+    assert!(
+        matches!(
+            error,
+            ConfigError::Command(hoard::command::Error::Validate(
+                hoard::command::ValidateError::Invalid(3)
+            ))
+        ),
+        "unexpected error: {error:?}"
+    );
+
+    tester.assert_has_output(
+        "exclusivity list contains environment \"undefined_env\", which is not defined",
+    );
+    tester.assert_has_output("hoard \"empty\" does not define a path for any environment");
+    tester.assert_has_output("hoard \"duplicates\": piles first, second all map to ");
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "verify_dir";
+const FILE_COUNT: usize = 5;
+
+const VERIFY_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.verify_dir]
+    "unix"    = "${HOME}/verify_dir"
+    "windows" = "${HOARD_TMP}/verify_dir"
+"#;
+
+fn verify_command(fraction: Option<&str>) -> Command {
+    Command::Verify {
+        hoards: vec![HOARD.parse().unwrap()],
+        verify_fraction: fraction.map(|s| s.parse().unwrap()),
+    }
+}
+
+#[tokio::test]
+async fn test_hoard_verify() {
+    let tester = Tester::new(VERIFY_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    for i in 0..FILE_COUNT {
+        fs::write(system_dir.join(format!("file{i}")), format!("content {i}"))
+            .await
+            .expect("failed to write file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    tester.expect_command(verify_command(None)).await;
+    assert!(tester.output().contains(&format!(
+        "{HOARD}: verified {FILE_COUNT} of {FILE_COUNT} files"
+    )));
+
+    // Verifying a fraction at a time still covers every file.
+    for expected in [2, 2, 1, 2] {
+        tester.expect_command(verify_command(Some("0.4"))).await;
+        assert!(tester.output().contains(&format!(
+            "{HOARD}: verified {expected} of {FILE_COUNT} files"
+        )));
+    }
+
+    let hoard_file = tester.data_dir().join("hoards").join(HOARD).join("file3");
+    fs::write(&hoard_file, "corrupted")
+        .await
+        .expect("failed to corrupt hoard file");
+    let error = tester
+        .run_command(verify_command(None))
+        .await
+        .expect_err("verifying a corrupted hoard should fail");
+    assert!(error.to_string().contains("1 file(s) failed verification"));
+    assert!(tester.output().contains("checksum mismatch"));
+}
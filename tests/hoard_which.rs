@@ -0,0 +1,57 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::Command;
+
+const HOARD: &str = "which";
+
+const WHICH_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+[envs.nowhere]
+    hostname = ["no-such-host.invalid"]
+
+[hoards]
+[hoards.which.saves]
+    "unix"    = "${HOME}/which/saves"
+    "windows" = "${HOARD_TMP}/which/saves"
+[hoards.which.elsewhere]
+    "nowhere" = "${HOME}/which/elsewhere"
+"#;
+
+#[tokio::test]
+async fn test_hoard_which() {
+    let tester = Tester::new(WHICH_TOML).await;
+    tester
+        .run_command(Command::Which {
+            hoard: HOARD.parse().unwrap(),
+        })
+        .await
+        .expect("which should succeed");
+
+    let current = if cfg!(windows) { "windows" } else { "unix" };
+    let other = if cfg!(windows) { "unix" } else { "windows" };
+    tester.assert_has_output(&format!("  {current}: true"));
+    tester.assert_has_output(&format!("  {other}: false"));
+    tester.assert_has_output("  nowhere: false\n    HOSTNAME == no-such-host.invalid: false");
+
+    let hoard_path = tester.data_dir().join("hoards").join(HOARD).join("saves");
+    let system_path = tester.home_dir().join(HOARD).join("saves");
+    tester.assert_has_output(&format!(
+        "  pile saves: {}\n    {}",
+        hoard_path.display(),
+        system_path.display()
+    ));
+    tester.assert_has_output("  pile elsewhere: no path for the current environments");
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use tokio::fs;
+
+const HOARD: &str = "junk";
+
+/// Content whose MD5 checksum is listed in the pile's `ignore_checksums`.
+const JUNK_CONTENT: &str = "telemetry cache v1";
+
+const JUNK_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.junk]
+    "unix"    = "${HOME}/junk"
+    "windows" = "${HOARD_TMP}/junk"
+[hoards.junk.config]
+    ignore_checksums = ["2829cce0d3a32f7afcf10cd0cc31f21b"]
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn hoard_dir(tester: &Tester) -> PathBuf {
+    tester.data_dir().join("hoards").join(HOARD)
+}
+
+async fn setup_files(tester: &Tester) -> PathBuf {
+    let system_dir = tester.home_dir().join(HOARD);
+    let junk_file = system_dir.join("telemetry.cache");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&junk_file, JUNK_CONTENT)
+        .await
+        .expect("failed to write junk file");
+    fs::write(system_dir.join("save.dat"), "level 1")
+        .await
+        .expect("failed to write save file");
+    junk_file
+}
+
+#[tokio::test]
+async fn test_blocklisted_file_is_not_backed_up() {
+    let tester = Tester::new(JUNK_TOML).await;
+    let junk_file = setup_files(&tester).await;
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should succeed");
+    assert!(hoard_dir(&tester).join("save.dat").exists());
+    assert!(!hoard_dir(&tester).join("telemetry.cache").exists());
+
+    tester.clear_output();
+    tester
+        .run_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await
+        .expect("status should succeed");
+    tester.assert_has_output(&format!("{HOARD}: up to date"));
+
+    // Once the content changes, the file is no longer junk and is backed up.
+    fs::write(&junk_file, "real data").await.unwrap();
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should succeed");
+    assert_eq!(
+        fs::read_to_string(hoard_dir(&tester).join("telemetry.cache"))
+            .await
+            .unwrap(),
+        "real data"
+    );
+}
+
+#[tokio::test]
+async fn test_blocklisted_file_shown_as_ignored_in_diff() {
+    let tester = Tester::new(JUNK_TOML).await;
+    let junk_file = setup_files(&tester).await;
+    tester.use_local_uuid().await;
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should succeed");
+
+    tester.clear_output();
+    tester
+        .run_command(Command::Diff {
+            hoard: HOARD.parse().unwrap(),
+            path: None,
+            verbose: false,
+            reverse: false,
+            group_by: None,
+            format: DiffFormat::Text,
+            color: ColorChoice::Never,
+            follow_renames: false,
+            against: None,
+        })
+        .await
+        .expect("diff should succeed");
+    tester.assert_has_output(&format!(
+        "{}: ignored -- file content matches one of the pile's ignore_checksums",
+        junk_file.display()
+    ));
+    tester.assert_not_has_output("created");
+}
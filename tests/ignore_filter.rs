@@ -1,7 +1,7 @@
 mod common;
 
 use common::base::DefaultConfigTester;
-use hoard::command::Command;
+use hoard::command::{BackupArgs, Command};
 use std::path::PathBuf;
 use tokio::fs;
 
@@ -18,6 +18,8 @@ fn ignored_files(tester: &DefaultConfigTester) -> Vec<PathBuf> {
         tester.home_dir().join("first_named_dir1").join(HOARD_FILE),
         tester.home_dir().join("first_named_dir2").join(HOARD_FILE),
         tester.home_dir().join("first_named_dir1").join(PILE_FILE),
+        // Unanchored patterns like `.hidden` match at any depth, like in a `.gitignore`.
+        tester.home_dir().join("first_named_dir1").join(NESTED_FILE),
         tester.home_dir().join("first_named_dir2").join(NESTED_FILE),
     ]
 }
@@ -51,7 +53,11 @@ async fn test_ignore_filter() {
     }
 
     tester
-        .expect_command(Command::Backup { hoards: Vec::new() })
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
         .await;
 
     // Delete ignored files from home so assertion works
@@ -0,0 +1,93 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const IGNORE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[config]
+    ignore = ["*.bak"]
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+[hoards.saves.config]
+    ignore = ["/cache/*", "!/cache/keep/", "logs/", "!important.bak"]
+"#;
+
+const KEPT: &[&str] = &[
+    "slot.sav",
+    // Re-included by the hoard config, which takes precedence over the global config.
+    "important.bak",
+    // The parent directory's contents are ignored, but this child is re-included.
+    "cache/keep/slot.sav",
+    "cache/keep/deeper/slot.sav",
+    // `/cache/*` is anchored to the pile root.
+    "nested/cache/slot.sav",
+    // `logs/` only matches directories.
+    "nested/logs",
+];
+
+const IGNORED: &[&str] = &[
+    "slot.bak",
+    "nested/slot.bak",
+    "cache/tmp.bin",
+    "cache/other/slot.sav",
+    "logs/today.log",
+    "nested/logs_dir/logs/today.log",
+];
+
+#[tokio::test]
+async fn test_gitignore_syntax() {
+    let tester = Tester::new(IGNORE_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    for file in KEPT.iter().chain(IGNORED) {
+        let path = system_dir.join(file);
+        fs::create_dir_all(path.parent().unwrap())
+            .await
+            .expect("failed to create system dirs");
+        fs::write(&path, file)
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    for file in KEPT {
+        assert!(
+            hoard_dir.join(file).is_file(),
+            "{file} should have been backed up"
+        );
+    }
+    for file in IGNORED {
+        assert!(
+            !hoard_dir.join(file).exists(),
+            "{file} should have been ignored"
+        );
+    }
+}
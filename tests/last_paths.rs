@@ -8,7 +8,7 @@ use hoard::checkers::history::last_paths::{
     Error as LastPathsError, HoardPaths, LastPaths, PilePaths,
 };
 use hoard::checkers::Error as CheckerError;
-use hoard::command::{BackupRestoreError, Command, Error as CommandError};
+use hoard::command::{BackupArgs, BackupRestoreError, Command, Error as CommandError};
 use hoard::config::Error as ConfigError;
 use hoard::paths::SystemPath;
 
@@ -109,7 +109,11 @@ async fn test_last_paths() {
         }
     });
 
-    let backup = Command::Backup { hoards: Vec::new() };
+    let backup = Command::Backup {
+        hoards: Vec::new(),
+        json: false,
+        args: BackupArgs::default(),
+    };
     tester.setup_files().await;
 
     tester.use_first_env();
@@ -0,0 +1,141 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::checkers::history::operation::ItemOperation;
+use hoard::command::HoardStatus;
+use hoard::config::Error as ConfigError;
+use hoard::hoard::iter::{DiffSource, HoardFileDiff};
+use hoard::lock::{DataDirLock, Error as LockError};
+use hoard::newtypes::HoardName;
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const LIBRARY_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+#[tokio::test]
+async fn test_library_api() {
+    let tester = Tester::new(LIBRARY_TOML).await;
+    let hoard: HoardName = HOARD.parse().unwrap();
+    let saves = tester.home_dir().join("saves");
+    fs::create_dir_all(&saves)
+        .await
+        .expect("failed to create system dir");
+    fs::write(saves.join("first"), "first")
+        .await
+        .expect("failed to write system file");
+    fs::write(saves.join("second"), "second")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    let config = tester.config();
+    assert_eq!(
+        config.status().await.expect("status should succeed")[&hoard],
+        HoardStatus::ModifiedLocally
+    );
+
+    let report = config
+        .backup(&[hoard.clone().into()])
+        .await
+        .expect("backup should succeed");
+    let operations = &report.hoards[&hoard];
+    assert_eq!(operations.len(), 2, "unexpected operations: {operations:?}");
+    assert!(
+        operations
+            .iter()
+            .all(|operation| matches!(operation, ItemOperation::Create(_))),
+        "unexpected operations: {operations:?}"
+    );
+    assert_eq!(report.changes().count(), 2);
+    assert_eq!(
+        config.status().await.expect("status should succeed")[&hoard],
+        HoardStatus::UpToDate
+    );
+
+    fs::write(saves.join("first"), "changed")
+        .await
+        .expect("failed to modify system file");
+    let diffs = config
+        .diff(&hoard.clone().into(), None)
+        .await
+        .expect("diff should succeed");
+    assert!(
+        matches!(
+            diffs.as_slice(),
+            [HoardFileDiff::TextModified {
+                file,
+                diff_source: DiffSource::Local,
+                ..
+            }] if file.system_path().ends_with("first")
+        ),
+        "unexpected diffs: {diffs:?}"
+    );
+
+    // Restoring with an empty list acts on all hoards. This system has never restored the
+    // hoard before, so every file is reported as created.
+    tester.use_remote_uuid().await;
+    let report = config.restore(&[]).await.expect("restore should succeed");
+    let changes: Vec<_> = report.changes().collect();
+    assert_eq!(changes.len(), 2, "unexpected changes: {changes:?}");
+    assert!(
+        changes
+            .iter()
+            .all(|(name, operation)| *name == &hoard
+                && matches!(operation, ItemOperation::Create(_))),
+        "unexpected changes: {changes:?}"
+    );
+    assert_eq!(
+        fs::read_to_string(saves.join("first"))
+            .await
+            .expect("failed to read restored file"),
+        "first"
+    );
+}
+
+#[tokio::test]
+async fn test_library_api_locks_data_dir() {
+    let tester = Tester::new(LIBRARY_TOML).await;
+    tester.use_local_uuid().await;
+    let config = tester.config();
+
+    let lock = DataDirLock::acquire(tester.data_dir()).expect("locking should succeed");
+    let error = config
+        .backup(&[])
+        .await
+        .expect_err("backup should fail while another process holds the lock");
+    assert!(
+        matches!(error, ConfigError::Lock(LockError::Locked(_))),
+        "unexpected error: {error}"
+    );
+    let error = config
+        .restore(&[])
+        .await
+        .expect_err("restore should fail while another process holds the lock");
+    assert!(
+        matches!(error, ConfigError::Lock(LockError::Locked(_))),
+        "unexpected error: {error}"
+    );
+
+    drop(lock);
+    config.backup(&[]).await.expect("backup should succeed");
+}
@@ -0,0 +1,80 @@
+#![cfg(windows)]
+
+mod common;
+
+use std::os::windows::fs::OpenOptionsExt;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "locked";
+
+const LOCKED_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.locked]
+    "unix"    = "${HOME}/locked"
+    "windows" = "${HOARD_TMP}/locked"
+[hoards.locked.config]
+    locked_files = "skip"
+"#;
+
+#[tokio::test]
+async fn test_skip_locked_files() {
+    let tester = Tester::new(LOCKED_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let locked = system_dir.join("game.sav");
+    let unlocked = system_dir.join("settings.ini");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&locked, "in use")
+        .await
+        .expect("failed to write locked file");
+    fs::write(&unlocked, "not in use")
+        .await
+        .expect("failed to write unlocked file");
+
+    // Opening with no sharing allowed makes other attempts to open the file fail, as a game
+    // holding its save file open would.
+    let handle = std::fs::OpenOptions::new()
+        .read(true)
+        .share_mode(0)
+        .open(&locked)
+        .expect("failed to open file exclusively");
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should skip locked files");
+    drop(handle);
+
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    assert!(
+        !hoard_dir.join("game.sav").exists(),
+        "locked file should have been skipped"
+    );
+    let content = fs::read_to_string(hoard_dir.join("settings.ini"))
+        .await
+        .expect("unlocked file should have been backed up");
+    assert_eq!(content, "not in use");
+}
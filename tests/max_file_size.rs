@@ -0,0 +1,144 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, ColorChoice, Command, DiffFormat};
+use tokio::fs;
+
+const HOARD: &str = "sizes";
+
+const SIZES_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.sizes]
+    "unix"    = "${HOME}/sizes"
+    "windows" = "${HOARD_TMP}/sizes"
+[hoards.sizes.config]
+    max_file_size = "1KiB"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn system_dir(tester: &Tester) -> PathBuf {
+    tester.home_dir().join(HOARD)
+}
+
+fn hoard_dir(tester: &Tester) -> PathBuf {
+    tester.data_dir().join("hoards").join(HOARD)
+}
+
+async fn setup_files(tester: &Tester) -> (PathBuf, PathBuf) {
+    let system_dir = system_dir(tester);
+    let large_file = system_dir.join("disk.img");
+    let small_file = system_dir.join("save.dat");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&large_file, vec![0u8; 4096])
+        .await
+        .expect("failed to write large file");
+    fs::write(&small_file, "small")
+        .await
+        .expect("failed to write small file");
+    (large_file, small_file)
+}
+
+#[tokio::test]
+async fn test_max_file_size_skips_large_files() {
+    let tester = Tester::new(SIZES_TOML).await;
+    let (large_file, _) = setup_files(&tester).await;
+
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    tester.assert_has_output(&format!(
+        "skipping {}: it is 4096 bytes, which is larger than the max_file_size of 1024 bytes",
+        large_file.display()
+    ));
+    assert!(hoard_dir(&tester).join("save.dat").exists());
+    assert!(!hoard_dir(&tester).join("disk.img").exists());
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(&format!("{HOARD}: up to date"));
+}
+
+#[tokio::test]
+async fn test_max_file_size_shown_as_excluded_in_diff() {
+    let tester = Tester::new(SIZES_TOML).await;
+    let (large_file, _) = setup_files(&tester).await;
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Diff {
+            hoard: HOARD.parse().unwrap(),
+            path: None,
+            verbose: false,
+            reverse: false,
+            group_by: None,
+            format: DiffFormat::Text,
+            color: ColorChoice::Never,
+            follow_renames: false,
+            against: None,
+        })
+        .await;
+    tester.assert_has_output(&format!(
+        "{}: excluded -- file is larger than the pile's max_file_size",
+        large_file.display()
+    ));
+    tester.assert_not_has_output("deleted");
+}
+
+#[tokio::test]
+async fn test_max_file_size_keeps_hoard_copy() {
+    let mut tester = Tester::new(SIZES_TOML).await;
+    let (large_file, _) = setup_files(&tester).await;
+    fs::write(&large_file, "small for now")
+        .await
+        .expect("failed to write file");
+
+    // Back up everything without a limit, then let the file grow past it.
+    let unlimited = SIZES_TOML.replace("max_file_size = \"1KiB\"", "");
+    tester.reset_config(&unlimited);
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+    assert!(hoard_dir(&tester).join("disk.img").exists());
+
+    tester.reset_config(SIZES_TOML);
+    fs::write(&large_file, vec![0u8; 4096])
+        .await
+        .expect("failed to grow large file");
+    tester.expect_command(backup()).await;
+
+    let content = fs::read_to_string(hoard_dir(&tester).join("disk.img"))
+        .await
+        .expect("hoard copy should be left alone");
+    assert_eq!(content, "small for now");
+}
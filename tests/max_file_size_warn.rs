@@ -0,0 +1,71 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "sizes";
+
+const SIZES_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.sizes]
+    "unix"    = "${HOME}/sizes"
+    "windows" = "${HOARD_TMP}/sizes"
+"#;
+
+#[tokio::test]
+async fn test_max_file_size_warn() {
+    let mut tester = Tester::new(SIZES_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let large_file = system_dir.join("disk.img");
+    let small_file = system_dir.join("save.dat");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&large_file, vec![0u8; 4096])
+        .await
+        .expect("failed to write large file");
+    fs::write(&small_file, "small")
+        .await
+        .expect("failed to write small file");
+
+    tester.use_local_uuid().await;
+    tester.mut_config().max_file_size_warn = Some(1024);
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    tester.assert_has_output(&format!(
+        "{} is 4096 bytes, which is larger than the warning threshold of 1024 bytes",
+        large_file.display()
+    ));
+    tester.assert_not_has_output(&format!("{} is ", small_file.display()));
+
+    let hoard_file = tester
+        .data_dir()
+        .join("hoards")
+        .join(HOARD)
+        .join("disk.img");
+    let content = fs::read(&hoard_file)
+        .await
+        .expect("large file should still be backed up");
+    assert_eq!(content.len(), 4096);
+}
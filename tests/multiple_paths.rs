@@ -0,0 +1,156 @@
+mod common;
+
+use std::path::Path;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+fn config(conflict: &str) -> String {
+    format!(
+        r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = ["${{HOME}}/first", "${{HOME}}/second"]
+    "windows" = ["${{HOARD_TMP}}/first", "${{HOARD_TMP}}/second"]
+    config = {{ source_conflict = "{conflict}" }}
+"#
+    )
+}
+
+async fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap())
+        .await
+        .expect("failed to create parent dir");
+    fs::write(path, content)
+        .await
+        .expect("failed to write file");
+}
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_paths_are_merged_and_restored_to_their_source() {
+    let tester = Tester::new(&config("error")).await;
+    let first = tester.home_dir().join("first");
+    let second = tester.home_dir().join("second");
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    write(&first.join("slot1.sav"), "first slot").await;
+    write(&second.join("profiles").join("slot2.sav"), "second slot").await;
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should succeed");
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("slot1.sav"))
+            .await
+            .unwrap(),
+        "first slot"
+    );
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("profiles").join("slot2.sav"))
+            .await
+            .unwrap(),
+        "second slot"
+    );
+
+    // Once the files are gone from the system, restore must use the recorded paths.
+    fs::remove_dir_all(&first).await.unwrap();
+    fs::remove_dir_all(&second).await.unwrap();
+    tester
+        .run_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await
+        .expect("restore should succeed");
+
+    assert_eq!(
+        fs::read_to_string(first.join("slot1.sav")).await.unwrap(),
+        "first slot"
+    );
+    assert_eq!(
+        fs::read_to_string(second.join("profiles").join("slot2.sav"))
+            .await
+            .unwrap(),
+        "second slot"
+    );
+    assert!(!first.join("profiles").exists());
+    assert!(!second.join("slot1.sav").exists());
+}
+
+#[tokio::test]
+async fn test_file_under_both_paths_is_error() {
+    let tester = Tester::new(&config("error")).await;
+    write(&tester.home_dir().join("first").join("save.dat"), "first").await;
+    write(&tester.home_dir().join("second").join("save.dat"), "second").await;
+
+    tester.use_local_uuid().await;
+    let error = tester
+        .run_command(backup())
+        .await
+        .expect_err("a file under both paths should fail the backup");
+    assert!(
+        error
+            .to_string()
+            .contains("exists under more than one path"),
+        "unexpected error: {error}"
+    );
+    assert!(!tester.data_dir().join("hoards").join(HOARD).exists());
+}
+
+#[tokio::test]
+async fn test_file_under_both_paths_with_first_wins() {
+    let tester = Tester::new(&config("first_wins")).await;
+    write(&tester.home_dir().join("first").join("save.dat"), "first").await;
+    write(&tester.home_dir().join("second").join("save.dat"), "second").await;
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(backup())
+        .await
+        .expect("backup should succeed");
+    assert_eq!(
+        fs::read_to_string(
+            tester
+                .data_dir()
+                .join("hoards")
+                .join(HOARD)
+                .join("save.dat")
+        )
+        .await
+        .unwrap(),
+        "first"
+    );
+}
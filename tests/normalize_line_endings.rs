@@ -0,0 +1,82 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "notes";
+
+const NORMALIZE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.notes]
+    "unix"    = "${HOME}/notes"
+    "windows" = "${HOARD_TMP}/notes"
+[hoards.notes.config]
+    normalize_line_endings = true
+"#;
+
+#[tokio::test]
+async fn test_line_ending_differences_are_up_to_date() {
+    let tester = Tester::new(NORMALIZE_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let system_file = system_dir.join("notes.txt");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(&system_file, "first line\nsecond line\n")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::write(&system_file, "first line\r\nsecond line\r\n")
+        .await
+        .expect("failed to rewrite system file with CRLF");
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    assert!(
+        tester.output().contains(&format!("{HOARD}: up to date")),
+        "line ending changes should not affect status"
+    );
+
+    fs::write(&system_file, "first line\r\nthird line\r\n")
+        .await
+        .expect("failed to modify system file");
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    assert!(
+        tester
+            .output()
+            .contains(&format!("{HOARD}: modified locally")),
+        "content changes should still affect status"
+    );
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::checkers::history::operation::util::TIME_FORMAT;
+use hoard::checkers::history::operation::v1::{Hoard as HoardV1, OperationV1, Pile as PileV1};
+use hoard::command::{BackupArgs, Command};
+use hoard::paths::RelativePath;
+use std::path::PathBuf;
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const OLDER_FORMAT_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+const CONTENT: &str = "level 1";
+const CONTENT_MD5: &str = "4fda1e980a77182467d3a97d8e4aecf0";
+
+async fn write_v1_log(tester: &Tester) {
+    let operation = OperationV1 {
+        timestamp: time::OffsetDateTime::now_utc(),
+        is_backup: true,
+        hoard_name: HOARD.parse().unwrap(),
+        hoard: HoardV1::Anonymous(PileV1::from(maplit::hashmap! {
+            RelativePath::try_from(PathBuf::from("save.dat")).unwrap() => CONTENT_MD5.parse().unwrap(),
+        })),
+    };
+
+    let path = tester
+        .data_dir()
+        .join("history")
+        .join(
+            tester
+                .get_uuid()
+                .await
+                .expect("getting uuid should succeed"),
+        )
+        .join(HOARD)
+        .join(format!(
+            "{}.log",
+            operation
+                .timestamp
+                .format(&TIME_FORMAT)
+                .expect("formatting timestamp should succeed")
+        ));
+    fs::create_dir_all(path.parent().unwrap())
+        .await
+        .expect("creating history dir should succeed");
+    let content = serde_json::to_vec(&operation).expect("serializing V1 operation should succeed");
+    fs::write(&path, &content)
+        .await
+        .expect("writing V1 operation should succeed");
+}
+
+#[tokio::test]
+async fn test_status_with_older_format_ok() {
+    let mut tester = Tester::new(OLDER_FORMAT_TOML).await;
+    tester.use_local_uuid().await;
+
+    let system_dir = tester.home_dir().join(HOARD);
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    for dir in [&system_dir, &hoard_dir] {
+        fs::create_dir_all(dir)
+            .await
+            .expect("failed to create directory");
+        fs::write(dir.join("save.dat"), CONTENT)
+            .await
+            .expect("failed to write save file");
+    }
+    write_v1_log(&tester).await;
+
+    assert!(
+        tester
+            .run_command(Command::Status {
+                hoard: None,
+                json: false
+            })
+            .await
+            .is_err(),
+        "status over old-format logs should fail by default"
+    );
+
+    tester.mut_config().older_format_ok = true;
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(&format!("{HOARD}: up to date"));
+    tester.assert_has_output("operation log format for hoard saves is outdated");
+
+    assert!(
+        tester
+            .run_command(Command::Backup {
+                hoards: vec![HOARD.parse().unwrap()],
+                json: false,
+                args: BackupArgs::default(),
+            })
+            .await
+            .is_err(),
+        "backup should still refuse to write over old-format logs"
+    );
+}
@@ -2,8 +2,8 @@ mod common;
 
 use common::tester::Tester;
 use hoard::checkers::history::operation::{Operation, OperationImpl};
-use hoard::checksum::{Checksum, MD5, SHA256};
-use hoard::command::Command;
+use hoard::checksum::{Checksum, ChecksumType, BLAKE3, MD5, SHA256};
+use hoard::command::{BackupArgs, Command};
 use hoard::newtypes::PileName;
 use hoard::paths::RelativePath;
 use tokio::fs;
@@ -28,6 +28,10 @@ exclusivity = [[ "unix", "windows" ]]
     config = { hash_algorithm = "sha256" }
     "unix" = "${HOME}/testing.txt"
     "windows" = "${HOARD_TMP}/testing.txt"
+[hoards.blake3]
+    config = { hash_algorithm = "blake3" }
+    "unix" = "${HOME}/testing.txt"
+    "windows" = "${HOARD_TMP}/testing.txt"
 [hoards.default]
     "unix" = "${HOME}/testing.txt"
     "windows" = "${HOARD_TMP}/testing.txt"
@@ -42,7 +46,11 @@ async fn test_operation_checksums() {
     common::create_file_with_random_data::<2048>(&file_path).await;
 
     tester
-        .expect_command(Command::Backup { hoards: Vec::new() })
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
         .await;
 
     let data = fs::read(&file_path)
@@ -50,6 +58,7 @@ async fn test_operation_checksums() {
         .expect("reading data from test file should succeed");
     let md5 = Checksum::MD5(MD5::from_data(&data));
     let sha256 = Checksum::SHA256(SHA256::from_data(&data));
+    let blake3 = Checksum::BLAKE3(BLAKE3::from_data(&data));
 
     let pile_name = PileName::anonymous();
     let md5_op = Operation::latest_local(&"md5".parse().unwrap(), Some((&pile_name, &rel_file)))
@@ -65,6 +74,13 @@ async fn test_operation_checksums() {
             .expect("operation should exist")
             .checksum_for(&pile_name, &rel_file)
             .expect("checksum should exist for file");
+    let blake3_op =
+        Operation::latest_local(&"blake3".parse().unwrap(), Some((&pile_name, &rel_file)))
+            .await
+            .expect("should not fail to load operation for blake3 hoard")
+            .expect("operation should exist")
+            .checksum_for(&pile_name, &rel_file)
+            .expect("checksum should exist for file");
     let default_op =
         Operation::latest_local(&"default".parse().unwrap(), Some((&pile_name, &rel_file)))
             .await
@@ -75,5 +91,166 @@ async fn test_operation_checksums() {
 
     assert_eq!(md5_op, md5);
     assert_eq!(sha256_op, sha256);
+    assert_eq!(blake3_op, blake3);
     assert_eq!(default_op, sha256);
 }
+
+fn single_hoard_config(hash_algorithm: &str) -> String {
+    format!(
+        r#"
+exclusivity = [[ "unix", "windows" ]]
+
+[envs]
+[envs.unix]
+    os = ["linux", "macos"]
+    env = [{{ var = "HOME" }}]
+[envs.windows]
+    os = ["windows"]
+    env = [{{ var = "HOARD_TMP" }}]
+
+[hoards]
+[hoards.mixed]
+    config = {{ hash_algorithm = "{hash_algorithm}" }}
+    "unix" = "${{HOME}}/testing.txt"
+    "windows" = "${{HOARD_TMP}}/testing.txt"
+"#
+    )
+}
+
+#[tokio::test]
+async fn test_mixed_checksum_history() {
+    let mut tester = Tester::new(&single_hoard_config("md5")).await;
+    let file_path = tester.home_dir().join("testing.txt");
+    let rel_file = RelativePath::none();
+    let pile_name = PileName::anonymous();
+    let hoard_name = "mixed".parse().unwrap();
+    common::create_file_with_random_data::<2048>(&file_path).await;
+
+    tester
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    // Switching algorithms alone is not a change: the MD5 in the log is still checked.
+    tester.reset_config(&single_hoard_config("sha256"));
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("mixed: up to date\n");
+
+    common::create_file_with_random_data::<2048>(&file_path).await;
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("mixed: modified locally");
+
+    tester
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    let checksum = Operation::latest_local(&hoard_name, Some((&pile_name, &rel_file)))
+        .await
+        .expect("should not fail to load operation for mixed hoard")
+        .expect("operation should exist")
+        .checksum_for(&pile_name, &rel_file)
+        .expect("checksum should exist for file");
+    assert_eq!(checksum.typ(), ChecksumType::SHA256);
+
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("mixed: up to date\n");
+}
+
+fn dir_hoard_config(hash_algorithm: &str) -> String {
+    format!(
+        r#"
+exclusivity = [[ "unix", "windows" ]]
+
+[envs]
+[envs.unix]
+    os = ["linux", "macos"]
+    env = [{{ var = "HOME" }}]
+[envs.windows]
+    os = ["windows"]
+    env = [{{ var = "HOARD_TMP" }}]
+
+[hoards]
+[hoards.rehash]
+    config = {{ hash_algorithm = "{hash_algorithm}" }}
+    "unix" = "${{HOME}}/rehash"
+    "windows" = "${{HOARD_TMP}}/rehash"
+"#
+    )
+}
+
+#[tokio::test]
+async fn test_upgrade_rehash() {
+    let mut tester = Tester::new(&dir_hoard_config("md5")).await;
+    let system_dir = tester.home_dir().join("rehash");
+    let pile_name = PileName::anonymous();
+    let hoard_name = "rehash".parse().unwrap();
+    let intact: RelativePath = "intact".parse().unwrap();
+    let corrupt: RelativePath = "corrupt".parse().unwrap();
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    common::create_file_with_random_data::<2048>(&system_dir.join("intact")).await;
+    common::create_file_with_random_data::<2048>(&system_dir.join("corrupt")).await;
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: Vec::new(),
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    let hoard_dir = tester.data_dir().join("hoards").join("rehash");
+    fs::write(hoard_dir.join("corrupt"), "changed outside of hoard")
+        .await
+        .expect("failed to modify hoard file");
+
+    tester.reset_config(&dir_hoard_config("blake3"));
+    tester
+        .expect_command(Command::Upgrade { rehash: true })
+        .await;
+    tester.assert_has_output("rehash: rehashed 1 files");
+    tester.assert_has_output("does not match its recorded checksum");
+
+    let operation = Operation::latest_local(&hoard_name, None)
+        .await
+        .expect("should not fail to load operation for rehash hoard")
+        .expect("operation should exist");
+    let data = fs::read(hoard_dir.join("intact"))
+        .await
+        .expect("reading hoard file should succeed");
+    assert_eq!(
+        operation.checksum_for(&pile_name, &intact),
+        Some(Checksum::BLAKE3(BLAKE3::from_data(&data))),
+        "intact files should be rehashed"
+    );
+    assert_eq!(
+        operation
+            .checksum_for(&pile_name, &corrupt)
+            .map(|checksum| checksum.typ()),
+        Some(ChecksumType::MD5),
+        "files that do not match their checksum should not be rehashed"
+    );
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::checkers::history::operation::util::file_is_log;
+use hoard::checkers::history::operation::Operation;
+use hoard::checkers::Checker;
+use hoard::command::{BackupArgs, Command};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+const HOARD: &str = "collision";
+
+const COLLISION_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.collision]
+    "unix"    = "${HOME}/collision"
+    "windows" = "${HOARD_TMP}/collision"
+"#;
+
+async fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut entries = fs::read_dir(dir).await.expect("failed to read history dir");
+    let mut files = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .expect("failed to read history entry")
+    {
+        if file_is_log(&entry.path()) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    files
+}
+
+#[tokio::test]
+async fn test_operations_with_same_timestamp_are_not_lost() {
+    let tester = Tester::new(COLLISION_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    fs::write(system_dir.join("file"), "content")
+        .await
+        .expect("failed to write file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    let history_dir = tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().to_string())
+        .join(HOARD);
+    let logs = log_files(&history_dir).await;
+    assert_eq!(logs.len(), 1, "expected exactly one log: {logs:?}");
+
+    // Committing an operation with the exact same timestamp must not overwrite the first.
+    let content = fs::read(&logs[0]).await.expect("failed to read log");
+    let operation: Operation = serde_json::from_slice(&content).expect("failed to parse log");
+    operation
+        .clone()
+        .commit_to_disk()
+        .await
+        .expect("failed to commit duplicate operation");
+    operation
+        .commit_to_disk()
+        .await
+        .expect("failed to commit second duplicate operation");
+
+    let logs = log_files(&history_dir).await;
+    assert_eq!(logs.len(), 3, "all logs should be kept: {logs:?}");
+    for log in &logs {
+        let log_content = fs::read(log).await.expect("failed to read log");
+        assert_eq!(log_content, content);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::common::base::{DefaultConfigTester, HOARD_ANON_FILE};
 use hoard::checkers::history::operation::{Operation, OperationImpl};
 use hoard::checksum::Checksum;
-use hoard::command::Command;
+use hoard::command::{BackupArgs, Command};
 use hoard::hoard_item::HoardItem;
 use hoard::newtypes::PileName;
 
@@ -46,7 +46,11 @@ async fn test_operations() {
     tester.setup_files().await;
 
     let file = tester.anon_file();
-    let backup = Command::Backup { hoards: Vec::new() };
+    let backup = Command::Backup {
+        hoards: Vec::new(),
+        json: false,
+        args: BackupArgs::default(),
+    };
     // 1 - Command should work because it is the first backup
     tester.use_local_uuid().await;
     tester.expect_command(backup.clone()).await;
@@ -0,0 +1,115 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const GLOB_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/game/profiles/*/save.dat"
+    "windows" = "${HOARD_TMP}/game/profiles/*/save.dat"
+    glob = true
+[hoards.nothing]
+    "unix"    = "${HOME}/game/profiles/*/missing.dat"
+    "windows" = "${HOARD_TMP}/game/profiles/*/missing.dat"
+    glob = true
+"#;
+
+fn backup(hoard: &str) -> Command {
+    Command::Backup {
+        hoards: vec![hoard.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn restore(hoard: &str) -> Command {
+    Command::Restore {
+        hoards: vec![hoard.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+fn profiles_dir(tester: &Tester) -> PathBuf {
+    tester.home_dir().join("game").join("profiles")
+}
+
+async fn setup_profiles(tester: &Tester) {
+    for profile in ["alice", "bob"] {
+        let dir = profiles_dir(tester).join(profile);
+        fs::create_dir_all(&dir).await.unwrap();
+        fs::write(dir.join("save.dat"), format!("{profile} save"))
+            .await
+            .unwrap();
+        fs::write(dir.join("settings.ini"), "not a save")
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_glob_pile_backs_up_matching_files() {
+    let tester = Tester::new(GLOB_TOML).await;
+    setup_profiles(&tester).await;
+    tester.expect_command(backup("saves")).await;
+
+    let hoard_dir = tester.data_dir().join("hoards").join("saves");
+    for profile in ["alice", "bob"] {
+        let content = fs::read_to_string(hoard_dir.join(profile).join("save.dat"))
+            .await
+            .unwrap();
+        assert_eq!(content, format!("{profile} save"));
+        assert!(!hoard_dir.join(profile).join("settings.ini").exists());
+    }
+
+    // A new profile is picked up and stored next to the others.
+    let carol = profiles_dir(&tester).join("carol");
+    fs::create_dir_all(&carol).await.unwrap();
+    fs::write(carol.join("save.dat"), "carol save")
+        .await
+        .unwrap();
+    tester.expect_command(backup("saves")).await;
+    assert!(hoard_dir.join("carol").join("save.dat").exists());
+    assert!(hoard_dir.join("alice").join("save.dat").exists());
+
+    fs::remove_dir_all(profiles_dir(&tester)).await.unwrap();
+    tester.expect_command(restore("saves")).await;
+    for profile in ["alice", "bob", "carol"] {
+        let content = fs::read_to_string(profiles_dir(&tester).join(profile).join("save.dat"))
+            .await
+            .unwrap();
+        assert_eq!(content, format!("{profile} save"));
+    }
+}
+
+#[tokio::test]
+async fn test_glob_pile_matching_nothing() {
+    let tester = Tester::new(GLOB_TOML).await;
+    setup_profiles(&tester).await;
+    tester.expect_command(backup("nothing")).await;
+    assert!(!tester.data_dir().join("hoards").join("nothing").exists());
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "prune_dir";
+
+const PRUNE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.prune_dir]
+    "unix"    = "${HOME}/prune_dir"
+    "windows" = "${HOARD_TMP}/prune_dir"
+"#;
+
+fn backup_command() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_prune_empty_dirs() {
+    let mut tester = Tester::new(PRUNE_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let nested_dir = system_dir.join("sub").join("nested");
+    let nested_file = nested_dir.join("file");
+    let top_file = system_dir.join("top");
+    fs::create_dir_all(&nested_dir)
+        .await
+        .expect("failed to create system dirs");
+    fs::write(&nested_file, "nested")
+        .await
+        .expect("failed to write nested file");
+    fs::write(&top_file, "top")
+        .await
+        .expect("failed to write top file");
+
+    tester.use_local_uuid().await;
+    tester.expect_command(backup_command()).await;
+
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    assert!(hoard_dir.join("sub").join("nested").join("file").exists());
+
+    // Without the flag, empty directories are left behind.
+    fs::remove_file(&nested_file)
+        .await
+        .expect("failed to remove nested file");
+    tester.expect_command(backup_command()).await;
+    assert!(!hoard_dir.join("sub").join("nested").join("file").exists());
+    assert!(hoard_dir.join("sub").join("nested").is_dir());
+
+    // Recreate and delete the file so that the next backup deletes it again.
+    fs::write(&nested_file, "nested")
+        .await
+        .expect("failed to rewrite nested file");
+    tester.expect_command(backup_command()).await;
+    fs::remove_file(&nested_file)
+        .await
+        .expect("failed to remove nested file");
+
+    tester.mut_config().prune_empty_dirs = true;
+    tester.expect_command(backup_command()).await;
+    assert!(
+        !hoard_dir.join("sub").exists(),
+        "empty directories should be pruned"
+    );
+
+    // The pile root is never removed, even when empty.
+    fs::remove_file(&top_file)
+        .await
+        .expect("failed to remove top file");
+    tester.expect_command(backup_command()).await;
+    assert!(!hoard_dir.join("top").exists());
+    assert!(hoard_dir.is_dir(), "pile root should not be pruned");
+}
@@ -0,0 +1,261 @@
+mod common;
+
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, ResolveStrategy, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "game";
+
+const RESOLVE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.game]
+    "unix"    = "${HOME}/game"
+    "windows" = "${HOARD_TMP}/game"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn restore() -> Command {
+    Command::Restore {
+        hoards: vec![HOARD.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+fn resolve(strategy: Option<ResolveStrategy>) -> Command {
+    Command::Resolve {
+        hoard: HOARD.parse().unwrap(),
+        strategy,
+    }
+}
+
+fn system_file(tester: &Tester, name: &str) -> PathBuf {
+    tester.home_dir().join("game").join(name)
+}
+
+fn hoard_file(tester: &Tester, name: &str) -> PathBuf {
+    tester.data_dir().join("hoards").join(HOARD).join(name)
+}
+
+async fn read(path: PathBuf) -> String {
+    fs::read_to_string(path).await.unwrap()
+}
+
+async fn assert_status(tester: &Tester, status: &str) {
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(&format!("{HOARD}: {status}"));
+}
+
+/// Backs up `save.txt` locally, then changes it remotely and locally so that both conflict.
+async fn setup_conflict(tester: &Tester) {
+    let game = tester.home_dir().join("game");
+    fs::create_dir_all(&game).await.unwrap();
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+    fs::write(game.join("settings.ini"), "original\n")
+        .await
+        .unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    tester.use_remote_uuid().await;
+    tester.expect_command(restore()).await;
+    fs::write(game.join("save.txt"), "remote\n").await.unwrap();
+    tester.expect_command(backup()).await;
+
+    tester.use_local_uuid().await;
+    fs::write(game.join("save.txt"), "local\n").await.unwrap();
+    assert_status(tester, "mixed changes").await;
+}
+
+#[tokio::test]
+async fn test_resolve_keep_local() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    setup_conflict(&tester).await;
+
+    tester
+        .expect_command(resolve(Some(ResolveStrategy::Local)))
+        .await;
+    assert_eq!(read(system_file(&tester, "save.txt")).await, "local\n");
+    assert_eq!(read(hoard_file(&tester, "save.txt")).await, "local\n");
+    assert_status(&tester, "up to date").await;
+}
+
+#[tokio::test]
+async fn test_resolve_keep_remote() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    setup_conflict(&tester).await;
+
+    tester
+        .expect_command(resolve(Some(ResolveStrategy::Remote)))
+        .await;
+    assert_eq!(read(system_file(&tester, "save.txt")).await, "remote\n");
+    assert_eq!(read(hoard_file(&tester, "save.txt")).await, "remote\n");
+    assert_status(&tester, "up to date").await;
+}
+
+#[tokio::test]
+async fn test_resolve_unexpected_changes() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    let game = tester.home_dir().join("game");
+    fs::create_dir_all(&game).await.unwrap();
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+    tester.expect_command(backup()).await;
+
+    // Change the hoard directly, both modifying and creating a file.
+    fs::write(hoard_file(&tester, "save.txt"), "edited\n")
+        .await
+        .unwrap();
+    fs::write(hoard_file(&tester, "extra.txt"), "extra\n")
+        .await
+        .unwrap();
+    assert_status(&tester, "unexpected changes").await;
+
+    tester
+        .expect_command(resolve(Some(ResolveStrategy::Remote)))
+        .await;
+    assert_eq!(read(system_file(&tester, "save.txt")).await, "edited\n");
+    assert_eq!(read(system_file(&tester, "extra.txt")).await, "extra\n");
+    assert_status(&tester, "up to date").await;
+}
+
+#[tokio::test]
+async fn test_resolve_unexpected_changes_keep_local() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    let game = tester.home_dir().join("game");
+    fs::create_dir_all(&game).await.unwrap();
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+    tester.expect_command(backup()).await;
+
+    fs::write(hoard_file(&tester, "extra.txt"), "extra\n")
+        .await
+        .unwrap();
+    assert_status(&tester, "unexpected changes").await;
+
+    tester
+        .expect_command(resolve(Some(ResolveStrategy::Local)))
+        .await;
+    assert!(!system_file(&tester, "extra.txt").exists());
+    assert!(!hoard_file(&tester, "extra.txt").exists());
+    assert_status(&tester, "up to date").await;
+}
+
+#[tokio::test]
+async fn test_resolve_changes_without_conflicts() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    let game = tester.home_dir().join("game");
+    fs::create_dir_all(&game).await.unwrap();
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+    fs::write(game.join("settings.ini"), "original\n")
+        .await
+        .unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    tester.use_remote_uuid().await;
+    tester.expect_command(restore()).await;
+    fs::write(game.join("save.txt"), "remote\n").await.unwrap();
+    tester.expect_command(backup()).await;
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+
+    tester.use_local_uuid().await;
+    fs::write(game.join("settings.ini"), "local\n")
+        .await
+        .unwrap();
+    assert_status(&tester, "mixed changes").await;
+
+    // No file conflicts, so nothing is prompted for.
+    tester.expect_command(resolve(None)).await;
+    assert_eq!(read(system_file(&tester, "save.txt")).await, "remote\n");
+    assert_eq!(read(hoard_file(&tester, "settings.ini")).await, "local\n");
+    assert_status(&tester, "up to date").await;
+}
+
+#[tokio::test]
+async fn test_resolve_nothing_to_resolve() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    let game = tester.home_dir().join("game");
+    fs::create_dir_all(&game).await.unwrap();
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+    tester.expect_command(backup()).await;
+
+    tester.clear_output();
+    tester.expect_command(resolve(None)).await;
+    tester.assert_has_output("game: nothing to resolve");
+}
+
+#[tokio::test]
+async fn test_resolve_remote_deletion() {
+    let tester = Tester::new(RESOLVE_TOML).await;
+    let game = tester.home_dir().join("game");
+    fs::create_dir_all(&game).await.unwrap();
+    fs::write(game.join("save.txt"), "original\n")
+        .await
+        .unwrap();
+    fs::write(game.join("settings.ini"), "original\n")
+        .await
+        .unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    tester.use_remote_uuid().await;
+    tester.expect_command(restore()).await;
+    fs::remove_file(game.join("save.txt")).await.unwrap();
+    tester.expect_command(backup()).await;
+
+    tester.use_local_uuid().await;
+    fs::write(game.join("save.txt"), "local\n").await.unwrap();
+    assert_status(&tester, "mixed changes").await;
+
+    tester
+        .expect_command(resolve(Some(ResolveStrategy::Remote)))
+        .await;
+    assert!(!system_file(&tester, "save.txt").exists());
+    assert_status(&tester, "up to date").await;
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use std::path::Path;
+use tokio::fs;
+
+const HOARD: &str = "project";
+
+const GITIGNORE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.project]
+    "unix"    = "${HOME}/project"
+    "windows" = "${HOARD_TMP}/project"
+[hoards.project.config]
+    respect_gitignore = true
+"#;
+
+async fn write_file(root: &Path, rel_path: &str, content: &str) {
+    let path = root.join(rel_path);
+    fs::create_dir_all(path.parent().unwrap())
+        .await
+        .expect("failed to create parent dirs");
+    fs::write(&path, content)
+        .await
+        .expect("failed to write file");
+}
+
+#[tokio::test]
+async fn test_nested_gitignore_files() {
+    let tester = Tester::new(GITIGNORE_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+
+    write_file(&system_dir, ".gitignore", "*.log\nbuild/\n").await;
+    write_file(&system_dir, "src/.gitignore", "!keep.log\n/generated.rs\n").await;
+    write_file(&system_dir, "src/deep/.gitignore", "*.tmp\n").await;
+
+    let kept = [
+        "main.rs",
+        "src/lib.rs",
+        "src/keep.log",
+        "src/deep/generated.rs",
+        "src/deep/code.rs",
+        "other/file.tmp",
+    ];
+    let ignored = [
+        "debug.log",
+        "build/output",
+        "src/build/output",
+        "src/other.log",
+        "src/generated.rs",
+        "src/deep/scratch.tmp",
+        "src/deep/trace.log",
+    ];
+    for file in kept.iter().chain(ignored.iter()) {
+        write_file(&system_dir, file, file).await;
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    for file in kept {
+        assert!(
+            hoard_dir.join(file).is_file(),
+            "{file} should have been backed up"
+        );
+    }
+    for file in ignored {
+        assert!(
+            !hoard_dir.join(file).exists(),
+            "{file} should have been ignored"
+        );
+    }
+    assert!(
+        hoard_dir.join("src").join(".gitignore").is_file(),
+        ".gitignore files themselves are backed up"
+    );
+}
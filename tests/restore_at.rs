@@ -0,0 +1,119 @@
+mod common;
+
+use std::time::Duration;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, Error as CommandError, RestoreArgs, RestoreAtError};
+use hoard::config::Error as ConfigError;
+use time::OffsetDateTime;
+use tokio::fs;
+
+const HOARD: &str = "archive";
+
+const RESTORE_AT_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.archive]
+    "unix"    = "${HOME}/archive"
+    "windows" = "${HOARD_TMP}/archive"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+fn restore_at(at: OffsetDateTime) -> Command {
+    Command::Restore {
+        hoards: vec![HOARD.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: Some(at),
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+/// Returns the current time, making sure it is strictly between the operations around it.
+fn checkpoint() -> OffsetDateTime {
+    std::thread::sleep(Duration::from_millis(10));
+    let now = OffsetDateTime::now_utc();
+    std::thread::sleep(Duration::from_millis(10));
+    now
+}
+
+#[tokio::test]
+async fn test_restore_at() {
+    let tester = Tester::new(RESTORE_AT_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let first = system_dir.join("first");
+    let second = system_dir.join("second");
+    fs::create_dir_all(&system_dir).await.unwrap();
+    tester.use_local_uuid().await;
+
+    let before_any = checkpoint();
+    fs::write(&first, "first v1").await.unwrap();
+    fs::write(&second, "second v1").await.unwrap();
+    tester.expect_command(backup()).await;
+    let after_first_backup = checkpoint();
+
+    fs::write(&first, "first v2").await.unwrap();
+    tester.expect_command(backup()).await;
+    let after_second_backup = checkpoint();
+
+    fs::write(&second, "local edit").await.unwrap();
+
+    // The hoard only has the latest version of `first`, so it cannot go back to v1.
+    let error = tester
+        .run_command(restore_at(after_first_backup))
+        .await
+        .expect_err("restoring a version no longer in the hoard should fail");
+    assert!(
+        matches!(
+            &error,
+            ConfigError::Command(CommandError::RestoreAt(RestoreAtError::Unavailable { files, .. }))
+                if files == &vec![format!("{HOARD}/first")]
+        ),
+        "unexpected error: {error:?}"
+    );
+    assert_eq!(
+        fs::read_to_string(&second).await.unwrap(),
+        "local edit",
+        "nothing should be restored if any file is unavailable"
+    );
+
+    tester.expect_command(restore_at(after_second_backup)).await;
+    assert_eq!(fs::read_to_string(&first).await.unwrap(), "first v2");
+    assert_eq!(fs::read_to_string(&second).await.unwrap(), "second v1");
+
+    let error = tester
+        .run_command(restore_at(before_any))
+        .await
+        .expect_err("restoring before any operation should fail");
+    assert!(
+        matches!(
+            &error,
+            ConfigError::Command(CommandError::RestoreAt(RestoreAtError::NoOperation { .. }))
+        ),
+        "unexpected error: {error:?}"
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const DRY_RUN_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+fn restore(dry_run: bool) -> Command {
+    Command::Restore {
+        hoards: vec![HOARD.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_restore_dry_run() {
+    let tester = Tester::new(DRY_RUN_TOML).await;
+    let root = tester.home_dir().join(HOARD);
+    let modified = root.join("modified.sav");
+    let unchanged = root.join("unchanged.sav");
+    let deleted = root.join("deleted.sav");
+    let created = root.join("created.sav");
+    fs::create_dir_all(&root).await.unwrap();
+    for file in [&modified, &unchanged, &deleted] {
+        fs::write(file, "original").await.unwrap();
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    // Make changes on another system, then put this system back to its original state.
+    tester.use_remote_uuid().await;
+    tester.expect_command(restore(false)).await;
+    fs::write(&modified, "changed").await.unwrap();
+    fs::remove_file(&deleted).await.unwrap();
+    fs::write(&created, "created").await.unwrap();
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+    tester.use_local_uuid().await;
+    fs::write(&modified, "original").await.unwrap();
+    fs::write(&deleted, "original").await.unwrap();
+    fs::remove_file(&created).await.unwrap();
+
+    tester.clear_output();
+    tester.expect_command(restore(true)).await;
+    tester.assert_has_output(&format!("saves: would modify {}\n", modified.display()));
+    tester.assert_has_output(&format!("saves: would create {}\n", created.display()));
+    tester.assert_has_output(&format!("saves: would DELETE {}\n", deleted.display()));
+    tester.assert_not_has_output(&unchanged.display().to_string());
+
+    assert_eq!(fs::read_to_string(&modified).await.unwrap(), "original");
+    assert_eq!(fs::read_to_string(&deleted).await.unwrap(), "original");
+    assert!(!created.exists(), "dry run should not create files");
+
+    // A second dry run sees the same changes because nothing was written, not even a log.
+    tester.clear_output();
+    tester.expect_command(restore(true)).await;
+    tester.assert_has_output(&format!("saves: would DELETE {}\n", deleted.display()));
+
+    tester.expect_command(restore(false)).await;
+    assert_eq!(fs::read_to_string(&modified).await.unwrap(), "changed");
+    assert_eq!(fs::read_to_string(&created).await.unwrap(), "created");
+    assert!(!deleted.exists(), "real restore should delete the file");
+
+    tester.clear_output();
+    tester.expect_command(restore(true)).await;
+    tester.assert_has_output("saves: no files would change\n");
+}
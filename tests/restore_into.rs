@@ -0,0 +1,152 @@
+mod common;
+
+use std::path::{Component, Path, PathBuf};
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, HoardStatus, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "saves";
+
+const RESTORE_INTO_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+fn restore_into(into: &Path) -> Command {
+    Command::Restore {
+        hoards: vec![HOARD.parse().unwrap()],
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: Some(into.to_path_buf()),
+        args: RestoreArgs::default(),
+    }
+}
+
+/// Returns where `path` is restored to under `into`.
+fn rebased(into: &Path, path: &Path) -> PathBuf {
+    path.components()
+        .fold(into.to_path_buf(), |rebased, component| match component {
+            Component::Prefix(prefix) => rebased.join(
+                prefix
+                    .as_os_str()
+                    .to_string_lossy()
+                    .trim_end_matches(':')
+                    .trim_start_matches(r"\\?\"),
+            ),
+            Component::RootDir => rebased,
+            component => rebased.join(component),
+        })
+}
+
+#[tokio::test]
+async fn test_restore_into_leaves_system_alone() {
+    let tester = Tester::new(RESTORE_INTO_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let save = system_dir.join("slot1").join("save.dat");
+    fs::create_dir_all(save.parent().unwrap()).await.unwrap();
+    fs::write(&save, "level 1").await.unwrap();
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed");
+
+    fs::write(&save, "level 2").await.unwrap();
+    let history = tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().as_hyphenated().to_string())
+        .join(HOARD);
+    let logs_before = std::fs::read_dir(&history).unwrap().count();
+
+    let into = tempfile::tempdir().unwrap();
+    tester
+        .run_command(restore_into(into.path()))
+        .await
+        .expect("restore --into should succeed");
+
+    let restored = rebased(into.path(), &save);
+    assert_eq!(fs::read_to_string(&restored).await.unwrap(), "level 1");
+    assert_eq!(
+        fs::read_to_string(&save).await.unwrap(),
+        "level 2",
+        "the system file should not be touched"
+    );
+    assert_eq!(
+        std::fs::read_dir(&history).unwrap().count(),
+        logs_before,
+        "no operation log should be written"
+    );
+
+    let statuses = tester.config().status().await.unwrap();
+    assert_eq!(
+        statuses[&HOARD.parse().unwrap()],
+        HoardStatus::ModifiedLocally,
+        "status should still see the local change"
+    );
+}
+
+#[tokio::test]
+async fn test_restore_into_reports_each_file_once() {
+    let tester = Tester::new(RESTORE_INTO_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let files = ["f", "g", "sub/h"];
+    for file in files {
+        let path = system_dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(&path, file).await.unwrap();
+    }
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed");
+
+    let into = tempfile::tempdir().unwrap();
+    tester.clear_output();
+    tester
+        .run_command(restore_into(into.path()))
+        .await
+        .expect("restore --into should succeed");
+
+    let output = tester.output();
+    for file in files {
+        let restored = rebased(into.path(), &system_dir.join(file));
+        let line = format!("restored to {}", restored.display());
+        assert_eq!(
+            output.matches(&line).count(),
+            1,
+            "{file} should be restored exactly once:\n{output}"
+        );
+        assert_eq!(fs::read_to_string(&restored).await.unwrap(), file);
+    }
+}
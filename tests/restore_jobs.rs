@@ -0,0 +1,109 @@
+mod common;
+
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "deep_tree";
+
+const JOBS_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.deep_tree]
+    "unix"    = "${HOME}/deep_tree"
+    "windows" = "${HOARD_TMP}/deep_tree"
+"#;
+
+/// Relative paths of files in a tree that is several directories deep.
+fn tree_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for a in ["a", "b"] {
+        for b in ["one", "two", "three"] {
+            for c in ["x", "y"] {
+                let dir = PathBuf::from(a).join(b).join(c);
+                for file in ["first", "second"] {
+                    files.push(dir.join(file));
+                }
+            }
+            files.push(PathBuf::from(a).join(b).join("file"));
+        }
+    }
+    files
+}
+
+#[tokio::test]
+async fn test_restore_with_jobs() {
+    let mut tester = Tester::new(JOBS_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    for file in tree_files() {
+        let path = system_dir.join(&file);
+        fs::create_dir_all(path.parent().unwrap())
+            .await
+            .expect("failed to create system dirs");
+        fs::write(&path, file.to_string_lossy().as_bytes())
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::remove_dir_all(&system_dir)
+        .await
+        .expect("failed to remove system dir");
+
+    tester.mut_config().jobs = NonZeroUsize::new(4);
+    tester
+        .expect_forced_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+
+    for file in tree_files() {
+        let path = system_dir.join(&file);
+        assert!(path.parent().unwrap().is_dir());
+        let content = fs::read_to_string(&path)
+            .await
+            .unwrap_or_else(|_| panic!("{} should have been restored", path.display()));
+        assert_eq!(content, file.to_string_lossy());
+    }
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output(&format!("{HOARD}: up to date"));
+}
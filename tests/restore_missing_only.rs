@@ -0,0 +1,90 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "missing";
+
+const MISSING_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.missing]
+    "unix"    = "${HOME}/missing"
+    "windows" = "${HOARD_TMP}/missing"
+"#;
+
+#[tokio::test]
+async fn test_restore_only_missing_files() {
+    let tester = Tester::new(MISSING_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let deleted = system_dir.join("deleted.txt");
+    let changed = system_dir.join("changed.txt");
+    fs::create_dir_all(&system_dir)
+        .await
+        .expect("failed to create system dir");
+    for file in [&deleted, &changed] {
+        fs::write(file, "original")
+            .await
+            .expect("failed to write system file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::remove_file(&deleted)
+        .await
+        .expect("failed to delete system file");
+    fs::write(&changed, "local changes")
+        .await
+        .expect("failed to modify system file");
+
+    tester
+        .run_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs {
+                missing_only: true,
+                ..RestoreArgs::default()
+            },
+        })
+        .await
+        .expect("restoring missing files should succeed");
+
+    let content = fs::read_to_string(&deleted)
+        .await
+        .expect("missing file should have been restored");
+    assert_eq!(content, "original");
+    let content = fs::read_to_string(&changed)
+        .await
+        .expect("failed to read changed file");
+    assert_eq!(
+        content, "local changes",
+        "existing files should not be overwritten"
+    );
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::tester::Tester;
+use filetime::FileTime;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const CONFIG: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[envs.unix]
+    os = ["linux", "macos"]
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+#[tokio::test]
+async fn test_restore_mtime() {
+    let mut tester = Tester::new(CONFIG).await;
+    let root = tester.home_dir().join("saves");
+    let file = root.join("save");
+    let hoards = vec!["saves".parse().unwrap()];
+    let restore = Command::Restore {
+        hoards: hoards.clone(),
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    };
+    let mtime = FileTime::from_unix_time(1_000_000_000, 123_456_000);
+
+    fs::create_dir_all(&root).await.unwrap();
+    fs::write(&file, "progress").await.unwrap();
+    filetime::set_file_mtime(&file, mtime).unwrap();
+    tester
+        .expect_command(Command::Backup {
+            hoards,
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::remove_file(&file).await.unwrap();
+    tester.expect_command(restore.clone()).await;
+    let metadata = fs::metadata(&file).await.unwrap();
+    assert_eq!(
+        mtime,
+        FileTime::from_last_modification_time(&metadata),
+        "restore should reapply the modification time recorded during backup"
+    );
+
+    fs::remove_file(&file).await.unwrap();
+    tester.mut_config().no_mtime = true;
+    tester.expect_command(restore).await;
+    let metadata = fs::metadata(&file).await.unwrap();
+    assert!(
+        FileTime::from_last_modification_time(&metadata) > mtime,
+        "--no-mtime should leave the time the file was restored at"
+    );
+}
@@ -1,7 +1,7 @@
 mod common;
 
 use common::tester::Tester;
-use hoard::command::Command;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
 #[cfg(unix)]
 use std::fs::Permissions;
 use tokio::fs;
@@ -67,7 +67,9 @@ exclusivity = [
 
 #[tokio::test]
 async fn test_default_permissions() {
-    let tester = Tester::with_log_level(CONFIG, tracing::Level::DEBUG).await;
+    let mut tester = Tester::with_log_level(CONFIG, tracing::Level::DEBUG).await;
+    // Only use the defaults instead of the permissions recorded during backup.
+    tester.mut_config().no_permissions = true;
     let root = tester.home_dir().join("defaultdir");
     let file = root.join("file");
     let ignored = root.join("subdir").join("ignore");
@@ -79,6 +81,8 @@ async fn test_default_permissions() {
     tester
         .expect_command(Command::Backup {
             hoards: hoards.clone(),
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 
@@ -102,7 +106,19 @@ async fn test_default_permissions() {
             .unwrap();
     }
 
-    tester.expect_command(Command::Restore { hoards }).await;
+    tester
+        .expect_command(Command::Restore {
+            hoards,
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
 
     let file_perms = fs::metadata(&file).await.unwrap().permissions();
     let dir_perms = fs::metadata(&root).await.unwrap().permissions();
@@ -151,12 +167,26 @@ async fn test_anon_txt_configured_perms() {
     tester
         .expect_command(Command::Backup {
             hoards: hoards.clone(),
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 
     fs::remove_file(&file).await.unwrap();
 
-    tester.expect_command(Command::Restore { hoards }).await;
+    tester
+        .expect_command(Command::Restore {
+            hoards,
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
 
     assert!(file.exists());
     let perms = fs::metadata(file).await.unwrap().permissions();
@@ -177,10 +207,24 @@ async fn test_anon_bin_configured_perms() {
     tester
         .expect_command(Command::Backup {
             hoards: hoards.clone(),
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 
-    tester.expect_command(Command::Restore { hoards }).await;
+    tester
+        .expect_command(Command::Restore {
+            hoards,
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
 
     let perms = fs::metadata(file).await.unwrap().permissions();
 
@@ -205,13 +249,27 @@ async fn test_anon_dir_configured_perms() {
     tester
         .expect_command(Command::Backup {
             hoards: hoards.clone(),
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 
     fs::remove_dir_all(&sub_dir).await.unwrap();
     fs::remove_file(&file1).await.unwrap();
 
-    tester.expect_command(Command::Restore { hoards }).await;
+    tester
+        .expect_command(Command::Restore {
+            hoards,
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
 
     assert!(root.exists());
     assert!(file1.exists());
@@ -238,7 +296,8 @@ async fn test_anon_dir_configured_perms() {
 
 #[tokio::test]
 async fn test_readonly_dir() {
-    let tester = Tester::new(CONFIG).await;
+    let mut tester = Tester::new(CONFIG).await;
+    tester.mut_config().no_permissions = true;
     let root = tester.home_dir().join("readonly");
     let sub_dir = root.join("subdir");
     let file = sub_dir.join("file");
@@ -250,6 +309,8 @@ async fn test_readonly_dir() {
     tester
         .expect_command(Command::Backup {
             hoards: hoards.clone(),
+            json: false,
+            args: BackupArgs::default(),
         })
         .await;
 
@@ -267,7 +328,19 @@ async fn test_readonly_dir() {
         }
     }
 
-    tester.expect_command(Command::Restore { hoards }).await;
+    tester
+        .expect_command(Command::Restore {
+            hoards,
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
 
     assert!(root.exists());
     assert!(sub_dir.exists());
@@ -286,6 +359,58 @@ async fn test_readonly_dir() {
     );
 }
 
+#[cfg(unix)]
+#[tokio::test]
+async fn test_recorded_permissions() {
+    let mut tester = Tester::new(CONFIG).await;
+    let root = tester.home_dir().join("defaultdir");
+    let script = root.join("script.sh");
+    let hoards = vec!["default_dir".parse().unwrap()];
+    let restore = Command::Restore {
+        hoards: hoards.clone(),
+        pick: false,
+        pick_file: Vec::new(),
+        version: None,
+        dry_run: false,
+        at: None,
+        json: false,
+        into: None,
+        args: RestoreArgs::default(),
+    };
+
+    fs::create_dir_all(&root).await.unwrap();
+    fs::write(&script, "#!/bin/sh\necho hello\n").await.unwrap();
+    fs::set_permissions(&script, Permissions::from_mode(0o750))
+        .await
+        .unwrap();
+    tester
+        .expect_command(Command::Backup {
+            hoards,
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    fs::remove_file(&script).await.unwrap();
+    tester.expect_command(restore.clone()).await;
+    let perms = fs::metadata(&script).await.unwrap().permissions();
+    assert_eq!(
+        0o100750,
+        perms.mode(),
+        "restore should reapply the permissions recorded during backup"
+    );
+
+    fs::remove_file(&script).await.unwrap();
+    tester.mut_config().no_permissions = true;
+    tester.expect_command(restore).await;
+    let perms = fs::metadata(&script).await.unwrap().permissions();
+    assert_eq!(
+        0o100600,
+        perms.mode(),
+        "--no-permissions should restore with the default permissions"
+    );
+}
+
 #[tokio::test]
 async fn test_hoard_file_permissions() {
     let tester = Tester::with_log_level(CONFIG, tracing::Level::DEBUG).await;
@@ -299,7 +424,13 @@ async fn test_hoard_file_permissions() {
     fs::write(&file1, "content 1").await.unwrap();
     fs::write(&file2, "content 2").await.unwrap();
 
-    tester.expect_command(Command::Backup { hoards }).await;
+    tester
+        .expect_command(Command::Backup {
+            hoards,
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
 
     let hoard_root = tester.data_dir().join("hoards").join("anon_dir");
     let hoard_file1 = hoard_root.join("file");
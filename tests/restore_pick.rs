@@ -0,0 +1,101 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, FileVersion, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "picked";
+
+const PICK_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.picked.saves]
+    "unix"    = "${HOME}/picked/saves"
+    "windows" = "${HOARD_TMP}/picked/saves"
+"#;
+
+#[tokio::test]
+async fn test_restore_picked_file() {
+    let tester = Tester::new(PICK_TOML).await;
+    let system_dir = tester.home_dir().join(HOARD).join("saves");
+    let chosen = system_dir.join("slot1").join("save.dat");
+    let other = system_dir.join("slot2").join("save.dat");
+    for file in [&chosen, &other] {
+        fs::create_dir_all(file.parent().unwrap())
+            .await
+            .expect("failed to create system dir");
+        fs::write(file, "original")
+            .await
+            .expect("failed to write save file");
+    }
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    for file in [&chosen, &other] {
+        fs::write(file, "modified")
+            .await
+            .expect("failed to modify save file");
+    }
+
+    tester
+        .expect_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: vec!["saves/slot1/save.dat".parse().unwrap()],
+            version: Some(FileVersion::Latest),
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+
+    let content = fs::read_to_string(&chosen)
+        .await
+        .expect("failed to read chosen file");
+    assert_eq!(content, "original", "the picked file should be restored");
+    let content = fs::read_to_string(&other)
+        .await
+        .expect("failed to read other file");
+    assert_eq!(content, "modified", "other files should not be restored");
+    tester.assert_has_output("picked/saves/slot1/save.dat: restored latest version");
+
+    assert!(
+        tester
+            .run_command(Command::Restore {
+                hoards: vec![HOARD.parse().unwrap()],
+                pick: false,
+                pick_file: vec!["saves/missing".parse().unwrap()],
+                version: None,
+                dry_run: false,
+                at: None,
+                json: false,
+                into: None,
+                args: RestoreArgs::default(),
+            })
+            .await
+            .is_err(),
+        "picking a file that is not in the hoard should fail"
+    );
+}
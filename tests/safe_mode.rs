@@ -0,0 +1,100 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, BackupRestoreError, Command, Error as CommandError};
+use hoard::config::Error as ConfigError;
+use tokio::fs;
+
+const HOARD: &str = "safe";
+
+const SAFE_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.safe]
+    "unix"    = "${HOME}/safe.txt"
+    "windows" = "${HOARD_TMP}/safe.txt"
+"#;
+
+#[tokio::test]
+async fn test_safe_mode_refuses_unexpected_changes() {
+    let mut tester = Tester::new(SAFE_TOML).await;
+    tester.mut_config().safe = true;
+    let system_file = tester.home_dir().join("safe.txt");
+    let hoard_file = tester.data_dir().join("hoards").join(HOARD);
+    fs::write(&system_file, "original")
+        .await
+        .expect("failed to write system file");
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup of a new hoard should succeed in safe mode");
+
+    // Something writes to the hoard directly.
+    fs::write(&hoard_file, "edited out of band")
+        .await
+        .expect("failed to modify hoard file");
+    fs::write(&system_file, "changed locally")
+        .await
+        .expect("failed to modify system file");
+
+    let error = tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect_err("safe mode should refuse a hoard with unexpected changes");
+    assert!(
+        matches!(
+            error,
+            ConfigError::Command(CommandError::Backup(BackupRestoreError::UnexpectedChanges(
+                _
+            )))
+        ),
+        "unexpected error: {error:?}"
+    );
+    let content = fs::read_to_string(&hoard_file)
+        .await
+        .expect("failed to read hoard file");
+    assert_eq!(
+        content, "edited out of band",
+        "hoard file should be untouched"
+    );
+
+    // Once the hoard matches the last operation again, backups proceed.
+    fs::write(&hoard_file, "original")
+        .await
+        .expect("failed to reset hoard file");
+    tester
+        .run_command(Command::Backup {
+            hoards: vec![HOARD.parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed once the unexpected changes are resolved");
+    let content = fs::read_to_string(&hoard_file)
+        .await
+        .expect("failed to read hoard file");
+    assert_eq!(content, "changed locally");
+}
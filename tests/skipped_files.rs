@@ -0,0 +1,131 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const HOARD: &str = "skipped";
+
+const CONFIG: &str = r#"
+[envs]
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.skipped]
+    "unix" = "${HOME}/skipped"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+async fn set_mode(path: &Path, mode: u32) {
+    fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .await
+        .expect("failed to set permissions");
+}
+
+/// Returns whether permissions are enforced, which they are not for the superuser.
+fn permissions_enforced(unreadable: &Path) -> bool {
+    std::fs::read(unreadable).is_err()
+}
+
+#[tokio::test]
+async fn test_unreadable_file_is_skipped_unless_strict() {
+    let mut tester = Tester::new(CONFIG).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    fs::create_dir_all(&system_dir).await.unwrap();
+    fs::write(system_dir.join("readable.txt"), "readable")
+        .await
+        .unwrap();
+    let unreadable = system_dir.join("unreadable.txt");
+    fs::write(&unreadable, "unreadable").await.unwrap();
+    set_mode(&unreadable, 0o000).await;
+    if !permissions_enforced(&unreadable) {
+        return;
+    }
+
+    tester.use_local_uuid().await;
+    tester.mut_config().strict = true;
+    tester
+        .run_command(backup())
+        .await
+        .expect_err("an unreadable file should fail a strict backup");
+    assert!(!hoard_dir.join("readable.txt").exists());
+
+    tester.mut_config().strict = false;
+    tester
+        .run_command(backup())
+        .await
+        .expect("an unreadable file should be skipped");
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("readable.txt"))
+            .await
+            .unwrap(),
+        "readable"
+    );
+    assert!(!hoard_dir.join("unreadable.txt").exists());
+    tester.assert_has_output("skipped 1 path(s) that could not be read");
+    tester.assert_has_output(&format!("  {}: ", unreadable.display()));
+}
+
+#[tokio::test]
+async fn test_unreadable_directory_is_not_deleted_from_hoard() {
+    let tester = Tester::new(CONFIG).await;
+    let system_dir = tester.home_dir().join(HOARD);
+    let hoard_dir = tester.data_dir().join("hoards").join(HOARD);
+    let private = system_dir.join("private");
+    fs::create_dir_all(&private).await.unwrap();
+    fs::write(private.join("secret.txt"), "secret")
+        .await
+        .unwrap();
+    fs::write(system_dir.join("public.txt"), "public")
+        .await
+        .unwrap();
+
+    tester.use_local_uuid().await;
+    tester
+        .run_command(backup())
+        .await
+        .expect("first backup should succeed");
+
+    set_mode(&private, 0o000).await;
+    if !permissions_enforced(&private.join("secret.txt")) {
+        set_mode(&private, 0o755).await;
+        return;
+    }
+
+    fs::write(system_dir.join("public.txt"), "changed")
+        .await
+        .unwrap();
+    let result = tester.run_command(backup()).await;
+    set_mode(&private, 0o755).await;
+    result.expect("an unreadable directory should be skipped");
+
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("public.txt"))
+            .await
+            .unwrap(),
+        "changed"
+    );
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("private").join("secret.txt"))
+            .await
+            .unwrap(),
+        "secret"
+    );
+    tester.assert_has_output(&format!("  {}: ", private.display()));
+}
@@ -0,0 +1,72 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use nix::sys::stat::Mode;
+use tokio::fs;
+
+const SPECIAL_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+#[tokio::test]
+async fn test_backup_skips_fifo() {
+    let tester = Tester::new(SPECIAL_TOML).await;
+    let saves = tester.home_dir().join("saves");
+    fs::create_dir_all(&saves)
+        .await
+        .expect("failed to create saves dir");
+    fs::write(saves.join("save"), "progress")
+        .await
+        .expect("failed to write save file");
+    nix::unistd::mkfifo(&saves.join("pipe"), Mode::S_IRWXU).expect("failed to create FIFO");
+
+    tester.use_local_uuid().await;
+    // Reading from the FIFO could block forever, so it must be skipped.
+    tester
+        .run_command(Command::Backup {
+            hoards: vec!["saves".parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await
+        .expect("backup should succeed");
+
+    let hoard_dir = tester.data_dir().join("hoards").join("saves");
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("save"))
+            .await
+            .expect("regular files should still be backed up"),
+        "progress"
+    );
+    assert!(
+        !hoard_dir.join("pipe").exists(),
+        "the FIFO should not be backed up"
+    );
+    assert!(
+        tester
+            .output()
+            .contains("is not a regular file or directory"),
+        "skipping the FIFO should be reported: {}",
+        tester.output()
+    );
+}
@@ -0,0 +1,81 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command};
+use tokio::fs;
+
+const SYMLINK_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.saves]
+    "unix"    = "${HOME}/saves"
+    "windows" = "${HOARD_TMP}/saves"
+"#;
+
+#[tokio::test]
+async fn test_symlinked_root_is_contained() {
+    let tester = Tester::new(SYMLINK_TOML).await;
+    let target = tester.home_dir().join("real_saves");
+    fs::create_dir_all(target.join("slot"))
+        .await
+        .expect("failed to create target dir");
+    fs::write(target.join("slot").join("save"), "progress")
+        .await
+        .expect("failed to write save file");
+    fs::write(tester.home_dir().join("secret"), "outside of the pile")
+        .await
+        .expect("failed to write outside file");
+
+    // The pile root is itself a symlink, which is followed.
+    fs::symlink(&target, tester.home_dir().join("saves"))
+        .await
+        .expect("failed to symlink pile root");
+    // Links that resolve outside of the pile root are not.
+    fs::symlink("/", target.join("escape"))
+        .await
+        .expect("failed to symlink to /");
+    fs::symlink("../secret", target.join("secret"))
+        .await
+        .expect("failed to symlink to outside file");
+
+    tester.use_local_uuid().await;
+    tester
+        .expect_command(Command::Backup {
+            hoards: vec!["saves".parse().unwrap()],
+            json: false,
+            args: BackupArgs::default(),
+        })
+        .await;
+
+    let hoard_dir = tester.data_dir().join("hoards").join("saves");
+    assert_eq!(
+        fs::read_to_string(hoard_dir.join("slot").join("save"))
+            .await
+            .expect("files in the symlink target should be backed up"),
+        "progress"
+    );
+    assert!(
+        !hoard_dir.join("escape").exists(),
+        "symlink to / should not be followed"
+    );
+    assert!(
+        !hoard_dir.join("secret").exists(),
+        "symlink outside of the pile should not be followed"
+    );
+    tester.assert_has_output("which is outside of");
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use common::tester::Tester;
+use hoard::command::{BackupArgs, Command, RestoreArgs};
+use tokio::fs;
+
+const HOARD: &str = "archive";
+
+const TRUNCATED_TOML: &str = r#"
+exclusivity = [
+    ["unix", "windows"]
+]
+
+[envs]
+[envs.windows]
+    os = ["windows"]
+[[envs.windows.env]]
+    var = "HOARD_TMP"
+[envs.unix]
+    os = ["linux", "macos"]
+[[envs.unix.env]]
+    var = "HOME"
+
+[hoards]
+[hoards.archive]
+    "unix"    = "${HOME}/archive.txt"
+    "windows" = "${HOARD_TMP}/archive.txt"
+"#;
+
+fn backup() -> Command {
+    Command::Backup {
+        hoards: vec![HOARD.parse().unwrap()],
+        json: false,
+        args: BackupArgs::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_truncated_log_is_skipped() {
+    let tester = Tester::new(TRUNCATED_TOML).await;
+    let file = tester.home_dir().join("archive.txt");
+    fs::write(&file, "first").await.unwrap();
+    tester.use_local_uuid().await;
+    tester.expect_command(backup()).await;
+
+    // Simulate being killed mid-write: one empty log and one with cut-off JSON, both newer than
+    // the real log.
+    let history_dir = tester
+        .data_dir()
+        .join("history")
+        .join(tester.local_uuid().as_hyphenated().to_string())
+        .join(HOARD);
+    let empty = history_dir.join("2999_01_01-00_00_00.000000.log");
+    let truncated = history_dir.join("2999_01_01-00_00_00.000000-1.log");
+    fs::write(&empty, "").await.unwrap();
+    fs::write(&truncated, r#"{"timestamp":"#).await.unwrap();
+
+    tester.clear_output();
+    tester
+        .expect_command(Command::Status {
+            hoard: None,
+            json: false,
+        })
+        .await;
+    tester.assert_has_output("skipping incomplete operation log");
+
+    fs::write(&file, "second").await.unwrap();
+    tester.expect_command(backup()).await;
+    fs::write(&file, "local change").await.unwrap();
+    tester
+        .expect_forced_command(Command::Restore {
+            hoards: vec![HOARD.parse().unwrap()],
+            pick: false,
+            pick_file: Vec::new(),
+            version: None,
+            dry_run: false,
+            at: None,
+            json: false,
+            into: None,
+            args: RestoreArgs::default(),
+        })
+        .await;
+    assert_eq!(fs::read_to_string(&file).await.unwrap(), "second");
+
+    tester
+        .expect_command(Command::Cleanup { dry_run: false })
+        .await;
+    assert!(empty.exists(), "incomplete logs should not be cleaned up");
+    assert!(
+        truncated.exists(),
+        "incomplete logs should not be cleaned up"
+    );
+
+    // No temporary files should be left behind by writing logs.
+    let mut entries = fs::read_dir(&history_dir).await.unwrap();
+    while let Some(entry) = entries.next_entry().await.unwrap() {
+        let name = entry.file_name();
+        assert!(
+            !name.to_string_lossy().ends_with(".tmp"),
+            "found temporary file {name:?}"
+        );
+    }
+}